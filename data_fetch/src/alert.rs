@@ -0,0 +1,373 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, io,
+    process::Command,
+    time::Duration,
+};
+
+use crate::{parse_duration, AssetKind, Decimal, PriceRow};
+
+// Rules
+
+/// Fire when an asset moves at least `percent_change` percent, up or down,
+/// within `window`. Once fired, stays quiet for `cooldown`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule
+{
+    pub asset: AssetKind,
+    pub percent_change: f64,
+    pub window: Duration,
+    pub cooldown: Duration,
+}
+
+impl AlertRule
+{
+    /// Parse `ASSET:PERCENT:WINDOW[:COOLDOWN]`, e.g. `btc:5:1h` or
+    /// `solana:2.5%:30m:2h`. The cooldown defaults to the window. `resolve`
+    /// maps the asset name to a tracked asset.
+    pub fn parse(spec: &str, resolve: impl Fn(&str) -> Option<AssetKind>) -> Result<Self, String>
+    {
+        let parts: Vec<&str> = spec.split(':').map(str::trim).collect();
+        let [asset, pct, window, rest @ ..] = parts.as_slice() else
+        {
+            return Err(format!("alert '{spec}': expected ASSET:PERCENT:WINDOW[:COOLDOWN]"));
+        };
+        if rest.len() > 1
+        {
+            return Err(format!("alert '{spec}': too many fields"));
+        }
+        let asset = resolve(asset).ok_or_else(|| format!("alert '{spec}': '{asset}' is not a tracked asset"))?;
+        let percent_change: f64 = pct
+            .trim_end_matches('%')
+            .parse()
+            .ok()
+            .filter(|p: &f64| p.is_finite() && *p > 0.0)
+            .ok_or_else(|| format!("alert '{spec}': '{pct}' is not a positive percentage"))?;
+        let window = parse_duration(window).map_err(|e| format!("alert '{spec}': {e}"))?;
+        let cooldown = match rest.first()
+        {
+            Some(c) => parse_duration(c).map_err(|e| format!("alert '{spec}': {e}"))?,
+            None => window,
+        };
+        Ok(Self { asset, percent_change, window, cooldown })
+    }
+}
+
+/// A rule that fired.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert
+{
+    pub asset: String,
+    pub currency: String,
+    /// Signed change from `from_price` to `to_price`, in percent
+    pub change_pct: f64,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub from_price: Decimal,
+    pub from_time: DateTime<Utc>,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub to_price: Decimal,
+    pub to_time: DateTime<Utc>,
+}
+
+impl fmt::Display for Alert
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let minutes = (self.to_time - self.from_time).num_minutes();
+        write!(
+            f,
+            "{} moved {:+.2}% in {minutes}m ({} -> {} {})",
+            self.asset, self.change_pct, self.from_price, self.to_price, self.currency
+        )
+    }
+}
+
+// Evaluation
+
+/// Check `rule` against `series`, the price history of one asset in one
+/// currency in time order. Compares the latest price against every earlier
+/// price within the window and fires on the largest move, unless the rule
+/// last fired less than `cooldown` before the latest price.
+pub fn evaluate(rule: &AlertRule, series: &[PriceRow], last_fired: Option<DateTime<Utc>>) -> Option<Alert>
+{
+    let (latest, earlier) = series.split_last()?;
+    let since_fired = last_fired.map(|t| (latest.fetched_at - t).to_std().unwrap_or_default());
+    if since_fired.is_some_and(|d| d < rule.cooldown)
+    {
+        return None;
+    }
+    let window = chrono::Duration::from_std(rule.window).ok()?;
+    let start = latest.fetched_at - window;
+    let pct = |from: &PriceRow| {
+        let change = (latest.price - from.price) / from.price * Decimal::ONE_HUNDRED;
+        change.to_f64().unwrap_or_default()
+    };
+    let from = earlier
+        .iter()
+        .filter(|row| row.fetched_at >= start && !row.price.is_zero())
+        .max_by(|a, b| pct(a).abs().total_cmp(&pct(b).abs()))?;
+    let change_pct = pct(from);
+    (change_pct.abs() >= rule.percent_change).then(|| Alert {
+        asset: rule.asset.name().to_string(),
+        currency: latest.currency.clone(),
+        change_pct,
+        from_price: from.price,
+        from_time: from.fetched_at,
+        to_price: latest.price,
+        to_time: latest.fetched_at,
+    })
+}
+
+/// Keeps the recent history each rule needs and when each rule last fired.
+#[derive(Debug, Default)]
+pub struct AlertTracker
+{
+    rules: Vec<AlertRule>,
+    history: HashMap<(AssetKind, String), VecDeque<PriceRow>>,
+    /// Keyed by rule index and currency
+    last_fired: HashMap<(usize, String), DateTime<Utc>>,
+}
+
+impl AlertTracker
+{
+    pub fn new(rules: Vec<AlertRule>) -> Self
+    {
+        Self { rules, ..Self::default() }
+    }
+
+    pub fn rules(&self) -> &[AlertRule]
+    {
+        &self.rules
+    }
+
+    /// Longest window of any rule for `asset`, if it has rules at all
+    pub fn window_for(&self, asset: &AssetKind) -> Option<Duration>
+    {
+        self.rules.iter().filter(|r| &r.asset == asset).map(|r| r.window).max()
+    }
+
+    /// Preload history (e.g. from the end of the history file) without
+    /// evaluating rules, so a restart doesn't lose the window.
+    pub fn seed(&mut self, asset: &AssetKind, rows: impl IntoIterator<Item = PriceRow>)
+    {
+        for row in rows
+        {
+            self.push(asset, row);
+        }
+    }
+
+    /// Record a new price and return the alerts it triggers.
+    pub fn observe(&mut self, asset: &AssetKind, row: PriceRow) -> Vec<Alert>
+    {
+        let currency = row.currency.clone();
+        if !self.push(asset, row)
+        {
+            return Vec::new();
+        }
+        let series: Vec<PriceRow> = self.history[&(asset.clone(), currency.clone())].iter().cloned().collect();
+        let mut alerts = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate().filter(|(_, r)| &r.asset == asset)
+        {
+            let key = (i, currency.clone());
+            if let Some(alert) = evaluate(rule, &series, self.last_fired.get(&key).copied())
+            {
+                self.last_fired.insert(key, alert.to_time);
+                alerts.push(alert);
+            }
+        }
+        alerts
+    }
+
+    /// Append to the asset's history and drop what's older than any rule
+    /// needs; false if no rule covers the asset.
+    fn push(&mut self, asset: &AssetKind, row: PriceRow) -> bool
+    {
+        let Some(window) = self.window_for(asset).and_then(|w| chrono::Duration::from_std(w).ok()) else { return false };
+        let history = self.history.entry((asset.clone(), row.currency.clone())).or_default();
+        let start = row.fetched_at - window;
+        history.push_back(row);
+        while history.front().is_some_and(|r| r.fetched_at < start)
+        {
+            history.pop_front();
+        }
+        true
+    }
+}
+
+// Alerters
+
+/// Where fired alerts go.
+pub trait Alerter: Send
+{
+    fn notify(&mut self, alert: &Alert) -> io::Result<()>;
+}
+
+/// Prints a line to stdout.
+#[derive(Debug, Default)]
+pub struct StdoutAlerter;
+
+impl Alerter for StdoutAlerter
+{
+    fn notify(&mut self, alert: &Alert) -> io::Result<()>
+    {
+        println!("ALERT: {alert}");
+        Ok(())
+    }
+}
+
+/// POSTs the alert as JSON.
+#[derive(Debug)]
+pub struct WebhookAlerter
+{
+    pub url: String,
+}
+
+impl Alerter for WebhookAlerter
+{
+    fn notify(&mut self, alert: &Alert) -> io::Result<()>
+    {
+        let body = serde_json::to_string(alert)?;
+        ureq::post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&body)
+            .map(|_| ())
+            .map_err(|e| io::Error::other(format!("webhook {}: {e}", self.url)))
+    }
+}
+
+/// Runs a shell command with the alert in `ALERT_*` environment variables.
+#[derive(Debug)]
+pub struct CommandAlerter
+{
+    pub command: String,
+}
+
+impl Alerter for CommandAlerter
+{
+    fn notify(&mut self, alert: &Alert) -> io::Result<()>
+    {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("ALERT_ASSET", &alert.asset)
+            .env("ALERT_CURRENCY", &alert.currency)
+            .env("ALERT_CHANGE_PCT", format!("{:.4}", alert.change_pct))
+            .env("ALERT_PRICE", alert.to_price.to_string())
+            .env("ALERT_MESSAGE", alert.to_string())
+            .status()?;
+        if status.success()
+        {
+            Ok(())
+        }
+        else
+        {
+            Err(io::Error::other(format!("alert command exited with {status}")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    fn btc() -> AssetKind
+    {
+        AssetKind::coin("bitcoin")
+    }
+
+    fn rule(pct: f64, window_mins: u64, cooldown_mins: u64) -> AlertRule
+    {
+        AlertRule {
+            asset: btc(),
+            percent_change: pct,
+            window: Duration::from_secs(window_mins * 60),
+            cooldown: Duration::from_secs(cooldown_mins * 60),
+        }
+    }
+
+    /// `(minute, price)` points from midnight, in USD
+    fn series(points: &[(i64, f64)]) -> Vec<PriceRow>
+    {
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap();
+        points
+            .iter()
+            .map(|&(min, price)| PriceRow {
+                fetched_at: start + chrono::Duration::minutes(min),
+                currency: "usd".to_string(),
+                price: Decimal::try_from(price).unwrap(),
+                source: String::new(),
+                market_at: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fires_when_the_move_crosses_the_threshold()
+    {
+        let r = rule(5.0, 60, 60);
+        assert_eq!(evaluate(&r, &series(&[(0, 100.0), (30, 104.9)]), None), None);
+
+        let alert = evaluate(&r, &series(&[(0, 100.0), (10, 102.0), (30, 105.0)]), None).unwrap();
+        assert_eq!((alert.from_price, alert.to_price), (dec!(100), dec!(105)));
+        assert!((alert.change_pct - 5.0).abs() < 1e-9);
+
+        // Drops count too, measured from the highest point in the window
+        let alert = evaluate(&r, &series(&[(0, 100.0), (20, 110.0), (40, 103.0)]), None).unwrap();
+        assert!((alert.change_pct + 6.36).abs() < 0.01, "{}", alert.change_pct);
+    }
+
+    #[test]
+    fn moves_older_than_the_window_are_ignored()
+    {
+        let r = rule(5.0, 60, 60);
+        // 100 -> 120 is +20%, but 100 was 61 minutes ago
+        assert_eq!(evaluate(&r, &series(&[(0, 100.0), (61, 120.0)]), None), None);
+        assert!(evaluate(&r, &series(&[(0, 100.0), (60, 120.0)]), None).is_some());
+    }
+
+    #[test]
+    fn cooldown_suppresses_repeats()
+    {
+        let mut tracker = AlertTracker::new(vec![rule(5.0, 60, 30)]);
+        let rows = series(&[(0, 100.0), (1, 110.0), (2, 111.0), (20, 112.0), (31, 113.0)]);
+        let fired: Vec<usize> = rows
+            .into_iter()
+            .enumerate()
+            .filter(|(_, row)| !tracker.observe(&btc(), row.clone()).is_empty())
+            .map(|(i, _)| i)
+            .collect();
+        // Fires at minute 1, stays quiet while the move persists, fires again
+        // once 30 minutes have passed
+        assert_eq!(fired, vec![1, 4]);
+    }
+
+    #[test]
+    fn seeded_history_counts_toward_the_window()
+    {
+        let mut tracker = AlertTracker::new(vec![rule(5.0, 60, 60)]);
+        let rows = series(&[(0, 100.0), (50, 106.0)]);
+        tracker.seed(&btc(), [rows[0].clone()]);
+        assert_eq!(tracker.observe(&btc(), rows[1].clone()).len(), 1);
+        assert!(tracker.observe(&AssetKind::sp500(), rows[1].clone()).is_empty());
+    }
+
+    #[test]
+    fn parses_rule_specs()
+    {
+        let resolve = |name: &str| name.parse::<AssetKind>().ok();
+        assert_eq!(AlertRule::parse("btc:5:1h", resolve).unwrap(), rule(5.0, 60, 60));
+        assert_eq!(AlertRule::parse("bitcoin : 2.5% : 30m : 2h", resolve).unwrap(), AlertRule {
+            percent_change: 2.5,
+            ..rule(0.0, 30, 120)
+        });
+        assert!(AlertRule::parse("btc:5", resolve).is_err());
+        assert!(AlertRule::parse("btc:-5:1h", resolve).is_err());
+        assert!(AlertRule::parse("doge:5:1h", resolve).unwrap_err().contains("not a tracked asset"));
+    }
+}
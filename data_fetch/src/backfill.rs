@@ -0,0 +1,232 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+use crate::{
+    parse_market_chart, parse_yahoo_series, source::YAHOO_BASE_URL, yahoo_chart_url, AssetKind, Decimal, FetchError,
+    HttpGet, PriceRow, Sleep, SourceConfig, DEFAULT_CURRENCY,
+};
+
+/// Longest span asked for in one request. CoinGecko only returns hourly
+/// points for ranges up to 90 days, and daily ones beyond.
+pub const MAX_WINDOW: Duration = Duration::from_secs(90 * 24 * 3600);
+
+/// Wait between requests unless told otherwise; CoinGecko's free tier
+/// allows about 30 a minute
+pub const DEFAULT_PAUSE: Duration = Duration::from_secs(2);
+
+/// `[from, to)` cut into consecutive windows no longer than `max`, oldest
+/// first.
+pub fn backfill_windows(from: DateTime<Utc>, to: DateTime<Utc>, max: Duration) -> Vec<(DateTime<Utc>, DateTime<Utc>)>
+{
+    let step = chrono::Duration::from_std(max).unwrap_or(chrono::Duration::MAX).max(chrono::Duration::seconds(1));
+    let mut windows = Vec::new();
+    let mut start = from;
+    while start < to
+    {
+        let end = start.checked_add_signed(step).map_or(to, |end| end.min(to));
+        windows.push((start, end));
+        start = end;
+    }
+    windows
+}
+
+pub fn coingecko_range_url(base: &str, id: &str, currency: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> String
+{
+    format!("{base}/coins/{id}/market_chart/range?vs_currency={currency}&from={}&to={}", from.timestamp(), to.timestamp())
+}
+
+/// Hourly closes of `symbol` between `from` and `to`
+pub fn yahoo_range_url(base: &str, symbol: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> String
+{
+    format!("{}?period1={}&period2={}&interval=1h", yahoo_chart_url(base, symbol), from.timestamp(), to.timestamp())
+}
+
+/// Fetches past prices a window at a time, pausing between requests.
+pub struct Backfiller
+{
+    http: Box<dyn HttpGet>,
+    currency: String,
+    coingecko: SourceConfig,
+    yahoo: SourceConfig,
+    pause: Duration,
+    sleep: Sleep,
+}
+
+impl Backfiller
+{
+    pub fn new(http: Box<dyn HttpGet>) -> Self
+    {
+        Self {
+            http,
+            currency: DEFAULT_CURRENCY.to_string(),
+            coingecko: SourceConfig::default(),
+            yahoo: SourceConfig::default(),
+            pause: DEFAULT_PAUSE,
+            sleep: Box::new(|d| {
+                std::thread::sleep(d);
+                true
+            }),
+        }
+    }
+
+    /// Quote coins in `currency`; tickers are always in their own
+    pub fn currency(mut self, currency: &str) -> Self
+    {
+        self.currency = currency.trim().to_ascii_lowercase();
+        self
+    }
+
+    /// Base URLs and keys, as for the live sources
+    pub fn configs(mut self, coingecko: SourceConfig, yahoo: SourceConfig) -> Self
+    {
+        self.coingecko = coingecko;
+        self.yahoo = yahoo;
+        self
+    }
+
+    /// Wait this long between requests
+    pub fn pause(mut self, pause: Duration) -> Self
+    {
+        self.pause = pause;
+        self
+    }
+
+    /// Wait through `sleep` instead of blocking the thread; it returns false
+    /// to stop early (on Ctrl+C, say) with what's been fetched so far.
+    pub fn sleep(mut self, sleep: Sleep) -> Self
+    {
+        self.sleep = sleep;
+        self
+    }
+
+    /// Prices of `asset` from `from` to `to`, oldest first, stamped with the
+    /// time they were current. `progress` hears after each window how many
+    /// are done, out of how many, and the prices so far.
+    pub fn fetch(
+        &mut self,
+        asset: &AssetKind,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        mut progress: impl FnMut(usize, usize, usize),
+    ) -> Result<Vec<PriceRow>, FetchError>
+    {
+        let windows = backfill_windows(from, to, MAX_WINDOW);
+        let mut rows = Vec::new();
+        for (i, &(start, end)) in windows.iter().enumerate()
+        {
+            if i > 0 && !(self.sleep)(self.pause)
+            {
+                break;
+            }
+            rows.extend(self.fetch_window(asset, start, end)?);
+            progress(i + 1, windows.len(), rows.len());
+        }
+        Ok(rows)
+    }
+
+    fn fetch_window(&mut self, asset: &AssetKind, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<PriceRow>, FetchError>
+    {
+        let row = |at: DateTime<Utc>, currency: &str, price: Decimal, source: &str, market_at| PriceRow {
+            fetched_at: at,
+            currency: currency.to_string(),
+            price,
+            source: source.to_string(),
+            market_at,
+        };
+        match asset
+        {
+            AssetKind::Coin(id) =>
+            {
+                let url = coingecko_range_url(self.coingecko.coingecko_base(), id, &self.currency, from, to);
+                let key = self.coingecko.key_header("x-cg-pro-api-key");
+                let series = parse_market_chart(&self.http.get_with_headers(&url, key.as_slice())?)?;
+                Ok(series.into_iter().map(|(at, price)| row(at, &self.currency, price, "CoinGecko", None)).collect())
+            }
+            AssetKind::Ticker(_) | AssetKind::Metal(_) =>
+            {
+                let symbol = asset.yahoo_symbol().unwrap_or_default();
+                let url = yahoo_range_url(self.yahoo.base_or(YAHOO_BASE_URL), symbol, from, to);
+                let (currency, series) = parse_yahoo_series(&self.http.get(&url)?)?;
+                let currency = currency.unwrap_or_else(|| DEFAULT_CURRENCY.to_string());
+                Ok(series.into_iter().map(|(at, price)| row(at, &currency, price, "Yahoo Finance", Some(at))).collect())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::ScriptedHttp;
+    use chrono::TimeZone;
+    use std::sync::{Arc, Mutex};
+
+    const MARKET_CHART: &str = include_str!("../tests/fixtures/coingecko_market_chart.json");
+    const HOURLY: &str = include_str!("../tests/fixtures/yahoo_chart_hourly.json");
+
+    fn day(d: u32) -> DateTime<Utc>
+    {
+        Utc.with_ymd_and_hms(2025, 8, d, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn windows_cover_the_range()
+    {
+        let week = Duration::from_secs(7 * 24 * 3600);
+        assert_eq!(backfill_windows(day(1), day(20), week), [(day(1), day(8)), (day(8), day(15)), (day(15), day(20))]);
+        assert_eq!(backfill_windows(day(1), day(8), week), [(day(1), day(8))]);
+        assert!(backfill_windows(day(8), day(8), week).is_empty());
+    }
+
+    #[test]
+    fn long_ranges_pause_between_windows()
+    {
+        let http = ScriptedHttp::new().respond("https://api.coingecko.com", MARKET_CHART);
+        let pauses = Arc::new(Mutex::new(Vec::new()));
+        let log = pauses.clone();
+        let sleep = Box::new(move |d| {
+            log.lock().unwrap().push(d);
+            true
+        });
+        let mut backfiller =
+            Backfiller::new(Box::new(http.clone())).currency("EUR").pause(Duration::from_secs(3)).sleep(sleep);
+        let mut progress = Vec::new();
+        let from = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let rows = backfiller
+            .fetch(&AssetKind::coin("bitcoin"), from, from + chrono::Duration::days(200), |done, of, n| progress.push((done, of, n)))
+            .unwrap();
+
+        assert_eq!(progress, [(1, 3, 3), (2, 3, 6), (3, 3, 9)]);
+        assert_eq!(*pauses.lock().unwrap(), [Duration::from_secs(3); 2]);
+        assert_eq!(rows.len(), 9);
+        assert_eq!(rows[0].currency, "eur");
+        assert_eq!(
+            http.urls()[0],
+            "https://api.coingecko.com/api/v3/coins/bitcoin/market_chart/range?vs_currency=eur&from=1735689600&to=1743465600"
+        );
+    }
+
+    #[test]
+    fn ticker_history_keeps_the_chart_currency()
+    {
+        let http = ScriptedHttp::new().respond("https://query2.finance.yahoo.com", HOURLY);
+        let rows = Backfiller::new(Box::new(http.clone())).fetch(&AssetKind::sp500(), day(5), day(6), |_, _, _| {}).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].to_csv(), "2025-08-05T13:30:00Z,usd,6351.59,Yahoo Finance,2025-08-05T13:30:00Z");
+        assert_eq!(
+            http.urls(),
+            ["https://query2.finance.yahoo.com/v8/finance/chart/%5EGSPC?period1=1754352000&period2=1754438400&interval=1h"]
+        );
+    }
+
+    #[test]
+    fn interrupted_pause_keeps_what_was_fetched()
+    {
+        let http = ScriptedHttp::new().respond("https://api.coingecko.com", MARKET_CHART);
+        let mut backfiller = Backfiller::new(Box::new(http.clone())).sleep(Box::new(|_| false));
+        let rows = backfiller.fetch(&AssetKind::coin("bitcoin"), day(1), day(1) + chrono::Duration::days(365), |_, _, _| {}).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(http.urls().len(), 1);
+    }
+}
@@ -0,0 +1,278 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use std::{collections::BTreeMap, fmt::Write as _};
+
+use crate::{
+    tracker::{GREEN, RED, RESET},
+    Decimal, Delta, PollEvent, PriceRecord,
+};
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One bar per value, scaled so the lowest value is `▁` and the highest
+/// `█`. A flat series is drawn at mid height, since there's no range to
+/// scale to.
+pub fn sparkline(values: &[Decimal]) -> String
+{
+    let (Some(min), Some(max)) = (values.iter().min(), values.iter().max()) else { return String::new() };
+    let range = *max - *min;
+    values
+        .iter()
+        .map(|v| {
+            if range.is_zero()
+            {
+                return BARS[3];
+            }
+            let level = ((*v - *min) * Decimal::from(BARS.len() - 1) / range).round();
+            BARS[level.to_usize().unwrap_or_default().min(BARS.len() - 1)]
+        })
+        .collect()
+}
+
+/// What the board knows of one asset in one currency
+#[derive(Debug, Clone)]
+struct Line
+{
+    /// The latest prices, oldest first
+    history: Vec<Decimal>,
+    since_last: Option<Delta>,
+    fetched_at: DateTime<Utc>,
+}
+
+/// The latest price of every asset, kept up to date from the poll events
+/// and drawn as a table for `data_fetch watch`.
+#[derive(Debug)]
+pub struct Board
+{
+    /// How many prices each sparkline covers
+    points: usize,
+    /// Per (asset, currency)
+    lines: BTreeMap<(String, String), Line>,
+    /// Assets whose latest fetch failed, and why
+    asset_errors: BTreeMap<String, String>,
+    /// Sources whose whole request failed in their latest round, and why
+    source_errors: BTreeMap<String, String>,
+    /// Sources that failed in the round still in progress
+    failed_this_round: BTreeMap<String, String>,
+}
+
+impl Board
+{
+    pub fn new(points: usize) -> Self
+    {
+        Self {
+            points: points.max(1),
+            lines: BTreeMap::new(),
+            asset_errors: BTreeMap::new(),
+            source_errors: BTreeMap::new(),
+            failed_this_round: BTreeMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, event: &PollEvent)
+    {
+        match event
+        {
+            PollEvent::Price(PriceRecord { asset, row }) =>
+            {
+                self.asset_errors.remove(asset.name());
+                let key = (asset.name().to_string(), row.currency.clone());
+                let line = self.lines.entry(key).or_insert_with(|| Line {
+                    history: Vec::new(),
+                    since_last: None,
+                    fetched_at: row.fetched_at,
+                });
+                line.since_last = line.history.last().map(|&last| Delta::between(last, row.price));
+                line.fetched_at = row.fetched_at;
+                line.history.push(row.price);
+                if line.history.len() > self.points
+                {
+                    line.history.remove(0);
+                }
+            }
+            PollEvent::Failed { asset, error, .. } =>
+            {
+                self.asset_errors.insert(asset.name().to_string(), error.to_string());
+            }
+            PollEvent::SourceFailed { source, assets, error } =>
+            {
+                for asset in assets
+                {
+                    self.asset_errors.insert(asset.name().to_string(), error.to_string());
+                }
+                self.failed_this_round.insert(source.clone(), error.to_string());
+            }
+            PollEvent::RoundDone { source, .. } => match self.failed_this_round.remove(source)
+            {
+                Some(error) =>
+                {
+                    self.source_errors.insert(source.clone(), error);
+                }
+                None =>
+                {
+                    self.source_errors.remove(source);
+                }
+            },
+        }
+    }
+
+    /// The table, one line per asset and currency, then any failing
+    /// sources. Assets that have never been priced still get a line so
+    /// their error shows. With `color`, rises are green and falls and
+    /// errors red.
+    pub fn render(&self, color: bool) -> String
+    {
+        let unpriced = self.asset_errors.keys().filter(|asset| !self.lines.keys().any(|(name, _)| name == *asset));
+        let mut rows: Vec<(Option<&Line>, [String; 6])> = self
+            .lines
+            .iter()
+            .map(|((asset, currency), line)| {
+                let cells = [
+                    asset.clone(),
+                    format!("{} {currency}", line.history.last().copied().unwrap_or_default()),
+                    line.since_last.map_or_else(|| "-".to_string(), |d| d.to_string()),
+                    sparkline(&line.history),
+                    line.fetched_at.format("%H:%M:%S").to_string(),
+                    self.status(asset),
+                ];
+                (Some(line), cells)
+            })
+            .collect();
+        rows.extend(unpriced.map(|asset| {
+            let cells = [asset.clone(), "-".into(), "-".into(), String::new(), "-".into(), self.status(asset)];
+            (None, cells)
+        }));
+
+        let header = ["asset", "price", "change", "trend", "updated", "status"].map(str::to_string);
+        let mut widths = [0; 6];
+        for cells in std::iter::once(&header).chain(rows.iter().map(|(_, cells)| cells))
+        {
+            for (width, cell) in widths.iter_mut().zip(cells)
+            {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let [w0, w1, w2, w3, w4, _] = widths;
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{:<w0$}  {:<w1$}  {:<w2$}  {:<w3$}  {:<w4$}  {}",
+            header[0], header[1], header[2], header[3], header[4], header[5]
+        );
+        for (line, cells) in &rows
+        {
+            // Pad before coloring, so escape codes don't count toward the width
+            let change = format!("{:<w2$}", cells[2]);
+            let change = match line.and_then(|l| l.since_last).map(|d| d.absolute.cmp(&Decimal::ZERO))
+            {
+                Some(std::cmp::Ordering::Greater) if color => format!("{GREEN}{change}{RESET}"),
+                Some(std::cmp::Ordering::Less) if color => format!("{RED}{change}{RESET}"),
+                _ => change,
+            };
+            let status = if color && cells[5] != "ok" { format!("{RED}{}{RESET}", cells[5]) } else { cells[5].clone() };
+            let _ = writeln!(
+                out,
+                "{:<w0$}  {:<w1$}  {change}  {:<w3$}  {:<w4$}  {status}",
+                cells[0], cells[1], cells[3], cells[4]
+            );
+        }
+        if !self.source_errors.is_empty()
+        {
+            out.push('\n');
+            for (source, error) in &self.source_errors
+            {
+                let _ = writeln!(out, "! {source} is failing: {error}");
+            }
+        }
+        out
+    }
+
+    fn status(&self, asset: &str) -> String
+    {
+        self.asset_errors.get(asset).map_or_else(|| "ok".to_string(), |error| format!("! {error}"))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{AssetKind, FetchError, PriceRow};
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    fn price(asset: &str, minute: u32, price: Decimal) -> PollEvent
+    {
+        let row = PriceRow {
+            fetched_at: Utc.with_ymd_and_hms(2026, 1, 5, 14, minute, 0).unwrap(),
+            currency: "usd".to_string(),
+            price,
+            source: "CoinGecko".to_string(),
+            market_at: None,
+        };
+        PollEvent::Price(PriceRecord::new(AssetKind::coin(asset), row))
+    }
+
+    fn round_done(source: &str) -> PollEvent
+    {
+        PollEvent::RoundDone { source: source.to_string(), next_in: None }
+    }
+
+    #[test]
+    fn sparklines_span_the_series_range()
+    {
+        assert_eq!(sparkline(&[]), "");
+        assert_eq!(sparkline(&[dec!(5)]), "▄");
+        assert_eq!(sparkline(&[dec!(2), dec!(2), dec!(2)]), "▄▄▄");
+        let rising: Vec<Decimal> = (0..8).map(Decimal::from).collect();
+        assert_eq!(sparkline(&rising), "▁▂▃▄▅▆▇█");
+        assert_eq!(sparkline(&[dec!(10), dec!(0), dec!(5), dec!(10)]), "█▁▅█");
+        // Only the shape matters, not the scale
+        assert_eq!(sparkline(&[dec!(100.1), dec!(100.2), dec!(100.3)]), sparkline(&[dec!(1), dec!(2), dec!(3)]));
+    }
+
+    #[test]
+    fn board_keeps_the_last_points_and_the_move_since_the_last_fetch()
+    {
+        let mut board = Board::new(3);
+        for (minute, p) in [(0, dec!(100)), (1, dec!(110)), (2, dec!(105)), (3, dec!(120))]
+        {
+            board.observe(&price("bitcoin", minute, p));
+        }
+        board.observe(&price("ethereum", 3, dec!(3000)));
+        assert_eq!(
+            board.render(false),
+            "asset     price     change         trend  updated   status\n\
+             bitcoin   120 usd   +15 (+14.29%)  ▃▁█    14:03:00  ok\n\
+             ethereum  3000 usd  -              ▄      14:03:00  ok\n"
+        );
+    }
+
+    #[test]
+    fn failures_are_flagged_until_the_next_good_round()
+    {
+        let mut board = Board::new(5);
+        board.observe(&price("bitcoin", 0, dec!(100)));
+        let error = FetchError::Network("connection refused".to_string());
+        let failed = PollEvent::SourceFailed {
+            source: "coingecko".to_string(),
+            assets: vec![AssetKind::coin("bitcoin"), AssetKind::coin("solana")],
+            error,
+        };
+        board.observe(&failed);
+        board.observe(&round_done("coingecko"));
+        let text = board.render(false);
+        assert!(text.contains("bitcoin  100 usd  -       ▄      14:00:00  ! "), "{text}");
+        assert!(text.lines().any(|l| l.starts_with("solana   -")), "{text}");
+        assert!(text.contains("\n! coingecko is failing: "), "{text}");
+        assert!(board.render(true).contains(RED));
+
+        board.observe(&price("bitcoin", 1, dec!(99)));
+        board.observe(&price("solana", 1, dec!(150)));
+        board.observe(&round_done("coingecko"));
+        let text = board.render(false);
+        assert!(!text.contains('!'), "{text}");
+        assert!(board.render(true).contains(&format!("{RED}-1 (-1.00%)")));
+    }
+}
@@ -0,0 +1,367 @@
+use serde::Deserialize;
+use std::{fs, path::{Path, PathBuf}, time::Duration};
+
+use crate::{parse_duration, parse_size, AlertRule, AssetKind, CoinProvider, Rotation, WritePolicy};
+
+/// Config file looked for in the working directory when none is named
+pub const DEFAULT_CONFIG: &str = "data_fetch.toml";
+
+// The file as written. Durations and sizes stay text until validated, so
+// errors can name the key they came from.
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig
+{
+    interval: Option<String>,
+    #[serde(default)]
+    currencies: Vec<String>,
+    sources: Option<Vec<String>>,
+    timeout: Option<String>,
+    max_staleness: Option<String>,
+    market_hours: Option<bool>,
+    #[serde(default)]
+    assets: Vec<RawAsset>,
+    #[serde(default)]
+    output: RawOutput,
+    #[serde(default)]
+    alerts: RawAlerts,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAsset
+{
+    kind: String,
+    id: Option<String>,
+    symbol: Option<String>,
+    currency: Option<String>,
+    interval: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawOutput
+{
+    format: Option<String>,
+    directory: Option<PathBuf>,
+    database: Option<PathBuf>,
+    write_policy: Option<String>,
+    heartbeat: Option<String>,
+    #[serde(default)]
+    rotation: RawRotation,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRotation
+{
+    max_size: Option<String>,
+    #[serde(default)]
+    daily: bool,
+    #[serde(default)]
+    compress: bool,
+    keep: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAlerts
+{
+    #[serde(default)]
+    rules: Vec<String>,
+    webhook: Option<String>,
+    command: Option<String>,
+}
+
+/// A validated `data_fetch.toml`. Anything left out is `None` (or empty),
+/// so the command line's value or default applies.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config
+{
+    /// Time between rounds for assets without their own
+    pub interval: Option<Duration>,
+    /// Currencies for coins without their own
+    pub currencies: Vec<String>,
+    pub sources: Option<Vec<CoinProvider>>,
+    pub timeout: Option<Duration>,
+    pub max_staleness: Option<Duration>,
+    /// Fetch stock tickers only while US markets are open
+    pub market_hours: Option<bool>,
+    pub assets: Vec<AssetConfig>,
+    pub output: OutputConfig,
+    pub alerts: AlertsConfig,
+}
+
+/// One `[[assets]]` entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetConfig
+{
+    pub asset: AssetKind,
+    /// Coins only; tickers are quoted in their own currency
+    pub currency: Option<String>,
+    pub interval: Option<Duration>,
+}
+
+/// Where prices are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat
+{
+    Csv,
+    Sqlite,
+}
+
+/// The `[output]` table.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputConfig
+{
+    pub format: Option<OutputFormat>,
+    /// Where CSV history files go
+    pub directory: Option<PathBuf>,
+    /// The SQLite database, with `format = "sqlite"`
+    pub database: Option<PathBuf>,
+    pub write_policy: Option<WritePolicy>,
+    pub rotation: Rotation,
+}
+
+/// The `[alerts]` table. Rules are kept as written, in the `--alert`
+/// syntax, until the tracked assets are known; see [`Config::alert_rules`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlertsConfig
+{
+    pub rules: Vec<String>,
+    pub webhook: Option<String>,
+    pub command: Option<String>,
+}
+
+impl Config
+{
+    /// Read and validate the file at `path`.
+    pub fn load(path: &Path) -> Result<Self, String>
+    {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Validate config text. Errors name the table and key at fault, e.g.
+    /// `assets[1].interval: invalid duration 'soon' (e.g. 30m, 12h, 7d)`.
+    pub fn parse(text: &str) -> Result<Self, String>
+    {
+        let raw: RawConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+        let sources = match raw.sources
+        {
+            Some(names) => Some(
+                names
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| name.parse().map_err(|e| format!("sources[{i}]: {e}")))
+                    .collect::<Result<_, String>>()?,
+            ),
+            None => None,
+        };
+        let assets =
+            raw.assets.iter().enumerate().map(|(i, a)| asset(a).map_err(|e| format!("assets[{i}].{e}"))).collect::<Result<_, _>>()?;
+        Ok(Self {
+            interval: duration("interval", &raw.interval)?,
+            currencies: raw.currencies.iter().map(|c| c.trim().to_ascii_lowercase()).collect(),
+            sources,
+            timeout: duration("timeout", &raw.timeout)?,
+            max_staleness: duration("max_staleness", &raw.max_staleness)?,
+            market_hours: raw.market_hours,
+            assets,
+            output: output(raw.output).map_err(|e| format!("output.{e}"))?,
+            alerts: AlertsConfig { rules: raw.alerts.rules, webhook: raw.alerts.webhook, command: raw.alerts.command },
+        })
+    }
+
+    /// The `[alerts]` rules, for assets among `tracked`.
+    pub fn alert_rules(&self, tracked: &[AssetKind]) -> Result<Vec<AlertRule>, String>
+    {
+        self.alerts
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| AlertRule::parse(spec, |name| AssetKind::lookup(name, tracked)).map_err(|e| format!("alerts.rules[{i}]: {e}")))
+            .collect()
+    }
+}
+
+fn duration(key: &str, value: &Option<String>) -> Result<Option<Duration>, String>
+{
+    value.as_deref().map(parse_duration).transpose().map_err(|e| format!("{key}: {e}"))
+}
+
+/// One `[[assets]]` entry; errors start with the key, for the caller to
+/// prefix with the entry
+fn asset(raw: &RawAsset) -> Result<AssetConfig, String>
+{
+    let asset = match raw.kind.trim().to_ascii_lowercase().as_str()
+    {
+        "coingecko" =>
+        {
+            if raw.symbol.is_some()
+            {
+                return Err("symbol: coingecko assets are named by id".to_string());
+            }
+            let id = raw.id.as_deref().filter(|id| !id.trim().is_empty());
+            AssetKind::parse_coin(id.ok_or("id: missing for a coingecko asset")?).map_err(|e| format!("id: {e}"))?
+        }
+        "yahoo" =>
+        {
+            if raw.id.is_some()
+            {
+                return Err("id: yahoo assets are named by symbol".to_string());
+            }
+            if raw.currency.is_some()
+            {
+                return Err("currency: yahoo tickers are always quoted in their own currency".to_string());
+            }
+            let symbol = raw.symbol.as_deref().filter(|s| !s.trim().is_empty());
+            AssetKind::ticker(symbol.ok_or("symbol: missing for a yahoo asset")?)
+        }
+        "metal" =>
+        {
+            if raw.symbol.is_some()
+            {
+                return Err("symbol: metals are named by id (gold, silver, ...)".to_string());
+            }
+            if raw.currency.is_some()
+            {
+                return Err("currency: metals are always quoted in USD".to_string());
+            }
+            let id = raw.id.as_deref().ok_or("id: missing for a metal")?;
+            AssetKind::Metal(id.parse().map_err(|e| format!("id: {e}"))?)
+        }
+        other => return Err(format!("kind: unknown kind '{other}' (valid: coingecko, yahoo, metal)")),
+    };
+    Ok(AssetConfig {
+        asset,
+        currency: raw.currency.as_deref().map(|c| c.trim().to_ascii_lowercase()),
+        interval: duration("interval", &raw.interval)?,
+    })
+}
+
+fn output(raw: RawOutput) -> Result<OutputConfig, String>
+{
+    let format = match raw.format.as_deref().map(str::trim)
+    {
+        None => None,
+        Some("csv") => Some(OutputFormat::Csv),
+        Some("sqlite") if cfg!(feature = "sqlite") => Some(OutputFormat::Sqlite),
+        Some("sqlite") => return Err("format: sqlite needs data_fetch built with --features sqlite".to_string()),
+        Some(other) => return Err(format!("format: unknown format '{other}' (valid: csv, sqlite)")),
+    };
+    if format == Some(OutputFormat::Sqlite) && raw.database.is_none()
+    {
+        return Err("database: needed with format = \"sqlite\"".to_string());
+    }
+    let heartbeat = duration("heartbeat", &raw.heartbeat)?;
+    let write_policy = match (raw.write_policy.as_deref().map(str::trim), heartbeat)
+    {
+        (None, None) => None,
+        (Some("always"), None) => Some(WritePolicy::Always),
+        (Some("always"), Some(_)) => return Err("heartbeat: only applies with write_policy = \"on-change\"".to_string()),
+        (Some("on-change"), None) => Some(WritePolicy::OnChange),
+        (Some("on-change") | None, Some(every)) => Some(WritePolicy::OnChangeOrInterval(every)),
+        (Some(other), _) => return Err(format!("write_policy: unknown policy '{other}' (valid: always, on-change)")),
+    };
+    let rotation = Rotation {
+        max_bytes: raw.rotation.max_size.as_deref().map(parse_size).transpose().map_err(|e| format!("rotation.max_size: {e}"))?,
+        daily: raw.rotation.daily,
+        compress: raw.rotation.compress,
+        keep: raw.rotation.keep,
+    };
+    Ok(OutputConfig { format, directory: raw.directory, database: raw.database, write_policy, rotation })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::Metal;
+
+    const EXAMPLE: &str = include_str!("../data_fetch.example.toml");
+
+    fn err(text: &str) -> String
+    {
+        Config::parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn example_config()
+    {
+        let config = Config::parse(EXAMPLE).unwrap();
+        assert_eq!(config.interval, Some(Duration::from_secs(60)));
+        assert_eq!(config.currencies, ["usd", "eur"]);
+        assert_eq!(config.sources, Some(vec![CoinProvider::CoinGecko, CoinProvider::Binance]));
+        assert_eq!(config.max_staleness, Some(Duration::from_secs(2 * 3600)));
+        assert_eq!(config.market_hours, Some(true));
+        assert_eq!(
+            config.assets,
+            [
+                AssetConfig { asset: AssetKind::coin("bitcoin"), currency: None, interval: Some(Duration::from_secs(15)) },
+                AssetConfig { asset: AssetKind::coin("ethereum"), currency: None, interval: None },
+                AssetConfig { asset: AssetKind::coin("solana"), currency: Some("gbp".to_string()), interval: None },
+                AssetConfig { asset: AssetKind::sp500(), currency: None, interval: Some(Duration::from_secs(300)) },
+                AssetConfig { asset: AssetKind::Metal(Metal::Gold), currency: None, interval: Some(Duration::from_secs(300)) },
+            ]
+        );
+        assert_eq!(config.output.format, Some(OutputFormat::Csv));
+        assert_eq!(config.output.directory.as_deref(), Some(Path::new("prices")));
+        assert_eq!(config.output.write_policy, Some(WritePolicy::OnChangeOrInterval(Duration::from_secs(3600))));
+        assert_eq!(
+            config.output.rotation,
+            Rotation { max_bytes: Some(10 << 20), daily: false, compress: true, keep: Some(14) }
+        );
+        assert_eq!(config.alerts.webhook.as_deref(), Some("https://hooks.example.com/prices"));
+
+        let tracked: Vec<AssetKind> = config.assets.iter().map(|a| a.asset.clone()).collect();
+        let rules = config.alert_rules(&tracked).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!((rules[1].asset.clone(), rules[1].percent_change), (AssetKind::sp500(), 2.0));
+        assert_eq!(rules[1].cooldown, Duration::from_secs(4 * 3600));
+    }
+
+    #[test]
+    fn everything_is_optional()
+    {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn errors_name_the_key()
+    {
+        assert_eq!(err("interval = \"soon\""), "interval: invalid duration 'soon' (e.g. 30m, 12h, 7d)");
+        assert_eq!(err("sources = [\"coingecko\", \"kraken\"]"), "sources[1]: unknown source 'kraken' (valid: coingecko, binance, coinbase)");
+
+        let assets = "[[assets]]\nkind = \"coingecko\"\nid = \"bitcoin\"\n\n[[assets]]\nkind = \"coingecko\"\nid = \"ethereum\"\ninterval = \"5x\"";
+        assert_eq!(err(assets), "assets[1].interval: invalid duration '5x': unit must be s, m, h or d");
+        assert_eq!(err("[[assets]]\nkind = \"kraken\""), "assets[0].kind: unknown kind 'kraken' (valid: coingecko, yahoo, metal)");
+        assert_eq!(err("[[assets]]\nkind = \"yahoo\""), "assets[0].symbol: missing for a yahoo asset");
+        assert_eq!(
+            err("[[assets]]\nkind = \"coingecko\"\nid = \"../btc\""),
+            "assets[0].id: invalid coin id '../btc' (only letters, digits and '-')"
+        );
+        assert!(err("[[assets]]\nkind = \"yahoo\"\nsymbol = \"AAPL\"\ncurrency = \"eur\"").starts_with("assets[0].currency: "));
+        assert_eq!(
+            err("[[assets]]\nkind = \"metal\"\nid = \"tin\""),
+            "assets[0].id: unknown metal 'tin' (valid: gold, silver, platinum, palladium, copper)"
+        );
+
+        assert_eq!(err("[output]\nformat = \"parquet\""), "output.format: unknown format 'parquet' (valid: csv, sqlite)");
+        assert!(err("[output.rotation]\nmax_size = \"lots\"").starts_with("output.rotation.max_size: invalid size"));
+        assert!(err("[output]\nwrite_policy = \"always\"\nheartbeat = \"1h\"").starts_with("output.heartbeat: "));
+
+        // Typos are caught by the parser, which names the key and line
+        let typo = err("[output]\ndirectry = \"prices\"");
+        assert!(typo.contains("unknown field `directry`") && typo.contains("line 2"), "{typo}");
+    }
+
+    #[test]
+    fn alert_rules_need_tracked_assets()
+    {
+        let config = Config::parse("[alerts]\nrules = [\"btc:5:1h\", \"doge:5:1h\"]").unwrap();
+        let e = config.alert_rules(&[AssetKind::coin("bitcoin")]).unwrap_err();
+        assert!(e.starts_with("alerts.rules[1]: "), "{e}");
+    }
+}
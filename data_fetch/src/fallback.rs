@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use crate::{AssetKind, FetchError, PriceSource, Prices};
+
+/// Tries sources in order, asking each one only while some asset it serves
+/// still has no price; a later source never replaces an earlier one's price.
+/// Every quote is tagged with the source that supplied it. The whole fetch
+/// fails only if no asset could be priced, with the first source's error,
+/// so a retry wrapper sees the primary's 429 or 5xx.
+pub struct FallbackSource
+{
+    sources: Vec<Box<dyn PriceSource>>,
+    assets: Vec<AssetKind>,
+}
+
+impl FallbackSource
+{
+    pub fn new(sources: Vec<Box<dyn PriceSource>>) -> Self
+    {
+        let mut assets: Vec<AssetKind> = Vec::new();
+        for asset in sources.iter().flat_map(|s| s.assets())
+        {
+            if !assets.contains(asset)
+            {
+                assets.push(asset.clone());
+            }
+        }
+        Self { sources, assets }
+    }
+}
+
+/// A copy of `e` for each asset it applies to. Parse errors can't be
+/// cloned, so they are carried as their message.
+fn copy_error(e: &FetchError) -> FetchError
+{
+    match e
+    {
+        FetchError::Network(msg) => FetchError::Network(msg.clone()),
+        FetchError::Status { url, code, retry_after } =>
+        {
+            FetchError::Status { url: url.clone(), code: *code, retry_after: *retry_after }
+        }
+        FetchError::Parse(_) => FetchError::Network(e.to_string()),
+        FetchError::MissingField(field) => FetchError::MissingField(field.clone()),
+        FetchError::Stale { market_at, age } => FetchError::Stale { market_at: *market_at, age: *age },
+    }
+}
+
+impl PriceSource for FallbackSource
+{
+    fn name(&self) -> &str
+    {
+        self.sources.first().map_or("none", |s| s.name())
+    }
+
+    fn assets(&self) -> &[AssetKind]
+    {
+        &self.assets
+    }
+
+    fn fetch_prices(&mut self) -> Result<Prices, FetchError>
+    {
+        let mut prices: Prices = BTreeMap::new();
+        let mut first_error = None;
+        for source in &mut self.sources
+        {
+            let priced = |prices: &Prices, asset: &AssetKind| matches!(prices.get(asset), Some(Ok(_)));
+            if source.assets().iter().all(|asset| priced(&prices, asset))
+            {
+                continue;
+            }
+            match source.fetch_prices()
+            {
+                Ok(fetched) =>
+                {
+                    for (asset, result) in fetched
+                    {
+                        if !priced(&prices, &asset)
+                        {
+                            let tagged = result.map(|quotes| quotes.into_iter().map(|q| q.from_source(source.name())).collect());
+                            prices.insert(asset, tagged);
+                        }
+                    }
+                }
+                Err(e) =>
+                {
+                    eprintln!("{}: {e}; trying the next source", source.name());
+                    for asset in source.assets()
+                    {
+                        if !priced(&prices, asset)
+                        {
+                            prices.insert(asset.clone(), Err(copy_error(&e)));
+                        }
+                    }
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error
+        {
+            Some(e) if !prices.values().any(Result::is_ok) => Err(e),
+            _ => Ok(prices),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{BinanceClient, CoinGeckoClient, CoinbaseClient, Quote, ScriptedHttp};
+    use rust_decimal_macros::dec;
+
+    const BINANCE: &str = include_str!("../tests/fixtures/binance_ticker_price.json");
+    const COINBASE: &str = include_str!("../tests/fixtures/coinbase_spot.json");
+    const PARTIAL: &str = include_str!("../tests/fixtures/coingecko_missing_ethereum.json");
+
+    /// A CoinGecko, Binance, Coinbase chain for bitcoin and ethereum over
+    /// `script`
+    fn chain(script: ScriptedHttp) -> (FallbackSource, ScriptedHttp)
+    {
+        let http = || Box::new(script.clone());
+        let usd = vec!["usd".to_string()];
+        let coins = [AssetKind::coin("bitcoin"), AssetKind::coin("ethereum")];
+        let mut sources: Vec<Box<dyn PriceSource>> = vec![Box::new(CoinGeckoClient::with_http(&coins, http()))];
+        for coin in ["bitcoin", "ethereum"]
+        {
+            sources.push(Box::new(BinanceClient::with_http(coin, &usd, http())));
+        }
+        for coin in ["bitcoin", "ethereum"]
+        {
+            sources.push(Box::new(CoinbaseClient::with_http(coin, &usd, http())));
+        }
+        (FallbackSource::new(sources), script)
+    }
+
+    fn quote(prices: &Prices, id: &str) -> Quote
+    {
+        match prices[&AssetKind::coin(id)].as_deref()
+        {
+            Ok([quote]) => quote.clone(),
+            other => panic!("{id}: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn first_source_down_falls_through_per_asset()
+    {
+        let (mut source, log) = chain(
+            ScriptedHttp::new()
+                .fail("https://api.coingecko.com", 503)
+                .respond("https://api.binance.com/api/v3/ticker/price?symbol=BTCUSDT", BINANCE)
+                .respond("https://api.coinbase.com/v2/prices/ETH-USD/spot", COINBASE),
+        );
+        let prices = source.fetch_prices().unwrap();
+        assert_eq!(quote(&prices, "bitcoin"), Quote::new("usd", dec!(119461.37)).from_source("Binance"));
+        assert_eq!(quote(&prices, "ethereum"), Quote::new("usd", dec!(119455.12)).from_source("Coinbase"));
+        // Bitcoin was priced by Binance, so Coinbase is only asked for Ethereum
+        assert_eq!(log.urls().len(), 4);
+        assert_eq!(source.name(), "CoinGecko");
+    }
+
+    #[test]
+    fn only_missing_assets_fall_back()
+    {
+        let (mut source, log) = chain(
+            ScriptedHttp::new()
+                .respond("https://api.coingecko.com", PARTIAL)
+                .respond("https://api.binance.com/api/v3/ticker/price?symbol=ETHUSDT", BINANCE),
+        );
+        let prices = source.fetch_prices().unwrap();
+        assert_eq!(quote(&prices, "bitcoin").source.as_deref(), Some("CoinGecko"));
+        assert_eq!(quote(&prices, "ethereum").source.as_deref(), Some("Binance"));
+        assert_eq!(log.urls().len(), 2);
+    }
+
+    #[test]
+    fn all_sources_down_reports_the_primary_error()
+    {
+        let (mut source, _) = chain(ScriptedHttp::new().fail("https://api.coingecko.com", 429));
+        let err = source.fetch_prices().unwrap_err();
+        assert!(matches!(err, FetchError::Status { code: 429, .. }), "{err}");
+        assert!(err.is_retryable());
+    }
+}
@@ -0,0 +1,1318 @@
+use serde::Deserialize;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+mod alert;
+mod backfill;
+mod board;
+mod config;
+mod fallback;
+mod metrics;
+mod output;
+mod poll;
+mod record;
+mod retry;
+mod rotate;
+mod schedule;
+mod shutdown;
+mod source;
+mod stats;
+mod tracker;
+#[cfg(feature = "sqlite")]
+mod store;
+
+pub use rust_decimal::Decimal;
+
+pub use alert::{evaluate, Alert, AlertRule, AlertTracker, Alerter, CommandAlerter, StdoutAlerter, WebhookAlerter};
+pub use backfill::{backfill_windows, coingecko_range_url, yahoo_range_url, Backfiller, DEFAULT_PAUSE, MAX_WINDOW};
+pub use board::{sparkline, Board};
+pub use config::{AlertsConfig, AssetConfig, Config, OutputConfig, OutputFormat, DEFAULT_CONFIG};
+pub use output::{
+    append_row, last_row, merge_rows, rows_since, HistoryWriter, PriceRecord, PriceRow, WritePolicy, CSV_HEADER,
+};
+pub use fallback::FallbackSource;
+pub use metrics::{serve_metrics, Metrics};
+pub use record::{format_price, RecordMode, Recorder, Storage};
+pub use rotate::{parse_size, Rotation};
+pub use schedule::{next_due, MarketHours, Schedule};
+pub use retry::{RetryPolicy, RetryingSource, Sleep};
+pub use shutdown::Shutdown;
+pub use poll::{poll_sources, poll_sources_every, PollEvent};
+pub use source::{
+    sources_for, sources_with_http, split_coingecko, BinanceClient, CoinGeckoClient, CoinProvider, CoinbaseClient,
+    PriceSource, Prices, SourceConfig, SourceOptions, YahooClient,
+};
+pub use stats::{candles, summarize, time_before, Candle, DailyRange, Summary, CANDLE_CSV_HEADER};
+pub use tracker::{render_changes, Change, Delta, PriceTracker};
+#[cfg(feature = "sqlite")]
+pub use store::{Ohlc, PriceStore};
+
+// Errors
+
+/// Why a price couldn't be fetched.
+#[derive(Debug)]
+pub enum FetchError
+{
+    /// Connection, DNS, TLS or read failure
+    Network(String),
+    /// The server answered with a non-success status
+    Status { url: String, code: u16, retry_after: Option<Duration> },
+    /// The body wasn't the JSON we expected
+    Parse(serde_json::Error),
+    /// The JSON parsed but didn't contain the price
+    MissingField(String),
+    /// The price is older than the configured limit
+    Stale { market_at: DateTime<Utc>, age: Duration },
+}
+
+impl fmt::Display for FetchError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            FetchError::Network(msg) => write!(f, "network error: {msg}"),
+            FetchError::Status { url, code, .. } => write!(f, "HTTP {code} from {url}"),
+            FetchError::Parse(e) => write!(f, "invalid JSON: {e}"),
+            FetchError::MissingField(field) => write!(f, "missing field: {field}"),
+            FetchError::Stale { market_at, age } => write!(
+                f,
+                "stale data: price is from {} ({} min old)",
+                market_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                age.as_secs() / 60
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FetchError
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)>
+    {
+        match self
+        {
+            FetchError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl FetchError
+{
+    /// Worth trying again: rate limiting (429) and server errors (5xx)
+    pub fn is_retryable(&self) -> bool
+    {
+        matches!(self, FetchError::Status { code, .. } if *code == 429 || *code >= 500)
+    }
+
+    /// Delay the server asked for via `Retry-After`, if any
+    pub fn retry_after(&self) -> Option<Duration>
+    {
+        match self
+        {
+            FetchError::Status { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for FetchError
+{
+    fn from(e: serde_json::Error) -> Self
+    {
+        FetchError::Parse(e)
+    }
+}
+
+// Trait
+
+/// One asset fetched on its own, without a [`PriceSource`].
+pub trait Pricing: Send
+{
+    /// Fetch the current price, stamped with the time the fetch completed.
+    fn fetch(&self, http: &mut dyn HttpGet) -> Result<PriceRecord, FetchError>;
+}
+
+/// A price in one currency. Currencies are lowercase ISO codes (`usd`, `eur`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote
+{
+    pub currency: String,
+    pub price: Decimal,
+    /// Which source supplied the price, when a fallback chain had to say;
+    /// otherwise it's the source that was asked
+    pub source: Option<String>,
+    /// When the price was current on the market, for sources that say
+    pub market_at: Option<DateTime<Utc>>,
+}
+
+impl Quote
+{
+    pub fn new(currency: impl Into<String>, price: Decimal) -> Self
+    {
+        Self { currency: currency.into(), price, source: None, market_at: None }
+    }
+
+    pub fn at_market_time(mut self, market_at: Option<DateTime<Utc>>) -> Self
+    {
+        self.market_at = market_at;
+        self
+    }
+
+    pub fn from_source(mut self, source: &str) -> Self
+    {
+        self.source = Some(source.to_string());
+        self
+    }
+}
+
+/// Currency used when none is configured
+pub const DEFAULT_CURRENCY: &str = "usd";
+
+// Structs
+
+/// A coin priced by CoinGecko, identified by its CoinGecko id
+/// (`bitcoin`, `solana`, ...), quoted in `currency`. Prices are written to
+/// `<symbol>.csv`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoinGeckoAsset
+{
+    pub id: String,
+    pub symbol: String,
+    pub currency: String,
+}
+
+/// A Yahoo Finance symbol (`^GSPC`, `AAPL`, `SPY`, ...). Prices are written
+/// to a file named after the symbol, e.g. `gspc.csv`. The currency is the
+/// instrument's own, as reported by Yahoo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YahooAsset
+{
+    pub symbol: String,
+}
+
+/// A precious or industrial metal, priced from its front-month COMEX/NYMEX
+/// futures contract on Yahoo Finance. Prices are per [`Metal::unit`], in USD,
+/// and written to a file named after the metal, e.g. `gold.csv`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetalsAsset
+{
+    pub metal: Metal,
+}
+
+impl CoinGeckoAsset
+{
+    pub fn new(id: impl Into<String>, symbol: impl Into<String>) -> Self
+    {
+        Self { id: id.into(), symbol: symbol.into(), currency: DEFAULT_CURRENCY.to_string() }
+    }
+
+    /// Asset for a CoinGecko id, with the ticker symbol for well-known coins
+    /// and the id itself otherwise.
+    pub fn from_id(id: &str) -> Self
+    {
+        Self::new(id, coin_symbol(id))
+    }
+
+    /// Quote in `currency` instead of USD.
+    pub fn in_currency(mut self, currency: &str) -> Self
+    {
+        self.currency = currency.trim().to_ascii_lowercase();
+        self
+    }
+}
+
+/// Ticker symbol for well-known CoinGecko ids, the id itself otherwise
+fn coin_symbol(id: &str) -> &str
+{
+    match id
+    {
+        "bitcoin" => "btc",
+        "ethereum" => "eth",
+        "solana" => "sol",
+        "dogecoin" => "doge",
+        "cardano" => "ada",
+        "ripple" => "xrp",
+        "litecoin" => "ltc",
+        other => other,
+    }
+}
+
+/// Kept so `Bitcoin::new()` still works; builds a [`CoinGeckoAsset`].
+pub struct Bitcoin;
+
+/// Kept so `Ethereum::new()` still works; builds a [`CoinGeckoAsset`].
+pub struct Ethereum;
+
+impl Bitcoin
+{
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> CoinGeckoAsset
+    {
+        CoinGeckoAsset::from_id("bitcoin")
+    }
+}
+
+impl Ethereum
+{
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> CoinGeckoAsset
+    {
+        CoinGeckoAsset::from_id("ethereum")
+    }
+}
+
+/// Kept so `SP500::new()` still works; builds a [`YahooAsset`] for `^GSPC`.
+pub struct SP500;
+
+impl SP500
+{
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> YahooAsset
+    {
+        YahooAsset::new("^GSPC")
+    }
+}
+
+impl YahooAsset
+{
+    pub fn new(symbol: impl Into<String>) -> Self
+    {
+        Self { symbol: symbol.into() }
+    }
+
+    /// Chart endpoint for this symbol, with `^` and friends percent-encoded.
+    pub fn url(&self) -> String
+    {
+        yahoo_chart_url(source::YAHOO_BASE_URL, &self.symbol)
+    }
+
+}
+
+impl MetalsAsset
+{
+    pub fn new(metal: Metal) -> Self
+    {
+        Self { metal }
+    }
+
+    pub fn url(&self) -> String
+    {
+        yahoo_chart_url(source::YAHOO_BASE_URL, self.metal.futures_symbol())
+    }
+}
+
+/// `^GSPC` -> `gspc`, `BRK-B` -> `brk-b`
+fn ticker_file_stem(symbol: &str) -> String
+{
+    symbol
+        .trim_start_matches('^')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+pub(crate) fn yahoo_chart_url(base: &str, symbol: &str) -> String
+{
+    let mut encoded = String::new();
+    for byte in symbol.bytes()
+    {
+        match byte
+        {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    format!("{base}/v8/finance/chart/{encoded}")
+}
+
+// Asset selection
+
+/// The assets that can be tracked, as chosen on the command line.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AssetKind
+{
+    /// Any coin CoinGecko prices, by CoinGecko id
+    Coin(String),
+    /// Any symbol Yahoo Finance charts (`^GSPC`, `AAPL`)
+    Ticker(String),
+    /// A metal, by way of its futures contract on Yahoo Finance
+    Metal(Metal),
+}
+
+impl AssetKind
+{
+    /// Short names accepted by `FromStr`
+    pub const KEYS: [&'static str; 8] = ["btc", "eth", "sp500", "gold", "silver", "platinum", "palladium", "copper"];
+
+    pub fn coin(id: &str) -> Self
+    {
+        AssetKind::Coin(id.trim().to_ascii_lowercase())
+    }
+
+    /// A coin id as given by the user. CoinGecko ids are lowercase letters,
+    /// digits and dashes, and nothing else is accepted, as the id goes into
+    /// request URLs and file names as is.
+    pub fn parse_coin(id: &str) -> Result<Self, String>
+    {
+        let id = id.trim().to_ascii_lowercase();
+        if id.is_empty()
+        {
+            return Err("empty coin id".to_string());
+        }
+        if !id.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        {
+            return Err(format!("invalid coin id '{id}' (only letters, digits and '-')"));
+        }
+        Ok(AssetKind::Coin(id))
+    }
+
+    pub fn ticker(symbol: &str) -> Self
+    {
+        AssetKind::Ticker(symbol.trim().to_ascii_uppercase())
+    }
+
+    /// The S&P 500 index
+    pub fn sp500() -> Self
+    {
+        AssetKind::ticker("^GSPC")
+    }
+
+    /// What's tracked when nothing is selected: Bitcoin, Ethereum and the S&P 500
+    pub fn defaults() -> Vec<AssetKind>
+    {
+        vec![AssetKind::coin("bitcoin"), AssetKind::coin("ethereum"), AssetKind::sp500()]
+    }
+
+    /// Human-readable name for log lines
+    pub fn name(&self) -> &str
+    {
+        match self
+        {
+            AssetKind::Coin(id) => id,
+            AssetKind::Ticker(symbol) => symbol,
+            AssetKind::Metal(metal) => metal.name(),
+        }
+    }
+
+    /// CoinGecko coin id, for assets priced there
+    pub fn coingecko_id(&self) -> Option<&str>
+    {
+        match self
+        {
+            AssetKind::Coin(id) => Some(id),
+            AssetKind::Ticker(_) | AssetKind::Metal(_) => None,
+        }
+    }
+
+    /// Yahoo Finance symbol, for assets priced there
+    pub fn yahoo_symbol(&self) -> Option<&str>
+    {
+        match self
+        {
+            AssetKind::Coin(_) => None,
+            AssetKind::Ticker(symbol) => Some(symbol),
+            AssetKind::Metal(metal) => Some(metal.futures_symbol()),
+        }
+    }
+
+    /// The asset in `among` that `name` refers to: a `FromStr` alias, a coin
+    /// id, a ticker symbol or a file stem (`btc`, `solana`, `^ixic`, `ixic`).
+    pub fn lookup(name: &str, among: &[AssetKind]) -> Option<AssetKind>
+    {
+        if let Ok(kind) = name.parse::<AssetKind>()
+        {
+            return among.contains(&kind).then_some(kind);
+        }
+        among
+            .iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name.trim()) || kind.file_stem() == name.trim().to_ascii_lowercase())
+            .cloned()
+    }
+
+    /// Name of the history file, without extension (`btc`, `gspc`, `gold`)
+    pub fn file_stem(&self) -> String
+    {
+        match self
+        {
+            AssetKind::Coin(id) => coin_symbol(id).to_ascii_lowercase(),
+            AssetKind::Ticker(symbol) => ticker_file_stem(symbol),
+            AssetKind::Metal(metal) => metal.name().to_string(),
+        }
+    }
+
+    pub fn build(&self) -> Box<dyn Pricing>
+    {
+        match self
+        {
+            AssetKind::Coin(id) => Box::new(CoinGeckoAsset::from_id(id)),
+            AssetKind::Ticker(symbol) => Box::new(YahooAsset::new(symbol.as_str())),
+            AssetKind::Metal(metal) => Box::new(MetalsAsset::new(*metal)),
+        }
+    }
+}
+
+impl FromStr for AssetKind
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s.trim().to_ascii_lowercase().as_str()
+        {
+            "btc" | "bitcoin" | "xbt" => Ok(AssetKind::coin("bitcoin")),
+            "eth" | "ethereum" | "ether" => Ok(AssetKind::coin("ethereum")),
+            "sp500" | "s&p500" | "spx" | "gspc" | "^gspc" => Ok(AssetKind::sp500()),
+            other => other.parse().map(AssetKind::Metal).map_err(|_| {
+                format!("unknown asset '{other}' (valid: {}; use --coins or --tickers for others)", AssetKind::KEYS.join(", "))
+            }),
+        }
+    }
+}
+
+/// Metals tracked through their futures contracts. Yahoo quotes these in
+/// USD: gold, silver, platinum and palladium per troy ounce (31.1 g), copper
+/// per pound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Metal
+{
+    Gold,
+    Silver,
+    Platinum,
+    Palladium,
+    Copper,
+}
+
+impl Metal
+{
+    pub const ALL: [Metal; 5] = [Metal::Gold, Metal::Silver, Metal::Platinum, Metal::Palladium, Metal::Copper];
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Metal::Gold => "gold",
+            Metal::Silver => "silver",
+            Metal::Platinum => "platinum",
+            Metal::Palladium => "palladium",
+            Metal::Copper => "copper",
+        }
+    }
+
+    /// ISO 4217 code for the precious metals (`XAU`); copper has none
+    pub fn code(&self) -> Option<&'static str>
+    {
+        match self
+        {
+            Metal::Gold => Some("XAU"),
+            Metal::Silver => Some("XAG"),
+            Metal::Platinum => Some("XPT"),
+            Metal::Palladium => Some("XPD"),
+            Metal::Copper => None,
+        }
+    }
+
+    /// Yahoo Finance symbol of the front-month futures contract
+    pub fn futures_symbol(&self) -> &'static str
+    {
+        match self
+        {
+            Metal::Gold => "GC=F",
+            Metal::Silver => "SI=F",
+            Metal::Platinum => "PL=F",
+            Metal::Palladium => "PA=F",
+            Metal::Copper => "HG=F",
+        }
+    }
+
+    /// What one price buys
+    pub fn unit(&self) -> &'static str
+    {
+        match self
+        {
+            Metal::Copper => "pound",
+            _ => "troy ounce",
+        }
+    }
+}
+
+/// By name (`gold`), ISO code (`xau`) or futures symbol (`gc=f`)
+impl FromStr for Metal
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        let name = s.trim();
+        Metal::ALL
+            .into_iter()
+            .find(|m| {
+                [Some(m.name()), m.code(), Some(m.futures_symbol())].into_iter().flatten().any(|n| n.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| format!("unknown metal '{name}' (valid: gold, silver, platinum, palladium, copper)"))
+    }
+}
+
+// JSON Models
+
+// CoinGecko: `{"bitcoin": {"usd": ..., "eur": ...}, "ethereum": {...}}`, one
+// entry per known id, one price per requested currency
+type CoinGeckoResponse = HashMap<String, HashMap<String, Decimal>>;
+
+/// CoinGecko `coins/{id}/market_chart` response: `[unix ms, price]` pairs.
+/// Prices stay `Number`s so their text survives, exponent form included.
+#[derive(Deserialize)]
+struct CoinGeckoMarketChart
+{
+    prices: Vec<(i64, serde_json::Number)>,
+}
+
+// Yahoo Finance. Every level may be empty or absent (`"result": null` on
+// errors, `"quote": [{}]` for symbols with no trades yet), so all default.
+#[derive(Deserialize)]
+pub struct YahooChartResponse
+{
+    chart: YahooChart,
+}
+
+/// Binance `api/v3/ticker/price` response. Prices come as strings.
+#[derive(Deserialize)]
+pub struct BinanceTicker
+{
+    pub symbol: String,
+    pub price: String,
+}
+
+/// Coinbase `v2/prices/<pair>/spot` response
+#[derive(Deserialize)]
+pub struct CoinbaseSpot
+{
+    pub data: CoinbaseAmount,
+}
+
+#[derive(Deserialize)]
+pub struct CoinbaseAmount
+{
+    pub amount: String,
+    pub base: String,
+    pub currency: String,
+}
+
+#[derive(Deserialize)]
+struct YahooChart
+{
+    #[serde(default)]
+    result: Option<Vec<YahooResult>>,
+}
+
+#[derive(Deserialize)]
+struct YahooResult
+{
+    #[serde(default)]
+    meta: YahooMeta,
+    /// Unix seconds, one per entry in each `close` array
+    #[serde(default)]
+    timestamp: Vec<i64>,
+    #[serde(default)]
+    indicators: YahooIndicators,
+}
+
+#[derive(Deserialize, Default)]
+struct YahooMeta
+{
+    currency: Option<String>,
+    #[serde(rename = "regularMarketTime")]
+    regular_market_time: Option<i64>,
+}
+
+#[derive(Deserialize, Default)]
+struct YahooIndicators
+{
+    #[serde(default)]
+    quote: Vec<YahooQuote>,
+}
+
+#[derive(Deserialize)]
+struct YahooQuote
+{
+    #[serde(default)]
+    close: Vec<Option<Decimal>>,
+}
+
+// Parsing
+
+/// Price of the coin with id `coin` in `currency` from a CoinGecko
+/// `simple/price` response.
+pub fn parse_coingecko(body: &str, coin: &str, currency: &str) -> Result<Decimal, FetchError>
+{
+    let parsed: CoinGeckoResponse = serde_json::from_str(body)?;
+    coin_price(&parsed, coin, currency)
+}
+
+/// CoinGecko leaves out unknown coins, and unknown currencies within a coin,
+/// rather than failing the request; both end up here as `MissingField`.
+pub(crate) fn coin_price(parsed: &CoinGeckoResponse, coin: &str, currency: &str) -> Result<Decimal, FetchError>
+{
+    parsed
+        .get(coin)
+        .and_then(|prices| prices.get(currency))
+        .copied()
+        .ok_or_else(|| FetchError::MissingField(format!("{coin}.{currency}")))
+}
+
+/// Binance trading pair for a coin in `currency`: `BTCUSDT`, `ETHEUR`.
+/// Binance has no plain USD pairs, so USD is taken from USDT.
+pub fn binance_symbol(coin: &str, currency: &str) -> String
+{
+    let quote = if currency.eq_ignore_ascii_case("usd") { "USDT" } else { currency };
+    format!("{}{}", coin_symbol(coin), quote).to_ascii_uppercase()
+}
+
+/// Coinbase currency pair for a coin in `currency`: `BTC-USD`.
+pub fn coinbase_pair(coin: &str, currency: &str) -> String
+{
+    format!("{}-{}", coin_symbol(coin), currency).to_ascii_uppercase()
+}
+
+fn parse_amount(text: &str, field: &str) -> Result<Decimal, FetchError>
+{
+    parse_decimal(text).ok_or_else(|| FetchError::MissingField(field.to_string()))
+}
+
+/// Parse a price exactly as written, plain or in exponent form (`1.5e-8`).
+pub fn parse_decimal(text: &str) -> Option<Decimal>
+{
+    let text = text.trim();
+    Decimal::from_str_exact(text).or_else(|_| Decimal::from_scientific(text)).ok()
+}
+
+/// Price from a Binance `ticker/price` response body.
+pub fn parse_binance_price(body: &str) -> Result<Decimal, FetchError>
+{
+    let parsed: BinanceTicker = serde_json::from_str(body)?;
+    parse_amount(&parsed.price, "price")
+}
+
+/// Price from a Coinbase spot price response body.
+pub fn parse_coinbase_spot(body: &str) -> Result<Decimal, FetchError>
+{
+    let parsed: CoinbaseSpot = serde_json::from_str(body)?;
+    parse_amount(&parsed.data.amount, "data.amount")
+}
+
+/// Last non-null close in a chart response, or `None` if there's no
+/// result, no quote, or only null closes.
+pub fn latest_close(chart: &YahooChartResponse) -> Option<Decimal>
+{
+    chart.chart.result.as_deref()?
+        .first()?
+        .indicators.quote.first()?
+        .close.iter().rev().flatten().next() // Last non-null close
+        .copied()
+}
+
+/// When the latest close was current: its entry in the timestamp array, or
+/// the chart's `regularMarketTime` when the timestamps don't line up.
+pub fn market_time(chart: &YahooChartResponse) -> Option<DateTime<Utc>>
+{
+    let result = chart.chart.result.as_deref()?.first()?;
+    let closes = &result.indicators.quote.first()?.close;
+    let last = closes.iter().rposition(Option::is_some)?;
+    let secs = result.timestamp.get(last).copied().or(result.meta.regular_market_time)?;
+    Utc.timestamp_opt(secs, 0).single()
+}
+
+/// Currency the chart is quoted in, lowercased (`usd`), if Yahoo says.
+pub fn chart_currency(chart: &YahooChartResponse) -> Option<String>
+{
+    let meta = &chart.chart.result.as_deref()?.first()?.meta;
+    meta.currency.as_deref().map(str::to_ascii_lowercase)
+}
+
+/// Latest close, its currency (USD when Yahoo doesn't say) and when it was
+/// current.
+pub fn parse_yahoo_quote(body: &str) -> Result<Quote, FetchError>
+{
+    let parsed: YahooChartResponse = serde_json::from_str(body)?;
+    let price = latest_close(&parsed)
+        .ok_or_else(|| FetchError::MissingField("chart.result[0].indicators.quote[0].close".to_string()))?;
+    let currency = chart_currency(&parsed).unwrap_or_else(|| DEFAULT_CURRENCY.to_string());
+    Ok(Quote::new(currency, price).at_market_time(market_time(&parsed)))
+}
+
+/// Prices with the time each was current, oldest first
+pub type Series = Vec<(DateTime<Utc>, Decimal)>;
+
+/// Every non-null close in a chart with its timestamp, and the chart's
+/// currency if Yahoo says.
+pub fn parse_yahoo_series(body: &str) -> Result<(Option<String>, Series), FetchError>
+{
+    let parsed: YahooChartResponse = serde_json::from_str(body)?;
+    let result = parsed
+        .chart
+        .result
+        .as_deref()
+        .and_then(<[YahooResult]>::first)
+        .ok_or_else(|| FetchError::MissingField("chart.result[0]".to_string()))?;
+    let closes = result.indicators.quote.first().map(|q| q.close.as_slice()).unwrap_or_default();
+    let series = result
+        .timestamp
+        .iter()
+        .zip(closes)
+        .filter_map(|(&secs, close)| Some((Utc.timestamp_opt(secs, 0).single()?, (*close)?)))
+        .collect();
+    Ok((chart_currency(&parsed), series))
+}
+
+/// Timestamped prices from a CoinGecko `market_chart` or
+/// `market_chart/range` response.
+pub fn parse_market_chart(body: &str) -> Result<Series, FetchError>
+{
+    let parsed: CoinGeckoMarketChart = serde_json::from_str(body)?;
+    parsed
+        .prices
+        .iter()
+        .enumerate()
+        .map(|(i, (ms, price))| {
+            let at = Utc.timestamp_millis_opt(*ms).single();
+            let price = parse_decimal(&price.to_string());
+            at.zip(price).ok_or_else(|| FetchError::MissingField(format!("prices[{i}]")))
+        })
+        .collect()
+}
+
+/// `quote` if it's no more than `max_age` old at `now`, a `Stale` error if
+/// it is. Quotes without a market time can't be judged and pass.
+pub fn check_fresh(quote: Quote, max_age: Duration, now: DateTime<Utc>) -> Result<Quote, FetchError>
+{
+    let Some(market_at) = quote.market_at else { return Ok(quote) };
+    let age = (now - market_at).to_std().unwrap_or_default();
+    if age > max_age
+    {
+        return Err(FetchError::Stale { market_at, age });
+    }
+    Ok(quote)
+}
+
+/// Last non-null close from a Yahoo Finance chart response body.
+pub fn parse_yahoo_close(body: &str) -> Result<Decimal, FetchError>
+{
+    let parsed: YahooChartResponse = serde_json::from_str(body)?;
+    latest_close(&parsed)
+        .ok_or_else(|| FetchError::MissingField("chart.result[0].indicators.quote[0].close".to_string()))
+}
+
+/// Duration shorthand: a number and a unit, `s`, `m`, `h` or `d`
+/// (`90s`, `30m`, `12h`, `7d`).
+pub fn parse_duration(text: &str) -> Result<Duration, String>
+{
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().map_err(|_| format!("invalid duration '{text}' (e.g. 30m, 12h, 7d)"))?;
+    let secs = match unit
+    {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("invalid duration '{text}': unit must be s, m, h or d")),
+    };
+    number
+        .checked_mul(secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration '{text}' is too long"))
+}
+
+// HTTP
+
+/// The HTTP layer sources go through; swap it out to script responses.
+pub trait HttpGet: Send
+{
+    /// GET `url` and return the body of a 2xx response.
+    fn get(&mut self, url: &str) -> Result<String, FetchError>;
+
+    /// `get` with extra request headers, such as an API key. Scripted
+    /// stand-ins can leave this as is and ignore them.
+    fn get_with_headers(&mut self, url: &str, headers: &[(&str, &str)]) -> Result<String, FetchError>
+    {
+        let _ = headers;
+        self.get(url)
+    }
+}
+
+/// Time allowed for a whole request when none is configured
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sent with every request unless another is configured
+pub const USER_AGENT: &str = concat!("data_fetch/", env!("CARGO_PKG_VERSION"));
+
+/// `HttpGet` backed by ureq, with a per-request timeout and user agent.
+pub struct UreqHttp
+{
+    agent: ureq::Agent,
+}
+
+impl UreqHttp
+{
+    pub fn new(timeout: Duration) -> Self
+    {
+        Self::with_user_agent(timeout, USER_AGENT)
+    }
+
+    pub fn with_user_agent(timeout: Duration, user_agent: &str) -> Self
+    {
+        Self { agent: ureq::AgentBuilder::new().timeout(timeout).user_agent(user_agent).build() }
+    }
+}
+
+impl Default for UreqHttp
+{
+    fn default() -> Self
+    {
+        Self::new(DEFAULT_TIMEOUT)
+    }
+}
+
+impl HttpGet for UreqHttp
+{
+    fn get(&mut self, url: &str) -> Result<String, FetchError>
+    {
+        call(self.agent.get(url), url)
+    }
+
+    fn get_with_headers(&mut self, url: &str, headers: &[(&str, &str)]) -> Result<String, FetchError>
+    {
+        let request = headers.iter().fold(self.agent.get(url), |request, (name, value)| request.set(name, value));
+        call(request, url)
+    }
+}
+
+/// A request `ScriptedHttp` was asked to make
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptedRequest
+{
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+}
+
+/// `HttpGet` that answers from a script instead of the network. The first
+/// entry whose URL prefix matches wins; anything unscripted is a 404.
+/// Clones share one request log, so several sources can be checked at once.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedHttp
+{
+    script: Vec<(String, Result<String, u16>)>,
+    requests: Arc<Mutex<Vec<ScriptedRequest>>>,
+}
+
+impl ScriptedHttp
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Answer requests for URLs starting with `prefix` with `body`
+    pub fn respond(mut self, prefix: &str, body: &str) -> Self
+    {
+        self.script.push((prefix.to_string(), Ok(body.to_string())));
+        self
+    }
+
+    /// Answer requests for URLs starting with `prefix` with status `code`
+    pub fn fail(mut self, prefix: &str, code: u16) -> Self
+    {
+        self.script.push((prefix.to_string(), Err(code)));
+        self
+    }
+
+    /// Every request so far, oldest first
+    pub fn requests(&self) -> Vec<ScriptedRequest>
+    {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Just the URLs of `requests`
+    pub fn urls(&self) -> Vec<String>
+    {
+        self.requests().into_iter().map(|r| r.url).collect()
+    }
+}
+
+impl HttpGet for ScriptedHttp
+{
+    fn get(&mut self, url: &str) -> Result<String, FetchError>
+    {
+        self.get_with_headers(url, &[])
+    }
+
+    fn get_with_headers(&mut self, url: &str, headers: &[(&str, &str)]) -> Result<String, FetchError>
+    {
+        let headers = headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        self.requests.lock().unwrap().push(ScriptedRequest { url: url.to_string(), headers });
+        let answer = self.script.iter().find(|(prefix, _)| url.starts_with(prefix.as_str())).map(|(_, a)| a.clone());
+        match answer.unwrap_or(Err(404))
+        {
+            Ok(body) => Ok(body),
+            Err(code) => Err(FetchError::Status { url: url.to_string(), code, retry_after: None }),
+        }
+    }
+}
+
+/// `Retry-After` in its delay-seconds form; HTTP dates are ignored.
+fn parse_retry_after(value: &str) -> Option<Duration>
+{
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+fn call(request: ureq::Request, url: &str) -> Result<String, FetchError>
+{
+    match request.call()
+    {
+        Ok(resp) => resp.into_string().map_err(|e| FetchError::Network(e.to_string())),
+        Err(ureq::Error::Status(code, resp)) => Err(FetchError::Status {
+            url: url.to_string(),
+            code,
+            retry_after: resp.header("Retry-After").and_then(parse_retry_after),
+        }),
+        Err(ureq::Error::Transport(t)) => Err(FetchError::Network(t.to_string())),
+    }
+}
+
+/// A record of `quote` for `asset`, fetched just now from `source`
+fn record_quote(asset: AssetKind, quote: Quote, source: &str) -> PriceRecord
+{
+    let row = PriceRow {
+        fetched_at: Utc::now(),
+        currency: quote.currency,
+        price: quote.price,
+        source: quote.source.unwrap_or_else(|| source.to_string()),
+        market_at: quote.market_at,
+    };
+    PriceRecord::new(asset, row)
+}
+
+// Implementations
+
+impl Pricing for CoinGeckoAsset
+{
+    fn fetch(&self, http: &mut dyn HttpGet) -> Result<PriceRecord, FetchError>
+    {
+        let url = format!("{}/simple/price?ids={}&vs_currencies={}", source::COINGECKO_BASE_URL, self.id, self.currency);
+        let price = parse_coingecko(&http.get(&url)?, &self.id, &self.currency)?;
+        Ok(record_quote(AssetKind::coin(&self.id), Quote::new(self.currency.as_str(), price), "CoinGecko"))
+    }
+}
+
+impl Pricing for YahooAsset
+{
+    fn fetch(&self, http: &mut dyn HttpGet) -> Result<PriceRecord, FetchError>
+    {
+        let quote = parse_yahoo_quote(&http.get(&self.url())?)?;
+        Ok(record_quote(AssetKind::ticker(&self.symbol), quote, "Yahoo Finance"))
+    }
+}
+
+impl Pricing for MetalsAsset
+{
+    fn fetch(&self, http: &mut dyn HttpGet) -> Result<PriceRecord, FetchError>
+    {
+        let quote = parse_yahoo_quote(&http.get(&self.url())?)?;
+        Ok(record_quote(AssetKind::Metal(self.metal), quote, "Yahoo Finance"))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const COINGECKO: &str = include_str!("../tests/fixtures/coingecko_simple_price.json");
+    const COINGECKO_MULTI_FIAT: &str = include_str!("../tests/fixtures/coingecko_multi_currency.json");
+    const BINANCE: &str = include_str!("../tests/fixtures/binance_ticker_price.json");
+    const COINBASE: &str = include_str!("../tests/fixtures/coinbase_spot.json");
+    const YAHOO: &str = include_str!("../tests/fixtures/yahoo_chart.json");
+    const YAHOO_STALE: &str = include_str!("../tests/fixtures/yahoo_chart_stale.json");
+    const YAHOO_EMPTY: &str = include_str!("../tests/fixtures/yahoo_empty_result.json");
+    const YAHOO_NO_QUOTE: &str = include_str!("../tests/fixtures/yahoo_missing_quote.json");
+    const YAHOO_NOT_FOUND: &str = include_str!("../tests/fixtures/yahoo_not_found.json");
+    const YAHOO_HOURLY: &str = include_str!("../tests/fixtures/yahoo_chart_hourly.json");
+    const MARKET_CHART: &str = include_str!("../tests/fixtures/coingecko_market_chart.json");
+    const YAHOO_GOLD: &str = include_str!("../tests/fixtures/yahoo_chart_gold.json");
+
+    fn chart(body: &str) -> YahooChartResponse
+    {
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn asset_names_and_aliases()
+    {
+        assert_eq!("btc".parse(), Ok(AssetKind::coin("bitcoin")));
+        assert_eq!("Bitcoin".parse(), Ok(AssetKind::coin("bitcoin")));
+        assert_eq!("ether".parse(), Ok(AssetKind::coin("ethereum")));
+        assert_eq!(" SPX ".parse(), Ok(AssetKind::ticker("^gspc")));
+
+        assert_eq!("gold".parse(), Ok(AssetKind::Metal(Metal::Gold)));
+        assert_eq!("XAG".parse(), Ok(AssetKind::Metal(Metal::Silver)));
+        assert_eq!("hg=f".parse(), Ok(AssetKind::Metal(Metal::Copper)));
+
+        let err = "doge".parse::<AssetKind>().unwrap_err();
+        assert_eq!(
+            err,
+            "unknown asset 'doge' (valid: btc, eth, sp500, gold, silver, platinum, palladium, copper; \
+             use --coins or --tickers for others)"
+        );
+    }
+
+    #[test]
+    fn metals_are_futures_named_after_the_metal()
+    {
+        let gold = AssetKind::Metal(Metal::Gold);
+        assert_eq!((gold.name(), gold.file_stem().as_str(), gold.yahoo_symbol()), ("gold", "gold", Some("GC=F")));
+        assert_eq!(gold.coingecko_id(), None);
+        assert_eq!(MetalsAsset::new(Metal::Gold).url(), "https://query2.finance.yahoo.com/v8/finance/chart/GC%3DF");
+        assert_eq!((Metal::Silver.unit(), Metal::Copper.unit()), ("troy ounce", "pound"));
+        assert_eq!(AssetKind::lookup("xau", std::slice::from_ref(&gold)), Some(gold));
+    }
+
+    #[test]
+    fn gold_futures_quote()
+    {
+        let gold = MetalsAsset::new(Metal::Gold);
+        let mut http = ScriptedHttp::new().respond("https://query2.finance.yahoo.com/v8/finance/chart/GC%3DF", YAHOO_GOLD);
+        let record = gold.fetch(&mut http).unwrap();
+        assert_eq!(record.asset, AssetKind::Metal(Metal::Gold));
+        assert_eq!((record.row.price, record.row.currency.as_str()), (dec!(3434.7), "usd"));
+        assert_eq!(record.row.source, "Yahoo Finance");
+        assert_eq!(record.row.market_at, Utc.timestamp_opt(1754416740, 0).single());
+    }
+
+    #[test]
+    fn coin_ids_are_checked()
+    {
+        assert_eq!(AssetKind::parse_coin(" Solana "), Ok(AssetKind::coin("solana")));
+        assert_eq!(AssetKind::parse_coin("usd-coin"), Ok(AssetKind::coin("usd-coin")));
+        assert_eq!(AssetKind::parse_coin(" "), Err("empty coin id".to_string()));
+        for id in ["../../etc/passwd", "bitcoin&vs_currencies=eur", "bit coin", "bitcoin/"]
+        {
+            assert!(AssetKind::parse_coin(id).unwrap_err().starts_with("invalid coin id"), "{id}");
+        }
+    }
+
+    #[test]
+    fn coin_symbols_name_the_output()
+    {
+        let btc = Bitcoin::new();
+        assert_eq!((btc.id.as_str(), btc.symbol.as_str()), ("bitcoin", "btc"));
+        assert_eq!(CoinGeckoAsset::from_id("dogecoin").symbol, "doge");
+        assert_eq!(CoinGeckoAsset::from_id("pepe").symbol, "pepe");
+    }
+
+    #[test]
+    fn coingecko_prices()
+    {
+        assert_eq!(parse_coingecko(COINGECKO, "bitcoin", "usd").unwrap(), dec!(119458.0));
+        assert_eq!(parse_coingecko(COINGECKO, "ethereum", "usd").unwrap(), dec!(3725.51));
+    }
+
+    #[test]
+    fn prices_parse_digit_for_digit()
+    {
+        // Neither of these survives a trip through f64
+        let body = r#"{"bitcoin":{"usd":62999.99999999999999},"pepe":{"usd":1.5e-8}}"#;
+        assert_eq!(parse_coingecko(body, "bitcoin", "usd").unwrap().to_string(), "62999.99999999999999");
+        assert_eq!(parse_coingecko(body, "pepe", "usd").unwrap(), dec!(0.000000015));
+        // Binance pads to eight places; the padding is kept as sent
+        assert_eq!(parse_binance_price(BINANCE).unwrap().to_string(), "119461.37000000");
+        assert_eq!(parse_decimal("0.1"), Some(dec!(0.1)));
+        assert_eq!(parse_decimal("1e-3"), Some(dec!(0.001)));
+        assert_eq!(parse_decimal("n/a"), None);
+    }
+
+    #[test]
+    fn coingecko_missing_coin()
+    {
+        let err = parse_coingecko(r#"{"bitcoin":{"usd":1.0}}"#, "ethereum", "usd").unwrap_err();
+        assert!(matches!(err, FetchError::MissingField(ref f) if f == "ethereum.usd"), "{err}");
+    }
+
+    #[test]
+    fn coingecko_currencies()
+    {
+        assert_eq!(parse_coingecko(COINGECKO_MULTI_FIAT, "bitcoin", "eur").unwrap(), dec!(102331.0));
+        assert_eq!(parse_coingecko(COINGECKO_MULTI_FIAT, "ethereum", "gbp").unwrap(), dec!(2771.2));
+        let err = parse_coingecko(COINGECKO_MULTI_FIAT, "bitcoin", "xyz").unwrap_err();
+        assert!(matches!(err, FetchError::MissingField(ref f) if f == "bitcoin.xyz"), "{err}");
+    }
+
+    #[test]
+    fn binance_prices()
+    {
+        assert_eq!(binance_symbol("bitcoin", "usd"), "BTCUSDT");
+        assert_eq!(binance_symbol("ethereum", "eur"), "ETHEUR");
+        assert_eq!(parse_binance_price(BINANCE).unwrap(), dec!(119461.37));
+        let err = parse_binance_price(r#"{"symbol":"BTCUSDT","price":"n/a"}"#).unwrap_err();
+        assert!(matches!(err, FetchError::MissingField(ref f) if f == "price"), "{err}");
+        let err = parse_binance_price(r#"{"code":-1121,"msg":"Invalid symbol."}"#).unwrap_err();
+        assert!(matches!(err, FetchError::Parse(_)), "{err}");
+    }
+
+    #[test]
+    fn coinbase_prices()
+    {
+        assert_eq!(coinbase_pair("solana", "eur"), "SOL-EUR");
+        assert_eq!(parse_coinbase_spot(COINBASE).unwrap(), dec!(119455.12));
+        let err = parse_coinbase_spot(r#"{"errors":[{"id":"not_found","message":"Invalid base currency"}]}"#).unwrap_err();
+        assert!(matches!(err, FetchError::Parse(_)), "{err}");
+    }
+
+    #[test]
+    fn yahoo_quote_carries_the_chart_currency()
+    {
+        let market_at = Utc.timestamp_opt(1754400720, 0).single();
+        assert_eq!(parse_yahoo_quote(YAHOO).unwrap(), Quote::new("usd", dec!(6389.77)).at_market_time(market_at));
+        assert_eq!(chart_currency(&chart(YAHOO_NO_QUOTE)).as_deref(), Some("usd"));
+        assert_eq!(chart_currency(&chart(YAHOO_NOT_FOUND)), None);
+    }
+
+    #[test]
+    fn market_time_is_the_latest_close()
+    {
+        // The trailing null close is skipped, and its timestamp with it
+        assert_eq!(market_time(&chart(YAHOO)), Utc.timestamp_opt(1754400720, 0).single());
+        // No timestamps at all: fall back to regularMarketTime
+        let body = r#"{"chart":{"result":[{"meta":{"regularMarketTime":1754337600},
+                      "indicators":{"quote":[{"close":[6329.94]}]}}]}}"#;
+        assert_eq!(market_time(&chart(body)), Utc.timestamp_opt(1754337600, 0).single());
+        assert_eq!(market_time(&chart(YAHOO_NO_QUOTE)), None);
+    }
+
+    #[test]
+    fn stale_charts_are_flagged()
+    {
+        // Two minutes after the fresh chart's last close
+        let now = Utc.timestamp_opt(1754400720 + 120, 0).unwrap();
+        let max = Duration::from_secs(15 * 60);
+
+        let fresh = check_fresh(parse_yahoo_quote(YAHOO).unwrap(), max, now).unwrap();
+        assert_eq!(fresh.price, dec!(6389.77));
+
+        // The previous evening's close, over 17 hours earlier
+        let err = check_fresh(parse_yahoo_quote(YAHOO_STALE).unwrap(), max, now).unwrap_err();
+        let FetchError::Stale { market_at, age } = err else { panic!("{err}") };
+        assert_eq!(market_at, Utc.timestamp_opt(1754337600, 0).unwrap());
+        assert_eq!(age, Duration::from_secs(1754400840 - 1754337600));
+        assert_eq!(err.to_string(), "stale data: price is from 2025-08-04T20:00:00Z (1054 min old)");
+        assert!(!err.is_retryable());
+
+        // Nothing to judge by, so it passes
+        assert!(check_fresh(Quote::new("usd", dec!(1)), max, now).is_ok());
+    }
+
+    #[test]
+    fn coingecko_garbage_is_a_parse_error()
+    {
+        let err = parse_coingecko("<html>rate limited</html>", "bitcoin", "usd").unwrap_err();
+        assert!(matches!(err, FetchError::Parse(_)), "{err}");
+    }
+
+    #[test]
+    fn duration_shorthand()
+    {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 30m"), Ok(Duration::from_secs(1800)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert!(parse_duration("7").unwrap_err().contains("unit"));
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("1.5h").is_err());
+        assert!(parse_duration("-1h").is_err());
+        assert!(parse_duration("99999999999999999d").is_err());
+    }
+
+    #[test]
+    fn lookup_among_tracked_assets()
+    {
+        let tracked = vec![AssetKind::coin("bitcoin"), AssetKind::coin("solana"), AssetKind::ticker("^IXIC")];
+        assert_eq!(AssetKind::lookup("btc", &tracked), Some(AssetKind::coin("bitcoin")));
+        assert_eq!(AssetKind::lookup("Solana", &tracked), Some(AssetKind::coin("solana")));
+        assert_eq!(AssetKind::lookup("sol", &tracked), Some(AssetKind::coin("solana")));
+        assert_eq!(AssetKind::lookup("^ixic", &tracked), Some(AssetKind::ticker("^IXIC")));
+        assert_eq!(AssetKind::lookup("eth", &tracked), None);
+    }
+
+    #[test]
+    fn retry_after_seconds_only()
+    {
+        assert_eq!(parse_retry_after(" 30 "), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+
+    #[test]
+    fn yahoo_urls_and_file_names()
+    {
+        let gspc = SP500::new();
+        assert_eq!(gspc.url(), "https://query2.finance.yahoo.com/v8/finance/chart/%5EGSPC");
+        assert_eq!(AssetKind::sp500().file_stem(), "gspc");
+        assert_eq!(YahooAsset::new("BRK-B").url(), "https://query2.finance.yahoo.com/v8/finance/chart/BRK-B");
+        assert_eq!(AssetKind::ticker("brk-b").file_stem(), "brk-b");
+        assert_eq!(YahooAsset::new("EURUSD=X").url(), "https://query2.finance.yahoo.com/v8/finance/chart/EURUSD%3DX");
+    }
+
+    #[test]
+    fn latest_close_skips_trailing_nulls()
+    {
+        assert_eq!(latest_close(&chart(YAHOO)), Some(dec!(6389.77)));
+        assert_eq!(parse_yahoo_close(YAHOO).unwrap(), dec!(6389.77));
+    }
+
+    #[test]
+    fn latest_close_of_empty_or_partial_charts()
+    {
+        assert_eq!(latest_close(&chart(YAHOO_EMPTY)), None);
+        assert_eq!(latest_close(&chart(YAHOO_NO_QUOTE)), None);
+        assert_eq!(latest_close(&chart(YAHOO_NOT_FOUND)), None);
+        assert!(matches!(parse_yahoo_close(YAHOO_NOT_FOUND), Err(FetchError::MissingField(_))));
+    }
+
+    #[test]
+    fn yahoo_without_closes()
+    {
+        let body = r#"{"chart":{"result":[{"indicators":{"quote":[{"close":[null,null]}]}}]}}"#;
+        assert!(matches!(parse_yahoo_close(body), Err(FetchError::MissingField(_))));
+
+        let body = r#"{"chart":{"result":[]}}"#;
+        assert!(matches!(parse_yahoo_close(body), Err(FetchError::MissingField(_))));
+    }
+
+    #[test]
+    fn market_chart_series()
+    {
+        let series = parse_market_chart(MARKET_CHART).unwrap();
+        let at = |h| Utc.with_ymd_and_hms(2025, 8, 5, h, 0, 0).unwrap();
+        assert_eq!(series, [(at(0), dec!(114102.51238)), (at(1), dec!(114288.0)), (at(2), dec!(114000))]);
+        assert_eq!(series[1].1.to_string(), "114288.0");
+
+        assert_eq!(parse_market_chart(r#"{"prices":[]}"#).unwrap(), []);
+        assert!(matches!(parse_market_chart(r#"{"error":"coin not found"}"#), Err(FetchError::Parse(_))));
+    }
+
+    #[test]
+    fn yahoo_hourly_series_skips_null_closes()
+    {
+        let (currency, series) = parse_yahoo_series(YAHOO_HOURLY).unwrap();
+        assert_eq!(currency.as_deref(), Some("usd"));
+        let at = |h, m| Utc.with_ymd_and_hms(2025, 8, 5, h, m, 0).unwrap();
+        assert_eq!(series, [(at(13, 30), dec!(6351.59)), (at(14, 30), dec!(6347.06)), (at(16, 30), dec!(6339.48))]);
+        assert!(matches!(parse_yahoo_series(YAHOO_NOT_FOUND), Err(FetchError::MissingField(_))));
+    }
+}
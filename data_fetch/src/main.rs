@@ -1,163 +1,935 @@
-use serde::Deserialize;
-use std::{fs::OpenOptions, io::Write, thread, time::Duration};
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use data_fetch::{
+    candles, parse_duration, parse_size, poll_sources_every, rows_since, sources_for, summarize, time_before, AlertRule, AlertTracker,
+    Alerter, AssetConfig, AssetKind, Backfiller, Board, CoinProvider, CommandAlerter, Config, HistoryWriter, MarketHours,
+    Metrics, OutputFormat, PollEvent, PriceSource, RecordMode, Recorder, RetryPolicy, RetryingSource, Rotation, Schedule,
+    serve_metrics, Shutdown, SourceOptions, StdoutAlerter, Storage, UreqHttp, WebhookAlerter, WritePolicy,
+    CANDLE_CSV_HEADER, DEFAULT_CONFIG, USER_AGENT,
+};
+#[cfg(feature = "sqlite")]
+use data_fetch::PriceStore;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{IsTerminal, Write as _},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-// Trait
+const ENV_HELP: &str = "\
+Environment:
+  COINGECKO_API_KEY   CoinGecko Pro key; switches to the Pro API unless COINGECKO_BASE_URL is set
+  BINANCE_API_KEY     Sent as X-MBX-APIKEY
+  <SOURCE>_BASE_URL   Send COINGECKO, BINANCE, COINBASE or YAHOO requests here instead,
+                      e.g. YAHOO_BASE_URL=http://localhost:8080 for a mock server";
 
-trait Pricing 
+/// Poll asset prices and append them to one CSV file per asset.
+#[derive(Parser, Debug)]
+#[command(author, version, about, after_help = ENV_HELP)]
+struct Args
 {
-    fn fetch_price(&mut self);
-    fn save_to_file(&self);
-}
+    #[command(subcommand)]
+    command: Option<Command>,
 
-// Structs
+    /// Read assets, output and alert settings from this TOML file (default:
+    /// ./data_fetch.toml if it exists); flags given here override it
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
 
-#[derive(Debug)]
-struct Bitcoin 
-{
-    price: f64,
+    /// Seconds between the start of one round of fetches and the next
+    #[arg(long, default_value_t = 10)]
+    interval: u64,
+
+    /// Assets to track, comma-separated (btc, eth, sp500, gold, silver,
+    /// platinum, palladium, copper). Defaults to btc, eth and sp500 unless
+    /// --coins or --tickers is given.
+    #[arg(long, value_delimiter = ',')]
+    assets: Vec<AssetKind>,
+
+    /// Extra coins to track by CoinGecko id, comma-separated
+    /// (e.g. solana,dogecoin)
+    #[arg(long, value_delimiter = ',', value_parser = parse_coin)]
+    coins: Vec<AssetKind>,
+
+    /// Extra Yahoo Finance symbols to track, comma-separated
+    /// (e.g. ^IXIC,AAPL,SPY)
+    #[arg(long, value_delimiter = ',', value_parser = parse_ticker)]
+    tickers: Vec<AssetKind>,
+
+    /// Currencies to quote coins in, comma-separated (e.g. usd,eur). Stock
+    /// tickers are always recorded in their own currency.
+    #[arg(long, value_delimiter = ',', default_value = "usd")]
+    currency: Vec<String>,
+
+    /// Where to get coin prices, in the order to try them; a coin falls back
+    /// to the next source when the ones before it fail
+    #[arg(long, value_delimiter = ',', default_value = "coingecko,binance,coinbase")]
+    sources: Vec<CoinProvider>,
+
+    /// When to append a row: on every fetch, or only when the price moved
+    #[arg(long, value_enum, default_value_t = WriteWhen::Always)]
+    write_policy: WriteWhen,
+
+    /// With --write-policy on-change, still write a row at least every N
+    /// minutes while the price is flat
+    #[arg(long)]
+    heartbeat_mins: Option<u64>,
+
+    /// Rotate a history file once it reaches this size (e.g. 500K, 10M)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_file_size: Option<u64>,
+
+    /// Rotate history files when the UTC day changes
+    #[arg(long, value_enum)]
+    rotate: Option<RotateEvery>,
+
+    /// Gzip rotated history files
+    #[arg(long, overrides_with = "no_compress")]
+    compress: bool,
+
+    /// Leave rotated history files uncompressed, even if the config file
+    /// says to gzip them
+    #[arg(long, overrides_with = "compress")]
+    no_compress: bool,
+
+    /// Keep only the newest N rotated files per asset
+    #[arg(long, value_name = "N")]
+    keep: Option<usize>,
+
+    /// Alert rule ASSET:PERCENT:WINDOW[:COOLDOWN], e.g. btc:5:1h for a 5%
+    /// move within an hour; repeatable. Alerts are printed to stdout.
+    #[arg(long = "alert", value_name = "RULE")]
+    alerts: Vec<String>,
+
+    /// Also POST alerts as JSON to this URL
+    #[arg(long, value_name = "URL")]
+    alert_webhook: Option<String>,
+
+    /// Also run this shell command for each alert, with ALERT_ASSET,
+    /// ALERT_CHANGE_PCT, ALERT_PRICE, ALERT_CURRENCY and ALERT_MESSAGE set
+    #[arg(long, value_name = "CMD")]
+    alert_command: Option<String>,
+
+    /// Store prices in this SQLite database instead of CSV files
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH")]
+    db: Option<PathBuf>,
+
+    /// Fetch and print prices and would-be alerts, but save nothing and
+    /// send no alerts
+    #[arg(long, conflicts_with = "print")]
+    dry_run: bool,
+
+    /// Print each price as well as saving it
+    #[arg(long)]
+    print: bool,
+
+    /// Serve Prometheus metrics (latest prices, fetch errors, last fetch
+    /// time) at http://ADDR/metrics while running, e.g. 127.0.0.1:9184
+    #[arg(long, value_name = "ADDR")]
+    metrics_addr: Option<String>,
+
+    /// Fetch once and exit
+    #[arg(long)]
+    once: bool,
+
+    /// Seconds allowed for each request before it counts as failed
+    #[arg(long, default_value_t = 10)]
+    timeout_secs: u64,
+
+    /// User-Agent header sent with every request
+    #[arg(long, value_name = "UA", default_value = USER_AGENT)]
+    user_agent: String,
+
+    /// Skip stock prices whose market data is older than this (e.g. 15m,
+    /// 2h); they're reported as failed fetches instead of recorded
+    #[arg(long, value_name = "AGE", value_parser = parse_duration)]
+    max_staleness: Option<Duration>,
+
+    /// Fetch stock tickers around the clock. By default they're fetched
+    /// once at startup, then only while US markets are open (09:30-16:00
+    /// New York time on weekdays) and once just after the close.
+    #[arg(long)]
+    ignore_market_hours: bool,
+
+    /// Retries after a 429 or 5xx before giving up on a round
+    #[arg(long, default_value_t = 3)]
+    retries: u32,
+
+    /// Seconds to wait before the first retry; doubles each time
+    #[arg(long, default_value_t = 1)]
+    retry_base_secs: u64,
+
+    /// Longest wait between retries, in seconds
+    #[arg(long, default_value_t = 60)]
+    retry_max_secs: u64,
 }
 
-#[derive(Debug)]
-struct Ethereum 
+#[derive(Subcommand, Debug)]
+enum Command
 {
-    price: f64,
+    /// Summarise recorded prices instead of fetching
+    Stats(StatsArgs),
+    /// Seed the history with past prices, skipping times already recorded
+    Backfill(BackfillArgs),
+    /// Fetch and save as usual, but show a price board that redraws in
+    /// place instead of scrolling lines
+    Watch(WatchArgs),
+    /// Print recorded prices as open/high/low/close candles for charting
+    ExportOhlc(ExportOhlcArgs),
 }
 
-#[derive(Debug)]
-struct SP500 
+/// A live table of each asset's price, last move, recent trend and errors,
+/// taking the same options as a plain run. Alerts go only to the webhook and
+/// command, if any, so they don't break up the board. When stdout isn't a
+/// terminal, prices are printed line by line instead.
+#[derive(clap::Args, Debug)]
+struct WatchArgs
 {
-    price: f64,
+    /// How many recent prices each trend sparkline covers
+    #[arg(long, default_value_t = 30)]
+    points: usize,
 }
 
-// JSON Models
+/// Min, max, mean, latest, change and daily high/low over recent history.
+#[derive(clap::Args, Debug)]
+struct StatsArgs
+{
+    /// Asset to summarise: a name or alias (btc, sp500), a coin id, a
+    /// ticker, or a history file name without `.csv`
+    #[arg(long)]
+    asset: String,
+
+    /// How far back to look (e.g. 30m, 12h, 7d)
+    #[arg(long, default_value = "7d", value_parser = parse_duration)]
+    since: Duration,
+
+    /// Only prices recorded in this currency
+    #[arg(long, default_value = "usd")]
+    currency: String,
+
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Directory holding the CSV history files
+    #[arg(long, default_value = ".")]
+    dir: PathBuf,
+
+    /// Read from this SQLite database instead of CSV files
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH")]
+    db: Option<PathBuf>,
+}
 
-// CoinGecko
-#[derive(Deserialize)]
-struct CoinGeckoResponse 
+/// Hourly prices over the past days, from CoinGecko or Yahoo Finance.
+#[derive(clap::Args, Debug)]
+struct BackfillArgs
 {
-    bitcoin: Option<CoinPrice>,
-    ethereum: Option<CoinPrice>,
+    /// Asset to backfill: a name or alias (btc, sp500, gold) or a CoinGecko
+    /// coin id
+    #[arg(long, required_unless_present = "ticker", conflicts_with = "ticker")]
+    asset: Option<String>,
+
+    /// A Yahoo Finance symbol to backfill instead (e.g. ^IXIC, AAPL)
+    #[arg(long)]
+    ticker: Option<String>,
+
+    /// How many days back to go
+    #[arg(long, default_value_t = 30)]
+    days: u32,
+
+    /// Currency to quote a coin in; tickers are always in their own
+    #[arg(long, default_value = "usd")]
+    currency: String,
+
+    /// Seconds to wait between requests, to stay under rate limits
+    #[arg(long, default_value_t = 2)]
+    pause_secs: u64,
+
+    /// Directory holding the CSV history files (default: the config file's
+    /// output directory, else the current one)
+    #[arg(long)]
+    dir: Option<PathBuf>,
+
+    /// Write to this SQLite database instead of CSV files
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH")]
+    db: Option<PathBuf>,
 }
 
-#[derive(Deserialize)]
-struct CoinPrice 
+/// Candles over fixed UTC buckets from the recorded history, printed to
+/// stdout.
+#[derive(clap::Args, Debug)]
+struct ExportOhlcArgs
 {
-    usd: f64,
+    /// Asset to export: a name or alias (btc, sp500, gold) or a CoinGecko
+    /// coin id
+    #[arg(long, required_unless_present = "ticker", conflicts_with = "ticker")]
+    asset: Option<String>,
+
+    /// A Yahoo Finance symbol to export instead (e.g. ^IXIC, AAPL)
+    #[arg(long)]
+    ticker: Option<String>,
+
+    /// Width of each candle (e.g. 15m, 1h, 1d); buckets are aligned to UTC
+    #[arg(long, default_value = "1h", value_parser = parse_duration)]
+    bucket: Duration,
+
+    /// Earliest price to include: an RFC 3339 time or a UTC date
+    /// (2026-01-05)
+    #[arg(long, value_parser = parse_time)]
+    from: Option<DateTime<Utc>>,
+
+    /// Include prices before this time (RFC 3339 or a UTC date)
+    #[arg(long, value_parser = parse_time)]
+    to: Option<DateTime<Utc>>,
+
+    /// Only prices recorded in this currency
+    #[arg(long, default_value = "usd")]
+    currency: String,
+
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    format: ExportFormat,
+
+    /// Print buckets with no prices as empty candles with a count of 0,
+    /// instead of leaving them out
+    #[arg(long)]
+    mark_empty: bool,
+
+    /// Directory holding the CSV history files (default: the config file's
+    /// output directory, else the current one)
+    #[arg(long)]
+    dir: Option<PathBuf>,
+
+    /// Read from this SQLite database instead of CSV files
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH")]
+    db: Option<PathBuf>,
 }
 
-// Yahoo Finance
-#[derive(Deserialize)]
-struct YahooChartResponse 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ExportFormat
 {
-    chart: YahooChart,
+    Csv,
+    Json,
 }
 
-#[derive(Deserialize)]
-struct YahooChart 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format
 {
-    result: Vec<YahooResult>,
+    Text,
+    Json,
 }
 
-#[derive(Deserialize)]
-struct YahooResult 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum RotateEvery
 {
-    indicators: YahooIndicators,
+    Daily,
 }
 
-#[derive(Deserialize)]
-struct YahooIndicators 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum WriteWhen
 {
-    quote: Vec<YahooQuote>,
+    Always,
+    OnChange,
 }
 
-#[derive(Deserialize)]
-struct YahooQuote 
+fn parse_coin(id: &str) -> Result<AssetKind, String>
 {
-    close: Vec<Option<f64>>,
+    AssetKind::parse_coin(id)
 }
 
-// Implementations
+fn parse_ticker(symbol: &str) -> Result<AssetKind, String>
+{
+    if symbol.trim().is_empty()
+    {
+        return Err("empty ticker symbol".to_string());
+    }
+    Ok(AssetKind::ticker(symbol))
+}
 
-impl Pricing for Bitcoin 
+/// An RFC 3339 time, or a date taken as midnight UTC
+fn parse_time(text: &str) -> Result<DateTime<Utc>, String>
 {
-    fn fetch_price(&mut self) 
+    let text = text.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(text)
     {
-        let url = "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=usd";
-        let resp = ureq::get(url).call().unwrap().into_string().unwrap();
-        let parsed: CoinGeckoResponse = serde_json::from_str(&resp).unwrap();
-        self.price = parsed.bitcoin.unwrap().usd;
+        return Ok(at.with_timezone(&Utc));
     }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+        .map_err(|_| format!("invalid time '{text}' (e.g. 2026-01-05 or 2026-01-05T14:00:00Z)"))
+}
 
-    fn save_to_file(&self) 
+/// History file stem for a `stats --asset` name. Known names and aliases
+/// map as they do when fetching; anything else is taken as a coin id if
+/// that coin has a history file, else as a ticker or a file stem.
+fn stats_stem(name: &str, dir: &Path) -> String
+{
+    if let Ok(kind) = name.parse::<AssetKind>()
+    {
+        return kind.file_stem();
+    }
+    if let Ok(coin) = AssetKind::parse_coin(name)
     {
-        let mut file = OpenOptions::new().append(true).create(true).open("bitcoin.txt").unwrap();
-        writeln!(file, "{:.2}", self.price).unwrap();
+        let stem = coin.file_stem();
+        if dir.join(format!("{stem}.csv")).exists()
+        {
+            return stem;
+        }
     }
+    AssetKind::ticker(name).file_stem()
+}
+
+/// `7d`, `12h`, `30m` or `90s`: the largest unit that divides evenly
+fn duration_text(d: Duration) -> String
+{
+    let secs = d.as_secs();
+    let (n, unit) = [(86400, "d"), (3600, "h"), (60, "m")]
+        .into_iter()
+        .find(|&(size, _)| secs > 0 && secs.is_multiple_of(size))
+        .map_or((secs, "s"), |(size, unit)| (secs / size, unit));
+    format!("{n}{unit}")
 }
 
-impl Pricing for Ethereum 
+fn run_stats(args: &StatsArgs) -> Result<(), String>
 {
-    fn fetch_price(&mut self) 
+    let stem = stats_stem(&args.asset, &args.dir);
+    let since = time_before(Utc::now(), args.since)
+        .ok_or_else(|| format!("--since {} goes back too far", duration_text(args.since)))?;
+    let currency = args.currency.trim().to_ascii_lowercase();
+
+    let rows = rows_since(&args.dir.join(format!("{stem}.csv")), since).map_err(|e| e.to_string())?;
+    #[cfg(feature = "sqlite")]
+    let rows = match &args.db
     {
-        let url = "https://api.coingecko.com/api/v3/simple/price?ids=ethereum&vs_currencies=usd";
-        let resp = ureq::get(url).call().unwrap().into_string().unwrap();
-        let parsed: CoinGeckoResponse = serde_json::from_str(&resp).unwrap();
-        self.price = parsed.ethereum.unwrap().usd;
+        Some(path) =>
+        {
+            let store = PriceStore::open(path).map_err(|e| e.to_string())?;
+            let to = Utc::now() + chrono::Duration::seconds(1);
+            store.range(&stem, since, to).map_err(|e| e.to_string())?
+        }
+        None => rows,
+    };
+    let summary = summarize(rows.iter().filter(|r| r.currency == currency).map(|r| (r.fetched_at, r.price)));
+
+    let since_text = duration_text(args.since);
+    match args.format
+    {
+        Format::Json =>
+        {
+            let report = serde_json::json!({
+                "asset": stem,
+                "currency": currency,
+                "since": since.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                "summary": summary,
+            });
+            println!("{report}");
+        }
+        Format::Text => match summary
+        {
+            Some(summary) => print!("{stem} in {currency}, last {since_text}:\n{summary}"),
+            None => println!("{stem}: no {currency} prices in the last {since_text}"),
+        },
     }
+    Ok(())
+}
+
+fn run_backfill(args: &BackfillArgs, config: &Config) -> Result<(), String>
+{
+    let asset = match (&args.asset, &args.ticker)
+    {
+        (_, Some(symbol)) => parse_ticker(symbol)?,
+        (Some(name), None) => name.parse().or_else(|_| parse_coin(name))?,
+        (None, None) => unreachable!("clap requires --asset or --ticker"),
+    };
+    let to = Utc::now();
+    let from = time_before(to, Duration::from_secs(u64::from(args.days) * 86400))
+        .ok_or_else(|| format!("--days {} goes back too far", args.days))?;
+
+    let options = SourceOptions::default().configs_from_env();
+    let http = UreqHttp::with_user_agent(options.timeout, &options.user_agent);
+    let mut backfiller = Backfiller::new(Box::new(http))
+        .currency(&args.currency)
+        .configs(options.coingecko, options.yahoo)
+        .pause(Duration::from_secs(args.pause_secs));
+    let name = asset.name();
+    println!("{name}: fetching {} days of prices...", args.days);
+    let rows = backfiller
+        .fetch(&asset, from, to, |done, total, count| println!("{name}: {done}/{total} requests, {count} prices"))
+        .map_err(|e| format!("{name}: {e}"))?;
 
-    fn save_to_file(&self) 
+    let dir = args.dir.clone().or_else(|| config.output.directory.clone()).unwrap_or_else(|| PathBuf::from("."));
+    let mut storage = Storage::Files(HistoryWriter::new(dir, WritePolicy::Always));
+    #[cfg(feature = "sqlite")]
+    let db = args.db.clone().or_else(|| {
+        (config.output.format == Some(OutputFormat::Sqlite)).then(|| config.output.database.clone()).flatten()
+    });
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = db
     {
-        let mut file = OpenOptions::new().append(true).create(true).open("ethereum.txt").unwrap();
-        writeln!(file, "{:.2}", self.price).unwrap();
+        let store = PriceStore::open(&path).map_err(|e| format!("could not open {}: {e}", path.display()))?;
+        storage = Storage::Db { store, policy: WritePolicy::Always, written: 0 };
     }
+    let added = storage.backfill(&asset, &rows)?;
+    println!("{name}: {} prices fetched, {added} new, {} already recorded.", rows.len(), rows.len() - added);
+    Ok(())
 }
 
-impl Pricing for SP500 
+fn run_export_ohlc(args: &ExportOhlcArgs, config: &Config) -> Result<(), String>
 {
-    fn fetch_price(&mut self)
+    let asset = match (&args.asset, &args.ticker)
     {
-        let url = "https://query2.finance.yahoo.com/v8/finance/chart/%5EGSPC";
-        let resp = ureq::get(url).call().unwrap().into_string().unwrap();
-        let parsed: YahooChartResponse = serde_json::from_str(&resp).unwrap();
+        (_, Some(symbol)) => parse_ticker(symbol)?,
+        (Some(name), None) => name.parse().or_else(|_| parse_coin(name))?,
+        (None, None) => unreachable!("clap requires --asset or --ticker"),
+    };
+    if let (Some(from), Some(to)) = (args.from, args.to)
+    {
+        if from >= to
+        {
+            return Err("--from must be before --to".to_string());
+        }
+    }
 
-        if let Some(quote) = parsed.chart.result.first()
-            .and_then(|r| r.indicators.quote.first())
-            .and_then(|q| q.close.iter().rev().flatten().next()) // Last non-null close
+    let dir = args.dir.clone().or_else(|| config.output.directory.clone()).unwrap_or_else(|| PathBuf::from("."));
+    let storage = Storage::Files(HistoryWriter::new(dir, WritePolicy::Always));
+    #[cfg(feature = "sqlite")]
+    let db = args.db.clone().or_else(|| {
+        (config.output.format == Some(OutputFormat::Sqlite)).then(|| config.output.database.clone()).flatten()
+    });
+    #[cfg(feature = "sqlite")]
+    let storage = match db
+    {
+        Some(path) =>
         {
-            self.price = *quote;
-        } 
-        else 
+            let store = PriceStore::open(&path).map_err(|e| format!("could not open {}: {e}", path.display()))?;
+            Storage::Db { store, policy: WritePolicy::Always, written: 0 }
+        }
+        None => storage,
+    };
+
+    let currency = args.currency.trim().to_ascii_lowercase();
+    let rows = storage.history(&asset, args.from.unwrap_or(DateTime::<Utc>::MIN_UTC))?;
+    let points = rows
+        .into_iter()
+        .filter(|r| r.currency == currency && args.to.is_none_or(|to| r.fetched_at < to))
+        .map(|r| (r.fetched_at, r.price));
+    let candles = candles(points, args.bucket, args.mark_empty);
+    match args.format
+    {
+        ExportFormat::Csv =>
         {
-            eprintln!("Failed to parse S&P 500 price.");
+            println!("{CANDLE_CSV_HEADER}");
+            for candle in &candles
+            {
+                println!("{}", candle.to_csv());
+            }
         }
+        ExportFormat::Json => println!("{}", serde_json::to_string(&candles).map_err(|e| e.to_string())?),
+    }
+    if candles.is_empty()
+    {
+        eprintln!("{}: no {currency} prices in range", asset.name());
     }
+    Ok(())
+}
 
-    fn save_to_file(&self) 
+/// The `--config` file, else `./data_fetch.toml` if there is one, else an
+/// empty config that leaves everything to the command line.
+fn load_config(path: Option<&Path>) -> Result<Config, String>
+{
+    match path
     {
-        let mut file = OpenOptions::new().append(true).create(true).open("sp500.txt").unwrap();
-        writeln!(file, "{:.2}", self.price).unwrap();
+        Some(path) => Config::load(path),
+        None if Path::new(DEFAULT_CONFIG).exists() => Config::load(Path::new(DEFAULT_CONFIG)),
+        None => Ok(Config::default()),
     }
 }
 
+/// Fill in from `config` every setting that wasn't given on the command
+/// line. `from_cli` says whether a flag was.
+fn apply_config(args: &mut Args, config: &Config, from_cli: impl Fn(&str) -> bool)
+{
+    if let Some(interval) = config.interval.filter(|_| !from_cli("interval"))
+    {
+        args.interval = interval.as_secs();
+    }
+    if !config.currencies.is_empty() && !from_cli("currency")
+    {
+        args.currency = config.currencies.clone();
+    }
+    if let Some(sources) = config.sources.clone().filter(|_| !from_cli("sources"))
+    {
+        args.sources = sources;
+    }
+    if let Some(timeout) = config.timeout.filter(|_| !from_cli("timeout_secs"))
+    {
+        args.timeout_secs = timeout.as_secs();
+    }
+    args.max_staleness = args.max_staleness.or(config.max_staleness);
+    args.ignore_market_hours |= config.market_hours == Some(false);
+
+    let rotation = &config.output.rotation;
+    args.max_file_size = args.max_file_size.or(rotation.max_bytes);
+    if rotation.daily
+    {
+        args.rotate.get_or_insert(RotateEvery::Daily);
+    }
+    args.compress = !args.no_compress && (args.compress || rotation.compress);
+    args.keep = args.keep.or(rotation.keep);
+    #[cfg(feature = "sqlite")]
+    if config.output.format == Some(OutputFormat::Sqlite)
+    {
+        args.db = args.db.take().or_else(|| config.output.database.clone());
+    }
 
+    args.alert_webhook = args.alert_webhook.take().or_else(|| config.alerts.webhook.clone());
+    args.alert_command = args.alert_command.take().or_else(|| config.alerts.command.clone());
+}
 
 fn main() 
 {
-    let mut assets: Vec<Box<dyn Pricing>> = vec![
-        Box::new(Bitcoin { price: 0.0 }),
-        Box::new(Ethereum { price: 0.0 }),
-        Box::new(SP500 { price: 0.0 }),
-    ];
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let mut watch = None;
+    let outcome = match args.command.take()
+    {
+        Some(Command::ExportOhlc(export)) =>
+        {
+            Some(load_config(args.config.as_deref()).and_then(|config| run_export_ohlc(&export, &config)))
+        }
+        Some(Command::Watch(w)) =>
+        {
+            watch = Some(w);
+            None
+        }
+        Some(Command::Stats(stats)) => Some(run_stats(&stats)),
+        Some(Command::Backfill(backfill)) =>
+        {
+            Some(load_config(args.config.as_deref()).and_then(|config| run_backfill(&backfill, &config)))
+        }
+        None => None,
+    };
+    if let Some(outcome) = outcome
+    {
+        if let Err(e) = outcome
+        {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let config = load_config(args.config.as_deref()).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        std::process::exit(2);
+    });
+    #[cfg(not(feature = "sqlite"))]
+    if config.output.format == Some(OutputFormat::Sqlite)
+    {
+        eprintln!("error: output.format = \"sqlite\" needs a build with --features sqlite");
+        std::process::exit(2);
+    }
+    let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    apply_config(&mut args, &config, from_cli);
+
+    // Assets named on the command line replace the config file's
+    let named = [args.assets, args.coins, args.tickers].concat();
+    let mut tracked: Vec<AssetConfig> = if !named.is_empty()
+    {
+        named.into_iter().map(|asset| AssetConfig { asset, currency: None, interval: None }).collect()
+    }
+    else if !config.assets.is_empty()
+    {
+        config.assets.clone()
+    }
+    else
+    {
+        AssetKind::defaults().into_iter().map(|asset| AssetConfig { asset, currency: None, interval: None }).collect()
+    };
+
+    // Each asset once, even if named twice ("btc,bitcoin"); the first entry wins
+    let mut seen = BTreeSet::new();
+    tracked.retain(|t| seen.insert(t.asset.clone()));
+    let selected: Vec<AssetKind> = seen.into_iter().collect();
+    let policy = RetryPolicy {
+        base: Duration::from_secs(args.retry_base_secs),
+        max: Duration::from_secs(args.retry_max_secs),
+        retries: args.retries,
+    };
+    let write_policy = match (config.output.write_policy, args.write_policy, args.heartbeat_mins)
+    {
+        (Some(policy), _, _) if !from_cli("write_policy") && !from_cli("heartbeat_mins") => policy,
+        (_, WriteWhen::Always, _) => WritePolicy::Always,
+        (_, WriteWhen::OnChange, None) => WritePolicy::OnChange,
+        (_, WriteWhen::OnChange, Some(mins)) => WritePolicy::OnChangeOrInterval(Duration::from_secs(mins * 60)),
+    };
+    let mut rules = Vec::new();
+    for spec in &args.alerts
+    {
+        match AlertRule::parse(spec, |name| AssetKind::lookup(name, &selected))
+        {
+            Ok(rule) => rules.push(rule),
+            Err(e) =>
+            {
+                eprintln!("error: {e}");
+                std::process::exit(2);
+            }
+        }
+    }
+    if args.alerts.is_empty()
+    {
+        rules = config.alert_rules(&selected).unwrap_or_else(|e| {
+            eprintln!("error: {e}");
+            std::process::exit(2);
+        });
+    }
+    // The board takes over the terminal; without one, watch prints lines
+    let tty = std::io::stdout().is_terminal();
+    let mut board = watch.as_ref().filter(|_| tty).map(|w| Board::new(w.points));
+    let mut alerters: Vec<Box<dyn Alerter>> = Vec::new();
+    if board.is_none()
+    {
+        alerters.push(Box::new(StdoutAlerter));
+    }
+    if let Some(url) = args.alert_webhook
+    {
+        alerters.push(Box::new(WebhookAlerter { url }));
+    }
+    if let Some(command) = args.alert_command
+    {
+        alerters.push(Box::new(CommandAlerter { command }));
+    }
+    let default_interval = Duration::from_secs(args.interval);
+    let once = args.once;
+
+    let shutdown = Shutdown::new();
+    // Graceful shutdown on Ctrl+C: finish the fetch in flight and its writes;
+    // a second Ctrl+C exits immediately
+    {
+        let s = shutdown.clone();
+        ctrlc::set_handler(move || {
+            if s.is_cancelled()
+            {
+                eprintln!("\nForced exit.");
+                std::process::exit(130);
+            }
+            eprintln!("\nCtrl+C received — finishing in-flight fetches (Ctrl+C again to force)...");
+            s.cancel();
+        })
+        .expect("failed to set Ctrl+C handler");
+    }
+    let started = Instant::now();
 
-    loop 
+    let options = SourceOptions {
+        currencies: args.currency.clone(),
+        timeout: Duration::from_secs(args.timeout_secs),
+        user_agent: args.user_agent,
+        coin_sources: args.sources,
+        max_staleness: args.max_staleness,
+        ..SourceOptions::default()
+    }
+    .configs_from_env();
+    // Assets quoted in the same currencies at the same interval share sources
+    let mut groups: BTreeMap<(Vec<String>, Duration), Vec<AssetKind>> = BTreeMap::new();
+    for t in tracked
+    {
+        let currencies = t.currency.map_or_else(|| args.currency.clone(), |c| vec![c]);
+        groups.entry((currencies, t.interval.unwrap_or(default_interval))).or_default().push(t.asset);
+    }
+    let mut sources: Vec<(Box<dyn PriceSource>, Schedule)> = Vec::new();
+    for ((currencies, interval), assets) in groups
+    {
+        let options = SourceOptions { currencies, ..options.clone() };
+        for source in sources_for(&assets, &options)
+        {
+            let mut schedule = Schedule::every(interval);
+            // Metal futures trade nearly around the clock, so only stocks wait
+            if !args.ignore_market_hours && source.assets().iter().all(|a| matches!(a, AssetKind::Ticker(_)))
+            {
+                schedule = schedule.during(MarketHours::us());
+            }
+            let s = shutdown.clone();
+            let source = RetryingSource::with_sleep(source, policy, Box::new(move |d| s.sleep(d)));
+            sources.push((Box::new(source), schedule));
+        }
+    }
+
+    let rotation = Rotation {
+        max_bytes: args.max_file_size,
+        daily: matches!(args.rotate, Some(RotateEvery::Daily)),
+        compress: args.compress,
+        keep: args.keep,
+    };
+    let directory = config.output.directory.clone().unwrap_or_else(|| PathBuf::from("."));
+    if !args.dry_run
+    {
+        if let Err(e) = std::fs::create_dir_all(&directory)
+        {
+            eprintln!("error: could not create {}: {e}", directory.display());
+            std::process::exit(2);
+        }
+    }
+    let storage = Storage::Files(HistoryWriter::new(directory, write_policy).rotation(rotation));
+    #[cfg(feature = "sqlite")]
+    let storage = match &args.db
     {
-        for asset in assets.iter_mut() 
+        // A dry run mustn't leave a new database file behind
+        Some(path) => match if args.dry_run { PriceStore::in_memory() } else { PriceStore::open(path) }
         {
-            asset.fetch_price();
-            asset.save_to_file();
+            Ok(store) => Storage::Db { store, policy: write_policy, written: 0 },
+            Err(e) =>
+            {
+                eprintln!("error: could not open {}: {e}", path.display());
+                std::process::exit(2);
+            }
+        },
+        None => storage,
+    };
+    let mode = match (args.dry_run, args.print || watch.is_some())
+    {
+        (true, _) => RecordMode::DryRun,
+        (false, true) => RecordMode::Print,
+        (false, false) => RecordMode::Write,
+    };
+    let color = tty && std::env::var_os("NO_COLOR").is_none();
+    let mut recorder =
+        Recorder::new(storage, AlertTracker::new(rules), alerters, mode).color(color).quiet(board.is_some());
+    recorder.seed_alerts(&selected);
+
+    let metrics = Arc::new(Mutex::new(Metrics::new()));
+    let server = args.metrics_addr.as_deref().map(|addr| match serve_metrics(addr, metrics.clone(), &shutdown)
+    {
+        Ok((bound, handle)) =>
+        {
+            println!("Serving metrics at http://{bound}/metrics");
+            handle
+        }
+        Err(e) =>
+        {
+            eprintln!("error: could not listen on {addr}: {e}");
+            std::process::exit(2);
         }
+    });
 
-        println!("Prices recorded. Sleeping for 10 seconds...");
-        thread::sleep(Duration::from_secs(10));
+    // One thread per source, so a slow or backing-off one doesn't hold up
+    // the rest; everything they fetch is written here
+    let (events, handles) = poll_sources_every(sources, once, &shutdown);
+    for event in events
+    {
+        metrics.lock().unwrap().observe(&event);
+        if let Some(board) = &mut board
+        {
+            board.observe(&event);
+            if matches!(event, PollEvent::RoundDone { .. })
+            {
+                // Home the cursor and clear the screen, then redraw
+                print!("\x1b[H\x1b[2J{}", board.render(color));
+                let _ = std::io::stdout().flush();
+            }
+        }
+        recorder.handle(event);
+    }
+    for handle in handles
+    {
+        let _ = handle.join();
+    }
+    // With --once nothing else stops the metrics listener
+    shutdown.cancel();
+    if let Some(server) = server
+    {
+        let _ = server.join();
+    }
+    if let Err(e) = recorder.sync()
+    {
+        eprintln!("could not sync history files: {e}");
+    }
+    print!("{}", recorder.summary(started.elapsed()));
+    if mode == RecordMode::DryRun
+    {
+        println!("Dry run finished in {}s; nothing was saved.", started.elapsed().as_secs());
+        return;
+    }
+    println!("Wrote {} records in {}s.", recorder.written(), started.elapsed().as_secs());
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const CONFIG: &str = r#"
+        interval = "1m"
+        currencies = ["eur"]
+        timeout = "30s"
+        max_staleness = "2h"
+
+        [output.rotation]
+        max_size = "10M"
+        compress = true
+        keep = 14
+
+        [alerts]
+        webhook = "https://hooks.example.com/prices"
+    "#;
+
+    /// Parse `flags` and apply [`CONFIG`] under them, as `main` does.
+    fn configured(flags: &[&str]) -> Args
+    {
+        let matches = Args::command().try_get_matches_from(std::iter::once("data_fetch").chain(flags.iter().copied())).unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        let config = Config::parse(CONFIG).unwrap();
+        apply_config(&mut args, &config, |id| matches.value_source(id) == Some(ValueSource::CommandLine));
+        args
+    }
+
+    #[test]
+    fn config_fills_in_what_the_flags_leave_out()
+    {
+        let args = configured(&[]);
+        assert_eq!((args.interval, args.timeout_secs), (60, 30));
+        assert_eq!(args.currency, ["eur"]);
+        assert_eq!(args.max_staleness, Some(Duration::from_secs(2 * 3600)));
+        assert_eq!((args.max_file_size, args.keep, args.compress), (Some(10 * 1024 * 1024), Some(14), true));
+        assert_eq!(args.alert_webhook.as_deref(), Some("https://hooks.example.com/prices"));
+    }
+
+    #[test]
+    fn flags_override_the_config()
+    {
+        let args = configured(&[
+            "--interval",
+            "10",
+            "--currency",
+            "usd",
+            "--timeout-secs",
+            "5",
+            "--max-staleness",
+            "15m",
+            "--max-file-size",
+            "500K",
+            "--keep",
+            "3",
+            "--alert-webhook",
+            "http://localhost/hook",
+        ]);
+        assert_eq!((args.interval, args.timeout_secs), (10, 5));
+        assert_eq!(args.currency, ["usd"]);
+        assert_eq!(args.max_staleness, Some(Duration::from_secs(15 * 60)));
+        assert_eq!((args.max_file_size, args.keep), (Some(500 * 1024), Some(3)));
+        assert_eq!(args.alert_webhook.as_deref(), Some("http://localhost/hook"));
+        // A flag left at its default still gives way to the config
+        assert_eq!(configured(&["--interval", "10"]).timeout_secs, 30);
+    }
+
+    #[test]
+    fn no_compress_beats_the_config()
+    {
+        assert!(!configured(&["--no-compress"]).compress);
+        // The last of the two flags wins
+        assert!(configured(&["--no-compress", "--compress"]).compress);
+        assert!(!configured(&["--compress", "--no-compress"]).compress);
     }
 }
@@ -0,0 +1,228 @@
+use chrono::{DateTime, Utc};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{Decimal, PollEvent, PriceRecord, Shutdown};
+
+/// How often the listener checks for Ctrl+C between connections
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+
+/// Latest prices and fetch health, fed from the poll events and rendered in
+/// the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct Metrics
+{
+    /// Latest price per (asset, currency)
+    prices: BTreeMap<(String, String), Decimal>,
+    errors: u64,
+    last_fetch: Option<DateTime<Utc>>,
+}
+
+impl Metrics
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, event: &PollEvent)
+    {
+        match event
+        {
+            PollEvent::Price(PriceRecord { asset, row }) =>
+            {
+                self.prices.insert((asset.name().to_string(), row.currency.clone()), row.price);
+                self.last_fetch = self.last_fetch.max(Some(row.fetched_at));
+            }
+            PollEvent::Failed { .. } | PollEvent::SourceFailed { .. } => self.errors += 1,
+            PollEvent::RoundDone { .. } => {}
+        }
+    }
+
+    pub fn render(&self) -> String
+    {
+        let mut out = String::new();
+        out.push_str("# HELP asset_price Latest fetched price.\n# TYPE asset_price gauge\n");
+        for ((asset, currency), price) in &self.prices
+        {
+            let _ = writeln!(out, "asset_price{{asset=\"{}\",currency=\"{}\"}} {price}", escape(asset), escape(currency));
+        }
+        out.push_str("# HELP fetch_errors_total Fetches that failed, per request or per asset.\n");
+        out.push_str("# TYPE fetch_errors_total counter\n");
+        let _ = writeln!(out, "fetch_errors_total {}", self.errors);
+        if let Some(at) = self.last_fetch
+        {
+            out.push_str("# HELP last_fetch_timestamp_seconds When the latest price was fetched.\n");
+            out.push_str("# TYPE last_fetch_timestamp_seconds gauge\n");
+            let _ = writeln!(out, "last_fetch_timestamp_seconds {}", at.timestamp());
+        }
+        out
+    }
+}
+
+/// Label values may not carry raw quotes, backslashes or newlines
+fn escape(value: &str) -> String
+{
+    value.replace('\\', r"\\").replace('"', "\\\"").replace('\n', r"\n")
+}
+
+/// Serve `metrics` at `http://<addr>/metrics` on a background thread until
+/// `shutdown`. Returns the bound address, which matters when `addr` asks
+/// for port 0.
+pub fn serve_metrics(
+    addr: &str,
+    metrics: Arc<Mutex<Metrics>>,
+    shutdown: &Shutdown,
+) -> io::Result<(SocketAddr, JoinHandle<()>)>
+{
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let local = listener.local_addr()?;
+    let shutdown = shutdown.clone();
+    let handle = thread::spawn(move || loop
+    {
+        match listener.accept()
+        {
+            Ok((stream, _)) =>
+            {
+                if let Err(e) = respond(stream, &metrics)
+                {
+                    eprintln!("metrics: {e}");
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock =>
+            {
+                if !shutdown.sleep(ACCEPT_POLL)
+                {
+                    break;
+                }
+            }
+            Err(e) => eprintln!("metrics: {e}"),
+        }
+    });
+    Ok((local, handle))
+}
+
+/// Answer one request: the metrics for `GET /metrics`, 404 for anything else
+fn respond(stream: TcpStream, metrics: &Mutex<Metrics>) -> io::Result<()>
+{
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers so the client sees a clean close
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2
+    {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next())
+    {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.lock().unwrap().render()),
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{poll_sources, AssetKind, FetchError, PriceSource, Prices, Quote};
+    use rust_decimal_macros::dec;
+
+    /// Prices bitcoin in two currencies and fails ethereum
+    struct OneRound
+    {
+        assets: Vec<AssetKind>,
+    }
+
+    impl PriceSource for OneRound
+    {
+        fn name(&self) -> &str
+        {
+            "CoinGecko"
+        }
+
+        fn assets(&self) -> &[AssetKind]
+        {
+            &self.assets
+        }
+
+        fn fetch_prices(&mut self) -> Result<Prices, FetchError>
+        {
+            let quotes = vec![Quote::new("usd", dec!(119458.5)), Quote::new("eur", dec!(102331))];
+            Ok(BTreeMap::from([
+                (AssetKind::coin("bitcoin"), Ok(quotes)),
+                (AssetKind::coin("ethereum"), Err(FetchError::MissingField("ethereum.usd".to_string()))),
+            ]))
+        }
+    }
+
+    /// Status and body of `GET path`
+    fn get(addr: SocketAddr, path: &str) -> (u16, String)
+    {
+        let response = match ureq::get(&format!("http://{addr}{path}")).call()
+        {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => panic!("{e}"),
+        };
+        (response.status(), response.into_string().unwrap())
+    }
+
+    #[test]
+    fn scrape_after_one_round()
+    {
+        let shutdown = Shutdown::new();
+        let metrics = Arc::new(Mutex::new(Metrics::new()));
+        let (addr, server) = serve_metrics("127.0.0.1:0", metrics.clone(), &shutdown).unwrap();
+
+        let source = OneRound { assets: vec![AssetKind::coin("bitcoin"), AssetKind::coin("ethereum")] };
+        let started = Utc::now().timestamp();
+        let (events, _) = poll_sources(vec![Box::new(source)], Duration::from_secs(60), true, &shutdown);
+        for event in events
+        {
+            metrics.lock().unwrap().observe(&event);
+        }
+
+        let (status, body) = get(addr, "/metrics");
+        assert_eq!(status, 200);
+        assert!(body.contains("asset_price{asset=\"bitcoin\",currency=\"usd\"} 119458.5\n"), "{body}");
+        assert!(body.contains("asset_price{asset=\"bitcoin\",currency=\"eur\"} 102331\n"), "{body}");
+        assert!(!body.contains("ethereum"), "{body}");
+        assert!(body.contains("fetch_errors_total 1\n"), "{body}");
+        let line = body.lines().find(|l| l.starts_with("last_fetch_timestamp_seconds ")).unwrap();
+        let at: i64 = line.split(' ').nth(1).unwrap().parse().unwrap();
+        assert!((started..=started + 5).contains(&at), "{line}");
+
+        assert_eq!(get(addr, "/").0, 404);
+
+        shutdown.cancel();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn nothing_fetched_yet()
+    {
+        let body = Metrics::new().render();
+        assert!(body.contains("fetch_errors_total 0\n"), "{body}");
+        assert!(!body.contains("asset_price{"), "{body}");
+        assert!(!body.contains("last_fetch_timestamp_seconds "), "{body}");
+    }
+}
@@ -0,0 +1,546 @@
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    parse_decimal,
+    rotate::{first_row_date, rotate, Rotation},
+    AssetKind, Decimal,
+};
+
+/// Header line of every price history file. `price` is per unit of the
+/// asset: one coin, one index point or share, and for metals one troy ounce
+/// (copper: one pound), as quoted on the futures market.
+pub const CSV_HEADER: &str = "fetched_at,currency,price,source,market_at";
+
+/// One line of a price history file. The currency is on every row so a file
+/// stays unambiguous if the configured currency changes between runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceRow
+{
+    pub fetched_at: DateTime<Utc>,
+    pub currency: String,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub price: Decimal,
+    /// Where the price came from (`CoinGecko`, `Binance`, ...); empty for
+    /// rows written before this was recorded
+    #[serde(default)]
+    pub source: String,
+    /// When the price was current on the market, for sources that report it
+    /// (Yahoo); it can lag `fetched_at` by hours when a market is closed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market_at: Option<DateTime<Utc>>,
+}
+
+/// A fetched price and the asset it's for. The fetch layer produces these
+/// and every writer (history files, SQLite, metrics, alerts, log lines)
+/// takes its asset name, time and price from one. In JSON the row's fields
+/// sit next to the asset name: `{"asset":"bitcoin","fetched_at":...}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceRecord
+{
+    #[serde(serialize_with = "asset_name")]
+    pub asset: AssetKind,
+    #[serde(flatten)]
+    pub row: PriceRow,
+}
+
+impl PriceRecord
+{
+    pub fn new(asset: AssetKind, row: PriceRow) -> Self
+    {
+        Self { asset, row }
+    }
+}
+
+fn asset_name<S: serde::Serializer>(asset: &AssetKind, s: S) -> Result<S::Ok, S::Error>
+{
+    s.serialize_str(asset.name())
+}
+
+impl PriceRow
+{
+    /// `2026-01-05T14:03:00Z,usd,119458.5,CoinGecko,` or, with a market
+    /// time, `2026-01-05T14:03:00Z,usd,6389.77,Yahoo Finance,2026-01-05T14:01:00Z`
+    pub fn to_csv(&self) -> String
+    {
+        let market_at = self.market_at.map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true));
+        format!(
+            "{},{},{},{},{}",
+            self.fetched_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.currency,
+            self.price,
+            self.source,
+            market_at.unwrap_or_default()
+        )
+    }
+
+    /// Parse a line written by `to_csv`; `None` for the header or anything
+    /// malformed (such as a line cut short by a crash). Older rows without
+    /// the source or market time columns are accepted without them.
+    pub fn from_csv(line: &str) -> Option<Self>
+    {
+        let timestamp = |text: &str| DateTime::parse_from_rfc3339(text).ok().map(|at| at.with_timezone(&Utc));
+        let mut fields = line.trim_end().split(',');
+        let fetched_at = timestamp(fields.next()?)?;
+        let currency = fields.next()?.to_string();
+        let price = parse_decimal(fields.next()?)?;
+        let source = fields.next().unwrap_or_default().to_string();
+        let market_at = match fields.next()
+        {
+            None | Some("") => None,
+            Some(text) => Some(timestamp(text)?),
+        };
+        if fields.next().is_some()
+        {
+            return None;
+        }
+        Some(Self { fetched_at, currency, price, source, market_at })
+    }
+}
+
+/// Append `row` to the CSV file at `path`, writing the header first if the
+/// file is new or empty.
+pub fn append_row(path: &Path, row: &PriceRow) -> io::Result<()>
+{
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    let mut text = String::new();
+    if file.metadata()?.len() == 0
+    {
+        text.push_str(CSV_HEADER);
+        text.push('\n');
+    }
+    text.push_str(&row.to_csv());
+    text.push('\n');
+    // One write per row, so a crash can't leave a header without its row
+    file.write_all(text.as_bytes())
+}
+
+/// When a fetched price is worth a new row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePolicy
+{
+    /// Every fetch
+    Always,
+    /// Only when the price differs from the last row written
+    OnChange,
+    /// On change, and at least this often as a heartbeat when flat
+    OnChangeOrInterval(Duration),
+}
+
+impl WritePolicy
+{
+    /// Whether `row` should be written, given the last row written for the
+    /// same asset and currency.
+    pub fn should_write(&self, last: Option<&PriceRow>, row: &PriceRow) -> bool
+    {
+        let Some(last) = last else { return true };
+        match self
+        {
+            WritePolicy::Always => true,
+            // Decimal equality ignores scale, so 1.50 and 1.5 are the same price
+            WritePolicy::OnChange => last.price != row.price,
+            WritePolicy::OnChangeOrInterval(every) =>
+            {
+                let since = (row.fetched_at - last.fetched_at).to_std().unwrap_or_default();
+                last.price != row.price || since >= *every
+            }
+        }
+    }
+}
+
+/// How far back from the end of a file to look for the last row
+const TAIL_BYTES: u64 = 64 * 1024;
+
+/// Last row for `currency` in the CSV file at `path`, looking only at the
+/// tail of the file. `Ok(None)` if the file doesn't exist or has no such row.
+pub fn last_row(path: &Path, currency: &str) -> io::Result<Option<PriceRow>>
+{
+    let mut file = match File::open(path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    // The first line may be cut in half by the seek; from_csv rejects it
+    Ok(String::from_utf8_lossy(&tail)
+        .lines()
+        .rev()
+        .filter_map(PriceRow::from_csv)
+        .find(|row| row.currency == currency))
+}
+
+/// Rows at or after `since` in the CSV file at `path`, in file order. Reads
+/// backwards from the end a chunk at a time, so only the recent part of a
+/// long history is read. Empty if the file doesn't exist.
+pub fn rows_since(path: &Path, since: DateTime<Utc>) -> io::Result<Vec<PriceRow>>
+{
+    let mut file = match File::open(path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut end = file.metadata()?.len();
+    let mut tail: Vec<u8> = Vec::new();
+    while end > 0
+    {
+        let start = end.saturating_sub(TAIL_BYTES);
+        let mut chunk = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+        end = start;
+        // Stop once a complete row older than `since` has been read; the
+        // first line of the buffer may be partial, so skip it
+        let text = String::from_utf8_lossy(&tail);
+        if end > 0 && text.lines().skip(1).filter_map(PriceRow::from_csv).any(|row| row.fetched_at < since)
+        {
+            break;
+        }
+    }
+    let text = String::from_utf8_lossy(&tail);
+    let skip = usize::from(end > 0);
+    Ok(text.lines().skip(skip).filter_map(PriceRow::from_csv).filter(|row| row.fetched_at >= since).collect())
+}
+
+/// Add `rows` to the CSV file at `path`, skipping any already there at the
+/// same second in the same currency, so merging twice adds nothing. The
+/// whole file is rewritten in time order under a temporary name and renamed
+/// into place; malformed lines are dropped on the way. Returns how many
+/// rows were added.
+pub fn merge_rows(path: &Path, rows: &[PriceRow]) -> io::Result<usize>
+{
+    let existing = match fs::read_to_string(path)
+    {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let mut merged: Vec<PriceRow> = existing.lines().filter_map(PriceRow::from_csv).collect();
+    let mut seen: HashSet<(i64, String)> = merged.iter().map(|r| (r.fetched_at.timestamp(), r.currency.clone())).collect();
+    let before = merged.len();
+    merged.extend(rows.iter().filter(|r| seen.insert((r.fetched_at.timestamp(), r.currency.clone()))).cloned());
+    let added = merged.len() - before;
+    if added == 0
+    {
+        return Ok(0);
+    }
+    merged.sort_by_key(|r| r.fetched_at);
+
+    let partial = path.with_extension("csv.partial");
+    let mut file = File::create(&partial)?;
+    let mut text = format!("{CSV_HEADER}\n");
+    for row in &merged
+    {
+        text.push_str(&row.to_csv());
+        text.push('\n');
+    }
+    file.write_all(text.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&partial, path)?;
+    Ok(added)
+}
+
+/// Appends rows to `<dir>/<stem>.csv` under a `WritePolicy`, remembering
+/// the last row written per file and currency. The first write to a file
+/// looks at what's already there, so restarts don't repeat a flat price.
+/// With a `Rotation`, full or finished files are moved aside before the
+/// next row is written.
+#[derive(Debug)]
+pub struct HistoryWriter
+{
+    dir: PathBuf,
+    policy: WritePolicy,
+    rotation: Rotation,
+    last: HashMap<(String, String), Option<PriceRow>>,
+    /// Day of the first row in each active file, for rotation
+    started: HashMap<String, Option<NaiveDate>>,
+    written: usize,
+}
+
+impl HistoryWriter
+{
+    pub fn new(dir: impl Into<PathBuf>, policy: WritePolicy) -> Self
+    {
+        Self {
+            dir: dir.into(),
+            policy,
+            rotation: Rotation::default(),
+            last: HashMap::new(),
+            started: HashMap::new(),
+            written: 0,
+        }
+    }
+
+    pub fn rotation(mut self, rotation: Rotation) -> Self
+    {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn path(&self, stem: &str) -> PathBuf
+    {
+        self.dir.join(format!("{stem}.csv"))
+    }
+
+    /// Write `row` to the `stem` file if the policy allows; returns whether
+    /// it was written.
+    pub fn write(&mut self, stem: &str, row: &PriceRow) -> io::Result<bool>
+    {
+        let path = self.path(stem);
+        let key = (stem.to_string(), row.currency.clone());
+        if !self.last.contains_key(&key)
+        {
+            let seeded = if self.policy == WritePolicy::Always { None } else { last_row(&path, &row.currency)? };
+            self.last.insert(key.clone(), seeded);
+        }
+        if !self.policy.should_write(self.last[&key].as_ref(), row)
+        {
+            return Ok(false);
+        }
+        if self.rotation.is_enabled()
+        {
+            self.rotate_if_due(stem, &path, row)?;
+        }
+        append_row(&path, row)?;
+        self.last.insert(key, Some(row.clone()));
+        self.written += 1;
+        Ok(true)
+    }
+
+    /// Merge older `rows` into the active `stem` file (see [`merge_rows`]).
+    /// Rotated files aren't checked for them.
+    pub fn merge(&mut self, stem: &str, rows: &[PriceRow]) -> io::Result<usize>
+    {
+        let added = merge_rows(&self.path(stem), rows)?;
+        // The file's first day may have moved back
+        self.started.remove(stem);
+        self.written += added;
+        Ok(added)
+    }
+
+    /// Rotate the active `stem` file before `row` is appended if it has
+    /// grown too big or `row` starts a new day.
+    fn rotate_if_due(&mut self, stem: &str, path: &Path, row: &PriceRow) -> io::Result<()>
+    {
+        let started = match self.started.get(stem)
+        {
+            Some(started) => *started,
+            None => first_row_date(path)?,
+        };
+        let day = row.fetched_at.date_naive();
+        let Some(started) = started else
+        {
+            self.started.insert(stem.to_string(), Some(day));
+            return Ok(());
+        };
+        let full = match self.rotation.max_bytes
+        {
+            Some(max) => fs::metadata(path).map(|m| m.len() >= max).unwrap_or(false),
+            None => false,
+        };
+        let new_day = self.rotation.daily && day > started;
+        if full || new_day
+        {
+            rotate(path, stem, started, &self.rotation)?;
+            self.started.insert(stem.to_string(), Some(day));
+        }
+        else
+        {
+            self.started.insert(stem.to_string(), Some(started));
+        }
+        Ok(())
+    }
+
+    /// Rows written by this writer so far
+    pub fn written(&self) -> usize
+    {
+        self.written
+    }
+
+    /// Make sure everything written so far has reached the disk.
+    pub fn sync(&self) -> io::Result<()>
+    {
+        let stems: std::collections::BTreeSet<&str> = self.last.keys().map(|(stem, _)| stem.as_str()).collect();
+        for stem in stems
+        {
+            match File::open(self.path(stem))
+            {
+                Ok(file) => file.sync_all()?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    /// Rows a minute apart, starting at midnight
+    fn series(prices: &[Decimal]) -> Vec<PriceRow>
+    {
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap();
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, &price)| PriceRow {
+                fetched_at: start + chrono::Duration::minutes(i as i64),
+                currency: "usd".to_string(),
+                price,
+                source: "CoinGecko".to_string(),
+                market_at: None,
+            })
+            .collect()
+    }
+
+    /// Rows written to a fresh file under `policy`
+    fn rows_written(policy: WritePolicy, rows: &[PriceRow]) -> usize
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = HistoryWriter::new(dir.path(), policy);
+        for row in rows
+        {
+            writer.write("gspc", row).unwrap();
+        }
+        let text = std::fs::read_to_string(writer.path("gspc")).unwrap();
+        text.lines().filter_map(PriceRow::from_csv).count()
+    }
+
+    #[test]
+    fn flat_series_under_each_policy()
+    {
+        let flat = series(&[dec!(6389.77); 12]);
+        assert_eq!(rows_written(WritePolicy::Always, &flat), 12);
+        assert_eq!(rows_written(WritePolicy::OnChange, &flat), 1);
+        // Minutes 0, 5 and 10
+        let heartbeat = WritePolicy::OnChangeOrInterval(Duration::from_secs(5 * 60));
+        assert_eq!(rows_written(heartbeat, &flat), 3);
+    }
+
+    #[test]
+    fn changes_are_always_written()
+    {
+        // 1.50 is the same price as 1.5, but the smallest real move counts
+        let moving = series(&[dec!(1.0), dec!(1.0), dec!(1.5), dec!(1.50), dec!(1.0), dec!(1.000000000001)]);
+        assert_eq!(rows_written(WritePolicy::OnChange, &moving), 4);
+    }
+
+    #[test]
+    fn restart_picks_up_the_last_row_on_disk()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let rows = series(&[dec!(5.0), dec!(5.0), dec!(5.0)]);
+        let mut first = HistoryWriter::new(dir.path(), WritePolicy::OnChange);
+        assert!(first.write("btc", &rows[0]).unwrap());
+
+        let mut restarted = HistoryWriter::new(dir.path(), WritePolicy::OnChange);
+        assert!(!restarted.write("btc", &rows[1]).unwrap());
+        let eur = PriceRow { currency: "eur".to_string(), ..rows[2].clone() };
+        assert!(restarted.write("btc", &eur).unwrap());
+        assert_eq!(last_row(&restarted.path("btc"), "usd").unwrap(), Some(rows[0].clone()));
+    }
+
+    #[test]
+    fn rows_since_reads_across_chunks()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("btc.csv");
+        // Well over TAIL_BYTES, so the read has to go back several chunks
+        let rows = series(&vec![dec!(1.5); 5000]);
+        for row in &rows
+        {
+            append_row(&path, row).unwrap();
+        }
+        let since = rows[1000].fetched_at;
+        assert_eq!(rows_since(&path, since).unwrap(), rows[1000..].to_vec());
+        assert_eq!(rows_since(&path, rows[0].fetched_at).unwrap().len(), 5000);
+        assert!(rows_since(&dir.path().join("none.csv"), since).unwrap().is_empty());
+    }
+
+    #[test]
+    fn merging_is_idempotent_and_keeps_time_order()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let rows = series(&[dec!(1), dec!(2), dec!(3), dec!(4)]);
+        let mut writer = HistoryWriter::new(dir.path(), WritePolicy::Always);
+        // Live rows already recorded for minutes 2 and 3
+        writer.write("btc", &rows[2]).unwrap();
+        writer.write("btc", &rows[3]).unwrap();
+
+        let mut history = series(&[dec!(1), dec!(2), dec!(9)]);
+        history.reverse();
+        assert_eq!(writer.merge("btc", &history).unwrap(), 2);
+        let text = fs::read_to_string(writer.path("btc")).unwrap();
+        assert_eq!(merge_rows(&writer.path("btc"), &history).unwrap(), 0);
+        assert_eq!(fs::read_to_string(writer.path("btc")).unwrap(), text);
+
+        assert!(text.starts_with(&format!("{CSV_HEADER}\n")));
+        let merged: Vec<PriceRow> = text.lines().filter_map(PriceRow::from_csv).collect();
+        assert_eq!(merged, rows);
+    }
+
+    #[test]
+    fn parses_what_it_writes()
+    {
+        let row = series(&[dec!(0.2213)]).remove(0);
+        assert_eq!(PriceRow::from_csv(&row.to_csv()), Some(row));
+        assert_eq!(PriceRow::from_csv(CSV_HEADER), None);
+        assert_eq!(PriceRow::from_csv("2026-01-05T00:00:00Z,us"), None);
+        let old = PriceRow::from_csv("2026-01-05T00:00:00Z,usd,0.2213").unwrap();
+        assert_eq!((old.price, old.source.as_str(), old.market_at), (dec!(0.2213), "", None));
+        let old = PriceRow::from_csv("2026-01-05T00:00:00Z,usd,0.2213,CoinGecko").unwrap();
+        assert_eq!((old.source.as_str(), old.market_at), ("CoinGecko", None));
+        assert_eq!(PriceRow::from_csv("2026-01-05T00:00:00Z,usd,6389.77,Yahoo Finance,yesterday"), None);
+    }
+
+    #[test]
+    fn prices_keep_their_digits()
+    {
+        // None of these survive a trip through f64
+        for price in ["62999.99999999999", "0.00000001", "0.12345678901234567890", "119461.37000000"]
+        {
+            let line = format!("2026-01-05T00:00:00Z,usd,{price},Binance,");
+            let row = PriceRow::from_csv(&line).unwrap();
+            assert_eq!(row.to_csv(), line);
+        }
+        let sum = series(&[dec!(0.1) + dec!(0.2)]).remove(0);
+        assert!(sum.to_csv().contains(",0.3,"), "{}", sum.to_csv());
+    }
+
+    #[test]
+    fn rows_carry_timestamp_currency_and_source()
+    {
+        let row = PriceRow {
+            fetched_at: Utc.with_ymd_and_hms(2026, 1, 5, 14, 3, 0).unwrap(),
+            currency: "eur".to_string(),
+            price: dec!(0.2213),
+            source: "Binance".to_string(),
+            market_at: None,
+        };
+        assert_eq!(row.to_csv(), "2026-01-05T14:03:00Z,eur,0.2213,Binance,");
+
+        let market_at = Some(Utc.with_ymd_and_hms(2026, 1, 2, 21, 0, 0).unwrap());
+        let yahoo = PriceRow { source: "Yahoo Finance".to_string(), market_at, ..row };
+        assert_eq!(yahoo.to_csv(), "2026-01-05T14:03:00Z,eur,0.2213,Yahoo Finance,2026-01-02T21:00:00Z");
+        assert_eq!(PriceRow::from_csv(&yahoo.to_csv()), Some(yahoo));
+    }
+}
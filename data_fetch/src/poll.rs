@@ -0,0 +1,233 @@
+use chrono::Utc;
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{AssetKind, FetchError, PriceRecord, PriceRow, PriceSource, Schedule, Shutdown};
+
+/// What the polling threads report back.
+#[derive(Debug)]
+pub enum PollEvent
+{
+    /// A price, stamped when its fetch completed
+    Price(PriceRecord),
+    /// The asset's price couldn't be fetched this round
+    Failed { asset: AssetKind, source: String, error: FetchError },
+    /// The whole request to the source failed, so none of `assets` were priced
+    SourceFailed { source: String, assets: Vec<AssetKind>, error: FetchError },
+    /// The source finished a round; `next_in` is `None` when it has stopped
+    RoundDone { source: String, next_in: Option<Duration> },
+}
+
+/// Run each source on its own thread, fetching every `interval` (or once)
+/// until `shutdown`, and send the results back over the returned channel.
+/// A slow or failing source only delays its own assets. The channel closes
+/// once every thread has finished its last round.
+pub fn poll_sources(
+    sources: Vec<Box<dyn PriceSource>>,
+    interval: Duration,
+    once: bool,
+    shutdown: &Shutdown,
+) -> (Receiver<PollEvent>, Vec<JoinHandle<()>>)
+{
+    poll_sources_every(sources.into_iter().map(|source| (source, Schedule::every(interval))).collect(), once, shutdown)
+}
+
+/// `poll_sources` with a schedule per source. Each thread sleeps until its
+/// source is next due; the first round always runs straight away.
+pub fn poll_sources_every(
+    sources: Vec<(Box<dyn PriceSource>, Schedule)>,
+    once: bool,
+    shutdown: &Shutdown,
+) -> (Receiver<PollEvent>, Vec<JoinHandle<()>>)
+{
+    let (tx, rx) = mpsc::channel();
+    let handles = sources
+        .into_iter()
+        .map(|(source, schedule)| {
+            let tx = tx.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || poll_one(source, schedule, once, &shutdown, &tx))
+        })
+        .collect();
+    (rx, handles)
+}
+
+fn poll_one(mut source: Box<dyn PriceSource>, schedule: Schedule, once: bool, shutdown: &Shutdown, tx: &Sender<PollEvent>)
+{
+    let mut round_start = Utc::now();
+    loop
+    {
+        if send_round(source.as_mut(), tx).is_err()
+        {
+            // Nobody is listening any more
+            return;
+        }
+        if once || shutdown.is_cancelled()
+        {
+            break;
+        }
+
+        // Schedule from the start of the round so fetch time doesn't add drift;
+        // if a round overran (say, while backing off), start the next one right away.
+        let now = Utc::now();
+        round_start = schedule.next_due(round_start).max(now);
+        let wait = (round_start - now).to_std().unwrap_or_default();
+        let done = PollEvent::RoundDone { source: source.name().to_string(), next_in: Some(wait) };
+        if tx.send(done).is_err() || !shutdown.sleep(wait)
+        {
+            break;
+        }
+    }
+    let _ = tx.send(PollEvent::RoundDone { source: source.name().to_string(), next_in: None });
+}
+
+/// Fetch once and send one event per asset and quote.
+fn send_round(source: &mut dyn PriceSource, tx: &Sender<PollEvent>) -> Result<(), mpsc::SendError<PollEvent>>
+{
+    let name = source.name().to_string();
+    let prices = match source.fetch_prices()
+    {
+        Ok(prices) => prices,
+        Err(error) =>
+        {
+            // The next round tries again
+            let assets = source.assets().to_vec();
+            return tx.send(PollEvent::SourceFailed { source: name, assets, error });
+        }
+    };
+    let fetched_at = Utc::now();
+    for (asset, quotes) in prices
+    {
+        match quotes
+        {
+            Ok(quotes) =>
+            {
+                for quote in quotes
+                {
+                    let source = quote.source.unwrap_or_else(|| name.clone());
+                    let row = PriceRow {
+                        fetched_at,
+                        currency: quote.currency,
+                        price: quote.price,
+                        source,
+                        market_at: quote.market_at,
+                    };
+                    tx.send(PollEvent::Price(PriceRecord::new(asset.clone(), row)))?;
+                }
+            }
+            Err(error) => tx.send(PollEvent::Failed { asset, source: name.clone(), error })?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{Prices, Quote};
+    use rust_decimal_macros::dec;
+    use std::collections::BTreeMap;
+
+    /// Answers with a fixed price after `delay`.
+    struct Delayed
+    {
+        assets: Vec<AssetKind>,
+        delay: Duration,
+    }
+
+    impl PriceSource for Delayed
+    {
+        fn name(&self) -> &str
+        {
+            self.assets[0].name()
+        }
+
+        fn assets(&self) -> &[AssetKind]
+        {
+            &self.assets
+        }
+
+        fn fetch_prices(&mut self) -> Result<Prices, FetchError>
+        {
+            thread::sleep(self.delay);
+            Ok(BTreeMap::from([(self.assets[0].clone(), Ok(vec![Quote::new("usd", dec!(1.0))]))]))
+        }
+    }
+
+    fn delayed(asset: AssetKind, delay: Duration) -> Box<dyn PriceSource>
+    {
+        Box::new(Delayed { assets: vec![asset], delay })
+    }
+
+    #[test]
+    fn slow_source_does_not_hold_up_fast_ones()
+    {
+        let started = Utc::now();
+        let sources = vec![
+            delayed(AssetKind::sp500(), Duration::from_secs(5)),
+            delayed(AssetKind::coin("bitcoin"), Duration::ZERO),
+            delayed(AssetKind::coin("ethereum"), Duration::from_millis(20)),
+        ];
+        let (rx, _handles) = poll_sources(sources, Duration::from_secs(60), true, &Shutdown::new());
+
+        // Both fast prices arrive, stamped right away, long before the slow one
+        let mut fast = Vec::new();
+        while fast.len() < 2
+        {
+            match rx.recv_timeout(Duration::from_secs(2)).expect("fast sources should report promptly")
+            {
+                PollEvent::Price(record) => fast.push((record.asset, record.row.fetched_at)),
+                PollEvent::RoundDone { .. } => {}
+                other => panic!("unexpected {other:?}"),
+            }
+        }
+        for (asset, fetched_at) in fast
+        {
+            assert_ne!(asset, AssetKind::sp500());
+            let lag = (fetched_at - started).to_std().unwrap();
+            assert!(lag < Duration::from_millis(500), "{} stamped {lag:?} after start", asset.name());
+        }
+    }
+
+    #[test]
+    fn channel_closes_after_one_round()
+    {
+        let (rx, handles) = poll_sources(
+            vec![delayed(AssetKind::coin("bitcoin"), Duration::ZERO)],
+            Duration::from_secs(60),
+            true,
+            &Shutdown::new(),
+        );
+        let events: Vec<PollEvent> = rx.iter().collect();
+        assert!(matches!(events[0], PollEvent::Price(_)));
+        assert!(matches!(events.last(), Some(PollEvent::RoundDone { next_in: None, .. })));
+        for h in handles
+        {
+            h.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn shutdown_stops_the_loop_during_the_sleep()
+    {
+        let shutdown = Shutdown::new();
+        let (rx, handles) = poll_sources(
+            vec![delayed(AssetKind::coin("bitcoin"), Duration::ZERO)],
+            Duration::from_secs(3600),
+            false,
+            &shutdown,
+        );
+        assert!(matches!(rx.recv().unwrap(), PollEvent::Price(_)));
+        assert!(matches!(rx.recv().unwrap(), PollEvent::RoundDone { next_in: Some(_), .. }));
+        shutdown.cancel();
+        for h in handles
+        {
+            h.join().unwrap();
+        }
+        assert_eq!(rx.iter().count(), 1);
+    }
+}
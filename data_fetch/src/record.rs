@@ -0,0 +1,429 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::time::Duration;
+
+use crate::{
+    render_changes, rows_since, AlertTracker, Alerter, AssetKind, Change, HistoryWriter, PollEvent, PriceRecord, PriceRow,
+    PriceTracker,
+};
+#[cfg(feature = "sqlite")]
+use crate::{PriceStore, WritePolicy};
+
+/// Where fetched prices go.
+pub enum Storage
+{
+    Files(HistoryWriter),
+    #[cfg(feature = "sqlite")]
+    Db { store: PriceStore, policy: WritePolicy, written: usize },
+}
+
+impl Storage
+{
+    /// Save `record` if the write policy allows; returns whether it was saved.
+    pub fn write(&mut self, record: &PriceRecord) -> Result<bool, String>
+    {
+        let (kind, row) = (&record.asset, &record.row);
+        match self
+        {
+            Storage::Files(writer) => writer.write(&kind.file_stem(), row).map_err(|e| e.to_string()),
+            #[cfg(feature = "sqlite")]
+            Storage::Db { store, policy, written } =>
+            {
+                let stem = kind.file_stem();
+                let last = store.latest_in(&stem, &row.currency).map_err(|e| e.to_string())?;
+                if !policy.should_write(last.as_ref(), row)
+                {
+                    return Ok(false);
+                }
+                store.insert(&stem, row).map_err(|e| e.to_string())?;
+                *written += 1;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Add past `rows`, skipping times already recorded; returns how many
+    /// were new.
+    pub fn backfill(&mut self, kind: &AssetKind, rows: &[PriceRow]) -> Result<usize, String>
+    {
+        match self
+        {
+            Storage::Files(writer) => writer.merge(&kind.file_stem(), rows).map_err(|e| e.to_string()),
+            #[cfg(feature = "sqlite")]
+            Storage::Db { store, written, .. } =>
+            {
+                let added = store.insert_missing(&kind.file_stem(), rows).map_err(|e| e.to_string())?;
+                *written += added;
+                Ok(added)
+            }
+        }
+    }
+
+    /// Saved rows at or after `since`, oldest first
+    pub fn history(&self, kind: &AssetKind, since: DateTime<Utc>) -> Result<Vec<PriceRow>, String>
+    {
+        match self
+        {
+            Storage::Files(writer) => rows_since(&writer.path(&kind.file_stem()), since).map_err(|e| e.to_string()),
+            #[cfg(feature = "sqlite")]
+            Storage::Db { store, .. } =>
+            {
+                // Rows are stamped to the second, so this end is past anything stored
+                let to = Utc::now() + chrono::Duration::seconds(1);
+                store.range(&kind.file_stem(), since, to).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    pub fn written(&self) -> usize
+    {
+        match self
+        {
+            Storage::Files(writer) => writer.written(),
+            #[cfg(feature = "sqlite")]
+            Storage::Db { written, .. } => *written,
+        }
+    }
+
+    pub fn sync(&self) -> Result<(), String>
+    {
+        match self
+        {
+            Storage::Files(writer) => writer.sync().map_err(|e| e.to_string()),
+            // Every insert is its own committed transaction
+            #[cfg(feature = "sqlite")]
+            Storage::Db { .. } => Ok(()),
+        }
+    }
+}
+
+/// What to do with each fetched price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordMode
+{
+    /// Save it
+    Write,
+    /// Save it and print it
+    Print,
+    /// Only print it: nothing is saved and alerts are printed, not sent
+    DryRun,
+}
+
+/// One fetched price as a log line:
+/// `2026-01-05T14:03:00Z bitcoin 119458.5 usd (CoinGecko)`
+pub fn format_price(record: &PriceRecord) -> String
+{
+    let (asset, row) = (&record.asset, &record.row);
+    let at = row.fetched_at.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let source = if row.source.is_empty() { "unknown source" } else { &row.source };
+    format!("{at} {} {} {} ({source})", asset.name(), row.price, row.currency)
+}
+
+/// Writes and alerts, all on the main thread so file handling stays simple.
+pub struct Recorder
+{
+    storage: Storage,
+    alerts: AlertTracker,
+    alerters: Vec<Box<dyn Alerter>>,
+    mode: RecordMode,
+    tracker: PriceTracker,
+    /// Prices since the last table was printed
+    changes: Vec<Change>,
+    color: bool,
+    /// Leave the console to something else, printing only save and alert
+    /// errors
+    quiet: bool,
+}
+
+impl Recorder
+{
+    pub fn new(storage: Storage, alerts: AlertTracker, alerters: Vec<Box<dyn Alerter>>, mode: RecordMode) -> Self
+    {
+        Self {
+            storage,
+            alerts,
+            alerters,
+            mode,
+            tracker: PriceTracker::new(),
+            changes: Vec::new(),
+            color: false,
+            quiet: false,
+        }
+    }
+
+    /// Color rises and falls in the change table
+    pub fn color(mut self, color: bool) -> Self
+    {
+        self.color = color;
+        self
+    }
+
+    /// Print no prices, change tables or fetch failures, e.g. while a
+    /// [`Board`](crate::Board) has the screen
+    pub fn quiet(mut self, quiet: bool) -> Self
+    {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Load enough of each asset's history to cover its alert windows.
+    pub fn seed_alerts(&mut self, assets: &[AssetKind])
+    {
+        for kind in assets
+        {
+            let Some(window) = self.alerts.window_for(kind) else { continue };
+            let since = Utc::now() - chrono::Duration::from_std(window).unwrap_or_default();
+            match self.storage.history(kind, since)
+            {
+                Ok(rows) => self.alerts.seed(kind, rows),
+                Err(e) => eprintln!("{}: could not read history for alerts: {e}", kind.name()),
+            }
+        }
+    }
+
+    fn record(&mut self, record: PriceRecord)
+    {
+        match self.mode
+        {
+            _ if self.quiet => {}
+            RecordMode::DryRun => println!("[dry run] {}", format_price(&record)),
+            RecordMode::Print => println!("{}", format_price(&record)),
+            RecordMode::Write => {}
+        }
+        if self.mode != RecordMode::DryRun
+        {
+            if let Err(e) = self.storage.write(&record)
+            {
+                eprintln!("could not save {}: {e}", format_price(&record));
+            }
+        }
+        let PriceRecord { asset, row } = record;
+        self.changes.push(self.tracker.observe(&asset, &row));
+        for alert in self.alerts.observe(&asset, row)
+        {
+            if self.mode == RecordMode::DryRun
+            {
+                if !self.quiet
+                {
+                    println!("[dry run] would alert: {alert}");
+                }
+                continue;
+            }
+            for alerter in &mut self.alerters
+            {
+                if let Err(e) = alerter.notify(&alert)
+                {
+                    eprintln!("{}: could not send alert: {e}", asset.name());
+                }
+            }
+        }
+    }
+
+    pub fn handle(&mut self, event: PollEvent)
+    {
+        match event
+        {
+            PollEvent::Price(record) => self.record(record),
+            PollEvent::Failed { asset, source, error } =>
+            {
+                if !self.quiet
+                {
+                    eprintln!("{}: fetch from {source} failed: {error}", asset.name());
+                }
+                self.tracker.failed(&asset);
+            }
+            PollEvent::SourceFailed { source, assets, error } =>
+            {
+                for asset in assets
+                {
+                    if !self.quiet
+                    {
+                        eprintln!("{}: fetch from {source} failed: {error}", asset.name());
+                    }
+                    self.tracker.failed(&asset);
+                }
+            }
+            PollEvent::RoundDone { .. } if self.quiet => self.changes.clear(),
+            PollEvent::RoundDone { source, next_in } => print!("{}", self.round_done(&source, next_in)),
+        }
+    }
+
+    /// The change table for the round just finished, if anything changed,
+    /// then the wait until the next one. `next_in` is `None` after the last
+    /// round (`--once`, or shutting down), which still gets its table.
+    fn round_done(&mut self, source: &str, next_in: Option<Duration>) -> String
+    {
+        let mut out = String::new();
+        if !self.changes.is_empty()
+        {
+            out = render_changes(&self.changes, self.color);
+            self.changes.clear();
+        }
+        if let Some(next_in) = next_in
+        {
+            out += &format!("{source}: prices checked. Sleeping for {} seconds...\n", next_in.as_secs());
+        }
+        out
+    }
+
+    /// Session totals and each asset's price range, for when the run ends
+    pub fn summary(&self, elapsed: Duration) -> String
+    {
+        self.tracker.summary(elapsed)
+    }
+
+    /// Rows saved so far
+    pub fn written(&self) -> usize
+    {
+        self.storage.written()
+    }
+
+    pub fn sync(&self) -> Result<(), String>
+    {
+        self.storage.sync()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{AlertRule, CommandAlerter, WritePolicy};
+    use rust_decimal_macros::dec;
+    use chrono::TimeZone;
+    use std::path::Path;
+
+    /// A recorder over CSV files in `dir`, with a 1%-in-an-hour BTC alert
+    /// that touches `dir/alerted` when it fires
+    fn recorder(dir: &Path, mode: RecordMode) -> Recorder
+    {
+        let rule = AlertRule {
+            asset: AssetKind::coin("bitcoin"),
+            percent_change: 1.0,
+            window: Duration::from_secs(3600),
+            cooldown: Duration::from_secs(3600),
+        };
+        let command = CommandAlerter { command: format!("touch '{}'", dir.join("alerted").display()) };
+        let storage = Storage::Files(HistoryWriter::new(dir, WritePolicy::Always));
+        Recorder::new(storage, AlertTracker::new(vec![rule]), vec![Box::new(command)], mode)
+    }
+
+    fn feed(recorder: &mut Recorder)
+    {
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 9, 0, 0).unwrap();
+        for (min, price) in [(0, dec!(100.0)), (10, dec!(105.0))]
+        {
+            let row = PriceRow {
+                fetched_at: start + chrono::Duration::minutes(min),
+                currency: "usd".to_string(),
+                price,
+                source: "CoinGecko".to_string(),
+                market_at: None,
+            };
+            recorder.handle(PollEvent::Price(PriceRecord::new(AssetKind::coin("bitcoin"), row)));
+        }
+    }
+
+    fn files_in(dir: &Path) -> Vec<String>
+    {
+        let mut names: Vec<String> =
+            std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn dry_run_creates_no_files()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let mut dry = recorder(dir.path(), RecordMode::DryRun);
+        feed(&mut dry);
+        dry.sync().unwrap();
+        assert_eq!(dry.written(), 0);
+        assert!(files_in(dir.path()).is_empty(), "{:?}", files_in(dir.path()));
+    }
+
+    #[test]
+    fn print_still_writes_and_alerts()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let mut print = recorder(dir.path(), RecordMode::Print);
+        feed(&mut print);
+        assert_eq!(print.written(), 2);
+        assert_eq!(files_in(dir.path()), ["alerted", "btc.csv"]);
+    }
+
+    #[test]
+    fn last_round_still_shows_its_changes()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let mut once = recorder(dir.path(), RecordMode::DryRun);
+        feed(&mut once);
+        let out = once.round_done("CoinGecko", None);
+        assert!(out.starts_with("asset "), "{out}");
+        assert!(out.contains("bitcoin"), "{out}");
+        assert!(!out.contains("Sleeping"), "{out}");
+        assert_eq!(once.round_done("CoinGecko", None), "");
+
+        feed(&mut once);
+        let out = once.round_done("CoinGecko", Some(Duration::from_secs(10)));
+        assert!(out.contains("bitcoin") && out.ends_with("CoinGecko: prices checked. Sleeping for 10 seconds...\n"), "{out}");
+    }
+
+    #[test]
+    fn price_lines()
+    {
+        let row = PriceRow {
+            fetched_at: Utc.with_ymd_and_hms(2026, 1, 5, 14, 3, 0).unwrap(),
+            currency: "eur".to_string(),
+            price: dec!(0.2213),
+            source: "Binance".to_string(),
+            market_at: None,
+        };
+        let record = PriceRecord::new(AssetKind::coin("dogecoin"), row);
+        assert_eq!(format_price(&record), "2026-01-05T14:03:00Z dogecoin 0.2213 eur (Binance)");
+    }
+
+    /// One record, read back from every place it ends up
+    #[test]
+    fn every_writer_renders_the_same_record()
+    {
+        let at = Utc.with_ymd_and_hms(2026, 1, 5, 14, 3, 0).unwrap();
+        let row =
+            PriceRow { fetched_at: at, currency: "usd".to_string(), price: dec!(119458.25), source: "CoinGecko".to_string(), market_at: None };
+        let record = PriceRecord::new(AssetKind::coin("bitcoin"), row.clone());
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut files = Storage::Files(HistoryWriter::new(dir.path(), WritePolicy::Always));
+        assert!(files.write(&record).unwrap());
+        files.sync().unwrap();
+        assert_eq!(files.history(&record.asset, at).unwrap(), std::slice::from_ref(&row));
+
+        #[cfg(feature = "sqlite")]
+        {
+            let mut db = Storage::Db { store: PriceStore::in_memory().unwrap(), policy: WritePolicy::Always, written: 0 };
+            assert!(db.write(&record).unwrap());
+            assert_eq!(db.history(&record.asset, at).unwrap(), std::slice::from_ref(&row));
+        }
+
+        let mut metrics = crate::Metrics::new();
+        metrics.observe(&PollEvent::Price(record.clone()));
+        let text = metrics.render();
+        assert!(text.contains("asset_price{asset=\"bitcoin\",currency=\"usd\"} 119458.25\n"), "{text}");
+        assert!(text.contains(&format!("last_fetch_timestamp_seconds {}\n", at.timestamp())));
+
+        assert_eq!(format_price(&record), "2026-01-05T14:03:00Z bitcoin 119458.25 usd (CoinGecko)");
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "asset": "bitcoin",
+                "fetched_at": "2026-01-05T14:03:00Z",
+                "currency": "usd",
+                "price": 119458.25,
+                "source": "CoinGecko",
+            })
+        );
+        assert_eq!(serde_json::from_value::<PriceRow>(json).unwrap(), row);
+    }
+}
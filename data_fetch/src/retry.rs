@@ -0,0 +1,204 @@
+use std::{thread, time::Duration};
+
+/// Waits between attempts; returns false to give up (e.g. on shutdown).
+pub type Sleep = Box<dyn FnMut(Duration) -> bool + Send>;
+
+use crate::{AssetKind, FetchError, PriceSource, Prices};
+
+/// How hard to retry a source that answers 429 or 5xx.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy
+{
+    /// Delay before the first retry; doubles after each failed attempt
+    pub base: Duration,
+    /// Upper bound on any single delay, `Retry-After` included
+    pub max: Duration,
+    /// Retries after the first attempt; 0 disables retrying
+    pub retries: u32,
+}
+
+impl Default for RetryPolicy
+{
+    fn default() -> Self
+    {
+        Self { base: Duration::from_secs(1), max: Duration::from_secs(60), retries: 3 }
+    }
+}
+
+impl RetryPolicy
+{
+    /// Delay before retry number `attempt` (0-based) after `err`: the
+    /// server's `Retry-After` if it sent one, else `base * 2^attempt`,
+    /// capped at `max` either way.
+    pub fn delay(&self, attempt: u32, err: &FetchError) -> Duration
+    {
+        let backoff = err
+            .retry_after()
+            .unwrap_or_else(|| self.base.saturating_mul(2u32.saturating_pow(attempt)));
+        backoff.min(self.max)
+    }
+}
+
+/// Wraps a source so rate limiting and server errors are retried with
+/// backoff. Each wrapper keeps its own state, so a struggling source only
+/// delays its own assets.
+pub struct RetryingSource
+{
+    inner: Box<dyn PriceSource>,
+    policy: RetryPolicy,
+    sleep: Sleep,
+}
+
+impl RetryingSource
+{
+    pub fn new(inner: Box<dyn PriceSource>, policy: RetryPolicy) -> Self
+    {
+        Self::with_sleep(inner, policy, Box::new(|d| {
+            thread::sleep(d);
+            true
+        }))
+    }
+
+    /// Like `new`, with the function used to wait between attempts. If it
+    /// returns false the last error is returned without retrying.
+    pub fn with_sleep(inner: Box<dyn PriceSource>, policy: RetryPolicy, sleep: Sleep) -> Self
+    {
+        Self { inner, policy, sleep }
+    }
+}
+
+impl PriceSource for RetryingSource
+{
+    fn name(&self) -> &str
+    {
+        self.inner.name()
+    }
+
+    fn assets(&self) -> &[AssetKind]
+    {
+        self.inner.assets()
+    }
+
+    fn fetch_prices(&mut self) -> Result<Prices, FetchError>
+    {
+        let mut attempt = 0;
+        loop
+        {
+            match self.inner.fetch_prices()
+            {
+                Err(e) if e.is_retryable() && attempt < self.policy.retries =>
+                {
+                    let delay = self.policy.delay(attempt, &e);
+                    eprintln!("{}: {e}; retrying in {}s", self.inner.name(), delay.as_secs_f64());
+                    if !(self.sleep)(delay)
+                    {
+                        return Err(e);
+                    }
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{CoinGeckoClient, HttpGet, Quote};
+    use rust_decimal_macros::dec;
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+
+    const SIMPLE_PRICE: &str = include_str!("../tests/fixtures/coingecko_simple_price.json");
+
+    /// Plays back canned responses and counts requests.
+    struct Scripted
+    {
+        responses: VecDeque<Result<String, FetchError>>,
+        calls: Arc<Mutex<u32>>,
+    }
+
+    impl HttpGet for Scripted
+    {
+        fn get(&mut self, url: &str) -> Result<String, FetchError>
+        {
+            *self.calls.lock().unwrap() += 1;
+            self.responses.pop_front().unwrap_or_else(|| panic!("unexpected request to {url}"))
+        }
+    }
+
+    fn status(code: u16, retry_after: Option<u64>) -> Result<String, FetchError>
+    {
+        Err(FetchError::Status {
+            url: "https://example.test".into(),
+            code,
+            retry_after: retry_after.map(Duration::from_secs),
+        })
+    }
+
+    type Shared<T> = Arc<Mutex<T>>;
+
+    /// A retrying CoinGecko source over `responses`, plus the recorded
+    /// sleeps and request count.
+    fn scripted(responses: Vec<Result<String, FetchError>>) -> (RetryingSource, Shared<Vec<Duration>>, Shared<u32>)
+    {
+        let calls = Arc::new(Mutex::new(0));
+        let http = Scripted { responses: responses.into(), calls: Arc::clone(&calls) };
+        let client = CoinGeckoClient::with_http(&[AssetKind::coin("bitcoin")], Box::new(http));
+        let slept = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&slept);
+        let source = RetryingSource::with_sleep(
+            Box::new(client),
+            RetryPolicy::default(),
+            Box::new(move |d| {
+                log.lock().unwrap().push(d);
+                true
+            }),
+        );
+        (source, slept, calls)
+    }
+
+    #[test]
+    fn honors_retry_after_then_succeeds()
+    {
+        let (mut source, slept, calls) = scripted(vec![status(429, Some(2)), Ok(SIMPLE_PRICE.to_string())]);
+        let prices = source.fetch_prices().unwrap();
+        assert_eq!(prices[&AssetKind::coin("bitcoin")].as_ref().unwrap(), &vec![Quote::new("usd", dec!(119458.0))]);
+        assert_eq!(*slept.lock().unwrap(), vec![Duration::from_secs(2)]);
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn backs_off_exponentially_then_gives_up()
+    {
+        let (mut source, slept, calls) = scripted((0..4).map(|_| status(503, None)).collect());
+        assert!(matches!(source.fetch_prices(), Err(FetchError::Status { code: 503, .. })));
+        let secs: Vec<u64> = slept.lock().unwrap().iter().map(Duration::as_secs).collect();
+        assert_eq!(secs, vec![1, 2, 4]);
+        assert_eq!(*calls.lock().unwrap(), 4);
+    }
+
+    #[test]
+    fn client_errors_are_not_retried()
+    {
+        let (mut source, slept, calls) = scripted(vec![status(404, None)]);
+        assert!(source.fetch_prices().is_err());
+        assert!(slept.lock().unwrap().is_empty());
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn delays_are_capped()
+    {
+        let policy = RetryPolicy { base: Duration::from_secs(1), max: Duration::from_secs(60), retries: 10 };
+        let err = FetchError::Status { url: String::new(), code: 500, retry_after: None };
+        assert_eq!(policy.delay(3, &err), Duration::from_secs(8));
+        assert_eq!(policy.delay(9, &err), Duration::from_secs(60));
+        let err = FetchError::Status { url: String::new(), code: 429, retry_after: Some(Duration::from_secs(3600)) };
+        assert_eq!(policy.delay(0, &err), Duration::from_secs(60));
+    }
+}
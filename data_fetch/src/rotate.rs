@@ -0,0 +1,262 @@
+use chrono::NaiveDate;
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::PriceRow;
+
+/// When `HistoryWriter` starts a fresh file, and what happens to old ones.
+/// A rotated file is renamed to `<stem>.<date>.csv` after the day of its
+/// first row, with `.1`, `.2`, ... before `.csv` when a day has several.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rotation
+{
+    /// Rotate a file once it has reached this many bytes
+    pub max_bytes: Option<u64>,
+    /// Rotate when a row falls on a later UTC day than the file's first row
+    pub daily: bool,
+    /// Gzip rotated files to `<name>.csv.gz`
+    pub compress: bool,
+    /// Keep only this many rotated files per asset, deleting the oldest
+    pub keep: Option<usize>,
+}
+
+impl Rotation
+{
+    pub fn is_enabled(&self) -> bool
+    {
+        self.max_bytes.is_some() || self.daily
+    }
+}
+
+/// Byte size shorthand: a number with an optional `K`, `M` or `G` suffix
+/// (binary multiples), e.g. `500`, `64K`, `10M`.
+pub fn parse_size(text: &str) -> Result<u64, String>
+{
+    let text = text.trim();
+    let upper = text.to_ascii_uppercase();
+    let digits = upper.trim_end_matches(['B']).trim_end_matches(['K', 'M', 'G']);
+    let multiplier: u64 = match &upper[digits.len()..]
+    {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        _ => return Err(format!("invalid size '{text}' (e.g. 500K, 10M, 1G)")),
+    };
+    let number: u64 = digits.parse().map_err(|_| format!("invalid size '{text}' (e.g. 500K, 10M, 1G)"))?;
+    number.checked_mul(multiplier).filter(|&n| n > 0).ok_or_else(|| format!("invalid size '{text}'"))
+}
+
+/// Day of the first row in the CSV file at `path`; `None` if the file
+/// doesn't exist or has no rows yet.
+pub(crate) fn first_row_date(path: &Path) -> io::Result<Option<NaiveDate>>
+{
+    let file = match File::open(path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    for line in BufReader::new(file).lines().take(2)
+    {
+        if let Some(row) = PriceRow::from_csv(&line?)
+        {
+            return Ok(Some(row.fetched_at.date_naive()));
+        }
+    }
+    Ok(None)
+}
+
+/// Rotated files for `stem` in `dir` as `(date, counter, path)`, oldest first
+fn rotated_files(dir: &Path, stem: &str) -> io::Result<Vec<(NaiveDate, u32, PathBuf)>>
+{
+    let prefix = format!("{stem}.");
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir)?
+    {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let Some(rest) = name.strip_prefix(&prefix) else { continue };
+        let Some(rest) = rest.strip_suffix(".csv.gz").or_else(|| rest.strip_suffix(".csv")) else { continue };
+        let (date, counter) = rest.split_once('.').unwrap_or((rest, "0"));
+        let (Ok(date), Ok(counter)) = (NaiveDate::parse_from_str(date, "%Y-%m-%d"), counter.parse()) else { continue };
+        found.push((date, counter, path));
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Next `<stem>.<date>[.n].csv` in `dir`: one past the highest counter
+/// already used for that day, compressed or not, so names keep sorting in
+/// order even after older ones are pruned.
+fn rotated_name(dir: &Path, stem: &str, date: NaiveDate) -> io::Result<PathBuf>
+{
+    let used = rotated_files(dir, stem)?.into_iter().filter(|(d, _, _)| *d == date).map(|(_, n, _)| n).max();
+    let name = match used
+    {
+        None => format!("{stem}.{date}.csv"),
+        Some(n) => format!("{stem}.{date}.{}.csv", n + 1),
+    };
+    Ok(dir.join(name))
+}
+
+/// Gzip `path` to `<path>.gz` and remove the original. The archive is
+/// written under a temporary name and renamed into place, so a crash
+/// leaves either the plain file or a complete archive.
+fn compress(path: &Path) -> io::Result<PathBuf>
+{
+    let target = path.with_extension("csv.gz");
+    let partial = path.with_extension("csv.gz.partial");
+    let mut encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&partial, &target)?;
+    fs::remove_file(path)?;
+    Ok(target)
+}
+
+/// Move the active file at `path` aside as a rotated file for `date`, then
+/// compress and prune per `rotation`. The move is a single rename, so the
+/// rows are always in one file or the other; the next write starts a fresh
+/// active file.
+pub(crate) fn rotate(path: &Path, stem: &str, date: NaiveDate, rotation: &Rotation) -> io::Result<PathBuf>
+{
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let mut rotated = rotated_name(dir, stem, date)?;
+    fs::rename(path, &rotated)?;
+    if rotation.compress
+    {
+        rotated = compress(&rotated)?;
+    }
+    if let Some(keep) = rotation.keep
+    {
+        let files = rotated_files(dir, stem)?;
+        for (_, _, old) in files.iter().take(files.len().saturating_sub(keep))
+        {
+            fs::remove_file(old)?;
+        }
+    }
+    Ok(rotated)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{HistoryWriter, WritePolicy};
+    use rust_decimal_macros::dec;
+    use chrono::{DateTime, TimeZone, Utc};
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn row(fetched_at: DateTime<Utc>) -> PriceRow
+    {
+        let source = "CoinGecko".to_string();
+        PriceRow { fetched_at, currency: "usd".to_string(), price: dec!(1.5), source, market_at: None }
+    }
+
+    /// `count` rows a minute apart on 5 Jan
+    fn minutes(count: i64) -> Vec<PriceRow>
+    {
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap();
+        (0..count).map(|i| row(start + chrono::Duration::minutes(i))).collect()
+    }
+
+    fn names(dir: &Path) -> Vec<String>
+    {
+        let mut names: Vec<String> =
+            fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+        names.sort();
+        names
+    }
+
+    fn data_rows(text: &str) -> usize
+    {
+        text.lines().filter_map(PriceRow::from_csv).count()
+    }
+
+    /// Header (43 bytes) and two rows (40 each) pass 100 bytes, so each
+    /// file ends up holding two rows
+    const TINY: u64 = 100;
+
+    #[test]
+    fn size_limit_rotates_with_dated_names()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let rotation = Rotation { max_bytes: Some(TINY), ..Rotation::default() };
+        let mut writer = HistoryWriter::new(dir.path(), WritePolicy::Always).rotation(rotation);
+        for row in minutes(6)
+        {
+            writer.write("btc", &row).unwrap();
+        }
+        assert_eq!(names(dir.path()), ["btc.2026-01-05.1.csv", "btc.2026-01-05.csv", "btc.csv"]);
+        for name in names(dir.path())
+        {
+            let text = fs::read_to_string(dir.path().join(&name)).unwrap();
+            assert_eq!(data_rows(&text), 2, "{name}");
+        }
+    }
+
+    #[test]
+    fn compresses_and_keeps_the_newest()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let rotation = Rotation { max_bytes: Some(TINY), compress: true, keep: Some(1), ..Rotation::default() };
+        let mut writer = HistoryWriter::new(dir.path(), WritePolicy::Always).rotation(rotation);
+        let rows = minutes(8);
+        for row in &rows
+        {
+            writer.write("btc", row).unwrap();
+        }
+        assert_eq!(names(dir.path()), ["btc.2026-01-05.2.csv.gz", "btc.csv"]);
+
+        let mut text = String::new();
+        GzDecoder::new(File::open(dir.path().join("btc.2026-01-05.2.csv.gz")).unwrap()).read_to_string(&mut text).unwrap();
+        let kept: Vec<PriceRow> = text.lines().filter_map(PriceRow::from_csv).collect();
+        assert_eq!(kept, rows[4..6].to_vec());
+    }
+
+    #[test]
+    fn daily_rotation_names_the_day_that_ended()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let rotation = Rotation { daily: true, ..Rotation::default() };
+        let mut writer = HistoryWriter::new(dir.path(), WritePolicy::Always).rotation(rotation);
+        let day = |d, h| row(Utc.with_ymd_and_hms(2026, 1, d, h, 0, 0).unwrap());
+        for row in [day(5, 9), day(5, 23), day(6, 0), day(6, 12)]
+        {
+            writer.write("btc", &row).unwrap();
+        }
+        assert_eq!(names(dir.path()), ["btc.2026-01-05.csv", "btc.csv"]);
+        assert_eq!(data_rows(&fs::read_to_string(dir.path().join("btc.csv")).unwrap()), 2);
+    }
+
+    #[test]
+    fn restart_picks_up_the_active_file_day()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let rotation = Rotation { daily: true, ..Rotation::default() };
+        let rows = minutes(1);
+        HistoryWriter::new(dir.path(), WritePolicy::Always).write("btc", &rows[0]).unwrap();
+
+        let mut restarted = HistoryWriter::new(dir.path(), WritePolicy::Always).rotation(rotation);
+        restarted.write("btc", &row(Utc.with_ymd_and_hms(2026, 1, 6, 0, 0, 0).unwrap())).unwrap();
+        assert_eq!(names(dir.path()), ["btc.2026-01-05.csv", "btc.csv"]);
+    }
+
+    #[test]
+    fn sizes()
+    {
+        assert_eq!(parse_size("500"), Ok(500));
+        assert_eq!(parse_size("64k"), Ok(64 * 1024));
+        assert_eq!(parse_size(" 10MB "), Ok(10 << 20));
+        assert_eq!(parse_size("1G"), Ok(1 << 30));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("M").is_err());
+    }
+}
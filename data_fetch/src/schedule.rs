@@ -0,0 +1,183 @@
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::{America::New_York, Tz};
+use std::time::Duration;
+
+/// Wait after the closing bell before the day's last fetch, so the chart
+/// carries the settled closing price
+const AFTER_CLOSE: chrono::Duration = chrono::Duration::minutes(5);
+
+/// A market's regular session: weekdays from `open` to `close`, local time
+/// in `tz`. Exchange holidays aren't known, so they count as open days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketHours
+{
+    pub tz: Tz,
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+}
+
+impl MarketHours
+{
+    /// NYSE and Nasdaq: 09:30 to 16:00 New York time
+    pub fn us() -> Self
+    {
+        Self {
+            tz: New_York,
+            open: NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            close: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+        }
+    }
+
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool
+    {
+        let local = at.with_timezone(&self.tz);
+        is_weekday(local.date_naive()) && (self.open..self.close).contains(&local.time())
+    }
+
+    /// `at` if the market is open then, else the start of the next session
+    pub fn next_open(&self, at: DateTime<Utc>) -> DateTime<Utc>
+    {
+        if self.is_open(at)
+        {
+            return at;
+        }
+        let local = at.with_timezone(&self.tz);
+        let mut day = local.date_naive();
+        if local.time() >= self.open
+        {
+            day = day.succ_opt().unwrap();
+        }
+        while !is_weekday(day)
+        {
+            day = day.succ_opt().unwrap();
+        }
+        self.at(day, self.open)
+    }
+
+    /// The close of the local day `at` falls on
+    fn close_of(&self, at: DateTime<Utc>) -> DateTime<Utc>
+    {
+        self.at(at.with_timezone(&self.tz).date_naive(), self.close)
+    }
+
+    fn at(&self, day: NaiveDate, time: NaiveTime) -> DateTime<Utc>
+    {
+        // Sessions don't straddle the small-hours DST changes, so the local
+        // time always exists
+        self.tz.from_local_datetime(&day.and_time(time)).earliest().unwrap().with_timezone(&Utc)
+    }
+}
+
+fn is_weekday(day: NaiveDate) -> bool
+{
+    !matches!(day.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// How often a source fetches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule
+{
+    pub interval: Duration,
+    /// Only fetch while this market is open
+    pub market_hours: Option<MarketHours>,
+}
+
+impl Schedule
+{
+    pub fn every(interval: Duration) -> Self
+    {
+        Self { interval, market_hours: None }
+    }
+
+    pub fn during(mut self, hours: MarketHours) -> Self
+    {
+        self.market_hours = Some(hours);
+        self
+    }
+
+    /// When to start the round after one that started at `last`; see
+    /// [`next_due`].
+    pub fn next_due(&self, last: DateTime<Utc>) -> DateTime<Utc>
+    {
+        next_due(last, self.interval, self.market_hours.as_ref())
+    }
+}
+
+/// When to start the round after one that started at `last`: `interval`
+/// later. With `hours`, rounds that would fall while the market is closed
+/// wait for the next open instead, except for one just after the close to
+/// record the closing price.
+pub fn next_due(last: DateTime<Utc>, interval: Duration, hours: Option<&MarketHours>) -> DateTime<Utc>
+{
+    let interval = chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::MAX);
+    let due = last.checked_add_signed(interval).unwrap_or(DateTime::<Utc>::MAX_UTC);
+    let Some(hours) = hours else { return due };
+    if hours.is_open(due)
+    {
+        return due;
+    }
+    if hours.is_open(last)
+    {
+        return hours.close_of(last) + AFTER_CLOSE;
+    }
+    hours.next_open(due)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// New York time on a day in August 2025 (EDT, UTC-4); the 4th was a Monday
+    fn ny(day: u32, h: u32, m: u32) -> DateTime<Utc>
+    {
+        New_York.with_ymd_and_hms(2025, 8, day, h, m, 0).unwrap().with_timezone(&Utc)
+    }
+
+    const FIVE_MINUTES: Duration = Duration::from_secs(300);
+
+    #[test]
+    fn without_market_hours_it_is_just_the_interval()
+    {
+        let last = ny(9, 3, 0);
+        assert_eq!(next_due(last, FIVE_MINUTES, None), ny(9, 3, 5));
+    }
+
+    #[test]
+    fn open_market_keeps_the_interval()
+    {
+        let us = MarketHours::us();
+        assert_eq!(next_due(ny(5, 9, 30), FIVE_MINUTES, Some(&us)), ny(5, 9, 35));
+        assert_eq!(next_due(ny(5, 15, 50), FIVE_MINUTES, Some(&us)), ny(5, 15, 55));
+    }
+
+    #[test]
+    fn one_fetch_after_the_close_then_wait_for_the_open()
+    {
+        let us = MarketHours::us();
+        let closing = next_due(ny(5, 15, 58), FIVE_MINUTES, Some(&us));
+        assert_eq!(closing, ny(5, 16, 5));
+        assert_eq!(next_due(closing, FIVE_MINUTES, Some(&us)), ny(6, 9, 30));
+    }
+
+    #[test]
+    fn weekends_are_skipped()
+    {
+        let us = MarketHours::us();
+        // Friday evening and Saturday both wait for Monday's open
+        assert_eq!(next_due(ny(8, 16, 5), FIVE_MINUTES, Some(&us)), ny(11, 9, 30));
+        assert_eq!(next_due(ny(9, 12, 0), FIVE_MINUTES, Some(&us)), ny(11, 9, 30));
+        // Early Monday waits for the same morning
+        assert_eq!(next_due(ny(11, 4, 0), FIVE_MINUTES, Some(&us)), ny(11, 9, 30));
+    }
+
+    #[test]
+    fn session_follows_daylight_saving()
+    {
+        let us = MarketHours::us();
+        // 09:30 in New York is 13:30 UTC in summer and 14:30 UTC in winter
+        assert!(us.is_open(Utc.with_ymd_and_hms(2025, 8, 5, 13, 30, 0).unwrap()));
+        assert!(!us.is_open(Utc.with_ymd_and_hms(2025, 12, 2, 13, 30, 0).unwrap()));
+        assert_eq!(us.next_open(Utc.with_ymd_and_hms(2025, 12, 2, 13, 30, 0).unwrap()), Utc.with_ymd_and_hms(2025, 12, 2, 14, 30, 0).unwrap());
+    }
+}
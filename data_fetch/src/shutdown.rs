@@ -0,0 +1,89 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often a cancellable sleep checks the token
+const SLICE: Duration = Duration::from_millis(50);
+
+/// Graceful shutdown token.
+/// Cancels new fetches and lets the in-flight one finish and be written.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown
+{
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Shutdown
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    pub fn cancel(&self)
+    {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool
+    {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Sleep for `d` in short slices, waking early once cancelled.
+    /// Returns false when interrupted.
+    pub fn sleep(&self, d: Duration) -> bool
+    {
+        let deadline = Instant::now() + d;
+        loop
+        {
+            if self.is_cancelled()
+            {
+                return false;
+            }
+            let now = Instant::now();
+            if now >= deadline
+            {
+                return true;
+            }
+            thread::sleep((deadline - now).min(SLICE));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn sleep_returns_early_when_cancelled()
+    {
+        let shutdown = Shutdown::new();
+        let s = shutdown.clone();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            s.cancel();
+        });
+        let started = Instant::now();
+        assert!(!shutdown.sleep(Duration::from_secs(30)));
+        assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+        canceller.join().unwrap();
+
+        // Already cancelled: doesn't sleep at all
+        assert!(!shutdown.sleep(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn sleep_runs_to_completion_otherwise()
+    {
+        let started = Instant::now();
+        assert!(Shutdown::new().sleep(Duration::from_millis(120)));
+        assert!(started.elapsed() >= Duration::from_millis(120));
+    }
+}
@@ -0,0 +1,745 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    binance_symbol, check_fresh, coin_price, coinbase_pair, parse_binance_price, parse_coinbase_spot, parse_yahoo_quote,
+    yahoo_chart_url, AssetKind, CoinGeckoResponse, Decimal, FallbackSource, FetchError, HttpGet, Quote, UreqHttp,
+    DEFAULT_CURRENCY, DEFAULT_TIMEOUT, USER_AGENT,
+};
+use chrono::Utc;
+use std::{fmt, str::FromStr, time::Duration};
+
+pub(crate) const COINGECKO_BASE_URL: &str = "https://api.coingecko.com/api/v3";
+/// CoinGecko's paid tier, used when there's an API key but no base URL
+pub(crate) const COINGECKO_PRO_BASE_URL: &str = "https://pro-api.coingecko.com/api/v3";
+pub(crate) const BINANCE_BASE_URL: &str = "https://api.binance.com";
+pub(crate) const COINBASE_BASE_URL: &str = "https://api.coinbase.com";
+pub(crate) const YAHOO_BASE_URL: &str = "https://query2.finance.yahoo.com";
+
+/// Where a source sends its requests and the key it sends with them.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SourceConfig
+{
+    /// Replaces the public API's scheme, host and path prefix, e.g. a
+    /// paid tier or a local mock server
+    pub base_url: Option<String>,
+    /// Sent in the source's API key header
+    pub api_key: Option<String>,
+}
+
+impl SourceConfig
+{
+    /// `<PREFIX>_BASE_URL` and `<PREFIX>_API_KEY` from the environment;
+    /// empty values count as unset.
+    pub fn from_env(prefix: &str) -> Self
+    {
+        let var = |name: &str| std::env::var(format!("{prefix}_{name}")).ok().filter(|v| !v.trim().is_empty());
+        Self { base_url: var("BASE_URL"), api_key: var("API_KEY") }
+    }
+
+    /// The configured base URL without a trailing slash, or `default`
+    pub(crate) fn base_or<'a>(&'a self, default: &'a str) -> &'a str
+    {
+        self.base_url.as_deref().map_or(default, |url| url.trim_end_matches('/'))
+    }
+
+    /// CoinGecko's base URL: the configured one, else the paid tier's when
+    /// there's a key and the public one when there isn't
+    pub(crate) fn coingecko_base(&self) -> &str
+    {
+        self.base_or(if self.api_key.is_some() { COINGECKO_PRO_BASE_URL } else { COINGECKO_BASE_URL })
+    }
+
+    /// The key as a `header: value` pair, if there is one
+    pub(crate) fn key_header(&self, header: &'static str) -> Option<(&'static str, &str)>
+    {
+        self.api_key.as_deref().map(|key| (header, key))
+    }
+}
+
+/// Keeps the key out of logs and error messages
+impl fmt::Debug for SourceConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.debug_struct("SourceConfig")
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// Per-asset outcome of one fetch from a source: a quote per currency.
+pub type Prices = BTreeMap<AssetKind, Result<Vec<Quote>, FetchError>>;
+
+/// Somewhere prices come from. One `fetch_prices` call covers every asset
+/// the source serves; an `Err` means the whole request failed, while a
+/// per-asset `Err` only affects that asset.
+pub trait PriceSource: Send
+{
+    fn name(&self) -> &str;
+    fn assets(&self) -> &[AssetKind];
+    fn fetch_prices(&mut self) -> Result<Prices, FetchError>;
+}
+
+/// Prices all CoinGecko assets, in every configured currency, with a single
+/// `simple/price` request.
+pub struct CoinGeckoClient
+{
+    assets: Vec<AssetKind>,
+    currencies: Vec<String>,
+    config: SourceConfig,
+    http: Box<dyn HttpGet>,
+}
+
+impl CoinGeckoClient
+{
+    /// Client for the given assets; ones CoinGecko doesn't price are ignored.
+    pub fn new(assets: &[AssetKind]) -> Self
+    {
+        Self::with_http(assets, Box::new(UreqHttp::default()))
+    }
+
+    pub fn with_http(assets: &[AssetKind], http: Box<dyn HttpGet>) -> Self
+    {
+        let assets = assets.iter().filter(|a| a.coingecko_id().is_some()).cloned().collect();
+        Self { assets, currencies: vec![DEFAULT_CURRENCY.to_string()], config: SourceConfig::default(), http }
+    }
+
+    /// Quote in these currencies (`usd`, `eur`, ...) instead of just USD.
+    /// Codes are passed through to CoinGecko; ones it doesn't know come back
+    /// as per-asset `MissingField` errors.
+    pub fn currencies(mut self, currencies: &[String]) -> Self
+    {
+        self.currencies = currencies_or_default(currencies);
+        self
+    }
+
+    /// Send requests to `config.base_url` and with its API key. A key
+    /// without a base URL means the paid tier.
+    pub fn config(mut self, config: SourceConfig) -> Self
+    {
+        self.config = config;
+        self
+    }
+
+    pub fn url(&self) -> String
+    {
+        let ids: Vec<&str> = self.assets.iter().filter_map(|a| a.coingecko_id()).collect();
+        format!(
+            "{}/simple/price?ids={}&vs_currencies={}",
+            self.config.coingecko_base(),
+            ids.join(","),
+            self.currencies.join(",")
+        )
+    }
+}
+
+/// Split a batched CoinGecko response into per-asset quotes. Coins missing
+/// from the response (CoinGecko silently drops unknown ids), or missing one
+/// of the currencies, get a `MissingField` error of their own.
+pub fn split_coingecko(body: &str, assets: &[AssetKind], currencies: &[String]) -> Result<Prices, FetchError>
+{
+    let parsed: CoinGeckoResponse = serde_json::from_str(body)?;
+    let quotes = |id: &str| -> Result<Vec<Quote>, FetchError> {
+        currencies
+            .iter()
+            .map(|currency| Ok(Quote::new(currency.as_str(), coin_price(&parsed, id, currency)?)))
+            .collect()
+    };
+    Ok(assets
+        .iter()
+        .filter_map(|asset| asset.coingecko_id().map(|id| (asset.clone(), quotes(id))))
+        .collect())
+}
+
+impl PriceSource for CoinGeckoClient
+{
+    fn name(&self) -> &str
+    {
+        "CoinGecko"
+    }
+
+    fn assets(&self) -> &[AssetKind]
+    {
+        &self.assets
+    }
+
+    fn fetch_prices(&mut self) -> Result<Prices, FetchError>
+    {
+        let url = self.url();
+        let key = self.config.key_header("x-cg-pro-api-key");
+        split_coingecko(&self.http.get_with_headers(&url, key.as_slice())?, &self.assets, &self.currencies)
+    }
+}
+
+/// Quote one coin in each currency with one request per currency, through
+/// `price`. Used by the exchanges that price a single pair per request.
+fn quote_each(
+    currencies: &[String],
+    mut price: impl FnMut(&str) -> Result<Decimal, FetchError>,
+) -> Result<Vec<Quote>, FetchError>
+{
+    currencies.iter().map(|currency| Ok(Quote::new(currency.as_str(), price(currency)?))).collect()
+}
+
+/// One coin from Binance's public ticker. USD is quoted from the USDT pair.
+pub struct BinanceClient
+{
+    assets: [AssetKind; 1],
+    currencies: Vec<String>,
+    config: SourceConfig,
+    http: Box<dyn HttpGet>,
+}
+
+impl BinanceClient
+{
+    pub fn with_http(coin: &str, currencies: &[String], http: Box<dyn HttpGet>) -> Self
+    {
+        let currencies = currencies_or_default(currencies);
+        Self { assets: [AssetKind::coin(coin)], currencies, config: SourceConfig::default(), http }
+    }
+
+    pub fn config(mut self, config: SourceConfig) -> Self
+    {
+        self.config = config;
+        self
+    }
+
+    pub fn url(&self, currency: &str) -> String
+    {
+        let coin = self.assets[0].coingecko_id().unwrap_or_default();
+        let base = self.config.base_or(BINANCE_BASE_URL);
+        format!("{base}/api/v3/ticker/price?symbol={}", binance_symbol(coin, currency))
+    }
+}
+
+impl PriceSource for BinanceClient
+{
+    fn name(&self) -> &str
+    {
+        "Binance"
+    }
+
+    fn assets(&self) -> &[AssetKind]
+    {
+        &self.assets
+    }
+
+    fn fetch_prices(&mut self) -> Result<Prices, FetchError>
+    {
+        let currencies = self.currencies.clone();
+        let quotes = quote_each(&currencies, |currency| {
+            let url = self.url(currency);
+            let key = self.config.key_header("X-MBX-APIKEY");
+            parse_binance_price(&self.http.get_with_headers(&url, key.as_slice())?)
+        })?;
+        Ok(BTreeMap::from([(self.assets[0].clone(), Ok(quotes))]))
+    }
+}
+
+/// One coin from Coinbase's spot price endpoint.
+pub struct CoinbaseClient
+{
+    assets: [AssetKind; 1],
+    currencies: Vec<String>,
+    config: SourceConfig,
+    http: Box<dyn HttpGet>,
+}
+
+impl CoinbaseClient
+{
+    pub fn with_http(coin: &str, currencies: &[String], http: Box<dyn HttpGet>) -> Self
+    {
+        let currencies = currencies_or_default(currencies);
+        Self { assets: [AssetKind::coin(coin)], currencies, config: SourceConfig::default(), http }
+    }
+
+    /// Send requests to `config.base_url`. The spot price endpoint is
+    /// public, so there is no key to send.
+    pub fn config(mut self, config: SourceConfig) -> Self
+    {
+        self.config = config;
+        self
+    }
+
+    pub fn url(&self, currency: &str) -> String
+    {
+        let coin = self.assets[0].coingecko_id().unwrap_or_default();
+        let base = self.config.base_or(COINBASE_BASE_URL);
+        format!("{base}/v2/prices/{}/spot", coinbase_pair(coin, currency))
+    }
+}
+
+impl PriceSource for CoinbaseClient
+{
+    fn name(&self) -> &str
+    {
+        "Coinbase"
+    }
+
+    fn assets(&self) -> &[AssetKind]
+    {
+        &self.assets
+    }
+
+    fn fetch_prices(&mut self) -> Result<Prices, FetchError>
+    {
+        let currencies = self.currencies.clone();
+        let quotes = quote_each(&currencies, |currency| {
+            let url = self.url(currency);
+            parse_coinbase_spot(&self.http.get(&url)?)
+        })?;
+        Ok(BTreeMap::from([(self.assets[0].clone(), Ok(quotes))]))
+    }
+}
+
+fn currencies_or_default(currencies: &[String]) -> Vec<String>
+{
+    if currencies.is_empty()
+    {
+        return vec![DEFAULT_CURRENCY.to_string()];
+    }
+    currencies.iter().map(|c| c.trim().to_ascii_lowercase()).collect()
+}
+
+/// Latest close for one symbol from Yahoo Finance. The chart endpoint takes
+/// a single symbol, so each ticker is its own source.
+pub struct YahooClient
+{
+    assets: [AssetKind; 1],
+    config: SourceConfig,
+    max_staleness: Option<Duration>,
+    http: Box<dyn HttpGet>,
+}
+
+impl YahooClient
+{
+    pub fn new(symbol: &str) -> Self
+    {
+        Self::with_http(symbol, Box::new(UreqHttp::default()))
+    }
+
+    pub fn with_http(symbol: &str, http: Box<dyn HttpGet>) -> Self
+    {
+        Self::for_asset(AssetKind::ticker(symbol), http)
+    }
+
+    /// Client for any asset with a Yahoo symbol, such as a metal's futures
+    /// contract; prices are reported under `asset` itself.
+    pub fn for_asset(asset: AssetKind, http: Box<dyn HttpGet>) -> Self
+    {
+        Self { assets: [asset], config: SourceConfig::default(), max_staleness: None, http }
+    }
+
+    /// Report closes older than `max` as `Stale` errors instead of prices.
+    /// Yahoo keeps serving the last close while a market is shut, and
+    /// sometimes lags for hours while it's open.
+    pub fn max_staleness(mut self, max: Option<Duration>) -> Self
+    {
+        self.max_staleness = max;
+        self
+    }
+
+    /// Send requests to `config.base_url`; Yahoo takes no key.
+    pub fn config(mut self, config: SourceConfig) -> Self
+    {
+        self.config = config;
+        self
+    }
+
+    fn symbol(&self) -> &str
+    {
+        self.assets[0].yahoo_symbol().unwrap_or_default()
+    }
+}
+
+impl PriceSource for YahooClient
+{
+    fn name(&self) -> &str
+    {
+        "Yahoo Finance"
+    }
+
+    fn assets(&self) -> &[AssetKind]
+    {
+        &self.assets
+    }
+
+    fn fetch_prices(&mut self) -> Result<Prices, FetchError>
+    {
+        let url = yahoo_chart_url(self.config.base_or(YAHOO_BASE_URL), self.symbol());
+        let mut quote = parse_yahoo_quote(&self.http.get(&url)?);
+        if let Some(max) = self.max_staleness
+        {
+            quote = quote.and_then(|q| check_fresh(q, max, Utc::now()));
+        }
+        Ok(BTreeMap::from([(self.assets[0].clone(), quote.map(|q| vec![q]))]))
+    }
+}
+
+/// Where coin prices can come from, for `SourceOptions::coin_sources`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoinProvider
+{
+    CoinGecko,
+    Binance,
+    Coinbase,
+}
+
+impl CoinProvider
+{
+    pub const ALL: [CoinProvider; 3] = [CoinProvider::CoinGecko, CoinProvider::Binance, CoinProvider::Coinbase];
+}
+
+impl fmt::Display for CoinProvider
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let name = match self
+        {
+            CoinProvider::CoinGecko => "coingecko",
+            CoinProvider::Binance => "binance",
+            CoinProvider::Coinbase => "coinbase",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for CoinProvider
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        let name = s.trim().to_ascii_lowercase();
+        CoinProvider::ALL
+            .into_iter()
+            .find(|p| p.to_string() == name)
+            .ok_or_else(|| format!("unknown source '{}' (valid: coingecko, binance, coinbase)", s.trim()))
+    }
+}
+
+/// How `sources_for` sets up the sources.
+#[derive(Debug, Clone)]
+pub struct SourceOptions
+{
+    /// Currencies to quote coins in (USD if empty); tickers are always in
+    /// their own currency
+    pub currencies: Vec<String>,
+    /// Time allowed for each request
+    pub timeout: Duration,
+    pub user_agent: String,
+    /// Where to get coin prices, in the order to try them
+    pub coin_sources: Vec<CoinProvider>,
+    /// Skip ticker prices older than this (see `YahooClient::max_staleness`)
+    pub max_staleness: Option<Duration>,
+    pub coingecko: SourceConfig,
+    pub binance: SourceConfig,
+    pub coinbase: SourceConfig,
+    pub yahoo: SourceConfig,
+}
+
+impl Default for SourceOptions
+{
+    fn default() -> Self
+    {
+        Self {
+            currencies: vec![DEFAULT_CURRENCY.to_string()],
+            timeout: DEFAULT_TIMEOUT,
+            user_agent: USER_AGENT.to_string(),
+            coin_sources: CoinProvider::ALL.to_vec(),
+            max_staleness: None,
+            coingecko: SourceConfig::default(),
+            binance: SourceConfig::default(),
+            coinbase: SourceConfig::default(),
+            yahoo: SourceConfig::default(),
+        }
+    }
+}
+
+impl SourceOptions
+{
+    /// Base URLs and API keys from `COINGECKO_*`, `BINANCE_*`, `COINBASE_*`
+    /// and `YAHOO_*` (see [`SourceConfig::from_env`]).
+    pub fn configs_from_env(mut self) -> Self
+    {
+        self.coingecko = SourceConfig::from_env("COINGECKO");
+        self.binance = SourceConfig::from_env("BINANCE");
+        self.coinbase = SourceConfig::from_env("COINBASE");
+        self.yahoo = SourceConfig::from_env("YAHOO");
+        self
+    }
+}
+
+/// The sources needed to price `assets`: one for all coins, which asks each
+/// of `coin_sources` in turn for whatever the ones before it couldn't price
+/// (CoinGecko fetches every coin in one request; the exchanges one per
+/// coin), and one per Yahoo ticker.
+pub fn sources_for(assets: &[AssetKind], options: &SourceOptions) -> Vec<Box<dyn PriceSource>>
+{
+    sources_with_http(assets, options, || Box::new(UreqHttp::with_user_agent(options.timeout, &options.user_agent)))
+}
+
+/// `sources_for`, with every client going through an `HttpGet` from `http`
+/// (say, a `ScriptedHttp`) instead of the network. `options.timeout` and
+/// `options.user_agent` are left to it.
+pub fn sources_with_http(
+    assets: &[AssetKind],
+    options: &SourceOptions,
+    http: impl Fn() -> Box<dyn HttpGet>,
+) -> Vec<Box<dyn PriceSource>>
+{
+    let mut sources: Vec<Box<dyn PriceSource>> = Vec::new();
+    let coins: Vec<&str> = assets.iter().filter_map(AssetKind::coingecko_id).collect();
+    let mut chain: Vec<Box<dyn PriceSource>> = Vec::new();
+    for provider in &options.coin_sources
+    {
+        match provider
+        {
+            CoinProvider::CoinGecko if !coins.is_empty() =>
+            {
+                let client = CoinGeckoClient::with_http(assets, http()).currencies(&options.currencies);
+                chain.push(Box::new(client.config(options.coingecko.clone())));
+            }
+            CoinProvider::CoinGecko => {}
+            CoinProvider::Binance =>
+            {
+                for coin in &coins
+                {
+                    let client = BinanceClient::with_http(coin, &options.currencies, http());
+                    chain.push(Box::new(client.config(options.binance.clone())));
+                }
+            }
+            CoinProvider::Coinbase =>
+            {
+                for coin in &coins
+                {
+                    let client = CoinbaseClient::with_http(coin, &options.currencies, http());
+                    chain.push(Box::new(client.config(options.coinbase.clone())));
+                }
+            }
+        }
+    }
+    if chain.len() == 1
+    {
+        sources.append(&mut chain);
+    }
+    else if !chain.is_empty()
+    {
+        sources.push(Box::new(FallbackSource::new(chain)));
+    }
+    for asset in assets.iter().filter(|a| a.yahoo_symbol().is_some())
+    {
+        let client = YahooClient::for_asset(asset.clone(), http()).config(options.yahoo.clone());
+        sources.push(Box::new(client.max_staleness(options.max_staleness)));
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::ScriptedHttp;
+    use rust_decimal_macros::dec;
+
+    const PARTIAL: &str = include_str!("../tests/fixtures/coingecko_missing_ethereum.json");
+    const MULTI: &str = include_str!("../tests/fixtures/coingecko_multi.json");
+    const MULTI_FIAT: &str = include_str!("../tests/fixtures/coingecko_multi_currency.json");
+    const BINANCE: &str = include_str!("../tests/fixtures/binance_ticker_price.json");
+
+    fn usd() -> Vec<String>
+    {
+        vec!["usd".to_string()]
+    }
+
+    /// The single USD price from an asset's quotes
+    fn usd_price(prices: &Prices, asset: &AssetKind) -> Option<Decimal>
+    {
+        match prices[asset].as_deref()
+        {
+            Ok([quote]) if quote.currency == "usd" => Some(quote.price),
+            _ => None,
+        }
+    }
+
+    fn coins(ids: &[&str]) -> Vec<AssetKind>
+    {
+        ids.iter().map(|id| AssetKind::coin(id)).collect()
+    }
+
+    #[test]
+    fn one_request_for_all_coins()
+    {
+        let client = CoinGeckoClient::new(&[AssetKind::coin("bitcoin"), AssetKind::sp500(), AssetKind::coin("solana")]);
+        assert_eq!(client.assets(), coins(&["bitcoin", "solana"]));
+        assert_eq!(
+            client.url(),
+            "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin,solana&vs_currencies=usd"
+        );
+    }
+
+    #[test]
+    fn missing_coin_only_fails_that_asset()
+    {
+        let prices = split_coingecko(PARTIAL, &coins(&["bitcoin", "ethereum"]), &usd()).unwrap();
+        assert_eq!(prices.len(), 2);
+        assert_eq!(usd_price(&prices, &AssetKind::coin("bitcoin")), Some(dec!(119458.0)));
+        assert!(matches!(
+            prices[&AssetKind::coin("ethereum")],
+            Err(FetchError::MissingField(ref f)) if f == "ethereum.usd"
+        ));
+    }
+
+    #[test]
+    fn splits_many_coins_and_flags_unknown_ids()
+    {
+        let assets = coins(&["bitcoin", "ethereum", "solana", "dogecoin", "not-a-coin"]);
+        let prices = split_coingecko(MULTI, &assets, &usd()).unwrap();
+        let price = |id: &str| usd_price(&prices, &AssetKind::coin(id));
+        assert_eq!(price("bitcoin"), Some(dec!(119458.0)));
+        assert_eq!(price("ethereum"), Some(dec!(3725.51)));
+        assert_eq!(price("solana"), Some(dec!(178.42)));
+        assert_eq!(price("dogecoin"), Some(dec!(0.2213)));
+        assert!(matches!(
+            prices[&AssetKind::coin("not-a-coin")],
+            Err(FetchError::MissingField(ref f)) if f == "not-a-coin.usd"
+        ));
+    }
+
+    #[test]
+    fn one_quote_per_currency()
+    {
+        let currencies: Vec<String> = ["usd", "EUR"].map(String::from).to_vec();
+        let client = CoinGeckoClient::new(&coins(&["bitcoin", "ethereum"])).currencies(&currencies);
+        assert!(client.url().ends_with("ids=bitcoin,ethereum&vs_currencies=usd,eur"), "{}", client.url());
+
+        let prices = split_coingecko(MULTI_FIAT, client.assets(), &client.currencies).unwrap();
+        assert_eq!(
+            prices[&AssetKind::coin("bitcoin")].as_ref().unwrap(),
+            &vec![Quote::new("usd", dec!(119458.0)), Quote::new("eur", dec!(102331.0))]
+        );
+
+        let odd = vec!["usd".to_string(), "xyz".to_string()];
+        let prices = split_coingecko(MULTI_FIAT, &coins(&["ethereum"]), &odd).unwrap();
+        assert!(matches!(
+            prices[&AssetKind::coin("ethereum")],
+            Err(FetchError::MissingField(ref f)) if f == "ethereum.xyz"
+        ));
+    }
+
+    #[test]
+    fn sources_group_by_provider()
+    {
+        let names = |assets: &[AssetKind]| -> Vec<String> {
+            sources_for(assets, &SourceOptions::default()).iter().map(|s| s.name().to_string()).collect()
+        };
+        assert_eq!(names(&AssetKind::defaults()), vec!["CoinGecko", "Yahoo Finance"]);
+        assert_eq!(names(&[AssetKind::sp500()]), vec!["Yahoo Finance"]);
+        assert_eq!(
+            names(&[AssetKind::ticker("aapl"), AssetKind::coin("solana"), AssetKind::ticker("^ixic")]),
+            vec!["CoinGecko", "Yahoo Finance", "Yahoo Finance"]
+        );
+        assert_eq!(names(&coins(&["ethereum"])), vec!["CoinGecko"]);
+    }
+
+    #[test]
+    fn coin_sources_set_the_order()
+    {
+        assert_eq!("Binance".parse(), Ok(CoinProvider::Binance));
+        assert_eq!("kraken".parse::<CoinProvider>().unwrap_err(), "unknown source 'kraken' (valid: coingecko, binance, coinbase)");
+
+        let options = SourceOptions { coin_sources: vec![CoinProvider::Coinbase, CoinProvider::CoinGecko], ..Default::default() };
+        let sources = sources_for(&coins(&["bitcoin", "solana"]), &options);
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].name(), "Coinbase");
+        assert_eq!(sources[0].assets(), coins(&["bitcoin", "solana"]));
+    }
+
+    #[test]
+    fn base_urls_and_keys()
+    {
+        let local = SourceConfig { base_url: Some("http://127.0.0.1:9000/".to_string()), api_key: None };
+        let client = BinanceClient::with_http("bitcoin", &usd(), Box::new(UreqHttp::default())).config(local.clone());
+        assert_eq!(client.url("usd"), "http://127.0.0.1:9000/api/v3/ticker/price?symbol=BTCUSDT");
+        let client = CoinbaseClient::with_http("bitcoin", &usd(), Box::new(UreqHttp::default())).config(local);
+        assert_eq!(client.url("eur"), "http://127.0.0.1:9000/v2/prices/BTC-EUR/spot");
+
+        // A key alone means the paid tier
+        let pro = SourceConfig { base_url: None, api_key: Some("CG-secret".to_string()) };
+        let client = CoinGeckoClient::new(&coins(&["bitcoin"])).config(pro.clone());
+        assert_eq!(client.url(), "https://pro-api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=usd");
+
+        let debug = format!("{pro:?}");
+        assert!(!debug.contains("CG-secret"), "{debug}");
+        assert!(debug.contains("<redacted>"), "{debug}");
+    }
+
+    #[test]
+    fn each_client_asks_its_own_url()
+    {
+        // Binance stops at the first failed currency, so let it succeed
+        let http = ScriptedHttp::new().respond("https://api.binance.com", BINANCE);
+        let keyed = SourceConfig { base_url: None, api_key: Some("key".to_string()) };
+        let fiat = vec!["usd".to_string(), "eur".to_string()];
+        let coingecko = CoinGeckoClient::with_http(&coins(&["bitcoin", "ethereum"]), Box::new(http.clone()));
+        let mut sources: Vec<Box<dyn PriceSource>> = vec![
+            Box::new(coingecko.currencies(&fiat).config(keyed.clone())),
+            Box::new(BinanceClient::with_http("ethereum", &fiat, Box::new(http.clone())).config(keyed)),
+            Box::new(CoinbaseClient::with_http("solana", &usd(), Box::new(http.clone()))),
+            Box::new(YahooClient::with_http("^IXIC", Box::new(http.clone()))),
+        ];
+        for source in &mut sources
+        {
+            let _ = source.fetch_prices();
+        }
+        let requests = http.requests();
+        let urls: Vec<&str> = requests.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(urls, [
+            "https://pro-api.coingecko.com/api/v3/simple/price?ids=bitcoin,ethereum&vs_currencies=usd,eur",
+            "https://api.binance.com/api/v3/ticker/price?symbol=ETHUSDT",
+            "https://api.binance.com/api/v3/ticker/price?symbol=ETHEUR",
+            "https://api.coinbase.com/v2/prices/SOL-USD/spot",
+            "https://query2.finance.yahoo.com/v8/finance/chart/%5EIXIC",
+        ]);
+        let key = |name: &str| vec![(name.to_string(), "key".to_string())];
+        assert_eq!(requests[0].headers, key("x-cg-pro-api-key"));
+        assert_eq!(requests[1].headers, key("X-MBX-APIKEY"));
+        assert!(requests[3].headers.is_empty() && requests[4].headers.is_empty());
+    }
+
+    #[test]
+    fn stale_ticker_prices_are_skipped()
+    {
+        const STALE: &str = include_str!("../tests/fixtures/yahoo_chart_stale.json");
+        let gspc = AssetKind::sp500();
+
+        // The fixture is from 2025, so any sensible limit rejects it
+        let hour = Some(Duration::from_secs(3600));
+        let http = ScriptedHttp::new().respond("", STALE);
+        let mut client = YahooClient::with_http("^GSPC", Box::new(http.clone())).max_staleness(hour);
+        let prices = client.fetch_prices().unwrap();
+        assert!(matches!(prices[&gspc], Err(FetchError::Stale { .. })), "{:?}", prices[&gspc]);
+
+        let mut client = YahooClient::with_http("^GSPC", Box::new(http));
+        let prices = client.fetch_prices().unwrap();
+        let quote = &prices[&gspc].as_ref().unwrap()[0];
+        assert_eq!(quote.market_at.map(|at| at.timestamp()), Some(1754337600));
+    }
+
+    #[test]
+    fn metals_come_from_their_futures_under_their_own_name()
+    {
+        const GOLD: &str = include_str!("../tests/fixtures/yahoo_chart_gold.json");
+        let http = ScriptedHttp::new().respond("", GOLD);
+        let gold = AssetKind::Metal(crate::Metal::Gold);
+        let assets = [AssetKind::coin("bitcoin"), gold.clone()];
+        let mut sources = sources_with_http(&assets, &SourceOptions::default(), || Box::new(http.clone()));
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[1].assets(), std::slice::from_ref(&gold));
+
+        let prices = sources[1].fetch_prices().unwrap();
+        assert_eq!(usd_price(&prices, &gold), Some(dec!(3434.7)));
+        assert_eq!(http.urls(), ["https://query2.finance.yahoo.com/v8/finance/chart/GC%3DF"]);
+    }
+}
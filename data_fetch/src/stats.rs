@@ -0,0 +1,323 @@
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use crate::Decimal;
+
+/// Highest and lowest price seen on one UTC day.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyRange
+{
+    pub date: NaiveDate,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub high: Decimal,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub low: Decimal,
+}
+
+/// Statistics over a stretch of price history.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Summary
+{
+    pub count: usize,
+    pub first_at: DateTime<Utc>,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub first: Decimal,
+    pub latest_at: DateTime<Utc>,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub latest: Decimal,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub min: Decimal,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub max: Decimal,
+    /// Rounded to `MEAN_DP` decimal places
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub mean: Decimal,
+    /// Change from the first price to the latest, in percent, to two
+    /// decimal places; `None` when the first price is zero
+    #[serde(with = "rust_decimal::serde::arbitrary_precision_option")]
+    pub change_pct: Option<Decimal>,
+    /// One entry per day that has prices, oldest first; days with no
+    /// prices (the recorder was off) are left out rather than guessed
+    pub daily: Vec<DailyRange>,
+}
+
+/// Decimal places kept in the mean, which rarely divides out exactly
+pub const MEAN_DP: u32 = 8;
+
+/// Summarise `(time, price)` points, in any order. `None` when there are no
+/// points.
+pub fn summarize(points: impl IntoIterator<Item = (DateTime<Utc>, Decimal)>) -> Option<Summary>
+{
+    let mut points: Vec<(DateTime<Utc>, Decimal)> = points.into_iter().collect();
+    points.sort_by_key(|&(at, _)| at);
+    let (&(first_at, first), &(latest_at, latest)) = (points.first()?, points.last()?);
+
+    let mut min = first;
+    let mut max = first;
+    let mut sum = Decimal::ZERO;
+    let mut days: BTreeMap<NaiveDate, (Decimal, Decimal)> = BTreeMap::new();
+    for &(at, price) in &points
+    {
+        min = min.min(price);
+        max = max.max(price);
+        sum += price;
+        let day = days.entry(at.date_naive()).or_insert((price, price));
+        day.0 = day.0.max(price);
+        day.1 = day.1.min(price);
+    }
+    Some(Summary {
+        count: points.len(),
+        first_at,
+        first,
+        latest_at,
+        latest,
+        min,
+        max,
+        mean: (sum / Decimal::from(points.len())).round_dp(MEAN_DP).normalize(),
+        change_pct: (!first.is_zero()).then(|| ((latest - first) / first * Decimal::ONE_HUNDRED).round_dp(2)),
+        daily: days.into_iter().map(|(date, (high, low))| DailyRange { date, high, low }).collect(),
+    })
+}
+
+/// The time `back` before `now`, or `None` when that's further back than a
+/// `DateTime` reaches.
+pub fn time_before(now: DateTime<Utc>, back: Duration) -> Option<DateTime<Utc>>
+{
+    chrono::Duration::from_std(back).ok().and_then(|back| now.checked_sub_signed(back))
+}
+
+impl fmt::Display for Summary
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        writeln!(f, "  prices   {} ({} to {})", self.count, self.first_at.format("%F %R"), self.latest_at.format("%F %R"))?;
+        writeln!(f, "  latest   {}", self.latest)?;
+        writeln!(f, "  min      {}", self.min)?;
+        writeln!(f, "  max      {}", self.max)?;
+        writeln!(f, "  mean     {}", self.mean)?;
+        match self.change_pct
+        {
+            Some(pct) if pct.is_sign_negative() => writeln!(f, "  change   {pct}%")?,
+            Some(pct) => writeln!(f, "  change   +{pct}%")?,
+            None => writeln!(f, "  change   n/a")?,
+        }
+        writeln!(f, "  daily high / low")?;
+        for day in &self.daily
+        {
+            writeln!(f, "    {}  {} / {}", day.date, day.high, day.low)?;
+        }
+        Ok(())
+    }
+}
+
+/// Open, high, low, close and sample count over one time bucket. An empty
+/// bucket has no prices and a count of 0.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candle
+{
+    /// Start of the bucket; it runs up to, not including, the next start
+    pub start: DateTime<Utc>,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision_option")]
+    pub open: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision_option")]
+    pub high: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision_option")]
+    pub low: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision_option")]
+    pub close: Option<Decimal>,
+    pub count: usize,
+}
+
+pub const CANDLE_CSV_HEADER: &str = "start,open,high,low,close,count";
+
+impl Candle
+{
+    /// `2026-01-05T14:00:00Z,100,110,95,105,12`; an empty bucket leaves the
+    /// prices blank
+    pub fn to_csv(&self) -> String
+    {
+        let price = |p: Option<Decimal>| p.map(|p| p.to_string()).unwrap_or_default();
+        format!(
+            "{},{},{},{},{},{}",
+            self.start.to_rfc3339_opts(SecondsFormat::Secs, true),
+            price(self.open),
+            price(self.high),
+            price(self.low),
+            price(self.close),
+            self.count
+        )
+    }
+}
+
+/// Group `(time, price)` points, in any order, into `bucket`-wide candles,
+/// oldest first. Buckets are aligned to the Unix epoch in UTC, so hourly
+/// buckets start on the hour and daily ones at UTC midnight whatever the
+/// local time zone; a point exactly on a boundary opens the later bucket.
+/// Points at the same time keep their order for open and close. With
+/// `mark_empty`, buckets with no points between the first and last ones
+/// are returned as empty candles; otherwise they're left out.
+pub fn candles(points: impl IntoIterator<Item = (DateTime<Utc>, Decimal)>, bucket: Duration, mark_empty: bool) -> Vec<Candle>
+{
+    let width = bucket.as_secs().max(1) as i64;
+    let start_of = |at: DateTime<Utc>| at.timestamp().div_euclid(width) * width;
+    let mut points: Vec<(DateTime<Utc>, Decimal)> = points.into_iter().collect();
+    points.sort_by_key(|&(at, _)| at);
+
+    let candle = |start: i64, price: Option<Decimal>, count: usize| Candle {
+        start: DateTime::from_timestamp(start, 0).unwrap_or_default(),
+        open: price,
+        high: price,
+        low: price,
+        close: price,
+        count,
+    };
+    let mut out: Vec<Candle> = Vec::new();
+    for (at, price) in points
+    {
+        let start = start_of(at);
+        match out.last_mut()
+        {
+            Some(last) if last.start.timestamp() == start =>
+            {
+                last.high = last.high.max(Some(price));
+                last.low = last.low.min(Some(price));
+                last.close = Some(price);
+                last.count += 1;
+                continue;
+            }
+            Some(last) if mark_empty =>
+            {
+                let gaps = (last.start.timestamp() + width..start).step_by(width as usize);
+                out.extend(gaps.map(|gap| candle(gap, None, 0)));
+            }
+            _ => {}
+        }
+        out.push(candle(start, Some(price), 1));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    fn at(day: u32, h: u32) -> DateTime<Utc>
+    {
+        Utc.with_ymd_and_hms(2026, 1, day, h, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn summary_over_a_few_days()
+    {
+        // Out of order, with nothing on the 6th
+        let points =
+            [(at(7, 9), dec!(90)), (at(5, 8), dec!(100)), (at(5, 20), dec!(120)), (at(7, 18), dec!(110)), (at(5, 12), dec!(95))];
+        let s = summarize(points).unwrap();
+        assert_eq!(s.count, 5);
+        assert_eq!((s.first, s.latest, s.latest_at), (dec!(100), dec!(110), at(7, 18)));
+        assert_eq!((s.min, s.max, s.mean), (dec!(90), dec!(120), dec!(103)));
+        assert_eq!(s.change_pct, Some(dec!(10)));
+        let date = |d| NaiveDate::from_ymd_opt(2026, 1, d).unwrap();
+        assert_eq!(
+            s.daily,
+            [
+                DailyRange { date: date(5), high: dec!(120), low: dec!(95) },
+                DailyRange { date: date(7), high: dec!(110), low: dec!(90) },
+            ]
+        );
+    }
+
+    #[test]
+    fn sub_satoshi_prices_stay_exact()
+    {
+        // As f64 these pick up noise in the last digits; as decimals the mean is exact
+        let s = summarize([(at(5, 0), dec!(0.00000001)), (at(5, 1), dec!(0.00000003))]).unwrap();
+        assert_eq!(s.mean, dec!(0.00000002));
+        assert_eq!(s.change_pct, Some(dec!(200)));
+        let json = serde_json::to_string(&s).unwrap();
+        assert!(json.contains(r#""min":0.00000001"#), "{json}");
+    }
+
+    fn minutes(h: u32, m: u32, s: u32) -> DateTime<Utc>
+    {
+        Utc.with_ymd_and_hms(2026, 1, 5, h, m, s).unwrap()
+    }
+
+    #[test]
+    fn candles_split_on_bucket_boundaries()
+    {
+        let points = [
+            (minutes(9, 59, 59), dec!(1)),
+            // Exactly on the hour: the 10:00 bucket's open
+            (minutes(10, 0, 0), dec!(5)),
+            (minutes(10, 30, 0), dec!(9)),
+            (minutes(10, 15, 0), dec!(2)),
+            (minutes(10, 59, 59), dec!(4)),
+            (minutes(11, 0, 0), dec!(7)),
+        ];
+        let hourly = candles(points, Duration::from_secs(3600), false);
+        let summary: Vec<_> = hourly.iter().map(|c| (c.start, c.open, c.high, c.low, c.close, c.count)).collect();
+        assert_eq!(
+            summary,
+            [
+                (minutes(9, 0, 0), Some(dec!(1)), Some(dec!(1)), Some(dec!(1)), Some(dec!(1)), 1),
+                (minutes(10, 0, 0), Some(dec!(5)), Some(dec!(9)), Some(dec!(2)), Some(dec!(4)), 4),
+                (minutes(11, 0, 0), Some(dec!(7)), Some(dec!(7)), Some(dec!(7)), Some(dec!(7)), 1),
+            ]
+        );
+        assert_eq!(hourly[1].to_csv(), "2026-01-05T10:00:00Z,5,9,2,4,4");
+    }
+
+    #[test]
+    fn empty_buckets_are_skipped_or_marked()
+    {
+        let points = [(minutes(9, 10, 0), dec!(1)), (minutes(12, 5, 0), dec!(2))];
+        let skipped = candles(points, Duration::from_secs(3600), false);
+        assert_eq!(skipped.iter().map(|c| c.start).collect::<Vec<_>>(), [minutes(9, 0, 0), minutes(12, 0, 0)]);
+
+        let marked = candles(points, Duration::from_secs(3600), true);
+        let counts: Vec<_> = marked.iter().map(|c| (c.start, c.count)).collect();
+        assert_eq!(counts, [(minutes(9, 0, 0), 1), (minutes(10, 0, 0), 0), (minutes(11, 0, 0), 0), (minutes(12, 0, 0), 1)]);
+        assert_eq!(marked[1].to_csv(), "2026-01-05T10:00:00Z,,,,,0");
+        let json = serde_json::to_string(&marked[1]).unwrap();
+        assert_eq!(json, r#"{"start":"2026-01-05T10:00:00Z","open":null,"high":null,"low":null,"close":null,"count":0}"#);
+
+        assert!(candles(Vec::new(), Duration::from_secs(60), true).is_empty());
+    }
+
+    #[test]
+    fn daily_candles_start_at_utc_midnight()
+    {
+        // 23:30 in New York on the 4th is 04:30 UTC on the 5th
+        let new_york = chrono::FixedOffset::west_opt(5 * 3600).unwrap();
+        let late = new_york.with_ymd_and_hms(2026, 1, 4, 23, 30, 0).unwrap().with_timezone(&Utc);
+        let early = Utc.with_ymd_and_hms(2026, 1, 4, 0, 0, 0).unwrap();
+        let daily = candles([(late, dec!(2)), (early, dec!(1))], Duration::from_secs(86400), false);
+        assert_eq!(daily.iter().map(|c| c.start).collect::<Vec<_>>(), [early, at(5, 0)]);
+        // Before the epoch, buckets still start on the boundary below
+        let old = Utc.with_ymd_and_hms(1969, 12, 31, 18, 0, 0).unwrap();
+        assert_eq!(candles([(old, dec!(1))], Duration::from_secs(86400), false)[0].start.to_rfc3339(), "1969-12-31T00:00:00+00:00");
+    }
+
+    #[test]
+    fn time_before_stops_at_the_earliest_date()
+    {
+        let day = Duration::from_secs(86400);
+        assert_eq!(time_before(at(7, 0), day * 2), Some(at(5, 0)));
+        assert_eq!(time_before(at(7, 0), day * 100_000_000), None);
+        assert_eq!(time_before(at(7, 0), Duration::MAX), None);
+    }
+
+    #[test]
+    fn empty_and_degenerate_ranges()
+    {
+        assert_eq!(summarize(Vec::new()), None);
+
+        let one = summarize([(at(5, 0), Decimal::ZERO)]).unwrap();
+        assert_eq!((one.count, one.min, one.max, one.change_pct), (1, Decimal::ZERO, Decimal::ZERO, None));
+    }
+}
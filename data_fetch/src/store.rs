@@ -0,0 +1,320 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{
+    params,
+    types::{FromSqlError, ValueRef},
+    Connection, Row,
+};
+use std::{path::Path, time::Duration};
+
+use crate::{parse_decimal, Decimal, PriceRow};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS prices (
+        asset TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        ts INTEGER NOT NULL,
+        price TEXT NOT NULL,
+        source TEXT NOT NULL DEFAULT '',
+        market_ts INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS prices_asset_ts ON prices (asset, ts);
+";
+
+/// Open, high, low and close of one asset over one time bucket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ohlc
+{
+    /// Start of the bucket
+    pub start: DateTime<Utc>,
+    pub currency: String,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+}
+
+/// Price history in SQLite, one row per fetched price. Assets are keyed by
+/// the same name as their history files (`btc`, `gspc`); timestamps are
+/// stored as Unix seconds and prices as text, so they keep their digits.
+#[derive(Debug)]
+pub struct PriceStore
+{
+    conn: Connection,
+}
+
+impl PriceStore
+{
+    /// Open (or create) the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self>
+    {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// A throwaway database, for tests and dry runs.
+    pub fn in_memory() -> rusqlite::Result<Self>
+    {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> rusqlite::Result<Self>
+    {
+        conn.execute_batch(SCHEMA)?;
+        // Databases from before market times were recorded
+        let has_market_ts: bool =
+            conn.query_row("SELECT COUNT(*) > 0 FROM pragma_table_info('prices') WHERE name = 'market_ts'", [], |r| r.get(0))?;
+        if !has_market_ts
+        {
+            conn.execute_batch("ALTER TABLE prices ADD COLUMN market_ts INTEGER")?;
+        }
+        Ok(Self { conn })
+    }
+
+    pub fn insert(&self, asset: &str, row: &PriceRow) -> rusqlite::Result<()>
+    {
+        self.conn.execute(
+            "INSERT INTO prices (asset, currency, ts, price, source, market_ts) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                asset,
+                row.currency,
+                row.fetched_at.timestamp(),
+                row.price.to_string(),
+                row.source,
+                row.market_at.map(|at| at.timestamp())
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Insert those of `rows` not already stored for `asset` at the same
+    /// second in the same currency, in one transaction. Returns how many
+    /// were new.
+    pub fn insert_missing(&self, asset: &str, rows: &[PriceRow]) -> rusqlite::Result<usize>
+    {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut added = 0;
+        for row in rows
+        {
+            let stored: bool = tx.query_row(
+                "SELECT EXISTS (SELECT 1 FROM prices WHERE asset = ?1 AND currency = ?2 AND ts = ?3)",
+                params![asset, row.currency, row.fetched_at.timestamp()],
+                |r| r.get(0),
+            )?;
+            if !stored
+            {
+                self.insert(asset, row)?;
+                added += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    /// Most recent price of `asset`, in whatever currency it was recorded.
+    pub fn latest(&self, asset: &str) -> rusqlite::Result<Option<PriceRow>>
+    {
+        let mut stmt = self.conn.prepare(
+            "SELECT ts, currency, price, source, market_ts FROM prices WHERE asset = ?1 ORDER BY ts DESC, rowid DESC LIMIT 1",
+        )?;
+        let mut rows = stmt.query_map(params![asset], price_row)?;
+        rows.next().transpose()
+    }
+
+    /// Most recent price of `asset` in `currency`.
+    pub fn latest_in(&self, asset: &str, currency: &str) -> rusqlite::Result<Option<PriceRow>>
+    {
+        let mut stmt = self.conn.prepare(
+            "SELECT ts, currency, price, source, market_ts FROM prices WHERE asset = ?1 AND currency = ?2
+             ORDER BY ts DESC, rowid DESC LIMIT 1",
+        )?;
+        let mut rows = stmt.query_map(params![asset, currency], price_row)?;
+        rows.next().transpose()
+    }
+
+    /// Prices of `asset` with `from <= fetched_at < to`, oldest first.
+    pub fn range(&self, asset: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> rusqlite::Result<Vec<PriceRow>>
+    {
+        let mut stmt = self.conn.prepare(
+            "SELECT ts, currency, price, source, market_ts FROM prices WHERE asset = ?1 AND ts >= ?2 AND ts < ?3 ORDER BY ts, rowid",
+        )?;
+        let rows = stmt.query_map(params![asset, from.timestamp(), to.timestamp()], price_row)?;
+        rows.collect()
+    }
+
+    /// Open/high/low/close of `asset` per `bucket`, oldest first. Buckets are
+    /// aligned to the Unix epoch (so hourly buckets start on the hour) and
+    /// only buckets with prices in them are returned; each currency gets its
+    /// own candles. High and low are picked by numeric value and returned
+    /// as stored.
+    pub fn ohlc(&self, asset: &str, bucket: Duration) -> rusqlite::Result<Vec<Ohlc>>
+    {
+        let secs = bucket.as_secs().max(1) as i64;
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT bucket, currency,
+                    FIRST_VALUE(price) OVER w, FIRST_VALUE(price) OVER hi, FIRST_VALUE(price) OVER lo,
+                    LAST_VALUE(price) OVER w
+             FROM (SELECT rowid, ts / ?2 * ?2 AS bucket, currency, ts, price FROM prices WHERE asset = ?1)
+             WINDOW w AS (
+                 PARTITION BY bucket, currency ORDER BY ts, rowid
+                 ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING
+             ),
+             hi AS (PARTITION BY bucket, currency ORDER BY CAST(price AS REAL) DESC),
+             lo AS (PARTITION BY bucket, currency ORDER BY CAST(price AS REAL))
+             ORDER BY bucket, currency",
+        )?;
+        let rows = stmt.query_map(params![asset, secs], |row| {
+            Ok(Ohlc {
+                start: timestamp(row.get(0)?),
+                currency: row.get(1)?,
+                open: price(row, 2)?,
+                high: price(row, 3)?,
+                low: price(row, 4)?,
+                close: price(row, 5)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+fn timestamp(secs: i64) -> DateTime<Utc>
+{
+    DateTime::from_timestamp(secs, 0).unwrap_or_default()
+}
+
+/// A stored price: text as written by `insert`, or a number from an
+/// older database
+fn price(row: &Row, idx: usize) -> rusqlite::Result<Decimal>
+{
+    let value = row.get_ref(idx)?;
+    let parsed = match value
+    {
+        ValueRef::Text(text) => std::str::from_utf8(text).ok().and_then(parse_decimal),
+        ValueRef::Real(real) => Decimal::try_from(real).ok(),
+        ValueRef::Integer(int) => Some(Decimal::from(int)),
+        _ => None,
+    };
+    parsed.ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(idx, value.data_type(), Box::new(FromSqlError::InvalidType)))
+}
+
+fn price_row(row: &Row) -> rusqlite::Result<PriceRow>
+{
+    Ok(PriceRow {
+        fetched_at: timestamp(row.get(0)?),
+        currency: row.get(1)?,
+        price: price(row, 2)?,
+        source: row.get(3)?,
+        market_at: row.get::<_, Option<i64>>(4)?.map(timestamp),
+    })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    fn at(h: u32, m: u32) -> DateTime<Utc>
+    {
+        Utc.with_ymd_and_hms(2026, 1, 5, h, m, 0).unwrap()
+    }
+
+    fn row(fetched_at: DateTime<Utc>, price: Decimal) -> PriceRow
+    {
+        PriceRow { fetched_at, currency: "usd".to_string(), price, source: "CoinGecko".to_string(), market_at: None }
+    }
+
+    #[test]
+    fn latest_and_range()
+    {
+        let store = PriceStore::in_memory().unwrap();
+        assert_eq!(store.latest("btc").unwrap(), None);
+        for (m, price) in [(0, dec!(1.0)), (10, dec!(2.0)), (20, dec!(3.0))]
+        {
+            store.insert("btc", &row(at(9, m), price)).unwrap();
+        }
+        store.insert("eth", &row(at(9, 30), dec!(9.0))).unwrap();
+
+        assert_eq!(store.latest("btc").unwrap(), Some(row(at(9, 20), dec!(3.0))));
+        assert_eq!(store.latest_in("btc", "eur").unwrap(), None);
+        let prices: Vec<Decimal> = store.range("btc", at(9, 5), at(9, 20)).unwrap().iter().map(|r| r.price).collect();
+        assert_eq!(prices, [dec!(2.0)]);
+    }
+
+    #[test]
+    fn backfill_twice_adds_nothing_new()
+    {
+        let store = PriceStore::in_memory().unwrap();
+        store.insert("btc", &row(at(9, 10), dec!(2.0))).unwrap();
+        let history = [row(at(9, 0), dec!(1.0)), row(at(9, 10), dec!(2.5)), row(at(9, 20), dec!(3.0))];
+        assert_eq!(store.insert_missing("btc", &history).unwrap(), 2);
+        assert_eq!(store.insert_missing("btc", &history).unwrap(), 0);
+        let prices: Vec<Decimal> = store.range("btc", at(9, 0), at(10, 0)).unwrap().iter().map(|r| r.price).collect();
+        // The price already stored at 9:10 is kept
+        assert_eq!(prices, [dec!(1.0), dec!(2.0), dec!(3.0)]);
+    }
+
+    #[test]
+    fn ohlc_per_hour()
+    {
+        let store = PriceStore::in_memory().unwrap();
+        let series = [
+            (at(9, 0), dec!(100.0)),
+            (at(9, 15), dec!(104.0)),
+            (at(9, 30), dec!(98.0)),
+            (at(9, 59), dec!(101.0)),
+            (at(10, 0), dec!(101.5)),
+            (at(10, 45), dec!(99.0)),
+            // Nothing from 11:00; that hour is skipped
+            (at(12, 5), dec!(110.0)),
+        ];
+        // Inserted out of order: candles follow the timestamps
+        for &(fetched_at, price) in series.iter().rev()
+        {
+            store.insert("btc", &row(fetched_at, price)).unwrap();
+        }
+
+        let candle = |start, open, high, low, close| Ohlc { start, currency: "usd".to_string(), open, high, low, close };
+        assert_eq!(
+            store.ohlc("btc", Duration::from_secs(3600)).unwrap(),
+            [
+                candle(at(9, 0), dec!(100.0), dec!(104.0), dec!(98.0), dec!(101.0)),
+                candle(at(10, 0), dec!(101.5), dec!(101.5), dec!(99.0), dec!(99.0)),
+                candle(at(12, 0), dec!(110.0), dec!(110.0), dec!(110.0), dec!(110.0)),
+            ]
+        );
+        assert!(store.ohlc("eth", Duration::from_secs(3600)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn prices_come_back_digit_for_digit()
+    {
+        let store = PriceStore::in_memory().unwrap();
+        let exact = dec!(62999.999999999999999);
+        store.insert("btc", &row(at(9, 0), exact)).unwrap();
+        store.insert("btc", &row(at(9, 1), dec!(0.00000001))).unwrap();
+        assert_eq!(store.range("btc", at(9, 0), at(9, 1)).unwrap()[0].price.to_string(), "62999.999999999999999");
+        let candle = &store.ohlc("btc", Duration::from_secs(3600)).unwrap()[0];
+        assert_eq!((candle.high, candle.low), (exact, dec!(0.00000001)));
+    }
+
+    #[test]
+    fn older_databases_gain_market_times()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prices.db");
+        let old = Connection::open(&path).unwrap();
+        old.execute_batch(
+            "CREATE TABLE prices (asset TEXT NOT NULL, currency TEXT NOT NULL, ts INTEGER NOT NULL,
+                                  price TEXT NOT NULL, source TEXT NOT NULL DEFAULT '');
+             INSERT INTO prices VALUES ('gspc', 'usd', 1767603600, '6389.77', 'Yahoo Finance');",
+        )
+        .unwrap();
+        drop(old);
+
+        let store = PriceStore::open(&path).unwrap();
+        assert_eq!(store.latest("gspc").unwrap().unwrap().market_at, None);
+        let market_at = Some(at(8, 58));
+        store.insert("gspc", &PriceRow { market_at, ..row(at(9, 0), dec!(6390.1)) }).unwrap();
+        assert_eq!(store.latest("gspc").unwrap().unwrap().market_at, market_at);
+    }
+}
@@ -0,0 +1,270 @@
+use std::{collections::BTreeMap, fmt, fmt::Write as _, time::Duration};
+
+use crate::{AssetKind, Decimal, PriceRow};
+
+pub(crate) const GREEN: &str = "\x1b[32m";
+pub(crate) const RED: &str = "\x1b[31m";
+pub(crate) const RESET: &str = "\x1b[0m";
+
+/// How far a price moved from an earlier one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delta
+{
+    pub absolute: Decimal,
+    /// Rounded to two places; `None` when the earlier price was zero
+    pub percent: Option<Decimal>,
+}
+
+impl Delta
+{
+    pub fn between(from: Decimal, to: Decimal) -> Self
+    {
+        let absolute = (to - from).normalize();
+        let percent = (!from.is_zero()).then(|| (absolute / from * Decimal::ONE_HUNDRED).round_dp(2));
+        Self { absolute, percent }
+    }
+}
+
+/// `+2.87 (+0.01%)`, `-1.5 (-3.00%)`
+impl fmt::Display for Delta
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let sign = |d: Decimal| if d.is_sign_positive() && !d.is_zero() { "+" } else { "" };
+        write!(f, "{}{}", sign(self.absolute), self.absolute)?;
+        match self.percent
+        {
+            Some(pct) => write!(f, " ({}{pct:.2}%)", sign(pct)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A fetched price and how it moved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change
+{
+    pub asset: String,
+    pub currency: String,
+    pub price: Decimal,
+    /// Since the previous fetch; `None` on the first
+    pub since_last: Option<Delta>,
+    /// Since the first fetch of the session; `None` on the first
+    pub since_start: Option<Delta>,
+}
+
+/// What was seen of one asset in one currency this session
+#[derive(Debug, Clone, Copy)]
+struct Seen
+{
+    first: Decimal,
+    last: Decimal,
+    min: Decimal,
+    max: Decimal,
+    fetches: u64,
+}
+
+/// Price moves and counts over the session, for the console.
+#[derive(Debug, Default)]
+pub struct PriceTracker
+{
+    /// Per (asset, currency)
+    prices: BTreeMap<(String, String), Seen>,
+    /// Failed fetches per asset
+    errors: BTreeMap<String, u64>,
+}
+
+impl PriceTracker
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Take in a fetched price and say how it moved.
+    pub fn observe(&mut self, asset: &AssetKind, row: &PriceRow) -> Change
+    {
+        let key = (asset.name().to_string(), row.currency.clone());
+        let price = row.price;
+        let (since_last, since_start) = match self.prices.get_mut(&key)
+        {
+            Some(seen) =>
+            {
+                let moved = (Delta::between(seen.last, price), Delta::between(seen.first, price));
+                seen.last = price;
+                seen.min = seen.min.min(price);
+                seen.max = seen.max.max(price);
+                seen.fetches += 1;
+                (Some(moved.0), Some(moved.1))
+            }
+            None =>
+            {
+                self.prices.insert(key.clone(), Seen { first: price, last: price, min: price, max: price, fetches: 1 });
+                (None, None)
+            }
+        };
+        Change { asset: key.0, currency: key.1, price, since_last, since_start }
+    }
+
+    pub fn failed(&mut self, asset: &AssetKind)
+    {
+        *self.errors.entry(asset.name().to_string()).or_default() += 1;
+    }
+
+    /// Session totals and each asset's range, e.g.
+    ///
+    /// ```text
+    /// Session: 1h02m05s, 250 prices, 3 failed fetches
+    ///   bitcoin usd: 124 prices, min 118990.5, max 119461.37
+    ///   ethereum: 3 failed fetches
+    /// ```
+    pub fn summary(&self, elapsed: Duration) -> String
+    {
+        let fetches: u64 = self.prices.values().map(|s| s.fetches).sum();
+        let errors: u64 = self.errors.values().sum();
+        let mut out = format!("Session: {}, {fetches} prices, {errors} failed fetches\n", elapsed_text(elapsed));
+        for ((asset, currency), seen) in &self.prices
+        {
+            let _ = writeln!(out, "  {asset} {currency}: {} prices, min {}, max {}", seen.fetches, seen.min, seen.max);
+        }
+        for (asset, count) in &self.errors
+        {
+            let _ = writeln!(out, "  {asset}: {count} failed fetches");
+        }
+        out
+    }
+}
+
+/// `1h02m05s`, `3m20s`, `45s`
+fn elapsed_text(d: Duration) -> String
+{
+    let secs = d.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60)
+    {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m{s:02}s"),
+        (h, m, s) => format!("{h}h{m:02}m{s:02}s"),
+    }
+}
+
+/// One aligned line per change: asset, price, move since the last fetch
+/// and since the session started. With `color`, rises are green and falls
+/// red.
+pub fn render_changes(changes: &[Change], color: bool) -> String
+{
+    let cells: Vec<[String; 4]> = changes
+        .iter()
+        .map(|c| {
+            let text = |d: &Option<Delta>| d.map_or_else(|| "-".to_string(), |d| d.to_string());
+            [c.asset.clone(), format!("{} {}", c.price, c.currency), text(&c.since_last), text(&c.since_start)]
+        })
+        .collect();
+    let header = ["asset", "price", "change", "session"].map(str::to_string);
+    let mut widths = [0; 4];
+    for row in std::iter::once(&header).chain(&cells)
+    {
+        for (width, cell) in widths.iter_mut().zip(row)
+        {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let [w0, w1, w2, _] = widths;
+    let mut out = String::new();
+    let _ = writeln!(out, "{:<w0$}  {:<w1$}  {:<w2$}  {}", header[0], header[1], header[2], header[3]);
+    for (change, row) in changes.iter().zip(&cells)
+    {
+        // Pad before coloring, so escape codes don't count toward the width
+        let paint = |delta: &Option<Delta>, text: String| match delta.map(|d| d.absolute.cmp(&Decimal::ZERO))
+        {
+            Some(std::cmp::Ordering::Greater) if color => format!("{GREEN}{text}{RESET}"),
+            Some(std::cmp::Ordering::Less) if color => format!("{RED}{text}{RESET}"),
+            _ => text,
+        };
+        let since_last = paint(&change.since_last, format!("{:<w2$}", row[2]));
+        let since_start = paint(&change.since_start, row[3].clone());
+        let _ = writeln!(out, "{:<w0$}  {:<w1$}  {since_last}  {since_start}", row[0], row[1]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use chrono::Utc;
+    use rust_decimal_macros::dec;
+
+    fn row(price: Decimal) -> PriceRow
+    {
+        PriceRow { fetched_at: Utc::now(), currency: "usd".to_string(), price, source: "CoinGecko".to_string(), market_at: None }
+    }
+
+    #[test]
+    fn first_fetch_has_nothing_to_compare()
+    {
+        let mut tracker = PriceTracker::new();
+        let change = tracker.observe(&AssetKind::coin("bitcoin"), &row(dec!(119458.5)));
+        assert_eq!(change.price, dec!(119458.5));
+        assert_eq!((change.since_last, change.since_start), (None, None));
+    }
+
+    #[test]
+    fn moves_since_last_and_since_start()
+    {
+        let mut tracker = PriceTracker::new();
+        let btc = AssetKind::coin("bitcoin");
+        tracker.observe(&btc, &row(dec!(100)));
+        let up = tracker.observe(&btc, &row(dec!(105.50)));
+        assert_eq!(up.since_last, Some(Delta { absolute: dec!(5.5), percent: Some(dec!(5.50)) }));
+        let down = tracker.observe(&btc, &row(dec!(99)));
+        let since_last = down.since_last.unwrap();
+        assert_eq!(since_last.absolute, dec!(-6.5));
+        assert_eq!(since_last.percent, Some(dec!(-6.16)));
+        assert_eq!(since_last.to_string(), "-6.5 (-6.16%)");
+        assert_eq!(down.since_start.unwrap().to_string(), "-1 (-1.00%)");
+        assert_eq!(up.since_last.unwrap().to_string(), "+5.5 (+5.50%)");
+    }
+
+    #[test]
+    fn flat_and_from_zero()
+    {
+        assert_eq!(Delta::between(dec!(2.50), dec!(2.5)).to_string(), "0 (0.00%)");
+        assert_eq!(Delta::between(dec!(0), dec!(3)), Delta { absolute: dec!(3), percent: None });
+        assert_eq!(Delta::between(dec!(0), dec!(3)).to_string(), "+3");
+    }
+
+    #[test]
+    fn table_lines_up_and_colors_only_when_asked()
+    {
+        let mut tracker = PriceTracker::new();
+        let eth = AssetKind::coin("ethereum");
+        let changes = vec![tracker.observe(&eth, &row(dec!(3000))), tracker.observe(&eth, &row(dec!(3030)))];
+        let plain = render_changes(&changes, false);
+        assert_eq!(
+            plain,
+            "asset     price     change        session\n\
+             ethereum  3000 usd  -             -\n\
+             ethereum  3030 usd  +30 (+1.00%)  +30 (+1.00%)\n"
+        );
+        let colored = render_changes(&changes, true);
+        assert!(colored.ends_with("\x1b[32m+30 (+1.00%)\x1b[0m  \x1b[32m+30 (+1.00%)\x1b[0m\n"), "{colored:?}");
+        assert!(!colored.lines().nth(1).unwrap().contains('\x1b'));
+    }
+
+    #[test]
+    fn summary_counts_and_ranges()
+    {
+        let mut tracker = PriceTracker::new();
+        let btc = AssetKind::coin("bitcoin");
+        for price in [dec!(101), dec!(99.5), dec!(100)]
+        {
+            tracker.observe(&btc, &row(price));
+        }
+        tracker.failed(&AssetKind::sp500());
+        assert_eq!(
+            tracker.summary(Duration::from_secs(3725)),
+            "Session: 1h02m05s, 3 prices, 1 failed fetches\n  bitcoin usd: 3 prices, min 99.5, max 101\n  ^GSPC: 1 failed fetches\n"
+        );
+    }
+}
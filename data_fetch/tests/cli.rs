@@ -0,0 +1,21 @@
+#![cfg(feature = "sqlite")]
+
+use std::process::Command;
+
+#[test]
+fn dry_run_creates_no_database()
+{
+    let dir = tempfile::tempdir().unwrap();
+    let db = dir.path().join("prices.db");
+    // Nothing listens on port 1, so the fetch fails fast; the run still
+    // shouldn't touch the database
+    let output = Command::new(env!("CARGO_BIN_EXE_data_fetch"))
+        .current_dir(dir.path())
+        .env("COINGECKO_BASE_URL", "http://127.0.0.1:1")
+        .args(["--dry-run", "--once", "--assets", "btc", "--sources", "coingecko", "--retries", "0", "--db"])
+        .arg(&db)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!db.exists());
+}
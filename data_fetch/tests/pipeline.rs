@@ -0,0 +1,119 @@
+//! Fetch, parse and write against mock APIs, through the same pieces the
+//! binary wires together.
+
+use data_fetch::{
+    poll_sources, sources_for, sources_with_http, AlertTracker, AssetKind, CoinProvider, HistoryWriter, PriceSource,
+    RecordMode, Recorder, ScriptedHttp, Shutdown, SourceConfig, SourceOptions, Storage, WritePolicy,
+};
+use httpmock::prelude::*;
+use std::{fs, path::Path, time::Duration};
+
+const COINGECKO_PARTIAL: &str = include_str!("fixtures/coingecko_missing_ethereum.json");
+const BINANCE: &str = include_str!("fixtures/binance_ticker_price.json");
+const YAHOO: &str = include_str!("fixtures/yahoo_chart.json");
+
+fn at(server: &MockServer, api_key: Option<&str>) -> SourceConfig
+{
+    SourceConfig { base_url: Some(server.base_url()), api_key: api_key.map(str::to_string) }
+}
+
+/// The single data row of `dir/<stem>.csv`, without its fetch timestamp
+fn only_row(dir: &Path, stem: &str) -> String
+{
+    let text = fs::read_to_string(dir.join(format!("{stem}.csv"))).unwrap();
+    let rows: Vec<&str> = text.lines().skip(1).collect();
+    assert_eq!(rows.len(), 1, "{stem}.csv: {text}");
+    rows[0].split_once(',').unwrap().1.to_string()
+}
+
+/// Run one round of `sources` and write what they fetch to CSV files in `dir`
+fn record_one_round(sources: Vec<Box<dyn PriceSource>>, dir: &Path) -> Recorder
+{
+    let storage = Storage::Files(HistoryWriter::new(dir, WritePolicy::Always));
+    let mut recorder = Recorder::new(storage, AlertTracker::new(Vec::new()), Vec::new(), RecordMode::Write);
+    let (events, handles) = poll_sources(sources, Duration::from_secs(60), true, &Shutdown::new());
+    for event in events
+    {
+        recorder.handle(event);
+    }
+    for handle in handles
+    {
+        handle.join().unwrap();
+    }
+    recorder.sync().unwrap();
+    recorder
+}
+
+#[test]
+fn fetch_parse_and_write()
+{
+    let server = MockServer::start();
+    let coingecko = server.mock(|when, then| {
+        when.method(GET)
+            .path("/simple/price")
+            .query_param("ids", "bitcoin,ethereum")
+            .header("x-cg-pro-api-key", "test-key");
+        then.status(200).header("Content-Type", "application/json").body(COINGECKO_PARTIAL);
+    });
+    let binance = server.mock(|when, then| {
+        when.method(GET).path("/api/v3/ticker/price").query_param("symbol", "ETHUSDT");
+        then.status(200).body(BINANCE);
+    });
+    let yahoo = server.mock(|when, then| {
+        when.method(GET).path("/v8/finance/chart/%5EGSPC");
+        then.status(200).body(YAHOO);
+    });
+
+    let options = SourceOptions {
+        coin_sources: vec![CoinProvider::CoinGecko, CoinProvider::Binance],
+        coingecko: at(&server, Some("test-key")),
+        binance: at(&server, None),
+        yahoo: at(&server, None),
+        ..SourceOptions::default()
+    };
+    let sources = sources_for(&AssetKind::defaults(), &options);
+
+    let dir = tempfile::tempdir().unwrap();
+    let recorder = record_one_round(sources, dir.path());
+
+    coingecko.assert();
+    binance.assert();
+    yahoo.assert();
+    assert_eq!(recorder.written(), 3);
+    assert_eq!(only_row(dir.path(), "btc"), "usd,119458,CoinGecko,");
+    // Ethereum was missing from CoinGecko, so Binance priced it
+    assert_eq!(only_row(dir.path(), "eth"), "usd,119461.37000000,Binance,");
+    // Yahoo says when its close was current
+    assert_eq!(only_row(dir.path(), "gspc"), "usd,6389.77,Yahoo Finance,2025-08-05T13:32:00Z");
+}
+
+#[test]
+fn scripted_round_without_the_network()
+{
+    let http = ScriptedHttp::new()
+        .fail("https://api.coingecko.com", 503)
+        .respond("https://api.binance.com/api/v3/ticker/price?symbol=BTCUSDT", BINANCE)
+        .fail("https://query2.finance.yahoo.com", 404);
+    let options =
+        SourceOptions { coin_sources: vec![CoinProvider::CoinGecko, CoinProvider::Binance], ..SourceOptions::default() };
+    let assets = [AssetKind::coin("bitcoin"), AssetKind::sp500()];
+    let sources = sources_with_http(&assets, &options, || Box::new(http.clone()));
+
+    let dir = tempfile::tempdir().unwrap();
+    let recorder = record_one_round(sources, dir.path());
+
+    // CoinGecko was down, so Binance priced bitcoin; the ticker failed outright
+    assert_eq!(recorder.written(), 1);
+    assert_eq!(only_row(dir.path(), "btc"), "usd,119461.37000000,Binance,");
+    assert!(!dir.path().join("gspc.csv").exists());
+    let summary = recorder.summary(Duration::from_secs(1));
+    assert!(summary.starts_with("Session: 1s, 1 prices, 1 failed fetches\n"), "{summary}");
+
+    let mut urls = http.urls();
+    urls.sort();
+    assert_eq!(urls, [
+        "https://api.binance.com/api/v3/ticker/price?symbol=BTCUSDT",
+        "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=usd",
+        "https://query2.finance.yahoo.com/v8/finance/chart/%5EGSPC",
+    ]);
+}
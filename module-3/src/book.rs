@@ -0,0 +1,217 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::loan::validate_loans;
+use crate::{BookStoreError, Loan, ReturnedLoan};
+
+/// Whether a book has been read yet, in the order that happens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadStatus {
+    #[default]
+    Unread,
+    Reading,
+    Read,
+}
+
+impl fmt::Display for ReadStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReadStatus::Unread => "unread",
+            ReadStatus::Reading => "reading",
+            ReadStatus::Read => "read",
+        })
+    }
+}
+
+impl FromStr for ReadStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "unread" => Ok(ReadStatus::Unread),
+            "reading" => Ok(ReadStatus::Reading),
+            "read" => Ok(ReadStatus::Read),
+            other => Err(format!("unknown read status '{other}' (valid: unread, reading, read)")),
+        }
+    }
+}
+
+/// A book. Everything past the year is optional, so files written before
+/// those fields existed still load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Book {
+    pub title: String,
+    pub author: String,
+    pub year: u16,
+    /// ISBN-10 or ISBN-13, digits only (and a final `X` for some ISBN-10s)
+    #[serde(default)]
+    pub isbn: Option<String>,
+    #[serde(default)]
+    pub genre: Option<String>,
+    /// 1 to 5
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub status: ReadStatus,
+    /// Who has it now, if it's lent out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loan: Option<Loan>,
+    /// Earlier loans, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub loan_history: Vec<ReturnedLoan>,
+}
+
+impl Book {
+    /// A book with no optional fields set. Fails if the year is 0 or more
+    /// than a year ahead.
+    pub fn new(title: &str, author: &str, year: u16) -> Result<Self, BookStoreError> {
+        let book = Book {
+            title: title.to_string(),
+            author: author.to_string(),
+            year,
+            isbn: None,
+            genre: None,
+            rating: None,
+            status: ReadStatus::Unread,
+            loan: None,
+            loan_history: Vec::new(),
+        };
+        book.validate().map_err(BookStoreError::Invalid)?;
+        Ok(book)
+    }
+
+    /// Set the ISBN, given with or without hyphens and spaces. Fails unless
+    /// its check digit is right.
+    pub fn with_isbn(mut self, isbn: &str) -> Result<Self, BookStoreError> {
+        self.isbn = Some(normalize_isbn(isbn).map_err(BookStoreError::Invalid)?);
+        Ok(self)
+    }
+
+    pub fn with_genre(mut self, genre: &str) -> Self {
+        self.genre = Some(genre.trim().to_string());
+        self
+    }
+
+    /// Fails unless `rating` is 1 to 5
+    pub fn with_rating(mut self, rating: u8) -> Result<Self, BookStoreError> {
+        self.rating = Some(rating);
+        self.validate().map_err(BookStoreError::Invalid)?;
+        Ok(self)
+    }
+
+    pub fn with_status(mut self, status: ReadStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Same title and author, ignoring case and surrounding spaces
+    pub fn same_as(&self, other: &Book) -> bool {
+        crate::normalize(&self.title) == crate::normalize(&other.title)
+            && crate::normalize(&self.author) == crate::normalize(&other.author)
+    }
+
+    /// Title or author contains `query`, ignoring case
+    pub fn matches(&self, query: &str) -> bool {
+        let query = crate::normalize(query);
+        self.title.to_lowercase().contains(&query) || self.author.to_lowercase().contains(&query)
+    }
+
+    /// Check what `new` and the `with_` methods check, for books that came
+    /// from a file.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.year == 0 || self.year > current_year() + 1 {
+            return Err(format!("implausible year {}", self.year));
+        }
+        if let Some(rating) = self.rating.filter(|r| !(1..=5).contains(r)) {
+            return Err(format!("rating {rating} is not between 1 and 5"));
+        }
+        if let Some(isbn) = &self.isbn {
+            normalize_isbn(isbn)?;
+        }
+        validate_loans(self)
+    }
+}
+
+/// `1984 by George Orwell, published in 1949`
+impl fmt::Display for Book {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} by {}, published in {}", self.title, self.author, self.year)
+    }
+}
+
+/// `isbn` without hyphens or spaces, if it's a valid ISBN-10 or ISBN-13.
+pub fn normalize_isbn(isbn: &str) -> Result<String, String> {
+    let digits: String = isbn.chars().filter(|c| !matches!(c, '-' | ' ')).collect::<String>().to_ascii_uppercase();
+    let value = |c: char| c.to_digit(10);
+    let valid = match digits.len() {
+        10 => {
+            // Weights 10 down to 1; the check digit may be X for 10
+            digits.chars().enumerate().try_fold(0, |sum, (i, c)| {
+                let d = if i == 9 && c == 'X' { Some(10) } else { value(c) }?;
+                Some(sum + d * (10 - i as u32))
+            }).is_some_and(|sum| sum % 11 == 0)
+        }
+        13 => {
+            // Weights alternate 1 and 3
+            digits.chars().enumerate().try_fold(0, |sum, (i, c)| {
+                Some(sum + value(c)? * if i % 2 == 0 { 1 } else { 3 })
+            }).is_some_and(|sum| sum % 10 == 0)
+        }
+        _ => false,
+    };
+    if !valid {
+        return Err(format!("invalid ISBN '{}'", isbn.trim()));
+    }
+    Ok(digits)
+}
+
+/// The current year, near enough: averaging leap years puts it off by at
+/// most a few hours around New Year.
+fn current_year() -> u16 {
+    const YEAR_SECS: u64 = 31_556_952;
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    (1970 + secs / YEAR_SECS) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isbn_check_digits() {
+        assert_eq!(normalize_isbn("0-306-40615-2"), Ok("0306406152".to_string()));
+        assert_eq!(normalize_isbn("978-0-306-40615-7"), Ok("9780306406157".to_string()));
+        // X stands for 10, but only as the check digit
+        assert_eq!(normalize_isbn("0-8044-2957-x"), Ok("080442957X".to_string()));
+        assert!(normalize_isbn("0-8044-2957-9").is_err());
+        assert!(normalize_isbn("X-8044-2957-0").is_err());
+        assert!(normalize_isbn("978-0-306-40615-8").is_err());
+        assert!(normalize_isbn("97803064061").is_err());
+        assert_eq!(normalize_isbn(" 978 0 306 4061A 7 "), Err("invalid ISBN '978 0 306 4061A 7'".to_string()));
+    }
+
+    #[test]
+    fn new_checks_year_and_rating() {
+        assert!(Book::new("Dune", "Frank Herbert", 1965).is_ok());
+        let invalid = |result: Result<Book, BookStoreError>| result.unwrap_err().to_string();
+        assert_eq!(invalid(Book::new("Dune", "Frank Herbert", 0)), "invalid book: implausible year 0");
+        assert_eq!(invalid(Book::new("Dune", "Frank Herbert", 9999)), "invalid book: implausible year 9999");
+        let dune = Book::new("Dune", "Frank Herbert", 1965).unwrap();
+        assert_eq!(dune.clone().with_rating(5).unwrap().rating, Some(5));
+        assert_eq!(invalid(dune.clone().with_rating(6)), "invalid book: rating 6 is not between 1 and 5");
+        assert!(dune.clone().with_rating(0).is_err());
+        assert_eq!(dune.with_isbn("978-0-441-17271-9").unwrap().isbn.as_deref(), Some("9780441172719"));
+    }
+
+    #[test]
+    fn read_status_names() {
+        assert_eq!("Read".parse(), Ok(ReadStatus::Read));
+        assert_eq!("".parse(), Ok(ReadStatus::Unread));
+        assert_eq!(ReadStatus::Reading.to_string(), "reading");
+        assert!("finished".parse::<ReadStatus>().is_err());
+    }
+}
@@ -0,0 +1,358 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::loan::{loan_fields, loans_from_fields};
+use crate::{Book, BookReader, BookStoreError, ReadStatus, save_books};
+
+/// The file formats books can be kept in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The original headerless `title,author,year` lines
+    Text,
+    /// CSV with a `title,author,year,...` header row, and the loan in flat
+    /// columns after the rest
+    Csv,
+    /// A JSON array of book objects
+    Json,
+}
+
+impl Format {
+    /// By extension: `.json`, `.csv`, and `.txt` for plain text
+    pub fn from_path(path: &str) -> Option<Format> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            "txt" => Some(Format::Text),
+            _ => None,
+        }
+    }
+
+    /// By contents: a JSON array, CSV with a header, or else plain text
+    pub fn detect(contents: &str) -> Format {
+        let start = contents.trim_start();
+        let first_line = start.lines().next().unwrap_or_default();
+        if start.starts_with('[') {
+            Format::Json
+        } else if first_line.trim().to_ascii_lowercase().starts_with("title,author,year") {
+            Format::Csv
+        } else {
+            Format::Text
+        }
+    }
+}
+
+pub fn save_json(books: &[Book], filename: &str) -> Result<(), BookStoreError> {
+    let mut writer = BufWriter::new(File::create(filename)?);
+    serde_json::to_writer_pretty(&mut writer, books).map_err(json_error)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn load_json(filename: &str) -> Result<Vec<Book>, BookStoreError> {
+    json_books(BufReader::new(File::open(filename)?))
+}
+
+fn json_books(reader: impl Read) -> Result<Vec<Book>, BookStoreError> {
+    let books: Vec<Book> = serde_json::from_reader(reader).map_err(json_error)?;
+    for (i, book) in books.iter().enumerate() {
+        book.validate().map_err(|e| BookStoreError::Invalid(format!("book {}: {e}", i + 1)))?;
+    }
+    Ok(books)
+}
+
+/// A book as a CSV row. A CSV cell can't hold a nested `Loan`, so the loan
+/// is spread over the columns `loan_fields` gives. Any column from `isbn` on
+/// may be missing, for files written before it existed.
+#[derive(Serialize, Deserialize)]
+struct CsvRow {
+    title: String,
+    author: String,
+    year: u16,
+    #[serde(default)]
+    isbn: Option<String>,
+    #[serde(default)]
+    genre: Option<String>,
+    #[serde(default)]
+    rating: Option<u8>,
+    #[serde(default)]
+    status: ReadStatus,
+    #[serde(default)]
+    borrower: String,
+    #[serde(default)]
+    checked_out_at: String,
+    #[serde(default)]
+    due: String,
+    #[serde(default)]
+    loan_history: String,
+}
+
+impl CsvRow {
+    fn new(book: &Book) -> Self {
+        let [borrower, checked_out_at, due, loan_history] = loan_fields(book);
+        CsvRow {
+            title: book.title.clone(),
+            author: book.author.clone(),
+            year: book.year,
+            isbn: book.isbn.clone(),
+            genre: book.genre.clone(),
+            rating: book.rating,
+            status: book.status,
+            borrower,
+            checked_out_at,
+            due,
+            loan_history,
+        }
+    }
+
+    fn into_book(self) -> Result<Book, String> {
+        let (loan, loan_history) =
+            loans_from_fields(&self.borrower, &self.checked_out_at, &self.due, &self.loan_history)?;
+        let book = Book {
+            title: self.title,
+            author: self.author,
+            year: self.year,
+            isbn: self.isbn,
+            genre: self.genre,
+            rating: self.rating,
+            status: self.status,
+            loan,
+            loan_history,
+        };
+        book.validate()?;
+        Ok(book)
+    }
+}
+
+pub fn save_csv(books: &[Book], filename: &str) -> Result<(), BookStoreError> {
+    let mut writer = csv::Writer::from_path(filename).map_err(csv_error)?;
+    for book in books {
+        writer.serialize(CsvRow::new(book)).map_err(csv_error)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn load_csv(filename: &str) -> Result<Vec<Book>, BookStoreError> {
+    csv_books(File::open(filename)?)?.collect()
+}
+
+/// The books in CSV from `reader`, read a record at a time
+fn csv_books(reader: impl Read) -> Result<impl Iterator<Item = Result<Book, BookStoreError>>, BookStoreError> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    Ok(reader.into_records().map(move |record| {
+        let record = record.map_err(csv_error)?;
+        let line = record.position().map_or(0, |p| p.line() as usize);
+        let row: CsvRow = record.deserialize(Some(&headers)).map_err(csv_error)?;
+        row.into_book().map_err(|message| BookStoreError::Parse { line, message })
+    }))
+}
+
+/// Load books in whichever format `filename` is in: by extension if it has
+/// a known one, by contents otherwise.
+pub fn load_auto(filename: &str) -> Result<Vec<Book>, BookStoreError> {
+    load_auto_iter(filename)?.collect()
+}
+
+/// The books `load_auto` would load, read as they're asked for. Plain text
+/// and CSV are read a record at a time; a JSON array is read whole first.
+pub fn load_auto_iter(
+    filename: &str,
+) -> Result<Box<dyn Iterator<Item = Result<Book, BookStoreError>>>, BookStoreError> {
+    let mut reader = BufReader::new(File::open(filename)?);
+    let format = match Format::from_path(filename) {
+        Some(format) => format,
+        // The start of the file is enough to tell
+        None => Format::detect(&String::from_utf8_lossy(reader.fill_buf()?)),
+    };
+    Ok(match format {
+        Format::Text => Box::new(BookReader::new(reader)),
+        Format::Csv => Box::new(csv_books(reader)?),
+        Format::Json => Box::new(json_books(reader)?.into_iter().map(Ok)),
+    })
+}
+
+/// Save books in the format `filename`'s extension calls for, plain text if
+/// it has none, by way of `<filename>.tmp` (see `replace_atomically`).
+pub fn save_auto(books: &[Book], filename: &str) -> Result<(), BookStoreError> {
+    replace_atomically(filename, |temp| match Format::from_path(filename).unwrap_or(Format::Text) {
+        Format::Text => save_books(books, temp),
+        Format::Csv => save_csv(books, temp),
+        Format::Json => save_json(books, temp),
+    })
+}
+
+/// Have `write` save to `<filename>.tmp`, which is flushed to disk and then
+/// replaces `filename`, so an interrupted save or a crash leaves the old file
+/// as it was. The temporary file is removed if any step fails.
+pub(crate) fn replace_atomically(
+    filename: &str,
+    write: impl FnOnce(&str) -> Result<(), BookStoreError>,
+) -> Result<(), BookStoreError> {
+    let temp = format!("{filename}.tmp");
+    let saved = write(&temp)
+        .and_then(|()| Ok(fs::OpenOptions::new().write(true).open(&temp)?.sync_all()?))
+        .and_then(|()| Ok(fs::rename(&temp, filename)?));
+    if let Err(e) = saved {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Copy the books in `from` to `to`, converting between formats as their
+/// names say. Returns how many books were copied.
+pub fn convert(from: &str, to: &str) -> Result<usize, BookStoreError> {
+    let books = load_auto(from)?;
+    save_auto(&books, to)?;
+    Ok(books.len())
+}
+
+fn json_error(e: serde_json::Error) -> BookStoreError {
+    if e.is_io() {
+        return BookStoreError::Io(e.into());
+    }
+    BookStoreError::Parse { line: e.line(), message: e.to_string() }
+}
+
+fn csv_error(e: csv::Error) -> BookStoreError {
+    if e.is_io_error() {
+        let csv::ErrorKind::Io(e) = e.into_kind() else { unreachable!() };
+        return BookStoreError::Io(e);
+    }
+    let line = e.position().map_or(0, |p| p.line() as usize);
+    BookStoreError::Parse { line, message: e.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Loan, ReturnedLoan};
+
+    fn books() -> Vec<Book> {
+        let hobbit = Book::new("The \"Hobbit\"", "Tolkien, J. R. R.", 1937)
+            .unwrap()
+            .with_isbn("0-261-10221-4")
+            .unwrap()
+            .with_genre("Fantasy, children's")
+            .with_rating(4)
+            .unwrap()
+            .with_status(ReadStatus::Read);
+        vec![
+            Book::new("Harry Potter, Book 1", "J. K. Rowling", 1997).unwrap(),
+            lent(hobbit),
+            Book::new("", "", 2000).unwrap(),
+        ]
+    }
+
+    /// `book` lent to Bob, after a loan to someone whose name needs quoting
+    fn lent(mut book: Book) -> Book {
+        let time = |t: &str| t.parse().unwrap();
+        let loan = |borrower: &str, checked_out_at, due| Loan { borrower: borrower.to_string(), checked_out_at, due };
+        book.loan_history = vec![ReturnedLoan {
+            loan: loan("Alice, \"A\"", time("2024-03-01T10:00:00Z"), time("2024-03-15T10:00:00Z")),
+            returned_at: time("2024-03-20T08:30:00.123456789Z"),
+        }];
+        book.loan = Some(loan("Bob", time("2024-04-01T09:15:00.5Z"), time("2024-04-15T23:59:59Z")));
+        book
+    }
+
+    /// A path in the temp dir, removed when dropped
+    struct TempFile(String);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("module-3-{}-{name}", std::process::id()));
+            TempFile(path.to_str().unwrap().to_string())
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        for name in ["books.txt", "books.csv", "books.json", "books"] {
+            let file = TempFile::new(name);
+            save_auto(&books(), &file.0).unwrap();
+            assert_eq!(load_auto(&file.0).unwrap(), books(), "{name}");
+        }
+    }
+
+    #[test]
+    fn csv_has_a_header_and_json_is_an_array() {
+        let csv = TempFile::new("header.csv");
+        save_csv(&books()[..1], &csv.0).unwrap();
+        assert_eq!(
+            fs::read_to_string(&csv.0).unwrap(),
+            "title,author,year,isbn,genre,rating,status,borrower,checked_out_at,due,loan_history\n\
+             \"Harry Potter, Book 1\",J. K. Rowling,1997,,,,unread,,,,\n"
+        );
+
+        let json = TempFile::new("array.json");
+        save_json(&books()[2..], &json.0).unwrap();
+        assert_eq!(
+            fs::read_to_string(&json.0).unwrap(),
+            "[\n  {\n    \"title\": \"\",\n    \"author\": \"\",\n    \"year\": 2000,\n    \"isbn\": null,\n    \
+             \"genre\": null,\n    \"rating\": null,\n    \"status\": \"unread\"\n  }\n]\n"
+        );
+    }
+
+    #[test]
+    fn contents_decide_without_an_extension() {
+        assert_eq!(Format::detect("  [{\"title\": \"Dune\"}]"), Format::Json);
+        assert_eq!(Format::detect("title,author,year\nDune,Frank Herbert,1965\n"), Format::Csv);
+        assert_eq!(Format::detect("Dune,Frank Herbert,1965\n"), Format::Text);
+
+        let file = TempFile::new("exported");
+        fs::write(&file.0, "title,author,year\nDune,Frank Herbert,1965\n").unwrap();
+        assert_eq!(load_auto(&file.0).unwrap(), [Book::new("Dune", "Frank Herbert", 1965).unwrap()]);
+    }
+
+    #[test]
+    fn convert_is_lossless_across_formats() {
+        let (txt, csv, json, back) =
+            (TempFile::new("c.txt"), TempFile::new("c.csv"), TempFile::new("c.json"), TempFile::new("back.txt"));
+        save_auto(&books(), &txt.0).unwrap();
+        assert_eq!(convert(&txt.0, &csv.0).unwrap(), 3);
+        assert_eq!(convert(&csv.0, &json.0).unwrap(), 3);
+        assert_eq!(convert(&json.0, &back.0).unwrap(), 3);
+        assert_eq!(fs::read_to_string(&back.0).unwrap(), fs::read_to_string(&txt.0).unwrap());
+    }
+
+    #[test]
+    fn bad_records_name_the_line() {
+        let csv = TempFile::new("bad.csv");
+        fs::write(&csv.0, "title,author,year\nDune,Frank Herbert,1965\nEmma,Jane Austen,soon\n").unwrap();
+        assert!(matches!(load_csv(&csv.0), Err(BookStoreError::Parse { line: 3, .. })));
+
+        let json = TempFile::new("bad.json");
+        fs::write(&json.0, "[\n  {\"title\": \"Dune\", \"author\": \"Frank Herbert\"}\n]").unwrap();
+        let err = load_json(&json.0).unwrap_err();
+        assert!(matches!(err, BookStoreError::Parse { line: 2, .. }), "{err}");
+
+        fs::write(&csv.0, "title,author,year,isbn,genre,rating,status\nDune,Frank Herbert,1965,,,7,read\n").unwrap();
+        assert_eq!(load_csv(&csv.0).unwrap_err().to_string(), "line 2: rating 7 is not between 1 and 5");
+        fs::write(&json.0, r#"[{"title": "Dune", "author": "Frank Herbert", "year": 1965, "isbn": "123"}]"#).unwrap();
+        assert_eq!(load_json(&json.0).unwrap_err().to_string(), "invalid book: book 1: invalid ISBN '123'");
+    }
+
+    #[test]
+    fn older_files_leave_new_fields_unset() {
+        let dune = Book::new("Dune", "Frank Herbert", 1965).unwrap();
+        let csv = TempFile::new("old.csv");
+        fs::write(&csv.0, "title,author,year\nDune,Frank Herbert,1965\n").unwrap();
+        assert_eq!(load_csv(&csv.0).unwrap(), std::slice::from_ref(&dune));
+        let json = TempFile::new("old.json");
+        fs::write(&json.0, r#"[{"title": "Dune", "author": "Frank Herbert", "year": 1965}]"#).unwrap();
+        assert_eq!(load_json(&json.0).unwrap(), [dune]);
+    }
+}
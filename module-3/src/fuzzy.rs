@@ -0,0 +1,216 @@
+use crate::{Book, Library};
+
+/// Scores below this are too far off to count as a match
+const MIN_SCORE: f64 = 0.6;
+
+/// A book `Library::fuzzy_search` found, and how well it matched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuzzyMatch<'a> {
+    pub book: &'a Book,
+    /// 1.0 for a title or author equal to the query, down towards 0.0 the
+    /// more edits it takes to get from one to the other
+    pub score: f64,
+}
+
+/// `text` in lower case with the accents taken off letters, so "Café" and
+/// "cafe" compare equal
+fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match unaccented(c) {
+            Some(letters) => folded.push_str(letters),
+            None => folded.push(c),
+        }
+    }
+    folded
+}
+
+/// The plain letters for an accented Latin one
+fn unaccented(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'è'..='ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ì'..='ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' | 'ľ' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò'..='ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ß' => "ss",
+        'ś' | 'š' | 'ş' => "s",
+        'ť' | 'ţ' => "t",
+        'ù'..='ü' | 'ū' | 'ů' | 'ű' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+/// The folded words of `text`, with punctuation dropped
+fn words(text: &str) -> Vec<String> {
+    fold(text).split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(str::to_string).collect()
+}
+
+/// Edits to turn `a` into `b`: inserting, deleting or changing a letter, or
+/// swapping two next to each other ("Tolkein" is one edit from "Tolkien")
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    // Rows of the usual table for the prefixes of `a` two back, one back,
+    // and now
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let change = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + change);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+/// 1.0 for equal strings, less the more of them has to change
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(&a, &b) as f64 / longest as f64
+}
+
+/// How well `field` matches the query `words`. Mostly that's how close the
+/// closest run of as many words in `field` is, so "hobit" finds "The
+/// Hobbit"; the rest is how close all of it is, so only an equal field
+/// scores 1.0.
+fn field_score(query: &[String], field: &str) -> f64 {
+    let (query, words) = (query.join(" "), words(field));
+    let whole = similarity(&query, &words.join(" "));
+    let query_words = query.split(' ').count();
+    if whole == 1.0 || words.len() <= query_words {
+        return whole;
+    }
+    let closest = words.windows(query_words).map(|run| similarity(&query, &run.join(" "))).fold(0.0, f64::max);
+    0.9 * closest + 0.1 * whole
+}
+
+impl Library {
+    /// Up to `max_results` books whose title or author is close to `query`,
+    /// ignoring case and accents, best first. Books that score the same come
+    /// in order of title.
+    pub fn fuzzy_search(&self, query: &str, max_results: usize) -> Vec<FuzzyMatch<'_>> {
+        let query = words(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut found: Vec<FuzzyMatch> = self
+            .iter()
+            .map(|book| {
+                let score = field_score(&query, &book.title).max(field_score(&query, &book.author));
+                FuzzyMatch { book, score }
+            })
+            .filter(|found| found.score >= MIN_SCORE)
+            .collect();
+        found.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| fold(&a.book.title).cmp(&fold(&b.book.title)))
+                .then_with(|| a.book.title.cmp(&b.book.title))
+        });
+        found.truncate(max_results);
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(books: &[(&str, &str, u16)]) -> Library {
+        let mut library = Library::new();
+        for &(title, author, year) in books {
+            library.add(Book::new(title, author, year).unwrap()).unwrap();
+        }
+        library
+    }
+
+    fn titles(found: Vec<FuzzyMatch<'_>>) -> Vec<&str> {
+        found.into_iter().map(|found| found.book.title.as_str()).collect()
+    }
+
+    fn distance(a: &str, b: &str) -> usize {
+        edit_distance(&a.chars().collect::<Vec<_>>(), &b.chars().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(distance("tolkein", "tolkien"), 1);
+        assert_eq!(distance("hobit", "hobbit"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "emma"), 4);
+        assert_eq!(distance("dune", "dune"), 0);
+        assert_eq!(similarity("abcd", "abdc"), 0.75);
+    }
+
+    #[test]
+    fn accents_and_case_fold_away() {
+        assert_eq!(fold("Crème Brûlée"), "creme brulee");
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(words("J. R. R. Tolkien"), ["j", "r", "r", "tolkien"]);
+
+        let library = library(&[("Café Society", "Ana Núñez", 2001), ("Cafe Stories", "Bo Li", 2003)]);
+        let found = library.fuzzy_search("cafe society", 10);
+        assert_eq!((found[0].book.title.as_str(), found[0].score), ("Café Society", 1.0));
+        assert_eq!(library.fuzzy_search("NUNEZ", 10)[0].book.author, "Ana Núñez");
+    }
+
+    #[test]
+    fn transposed_and_missing_letters_still_match() {
+        let library = library(&[
+            ("The Hobbit", "J. R. R. Tolkien", 1937),
+            ("Dune", "Frank Herbert", 1965),
+            ("Emma", "Jane Austen", 1815),
+        ]);
+        assert_eq!(titles(library.fuzzy_search("Tolkein", 10)), ["The Hobbit"]);
+        assert_eq!(titles(library.fuzzy_search("hobit", 10)), ["The Hobbit"]);
+        assert_eq!(titles(library.fuzzy_search("Frnak Hrebert", 10)), ["Dune"]);
+        assert_eq!(titles(library.fuzzy_search("Jane Austin", 10)), ["Emma"]);
+        assert!(library.fuzzy_search("Neuromancer", 10).is_empty());
+        assert!(library.fuzzy_search(" ?! ", 10).is_empty());
+    }
+
+    #[test]
+    fn exact_matches_rank_first() {
+        let library = library(&[
+            ("Dune Messiah", "Frank Herbert", 1969),
+            ("Dunes", "Someone Else", 2010),
+            ("Dune", "Frank Herbert", 1965),
+            ("Emma", "Dune", 1990),
+        ]);
+        let found = library.fuzzy_search("dune", 10);
+        // Emma's author is exactly "Dune", so it ties with Dune
+        assert_eq!(titles(found.clone()), ["Dune", "Emma", "Dune Messiah", "Dunes"]);
+        assert_eq!((found[0].score, found[1].score), (1.0, 1.0));
+        assert!(found[2].score < 1.0 && found[2].score > found[3].score);
+    }
+
+    #[test]
+    fn ties_go_by_title_and_results_are_capped() {
+        let library = library(&[
+            ("Zeta Test", "A. Writer", 2000),
+            ("meta test", "A. Writer", 2000),
+            ("Beta Test", "A. Writer", 2000),
+        ]);
+        let found = library.fuzzy_search("test", 10);
+        assert!(found.iter().all(|found| found.score == 0.9 + 0.1 * similarity("test", "beta test")));
+        // Case doesn't count in the order either
+        assert_eq!(titles(found), ["Beta Test", "meta test", "Zeta Test"]);
+        assert_eq!(titles(library.fuzzy_search("test", 2)), ["Beta Test", "meta test"]);
+    }
+}
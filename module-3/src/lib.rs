@@ -0,0 +1,765 @@
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::str::FromStr;
+
+use loan::{loan_fields, loans_from_fields};
+
+mod book;
+mod formats;
+mod fuzzy;
+mod loan;
+mod merge;
+mod stream;
+
+pub use book::{Book, ReadStatus, normalize_isbn};
+pub use formats::{
+    Format, convert, load_auto, load_auto_iter, load_csv, load_json, save_auto, save_csv, save_json,
+};
+pub use fuzzy::FuzzyMatch;
+pub use loan::{Loan, ReturnedLoan};
+pub use merge::{MergeConflict, MergeReport, MergeStrategy};
+pub use stream::{BookReader, append_book, load_books_iter, save_books_atomic};
+
+fn normalize(text: &str) -> String {
+    text.trim().to_lowercase()
+}
+
+/// Why a book file couldn't be saved or loaded, or a library change was
+/// refused.
+#[derive(Debug)]
+pub enum BookStoreError {
+    Io(io::Error),
+    /// A record that isn't `title,author,year[,isbn,genre,rating,status]`
+    /// (plus the loan fields, for books that have been lent) or holds an
+    /// invalid book; `line` counts from 1
+    Parse { line: usize, message: String },
+    /// A book that fails `Book::validate`
+    Invalid(String),
+    /// A book with the same title and author is already in the library
+    Duplicate { title: String, author: String },
+    /// No book has this title
+    NotFound(String),
+    /// Checking out a book that's already lent out
+    OnLoan { title: String, borrower: String },
+    /// Checking in a book that isn't lent out
+    NotOnLoan(String),
+}
+
+impl fmt::Display for BookStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookStoreError::Io(e) => write!(f, "I/O error: {e}"),
+            BookStoreError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            BookStoreError::Duplicate { title, author } => write!(f, "'{title}' by {author} is already in the library"),
+            BookStoreError::NotFound(title) => write!(f, "no book titled '{title}'"),
+            BookStoreError::Invalid(message) => write!(f, "invalid book: {message}"),
+            BookStoreError::OnLoan { title, borrower } => write!(f, "'{title}' is already lent to {borrower}"),
+            BookStoreError::NotOnLoan(title) => write!(f, "'{title}' is not lent out"),
+        }
+    }
+}
+
+impl std::error::Error for BookStoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BookStoreError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BookStoreError {
+    fn from(e: io::Error) -> Self {
+        BookStoreError::Io(e)
+    }
+}
+
+/// Quote a field the RFC 4180 way if it needs it: wrap it in double quotes
+/// and double any quotes inside.
+fn quote_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Split one record into fields. Returns `Ok(None)` if a quoted field is
+/// still open at the end, meaning the record continues on the next line.
+fn split_record(record: &str) -> Result<Option<Vec<String>>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = record.chars().peekable();
+    loop {
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Ok(None),
+                }
+            }
+            match chars.next() {
+                Some(',') => fields.push(std::mem::take(&mut field)),
+                None => {
+                    fields.push(field);
+                    return Ok(Some(fields));
+                }
+                Some(c) => return Err(format!("unexpected '{c}' after a quoted field")),
+            }
+        } else {
+            loop {
+                match chars.next() {
+                    Some(',') => break,
+                    Some('"') => return Err("stray '\"' in an unquoted field".to_string()),
+                    Some(c) => field.push(c),
+                    None => {
+                        fields.push(field);
+                        return Ok(Some(fields));
+                    }
+                }
+            }
+            fields.push(std::mem::take(&mut field));
+        }
+    }
+}
+
+fn write_books(writer: impl Write, books: impl IntoIterator<Item = impl Borrow<Book>>) -> io::Result<()> {
+    let mut writer = BufWriter::new(writer);
+    for book in books {
+        let book = book.borrow();
+        // Format: title,author,year,isbn,genre,rating,status
+        write!(
+            writer,
+            "{},{},{},{},{},{},{}",
+            quote_field(&book.title),
+            quote_field(&book.author),
+            book.year,
+            book.isbn.as_deref().unwrap_or_default(),
+            quote_field(book.genre.as_deref().unwrap_or_default()),
+            book.rating.map(|r| r.to_string()).unwrap_or_default(),
+            book.status
+        )?;
+        // then borrower,checked_out_at,due,loan_history, only for books that
+        // have been lent so older readers still take the rest
+        if book.loan.is_some() || !book.loan_history.is_empty() {
+            for field in loan_fields(book) {
+                write!(writer, ",{}", quote_field(&field))?;
+            }
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+pub(crate) fn read_books(reader: impl io::Read) -> Result<Vec<Book>, BookStoreError> {
+    BookReader::new(io::BufReader::new(reader)).collect()
+}
+
+/// Files written before the optional fields existed have just the first
+/// three; the rest are left unset for those. Only books that have been lent
+/// have the last four.
+fn book_from_fields(fields: Vec<String>) -> Result<Book, String> {
+    if ![3, 7, 11].contains(&fields.len()) {
+        return Err(format!("expected 3, 7 or 11 fields, found {}", fields.len()));
+    }
+    let mut fields = fields.into_iter();
+    let (title, author, year) = (fields.next().unwrap(), fields.next().unwrap(), fields.next().unwrap());
+    let mut next = || fields.next().filter(|f| !f.trim().is_empty());
+    let year = year.trim().parse::<u16>().map_err(|_| format!("invalid year '{year}'"))?;
+    let mut book = Book {
+        title,
+        author,
+        year,
+        isbn: None,
+        genre: None,
+        rating: None,
+        status: ReadStatus::Unread,
+        loan: None,
+        loan_history: Vec::new(),
+    };
+    book.isbn = next().map(|isbn| normalize_isbn(&isbn)).transpose()?;
+    book.genre = next();
+    book.rating = next().map(|r| r.trim().parse().map_err(|_| format!("invalid rating '{r}'"))).transpose()?;
+    book.status = next().unwrap_or_default().parse()?;
+    let [borrower, checked_out_at, due, history] = [(); 4].map(|()| fields.next().unwrap_or_default());
+    (book.loan, book.loan_history) = loans_from_fields(&borrower, &checked_out_at, &due, &history)?;
+    book.validate()?;
+    Ok(book)
+}
+
+pub fn save_books(books: &[Book], filename: &str) -> Result<(), BookStoreError> {
+    write_books(File::create(filename)?, books)?;
+    Ok(())
+}
+
+/// All the books in `filename` at once; `load_books_iter` reads them one at
+/// a time.
+pub fn load_books(filename: &str) -> Result<Vec<Book>, BookStoreError> {
+    load_books_iter(filename)?.collect()
+}
+
+/// What `Library::add` does with a book whose title and author are already
+/// there.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Refuse it with `BookStoreError::Duplicate`
+    #[default]
+    Reject,
+    /// Replace the existing entry with it, in place
+    Merge,
+}
+
+/// What to order books by when listing them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Title,
+    Author,
+    Year,
+}
+
+impl SortKey {
+    fn value(self, book: &Book) -> SortValue {
+        match self {
+            SortKey::Title => SortValue::Text(normalize(&book.title)),
+            SortKey::Author => SortValue::Text(normalize(&book.author)),
+            SortKey::Year => SortValue::Year(book.year),
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "title" => Ok(SortKey::Title),
+            "author" => Ok(SortKey::Author),
+            "year" => Ok(SortKey::Year),
+            other => Err(format!("unknown sort key '{other}' (valid: title, author, year)")),
+        }
+    }
+}
+
+/// One book's value for a `SortKey`
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Text(String),
+    Year(u16),
+}
+
+/// Positions in `Library::books` under each key, in ascending order
+type Index<K> = BTreeMap<K, Vec<usize>>;
+
+fn index_insert<K: Ord>(index: &mut Index<K>, key: K, position: usize) {
+    let positions = index.entry(key).or_default();
+    let at = positions.partition_point(|&p| p < position);
+    positions.insert(at, position);
+}
+
+fn index_remove<K: Ord>(index: &mut Index<K>, key: &K, position: usize) {
+    if let Some(positions) = index.get_mut(key) {
+        positions.retain(|&p| p != position);
+        if positions.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+/// A collection of books with no two sharing a title and author, indexed by
+/// author and year.
+#[derive(Debug, Clone, Default)]
+pub struct Library {
+    books: Vec<Book>,
+    policy: DuplicatePolicy,
+    /// Keyed by normalized author
+    by_author: Index<String>,
+    by_year: Index<u16>,
+}
+
+impl Library {
+    pub fn new() -> Self {
+        Library::default()
+    }
+
+    pub fn with_policy(policy: DuplicatePolicy) -> Self {
+        Library { policy, ..Library::default() }
+    }
+
+    pub fn add(&mut self, book: Book) -> Result<(), BookStoreError> {
+        match self.duplicate_of(&book) {
+            None => self.push_book(book),
+            Some(i) if self.policy == DuplicatePolicy::Merge => {
+                self.replace_book(i, book);
+            }
+            Some(_) => return Err(BookStoreError::Duplicate { title: book.title, author: book.author }),
+        }
+        Ok(())
+    }
+
+    /// Take out the first book with exactly this title, ignoring case.
+    pub fn remove(&mut self, title: &str) -> Option<Book> {
+        let i = self.position(title)?;
+        let book = self.books.remove(i);
+        index_remove(&mut self.by_author, &normalize(&book.author), i);
+        index_remove(&mut self.by_year, &book.year, i);
+        // Everything after it moved down one
+        for positions in self.by_author.values_mut().chain(self.by_year.values_mut()) {
+            for p in positions.iter_mut().filter(|p| **p > i) {
+                *p -= 1;
+            }
+        }
+        Some(book)
+    }
+
+    /// Replace the first book titled `title` with `book`, returning the old
+    /// one. Fails if there's no such book, or if `book` would duplicate
+    /// another entry.
+    pub fn update(&mut self, title: &str, book: Book) -> Result<Book, BookStoreError> {
+        let i = self.position(title).ok_or_else(|| BookStoreError::NotFound(title.to_string()))?;
+        if self.duplicate_of(&book).is_some_and(|j| j != i) {
+            return Err(BookStoreError::Duplicate { title: book.title, author: book.author });
+        }
+        Ok(self.replace_book(i, book))
+    }
+
+    /// Books whose title contains `query`, ignoring case
+    pub fn find_by_title(&self, query: &str) -> Vec<&Book> {
+        let query = normalize(query);
+        self.books.iter().filter(|b| b.title.to_lowercase().contains(&query)).collect()
+    }
+
+    /// Books whose author contains `query`, ignoring case. Looking up a
+    /// whole name is quicker with `by_author`.
+    pub fn find_by_author(&self, query: &str) -> Vec<&Book> {
+        let query = normalize(query);
+        self.books.iter().filter(|b| b.author.to_lowercase().contains(&query)).collect()
+    }
+
+    /// Books by exactly this author, ignoring case and surrounding spaces
+    pub fn by_author(&self, author: &str) -> Vec<&Book> {
+        self.by_author.get(&normalize(author)).map_or_else(Vec::new, |positions| self.at(positions))
+    }
+
+    /// Every author, once each and in order, as first written
+    pub fn authors(&self) -> Vec<&str> {
+        self.by_author.values().map(|positions| self.books[positions[0]].author.trim()).collect()
+    }
+
+    /// Books not marked as read
+    pub fn unread(&self) -> Vec<&Book> {
+        self.books.iter().filter(|b| b.status != ReadStatus::Read).collect()
+    }
+
+    /// Books in `genre`, ignoring case
+    pub fn by_genre(&self, genre: &str) -> Vec<&Book> {
+        let genre = normalize(genre);
+        self.books.iter().filter(|b| b.genre.as_deref().is_some_and(|g| normalize(g) == genre)).collect()
+    }
+
+    /// Books whose title or author contains `query`, ignoring case
+    pub fn search(&self, query: &str) -> Vec<&Book> {
+        self.books.iter().filter(|b| b.matches(query)).collect()
+    }
+
+    pub fn books_in_year_range(&self, years: Range<u16>) -> Vec<&Book> {
+        let mut positions: Vec<usize> = self.by_year.range(years).flat_map(|(_, p)| p).copied().collect();
+        positions.sort_unstable();
+        self.at(&positions)
+    }
+
+    /// All books ordered by each of `keys` in turn, ignoring case; books
+    /// that tie on all of them keep the order they were added in.
+    pub fn sorted(&self, keys: &[SortKey]) -> Vec<&Book> {
+        // The indexes are already in order of the first key, if it's the
+        // author or year, so only books that tie on it need sorting
+        let everything: Vec<usize> = (0..self.books.len()).collect();
+        let (groups, rest): (Vec<&[usize]>, &[SortKey]) = match keys.split_first() {
+            Some((SortKey::Author, rest)) => (self.by_author.values().map(Vec::as_slice).collect(), rest),
+            Some((SortKey::Year, rest)) => (self.by_year.values().map(Vec::as_slice).collect(), rest),
+            _ => (vec![&everything], keys),
+        };
+        let mut sorted = Vec::with_capacity(self.books.len());
+        for group in groups {
+            let mut books = self.at(group);
+            if !rest.is_empty() {
+                books.sort_by_cached_key(|b| rest.iter().map(|key| key.value(b)).collect::<Vec<_>>());
+            }
+            sorted.append(&mut books);
+        }
+        sorted
+    }
+
+    /// How many books came out in each decade, keyed by its first year
+    pub fn count_by_decade(&self) -> BTreeMap<u16, usize> {
+        let mut counts = BTreeMap::new();
+        for (year, positions) in &self.by_year {
+            *counts.entry(year / 10 * 10).or_default() += positions.len();
+        }
+        counts
+    }
+
+    /// How many books each author has, under the author's name as first
+    /// written
+    pub fn count_by_author(&self) -> BTreeMap<String, usize> {
+        self.by_author.values().map(|p| (self.books[p[0]].author.trim().to_string(), p.len())).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.books.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.books.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Book> {
+        self.books.iter()
+    }
+
+    /// Save in the format `filename`'s extension calls for (see `save_auto`)
+    pub fn save(&self, filename: &str) -> Result<(), BookStoreError> {
+        save_auto(&self.books, filename)
+    }
+
+    /// Load a file in any format (see `load_auto`), adding each book under
+    /// `policy`.
+    pub fn load(filename: &str, policy: DuplicatePolicy) -> Result<Self, BookStoreError> {
+        let mut library = Library::with_policy(policy);
+        for book in load_auto(filename)? {
+            library.add(book)?;
+        }
+        Ok(library)
+    }
+
+    fn position(&self, title: &str) -> Option<usize> {
+        let title = normalize(title);
+        self.books.iter().position(|b| normalize(&b.title) == title)
+    }
+
+    /// The first book titled `title`, for changes that leave its author and
+    /// year alone
+    fn book_mut(&mut self, title: &str) -> Result<&mut Book, BookStoreError> {
+        let i = self.position(title).ok_or_else(|| BookStoreError::NotFound(title.to_string()))?;
+        Ok(&mut self.books[i])
+    }
+
+    /// Position of the book with the same title and author as `book`
+    fn duplicate_of(&self, book: &Book) -> Option<usize> {
+        let positions = self.by_author.get(&normalize(&book.author))?;
+        positions.iter().copied().find(|&i| self.books[i].same_as(book))
+    }
+
+    fn at(&self, positions: &[usize]) -> Vec<&Book> {
+        positions.iter().map(|&i| &self.books[i]).collect()
+    }
+
+    /// Append `book`, which mustn't duplicate another
+    fn push_book(&mut self, book: Book) {
+        let i = self.books.len();
+        index_insert(&mut self.by_author, normalize(&book.author), i);
+        index_insert(&mut self.by_year, book.year, i);
+        self.books.push(book);
+    }
+
+    /// Put `book` in place of the one at `i`, returning that one
+    fn replace_book(&mut self, i: usize, book: Book) -> Book {
+        let old = std::mem::replace(&mut self.books[i], book);
+        index_remove(&mut self.by_author, &normalize(&old.author), i);
+        index_remove(&mut self.by_year, &old.year, i);
+        index_insert(&mut self.by_author, normalize(&self.books[i].author), i);
+        index_insert(&mut self.by_year, self.books[i].year, i);
+        old
+    }
+}
+
+impl<'a> IntoIterator for &'a Library {
+    type Item = &'a Book;
+    type IntoIter = std::slice::Iter<'a, Book>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.books.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(title: &str, author: &str, year: u16) -> Book {
+        Book::new(title, author, year).unwrap()
+    }
+
+    fn library() -> Library {
+        let mut library = Library::new();
+        library.add(book("1984", "George Orwell", 1949)).unwrap();
+        library.add(book("Animal Farm", "George Orwell", 1945)).unwrap();
+        library.add(book("The Hobbit", "J. R. R. Tolkien", 1937)).unwrap();
+        library
+    }
+
+    fn titles(books: Vec<&Book>) -> Vec<&str> {
+        books.iter().map(|b| b.title.as_str()).collect()
+    }
+
+    fn round_trip(books: &[Book]) -> Vec<Book> {
+        let mut out = Vec::new();
+        write_books(&mut out, books).unwrap();
+        read_books(out.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn commas_quotes_and_empty_fields_round_trip() {
+        let books = vec![
+            book("Harry Potter, Book 1", "J. K. Rowling", 1997),
+            book("The \"Hobbit\"", "Tolkien, J. R. R.", 1937),
+            book("", "", 2000),
+            book("  Padded  ", "Line\nbreak", 2001),
+        ];
+        assert_eq!(round_trip(&books), books);
+    }
+
+    #[test]
+    fn only_fields_that_need_it_are_quoted() {
+        let mut out = Vec::new();
+        write_books(&mut out, &[book("Dune", "Frank Herbert", 1965), book("A, B", "Say \"hi\"", 1)]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Dune,Frank Herbert,1965,,,,unread\n\"A, B\",\"Say \"\"hi\"\"\",1,,,,unread\n"
+        );
+    }
+
+    #[test]
+    fn optional_fields_round_trip() {
+        let dune = book("Dune", "Frank Herbert", 1965)
+            .with_isbn("978-0-441-17271-9")
+            .unwrap()
+            .with_genre("Science fiction, classic")
+            .with_rating(5)
+            .unwrap()
+            .with_status(ReadStatus::Read);
+        let mut out = Vec::new();
+        write_books(&mut out, std::slice::from_ref(&dune)).unwrap();
+        assert_eq!(
+            String::from_utf8(out.clone()).unwrap(),
+            "Dune,Frank Herbert,1965,9780441172719,\"Science fiction, classic\",5,read\n"
+        );
+        assert_eq!(read_books(out.as_slice()).unwrap(), [dune]);
+    }
+
+    #[test]
+    fn invalid_optional_fields_name_the_line() {
+        let error = |text: &str| read_books(text.as_bytes()).unwrap_err().to_string();
+        assert_eq!(error("Dune,Frank Herbert,1965,,,9,read\n"), "line 1: rating 9 is not between 1 and 5");
+        assert_eq!(error("a,b,1\nDune,Frank Herbert,1965,12345,,,\n"), "line 2: invalid ISBN '12345'");
+        assert!(error("Dune,Frank Herbert,1965,,,,done\n").starts_with("line 1: unknown read status 'done'"));
+        assert_eq!(error("Dune,Frank Herbert,0\n"), "line 1: implausible year 0");
+        assert_eq!(error("Dune,Frank Herbert,1965,\n"), "line 1: expected 3, 7 or 11 fields, found 4");
+    }
+
+    #[test]
+    fn unread_and_genre_filters() {
+        let mut library = Library::new();
+        library.add(book("Dune", "Frank Herbert", 1965).with_genre("Sci-Fi").with_status(ReadStatus::Read)).unwrap();
+        library.add(book("Emma", "Jane Austen", 1815).with_genre("Romance").with_status(ReadStatus::Reading)).unwrap();
+        library.add(book("Neuromancer", "William Gibson", 1984).with_genre("sci-fi")).unwrap();
+        assert_eq!(titles(library.unread()), ["Emma", "Neuromancer"]);
+        assert_eq!(titles(library.by_genre(" SCI-FI ")), ["Dune", "Neuromancer"]);
+        assert!(library.by_genre("horror").is_empty());
+    }
+
+    #[test]
+    fn old_files_still_load() {
+        let text = "1984,George Orwell,1949\n\nTo Kill a Mockingbird,Harper Lee,1960\n";
+        assert_eq!(
+            read_books(text.as_bytes()).unwrap(),
+            [book("1984", "George Orwell", 1949), book("To Kill a Mockingbird", "Harper Lee", 1960)]
+        );
+    }
+
+    #[test]
+    fn malformed_lines_name_the_line() {
+        let error = |text: &str| read_books(text.as_bytes()).unwrap_err().to_string();
+        assert_eq!(
+            error("1984,George Orwell,1949\nHarry Potter, Book 1,Rowling,1997\n"),
+            "line 2: expected 3, 7 or 11 fields, found 4"
+        );
+        assert_eq!(error("Dune,Frank Herbert,soon\n"), "line 1: invalid year 'soon'");
+        assert_eq!(error("a,b,1\n\n\"Dune,Frank Herbert,1965\n"), "line 3: unterminated quoted field");
+        assert_eq!(error("\"Dune\"x,Frank Herbert,1965\n"), "line 1: unexpected 'x' after a quoted field");
+        assert_eq!(error("Du\"ne,Frank Herbert,1965\n"), "line 1: stray '\"' in an unquoted field");
+    }
+
+    #[test]
+    fn add_and_remove() {
+        let mut library = library();
+        assert_eq!(library.len(), 3);
+        assert_eq!(library.remove("the HOBBIT"), Some(book("The Hobbit", "J. R. R. Tolkien", 1937)));
+        assert_eq!(library.remove("The Hobbit"), None);
+        assert_eq!(titles(library.iter().collect()), ["1984", "Animal Farm"]);
+    }
+
+    #[test]
+    fn duplicates_are_rejected_by_default() {
+        let mut library = library();
+        let err = library.add(book(" animal farm ", "GEORGE ORWELL", 1946)).unwrap_err();
+        assert_eq!(err.to_string(), "' animal farm ' by GEORGE ORWELL is already in the library");
+        assert_eq!(library.len(), 3);
+        // Same title, different author is a different book
+        library.add(book("1984", "Someone Else", 2001)).unwrap();
+        assert_eq!(library.len(), 4);
+    }
+
+    #[test]
+    fn duplicates_replace_in_place_when_merging() {
+        let mut library = Library::with_policy(DuplicatePolicy::Merge);
+        library.add(book("Dune", "Frank Herbert", 1956)).unwrap();
+        library.add(book("Emma", "Jane Austen", 1815)).unwrap();
+        library.add(book("dune", "frank herbert", 1965)).unwrap();
+        let books: Vec<Book> = library.iter().cloned().collect();
+        assert_eq!(books, [book("dune", "frank herbert", 1965), book("Emma", "Jane Austen", 1815)]);
+    }
+
+    #[test]
+    fn update_replaces_and_checks_duplicates() {
+        let mut library = library();
+        let old = library.update("1984", book("Nineteen Eighty-Four", "George Orwell", 1949)).unwrap();
+        assert_eq!(old.title, "1984");
+        assert_eq!(titles(library.find_by_title("eighty")), ["Nineteen Eighty-Four"]);
+
+        let err = library.update("The Hobbit", book("Animal Farm", "George Orwell", 1945)).unwrap_err();
+        assert!(matches!(err, BookStoreError::Duplicate { .. }));
+        // Keeping the same title and author is fine
+        library.update("The Hobbit", book("The Hobbit", "J. R. R. Tolkien", 1938)).unwrap();
+        let missing = library.update("Dune", book("Dune", "Frank Herbert", 1965));
+        assert!(matches!(missing, Err(BookStoreError::NotFound(_))));
+    }
+
+    #[test]
+    fn searches_ignore_case_and_match_substrings() {
+        let library = library();
+        assert_eq!(titles(library.find_by_title("HOB")), ["The Hobbit"]);
+        assert_eq!(titles(library.find_by_author("orwell")), ["1984", "Animal Farm"]);
+        assert!(library.find_by_author("austen").is_empty());
+        assert_eq!(titles(library.books_in_year_range(1937..1946)), ["Animal Farm", "The Hobbit"]);
+        assert!(library.books_in_year_range(1950..1960).is_empty());
+    }
+
+    #[test]
+    fn search_sort_and_count() {
+        let mut library = library();
+        library.add(book("Emma", "Jane Austen", 1815)).unwrap();
+        assert_eq!(titles(library.search("EM")), ["Emma"]);
+        assert_eq!(titles(library.search("george")), ["1984", "Animal Farm"]);
+        assert_eq!(titles(library.sorted(&[SortKey::Year])), ["Emma", "The Hobbit", "Animal Farm", "1984"]);
+        assert_eq!(titles(library.sorted(&[SortKey::Author])), ["1984", "Animal Farm", "The Hobbit", "Emma"]);
+        assert_eq!(library.count_by_decade(), BTreeMap::from([(1810, 1), (1930, 1), (1940, 2)]));
+        library.add(book("Homage to Catalonia", "george orwell", 1938)).unwrap();
+        assert_eq!(library.count_by_author()["George Orwell"], 3);
+        assert_eq!("Year".parse(), Ok(SortKey::Year));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("module-3-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let library = library();
+        library.save(path).unwrap();
+        let loaded = Library::load(path, DuplicatePolicy::Reject).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.iter().collect::<Vec<_>>(), library.iter().collect::<Vec<_>>());
+    }
+
+    /// The indexes `library` would have if built from scratch
+    fn assert_indexes_consistent(library: &Library) {
+        let mut by_author: Index<String> = BTreeMap::new();
+        let mut by_year: Index<u16> = BTreeMap::new();
+        for (i, book) in library.books.iter().enumerate() {
+            by_author.entry(normalize(&book.author)).or_default().push(i);
+            by_year.entry(book.year).or_default().push(i);
+        }
+        assert_eq!(library.by_author, by_author);
+        assert_eq!(library.by_year, by_year);
+    }
+
+    #[test]
+    fn indexes_follow_adds_removals_and_updates() {
+        let mut library = library();
+        library.add(book("Emma", "Jane Austen", 1815)).unwrap();
+        library.add(book("Persuasion", "jane austen ", 1817)).unwrap();
+        assert_indexes_consistent(&library);
+        assert_eq!(library.authors(), ["George Orwell", "J. R. R. Tolkien", "Jane Austen"]);
+
+        library.remove("1984").unwrap();
+        library.remove("The Hobbit").unwrap();
+        assert_indexes_consistent(&library);
+        assert_eq!(library.authors(), ["George Orwell", "Jane Austen"]);
+        assert_eq!(titles(library.by_author("JANE AUSTEN")), ["Emma", "Persuasion"]);
+
+        library.update("Animal Farm", book("Animal Farm", "Eric Blair", 1946)).unwrap();
+        assert_indexes_consistent(&library);
+        assert!(library.by_author("George Orwell").is_empty());
+        assert_eq!(titles(library.books_in_year_range(1940..1950)), ["Animal Farm"]);
+
+        let mut merging = Library::with_policy(DuplicatePolicy::Merge);
+        merging.add(book("Dune", "Frank Herbert", 1956)).unwrap();
+        merging.add(book("dune", "frank herbert", 1965)).unwrap();
+        assert_indexes_consistent(&merging);
+    }
+
+    #[test]
+    fn sorting_by_several_keys_keeps_ties_in_order() {
+        let mut library = Library::new();
+        for (title, author, year) in [
+            ("Persuasion", "Jane Austen", 1817),
+            ("Animal Farm", "George Orwell", 1945),
+            ("Emma", "Jane Austen", 1815),
+            ("1984", "George Orwell", 1949),
+            ("Sanditon", "Jane Austen", 1817),
+        ] {
+            library.add(book(title, author, year)).unwrap();
+        }
+        use SortKey::{Author, Title, Year};
+        let sorted = |keys: &[SortKey]| titles(library.sorted(keys));
+        assert_eq!(sorted(&[Author, Year]), ["Animal Farm", "1984", "Emma", "Persuasion", "Sanditon"]);
+        assert_eq!(sorted(&[Year, Title]), ["Emma", "Persuasion", "Sanditon", "Animal Farm", "1984"]);
+        assert_eq!(sorted(&[Title, Year]), ["1984", "Animal Farm", "Emma", "Persuasion", "Sanditon"]);
+        // Persuasion and Sanditon tie on both and stay as added
+        assert_eq!(sorted(&[Year]), ["Emma", "Persuasion", "Sanditon", "Animal Farm", "1984"]);
+        assert_eq!(sorted(&[]), ["Persuasion", "Animal Farm", "Emma", "1984", "Sanditon"]);
+    }
+
+    #[test]
+    fn author_index_looks_only_at_matching_books() {
+        let mut library = Library::new();
+        for i in 0..50_000u16 {
+            library.add(book(&format!("Book {i}"), &format!("Author {}", i % 1000), 1900 + i % 120)).unwrap();
+        }
+        let authors: Vec<String> = (0..20).map(|i| format!("Author {}", i * 37)).collect();
+
+        let indexed: Vec<Vec<&Book>> = authors.iter().map(|a| library.by_author(a)).collect();
+        let scanned: Vec<Vec<&Book>> = authors
+            .iter()
+            .map(|a| library.iter().filter(|b| normalize(&b.author) == normalize(a)).collect())
+            .collect();
+        assert_eq!(indexed, scanned);
+        assert!(indexed.iter().all(|books| books.len() == 50));
+        assert_eq!(library.authors().len(), 1000);
+
+        // A lookup reads only its author's positions, where a scan compares
+        // every book
+        let visited: usize = authors.iter().map(|a| library.by_author[&normalize(a)].len()).sum();
+        assert_eq!(visited, authors.len() * 50);
+        assert_eq!(visited * 1000, authors.len() * library.len());
+    }
+}
@@ -0,0 +1,222 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{Book, BookStoreError, Library};
+
+/// Who has a book and when it's due back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Loan {
+    pub borrower: String,
+    pub checked_out_at: DateTime<Utc>,
+    pub due: DateTime<Utc>,
+}
+
+impl Loan {
+    /// Past due at `as_of`; a book due at exactly that moment isn't yet
+    pub fn is_overdue(&self, as_of: DateTime<Utc>) -> bool {
+        self.due < as_of
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.borrower.trim().is_empty() {
+            return Err("loan has no borrower".to_string());
+        }
+        if self.due <= self.checked_out_at {
+            return Err(format!("due {} is not after the checkout at {}", time(self.due), time(self.checked_out_at)));
+        }
+        Ok(())
+    }
+}
+
+/// A loan that's over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReturnedLoan {
+    #[serde(flatten)]
+    pub loan: Loan,
+    pub returned_at: DateTime<Utc>,
+}
+
+/// Check a book's current loan and loan history, as `Book::validate` does.
+pub(crate) fn validate_loans(book: &Book) -> Result<(), String> {
+    if let Some(loan) = &book.loan {
+        loan.validate()?;
+    }
+    for past in &book.loan_history {
+        past.loan.validate()?;
+        if past.returned_at < past.loan.checked_out_at {
+            return Err(format!("returned at {} before the checkout", time(past.returned_at)));
+        }
+    }
+    Ok(())
+}
+
+fn time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// The loan columns that follow `status` in the text and CSV formats:
+/// borrower, checked out, due, and the history as a JSON array. All empty
+/// for a book that has never been lent.
+pub(crate) fn loan_fields(book: &Book) -> [String; 4] {
+    let [borrower, checked_out_at, due] = match &book.loan {
+        Some(loan) => [loan.borrower.clone(), time(loan.checked_out_at), time(loan.due)],
+        None => Default::default(),
+    };
+    let history = if book.loan_history.is_empty() {
+        String::new()
+    } else {
+        serde_json::to_string(&book.loan_history).expect("loans serialize")
+    };
+    [borrower, checked_out_at, due, history]
+}
+
+/// The reverse of `loan_fields`
+pub(crate) fn loans_from_fields(
+    borrower: &str,
+    checked_out_at: &str,
+    due: &str,
+    history: &str,
+) -> Result<(Option<Loan>, Vec<ReturnedLoan>), String> {
+    let parse = |field: &str| {
+        DateTime::parse_from_rfc3339(field.trim())
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|_| format!("invalid time '{field}'"))
+    };
+    let loan = match (borrower.trim(), checked_out_at.trim(), due.trim()) {
+        ("", "", "") => None,
+        (_, "", _) | (_, _, "") => return Err("loan needs a checkout time and a due time".to_string()),
+        (borrower, checked_out_at, due) => {
+            Some(Loan { borrower: borrower.to_string(), checked_out_at: parse(checked_out_at)?, due: parse(due)? })
+        }
+    };
+    let history = match history.trim() {
+        "" => Vec::new(),
+        history => serde_json::from_str(history).map_err(|e| format!("invalid loan history: {e}"))?,
+    };
+    Ok((loan, history))
+}
+
+impl Library {
+    /// Lend the book titled `title` to `borrower` until `due`. Fails if it's
+    /// already lent out or `due` has passed.
+    pub fn check_out(&mut self, title: &str, borrower: &str, due: DateTime<Utc>) -> Result<&Book, BookStoreError> {
+        let book = self.book_mut(title)?;
+        if let Some(loan) = &book.loan {
+            return Err(BookStoreError::OnLoan { title: book.title.clone(), borrower: loan.borrower.clone() });
+        }
+        let loan = Loan { borrower: borrower.trim().to_string(), checked_out_at: Utc::now(), due };
+        loan.validate().map_err(BookStoreError::Invalid)?;
+        book.loan = Some(loan);
+        Ok(book)
+    }
+
+    /// Take back the book titled `title`, moving its loan into the book's
+    /// history. Fails if it isn't lent out.
+    pub fn check_in(&mut self, title: &str) -> Result<ReturnedLoan, BookStoreError> {
+        let book = self.book_mut(title)?;
+        let loan = book.loan.take().ok_or_else(|| BookStoreError::NotOnLoan(book.title.clone()))?;
+        let returned = ReturnedLoan { loan, returned_at: Utc::now() };
+        book.loan_history.push(returned.clone());
+        Ok(returned)
+    }
+
+    /// Books lent out, soonest due first
+    pub fn current_loans(&self) -> Vec<&Book> {
+        let mut lent: Vec<&Book> = self.iter().filter(|b| b.loan.is_some()).collect();
+        lent.sort_by_key(|b| b.loan.as_ref().map(|loan| loan.due));
+        lent
+    }
+
+    /// Books lent out and past due at `as_of`, longest overdue first
+    pub fn overdue(&self, as_of: DateTime<Utc>) -> Vec<&Book> {
+        let mut lent = self.current_loans();
+        lent.retain(|b| b.loan.as_ref().is_some_and(|loan| loan.is_overdue(as_of)));
+        lent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn library() -> Library {
+        let mut library = Library::new();
+        for (title, author, year) in [("Dune", "Frank Herbert", 1965), ("Emma", "Jane Austen", 1815)] {
+            library.add(Book::new(title, author, year).unwrap()).unwrap();
+        }
+        library
+    }
+
+    fn titles(books: Vec<&Book>) -> Vec<&str> {
+        books.into_iter().map(|b| b.title.as_str()).collect()
+    }
+
+    #[test]
+    fn a_book_is_lent_once_at_a_time() {
+        let mut library = library();
+        let due = Utc::now() + TimeDelta::days(14);
+        let dune = library.check_out("dune", " Alice ", due).unwrap();
+        assert_eq!(dune.loan.as_ref().map(|loan| loan.borrower.as_str()), Some("Alice"));
+
+        let err = library.check_out("Dune", "Bob", due).unwrap_err();
+        assert_eq!(err.to_string(), "'Dune' is already lent to Alice");
+        assert_eq!(library.check_in("Emma").unwrap_err().to_string(), "'Emma' is not lent out");
+        assert!(matches!(library.check_out("Ulysses", "Bob", due), Err(BookStoreError::NotFound(_))));
+        let past = Utc::now() - TimeDelta::days(1);
+        assert!(matches!(library.check_out("Emma", "Bob", past), Err(BookStoreError::Invalid(_))));
+        assert!(matches!(library.check_out("Emma", " ", due), Err(BookStoreError::Invalid(_))));
+        assert_eq!(titles(library.current_loans()), ["Dune"]);
+    }
+
+    #[test]
+    fn check_in_keeps_the_loan_in_the_history() {
+        let mut library = library();
+        let due = Utc::now() + TimeDelta::days(14);
+        library.check_out("Dune", "Alice", due).unwrap();
+        let returned = library.check_in("Dune").unwrap();
+        assert_eq!((returned.loan.borrower.as_str(), returned.loan.due), ("Alice", due));
+        assert!(returned.returned_at >= returned.loan.checked_out_at);
+
+        library.check_out("Dune", "Bob", due).unwrap();
+        let dune = library.find_by_title("Dune")[0];
+        assert_eq!(dune.loan_history, [returned]);
+        assert_eq!(dune.loan.as_ref().unwrap().borrower, "Bob");
+    }
+
+    #[test]
+    fn overdue_starts_just_after_the_due_time() {
+        let mut library = library();
+        let now = Utc::now();
+        let (dune_due, emma_due) = (now + TimeDelta::days(7), now + TimeDelta::days(3));
+        library.check_out("Dune", "Alice", dune_due).unwrap();
+        library.check_out("Emma", "Bob", emma_due).unwrap();
+        assert_eq!(titles(library.current_loans()), ["Emma", "Dune"]);
+
+        assert!(library.overdue(now).is_empty());
+        assert!(library.overdue(emma_due).is_empty());
+        assert_eq!(titles(library.overdue(emma_due + TimeDelta::seconds(1))), ["Emma"]);
+        assert_eq!(titles(library.overdue(dune_due)), ["Emma"]);
+        assert_eq!(titles(library.overdue(dune_due + TimeDelta::milliseconds(1))), ["Emma", "Dune"]);
+    }
+
+    #[test]
+    fn loan_fields_round_trip() {
+        let mut library = library();
+        library.check_out("Dune", "Alice, of \"Wonderland\"", Utc::now() + TimeDelta::days(14)).unwrap();
+        library.check_in("Dune").unwrap();
+        library.check_out("Dune", "Bob", Utc::now() + TimeDelta::days(7)).unwrap();
+        let dune = library.find_by_title("Dune")[0];
+        let [borrower, checked_out_at, due, history] = loan_fields(dune);
+        assert_eq!(borrower, "Bob");
+        let (loan, loan_history) = loans_from_fields(&borrower, &checked_out_at, &due, &history).unwrap();
+        assert_eq!((loan.as_ref(), &loan_history), (dune.loan.as_ref(), &dune.loan_history));
+
+        let emma = library.find_by_title("Emma")[0];
+        assert_eq!(loan_fields(emma), <[String; 4]>::default());
+        assert_eq!(loans_from_fields("", "", "", "").unwrap(), (None, Vec::new()));
+        assert!(loans_from_fields("Bob", "", &due, "").is_err());
+        assert!(loans_from_fields("Bob", "yesterday", &due, "").is_err());
+    }
+}
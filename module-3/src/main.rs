@@ -1,59 +1,229 @@
-use std::fs::File;
-use std::io::{Write, BufReader, BufRead};
+use std::path::Path;
+use std::process;
 
-struct Book {
-    title: String,
-    author: String,
-    year: u16,
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
+use module_3::{
+    Book, BookStoreError, DuplicatePolicy, Library, MergeStrategy, ReadStatus, SortKey, convert, load_auto_iter,
+};
+
+/// Keep track of a list of books.
+#[derive(Parser)]
+struct Cli {
+    /// Book store to read and update: .txt, .csv or .json
+    #[arg(long, default_value = "books.txt", global = true)]
+    file: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Add a book
+    Add {
+        #[arg(long)]
+        title: String,
+        #[arg(long)]
+        author: String,
+        #[arg(long)]
+        year: u16,
+        /// ISBN-10 or ISBN-13, hyphens allowed
+        #[arg(long)]
+        isbn: Option<String>,
+        #[arg(long)]
+        genre: Option<String>,
+        /// 1 to 5
+        #[arg(long)]
+        rating: Option<u8>,
+        /// unread, reading or read
+        #[arg(long, default_value = "unread")]
+        status: ReadStatus,
+    },
+    /// List every book
+    List {
+        /// title, author or year, or several to break ties (author,year);
+        /// the order they were added in if not given
+        #[arg(long, value_delimiter = ',')]
+        sort: Vec<SortKey>,
+    },
+    /// Books whose title or author contains QUERY, ignoring case
+    Search {
+        query: String,
+        /// Also find near misses, like misspellings, best first with their
+        /// score out of 1
+        #[arg(long)]
+        fuzzy: bool,
+        /// With --fuzzy, show at most this many
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Remove a book by title
+    Remove {
+        #[arg(long)]
+        title: String,
+    },
+    /// Count books per decade and per author
+    Stats,
+    /// Write the store to another file, in the format its extension says
+    Convert { to: String },
+    /// Bring in the books from another file and show what changed
+    Merge {
+        other: String,
+        /// prefer-self, prefer-other, or combine to fill in missing details
+        #[arg(long, default_value = "combine")]
+        strategy: MergeStrategy,
+    },
+    /// Lend a book out
+    Checkout {
+        #[arg(long)]
+        title: String,
+        #[arg(long)]
+        borrower: String,
+        /// YYYY-MM-DD, due by the end of that day (UTC), or an RFC 3339 time
+        #[arg(long, value_parser = parse_time)]
+        due: DateTime<Utc>,
+    },
+    /// Take back a lent book
+    Checkin {
+        #[arg(long)]
+        title: String,
+    },
+    /// Lent books past their due date
+    Overdue {
+        /// Check as of this time rather than now; same forms as --due
+        #[arg(long, value_parser = parse_time)]
+        as_of: Option<DateTime<Utc>>,
+    },
+}
+
+/// `2024-05-01`, meaning the end of that day in UTC, or an RFC 3339 time
+fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(23, 59, 59).expect("valid time").and_utc());
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| format!("invalid time '{s}' (expected YYYY-MM-DD or RFC 3339)"))
+}
+
+/// The store at `file`, or an empty library if there isn't one yet
+fn open(file: &str) -> Result<Library, BookStoreError> {
+    if !Path::new(file).exists() {
+        return Ok(Library::new());
+    }
+    Library::load(file, DuplicatePolicy::Reject)
 }
 
-fn save_books(books: &Vec<Book>, filename: &str) {
-    let mut file = File::create(filename).expect("Could not create file");
+fn print_books<'a>(books: impl IntoIterator<Item = &'a Book>) {
     for book in books {
-        // Format: title,author,year
-        writeln!(file, "{},{},{}", book.title, book.author, book.year)
-            .expect("Failed to write to file");
+        println!("{book}");
     }
 }
 
-fn load_books(filename: &str) -> Vec<Book> {
-    let file = File::open(filename).expect("Could not open file");
-    let reader = BufReader::new(file);
-
-    let mut books = Vec::new();
-
-    for line in reader.lines() {
-        if let Ok(record) = line {
-            let parts: Vec<&str> = record.trim().split(',').collect();
-            if parts.len() == 3 {
-                if let Ok(year) = parts[2].parse::<u16>() {
-                    books.push(Book {
-                        title: parts[0].to_string(),
-                        author: parts[1].to_string(),
-                        year,
-                    });
+fn run(cli: Cli) -> Result<(), BookStoreError> {
+    match cli.command {
+        Command::Add { title, author, year, isbn, genre, rating, status } => {
+            let mut library = open(&cli.file)?;
+            let mut book = Book::new(&title, &author, year)?.with_status(status);
+            if let Some(isbn) = isbn {
+                book = book.with_isbn(&isbn)?;
+            }
+            if let Some(genre) = genre {
+                book = book.with_genre(&genre);
+            }
+            if let Some(rating) = rating {
+                book = book.with_rating(rating)?;
+            }
+            library.add(book.clone())?;
+            library.save(&cli.file)?;
+            println!("Added {book}");
+        }
+        Command::List { sort } => print_books(open(&cli.file)?.sorted(&sort)),
+        Command::Search { query, fuzzy: true, limit } => {
+            let library = open(&cli.file)?;
+            let found = library.fuzzy_search(&query, limit);
+            if found.is_empty() {
+                println!("No books match '{query}'");
+            }
+            for found in found {
+                println!("{:.2}  {}", found.score, found.book);
+            }
+        }
+        Command::Search { query, fuzzy: false, .. } => {
+            // Streamed, so the store needn't fit in memory
+            let mut found = 0;
+            if Path::new(&cli.file).exists() {
+                for book in load_auto_iter(&cli.file)? {
+                    let book = book?;
+                    if book.matches(&query) {
+                        println!("{book}");
+                        found += 1;
+                    }
                 }
             }
+            if found == 0 {
+                println!("No books match '{query}'");
+            }
+        }
+        Command::Remove { title } => {
+            let mut library = open(&cli.file)?;
+            let book = library.remove(&title).ok_or(BookStoreError::NotFound(title))?;
+            library.save(&cli.file)?;
+            println!("Removed {book}");
+        }
+        Command::Stats => {
+            let library = open(&cli.file)?;
+            println!("{} books", library.len());
+            println!("By decade:");
+            for (decade, count) in library.count_by_decade() {
+                println!("  {decade}s: {count}");
+            }
+            println!("By author:");
+            for (author, count) in library.count_by_author() {
+                println!("  {author}: {count}");
+            }
+        }
+        Command::Convert { to } => {
+            let count = convert(&cli.file, &to)?;
+            println!("Wrote {count} books to {to}");
+        }
+        Command::Merge { other, strategy } => {
+            let mut library = open(&cli.file)?;
+            let report = library.merge(Library::load(&other, DuplicatePolicy::Reject)?, strategy);
+            library.save(&cli.file)?;
+            print!("{report}");
+        }
+        Command::Checkout { title, borrower, due } => {
+            let mut library = open(&cli.file)?;
+            let book = library.check_out(&title, &borrower, due)?.clone();
+            library.save(&cli.file)?;
+            println!("Lent {} to {}, due {}", book.title, borrower.trim(), due.format("%Y-%m-%d %H:%M UTC"));
+        }
+        Command::Checkin { title } => {
+            let mut library = open(&cli.file)?;
+            let returned = library.check_in(&title)?;
+            library.save(&cli.file)?;
+            println!("Checked in {} from {}", title.trim(), returned.loan.borrower);
+        }
+        Command::Overdue { as_of } => {
+            let library = open(&cli.file)?;
+            let overdue = library.overdue(as_of.unwrap_or_else(Utc::now));
+            if overdue.is_empty() {
+                println!("Nothing is overdue");
+            }
+            for book in overdue {
+                let loan = book.loan.as_ref().expect("overdue books are lent");
+                println!("{book}: lent to {}, due {}", loan.borrower, loan.due.format("%Y-%m-%d %H:%M UTC"));
+            }
         }
     }
-
-    books
+    Ok(())
 }
 
 fn main() {
-    let books = vec![
-        Book { title: "1984".to_string(), author: "George Orwell".to_string(), year: 1949 },
-        Book { title: "To Kill a Mockingbird".to_string(), author: "Harper Lee".to_string(), year: 1960 },
-        Book { title: "The Rust Programming Language".to_string(), author: "Steve Klabnik".to_string(), year: 2019 },
-    ];
-
-    save_books(&books, "books.txt");
-    println!("Books saved to file.");
-
-    let loaded_books = load_books("books.txt");
-    println!("\nLoaded books:");
-    for book in loaded_books {
-        println!("{} by {}, published in {}", book.title, book.author, book.year);
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("Error: {e}");
+        process::exit(1);
     }
 }
-
@@ -0,0 +1,250 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Book, Library, normalize_isbn};
+
+/// What `Library::merge` does when both sides have the same book.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Keep this library's copy as it is
+    PreferSelf,
+    /// Take the other library's copy in place of this one's
+    PreferOther,
+    /// Fill in optional fields this copy lacks from the other one. Copies
+    /// with different years are left alone and reported as conflicts.
+    #[default]
+    Combine,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "prefer-self" => Ok(MergeStrategy::PreferSelf),
+            "prefer-other" => Ok(MergeStrategy::PreferOther),
+            "combine" => Ok(MergeStrategy::Combine),
+            other => Err(format!("unknown merge strategy '{other}' (valid: prefer-self, prefer-other, combine)")),
+        }
+    }
+}
+
+/// The same book on both sides, with details that couldn't be reconciled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    pub ours: Book,
+    pub theirs: Book,
+    pub reason: String,
+}
+
+/// What `Library::merge` did with each of the other library's books.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    /// Books only the other library had, now added
+    pub added: Vec<Book>,
+    /// Books both had whose copy here changed, as they are now
+    pub updated: Vec<Book>,
+    /// Books both had where nothing changed here
+    pub skipped: Vec<Book>,
+    /// Books both had that were left alone because the copies disagree
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// ```text
+/// Added 1:
+///   Dune by Frank Herbert, published in 1965
+/// Conflicts 1:
+///   Emma by Jane Austen, published in 1815: year 1815 here, 1816 in the other
+/// ```
+impl fmt::Display for MergeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sections = [("Added", &self.added), ("Updated", &self.updated), ("Skipped", &self.skipped)];
+        for (name, books) in sections.into_iter().filter(|(_, books)| !books.is_empty()) {
+            writeln!(f, "{name} {}:", books.len())?;
+            for book in books {
+                writeln!(f, "  {book}")?;
+            }
+        }
+        if !self.conflicts.is_empty() {
+            writeln!(f, "Conflicts {}:", self.conflicts.len())?;
+            for conflict in &self.conflicts {
+                writeln!(f, "  {}: {}", conflict.ours, conflict.reason)?;
+            }
+        }
+        if self.added.is_empty() && self.updated.is_empty() && self.skipped.is_empty() && self.conflicts.is_empty() {
+            writeln!(f, "Nothing to merge")?;
+        }
+        Ok(())
+    }
+}
+
+/// Same ISBN, hyphens or not; or, when either has no ISBN, same title and
+/// author. Two different ISBNs are two different books.
+fn matches(ours: &Book, theirs: &Book) -> bool {
+    let isbn = |book: &Book| book.isbn.as_deref().and_then(|isbn| normalize_isbn(isbn).ok());
+    match (isbn(ours), isbn(theirs)) {
+        (Some(a), Some(b)) => a == b,
+        _ => ours.same_as(theirs),
+    }
+}
+
+/// `ours` with the optional fields it lacks taken from `theirs`
+fn combine(ours: &Book, theirs: &Book) -> Book {
+    let mut book = ours.clone();
+    book.isbn = book.isbn.or_else(|| theirs.isbn.clone());
+    book.genre = book.genre.or_else(|| theirs.genre.clone());
+    book.rating = book.rating.or(theirs.rating);
+    book.status = book.status.max(theirs.status);
+    book
+}
+
+impl Library {
+    /// Bring in the books from `other`. Books are the same if they share an
+    /// ISBN, or else (when one has none) a title and author; `strategy` says
+    /// which copy wins. A change that would make a book the same as another
+    /// one here is left out and reported as a conflict.
+    pub fn merge(&mut self, other: Library, strategy: MergeStrategy) -> MergeReport {
+        let mut report = MergeReport::default();
+        for theirs in other.books {
+            let Some(i) = self.books.iter().position(|ours| matches(ours, &theirs)) else {
+                self.push_book(theirs.clone());
+                report.added.push(theirs);
+                continue;
+            };
+            let ours = &self.books[i];
+            let merged = match strategy {
+                MergeStrategy::PreferSelf => ours.clone(),
+                MergeStrategy::PreferOther => theirs.clone(),
+                MergeStrategy::Combine if ours.year != theirs.year => {
+                    let reason = format!("year {} here, {} in the other", ours.year, theirs.year);
+                    report.conflicts.push(MergeConflict { ours: ours.clone(), theirs, reason });
+                    continue;
+                }
+                MergeStrategy::Combine => combine(ours, &theirs),
+            };
+            if merged == *ours {
+                report.skipped.push(merged);
+            } else if let Some((_, other)) =
+                self.books.iter().enumerate().find(|&(j, book)| j != i && matches(book, &merged))
+            {
+                let reason = format!("would duplicate {other}");
+                report.conflicts.push(MergeConflict { ours: ours.clone(), theirs, reason });
+            } else {
+                self.replace_book(i, merged.clone());
+                report.updated.push(merged);
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DuplicatePolicy, ReadStatus, read_books};
+
+    const OURS: &str = include_str!("../tests/fixtures/ours.txt");
+    const DISJOINT: &str = include_str!("../tests/fixtures/theirs_disjoint.txt");
+    const DUPLICATE: &str = include_str!("../tests/fixtures/theirs_duplicate.txt");
+    const CONFLICTING: &str = include_str!("../tests/fixtures/theirs_conflicting.txt");
+
+    fn library(text: &str) -> Library {
+        let mut library = Library::with_policy(DuplicatePolicy::Reject);
+        for book in read_books(text.as_bytes()).unwrap() {
+            library.add(book).unwrap();
+        }
+        library
+    }
+
+    fn titles(books: &[Book]) -> Vec<&str> {
+        books.iter().map(|b| b.title.as_str()).collect()
+    }
+
+    #[test]
+    fn disjoint_books_are_all_added() {
+        let mut ours = library(OURS);
+        let report = ours.merge(library(DISJOINT), MergeStrategy::Combine);
+        assert_eq!(titles(&report.added), ["Emma", "Neuromancer"]);
+        assert!(report.updated.is_empty() && report.skipped.is_empty() && report.conflicts.is_empty());
+        assert_eq!(ours.len(), 5);
+    }
+
+    #[test]
+    fn duplicates_are_skipped_or_filled_in() {
+        let mut ours = library(OURS);
+        let report = ours.merge(library(DUPLICATE), MergeStrategy::Combine);
+        // The Hobbit matches on ISBN despite the different title; Dune
+        // matches on title and author and gains a genre and rating
+        assert_eq!(titles(&report.skipped), ["The Hobbit"]);
+        assert_eq!(titles(&report.updated), ["Dune"]);
+        assert!(report.added.is_empty() && report.conflicts.is_empty());
+        let dune = &ours.find_by_title("dune")[0];
+        assert_eq!((dune.genre.as_deref(), dune.rating, dune.status), (Some("Sci-Fi"), Some(4), ReadStatus::Read));
+        assert_eq!(ours.len(), 3);
+    }
+
+    #[test]
+    fn combine_reports_conflicting_years() {
+        let mut ours = library(OURS);
+        let report = ours.merge(library(CONFLICTING), MergeStrategy::Combine);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].reason, "year 1949 here, 1948 in the other");
+        assert_eq!(ours.find_by_title("1984")[0].year, 1949);
+        assert_eq!(
+            report.to_string(),
+            "Conflicts 1:\n  1984 by George Orwell, published in 1949: year 1949 here, 1948 in the other\n"
+        );
+    }
+
+    #[test]
+    fn prefer_self_and_prefer_other() {
+        let mut ours = library(OURS);
+        let report = ours.merge(library(CONFLICTING), MergeStrategy::PreferSelf);
+        assert_eq!(titles(&report.skipped), ["1984"]);
+        assert_eq!(ours.find_by_title("1984")[0].year, 1949);
+
+        let report = ours.merge(library(CONFLICTING), MergeStrategy::PreferOther);
+        assert_eq!(titles(&report.updated), ["1984"]);
+        assert_eq!(ours.find_by_title("1984")[0].year, 1948);
+        assert_eq!(report.to_string(), "Updated 1:\n  1984 by George Orwell, published in 1948\n");
+    }
+
+    #[test]
+    fn different_isbns_are_different_books() {
+        let dune = || Book::new("Dune", "Frank Herbert", 1965).unwrap();
+        let mut ours = Library::new();
+        ours.push_book(dune().with_isbn("0441013597").unwrap());
+        let mut theirs = Library::new();
+        theirs.push_book(dune().with_isbn("0261102214").unwrap());
+        theirs.push_book(dune());
+
+        let report = ours.merge(theirs, MergeStrategy::Combine);
+        // The second ISBN is another book; the copy without one is ours
+        assert_eq!(report.added.len(), 1);
+        assert_eq!(report.added[0].isbn.as_deref(), Some("0261102214"));
+        assert_eq!(titles(&report.skipped), ["Dune"]);
+        assert_eq!(ours.len(), 2);
+    }
+
+    #[test]
+    fn prefer_other_leaves_out_changes_that_would_duplicate() {
+        let mut ours = library(OURS);
+        let mut theirs = Library::new();
+        // The Hobbit's ISBN, but Dune's title and author
+        theirs.push_book(Book::new("Dune", "Frank Herbert", 1965).unwrap().with_isbn("0261102214").unwrap());
+
+        let report = ours.merge(theirs, MergeStrategy::PreferOther);
+        assert!(report.updated.is_empty());
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].ours.title, "The Hobbit");
+        assert_eq!(report.conflicts[0].reason, "would duplicate Dune by Frank Herbert, published in 1965");
+        assert_eq!(ours.find_by_title("dune").len(), 1);
+        assert_eq!(ours.find_by_title("hobbit").len(), 1);
+    }
+
+    #[test]
+    fn strategy_names() {
+        assert_eq!("prefer-other".parse(), Ok(MergeStrategy::PreferOther));
+        assert!("newest".parse::<MergeStrategy>().is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::borrow::Borrow;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Lines, Read, Seek, SeekFrom, Write};
+use std::iter::Enumerate;
+
+use crate::formats::replace_atomically;
+use crate::{Book, BookStoreError, book_from_fields, split_record, write_books};
+
+/// The books in a plain text store, parsed a record at a time as they're
+/// asked for. Stops after the first error.
+pub struct BookReader<R> {
+    lines: Enumerate<Lines<R>>,
+    done: bool,
+}
+
+impl<R: BufRead> BookReader<R> {
+    pub fn new(reader: R) -> Self {
+        BookReader { lines: reader.lines().enumerate(), done: false }
+    }
+
+    fn read_book(&mut self) -> Option<Result<Book, BookStoreError>> {
+        // A quoted field can span lines; `record` collects them and `start`
+        // is the line the record began on
+        let mut record = String::new();
+        let mut start = 0;
+
+        for (i, line) in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if record.is_empty() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                start = i + 1;
+            } else {
+                record.push('\n');
+            }
+            record.push_str(&line);
+
+            let parse_error = |message| BookStoreError::Parse { line: start, message };
+            match split_record(&record) {
+                Ok(None) => continue,
+                Ok(Some(fields)) => return Some(book_from_fields(fields).map_err(parse_error)),
+                Err(message) => return Some(Err(parse_error(message))),
+            }
+        }
+
+        if record.is_empty() {
+            return None;
+        }
+        Some(Err(BookStoreError::Parse { line: start, message: "unterminated quoted field".to_string() }))
+    }
+}
+
+impl<R: BufRead> Iterator for BookReader<R> {
+    type Item = Result<Book, BookStoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let book = self.read_book();
+        self.done = !matches!(book, Some(Ok(_)));
+        book
+    }
+}
+
+/// The books in the plain text store `filename`, read as they're asked for
+/// so a file of any size can be filtered without loading all of it.
+pub fn load_books_iter(filename: &str) -> Result<BookReader<BufReader<File>>, BookStoreError> {
+    Ok(BookReader::new(BufReader::new(File::open(filename)?)))
+}
+
+/// Add `book` to the end of the plain text store `filename`, creating it if
+/// need be, without rewriting the books already there. Doesn't check for
+/// duplicates.
+pub fn append_book(filename: &str, book: &Book) -> Result<(), BookStoreError> {
+    book.validate().map_err(BookStoreError::Invalid)?;
+    let mut file = OpenOptions::new().read(true).append(true).create(true).open(filename)?;
+    // Finish off a last line that lacks its newline, so the book gets its own
+    if file.seek(SeekFrom::End(0))? > 0 {
+        let mut last = [0];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last != *b"\n" {
+            file.write_all(b"\n")?;
+        }
+    }
+    write_books(&file, std::slice::from_ref(book))?;
+    Ok(())
+}
+
+/// Write `books` to the plain text store `filename` as they come, by way of
+/// `<filename>.tmp` like `save_auto`, so a failed save leaves the old file
+/// as it was.
+pub fn save_books_atomic(
+    books: impl IntoIterator<Item = impl Borrow<Book>>,
+    filename: &str,
+) -> Result<(), BookStoreError> {
+    replace_atomically(filename, |temp| {
+        let file = File::create(temp)?;
+        write_books(&file, books)?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const BOOKS: u32 = 200_000;
+
+    /// A store of `BOOKS` generated books; one in a hundred is by Jane Austen
+    fn large_store(dir: &TempDir) -> String {
+        let path = dir.path().join("large.txt").to_str().unwrap().to_string();
+        let books = (0..BOOKS).map(|i| {
+            let author = if i % 100 == 0 { "Jane Austen".to_string() } else { format!("Author {i}") };
+            Book::new(&format!("Book {i}"), &author, 1800 + (i % 200) as u16).unwrap()
+        });
+        save_books_atomic(books, &path).unwrap();
+        path
+    }
+
+    #[test]
+    fn a_large_file_streams_through_a_filter() {
+        let dir = TempDir::new().unwrap();
+        let path = large_store(&dir);
+
+        let austen: Vec<Book> = load_books_iter(&path)
+            .unwrap()
+            .filter(|b| b.as_ref().map_or(true, |b| b.matches("austen")))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(austen.len(), (BOOKS / 100) as usize);
+        assert_eq!(austen[1].title, "Book 100");
+        assert_eq!(load_books_iter(&path).unwrap().count(), BOOKS as usize);
+
+        // A bad record at the very end only shows up once it's reached, so
+        // the books before it come out first
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"Book,Author,soon\n").unwrap();
+        let first: Vec<Book> = load_books_iter(&path).unwrap().take(10).collect::<Result<_, _>>().unwrap();
+        assert_eq!(first.len(), 10);
+        let last = load_books_iter(&path).unwrap().last().unwrap();
+        assert_eq!(last.unwrap_err().to_string(), format!("line {}: invalid year 'soon'", BOOKS + 1));
+    }
+
+    #[test]
+    fn reading_stops_at_the_first_error() {
+        let text = "Dune,Frank Herbert,1965\n1984,George Orwell\nEmma,Jane Austen,1815\n";
+        let mut books = BookReader::new(text.as_bytes());
+        assert_eq!(books.next().unwrap().unwrap().title, "Dune");
+        assert!(matches!(books.next(), Some(Err(BookStoreError::Parse { line: 2, .. }))));
+        assert!(books.next().is_none());
+    }
+
+    #[test]
+    fn appending_adds_one_record() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("books.txt").to_str().unwrap().to_string();
+        let dune = Book::new("Dune", "Frank Herbert", 1965).unwrap();
+        let emma = Book::new("Emma, a novel", "Jane Austen", 1815).unwrap();
+
+        append_book(&path, &dune).unwrap();
+        append_book(&path, &emma).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "Dune,Frank Herbert,1965,,,,unread\n\"Emma, a novel\",Jane Austen,1815,,,,unread\n"
+        );
+
+        // Written by hand, without a final newline
+        fs::write(&path, "1984,George Orwell,1949").unwrap();
+        append_book(&path, &dune).unwrap();
+        let titles: Vec<String> = load_books_iter(&path).unwrap().map(|b| b.unwrap().title).collect();
+        assert_eq!(titles, ["1984", "Dune"]);
+
+        let mut invalid = dune.clone();
+        invalid.rating = Some(9);
+        assert!(matches!(append_book(&path, &invalid), Err(BookStoreError::Invalid(_))));
+    }
+
+    #[test]
+    fn a_failed_atomic_save_keeps_the_old_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("books.txt").to_str().unwrap().to_string();
+        let dune = Book::new("Dune", "Frank Herbert", 1965).unwrap();
+        save_books_atomic([&dune], &path).unwrap();
+        assert_eq!(crate::load_books(&path).unwrap(), std::slice::from_ref(&dune));
+
+        fs::create_dir(format!("{path}.tmp")).unwrap();
+        assert!(save_books_atomic(Vec::<Book>::new(), &path).is_err());
+        assert_eq!(crate::load_books(&path).unwrap(), [dune]);
+    }
+}
@@ -0,0 +1,250 @@
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+use predicates::str::contains;
+use tempfile::TempDir;
+
+fn books(store: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("module-3").unwrap();
+    cmd.arg("--file").arg(store);
+    cmd
+}
+
+/// A store with three books in it
+fn store() -> (TempDir, std::path::PathBuf) {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("books.txt");
+    fs::write(
+        &store,
+        "1984,George Orwell,1949\nAnimal Farm,George Orwell,1945\n\"Harry Potter, Book 1\",J. K. Rowling,1997\n",
+    )
+    .unwrap();
+    (dir, store)
+}
+
+#[test]
+fn add_creates_the_store_and_persists() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("new.json");
+    books(&store)
+        .args(["add", "--title", "Dune", "--author", "Frank Herbert", "--year", "1965"])
+        .assert()
+        .success()
+        .stdout("Added Dune by Frank Herbert, published in 1965\n");
+    books(&store)
+        .args(["add", "--title", "Emma", "--author", "Jane Austen", "--year", "1815"])
+        .assert()
+        .success();
+    books(&store)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout("Dune by Frank Herbert, published in 1965\nEmma by Jane Austen, published in 1815\n");
+    assert!(fs::read_to_string(&store).unwrap().starts_with('['));
+}
+
+#[test]
+fn add_takes_the_optional_fields_and_checks_them() {
+    let (_dir, store) = store();
+    books(&store)
+        .args(["add", "--title", "Dune", "--author", "Frank Herbert", "--year", "1965"])
+        .args(["--isbn", "978-0-441-17271-9", "--genre", "Sci-Fi", "--rating", "5", "--status", "read"])
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&store).unwrap().ends_with("Dune,Frank Herbert,1965,9780441172719,Sci-Fi,5,read\n"));
+    books(&store)
+        .args(["add", "--title", "Emma", "--author", "Jane Austen", "--year", "1815", "--isbn", "978-0-441-17271-8"])
+        .assert()
+        .failure()
+        .stderr("Error: invalid book: invalid ISBN '978-0-441-17271-8'\n");
+}
+
+#[test]
+fn duplicate_add_fails_and_leaves_the_store_alone() {
+    let (_dir, store) = store();
+    let before = fs::read_to_string(&store).unwrap();
+    books(&store)
+        .args(["add", "--title", "1984", "--author", "george orwell", "--year", "1949"])
+        .assert()
+        .failure()
+        .stderr("Error: '1984' by george orwell is already in the library\n");
+    assert_eq!(fs::read_to_string(&store).unwrap(), before);
+}
+
+#[test]
+fn list_sorts() {
+    let (_dir, store) = store();
+    books(&store).args(["list", "--sort", "year"]).assert().success().stdout(
+        "Animal Farm by George Orwell, published in 1945\n\
+         1984 by George Orwell, published in 1949\n\
+         Harry Potter, Book 1 by J. K. Rowling, published in 1997\n",
+    );
+    books(&store).args(["list", "--sort", "author,year"]).assert().success().stdout(
+        "Animal Farm by George Orwell, published in 1945\n\
+         1984 by George Orwell, published in 1949\n\
+         Harry Potter, Book 1 by J. K. Rowling, published in 1997\n",
+    );
+    books(&store).args(["list", "--sort", "colour"]).assert().failure().stderr(contains("unknown sort key 'colour'"));
+}
+
+#[test]
+fn search_matches_titles_and_authors() {
+    let (_dir, store) = store();
+    books(&store)
+        .args(["search", "ROWLING"])
+        .assert()
+        .success()
+        .stdout("Harry Potter, Book 1 by J. K. Rowling, published in 1997\n");
+    books(&store).args(["search", "farm"]).assert().success().stdout(contains("Animal Farm"));
+    books(&store).args(["search", "austen"]).assert().success().stdout("No books match 'austen'\n");
+}
+
+#[test]
+fn remove_persists() {
+    let (_dir, store) = store();
+    books(&store)
+        .args(["remove", "--title", "animal farm"])
+        .assert()
+        .success()
+        .stdout("Removed Animal Farm by George Orwell, published in 1945\n");
+    assert!(!fs::read_to_string(&store).unwrap().contains("Animal Farm"));
+    books(&store)
+        .args(["remove", "--title", "Animal Farm"])
+        .assert()
+        .failure()
+        .stderr("Error: no book titled 'Animal Farm'\n");
+}
+
+#[test]
+fn stats_per_decade_and_author() {
+    let (_dir, store) = store();
+    books(&store).arg("stats").assert().success().stdout(
+        "3 books\nBy decade:\n  1940s: 2\n  1990s: 1\nBy author:\n  George Orwell: 2\n  J. K. Rowling: 1\n",
+    );
+}
+
+#[test]
+fn convert_writes_another_format() {
+    let (dir, store) = store();
+    let csv = dir.path().join("books.csv");
+    books(&store).arg("convert").arg(&csv).assert().success().stdout(contains("Wrote 3 books"));
+    assert!(fs::read_to_string(&csv).unwrap().starts_with("title,author,year,isbn,genre,rating,status,"));
+    books(&csv).arg("list").assert().success().stdout(contains("Harry Potter, Book 1 by J. K. Rowling"));
+}
+
+#[test]
+fn merge_prints_the_report_and_saves() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("ours.txt");
+    fs::copy("tests/fixtures/ours.txt", &store).unwrap();
+    books(&store)
+        .args(["merge", "tests/fixtures/theirs_disjoint.txt"])
+        .assert()
+        .success()
+        .stdout(
+            "Added 2:\n  Emma by Jane Austen, published in 1815\n  Neuromancer by William Gibson, published in 1984\n",
+        );
+    books(&store)
+        .args(["merge", "tests/fixtures/theirs_conflicting.txt", "--strategy", "prefer-other"])
+        .assert()
+        .success()
+        .stdout("Updated 1:\n  1984 by George Orwell, published in 1948\n");
+    books(&store).arg("stats").assert().success().stdout(contains("5 books"));
+}
+
+#[test]
+fn failed_save_keeps_the_old_store() {
+    let (_dir, store) = store();
+    let before = fs::read_to_string(&store).unwrap();
+    // Something already sits where the new copy would be written
+    let temp = store.with_extension("txt.tmp");
+    fs::create_dir(&temp).unwrap();
+    books(&store)
+        .args(["add", "--title", "Dune", "--author", "Frank Herbert", "--year", "1965"])
+        .assert()
+        .failure()
+        .stderr(contains("I/O error"));
+    assert_eq!(fs::read_to_string(&store).unwrap(), before);
+    assert!(temp.is_dir());
+}
+
+#[test]
+fn loans_persist_and_show_when_overdue() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("books.csv");
+    books(&store).args(["add", "--title", "Dune", "--author", "Frank Herbert", "--year", "1965"]).assert().success();
+    books(&store)
+        .args(["checkout", "--title", "dune", "--borrower", "Alice", "--due", "2099-06-01"])
+        .assert()
+        .success()
+        .stdout("Lent Dune to Alice, due 2099-06-01 23:59 UTC\n");
+    books(&store)
+        .args(["checkout", "--title", "Dune", "--borrower", "Bob", "--due", "2099-06-01"])
+        .assert()
+        .failure()
+        .stderr("Error: 'Dune' is already lent to Alice\n");
+
+    books(&store).arg("overdue").assert().success().stdout("Nothing is overdue\n");
+    books(&store)
+        .args(["overdue", "--as-of", "2099-06-01T23:59:59Z"])
+        .assert()
+        .success()
+        .stdout("Nothing is overdue\n");
+    books(&store)
+        .args(["overdue", "--as-of", "2099-06-02"])
+        .assert()
+        .success()
+        .stdout("Dune by Frank Herbert, published in 1965: lent to Alice, due 2099-06-01 23:59 UTC\n");
+
+    books(&store).args(["checkin", "--title", "Dune"]).assert().success().stdout("Checked in Dune from Alice\n");
+    books(&store).args(["checkin", "--title", "Dune"]).assert().failure().stderr("Error: 'Dune' is not lent out\n");
+    // Alice is still in the loan history
+    assert!(fs::read_to_string(&store).unwrap().contains("Alice"));
+    books(&store).args(["overdue", "--as-of", "2099-06-02"]).assert().success().stdout("Nothing is overdue\n");
+}
+
+#[test]
+fn search_streams_a_large_store() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("large.txt");
+    let records: String = (0..100_000).map(|i| format!("Book {i},Author {i},{}\n", 1900 + i % 100)).collect();
+    fs::write(&store, records).unwrap();
+    books(&store)
+        .args(["search", "author 99999"])
+        .assert()
+        .success()
+        .stdout("Book 99999 by Author 99999, published in 1999\n");
+    books(&store).args(["search", "book 1000"]).assert().success().stdout(
+        "Book 1000 by Author 1000, published in 1900\n\
+         Book 10000 by Author 10000, published in 1900\n\
+         Book 10001 by Author 10001, published in 1901\n\
+         Book 10002 by Author 10002, published in 1902\n\
+         Book 10003 by Author 10003, published in 1903\n\
+         Book 10004 by Author 10004, published in 1904\n\
+         Book 10005 by Author 10005, published in 1905\n\
+         Book 10006 by Author 10006, published in 1906\n\
+         Book 10007 by Author 10007, published in 1907\n\
+         Book 10008 by Author 10008, published in 1908\n\
+         Book 10009 by Author 10009, published in 1909\n",
+    );
+}
+
+#[test]
+fn fuzzy_search_shows_scores() {
+    let (_dir, store) = store();
+    books(&store).args(["search", "Orwall"]).assert().success().stdout("No books match 'Orwall'\n");
+    books(&store).args(["search", "--fuzzy", "Orwall"]).assert().success().stdout(
+        "0.79  1984 by George Orwell, published in 1949\n0.79  Animal Farm by George Orwell, published in 1945\n",
+    );
+    books(&store)
+        .args(["search", "--fuzzy", "Orwall", "--limit", "1"])
+        .assert()
+        .success()
+        .stdout("0.79  1984 by George Orwell, published in 1949\n");
+    books(&store)
+        .args(["search", "--fuzzy", "harry poter"])
+        .assert()
+        .success()
+        .stdout("0.88  Harry Potter, Book 1 by J. K. Rowling, published in 1997\n");
+}
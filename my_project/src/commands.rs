@@ -0,0 +1,7 @@
+//! The subcommands of the `my_project` binary, one module each. Each has
+//! its arguments and a `run` that returns an error message on failure.
+
+pub mod guess;
+pub mod numbers;
+pub mod temps;
+pub mod words;
@@ -0,0 +1,57 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use clap::Args;
+use my_project::guessing::{Difficulty, HighScores, Outcome, play, play_bot};
+use rand::Rng;
+
+#[derive(Args)]
+pub struct GuessArgs {
+    #[arg(long, value_enum, default_value_t = Difficulty::Normal)]
+    difficulty: Difficulty,
+    /// Show the best wins and exit
+    #[arg(long, conflicts_with = "bot")]
+    scores: bool,
+    /// Watch the computer find the number by halving the range each guess
+    #[arg(long)]
+    bot: bool,
+    /// Where the best wins are kept
+    #[arg(long, value_name = "FILE", default_value = "guess_scores.json")]
+    scores_file: PathBuf,
+}
+
+/// The scores in `path`, or none after a warning if the file is unreadable
+fn load_scores(path: &Path) -> HighScores {
+    HighScores::load(path).unwrap_or_else(|e| {
+        eprintln!("Warning: {e}; starting a new score list");
+        HighScores::default()
+    })
+}
+
+pub fn run(args: GuessArgs) -> Result<(), String> {
+    let mut scores = load_scores(&args.scores_file);
+    if args.scores {
+        print!("{scores}");
+        return Ok(());
+    }
+    let secret = rand::rng().random_range(args.difficulty.range());
+    if args.bot {
+        play_bot(secret, args.difficulty, io::stdout()).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+    let outcome = play(secret, args.difficulty, io::stdin().lock(), io::stdout())
+        .map_err(|e| e.to_string())?;
+    if let Outcome::Won { attempts } = outcome
+        && let Some(rank) = scores.record(args.difficulty, attempts, Utc::now())
+    {
+        println!("That's number {rank} on the high score list!");
+        if let Err(e) = scores.save(&args.scores_file) {
+            eprintln!(
+                "Warning: cannot save scores to {}: {e}",
+                args.scores_file.display()
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use clap::Args;
+use my_project::input::{parse_words, read_all};
+use my_project::numbers::{Rule, Stats, analyze, fizz_buzz_rules};
+
+#[derive(Args)]
+pub struct NumbersArgs {
+    /// Numbers to analyze; read from standard input, separated by spaces or
+    /// lines, if none are given
+    #[arg(allow_negative_numbers = true)]
+    numbers: Vec<i64>,
+    /// Another rule as DIVISOR:LABEL, like 7:Boom; may be repeated. Labels
+    /// of matching rules join up in the order given, after Fizz and Buzz
+    #[arg(long = "rule", value_name = "DIVISOR:LABEL")]
+    rules: Vec<Rule>,
+    /// Leave out the Fizz and Buzz rules
+    #[arg(long)]
+    no_default_rules: bool,
+}
+
+pub fn run(args: NumbersArgs) -> Result<(), String> {
+    let numbers = if args.numbers.is_empty() {
+        parse_words(&read_all(None)?, "a whole number")?
+    } else {
+        args.numbers
+    };
+    let mut rules = if args.no_default_rules {
+        Vec::new()
+    } else {
+        fizz_buzz_rules()
+    };
+    rules.extend(args.rules);
+
+    let Some(stats) = Stats::of(&numbers) else {
+        println!("No numbers");
+        return Ok(());
+    };
+    for classification in analyze(&numbers, &rules) {
+        println!("{}: {}", classification.number, classification.label);
+    }
+    println!();
+    println!("Count:  {}", stats.count);
+    println!("Sum:    {}", stats.sum);
+    println!("Min:    {}", stats.min);
+    println!("Max:    {}", stats.max);
+    println!("Mean:   {:.2}", stats.mean);
+    println!("Median: {}", stats.median);
+    println!("Mode:   {}", stats.mode);
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use std::io::BufRead;
+
+use clap::{Args, Subcommand, ValueEnum};
+use my_project::input::open;
+use my_project::temperature::{Temperature, Unit};
+use serde::Serialize;
+
+#[derive(Args)]
+pub struct TempsArgs {
+    #[arg(long, value_enum, default_value_t = Format::Text, global = true)]
+    format: Format,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert one value, or one per line of standard input
+    #[command(allow_negative_numbers = true)]
+    Convert {
+        /// Read values from standard input if not given
+        value: Option<f64>,
+        #[arg(long)]
+        from: Unit,
+        #[arg(long)]
+        to: Unit,
+    },
+    /// Show a range of values next to their conversions
+    #[command(allow_negative_numbers = true)]
+    Table {
+        /// First value
+        #[arg(long)]
+        from: f64,
+        /// Last value, included if a step lands on it
+        #[arg(long)]
+        to: f64,
+        #[arg(long, default_value_t = 10.0)]
+        step: f64,
+        /// Unit of the values
+        #[arg(long)]
+        unit: Unit,
+        /// Unit to convert them to
+        #[arg(long)]
+        target: Unit,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Text, and aligned columns for a table
+    Text,
+    /// An array of {"value", "from", "converted", "to"} objects
+    Json,
+}
+
+#[derive(Serialize)]
+struct Conversion {
+    value: f64,
+    from: Unit,
+    converted: f64,
+    to: Unit,
+}
+
+impl Conversion {
+    fn new(value: f64, from: Unit, to: Unit) -> Result<Self, String> {
+        let converted = Temperature::new(value, from)
+            .map_err(|e| e.to_string())?
+            .to(to);
+        Ok(Conversion {
+            value,
+            from,
+            converted: converted.value(),
+            to,
+        })
+    }
+
+    /// `98.6°F = 37.00°C`
+    fn describe(&self) -> String {
+        format!(
+            "{}{} = {:.2}{}",
+            self.value,
+            self.from.symbol(),
+            self.converted,
+            self.to.symbol()
+        )
+    }
+}
+
+fn print_json(conversions: &[Conversion]) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(conversions).expect("conversions serialize")
+    );
+}
+
+/// Conversions for the values on each line of `input`, reporting lines that
+/// aren't a temperature on standard error. Blank lines are skipped.
+fn convert_lines(
+    input: impl BufRead,
+    from: Unit,
+    to: Unit,
+) -> Result<(Vec<Conversion>, usize), String> {
+    let mut conversions = Vec::new();
+    let mut failed = 0;
+    for (i, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| format!("cannot read input: {e}"))?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let conversion = line
+            .parse()
+            .map_err(|_| format!("'{line}' is not a number"))
+            .and_then(|value| Conversion::new(value, from, to));
+        match conversion {
+            Ok(conversion) => conversions.push(conversion),
+            Err(e) => {
+                eprintln!("line {}: {e}", i + 1);
+                failed += 1;
+            }
+        }
+    }
+    Ok((conversions, failed))
+}
+
+/// `from` to `to` in steps of `step`, worked out from the start each time
+/// so steps like 0.1 don't drift
+fn range(from: f64, to: f64, step: f64) -> Result<Vec<f64>, String> {
+    if !(step > 0.0 && step.is_finite()) {
+        return Err(format!("step must be above 0, not {step}"));
+    }
+    if to < from {
+        return Err(format!("--to {to} is below --from {from}"));
+    }
+    let steps = ((to - from) / step + 1e-9).floor() as usize;
+    Ok((0..=steps).map(|i| from + i as f64 * step).collect())
+}
+
+fn print_table(conversions: &[Conversion], unit: Unit, target: Unit) {
+    let cells: Vec<(String, String)> = conversions
+        .iter()
+        .map(|c| (format!("{:.2}", c.value), format!("{:.2}", c.converted)))
+        .collect();
+    // The symbols count by characters; the cells are all ASCII
+    let mut left = unit.symbol().chars().count();
+    let mut right = target.symbol().chars().count();
+    for (value, converted) in &cells {
+        left = left.max(value.len());
+        right = right.max(converted.len());
+    }
+    println!("{:>left$}  {:>right$}", unit.symbol(), target.symbol());
+    for (value, converted) in cells {
+        println!("{value:>left$}  {converted:>right$}");
+    }
+}
+
+pub fn run(args: TempsArgs) -> Result<(), String> {
+    match args.command {
+        Command::Convert {
+            value: Some(value),
+            from,
+            to,
+        } => {
+            let conversion = Conversion::new(value, from, to)?;
+            match args.format {
+                Format::Text => println!("{}", conversion.describe()),
+                Format::Json => print_json(&[conversion]),
+            }
+        }
+        Command::Convert {
+            value: None,
+            from,
+            to,
+        } => {
+            let (conversions, failed) = convert_lines(open(None)?, from, to)?;
+            match args.format {
+                Format::Text => {
+                    for conversion in &conversions {
+                        println!("{}", conversion.describe());
+                    }
+                }
+                Format::Json => print_json(&conversions),
+            }
+            if failed > 0 {
+                return Err(format!("{failed} of the values could not be converted"));
+            }
+        }
+        Command::Table {
+            from,
+            to,
+            step,
+            unit,
+            target,
+        } => {
+            let conversions = range(from, to, step)?
+                .into_iter()
+                .map(|value| Conversion::new(value, unit, target))
+                .collect::<Result<Vec<_>, _>>()?;
+            match args.format {
+                Format::Text => print_table(&conversions, unit, target),
+                Format::Json => print_json(&conversions),
+            }
+        }
+    }
+    Ok(())
+}
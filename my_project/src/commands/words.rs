@@ -0,0 +1,185 @@
+use std::fs;
+
+use clap::{Args, ValueEnum};
+use my_project::input::{name, open, read_all};
+use my_project::stop_words::StopWords;
+use my_project::tokenizer::Tokenizer;
+use my_project::word_freq::{MIN_COUNT, WordComparison, compare_frequencies_min, top_n_words_from};
+use serde::Serialize;
+
+#[derive(Args)]
+pub struct WordsArgs {
+    /// Text to read; standard input if not given or "-"
+    file: Option<String>,
+    /// How many words to show
+    #[arg(long, short = 'n', default_value_t = 10)]
+    top: usize,
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    /// Skip words with fewer letters than this
+    #[arg(long, default_value_t = 1)]
+    min_len: usize,
+    /// File of stop words, one per line, to use instead of the English list
+    #[arg(long, value_name = "FILE")]
+    stop_words: Option<String>,
+    /// Count every word, common or not
+    #[arg(long, conflicts_with = "stop_words")]
+    no_stop_words: bool,
+    /// Another word to leave out; may be repeated
+    #[arg(long, value_name = "WORD")]
+    stop: Vec<String>,
+    /// A stop word to count after all; may be repeated
+    #[arg(long, value_name = "WORD")]
+    keep: Vec<String>,
+    /// Compare with another file instead, showing the words most typical of
+    /// each
+    #[arg(long, value_name = "OTHER")]
+    compare: Option<String>,
+    /// With --compare, leave out words seen fewer times than this in both
+    #[arg(long, default_value_t = MIN_COUNT, requires = "compare")]
+    min_count: usize,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Aligned columns
+    Table,
+    /// An array of {"word", "count"} objects
+    Json,
+}
+
+#[derive(Serialize)]
+struct WordCount<'a> {
+    word: &'a str,
+    count: usize,
+}
+
+fn stop_words(args: &WordsArgs) -> Result<StopWords, String> {
+    let stop_words = match &args.stop_words {
+        _ if args.no_stop_words => StopWords::none(),
+        Some(path) => StopWords::parse(
+            &fs::read_to_string(path).map_err(|e| format!("cannot read {path}: {e}"))?,
+        ),
+        None => StopWords::english(),
+    };
+    Ok(stop_words.with(&args.stop).without(&args.keep))
+}
+
+fn print_table(top: &[(String, usize)]) {
+    if top.is_empty() {
+        println!("No words found");
+        return;
+    }
+    let word_width = top
+        .iter()
+        .map(|(w, _)| w.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let count_width = top
+        .iter()
+        .map(|(_, c)| c.to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max(5);
+    println!("{:<word_width$}  {:>count_width$}", "WORD", "COUNT");
+    for (word, count) in top {
+        println!("{word:<word_width$}  {count:>count_width$}");
+    }
+}
+
+/// `rows` under `heading`, with words padded to `width`
+fn print_comparison_table(heading: &str, rows: &[&WordComparison], width: usize) {
+    println!("More common in {heading}:");
+    if rows.is_empty() {
+        println!("  nothing");
+        return;
+    }
+    println!(
+        "  {:<width$}  {:>5}  {:>5}  {:>5}",
+        "WORD", "HERE", "OTHER", "SCORE"
+    );
+    for c in rows {
+        let (here, other) = if c.score > 0.0 {
+            (c.count_a, c.count_b)
+        } else {
+            (c.count_b, c.count_a)
+        };
+        println!(
+            "  {:<width$}  {here:>5}  {other:>5}  {:>5.2}",
+            c.word,
+            c.score.abs()
+        );
+    }
+}
+
+/// The words most typical of the input and of `other`, `args.top` of each
+fn compare(args: &WordsArgs, other: &str, tokenizer: &Tokenizer) -> Result<(), String> {
+    let a = read_all(args.file.as_deref())?;
+    let b = read_all(Some(other))?;
+    let compared = compare_frequencies_min(&a, &b, tokenizer, args.min_count);
+    let more_in_a: Vec<&WordComparison> = compared
+        .iter()
+        .filter(|c| c.score > 0.0)
+        .take(args.top)
+        .collect();
+    let more_in_b: Vec<&WordComparison> = compared
+        .iter()
+        .rev()
+        .filter(|c| c.score < 0.0)
+        .take(args.top)
+        .collect();
+    match args.format {
+        Format::Table => {
+            let name = name(args.file.as_deref());
+            let width = more_in_a
+                .iter()
+                .chain(&more_in_b)
+                .map(|c| c.word.chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(4);
+            print_comparison_table(name, &more_in_a, width);
+            print_comparison_table(other, &more_in_b, width);
+        }
+        Format::Json => {
+            let json = serde_json::json!({ "more_in_a": more_in_a, "more_in_b": more_in_b });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json).expect("comparisons serialize")
+            );
+        }
+    }
+    Ok(())
+}
+
+pub fn run(args: WordsArgs) -> Result<(), String> {
+    let tokenizer = Tokenizer::new()
+        .lowercase(true)
+        .strip_punctuation(true)
+        .min_len(args.min_len)
+        .stop_words(stop_words(&args)?);
+    if let Some(other) = &args.compare {
+        return compare(&args, other, &tokenizer);
+    }
+    // Read a line at a time, so the input can be bigger than memory
+    let top = top_n_words_from(open(args.file.as_deref())?, args.top, &tokenizer)
+        .map_err(|e| format!("cannot read input: {e}"))?;
+    match args.format {
+        Format::Table => print_table(&top),
+        Format::Json => {
+            let rows: Vec<WordCount> = top
+                .iter()
+                .map(|(word, count)| WordCount {
+                    word,
+                    count: *count,
+                })
+                .collect();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&rows).expect("word counts serialize")
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,459 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Wins kept per difficulty in `HighScores`
+pub const SCORES_KEPT: usize = 5;
+
+/// 0 if `guess` is the secret, 1 if it's too high and -1 if it's too low
+pub fn check_guess(guess: i32, secret: i32) -> i32 {
+    if guess == secret {
+        0
+    } else if guess > secret {
+        1
+    } else {
+        -1
+    }
+}
+
+/// How close a wrong guess was
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Closeness {
+    /// Within a tenth of the range of the secret
+    Warm,
+    Cold,
+}
+
+/// What a guess tells the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hint {
+    Correct,
+    TooHigh(Closeness),
+    TooLow(Closeness),
+}
+
+/// `check_guess`, plus whether a wrong guess was warm or cold for a secret
+/// in `range`
+pub fn check_guess_with_hint(guess: i32, secret: i32, range: &RangeInclusive<i32>) -> Hint {
+    let warm_within = ((range.end() - range.start() + 1) / 10).max(1);
+    let closeness = if (guess - secret).abs() <= warm_within {
+        Closeness::Warm
+    } else {
+        Closeness::Cold
+    };
+    match check_guess(guess, secret) {
+        0 => Hint::Correct,
+        1 => Hint::TooHigh(closeness),
+        _ => Hint::TooLow(closeness),
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    /// 1 to 10, 5 guesses
+    Easy,
+    /// 1 to 100, 8 guesses
+    #[default]
+    Normal,
+    /// 1 to 1000, 12 guesses
+    Hard,
+}
+
+impl Difficulty {
+    /// The numbers the secret is picked from
+    pub fn range(self) -> RangeInclusive<i32> {
+        match self {
+            Difficulty::Easy => 1..=10,
+            Difficulty::Normal => 1..=100,
+            Difficulty::Hard => 1..=1000,
+        }
+    }
+
+    /// Guesses the player gets
+    pub fn max_attempts(self) -> u32 {
+        match self {
+            Difficulty::Easy => 5,
+            Difficulty::Normal => 8,
+            Difficulty::Hard => 12,
+        }
+    }
+}
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Won {
+        attempts: u32,
+    },
+    /// Out of guesses
+    Lost,
+    /// The player typed "q", or the input ran out
+    Quit,
+}
+
+/// Play a game for `secret`, reading guesses a line at a time from `input`
+/// and writing prompts and hints to `output`. Lines that aren't a number in
+/// range are asked for again and don't use up a guess.
+pub fn play(
+    secret: i32,
+    difficulty: Difficulty,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<Outcome> {
+    let range = difficulty.range();
+    let max_attempts = difficulty.max_attempts();
+    writeln!(output, "Welcome to the Guessing Game!")?;
+    writeln!(
+        output,
+        "I'm thinking of a number from {} to {}. You have {max_attempts} guesses; type q to quit.",
+        range.start(),
+        range.end()
+    )?;
+
+    let mut attempts = 0;
+    let mut line = String::new();
+    while attempts < max_attempts {
+        write!(output, "Guess {}: ", attempts + 1)?;
+        output.flush()?;
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(Outcome::Quit);
+        }
+        let line = line.trim();
+        if line.eq_ignore_ascii_case("q") {
+            writeln!(output, "Bye! The number was {secret}.")?;
+            return Ok(Outcome::Quit);
+        }
+        let guess: i32 = match line.parse() {
+            Ok(guess) if range.contains(&guess) => guess,
+            _ => {
+                writeln!(
+                    output,
+                    "'{line}' isn't a number from {} to {}.",
+                    range.start(),
+                    range.end()
+                )?;
+                continue;
+            }
+        };
+        attempts += 1;
+
+        let (direction, closeness) = match check_guess_with_hint(guess, secret, &range) {
+            Hint::Correct => {
+                writeln!(
+                    output,
+                    "{guess} is correct! You found it in {attempts} guesses."
+                )?;
+                return Ok(Outcome::Won { attempts });
+            }
+            Hint::TooHigh(closeness) => ("high", closeness),
+            Hint::TooLow(closeness) => ("low", closeness),
+        };
+        let closeness = match closeness {
+            Closeness::Warm => "warm",
+            Closeness::Cold => "cold",
+        };
+        writeln!(output, "{guess} is too {direction}, but {closeness}.")?;
+    }
+
+    writeln!(output, "Out of guesses! The number was {secret}.")?;
+    Ok(Outcome::Lost)
+}
+
+/// Find `secret` by halving the range with `check_guess` each time, writing
+/// each probe to `output`. Returns the guesses it took, which is never more
+/// than ⌈log2(size of the range)⌉.
+pub fn play_bot(secret: i32, difficulty: Difficulty, mut output: impl Write) -> io::Result<u32> {
+    let range = difficulty.range();
+    let (mut low, mut high) = (*range.start(), *range.end());
+    let mut attempts = 0;
+    while low <= high {
+        let probe = low + (high - low) / 2;
+        attempts += 1;
+        match check_guess(probe, secret) {
+            0 => {
+                writeln!(output, "Probe {attempts}: {probe} is correct")?;
+                return Ok(attempts);
+            }
+            1 => {
+                writeln!(output, "Probe {attempts}: {probe} is too high")?;
+                high = probe - 1;
+            }
+            _ => {
+                writeln!(output, "Probe {attempts}: {probe} is too low")?;
+                low = probe + 1;
+            }
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{secret} is outside {low}..={high}"),
+    ))
+}
+
+/// A win worth remembering.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    pub attempts: u32,
+    pub at: DateTime<Utc>,
+}
+
+/// The fewest-guess wins for each difficulty, best first, as kept in a
+/// small JSON file between games.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HighScores {
+    #[serde(flatten)]
+    pub scores: BTreeMap<Difficulty, Vec<Score>>,
+}
+
+impl HighScores {
+    /// The scores in `path`; none if there's no such file yet. A file that
+    /// isn't valid JSON is an error, for the caller to warn about and start
+    /// over.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("{} is not a score file: {e}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HighScores::default()),
+            Err(e) => Err(format!("cannot read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).expect("scores serialize");
+        fs::write(path, json + "\n")
+    }
+
+    /// The wins for `difficulty`, best first
+    pub fn best(&self, difficulty: Difficulty) -> &[Score] {
+        self.scores.get(&difficulty).map_or(&[], Vec::as_slice)
+    }
+
+    /// Add a win in `attempts` guesses. Fewer guesses rank higher, and the
+    /// earlier of two equal wins keeps its place; only the best
+    /// `SCORES_KEPT` are kept. Returns where the win ranks from 1, if it
+    /// made the list.
+    pub fn record(
+        &mut self,
+        difficulty: Difficulty,
+        attempts: u32,
+        at: DateTime<Utc>,
+    ) -> Option<usize> {
+        let scores = self.scores.entry(difficulty).or_default();
+        let rank = scores.partition_point(|s| s.attempts <= attempts);
+        if rank >= SCORES_KEPT {
+            return None;
+        }
+        scores.insert(rank, Score { attempts, at });
+        scores.truncate(SCORES_KEPT);
+        Some(rank + 1)
+    }
+}
+
+/// ```text
+/// easy:
+///   1. 2 guesses on 2024-05-01 18:30 UTC
+/// normal:
+///   no wins yet
+/// ```
+impl fmt::Display for HighScores {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for difficulty in Difficulty::value_variants() {
+            let name = difficulty.to_possible_value().expect("no skipped variants");
+            writeln!(f, "{}:", name.get_name())?;
+            let best = self.best(*difficulty);
+            if best.is_empty() {
+                writeln!(f, "  no wins yet")?;
+            }
+            for (i, score) in best.iter().enumerate() {
+                let guesses = if score.attempts == 1 {
+                    "guess"
+                } else {
+                    "guesses"
+                };
+                writeln!(
+                    f,
+                    "  {}. {} {guesses} on {}",
+                    i + 1,
+                    score.attempts,
+                    score.at.format("%Y-%m-%d %H:%M UTC")
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// The outcome and everything written for a game fed `input`
+    fn game(secret: i32, difficulty: Difficulty, input: &str) -> (Outcome, String) {
+        let mut output = Vec::new();
+        let outcome = play(secret, difficulty, input.as_bytes(), &mut output).unwrap();
+        (outcome, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn hints_say_which_way_and_how_close() {
+        let range = Difficulty::Normal.range();
+        assert_eq!(check_guess_with_hint(42, 42, &range), Hint::Correct);
+        assert_eq!(
+            check_guess_with_hint(52, 42, &range),
+            Hint::TooHigh(Closeness::Warm)
+        );
+        assert_eq!(
+            check_guess_with_hint(53, 42, &range),
+            Hint::TooHigh(Closeness::Cold)
+        );
+        assert_eq!(
+            check_guess_with_hint(1, 42, &range),
+            Hint::TooLow(Closeness::Cold)
+        );
+        // Only the next number over is warm on easy
+        let easy = Difficulty::Easy.range();
+        assert_eq!(
+            check_guess_with_hint(6, 7, &easy),
+            Hint::TooLow(Closeness::Warm)
+        );
+        assert_eq!(
+            check_guess_with_hint(5, 7, &easy),
+            Hint::TooLow(Closeness::Cold)
+        );
+    }
+
+    #[test]
+    fn finding_the_secret_wins() {
+        let (outcome, output) = game(42, Difficulty::Normal, "50\n40\n42\n");
+        assert_eq!(outcome, Outcome::Won { attempts: 3 });
+        assert!(output.contains("Guess 1: 50 is too high, but warm.\n"));
+        assert!(output.contains("Guess 2: 40 is too low, but warm.\n"));
+        assert!(output.ends_with("Guess 3: 42 is correct! You found it in 3 guesses.\n"));
+    }
+
+    #[test]
+    fn running_out_of_guesses_loses() {
+        let (outcome, output) = game(7, Difficulty::Easy, "1\n2\n3\n4\n5\n7\n");
+        assert_eq!(outcome, Outcome::Lost);
+        assert!(output.contains("Guess 5: 5 is too low, but cold.\n"));
+        assert!(output.ends_with("Out of guesses! The number was 7.\n"));
+    }
+
+    #[test]
+    fn invalid_input_is_asked_for_again() {
+        let (outcome, output) = game(7, Difficulty::Easy, "seven\n\n11\n0\n-3\n7\n");
+        assert_eq!(outcome, Outcome::Won { attempts: 1 });
+        assert!(output.contains("'seven' isn't a number from 1 to 10.\n"));
+        assert!(output.contains("'' isn't a number from 1 to 10.\n"));
+        assert!(output.contains("'11' isn't a number from 1 to 10.\n"));
+        assert_eq!(output.matches("Guess 1: ").count(), 6);
+    }
+
+    #[test]
+    fn q_or_the_end_of_input_quits() {
+        let (outcome, output) = game(500, Difficulty::Hard, "250\n Q \n400\n");
+        assert_eq!(outcome, Outcome::Quit);
+        assert!(output.ends_with("Guess 2: Bye! The number was 500.\n"));
+        assert_eq!(game(500, Difficulty::Hard, "250\n").0, Outcome::Quit);
+    }
+
+    /// ⌈log2(n)⌉
+    fn log2_ceil(n: i32) -> u32 {
+        (n as u32).next_power_of_two().trailing_zeros()
+    }
+
+    #[test]
+    fn the_bot_finds_every_secret_within_log2_guesses() {
+        for difficulty in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard] {
+            let range = difficulty.range();
+            let limit = log2_ceil(range.end() - range.start() + 1);
+            let worst = range
+                .clone()
+                .map(|secret| play_bot(secret, difficulty, io::sink()).unwrap())
+                .max()
+                .unwrap();
+            assert_eq!(worst, limit);
+            assert!(worst <= difficulty.max_attempts());
+        }
+        assert_eq!(log2_ceil(1000), 10);
+
+        let mut output = Vec::new();
+        assert_eq!(play_bot(7, Difficulty::Easy, &mut output).unwrap(), 4);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Probe 1: 5 is too low\nProbe 2: 8 is too high\nProbe 3: 6 is too low\n\
+             Probe 4: 7 is correct\n"
+        );
+    }
+
+    #[test]
+    fn fewer_guesses_rank_higher_and_ties_keep_their_place() {
+        let start = Utc::now();
+        let at = |minutes| start + TimeDelta::minutes(minutes);
+        let mut scores = HighScores::default();
+        assert_eq!(scores.record(Difficulty::Normal, 6, at(0)), Some(1));
+        assert_eq!(scores.record(Difficulty::Normal, 4, at(1)), Some(1));
+        assert_eq!(scores.record(Difficulty::Normal, 6, at(2)), Some(3));
+        assert_eq!(scores.record(Difficulty::Easy, 3, at(3)), Some(1));
+        let normal: Vec<(u32, DateTime<Utc>)> = scores
+            .best(Difficulty::Normal)
+            .iter()
+            .map(|s| (s.attempts, s.at))
+            .collect();
+        assert_eq!(normal, [(4, at(1)), (6, at(0)), (6, at(2))]);
+
+        for minutes in 4..6 {
+            scores.record(Difficulty::Normal, 5, at(minutes));
+        }
+        assert_eq!(scores.best(Difficulty::Normal).len(), SCORES_KEPT);
+        assert_eq!(scores.record(Difficulty::Normal, 6, at(6)), None);
+        assert_eq!(scores.record(Difficulty::Normal, 1, at(7)), Some(1));
+        let attempts: Vec<u32> = scores
+            .best(Difficulty::Normal)
+            .iter()
+            .map(|s| s.attempts)
+            .collect();
+        assert_eq!(attempts, [1, 4, 5, 5, 6]);
+        assert!(scores.best(Difficulty::Hard).is_empty());
+    }
+
+    #[test]
+    fn scores_round_trip_through_a_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("scores.json");
+        assert_eq!(HighScores::load(&path).unwrap(), HighScores::default());
+
+        let mut scores = HighScores::default();
+        scores.record(Difficulty::Hard, 9, Utc::now());
+        scores.record(Difficulty::Easy, 2, Utc::now());
+        scores.save(&path).unwrap();
+        assert_eq!(HighScores::load(&path).unwrap(), scores);
+        assert!(fs::read_to_string(&path).unwrap().contains("\"hard\""));
+
+        let listing = scores.to_string();
+        assert!(listing.starts_with("easy:\n  1. 2 guesses on "));
+        assert!(listing.contains("normal:\n  no wins yet\nhard:\n  1. 9 guesses on "));
+
+        fs::write(&path, "{ not json").unwrap();
+        assert!(
+            HighScores::load(&path)
+                .unwrap_err()
+                .contains("is not a score file")
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// `file`, or standard input for none or "-", read a line at a time
+pub fn open(file: Option<&str>) -> Result<Box<dyn BufRead>, String> {
+    match file {
+        None | Some("-") => Ok(Box::new(io::stdin().lock())),
+        Some(path) => Ok(Box::new(BufReader::new(
+            File::open(path).map_err(|e| format!("cannot read {path}: {e}"))?,
+        ))),
+    }
+}
+
+/// All of `file`, or of standard input for none or "-"
+pub fn read_all(file: Option<&str>) -> Result<String, String> {
+    let mut text = String::new();
+    open(file)?
+        .read_to_string(&mut text)
+        .map_err(|e| format!("cannot read {}: {e}", name(file)))?;
+    Ok(text)
+}
+
+/// What to call `file` in messages
+pub fn name(file: Option<&str>) -> &str {
+    file.filter(|f| *f != "-").unwrap_or("standard input")
+}
+
+/// The whitespace-separated words of `text` parsed as `T`, failing on the
+/// first that isn't one; `what` names a `T` for the error
+pub fn parse_words<T: std::str::FromStr>(text: &str, what: &str) -> Result<Vec<T>, String> {
+    text.split_whitespace()
+        .map(|word| word.parse().map_err(|_| format!("'{word}' is not {what}")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_parse_or_fail_on_the_first_bad_one() {
+        assert_eq!(
+            parse_words::<i64>(" 1 -2\n3 ", "a whole number"),
+            Ok(vec![1, -2, 3])
+        );
+        assert_eq!(
+            parse_words::<i64>("1 two 3 four", "a whole number"),
+            Err("'two' is not a whole number".to_string())
+        );
+        assert_eq!(parse_words::<f64>("", "a number"), Ok(vec![]));
+    }
+
+    #[test]
+    fn missing_files_are_named() {
+        let err = open(Some("/no/such/file")).err().unwrap();
+        assert!(err.starts_with("cannot read /no/such/file: "));
+        assert_eq!(name(Some("-")), "standard input");
+        assert_eq!(name(Some("a.txt")), "a.txt");
+    }
+}
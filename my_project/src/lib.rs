@@ -0,0 +1,10 @@
+//! The assignments as a library, so their functions can be tested and
+//! reused. The `my_project` binary gives each a subcommand.
+
+pub mod guessing;
+pub mod input;
+pub mod numbers;
+pub mod stop_words;
+pub mod temperature;
+pub mod tokenizer;
+pub mod word_freq;
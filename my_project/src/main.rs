@@ -1,3 +1,43 @@
+use std::process;
+
+use clap::{Parser, Subcommand};
+
+mod commands;
+
+use commands::{guess, numbers, temps, words};
+
+/// The summer assignments, from the command line.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert temperatures between Celsius, Fahrenheit and Kelvin. Units
+    /// can be given as c, celsius or °C, and so on.
+    Temps(temps::TempsArgs),
+    /// Label numbers Fizz, Buzz and so on, or else even or odd, and show
+    /// some statistics about them
+    Numbers(numbers::NumbersArgs),
+    /// Guess the secret number, with a hint after each try
+    Guess(guess::GuessArgs),
+    /// Show the most frequent words in a file or standard input, ignoring
+    /// case, punctuation and common English words
+    Words(words::WordsArgs),
+}
+
 fn main() {
-    println!("Hello Jose Maldonado!");
-}
\ No newline at end of file
+    let result = match Cli::parse().command {
+        Command::Temps(args) => temps::run(args),
+        Command::Numbers(args) => numbers::run(args),
+        Command::Guess(args) => guess::run(args),
+        Command::Words(args) => words::run(args),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+pub fn is_even(n: i32) -> bool {
+    n % 2 == 0
+}
+
+/// The sum from `low` to `high` inclusive, stepping by `step`. Panics if
+/// `step` is 0.
+pub fn sum_with_step(low: i64, high: i64, step: usize) -> i128 {
+    (low..=high).step_by(step).map(i128::from).sum()
+}
+
+/// Label numbers divisible by `divisor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    divisor: i64,
+    label: String,
+}
+
+impl Rule {
+    /// Fails for a divisor of 0 or an empty label
+    pub fn new(divisor: i64, label: &str) -> Result<Self, String> {
+        if divisor == 0 {
+            return Err(format!("rule '{label}' cannot divide by 0"));
+        }
+        if label.trim().is_empty() {
+            return Err(format!("rule for {divisor} has no label"));
+        }
+        Ok(Rule {
+            divisor,
+            label: label.trim().to_string(),
+        })
+    }
+
+    pub fn divisor(&self) -> i64 {
+        self.divisor
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn matches(&self, n: i64) -> bool {
+        // Wrapping, as i64::MIN % -1 overflows though it divides evenly
+        n.wrapping_rem(self.divisor) == 0
+    }
+}
+
+/// `7:Boom`
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (divisor, label) = s
+            .split_once(':')
+            .ok_or_else(|| format!("rule '{s}' should be DIVISOR:LABEL"))?;
+        let divisor = divisor
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid divisor '{}' in rule '{s}'", divisor.trim()))?;
+        Rule::new(divisor, label)
+    }
+}
+
+/// Fizz on 3 and Buzz on 5, so 15 is FizzBuzz
+pub fn fizz_buzz_rules() -> Vec<Rule> {
+    vec![
+        Rule::new(3, "Fizz").expect("valid rule"),
+        Rule::new(5, "Buzz").expect("valid rule"),
+    ]
+}
+
+/// What a number was called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Label {
+    /// The labels of every rule it matched, in rule order
+    Rules(String),
+    /// No rule matched
+    Even,
+    Odd,
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Label::Rules(labels) => f.write_str(labels),
+            Label::Even => f.write_str("Even"),
+            Label::Odd => f.write_str("Odd"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Classification {
+    pub number: i64,
+    pub label: Label,
+}
+
+/// Label each of `numbers` with the rules it matches, or as even or odd if
+/// it matches none.
+pub fn analyze(numbers: &[i64], rules: &[Rule]) -> Vec<Classification> {
+    numbers
+        .iter()
+        .map(|&number| {
+            let labels: String = rules
+                .iter()
+                .filter(|rule| rule.matches(number))
+                .map(Rule::label)
+                .collect();
+            let label = if !labels.is_empty() {
+                Label::Rules(labels)
+            } else if number % 2 == 0 {
+                Label::Even
+            } else {
+                Label::Odd
+            };
+            Classification { number, label }
+        })
+        .collect()
+}
+
+/// Summary figures for a list of numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    /// Wide enough that no list of i64s can overflow it
+    pub sum: i128,
+    pub min: i64,
+    pub max: i64,
+    pub mean: f64,
+    /// The middle number, or halfway between the middle two
+    pub median: f64,
+    /// The most common number; the smallest of them if there's a tie
+    pub mode: i64,
+}
+
+impl Stats {
+    /// `None` for no numbers
+    pub fn of(numbers: &[i64]) -> Option<Self> {
+        let mut sorted = numbers.to_vec();
+        sorted.sort_unstable();
+        let (&min, &max) = (sorted.first()?, sorted.last()?);
+        let count = sorted.len();
+        let sum: i128 = sorted.iter().map(|&n| i128::from(n)).sum();
+
+        let middle = count / 2;
+        let median = if count % 2 == 1 {
+            sorted[middle] as f64
+        } else {
+            (sorted[middle - 1] as f64 + sorted[middle] as f64) / 2.0
+        };
+
+        let mut counts: HashMap<i64, usize> = HashMap::new();
+        for &n in &sorted {
+            *counts.entry(n).or_default() += 1;
+        }
+        let (mode, _) = counts
+            .into_iter()
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+            .expect("not empty");
+
+        Some(Stats {
+            count,
+            sum,
+            min,
+            max,
+            mean: sum as f64 / count as f64,
+            median,
+            mode,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(numbers: &[i64], rules: &[Rule]) -> Vec<String> {
+        analyze(numbers, rules)
+            .into_iter()
+            .map(|c| c.label.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn labels_combine_in_rule_order() {
+        let numbers = [12, 3, 5, 7, 15, 20, 22, 9, 10, 30];
+        assert_eq!(
+            labels(&numbers, &fizz_buzz_rules()),
+            [
+                "Fizz", "Fizz", "Buzz", "Odd", "FizzBuzz", "Buzz", "Even", "Fizz", "Buzz",
+                "FizzBuzz"
+            ]
+        );
+
+        let mut rules = fizz_buzz_rules();
+        rules.push("7:Boom".parse().unwrap());
+        assert_eq!(
+            labels(&[105, 21, 14], &rules),
+            ["FizzBuzzBoom", "FizzBoom", "Boom"]
+        );
+        rules.reverse();
+        assert_eq!(labels(&[105], &rules), ["BoomBuzzFizz"]);
+    }
+
+    #[test]
+    fn even_and_odd_only_when_no_rule_matches() {
+        assert_eq!(labels(&[4, 7, 0], &[]), ["Even", "Odd", "Even"]);
+        // 6 is even, but Fizz wins
+        assert_eq!(
+            analyze(&[6], &fizz_buzz_rules())[0].label,
+            Label::Rules("Fizz".to_string())
+        );
+        assert_eq!(labels(&[0], &fizz_buzz_rules()), ["FizzBuzz"]);
+    }
+
+    #[test]
+    fn negative_numbers_and_divisors() {
+        assert_eq!(
+            labels(&[-15, -9, -4, -7], &fizz_buzz_rules()),
+            ["FizzBuzz", "Fizz", "Even", "Odd"]
+        );
+        let rules = [
+            Rule::new(-2, "Pair").unwrap(),
+            Rule::new(-1, "Any").unwrap(),
+        ];
+        assert_eq!(labels(&[6, i64::MIN], &rules), ["PairAny", "PairAny"]);
+    }
+
+    #[test]
+    fn bad_rules_are_rejected() {
+        assert_eq!(
+            "0:Never".parse::<Rule>(),
+            Err("rule 'Never' cannot divide by 0".to_string())
+        );
+        assert!(Rule::new(0, "Never").is_err());
+        assert!("7".parse::<Rule>().is_err());
+        assert!("seven:Boom".parse::<Rule>().is_err());
+        assert!("7: ".parse::<Rule>().is_err());
+        let rule: Rule = " 7 : Boom ".parse().unwrap();
+        assert_eq!((rule.divisor(), rule.label()), (7, "Boom"));
+    }
+
+    #[test]
+    fn stats_of_some_numbers() {
+        let stats = Stats::of(&[12, 3, 5, 7, 15, 20, 22, 9, 10, 30, 5]).unwrap();
+        assert_eq!(
+            (stats.count, stats.sum, stats.min, stats.max),
+            (11, 138, 3, 30)
+        );
+        assert!((stats.mean - 138.0 / 11.0).abs() < 1e-12);
+        assert_eq!((stats.median, stats.mode), (10.0, 5));
+
+        let stats = Stats::of(&[-4, 1, -4, 1, 9, -10]).unwrap();
+        // Even count: halfway between the middle two; tied modes: smallest
+        assert_eq!((stats.median, stats.mode), (-1.5, -4));
+        assert_eq!((stats.min, stats.max, stats.sum), (-10, 9, -7));
+
+        let stats = Stats::of(&[i64::MAX, i64::MAX]).unwrap();
+        assert_eq!(stats.sum, 2 * i128::from(i64::MAX));
+        assert_eq!(Stats::of(&[42]).unwrap().median, 42.0);
+    }
+
+    #[test]
+    fn stepped_sums() {
+        assert_eq!(sum_with_step(0, 100, 1), 5050);
+        assert_eq!(sum_with_step(0, 10, 2), 30);
+        assert_eq!(sum_with_step(5, 15, 3), 5 + 8 + 11 + 14);
+        assert_eq!(sum_with_step(-3, 3, 1), 0);
+        assert_eq!(sum_with_step(10, 0, 1), 0);
+    }
+
+    #[test]
+    fn empty_input() {
+        assert!(analyze(&[], &fizz_buzz_rules()).is_empty());
+        assert_eq!(Stats::of(&[]), None);
+    }
+}
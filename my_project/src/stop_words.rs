@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+
+/// The bundled English list, in `StopWords::parse` form
+const ENGLISH: &str = include_str!("stop_words_en.txt");
+
+/// Words to leave out when counting, matched ignoring case.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StopWords(HashSet<String>);
+
+impl StopWords {
+    /// No stop words: every word counts
+    pub fn none() -> Self {
+        StopWords::default()
+    }
+
+    /// The bundled list of common English words
+    pub fn english() -> Self {
+        StopWords::parse(ENGLISH)
+    }
+
+    /// The words in `list`, one per line; blank lines and lines starting
+    /// with `#` are skipped
+    pub fn parse(list: &str) -> Self {
+        let words = list
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        StopWords::none().with(words)
+    }
+
+    /// These and `words` too
+    pub fn with<S: AsRef<str>>(mut self, words: impl IntoIterator<Item = S>) -> Self {
+        self.0
+            .extend(words.into_iter().map(|w| w.as_ref().to_lowercase()));
+        self
+    }
+
+    /// These but not `words`, so they count after all
+    pub fn without<S: AsRef<str>>(mut self, words: impl IntoIterator<Item = S>) -> Self {
+        for word in words {
+            self.0.remove(&word.as_ref().to_lowercase());
+        }
+        self
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        if word.chars().any(char::is_uppercase) {
+            self.0.contains(&word.to_lowercase())
+        } else {
+            self.0.contains(word)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn english_with_additions_and_overrides() {
+        let stop = StopWords::english().with(["Alice"]).without(["not"]);
+        assert!(stop.contains("the") && stop.contains("The") && stop.contains("ALICE"));
+        assert!(!stop.contains("not") && !stop.contains("rabbit"));
+        assert_eq!(stop.len(), StopWords::english().len());
+        assert!(StopWords::english().len() > 100);
+        assert!(StopWords::none().is_empty());
+    }
+
+    #[test]
+    fn parses_a_list() {
+        let stop = StopWords::parse("# names\nAlice\n\n  Hatter  \n");
+        assert_eq!(stop, StopWords::none().with(["alice", "hatter"]));
+    }
+}
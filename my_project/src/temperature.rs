@@ -0,0 +1,340 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+const FREEZING_POINT_FAHRENHEIT: f64 = 32.0;
+/// 0°C in kelvin
+const FREEZING_POINT_KELVIN: f64 = 273.15;
+
+pub fn fahrenheit_to_celsius(f: f64) -> f64 {
+    Unit::Fahrenheit.convert(f, Unit::Celsius)
+}
+
+pub fn celsius_to_fahrenheit(c: f64) -> f64 {
+    Unit::Celsius.convert(c, Unit::Fahrenheit)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl Unit {
+    /// The coldest there is, in this unit
+    pub fn absolute_zero(self) -> f64 {
+        match self {
+            Unit::Celsius => -FREEZING_POINT_KELVIN,
+            Unit::Fahrenheit => -459.67,
+            Unit::Kelvin => 0.0,
+        }
+    }
+
+    /// "°C", "°F" or "K"
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Unit::Celsius => "°C",
+            Unit::Fahrenheit => "°F",
+            Unit::Kelvin => "K",
+        }
+    }
+
+    /// `value` in this unit as a value in `to`, without any checks
+    fn convert(self, value: f64, to: Unit) -> f64 {
+        let celsius = match self {
+            Unit::Celsius => value,
+            Unit::Fahrenheit => (value - FREEZING_POINT_FAHRENHEIT) * 5.0 / 9.0,
+            Unit::Kelvin => value - FREEZING_POINT_KELVIN,
+        };
+        match to {
+            Unit::Celsius => celsius,
+            Unit::Fahrenheit => celsius * 9.0 / 5.0 + FREEZING_POINT_FAHRENHEIT,
+            Unit::Kelvin => celsius + FREEZING_POINT_KELVIN,
+        }
+    }
+}
+
+/// "c", "celsius" or "°C", and likewise for the others, ignoring case
+impl FromStr for Unit {
+    type Err = TemperatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "c" | "°c" | "celsius" => Ok(Unit::Celsius),
+            "f" | "°f" | "fahrenheit" => Ok(Unit::Fahrenheit),
+            "k" | "kelvin" => Ok(Unit::Kelvin),
+            _ => Err(TemperatureError::UnknownUnit(s.to_string())),
+        }
+    }
+}
+
+/// Why a value isn't a temperature.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemperatureError {
+    BelowAbsoluteZero { value: f64, unit: Unit },
+    NotANumber,
+    UnknownUnit(String),
+}
+
+impl fmt::Display for TemperatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemperatureError::BelowAbsoluteZero { value, unit } => write!(
+                f,
+                "{value}{} is below absolute zero ({}{})",
+                unit.symbol(),
+                unit.absolute_zero(),
+                unit.symbol()
+            ),
+            TemperatureError::NotANumber => write!(f, "temperature is not a number"),
+            TemperatureError::UnknownUnit(unit) => {
+                write!(
+                    f,
+                    "unknown unit '{unit}' (valid: c, f, k, or celsius, fahrenheit, kelvin)"
+                )
+            }
+        }
+    }
+}
+
+impl Error for TemperatureError {}
+
+/// A temperature in some unit, never below absolute zero.
+///
+/// Temperatures compare by how hot they are whatever their units, so
+/// -40°C equals -40°F.
+#[derive(Debug, Clone, Copy)]
+pub struct Temperature {
+    value: f64,
+    unit: Unit,
+}
+
+impl Temperature {
+    pub fn new(value: f64, unit: Unit) -> Result<Self, TemperatureError> {
+        if value.is_nan() {
+            return Err(TemperatureError::NotANumber);
+        }
+        if value < unit.absolute_zero() {
+            return Err(TemperatureError::BelowAbsoluteZero { value, unit });
+        }
+        Ok(Temperature { value, unit })
+    }
+
+    pub fn celsius(value: f64) -> Result<Self, TemperatureError> {
+        Temperature::new(value, Unit::Celsius)
+    }
+
+    pub fn fahrenheit(value: f64) -> Result<Self, TemperatureError> {
+        Temperature::new(value, Unit::Fahrenheit)
+    }
+
+    pub fn kelvin(value: f64) -> Result<Self, TemperatureError> {
+        Temperature::new(value, Unit::Kelvin)
+    }
+
+    pub fn value(self) -> f64 {
+        self.value
+    }
+
+    pub fn unit(self) -> Unit {
+        self.unit
+    }
+
+    /// The same temperature in `unit`
+    pub fn to(self, unit: Unit) -> Temperature {
+        // Rounding can land a hair below absolute zero
+        let value = self
+            .unit
+            .convert(self.value, unit)
+            .max(unit.absolute_zero());
+        Temperature { value, unit }
+    }
+}
+
+impl TryFrom<(f64, Unit)> for Temperature {
+    type Error = TemperatureError;
+
+    fn try_from((value, unit): (f64, Unit)) -> Result<Self, Self::Error> {
+        Temperature::new(value, unit)
+    }
+}
+
+impl From<Temperature> for (f64, Unit) {
+    fn from(temperature: Temperature) -> Self {
+        (temperature.value, temperature.unit)
+    }
+}
+
+/// Equal to within rounding once in the same unit
+impl PartialEq for Temperature {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Temperature {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let (a, b) = (self.to(Unit::Kelvin).value, other.to(Unit::Kelvin).value);
+        if (a - b).abs() <= 1e-9 * a.abs().max(1.0) {
+            return Some(Ordering::Equal);
+        }
+        a.partial_cmp(&b)
+    }
+}
+
+/// `98.6°F`, with as many decimals as the formatter asks for
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)?;
+        f.write_str(self.unit.symbol())
+    }
+}
+
+/// Each of `values`, in `from`, converted to `to`. Fails on the first
+/// value that's below absolute zero.
+pub fn convert_series(values: &[f64], from: Unit, to: Unit) -> Result<Vec<f64>, TemperatureError> {
+    values
+        .iter()
+        .map(|&value| Ok(Temperature::new(value, from)?.to(to).value()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNITS: [Unit; 3] = [Unit::Celsius, Unit::Fahrenheit, Unit::Kelvin];
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{actual} is not {expected}"
+        );
+    }
+
+    /// The same temperature in °C, °F and K
+    const FIXED_POINTS: [[f64; 3]; 5] = [
+        [-273.15, -459.67, 0.0],
+        [-40.0, -40.0, 233.15],
+        [0.0, 32.0, 273.15],
+        [37.0, 98.6, 310.15],
+        [100.0, 212.0, 373.15],
+    ];
+
+    #[test]
+    fn fixed_points_convert_between_every_pair_of_units() {
+        for point in FIXED_POINTS {
+            for (from, &value) in UNITS.iter().zip(&point) {
+                let temperature = Temperature::new(value, *from).unwrap();
+                for (to, &expected) in UNITS.iter().zip(&point) {
+                    let converted = temperature.to(*to);
+                    assert_eq!(converted.unit(), *to);
+                    assert_close(converted.value(), expected);
+                    assert_eq!(converted, temperature);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_old_functions_still_work() {
+        assert_close(fahrenheit_to_celsius(212.0), 100.0);
+        assert_close(celsius_to_fahrenheit(-40.0), -40.0);
+        assert_close(fahrenheit_to_celsius(FREEZING_POINT_FAHRENHEIT), 0.0);
+        // No checks here, as before
+        assert_close(celsius_to_fahrenheit(-300.0), -508.0);
+    }
+
+    #[test]
+    fn comparison_accounts_for_units() {
+        let minus_forty_c = Temperature::celsius(-40.0).unwrap();
+        assert_eq!(minus_forty_c, Temperature::fahrenheit(-40.0).unwrap());
+        assert_ne!(
+            Temperature::celsius(0.0).unwrap(),
+            Temperature::fahrenheit(0.0).unwrap()
+        );
+        assert!(Temperature::celsius(0.0).unwrap() > Temperature::fahrenheit(0.0).unwrap());
+        assert!(Temperature::kelvin(300.0).unwrap() < Temperature::fahrenheit(100.0).unwrap());
+        assert!(Temperature::kelvin(0.0).unwrap() <= minus_forty_c);
+    }
+
+    #[test]
+    fn absolute_zero_is_the_lowest_in_every_unit() {
+        for unit in UNITS {
+            let zero = unit.absolute_zero();
+            let coldest = Temperature::new(zero, unit).unwrap();
+            assert_eq!(coldest, Temperature::kelvin(0.0).unwrap());
+            for to in UNITS {
+                assert!(coldest.to(to).value() >= to.absolute_zero());
+            }
+            let below = zero - 0.01;
+            assert_eq!(
+                Temperature::new(below, unit).unwrap_err(),
+                TemperatureError::BelowAbsoluteZero { value: below, unit }
+            );
+        }
+        assert_eq!(
+            Temperature::kelvin(f64::NAN).unwrap_err(),
+            TemperatureError::NotANumber
+        );
+        assert_eq!(
+            Temperature::fahrenheit(-500.0).unwrap_err().to_string(),
+            "-500°F is below absolute zero (-459.67°F)"
+        );
+    }
+
+    #[test]
+    fn units_parse_by_letter_name_or_symbol() {
+        for (names, unit) in [
+            (["c", "Celsius", "°C"], Unit::Celsius),
+            (["F", "fahrenheit", "°f"], Unit::Fahrenheit),
+            (["k", "KELVIN", " K "], Unit::Kelvin),
+        ] {
+            for name in names {
+                assert_eq!(name.parse(), Ok(unit), "{name}");
+            }
+        }
+        assert_eq!(
+            "°K".parse::<Unit>(),
+            Err(TemperatureError::UnknownUnit("°K".to_string()))
+        );
+        assert!("rankine".parse::<Unit>().is_err());
+    }
+
+    #[test]
+    fn conversions_between_tuples() {
+        let body = Temperature::try_from((98.6, Unit::Fahrenheit)).unwrap();
+        let (value, unit) = body.to(Unit::Celsius).into();
+        assert_close(value, 37.0);
+        assert_eq!(unit, Unit::Celsius);
+        assert!(Temperature::try_from((-1.0, Unit::Kelvin)).is_err());
+        assert_eq!(format!("{:.1}", body), "98.6°F");
+        assert_eq!(Temperature::kelvin(273.15).unwrap().to_string(), "273.15K");
+    }
+
+    #[test]
+    fn series_convert_together() {
+        let celsius =
+            convert_series(&[32.0, 212.0, -40.0], Unit::Fahrenheit, Unit::Celsius).unwrap();
+        for (actual, expected) in celsius.into_iter().zip([0.0, 100.0, -40.0]) {
+            assert_close(actual, expected);
+        }
+        assert!(
+            convert_series(&[], Unit::Kelvin, Unit::Celsius)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            convert_series(&[10.0, -5.0, 20.0], Unit::Kelvin, Unit::Celsius),
+            Err(TemperatureError::BelowAbsoluteZero {
+                value: -5.0,
+                unit: Unit::Kelvin
+            })
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+
+use crate::stop_words::StopWords;
+
+/// How to split text into words for counting. `Tokenizer::new()` splits on
+/// whitespace and nothing more, so "The" and "the," are different words;
+/// the builder methods turn on the rest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tokenizer {
+    lowercase: bool,
+    strip_punctuation: bool,
+    #[cfg(feature = "unicode")]
+    unicode_words: bool,
+    min_len: usize,
+    stop_words: StopWords,
+}
+
+impl Tokenizer {
+    pub fn new() -> Self {
+        Tokenizer::default()
+    }
+
+    /// Count "The" and "the" as the same word
+    pub fn lowercase(mut self, on: bool) -> Self {
+        self.lowercase = on;
+        self
+    }
+
+    /// Take punctuation off the start and end of each word, so "fox." and
+    /// "(fox" are "fox". Punctuation inside a word stays: "don't" and
+    /// "well-known" are one word each.
+    pub fn strip_punctuation(mut self, on: bool) -> Self {
+        self.strip_punctuation = on;
+        self
+    }
+
+    /// Find words by the Unicode word boundary rules instead of splitting
+    /// on whitespace, for scripts that don't put spaces between words.
+    /// Punctuation between words is dropped either way.
+    #[cfg(feature = "unicode")]
+    pub fn unicode_words(mut self, on: bool) -> Self {
+        self.unicode_words = on;
+        self
+    }
+
+    /// Skip words with fewer than `chars` characters, counted after any
+    /// punctuation is stripped
+    pub fn min_len(mut self, chars: usize) -> Self {
+        self.min_len = chars;
+        self
+    }
+
+    /// Leave out `stop_words`, matched ignoring case
+    pub fn stop_words(mut self, stop_words: StopWords) -> Self {
+        self.stop_words = stop_words;
+        self
+    }
+
+    /// The words of `text`, borrowed from it unless lowercasing changed them
+    pub fn tokens<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Cow<'a, str>> + 'a {
+        self.split(text)
+            .map(|word| {
+                if self.strip_punctuation {
+                    word.trim_matches(|c: char| !c.is_alphanumeric())
+                } else {
+                    word
+                }
+            })
+            .filter(|word| {
+                !word.is_empty()
+                    && word.chars().count() >= self.min_len
+                    && !self.stop_words.contains(word)
+            })
+            .map(|word| {
+                if self.lowercase && word.chars().any(char::is_uppercase) {
+                    Cow::Owned(word.to_lowercase())
+                } else {
+                    Cow::Borrowed(word)
+                }
+            })
+    }
+
+    #[cfg(feature = "unicode")]
+    fn split<'a>(&self, text: &'a str) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        if self.unicode_words {
+            Box::new(text.unicode_words())
+        } else {
+            Box::new(text.split_whitespace())
+        }
+    }
+
+    #[cfg(not(feature = "unicode"))]
+    fn split<'a>(&self, text: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        text.split_whitespace()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(tokenizer: &Tokenizer, text: &str) -> Vec<String> {
+        tokenizer.tokens(text).map(Cow::into_owned).collect()
+    }
+
+    #[test]
+    fn plain_splits_on_whitespace_only() {
+        assert_eq!(
+            tokens(&Tokenizer::new(), "The cat, the\tHat."),
+            ["The", "cat,", "the", "Hat."]
+        );
+    }
+
+    #[test]
+    fn punctuation_comes_off_the_ends_only() {
+        let tokenizer = Tokenizer::new().strip_punctuation(true);
+        assert_eq!(
+            tokens(&tokenizer, "\"Don't,\" she said -- (well-known) ... fox!"),
+            ["Don't", "she", "said", "well-known", "fox"]
+        );
+    }
+
+    #[test]
+    fn lowercase_and_min_len() {
+        let tokenizer = Tokenizer::new()
+            .lowercase(true)
+            .strip_punctuation(true)
+            .min_len(3);
+        assert_eq!(
+            tokens(&tokenizer, "A Cat and an OWL. Ёж, ÉTÉ!"),
+            ["cat", "and", "owl", "été"]
+        );
+        // Words already in lower case aren't copied
+        assert!(matches!(
+            tokenizer.tokens("owl").next(),
+            Some(Cow::Borrowed("owl"))
+        ));
+    }
+
+    #[test]
+    fn stop_words_are_left_out() {
+        let tokenizer = Tokenizer::new()
+            .strip_punctuation(true)
+            .stop_words(StopWords::english().with(["alice"]));
+        assert_eq!(
+            tokens(&tokenizer, "The Rabbit and ALICE, not the Queen."),
+            ["Rabbit", "Queen"]
+        );
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn unicode_words_split_without_spaces() {
+        let tokenizer = Tokenizer::new().unicode_words(true).lowercase(true);
+        assert_eq!(
+            tokens(&tokenizer, "Don't stop—the end.「東京」"),
+            ["don't", "stop", "the", "end", "東", "京"]
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+use serde::Serialize;
+
+use crate::tokenizer::Tokenizer;
+
+/// Words `compare_frequencies` leaves out unless they appear at least this
+/// often in one text or the other
+pub const MIN_COUNT: usize = 2;
+
+/// A word's counts in two texts, and how much more common it is in the
+/// first.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WordComparison {
+    pub word: String,
+    pub count_a: usize,
+    pub count_b: usize,
+    /// The log of how many times likelier the word is in the first text
+    /// than the second: above 0 for words more common in the first, below 0
+    /// for the second
+    pub score: f64,
+}
+
+/// How many times each whitespace-separated word appears in `text`. The
+/// keys borrow from `text`, so counting allocates nothing per word.
+pub fn word_counts(text: &str) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for word in text.split_whitespace() {
+        *counts.entry(word).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// How many times each word appears in `text`, split into words by
+/// `tokenizer`
+pub fn word_counts_with<'a>(
+    text: &'a str,
+    tokenizer: &'a Tokenizer,
+) -> HashMap<Cow<'a, str>, usize> {
+    let mut counts = HashMap::new();
+    for word in tokenizer.tokens(text) {
+        *counts.entry(word).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// `word_counts_with` for text read from `reader` a line at a time, so it
+/// needn't all fit in memory; only the counts are kept
+pub fn word_counts_from(
+    mut reader: impl BufRead,
+    tokenizer: &Tokenizer,
+) -> io::Result<HashMap<String, usize>> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        for word in tokenizer.tokens(&line) {
+            match counts.get_mut(word.as_ref()) {
+                Some(count) => *count += 1,
+                None => {
+                    counts.insert(word.into_owned(), 1);
+                }
+            }
+        }
+        line.clear();
+    }
+    Ok(counts)
+}
+
+/// Most common first; words with the same count in alphabetical order
+fn by_count_then_word<K: AsRef<str>>(a: &(K, usize), b: &(K, usize)) -> Ordering {
+    b.1.cmp(&a.1).then_with(|| a.0.as_ref().cmp(b.0.as_ref()))
+}
+
+/// The `n` biggest of `counts`, in the order `top_n_words` gives
+fn top_n<K: AsRef<str>>(counts: HashMap<K, usize>, n: usize) -> Vec<(String, usize)> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut counts: Vec<(K, usize)> = counts.into_iter().collect();
+    // Only the top n need sorting
+    if n < counts.len() {
+        counts.select_nth_unstable_by(n - 1, by_count_then_word);
+        counts.truncate(n);
+    }
+    counts.sort_unstable_by(by_count_then_word);
+    counts
+        .into_iter()
+        .map(|(word, count)| (word.as_ref().to_string(), count))
+        .collect()
+}
+
+/// The `n` most frequent words in `text` with their counts, most frequent
+/// first. Words with the same count are in alphabetical order, so the result
+/// doesn't depend on hashing. Fewer than `n` if `text` has fewer distinct
+/// words.
+pub fn top_n_words(text: &str, n: usize) -> Vec<(String, usize)> {
+    top_n(word_counts(text), n)
+}
+
+/// `top_n_words`, with `tokenizer` deciding what the words are
+pub fn top_n_words_with(text: &str, n: usize, tokenizer: &Tokenizer) -> Vec<(String, usize)> {
+    top_n(word_counts_with(text, tokenizer), n)
+}
+
+/// `top_n_words_with` for text read from `reader` (see `word_counts_from`)
+pub fn top_n_words_from(
+    reader: impl BufRead,
+    n: usize,
+    tokenizer: &Tokenizer,
+) -> io::Result<Vec<(String, usize)>> {
+    Ok(top_n(word_counts_from(reader, tokenizer)?, n))
+}
+
+/// The most frequent word in `text` and how often it appears. A tie goes to
+/// the word that comes first alphabetically. Text with no words gives an
+/// empty string and 0.
+pub fn most_frequent_word(text: &str) -> (String, usize) {
+    top_n_words(text, 1).pop().unwrap_or_default()
+}
+
+/// `most_frequent_word`, with `tokenizer` deciding what the words are
+pub fn most_frequent_word_with(text: &str, tokenizer: &Tokenizer) -> (String, usize) {
+    top_n_words_with(text, 1, tokenizer)
+        .pop()
+        .unwrap_or_default()
+}
+
+/// Which words are more common in `a` than `b` and the other way round,
+/// those most distinctive of `a` first and of `b` last. Words appearing
+/// fewer than `MIN_COUNT` times in both are left out.
+pub fn compare_frequencies(a: &str, b: &str, tokenizer: &Tokenizer) -> Vec<WordComparison> {
+    compare_frequencies_min(a, b, tokenizer, MIN_COUNT)
+}
+
+/// `compare_frequencies`, keeping words that appear at least `min_count`
+/// times in either text
+pub fn compare_frequencies_min(
+    a: &str,
+    b: &str,
+    tokenizer: &Tokenizer,
+    min_count: usize,
+) -> Vec<WordComparison> {
+    let (counts_a, counts_b) = (
+        word_counts_with(a, tokenizer),
+        word_counts_with(b, tokenizer),
+    );
+    let mut vocabulary: Vec<&Cow<str>> = counts_a.keys().chain(counts_b.keys()).collect();
+    vocabulary.sort_unstable();
+    vocabulary.dedup();
+    // Add one to every count, so a word missing from one text doesn't make
+    // the odds infinite
+    let total = |counts: &HashMap<Cow<str>, usize>| {
+        (counts.values().sum::<usize>() + vocabulary.len()) as f64
+    };
+    let (total_a, total_b) = (total(&counts_a), total(&counts_b));
+
+    let mut compared: Vec<WordComparison> = vocabulary
+        .iter()
+        .map(|&word| {
+            let count_a = counts_a.get(word).copied().unwrap_or(0);
+            let count_b = counts_b.get(word).copied().unwrap_or(0);
+            let score =
+                ((count_a + 1) as f64 / total_a).ln() - ((count_b + 1) as f64 / total_b).ln();
+            WordComparison {
+                word: word.to_string(),
+                count_a,
+                count_b,
+                score,
+            }
+        })
+        .filter(|c| c.count_a >= min_count || c.count_b >= min_count)
+        .collect();
+    compared.sort_by(|x, y| {
+        y.score
+            .total_cmp(&x.score)
+            .then_with(|| x.word.cmp(&y.word))
+    });
+    compared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stop_words::StopWords;
+
+    fn owned(pairs: &[(&str, usize)]) -> Vec<(String, usize)> {
+        pairs.iter().map(|&(w, c)| (w.to_string(), c)).collect()
+    }
+
+    #[test]
+    fn counts_words() {
+        let text = "the quick brown fox jumps over the lazy dog the quick brown fox";
+        assert_eq!(most_frequent_word(text), ("the".to_string(), 3));
+        assert_eq!(
+            top_n_words(text, 4),
+            owned(&[("the", 3), ("brown", 2), ("fox", 2), ("quick", 2)])
+        );
+        assert_eq!(word_counts(text)["lazy"], 1);
+    }
+
+    #[test]
+    fn ties_go_alphabetically() {
+        assert_eq!(
+            most_frequent_word("pear apple pear apple"),
+            ("apple".to_string(), 2)
+        );
+        assert_eq!(
+            top_n_words("c b a c b a d", 10),
+            owned(&[("a", 2), ("b", 2), ("c", 2), ("d", 1)])
+        );
+        assert_eq!(
+            top_n_words("c b a c b a d", 2),
+            owned(&[("a", 2), ("b", 2)])
+        );
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(most_frequent_word(""), (String::new(), 0));
+        assert_eq!(most_frequent_word(" \n\t "), (String::new(), 0));
+        assert!(top_n_words("", 5).is_empty());
+        assert!(top_n_words("some words", 0).is_empty());
+    }
+
+    fn english() -> Tokenizer {
+        Tokenizer::new().lowercase(true).strip_punctuation(true)
+    }
+
+    #[test]
+    fn punctuation_heavy_text() {
+        let text = "\"Well,\" said the Hatter, \"don't -- DON'T -- touch the tea!\" The tea... (the TEA?) Well!";
+        // the 4 (the, the, The, the), tea 3, well 2, don't 2, said, hatter and
+        // touch 1; "--" is all punctuation and drops out
+        assert_eq!(
+            top_n_words_with(text, 10, &english()),
+            owned(&[
+                ("the", 4),
+                ("tea", 3),
+                ("don't", 2),
+                ("well", 2),
+                ("hatter", 1),
+                ("said", 1),
+                ("touch", 1)
+            ])
+        );
+        assert_eq!(
+            top_n_words_with(text, 2, &english().min_len(4)),
+            owned(&[("don't", 2), ("well", 2)])
+        );
+        // Without a tokenizer every spelling is its own word
+        assert_eq!(most_frequent_word(text), ("--".to_string(), 2));
+    }
+
+    #[test]
+    fn non_latin_script() {
+        let text = "Война и мир. Мир — это не война; МИР!";
+        assert_eq!(
+            top_n_words_with(text, 3, &english()),
+            owned(&[("мир", 3), ("война", 2), ("и", 1)])
+        );
+        assert_eq!(
+            most_frequent_word_with(text, &english().min_len(4)),
+            ("война".to_string(), 2)
+        );
+    }
+
+    #[test]
+    fn mixed_case() {
+        let text = "Rust rust RUST rUsT Go go Zig";
+        assert_eq!(most_frequent_word(text), ("Go".to_string(), 1));
+        assert_eq!(
+            top_n_words_with(text, 5, &Tokenizer::new().lowercase(true)),
+            owned(&[("rust", 4), ("go", 2), ("zig", 1)])
+        );
+        assert_eq!(most_frequent_word_with("", &english()), (String::new(), 0));
+    }
+
+    #[test]
+    fn stop_words_let_other_words_through() {
+        let text = "The Queen and the King, and the Knave of Hearts; the Queen!";
+        assert_eq!(
+            most_frequent_word_with(text, &english()),
+            ("the".to_string(), 4)
+        );
+        let tokenizer = english().stop_words(StopWords::english());
+        assert_eq!(
+            top_n_words_with(text, 2, &tokenizer),
+            owned(&[("queen", 2), ("hearts", 1)])
+        );
+    }
+
+    #[test]
+    fn streams_a_large_reader() {
+        // About 5 MB over 100,000 lines
+        let text = "The cat sat on the mat; the dog didn't.\n".repeat(100_000);
+        let tokenizer = english().stop_words(StopWords::english());
+        let counts = word_counts_from(text.as_bytes(), &tokenizer).unwrap();
+        assert_eq!(counts.len(), 5);
+        assert_eq!(
+            top_n_words_from(io::BufReader::new(text.as_bytes()), 3, &tokenizer).unwrap(),
+            owned(&[("cat", 100_000), ("didn't", 100_000), ("dog", 100_000)])
+        );
+        // The same as counting it all at once
+        let at_once = word_counts_with(&text, &tokenizer);
+        assert!(
+            counts
+                .iter()
+                .all(|(word, &count)| at_once[word.as_str()] == count)
+        );
+    }
+
+    const CATS: &str = "The cat sat. The cat purred, and the cat chased a mouse. \
+                        A mouse is no match for a cat; the mouse hid.";
+    const DOGS: &str = "The dog barked. The dog fetched a ball, and the dog chased the ball \
+                        again. A dog and a cat met once.";
+
+    #[test]
+    fn distinctive_words_surface_on_the_right_side() {
+        let tokenizer = english().stop_words(StopWords::english());
+        let compared = compare_frequencies(CATS, DOGS, &tokenizer);
+        let words: Vec<&str> = compared.iter().map(|c| c.word.as_str()).collect();
+        // "chased" is once in each, too rare to show
+        assert_eq!(words, ["mouse", "cat", "ball", "dog"]);
+
+        let first = &compared[0];
+        assert_eq!((first.count_a, first.count_b), (3, 0));
+        assert!(first.score > 0.0);
+        let last = compared.last().unwrap();
+        assert_eq!(
+            (last.word.as_str(), last.count_a, last.count_b),
+            ("dog", 0, 4)
+        );
+        assert!(last.score < compared[2].score && compared[2].score < 0.0);
+    }
+
+    #[test]
+    fn min_count_filters_rare_words() {
+        let tokenizer = english().stop_words(StopWords::english());
+        let words = |min_count| -> Vec<String> {
+            compare_frequencies_min(CATS, DOGS, &tokenizer, min_count)
+                .into_iter()
+                .map(|c| c.word)
+                .collect()
+        };
+        assert_eq!(words(4), ["cat", "dog"]);
+        assert_eq!(words(5), Vec::<String>::new());
+        assert_eq!(words(1).len(), 12);
+        // Swapping the texts flips the order
+        let flipped = compare_frequencies(DOGS, CATS, &tokenizer);
+        assert_eq!(flipped[0].word, "dog");
+        assert_eq!(flipped.last().unwrap().word, "mouse");
+    }
+
+    #[test]
+    fn large_input_is_quick() {
+        // About 10 MB: a million words, 10,000 of them distinct
+        let text: String = (0..1_000_000)
+            .map(|i| format!("word{} ", i % 10_000))
+            .collect();
+        let start = std::time::Instant::now();
+        let top = top_n_words(&text, 3);
+        assert!(start.elapsed().as_secs() < 5, "took {:?}", start.elapsed());
+        assert_eq!(
+            top,
+            owned(&[("word0", 100), ("word1", 100), ("word10", 100)])
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::fs;
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use predicates::str::contains;
+use tempfile::TempDir;
+
+/// `my_project guess`, keeping its scores in `dir`
+fn guess(dir: &TempDir) -> Command {
+    let mut command = Command::cargo_bin("my_project").unwrap();
+    command
+        .args(["guess", "--scores-file"])
+        .arg(dir.path().join("scores.json"));
+    command
+}
+
+#[test]
+fn plays_from_stdin_until_q() {
+    let dir = TempDir::new().unwrap();
+    guess(&dir)
+        .args(["--difficulty", "easy"])
+        .write_stdin("eleven\nq\n")
+        .assert()
+        .success()
+        .stdout(contains("'eleven' isn't a number from 1 to 10.\n").and(contains("Bye!")));
+}
+
+#[test]
+fn the_bot_always_wins() {
+    let dir = TempDir::new().unwrap();
+    guess(&dir)
+        .args(["--bot", "--difficulty", "hard"])
+        .assert()
+        .success()
+        .stdout(contains("Probe 1: 500 is").and(contains(" is correct\n")));
+}
+
+#[test]
+fn a_broken_score_file_is_replaced_after_a_warning() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scores.json"), "{ broken").unwrap();
+    guess(&dir)
+        .arg("--scores")
+        .assert()
+        .success()
+        .stdout("easy:\n  no wins yet\nnormal:\n  no wins yet\nhard:\n  no wins yet\n")
+        .stderr(contains("is not a score file"));
+}
+
+#[test]
+fn bad_arguments_fail() {
+    let dir = TempDir::new().unwrap();
+    guess(&dir)
+        .args(["--difficulty", "impossible"])
+        .assert()
+        .failure()
+        .stderr(contains("invalid value 'impossible'"));
+    Command::cargo_bin("my_project")
+        .unwrap()
+        .arg("juggle")
+        .assert()
+        .failure();
+}
@@ -0,0 +1,50 @@
+use assert_cmd::Command;
+use predicates::str::contains;
+
+/// `my_project numbers`
+fn numbers() -> Command {
+    let mut command = Command::cargo_bin("my_project").unwrap();
+    command.arg("numbers");
+    command
+}
+
+#[test]
+fn labels_numbers_from_args_and_adds_stats() {
+    numbers()
+        .args(["15", "-4", "7", "14", "--rule", "7:Boom"])
+        .assert()
+        .success()
+        .stdout(
+            "15: FizzBuzz\n-4: Even\n7: Boom\n14: Boom\n\nCount:  4\nSum:    32\nMin:    -4\n\
+             Max:    15\nMean:   8.00\nMedian: 10.5\nMode:   -4\n",
+        );
+}
+
+#[test]
+fn reads_stdin_and_can_drop_the_default_rules() {
+    numbers()
+        .args(["--no-default-rules", "--rule", "2:Two"])
+        .write_stdin("3 4\n5\n")
+        .assert()
+        .success()
+        .stdout(contains("3: Odd\n4: Two\n5: Odd\n"));
+    numbers()
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout("No numbers\n");
+}
+
+#[test]
+fn bad_numbers_and_rules_fail() {
+    numbers()
+        .write_stdin("1 two 3")
+        .assert()
+        .failure()
+        .stderr("Error: 'two' is not a whole number\n");
+    numbers()
+        .args(["1", "--rule", "0:Never"])
+        .assert()
+        .failure()
+        .stderr(contains("cannot divide by 0"));
+}
@@ -0,0 +1,96 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+/// `my_project temps`
+fn temps() -> Command {
+    let mut command = Command::cargo_bin("my_project").unwrap();
+    command.arg("temps");
+    command
+}
+
+#[test]
+fn converts_one_value() {
+    temps()
+        .args(["convert", "98.6", "--from", "f", "--to", "celsius"])
+        .assert()
+        .success()
+        .stdout("98.6°F = 37.00°C\n");
+    temps()
+        .args(["convert", "-40", "--from", "°C", "--to", "F"])
+        .assert()
+        .success()
+        .stdout("-40°C = -40.00°F\n");
+    temps()
+        .args([
+            "convert", "0", "--from", "k", "--to", "c", "--format", "json",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("\"from\": \"kelvin\"").and(contains("\"converted\": -273.15")));
+}
+
+#[test]
+fn bad_units_and_values_fail() {
+    temps()
+        .args(["convert", "1", "--from", "rankine", "--to", "c"])
+        .assert()
+        .failure()
+        .stderr(contains("unknown unit 'rankine'"));
+    temps()
+        .args(["convert", "-500", "--from", "f", "--to", "c"])
+        .assert()
+        .failure()
+        .stderr("Error: -500°F is below absolute zero (-459.67°F)\n");
+}
+
+#[test]
+fn prints_an_aligned_table() {
+    temps()
+        .args([
+            "table", "--from", "0", "--to", "100", "--step", "25", "--unit", "c", "--target", "f",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            "    °C      °F\n  0.00   32.00\n 25.00   77.00\n 50.00  122.00\n 75.00  167.00\n\
+             100.00  212.00\n",
+        );
+    temps()
+        .args([
+            "table", "--from", "0", "--to", "1", "--step", "0.3", "--unit", "c", "--target", "k",
+        ])
+        .assert()
+        .success()
+        .stdout("  °C       K\n0.00  273.15\n0.30  273.45\n0.60  273.75\n0.90  274.05\n");
+    temps()
+        .args([
+            "table", "--from", "10", "--to", "0", "--unit", "c", "--target", "f",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("below --from"));
+}
+
+#[test]
+fn converts_lines_of_stdin_and_reports_bad_ones() {
+    temps()
+        .args(["convert", "--from", "c", "--to", "f"])
+        .write_stdin("0\n100\n\n-300\nwarm\n37\n")
+        .assert()
+        .failure()
+        .stdout("0°C = 32.00°F\n100°C = 212.00°F\n37°C = 98.60°F\n")
+        .stderr(
+            "line 4: -300°C is below absolute zero (-273.15°C)\nline 5: 'warm' is not a number\n\
+             Error: 2 of the values could not be converted\n",
+        );
+    temps()
+        .args(["--format", "json", "convert", "--from", "c", "--to", "k"])
+        .write_stdin("0\n")
+        .assert()
+        .success()
+        .stdout(
+            "[\n  {\n    \"value\": 0.0,\n    \"from\": \"celsius\",\n    \"converted\": 273.15,\n    \
+             \"to\": \"kelvin\"\n  }\n]\n",
+        );
+}
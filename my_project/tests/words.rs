@@ -0,0 +1,128 @@
+use std::fs;
+
+use assert_cmd::Command;
+use predicates::str::contains;
+use tempfile::TempDir;
+
+const TEXT: &str = "The Queen said to the King: \"Off with the Knave's head!\"\n\
+                    The King said nothing. The Queen said it again.\n";
+
+/// `my_project words`
+fn words() -> Command {
+    let mut command = Command::cargo_bin("my_project").unwrap();
+    command.arg("words");
+    command
+}
+
+#[test]
+fn reads_stdin_and_leaves_out_stop_words() {
+    words()
+        .args(["-n", "3"])
+        .write_stdin(TEXT)
+        .assert()
+        .success()
+        .stdout("WORD   COUNT\nsaid       3\nking       2\nqueen      2\n");
+    words()
+        .args(["-n", "1", "--no-stop-words"])
+        .write_stdin(TEXT)
+        .assert()
+        .success()
+        .stdout("WORD  COUNT\nthe       5\n");
+    words()
+        .args(["-n", "2", "--stop", "said", "--keep", "the"])
+        .write_stdin(TEXT)
+        .assert()
+        .success()
+        .stdout("WORD  COUNT\nthe       5\nking      2\n");
+}
+
+#[test]
+fn json_output_is_an_array_of_objects() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("text.txt");
+    fs::write(&path, TEXT).unwrap();
+    let output = words()
+        .arg(&path)
+        .args(["--top", "2", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([{"word": "said", "count": 3}, {"word": "king", "count": 2}])
+    );
+}
+
+#[test]
+fn stop_word_file_replaces_the_english_list() {
+    let dir = TempDir::new().unwrap();
+    let stop = dir.path().join("stop.txt");
+    fs::write(&stop, "# just these\nthe\nsaid\n").unwrap();
+    words()
+        .arg("--stop-words")
+        .arg(&stop)
+        .args(["-n", "2", "--min-len", "4"])
+        .write_stdin(TEXT)
+        .assert()
+        .success()
+        .stdout("WORD   COUNT\nking       2\nqueen      2\n");
+}
+
+const CATS: &str = "The cat sat. The cat purred, and the cat chased a mouse.\n\
+                    A mouse is no match for a cat; the mouse hid.\n";
+const DOGS: &str = "The dog barked. The dog fetched a ball, and the dog chased the ball\n\
+                    again. A dog and a cat met once.\n";
+
+#[test]
+fn compare_shows_the_words_typical_of_each_file() {
+    let dir = TempDir::new().unwrap();
+    let (cats, dogs) = (dir.path().join("cats.txt"), dir.path().join("dogs.txt"));
+    fs::write(&cats, CATS).unwrap();
+    fs::write(&dogs, DOGS).unwrap();
+    words()
+        .arg(&cats)
+        .arg("--compare")
+        .arg(&dogs)
+        .assert()
+        .success()
+        .stdout(format!(
+            "More common in {}:\n\
+             \x20 WORD    HERE  OTHER  SCORE\n\
+             \x20 mouse      3      0   1.34\n\
+             \x20 cat        4      1   0.87\n\
+             More common in {}:\n\
+             \x20 WORD    HERE  OTHER  SCORE\n\
+             \x20 dog        4      0   1.65\n\
+             \x20 ball       2      0   1.14\n",
+            cats.display(),
+            dogs.display()
+        ));
+
+    let output = words()
+        .arg("--compare")
+        .arg(&dogs)
+        .args(["--format", "json", "--top", "1"])
+        .write_stdin(CATS)
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["more_in_a"][0]["word"], "mouse");
+    assert_eq!(json["more_in_a"].as_array().unwrap().len(), 1);
+    assert_eq!(json["more_in_b"][0]["word"], "dog");
+    assert_eq!(json["more_in_b"][0]["count_b"], 4);
+}
+
+#[test]
+fn missing_file_is_an_error() {
+    words()
+        .arg("no-such-file.txt")
+        .assert()
+        .failure()
+        .stderr(contains("Error: cannot read no-such-file.txt"));
+    words()
+        .write_stdin("the and of")
+        .assert()
+        .success()
+        .stdout("No words found\n");
+}
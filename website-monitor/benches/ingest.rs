@@ -0,0 +1,57 @@
+//! URL list ingestion over a generated 1M-line file: one streaming pass,
+//! so time should grow linearly with the line count.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::{fmt::Write as _, fs, path::PathBuf};
+use website_monitor::UrlList;
+
+/// A URL file of `lines` (a multiple of 100) lines: one in ten repeats the
+/// line before it with the host uppercased and a fragment added, so only
+/// normalizing matches it, and one in a hundred isn't a URL at all.
+fn url_file(lines: usize) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("wm-ingest-{lines}-{}.txt", std::process::id()));
+    let mut text = String::with_capacity(lines * 48);
+    for i in 0..lines {
+        match i % 100 {
+            0 => text.push_str("not a url\n"),
+            n if n % 10 == 2 => {
+                let j = i - 1;
+                let _ = writeln!(text, "https://HOST{j}.example.com/path/{}?q={j}#top", j % 7);
+            }
+            _ => {
+                let _ = writeln!(text, "https://host{i}.example.com/path/{}?q={i}", i % 7);
+            }
+        }
+    }
+    fs::write(&path, text).expect("temp file writes");
+    path
+}
+
+fn ingest(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ingest");
+    group.sample_size(10);
+    for lines in [100_000, 1_000_000] {
+        let path = url_file(lines);
+        let mut list = UrlList::default();
+        list.read_path(&path).expect("file reads");
+        let counts = list.counts();
+        assert_eq!(
+            (counts.duplicates, counts.invalid),
+            (lines / 10, lines / 100),
+            "the file should exercise deduplication"
+        );
+        group.throughput(Throughput::Elements(lines as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(lines), &path, |b, path| {
+            b.iter(|| {
+                let mut list = UrlList::default();
+                list.read_path(path).expect("file reads");
+                list.counts()
+            })
+        });
+        let _ = fs::remove_file(&path);
+    }
+    group.finish();
+}
+
+criterion_group!(benches, ingest);
+criterion_main!(benches);
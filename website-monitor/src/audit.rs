@@ -0,0 +1,186 @@
+//! http -> https upgrade audit: which URLs still answer over plain http
+//! instead of redirecting to https.
+//!
+//! Each http URL is requested once without following redirects, and the
+//! answer is classified from its status and `Location` header.
+
+use std::time::Duration;
+
+use reqwest::{blocking::Client, header::LOCATION, redirect::Policy};
+use serde::Serialize;
+use url::Url;
+
+use crate::{CheckError, Target, send};
+
+/// URLs requested at the same time by [`https_audit`].
+const PARALLEL_CHECKS: usize = 16;
+
+/// What a URL's plain http variant does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpsUpgrade {
+    /// Redirects to the same host and path over https
+    RedirectsToHttps,
+    /// Redirects, but not to the https equivalent: another host or path, or
+    /// still plain http
+    RedirectsElsewhere,
+    /// Answers over plain http without redirecting
+    ServesPlainHttp,
+    /// The http request failed (nothing listening, timed out, ...)
+    HttpUnavailable,
+}
+
+/// One URL of an [`HttpsAudit`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HttpsAuditEntry {
+    /// As listed
+    pub url: String,
+    /// The plain http URL requested
+    pub http_url: String,
+    pub upgrade: HttpsUpgrade,
+    /// Status of the http answer, or why there wasn't one
+    pub status: Result<u16, CheckError>,
+    /// `Location` of the answer, as sent
+    pub location: Option<String>,
+}
+
+/// Per-URL results of [`https_audit`], in the order the URLs were listed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HttpsAudit {
+    pub entries: Vec<HttpsAuditEntry>,
+}
+
+impl HttpsAudit {
+    pub fn count(&self, upgrade: HttpsUpgrade) -> usize {
+        self.entries.iter().filter(|e| e.upgrade == upgrade).count()
+    }
+
+    /// URLs whose http variant answers without sending visitors to https.
+    pub fn insecure(&self) -> impl Iterator<Item = &HttpsAuditEntry> {
+        self.entries.iter().filter(|e| {
+            matches!(
+                e.upgrade,
+                HttpsUpgrade::ServesPlainHttp | HttpsUpgrade::RedirectsElsewhere
+            )
+        })
+    }
+}
+
+/// Whether `location`, taken relative to `from`, is `from` over https.
+/// Ports aren't compared, since http and https listen on different ones.
+fn is_https_equivalent(from: &Url, location: &str) -> bool {
+    from.join(location).is_ok_and(|to| {
+        to.scheme() == "https"
+            && to.host_str() == from.host_str()
+            && to.path() == from.path()
+            && to.query() == from.query()
+    })
+}
+
+fn classify(client: &Client, timeout: Duration, url: &str, http_url: Url) -> HttpsAuditEntry {
+    let (upgrade, status, location) = match send(client.get(http_url.clone()), timeout) {
+        Ok(resp) => {
+            let code = resp.status().as_u16();
+            let location = resp
+                .headers()
+                .get(LOCATION)
+                .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned());
+            let upgrade = match &location {
+                Some(to) if resp.status().is_redirection() => {
+                    if is_https_equivalent(&http_url, to) {
+                        HttpsUpgrade::RedirectsToHttps
+                    } else {
+                        HttpsUpgrade::RedirectsElsewhere
+                    }
+                }
+                _ => HttpsUpgrade::ServesPlainHttp,
+            };
+            (upgrade, Ok(code), location)
+        }
+        Err(e) => (HttpsUpgrade::HttpUnavailable, Err(e), None),
+    };
+    HttpsAuditEntry {
+        url: url.to_string(),
+        http_url: http_url.into(),
+        upgrade,
+        status,
+        location,
+    }
+}
+
+/// Request the http variant of every http URL in `urls` (and of every https
+/// URL too with `include_https`: same host, path and port) without
+/// following redirects, and classify the answers. URLs of other schemes,
+/// unparsable ones and repeats of an http URL already audited are skipped.
+pub fn https_audit<S: AsRef<str>>(
+    urls: &[S],
+    timeout: Duration,
+    include_https: bool,
+) -> HttpsAudit {
+    let mut seen = std::collections::HashSet::new();
+    let todo: Vec<(String, Url)> = urls
+        .iter()
+        .map(AsRef::as_ref)
+        .filter_map(|u| {
+            let mut http = Url::parse(u).ok()?;
+            // Credentials aren't sent in the clear, nor shown
+            let _ = http.set_username("");
+            let _ = http.set_password(None);
+            match http.scheme() {
+                "http" => {}
+                "https" if include_https => http.set_scheme("http").ok()?,
+                _ => return None,
+            }
+            http.set_fragment(None);
+            let shown = Target::new(u).display_url().into_owned();
+            seen.insert(http.clone()).then_some((shown, http))
+        })
+        .collect();
+
+    let client = Client::builder()
+        .timeout(timeout)
+        .redirect(Policy::none())
+        .build()
+        .expect("failed to build reqwest client");
+    let mut entries = Vec::with_capacity(todo.len());
+    for batch in todo.chunks(PARALLEL_CHECKS) {
+        std::thread::scope(|s| {
+            let handles: Vec<_> = batch
+                .iter()
+                .map(|(url, http)| {
+                    let client = &client;
+                    s.spawn(move || classify(client, timeout, url, http.clone()))
+                })
+                .collect();
+            entries.extend(
+                handles
+                    .into_iter()
+                    .map(|h| h.join().expect("https audit check panicked")),
+            );
+        });
+    }
+    HttpsAudit { entries }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn https_equivalent_needs_same_host_and_path() {
+        let from = Url::parse("http://example.com/shop?x=1").unwrap();
+        assert!(is_https_equivalent(&from, "https://example.com/shop?x=1"));
+        assert!(is_https_equivalent(
+            &from,
+            "https://example.com:8443/shop?x=1"
+        ));
+        assert!(!is_https_equivalent(&from, "https://example.com/"));
+        assert!(!is_https_equivalent(
+            &from,
+            "https://www.example.com/shop?x=1"
+        ));
+        assert!(!is_https_equivalent(&from, "http://example.com/shop?x=1"));
+        // Relative: stays on http
+        assert!(!is_https_equivalent(&from, "/shop?x=1"));
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    io::{self, Read},
+    time::Duration,
+};
+
+use crate::CheckError;
+
+const CHUNK_BYTES: usize = 16 * 1024;
+
+/// Read a response body in chunks, failing as soon as more than `limit`
+/// bytes have arrived. With `keep`, the bytes read are returned; the kept
+/// buffer never grows past `limit`.
+pub(crate) fn read_capped<R: Read>(
+    mut body: R,
+    limit: u64,
+    keep: bool,
+    timeout: Duration,
+) -> Result<Option<Vec<u8>>, CheckError> {
+    let mut chunk = vec![0u8; CHUNK_BYTES];
+    let mut kept = keep.then(Vec::new);
+    let mut total = 0u64;
+    loop {
+        let n = match body.read(&mut chunk) {
+            Ok(0) => return Ok(kept),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                return Err(CheckError::TimedOut { limit: timeout });
+            }
+            Err(e) => return Err(format!("body read error: {e}").into()),
+        };
+        total += n as u64;
+        if total > limit {
+            return Err(CheckError::BodyTooLarge {
+                limit,
+                observed_at_least: total,
+            });
+        }
+        if let Some(kept) = &mut kept {
+            kept.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn body_at_the_limit_is_kept_whole() {
+        let data = vec![b'x'; 40_000];
+        let kept = read_capped(&data[..], 40_000, true, TIMEOUT).unwrap();
+        assert_eq!(kept.unwrap().len(), 40_000);
+        assert_eq!(read_capped(&data[..], 40_000, false, TIMEOUT), Ok(None));
+    }
+
+    #[test]
+    fn endless_body_stops_just_past_the_limit() {
+        let err = read_capped(io::repeat(b'x'), 50_000, true, TIMEOUT).unwrap_err();
+        let CheckError::BodyTooLarge {
+            limit,
+            observed_at_least,
+        } = err
+        else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(limit, 50_000);
+        assert!(observed_at_least > limit);
+        assert!(observed_at_least <= limit + CHUNK_BYTES as u64);
+    }
+}
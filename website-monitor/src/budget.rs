@@ -0,0 +1,107 @@
+use serde::Serialize;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::serde_util;
+
+/// Cap on the requests sent, shared by every worker (and every pass) that
+/// holds a clone. Each check attempt, retries included, takes one request.
+#[derive(Debug, Clone)]
+pub struct RequestBudget {
+    limit: usize,
+    used: Arc<AtomicUsize>,
+}
+
+impl RequestBudget {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Take one request; false (taking nothing) once the limit is reached.
+    pub fn try_take(&self) -> bool {
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                (used < self.limit).then_some(used + 1)
+            })
+            .is_ok()
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.limit.saturating_sub(self.used())
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Start counting from zero again.
+    pub fn reset(&self) {
+        self.used.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Which budget of a continuous run ran out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "budget", rename_all = "snake_case")]
+pub enum BudgetExhausted {
+    /// All `limit` requests were sent
+    Requests { limit: usize },
+    /// The run has gone on for `limit`
+    Time {
+        #[serde(rename = "limit_ms", with = "serde_util::duration_ms")]
+        limit: Duration,
+    },
+}
+
+impl std::fmt::Display for BudgetExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetExhausted::Requests { limit } => {
+                write!(f, "request budget of {limit} exhausted")
+            }
+            BudgetExhausted::Time { limit } => {
+                write!(f, "run budget of {}s exhausted", limit.as_secs())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_takes_never_pass_the_limit() {
+        let budget = RequestBudget::new(250);
+        let taken: usize = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| s.spawn(|| (0..100).filter(|_| budget.try_take()).count()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+        assert_eq!(taken, 250);
+        assert_eq!(budget.used(), 250);
+        assert!(budget.is_exhausted());
+
+        budget.reset();
+        assert_eq!(budget.remaining(), 250);
+        assert!(budget.clone().try_take());
+        assert_eq!(budget.used(), 1);
+    }
+}
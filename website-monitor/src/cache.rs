@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Whether a response was served from a cache, as far as headers tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+    Unknown,
+}
+
+impl fmt::Display for CacheStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Miss => "miss",
+            CacheStatus::Unknown => "unknown",
+        })
+    }
+}
+
+fn classify_token(value: &str) -> Option<CacheStatus> {
+    let v = value.to_ascii_uppercase();
+    // X-Cache may list several hops ("MISS, HIT"); the client-facing hop is last.
+    let last = v.rsplit(',').next().unwrap_or(&v).trim();
+    if last.contains("HIT") || matches!(last, "STALE" | "REVALIDATED" | "UPDATING") {
+        Some(CacheStatus::Hit)
+    } else if last.contains("MISS") || matches!(last, "EXPIRED" | "BYPASS" | "DYNAMIC") {
+        Some(CacheStatus::Miss)
+    } else {
+        None
+    }
+}
+
+/// Derive the cache status from response headers (names case-insensitive).
+///
+/// `CF-Cache-Status` wins over `X-Cache`, which wins over `Age`; an `Age`
+/// above zero means the response sat in some cache. Anything else is Unknown.
+pub fn detect_cache_status(headers: &[(String, String)]) -> CacheStatus {
+    let get = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    };
+
+    if let Some(status) = get("cf-cache-status").and_then(classify_token) {
+        return status;
+    }
+    if let Some(status) = get("x-cache").and_then(classify_token) {
+        return status;
+    }
+    match get("age").and_then(|v| v.trim().parse::<u64>().ok()) {
+        Some(age) if age > 0 => CacheStatus::Hit,
+        _ => CacheStatus::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn header_table() {
+        use CacheStatus::*;
+        let cases: &[(&[(&str, &str)], CacheStatus)] = &[
+            (&[], Unknown),
+            (&[("X-Cache", "HIT")], Hit),
+            (&[("x-cache", "Hit from cloudfront")], Hit),
+            (&[("X-Cache", "Miss from cloudfront")], Miss),
+            (&[("X-Cache", "MISS, HIT")], Hit),
+            (&[("X-Cache", "HIT, MISS")], Miss),
+            (&[("CF-Cache-Status", "HIT")], Hit),
+            (&[("CF-Cache-Status", "DYNAMIC")], Miss),
+            (&[("CF-Cache-Status", "EXPIRED")], Miss),
+            (&[("cf-cache-status", "REVALIDATED")], Hit),
+            (&[("CF-Cache-Status", "MISS"), ("X-Cache", "HIT")], Miss),
+            (&[("Age", "120")], Hit),
+            (&[("Age", "0")], Unknown),
+            (&[("Age", "soon")], Unknown),
+            (&[("X-Cache", "MISS"), ("Age", "30")], Miss),
+            (&[("X-Cache", "something"), ("Age", "30")], Hit),
+            (&[("Content-Type", "text/html")], Unknown),
+        ];
+        for (pairs, expected) in cases {
+            assert_eq!(detect_cache_status(&headers(pairs)), *expected, "{pairs:?}");
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Canary checks: the same URLs checked against two origins (say the old
+//! and new servers of a migration) in one pass, with each pair of answers
+//! compared by status, latency and body.
+
+use serde::Serialize;
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+
+use crate::{CheckError, MonitorConfig, Target, WebsiteStatus, run_pass, serde_util};
+
+/// When two answers count as the same.
+#[derive(Debug, Clone)]
+pub struct CanaryOptions {
+    /// Largest difference in response time still counted as similar
+    pub latency_tolerance: Duration,
+}
+
+impl Default for CanaryOptions {
+    fn default() -> Self {
+        Self {
+            latency_tolerance: Duration::from_millis(500),
+        }
+    }
+}
+
+/// One URL to check against a baseline origin and a candidate origin.
+#[derive(Debug, Clone)]
+pub struct CanaryPair {
+    pub baseline: Target,
+    pub candidate: Target,
+}
+
+impl CanaryPair {
+    /// A port of 0 in an address means the URL's own (or default) port.
+    pub fn new(url: &str, baseline: SocketAddr, candidate: SocketAddr) -> Self {
+        let side = |addr, role| {
+            let target = Target::new(url).with_resolve(addr);
+            let id = format!("{} ({role})", target.display_url());
+            target.with_id(id)
+        };
+        Self {
+            baseline: side(baseline, "baseline"),
+            candidate: side(candidate, "candidate"),
+        }
+    }
+}
+
+/// How one origin answered.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CanarySide {
+    pub address: SocketAddr,
+    pub status: Result<u16, CheckError>,
+    #[serde(rename = "response_time_ms", with = "serde_util::duration_ms")]
+    pub response_time: Duration,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_hash: Option<String>,
+}
+
+/// A URL's answers from both origins, side by side. A side is `None` when
+/// the pass ended before it was checked.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CanaryComparison {
+    pub url: String,
+    pub baseline: Option<CanarySide>,
+    pub candidate: Option<CanarySide>,
+    /// Same status code, or failed the same way
+    pub status_match: bool,
+    /// Candidate minus baseline in milliseconds, when both answered
+    #[serde(rename = "latency_delta_ms", skip_serializing_if = "Option::is_none")]
+    pub latency_delta: Option<f64>,
+    /// Latencies within `CanaryOptions::latency_tolerance` of each other,
+    /// or not comparable
+    pub latency_match: bool,
+    /// Same body hash; `None` unless both answered with a body
+    pub content_match: Option<bool>,
+}
+
+impl CanaryComparison {
+    /// Pair up the results for one URL; either may be missing.
+    pub fn new(
+        url: &str,
+        baseline: Option<(SocketAddr, &WebsiteStatus)>,
+        candidate: Option<(SocketAddr, &WebsiteStatus)>,
+        options: &CanaryOptions,
+    ) -> Self {
+        let side = |(address, ws): (SocketAddr, &WebsiteStatus)| CanarySide {
+            address,
+            status: ws.status.clone(),
+            response_time: ws.response_time,
+            body_hash: ws.body_hash.clone(),
+        };
+        let (baseline, candidate) = (baseline.map(side), candidate.map(side));
+        let (mut status_match, mut latency_delta, mut content_match) = (false, None, None);
+        if let (Some(b), Some(c)) = (&baseline, &candidate) {
+            status_match = match (&b.status, &c.status) {
+                (Ok(b), Ok(c)) => b == c,
+                (Err(b), Err(c)) => b.kind() == c.kind(),
+                _ => false,
+            };
+            if b.status.is_ok() && c.status.is_ok() {
+                let micros = |d: Duration| d.as_micros() as f64;
+                latency_delta = Some((micros(c.response_time) - micros(b.response_time)) / 1000.0);
+            }
+            if let (Some(b), Some(c)) = (&b.body_hash, &c.body_hash) {
+                content_match = Some(b == c);
+            }
+        }
+        let tolerance = options.latency_tolerance.as_secs_f64() * 1000.0;
+        Self {
+            url: url.to_string(),
+            baseline,
+            candidate,
+            status_match,
+            latency_delta,
+            latency_match: latency_delta.is_none_or(|d| d.abs() <= tolerance),
+            content_match,
+        }
+    }
+
+    /// Whether the origins behaved differently in any way compared.
+    pub fn diverges(&self) -> bool {
+        !self.status_match || !self.latency_match || self.content_match == Some(false)
+    }
+}
+
+/// Check every pair in one pass (bodies hashed, so `config.hash_body` is
+/// turned on) and compare each URL's two answers, in the order given.
+pub fn canary(
+    pairs: Vec<CanaryPair>,
+    mut config: MonitorConfig,
+    options: &CanaryOptions,
+) -> Vec<CanaryComparison> {
+    config.hash_body = true;
+    let targets: Vec<Target> = pairs
+        .iter()
+        .flat_map(|p| [p.baseline.clone(), p.candidate.clone()])
+        .collect();
+    let report = run_pass(targets, config, None);
+    let results: HashMap<&str, &WebsiteStatus> =
+        report.results.iter().map(|ws| (ws.id(), ws)).collect();
+    let find = |target: &Target| {
+        let addr = target.resolve?;
+        results.get(&*target.id()).map(|&ws| (addr, ws))
+    };
+    pairs
+        .iter()
+        .map(|p| {
+            CanaryComparison::new(
+                &p.baseline.display_url(),
+                find(&p.baseline),
+                find(&p.candidate),
+                options,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(status: Result<u16, &str>, ms: u64, hash: Option<&str>) -> WebsiteStatus {
+        WebsiteStatus {
+            body_hash: hash.map(String::from),
+            ..WebsiteStatus::new(
+                "https://a.test",
+                status.map_err(CheckError::from),
+                Duration::from_millis(ms),
+            )
+        }
+    }
+
+    fn compare(a: Option<&WebsiteStatus>, b: Option<&WebsiteStatus>) -> CanaryComparison {
+        let (old, new) = ("10.0.0.1:0".parse().unwrap(), "10.0.0.2:0".parse().unwrap());
+        CanaryComparison::new(
+            "https://a.test",
+            a.map(|ws| (old, ws)),
+            b.map(|ws| (new, ws)),
+            &CanaryOptions::default(),
+        )
+    }
+
+    #[test]
+    fn identical_answers_match() {
+        let c = compare(
+            Some(&result(Ok(200), 100, Some("abc"))),
+            Some(&result(Ok(200), 400, Some("abc"))),
+        );
+        assert!(c.status_match && c.latency_match);
+        assert_eq!(c.content_match, Some(true));
+        assert_eq!(c.latency_delta, Some(300.0));
+        assert!(!c.diverges());
+    }
+
+    #[test]
+    fn each_difference_counts() {
+        let slow = compare(
+            Some(&result(Ok(200), 100, Some("abc"))),
+            Some(&result(Ok(200), 700, Some("abc"))),
+        );
+        assert!(!slow.latency_match && slow.diverges());
+
+        let content = compare(
+            Some(&result(Ok(200), 100, Some("abc"))),
+            Some(&result(Ok(200), 100, Some("def"))),
+        );
+        assert_eq!(content.content_match, Some(false));
+        assert!(content.status_match && content.diverges());
+
+        let status = compare(
+            Some(&result(Ok(200), 100, None)),
+            Some(&result(Ok(404), 100, None)),
+        );
+        assert!(!status.status_match && status.content_match.is_none());
+    }
+
+    #[test]
+    fn pairs_survive_a_side_failing_or_missing() {
+        let down = compare(
+            Some(&result(Ok(200), 100, Some("abc"))),
+            Some(&result(Err("connection refused"), 3, None)),
+        );
+        assert!(!down.status_match && down.diverges());
+        assert_eq!((down.latency_delta, down.content_match), (None, None));
+        assert!(down.latency_match);
+
+        let missing = compare(Some(&result(Ok(200), 100, None)), None);
+        assert!(missing.candidate.is_none() && missing.diverges());
+
+        // Failing the same way on both is behaving identically
+        let both = compare(
+            Some(&result(Err("connect error: a"), 5, None)),
+            Some(&result(Err("connect error: b"), 9, None)),
+        );
+        assert!(both.status_match && !both.diverges());
+    }
+}
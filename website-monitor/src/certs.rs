@@ -0,0 +1,483 @@
+//! TLS certificate inventory (enabled with the `certs` feature).
+//!
+//! Each host is handshaken once with rustls and the leaf certificate is read
+//! straight out of its DER encoding. Certificates are reported whether or not
+//! they chain to a trusted root: an inventory has to show the expired and
+//! self-signed ones too, so verification failures are recorded, not fatal.
+
+use std::{
+    collections::BTreeSet,
+    io,
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rustls::{
+    ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    client::{
+        WebPkiServerVerifier,
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    },
+    pki_types::{CertificateDer, ServerName, UnixTime},
+};
+use serde::Serialize;
+use url::Url;
+
+use crate::CheckError;
+
+/// Hosts handshaken at the same time by [`cert_inventory`].
+const PARALLEL_HANDSHAKES: usize = 16;
+
+/// What the leaf certificate of one host says about itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CertInfo {
+    pub subject: String,
+    pub issuer: String,
+    /// DNS names and IP addresses from the subjectAltName extension
+    pub sans: Vec<String>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    /// Why the chain didn't verify against the bundled roots, if it didn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub untrusted: Option<String>,
+}
+
+impl CertInfo {
+    /// Whole days until `not_after`; negative once expired.
+    pub fn days_remaining(&self, now: DateTime<Utc>) -> i64 {
+        (self.not_after - now).num_days()
+    }
+}
+
+/// One host of a [`cert_inventory`].
+#[derive(Debug, Clone, Serialize)]
+pub struct CertEntry {
+    pub host: String,
+    pub port: u16,
+    pub result: Result<CertInfo, CheckError>,
+}
+
+impl CertEntry {
+    /// Expired, or expiring within `days` of `now`. Hosts that couldn't be
+    /// inspected don't count.
+    pub fn expires_within(&self, days: i64, now: DateTime<Utc>) -> bool {
+        self.result
+            .as_ref()
+            .is_ok_and(|cert| cert.days_remaining(now) < days)
+    }
+}
+
+/// Verifier that lets every certificate through but remembers what the
+/// webpki verifier made of it.
+#[derive(Debug)]
+struct Recording {
+    inner: Arc<WebPkiServerVerifier>,
+    verdict: Mutex<Option<String>>,
+}
+
+impl ServerCertVerifier for Recording {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if let Err(e) = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        ) {
+            *self.verdict.lock().unwrap() = Some(e.to_string());
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+fn recording_verifier() -> Arc<Recording> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+        .build()
+        .expect("the bundled roots are valid");
+    Arc::new(Recording {
+        inner,
+        verdict: Mutex::new(None),
+    })
+}
+
+fn io_error(phase: &str, err: io::Error, timeout: Duration) -> CheckError {
+    if matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    ) {
+        CheckError::TimedOut { limit: timeout }
+    } else {
+        format!("tls error: {phase}: {err}").into()
+    }
+}
+
+/// Handshake with `host:port` (sending `host` as SNI) and describe the
+/// certificate it presents.
+pub fn inspect_certificate(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> Result<CertInfo, CheckError> {
+    let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+    let deadline = Instant::now() + timeout;
+    let addrs: Vec<SocketAddr> = (bare_host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("tls error: dns: {e}"))?
+        .collect();
+
+    let mut last_err = None;
+    let mut sock = None;
+    for addr in &addrs {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(CheckError::TimedOut { limit: timeout });
+        }
+        match TcpStream::connect_timeout(addr, left) {
+            Ok(s) => {
+                sock = Some(s);
+                break;
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+    let mut sock = match (sock, last_err) {
+        (Some(sock), _) => sock,
+        (None, Some(e)) => return Err(io_error("connect", e, timeout)),
+        (None, None) => return Err(format!("tls error: dns: no address for {host}").into()),
+    };
+    let left = deadline.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+        return Err(CheckError::TimedOut { limit: timeout });
+    }
+    sock.set_read_timeout(Some(left))
+        .and_then(|_| sock.set_write_timeout(Some(left)))
+        .map_err(|e| io_error("connect", e, timeout))?;
+
+    let verifier = recording_verifier();
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("ring supports the default protocol versions")
+        .dangerous()
+        .with_custom_certificate_verifier(verifier.clone())
+        .with_no_client_auth();
+    let name = ServerName::try_from(bare_host.to_string())
+        .map_err(|e| format!("tls error: invalid server name: {e}"))?;
+    let mut conn =
+        ClientConnection::new(Arc::new(config), name).map_err(|e| format!("tls error: {e}"))?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut sock)
+            .map_err(|e| io_error("handshake", e, timeout))?;
+    }
+
+    let leaf = conn
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .ok_or("tls error: server sent no certificate")?;
+    let mut info =
+        parse_certificate(leaf).ok_or("tls error: could not parse the server certificate")?;
+    info.untrusted = verifier.verdict.lock().unwrap().take();
+    conn.send_close_notify();
+    let _ = conn.complete_io(&mut sock);
+    Ok(info)
+}
+
+/// Inspect every distinct https host (and port) in `urls` once. Entries come
+/// back soonest expiry first, with hosts that couldn't be inspected last;
+/// non-https and unparsable URLs are skipped.
+pub fn cert_inventory<S: AsRef<str>>(urls: &[S], timeout: Duration) -> Vec<CertEntry> {
+    let hosts: BTreeSet<(String, u16)> = urls
+        .iter()
+        .filter_map(|u| Url::parse(u.as_ref()).ok())
+        .filter(|u| u.scheme() == "https")
+        .filter_map(|u| Some((u.host_str()?.to_string(), u.port_or_known_default()?)))
+        .collect();
+
+    let hosts: Vec<_> = hosts.into_iter().collect();
+    let mut entries = Vec::with_capacity(hosts.len());
+    for batch in hosts.chunks(PARALLEL_HANDSHAKES) {
+        std::thread::scope(|s| {
+            let handles: Vec<_> = batch
+                .iter()
+                .map(|(host, port)| {
+                    s.spawn(move || CertEntry {
+                        host: host.clone(),
+                        port: *port,
+                        result: inspect_certificate(host, *port, timeout),
+                    })
+                })
+                .collect();
+            entries.extend(
+                handles
+                    .into_iter()
+                    .map(|h| h.join().expect("certificate inspection panicked")),
+            );
+        });
+    }
+    entries.sort_by_key(|e| match &e.result {
+        Ok(cert) => (false, Some(cert.not_after)),
+        Err(_) => (true, None),
+    });
+    entries
+}
+
+// ---------------------------------------------------------------------------
+// Just enough DER to pull names, validity and SANs out of an X.509 certificate
+
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const OID: u8 = 0x06;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+/// `[0] EXPLICIT` version
+const VERSION: u8 = 0xa0;
+/// `[3] EXPLICIT` extensions
+const EXTENSIONS: u8 = 0xa3;
+const OCTET_STRING: u8 = 0x04;
+/// GeneralName `[2] IMPLICIT IA5String`
+const SAN_DNS: u8 = 0x82;
+/// GeneralName `[7] IMPLICIT OCTET STRING`
+const SAN_IP: u8 = 0x87;
+/// 2.5.29.17
+const OID_SAN: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// A cursor over a run of DER TLVs.
+struct Der<'a>(&'a [u8]);
+
+impl<'a> Der<'a> {
+    /// Next TLV as `(tag, contents)`.
+    fn next(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.0.split_first()?;
+        let (&first, mut rest) = rest.split_first()?;
+        let len = if first < 0x80 {
+            first as usize
+        } else {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > 4 || rest.len() < n {
+                return None;
+            }
+            let len = rest[..n]
+                .iter()
+                .fold(0usize, |acc, &b| acc << 8 | b as usize);
+            rest = &rest[n..];
+            len
+        };
+        if rest.len() < len {
+            return None;
+        }
+        let (value, rest) = rest.split_at(len);
+        self.0 = rest;
+        Some((tag, value))
+    }
+
+    /// Next TLV, which must carry `tag`.
+    fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+        self.next().filter(|(t, _)| *t == tag).map(|(_, v)| v)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+fn parse_certificate(der: &[u8]) -> Option<CertInfo> {
+    let cert = Der(der).expect(SEQUENCE)?;
+    let mut tbs = Der(Der(cert).expect(SEQUENCE)?);
+    if tbs.next()?.0 == VERSION {
+        tbs.next()?; // serial number
+    }
+    tbs.expect(SEQUENCE)?; // signature algorithm
+    let issuer = name(tbs.expect(SEQUENCE)?)?;
+    let mut validity = Der(tbs.expect(SEQUENCE)?);
+    let not_before = time(validity.next()?)?;
+    let not_after = time(validity.next()?)?;
+    let subject = name(tbs.expect(SEQUENCE)?)?;
+    tbs.expect(SEQUENCE)?; // subject public key info
+
+    let mut sans = Vec::new();
+    while let Some((tag, value)) = tbs.next() {
+        if tag == EXTENSIONS {
+            sans = subject_alt_names(Der(value).expect(SEQUENCE)?)?;
+        }
+    }
+    Some(CertInfo {
+        subject,
+        issuer,
+        sans,
+        not_before,
+        not_after,
+        untrusted: None,
+    })
+}
+
+/// Short label for the usual name attributes (all under 2.5.4).
+fn attribute_label(oid: &[u8]) -> Option<&'static str> {
+    match oid {
+        [0x55, 0x04, 0x03] => Some("CN"),
+        [0x55, 0x04, 0x06] => Some("C"),
+        [0x55, 0x04, 0x07] => Some("L"),
+        [0x55, 0x04, 0x08] => Some("ST"),
+        [0x55, 0x04, 0x0a] => Some("O"),
+        [0x55, 0x04, 0x0b] => Some("OU"),
+        _ => None,
+    }
+}
+
+/// `CN=..., O=...` from a Name, most specific attribute first. Attributes
+/// without a short label are left out.
+fn name(der: &[u8]) -> Option<String> {
+    let mut parts = Vec::new();
+    let mut rdns = Der(der);
+    while !rdns.is_empty() {
+        let mut set = Der(rdns.expect(SET)?);
+        while !set.is_empty() {
+            let mut atv = Der(set.expect(SEQUENCE)?);
+            let oid = atv.expect(OID)?;
+            let (_, value) = atv.next()?;
+            if let Some(label) = attribute_label(oid) {
+                parts.push(format!("{label}={}", String::from_utf8_lossy(value)));
+            }
+        }
+    }
+    parts.reverse();
+    Some(parts.join(", "))
+}
+
+fn time((tag, value): (u8, &[u8])) -> Option<DateTime<Utc>> {
+    let text = std::str::from_utf8(value).ok()?.strip_suffix('Z')?;
+    let (year, rest) = match tag {
+        // Two-digit years: 50-99 are 19xx (RFC 5280 section 4.1.2.5.1)
+        UTC_TIME => {
+            let yy: i32 = text.get(..2)?.parse().ok()?;
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, text.get(2..)?)
+        }
+        GENERALIZED_TIME => (text.get(..4)?.parse().ok()?, text.get(4..)?),
+        _ => return None,
+    };
+    if rest.len() != 10 || !rest.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |i: usize| rest[i..i + 2].parse::<u32>().ok();
+    let naive = NaiveDate::from_ymd_opt(year, field(0)?, field(2)?)?.and_hms_opt(
+        field(4)?,
+        field(6)?,
+        field(8)?,
+    )?;
+    Some(naive.and_utc())
+}
+
+fn subject_alt_names(extensions: &[u8]) -> Option<Vec<String>> {
+    let mut list = Der(extensions);
+    while !list.is_empty() {
+        let mut ext = Der(list.expect(SEQUENCE)?);
+        if ext.expect(OID)? != OID_SAN {
+            continue;
+        }
+        let (mut tag, mut value) = ext.next()?;
+        if tag != OCTET_STRING {
+            // The optional `critical` flag comes first
+            (tag, value) = ext.next()?;
+        }
+        if tag != OCTET_STRING {
+            return None;
+        }
+        let mut names = Der(Der(value).expect(SEQUENCE)?);
+        let mut sans = Vec::new();
+        while let Some((tag, value)) = names.next() {
+            match (tag, value.len()) {
+                (SAN_DNS, _) => sans.push(String::from_utf8_lossy(value).into_owned()),
+                (SAN_IP, 4) => {
+                    let octets: [u8; 4] = value.try_into().ok()?;
+                    sans.push(std::net::Ipv4Addr::from(octets).to_string());
+                }
+                (SAN_IP, 16) => {
+                    let octets: [u8; 16] = value.try_into().ok()?;
+                    sans.push(std::net::Ipv6Addr::from(octets).to_string());
+                }
+                _ => {}
+            }
+        }
+        return Some(sans);
+    }
+    Some(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_form_lengths() {
+        let mut body = vec![0x04, 0x81, 200];
+        body.extend([7u8; 200]);
+        body.extend([0x05, 0x00]);
+        let mut der = Der(&body);
+        assert_eq!(der.next().map(|(t, v)| (t, v.len())), Some((0x04, 200)));
+        assert_eq!(der.next(), Some((0x05, &[][..])));
+        assert!(der.is_empty());
+        // Truncated contents
+        assert_eq!(Der(&[0x04, 0x05, 1, 2]).next(), None);
+    }
+
+    #[test]
+    fn both_time_encodings() {
+        let utc = time((UTC_TIME, b"491231235959Z")).unwrap();
+        assert_eq!(utc.to_rfc3339(), "2049-12-31T23:59:59+00:00");
+        assert_eq!(time((UTC_TIME, b"700101000000Z")).unwrap().timestamp(), 0);
+        let general = time((GENERALIZED_TIME, b"20610301120000Z")).unwrap();
+        assert_eq!(general.to_rfc3339(), "2061-03-01T12:00:00+00:00");
+        assert_eq!(time((UTC_TIME, b"7001010000Z")), None);
+        assert_eq!(time((UTC_TIME, b"700101000000+0100")), None);
+    }
+
+    #[test]
+    fn names_list_the_most_specific_attribute_first() {
+        // SEQUENCE { SET { C=US } SET { O=Example } SET { CN=example.test } }
+        let mut der = Vec::new();
+        for (oid_last, value) in [(0x06u8, "US"), (0x0a, "Example"), (0x03, "example.test")] {
+            let mut atv = vec![OID, 3, 0x55, 0x04, oid_last, 0x0c, value.len() as u8];
+            atv.extend(value.as_bytes());
+            let mut seq = vec![SEQUENCE, atv.len() as u8];
+            seq.extend(atv);
+            der.push(SET);
+            der.push(seq.len() as u8);
+            der.extend(seq);
+        }
+        assert_eq!(name(&der).unwrap(), "CN=example.test, O=Example, C=US");
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    WebsiteStatus,
+    schema::{SchemaError, Versioned, from_versioned},
+};
+
+/// Completed results of a pass, appended to an NDJSON file as they come in
+/// so a crashed or cancelled pass can pick up where it stopped.
+///
+/// Lines are buffered and flushed every `flush_every` results or
+/// `flush_interval`, whichever comes first; a crash loses at most that much.
+#[derive(Debug)]
+pub struct Checkpoint {
+    writer: BufWriter<File>,
+    saved: HashMap<String, WebsiteStatus>,
+    unflushed: usize,
+    last_flush: Instant,
+    flush_every: usize,
+    flush_interval: Duration,
+}
+
+impl Checkpoint {
+    /// Open (or create) the checkpoint at `path`, loading what it holds.
+    ///
+    /// A partial or unparsable last line, as left by a crash mid-write, is
+    /// dropped; a bad line anywhere else, or a line written by a newer
+    /// version, is an `InvalidData` error.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut text = Vec::new();
+        file.read_to_end(&mut text)?;
+
+        let (saved, good_len) = parse(&text)?;
+        // Appends always land at the end, so trimming is all it takes
+        if good_len < text.len() {
+            file.set_len(good_len as u64)?;
+        }
+        Ok(Self {
+            writer: BufWriter::new(file),
+            saved,
+            unflushed: 0,
+            last_flush: Instant::now(),
+            flush_every: 100,
+            flush_interval: Duration::from_secs(5),
+        })
+    }
+
+    pub fn with_flush_every(mut self, results: usize, interval: Duration) -> Self {
+        self.flush_every = results.max(1);
+        self.flush_interval = interval;
+        self
+    }
+
+    /// The saved result for the target with `id` (see [`crate::Target::id`]),
+    /// if an earlier run completed it.
+    pub fn saved(&self, id: &str) -> Option<&WebsiteStatus> {
+        self.saved.get(id)
+    }
+
+    /// Number of targets already completed.
+    pub fn len(&self) -> usize {
+        self.saved.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.saved.is_empty()
+    }
+
+    /// Append a completed result, flushing if one is due.
+    pub fn record(&mut self, ws: &WebsiteStatus) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, &Versioned::new(ws))?;
+        self.writer.write_all(b"\n")?;
+        self.saved.insert(ws.id().to_string(), ws.clone());
+        self.unflushed += 1;
+        if self.unflushed >= self.flush_every || self.last_flush.elapsed() >= self.flush_interval {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.unflushed = 0;
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+}
+
+/// Saved results by target id, plus the length of the prefix of `text` made of
+/// good lines.
+fn parse(text: &[u8]) -> io::Result<(HashMap<String, WebsiteStatus>, usize)> {
+    let mut saved = HashMap::new();
+    let mut good_len = 0;
+    let mut lines = text.split_inclusive(|&b| b == b'\n').enumerate().peekable();
+    while let Some((i, line)) = lines.next() {
+        let is_last = lines.peek().is_none();
+        if !line.ends_with(b"\n") {
+            // Cut off mid-write; the trailing fragment is dropped
+            break;
+        }
+        if line.trim_ascii().is_empty() {
+            good_len += line.len();
+            continue;
+        }
+        let ws = serde_json::from_slice(line)
+            .map_err(SchemaError::Corrupt)
+            .and_then(from_versioned::<WebsiteStatus>);
+        match ws {
+            Ok(ws) => {
+                saved.insert(ws.id().to_string(), ws);
+                good_len += line.len();
+            }
+            Err(SchemaError::Corrupt(_)) if is_last => break,
+            Err(e) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("checkpoint line {}: {e}", i + 1),
+                ));
+            }
+        }
+    }
+    Ok((saved, good_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(url: &str) -> String {
+        let ws = WebsiteStatus::new(url, Ok(200), Duration::from_millis(10));
+        serde_json::to_string(&ws).unwrap() + "\n"
+    }
+
+    #[test]
+    fn trailing_damage_is_dropped() {
+        let good = line("https://a.test") + &line("https://b.test");
+        for tail in ["{\"url\": \"https://c.te", "not json\n", ""] {
+            let (saved, len) = parse((good.clone() + tail).as_bytes()).unwrap();
+            assert_eq!(saved.len(), 2);
+            assert_eq!(len, good.len());
+        }
+    }
+
+    #[test]
+    fn damage_in_the_middle_is_an_error() {
+        let text = line("https://a.test") + "garbage\n" + &line("https://b.test");
+        let err = parse(text.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("line 2"));
+    }
+}
@@ -0,0 +1,213 @@
+use serde::Serialize;
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{CheckError, WebsiteStatus, serde_util};
+
+/// When a slowdown counts as a regression, and how success is judged.
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    /// Minimum absolute increase in response time
+    pub min_increase: Duration,
+    /// Minimum relative increase, in percent of the old response time
+    pub min_increase_pct: f64,
+    /// Count 4xx responses as failures when deciding state changes
+    pub treat_4xx_as_failure: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            min_increase: Duration::from_millis(100),
+            min_increase_pct: 20.0,
+            treat_4xx_as_failure: true,
+        }
+    }
+}
+
+/// A URL whose success/failure state flipped between the two runs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StateChange {
+    pub url: String,
+    pub before: Result<u16, CheckError>,
+    pub after: Result<u16, CheckError>,
+    /// True for OK -> ERR, false for ERR -> OK
+    pub now_failing: bool,
+}
+
+/// A URL that succeeded both times but got slower beyond the thresholds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyRegression {
+    pub url: String,
+    #[serde(rename = "before_ms", with = "serde_util::duration_ms")]
+    pub before: Duration,
+    #[serde(rename = "after_ms", with = "serde_util::duration_ms")]
+    pub after: Duration,
+}
+
+impl LatencyRegression {
+    pub fn increase(&self) -> Duration {
+        self.after.saturating_sub(self.before)
+    }
+
+    /// Increase relative to the old response time, in percent.
+    pub fn increase_pct(&self) -> f64 {
+        pct_increase(self.before, self.after)
+    }
+}
+
+fn pct_increase(before: Duration, after: Duration) -> f64 {
+    let before = before.as_secs_f64();
+    if before == 0.0 {
+        return f64::INFINITY;
+    }
+    (after.as_secs_f64() - before) / before * 100.0
+}
+
+/// Differences between two sets of results, each list sorted by URL.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ResultDiff {
+    pub changed: Vec<StateChange>,
+    pub regressions: Vec<LatencyRegression>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ResultDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+            && self.regressions.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+    }
+
+    /// Latency regressions or URLs that started failing.
+    pub fn has_regressions(&self) -> bool {
+        !self.regressions.is_empty() || self.changed.iter().any(|c| c.now_failing)
+    }
+}
+
+/// Compare an earlier set of results against a later one, matching them by
+/// target id (see [`WebsiteStatus::id`]), which is also what the diff
+/// reports as the URL; for targets checked one way only, it's the URL. If
+/// an id appears more than once in either set, its last result is used.
+pub fn compare(
+    before: &[WebsiteStatus],
+    after: &[WebsiteStatus],
+    options: &DiffOptions,
+) -> ResultDiff {
+    let index = |results: &[WebsiteStatus]| -> BTreeMap<String, WebsiteStatus> {
+        results
+            .iter()
+            .map(|ws| (ws.id().to_string(), ws.clone()))
+            .collect()
+    };
+    let before = index(before);
+    let after = index(after);
+    let mut diff = ResultDiff::default();
+
+    for (url, old) in &before {
+        let Some(new) = after.get(url) else {
+            diff.removed.push(url.clone());
+            continue;
+        };
+        let was_ok = old.is_success(options.treat_4xx_as_failure);
+        let is_ok = new.is_success(options.treat_4xx_as_failure);
+        if was_ok != is_ok {
+            diff.changed.push(StateChange {
+                url: url.clone(),
+                before: old.status.clone(),
+                after: new.status.clone(),
+                now_failing: was_ok,
+            });
+        } else if was_ok
+            && new.response_time.saturating_sub(old.response_time) > options.min_increase
+            && pct_increase(old.response_time, new.response_time) > options.min_increase_pct
+        {
+            diff.regressions.push(LatencyRegression {
+                url: url.clone(),
+                before: old.response_time,
+                after: new.response_time,
+            });
+        }
+    }
+    diff.added = after
+        .keys()
+        .filter(|url| !before.contains_key(*url))
+        .cloned()
+        .collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(url: &str, status: Result<u16, &str>, ms: u64) -> WebsiteStatus {
+        WebsiteStatus::new(
+            url,
+            status.map_err(CheckError::from),
+            Duration::from_millis(ms),
+        )
+    }
+
+    #[test]
+    fn finds_every_category() {
+        let before = vec![
+            result("https://a.test", Ok(200), 100),
+            result("https://b.test", Ok(200), 100),
+            result("https://c.test", Err("timeout: x"), 5000),
+            result("https://gone.test", Ok(200), 10),
+            result("https://steady.test", Ok(200), 100),
+        ];
+        let after = vec![
+            result("https://a.test", Ok(503), 100),
+            result("https://b.test", Ok(200), 400),
+            result("https://c.test", Ok(200), 50),
+            result("https://new.test", Ok(200), 10),
+            result("https://steady.test", Ok(200), 150),
+        ];
+        let diff = compare(&before, &after, &DiffOptions::default());
+
+        let flips: Vec<(&str, bool)> = diff
+            .changed
+            .iter()
+            .map(|c| (c.url.as_str(), c.now_failing))
+            .collect();
+        assert_eq!(
+            flips,
+            vec![("https://a.test", true), ("https://c.test", false)]
+        );
+        assert_eq!(diff.regressions.len(), 1);
+        assert_eq!(diff.regressions[0].url, "https://b.test");
+        assert_eq!(diff.regressions[0].increase_pct().round(), 300.0);
+        assert_eq!(diff.added, vec!["https://new.test"]);
+        assert_eq!(diff.removed, vec!["https://gone.test"]);
+        assert!(diff.has_regressions());
+    }
+
+    #[test]
+    fn both_thresholds_must_be_exceeded() {
+        let before = vec![
+            result("https://fast.test", Ok(200), 1),
+            result("https://slow.test", Ok(200), 10_000),
+        ];
+        let after = vec![
+            // +5000%, but only +50ms
+            result("https://fast.test", Ok(200), 51),
+            // +500ms, but only +5%
+            result("https://slow.test", Ok(200), 10_500),
+        ];
+        let diff = compare(&before, &after, &DiffOptions::default());
+        assert!(diff.is_empty());
+        assert!(!diff.has_regressions());
+    }
+
+    #[test]
+    fn recovery_alone_is_not_a_regression() {
+        let before = vec![result("https://a.test", Ok(500), 10)];
+        let after = vec![result("https://a.test", Ok(200), 10)];
+        let diff = compare(&before, &after, &DiffOptions::default());
+        assert_eq!(diff.changed.len(), 1);
+        assert!(!diff.has_regressions());
+    }
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+
+/// Content codings the monitor can ask for in `Accept-Encoding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+}
+
+impl Encoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// The `Accept-Encoding` value for `encodings`, `None` when empty.
+pub(crate) fn accept_encoding(encodings: &[Encoding]) -> Option<String> {
+    if encodings.is_empty() {
+        return None;
+    }
+    let names: Vec<&str> = encodings.iter().map(|e| e.as_str()).collect();
+    Some(names.join(", "))
+}
+
+/// Whether a `Content-Encoding` value means the body wasn't compressed.
+pub(crate) fn is_identity(encoding: Option<&str>) -> bool {
+    encoding.is_none_or(|e| e.trim().is_empty() || e.trim().eq_ignore_ascii_case("identity"))
+}
+
+/// How a response body came over the wire.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Compression {
+    /// `Content-Encoding` as sent (`None` = identity)
+    pub encoding: Option<String>,
+    /// Size on the wire: `Content-Length`, or the bytes read when absent
+    pub wire_bytes: Option<u64>,
+    /// Size after decoding, when the body was captured and could be decoded
+    pub decoded_bytes: Option<u64>,
+}
+
+impl Compression {
+    /// Decoded size over wire size, e.g. `4.0` for a body gzipped to a quarter.
+    pub fn ratio(&self) -> Option<f64> {
+        match (self.wire_bytes, self.decoded_bytes) {
+            (Some(wire), Some(decoded)) if wire > 0 => Some(decoded as f64 / wire as f64),
+            _ => None,
+        }
+    }
+}
+
+/// Decode a body sent with `encoding`, stopping one byte past `limit` so a
+/// small body can't unpack into an unbounded one; the caller checks the
+/// length. Gzip and deflate are decoded; other codings, such as brotli,
+/// come back as `None`, since no decoder is built in.
+pub(crate) fn decode(encoding: Option<&str>, body: &[u8], limit: u64) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let cap = limit.saturating_add(1);
+    match encoding.map(|e| e.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("") | Some("identity") => return Some(body.to_vec()),
+        Some("gzip") | Some("x-gzip") => flate2::read::GzDecoder::new(body)
+            .take(cap)
+            .read_to_end(&mut out),
+        Some("deflate") => flate2::read::ZlibDecoder::new(body)
+            .take(cap)
+            .read_to_end(&mut out),
+        Some(_) => return None,
+    }
+    .ok()?;
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Compression as Level, write::GzEncoder};
+    use std::io::Write;
+
+    #[test]
+    fn gzip_bodies_are_decoded() {
+        let text = "hello ".repeat(100);
+        let mut enc = GzEncoder::new(Vec::new(), Level::default());
+        enc.write_all(text.as_bytes()).unwrap();
+        let gz = enc.finish().unwrap();
+        assert_eq!(
+            decode(Some("gzip"), &gz, u64::MAX),
+            Some(text.clone().into_bytes())
+        );
+        assert_eq!(decode(None, b"plain", u64::MAX), Some(b"plain".to_vec()));
+        assert_eq!(decode(Some("br"), b"\x0b", u64::MAX), None);
+        assert_eq!(decode(Some("gzip"), b"not gzip", u64::MAX), None);
+        // Cut off one byte past the limit
+        assert_eq!(
+            decode(Some("gzip"), &gz, 10),
+            Some(text.as_bytes()[..11].to_vec())
+        );
+    }
+
+    #[test]
+    fn identity_and_accept_header() {
+        assert!(is_identity(None));
+        assert!(is_identity(Some("Identity")));
+        assert!(!is_identity(Some("gzip")));
+        assert_eq!(accept_encoding(&[]), None);
+        assert_eq!(accept_encoding(&[Encoding::Gzip]).as_deref(), Some("gzip"));
+    }
+
+    #[test]
+    fn ratio_needs_both_sizes() {
+        let c = Compression {
+            encoding: Some("gzip".into()),
+            wire_bytes: Some(250),
+            decoded_bytes: Some(1000),
+        };
+        assert_eq!(c.ratio(), Some(4.0));
+        assert_eq!(
+            Compression {
+                decoded_bytes: None,
+                ..c
+            }
+            .ratio(),
+            None
+        );
+    }
+}
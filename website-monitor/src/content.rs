@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, VecDeque},
+    time::Duration,
+};
+
+use crate::{WebsiteStatus, serde_util};
+
+/// FNV-1a over the body, after removing every match of `ignore` (applied to
+/// the body as lossy UTF-8). 16 hex digits; stable across runs and builds.
+pub fn body_hash(body: &[u8], ignore: &[Regex]) -> String {
+    let text = if ignore.is_empty() {
+        Cow::Borrowed(body)
+    } else {
+        let mut text = String::from_utf8_lossy(body).into_owned();
+        for pattern in ignore {
+            if let Cow::Owned(replaced) = pattern.replace_all(&text, "") {
+                text = replaced;
+            }
+        }
+        Cow::Owned(text.into_bytes())
+    };
+    format!("{:016x}", fnv1a(&text))
+}
+
+/// 64-bit FNV-1a.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// When to call a URL's content flapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlapConfig {
+    /// How far back to look
+    pub window: Duration,
+    /// Times a body hash has to be seen within the window to count, so a
+    /// one-off variant doesn't trip it
+    pub min_occurrences: usize,
+}
+
+impl Default for FlapConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(600),
+            min_occurrences: 2,
+        }
+    }
+}
+
+/// More than one body was served for a URL within the window, each often
+/// enough to count: e.g. a load balancer alternating between two deploys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentFlapping {
+    /// Each body hash seen often enough, with how often, most seen first
+    pub versions: Vec<(String, usize)>,
+    #[serde(rename = "window_ms", with = "serde_util::duration_ms")]
+    pub window: Duration,
+}
+
+/// Body hashes seen per target over a sliding window.
+#[derive(Debug, Default)]
+pub struct FlapDetector {
+    config: FlapConfig,
+    seen: HashMap<String, VecDeque<(DateTime<Utc>, String)>>,
+}
+
+impl FlapDetector {
+    pub fn new(config: FlapConfig) -> Self {
+        Self {
+            config,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Take in a result; `Some` when its URL is flapping as of this result.
+    /// Results without a body hash (failed, or hashing off) are ignored.
+    pub fn observe(&mut self, ws: &WebsiteStatus) -> Option<ContentFlapping> {
+        let hash = ws.body_hash.as_ref()?;
+        let seen = self.seen.entry(ws.id().to_string()).or_default();
+        seen.push_back((ws.timestamp, hash.clone()));
+        let window =
+            chrono::Duration::from_std(self.config.window).unwrap_or(chrono::Duration::MAX);
+        while seen
+            .front()
+            .is_some_and(|(at, _)| ws.timestamp - *at > window)
+        {
+            seen.pop_front();
+        }
+
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for (_, hash) in seen.iter() {
+            *counts.entry(hash).or_default() += 1;
+        }
+        let mut versions: Vec<(String, usize)> = counts
+            .into_iter()
+            .filter(|&(_, n)| n >= self.config.min_occurrences.max(1))
+            .map(|(hash, n)| (hash.to_string(), n))
+            .collect();
+        if versions.len() < 2 {
+            return None;
+        }
+        versions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Some(ContentFlapping {
+            versions,
+            window: self.config.window,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignored_text_does_not_change_the_hash() {
+        let csrf = Regex::new(r#"name="csrf" value="[^"]*""#).unwrap();
+        let a = br#"<form><input name="csrf" value="abc123"></form>"#;
+        let b = br#"<form><input name="csrf" value="zzz999"></form>"#;
+        assert_ne!(body_hash(a, &[]), body_hash(b, &[]));
+        assert_eq!(
+            body_hash(a, std::slice::from_ref(&csrf)),
+            body_hash(b, std::slice::from_ref(&csrf))
+        );
+        // FNV-1a of nothing is the offset basis
+        assert_eq!(body_hash(b"", &[]), "cbf29ce484222325");
+    }
+}
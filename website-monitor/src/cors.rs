@@ -0,0 +1,173 @@
+use reqwest::{
+    Method,
+    header::{ACCESS_CONTROL_REQUEST_METHOD, HOST, ORIGIN},
+};
+
+use crate::{CheckError, Fetched, MonitorConfig, Target, headers, send};
+
+/// What a CORS preflight (`OPTIONS`) for a target must answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsExpect {
+    /// Sent as `Origin`
+    pub origin: String,
+    /// Sent as `Access-Control-Request-Method`
+    pub request_method: String,
+    /// Required `Access-Control-Allow-Origin`; `None` accepts the origin
+    /// itself or `*`
+    pub allow_origin: Option<String>,
+    /// Methods `Access-Control-Allow-Methods` must list; the request method
+    /// when empty. `*` in the response allows any.
+    pub allow_methods: Vec<String>,
+}
+
+impl CorsExpect {
+    pub fn new(origin: impl Into<String>, request_method: impl Into<String>) -> Self {
+        Self {
+            origin: origin.into(),
+            request_method: request_method.into(),
+            allow_origin: None,
+            allow_methods: Vec::new(),
+        }
+    }
+
+    pub fn with_allow_origin(mut self, allow_origin: impl Into<String>) -> Self {
+        self.allow_origin = Some(allow_origin.into());
+        self
+    }
+
+    pub fn with_allow_methods<S: Into<String>>(
+        mut self,
+        methods: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.allow_methods = methods.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Check the allow headers of a preflight response.
+    pub fn verify(
+        &self,
+        allow_origin: Option<&str>,
+        allow_methods: Option<&str>,
+    ) -> Result<(), CheckError> {
+        let origin_ok = match (allow_origin.map(str::trim), &self.allow_origin) {
+            (None, _) => false,
+            (Some(actual), Some(expected)) => actual == expected.trim(),
+            (Some(actual), None) => actual == "*" || actual == self.origin.trim(),
+        };
+        let wanted = if self.allow_methods.is_empty() {
+            std::slice::from_ref(&self.request_method)
+        } else {
+            self.allow_methods.as_slice()
+        };
+        let methods_ok = allow_methods.is_some_and(|listed| {
+            let listed: Vec<&str> = listed.split(',').map(str::trim).collect();
+            listed.contains(&"*")
+                || wanted
+                    .iter()
+                    .all(|m| listed.iter().any(|l| l.eq_ignore_ascii_case(m.trim())))
+        });
+        if origin_ok && methods_ok {
+            return Ok(());
+        }
+        Err(CheckError::CorsMisconfigured {
+            origin: self.origin.clone(),
+            allow_origin: allow_origin.map(str::to_string),
+            allow_methods: allow_methods.map(str::to_string),
+        })
+    }
+}
+
+/// Send the preflight for `target` and check its allow headers.
+/// `request_id` is a header name and value to send along.
+pub(crate) fn preflight(
+    client: &reqwest::blocking::Client,
+    target: &Target,
+    expect: &CorsExpect,
+    config: &MonitorConfig,
+    request_id: Option<(&str, &str)>,
+) -> Result<Fetched, CheckError> {
+    let mut req = client
+        .request(Method::OPTIONS, &target.url)
+        .header(ORIGIN, &expect.origin)
+        .header(ACCESS_CONTROL_REQUEST_METHOD, &expect.request_method);
+    if let Some(host) = &target.host_header {
+        req = req.header(HOST, host);
+    }
+    for (name, value) in target.request_headers() {
+        req = req.header(name, value.as_ref());
+    }
+    if let Some((name, id)) = request_id {
+        req = req.header(name, id);
+    }
+    let resp = send(req, config.request_timeout)?;
+
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+    };
+    expect.verify(
+        header("access-control-allow-origin").as_deref(),
+        header("access-control-allow-methods").as_deref(),
+    )?;
+
+    Ok(Fetched {
+        code: resp.status().as_u16(),
+        headers: config
+            .capture_headers
+            .then(|| headers::capture_map(resp.headers(), config.header_limits)),
+        compression: None,
+        phases: None,
+        body: None,
+        body_hash: None,
+        banner: None,
+        location: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> CorsExpect {
+        CorsExpect::new("https://app.test", "POST")
+    }
+
+    #[test]
+    fn origin_echo_or_wildcard_by_default() {
+        assert!(app().verify(Some("https://app.test"), Some("POST")).is_ok());
+        assert!(app().verify(Some("*"), Some("GET, post")).is_ok());
+        assert!(
+            app()
+                .verify(Some("https://other.test"), Some("POST"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn an_explicit_origin_must_match_exactly() {
+        let expect = app().with_allow_origin("https://app.test");
+        assert!(
+            expect
+                .verify(Some("https://app.test"), Some("POST"))
+                .is_ok()
+        );
+        assert_eq!(
+            expect.verify(Some("*"), Some("POST")),
+            Err(CheckError::CorsMisconfigured {
+                origin: "https://app.test".into(),
+                allow_origin: Some("*".into()),
+                allow_methods: Some("POST".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn every_expected_method_must_be_allowed() {
+        let expect = app().with_allow_methods(["GET", "PUT"]);
+        assert!(expect.verify(Some("*"), Some("GET, PUT, DELETE")).is_ok());
+        assert!(expect.verify(Some("*"), Some("*")).is_ok());
+        assert!(expect.verify(Some("*"), Some("GET")).is_err());
+        assert!(expect.verify(Some("*"), None).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Duration};
+
+use crate::serde_util;
+
+/// Why a check produced no usable status code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "Tagged", from = "Repr")]
+pub enum CheckError {
+    /// The check ran into the configured request timeout
+    TimedOut { limit: Duration },
+    /// The response body exceeded `max_body_bytes`; the download was cut
+    /// short (or skipped, when `Content-Length` already said so)
+    BodyTooLarge { limit: u64, observed_at_least: u64 },
+    /// The check answered, but slower than the target's
+    /// `max_response_time`
+    TooSlow { limit: Duration, actual: Duration },
+    /// The target requires compression but the response was identity-encoded
+    Uncompressed,
+    /// A negative check got an answer it didn't expect
+    UnexpectedlyReachable { status: u16 },
+    /// A CORS preflight didn't allow the expected origin or methods; the
+    /// allow headers are as the response had them (`None` when missing)
+    CorsMisconfigured {
+        origin: String,
+        allow_origin: Option<String>,
+        allow_methods: Option<String>,
+    },
+    /// Nothing took the connection: every address refused it (mail checks)
+    ConnectionRefused,
+    /// A mail server answered `to` (`greeting`, `EHLO`, `CAPABILITY`,
+    /// `STARTTLS`) with something other than a success, or without the
+    /// capabilities it was expected to announce
+    BadReply { to: String, reply: String },
+    /// A target expecting a redirect to `expected` (a URL prefix) got no
+    /// redirect, or one to somewhere else
+    WrongRedirect {
+        status: u16,
+        location: Option<String>,
+        expected: String,
+    },
+    /// DNS, connect, TLS and protocol failures, and anything else
+    Transport(String),
+}
+
+impl CheckError {
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, CheckError::TimedOut { .. })
+    }
+
+    /// Short name of the failure kind, as used in the JSON `kind` tag.
+    /// Transport errors are only a message, so they all land in `"other"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CheckError::TimedOut { .. } => "timed_out",
+            CheckError::BodyTooLarge { .. } => "body_too_large",
+            CheckError::TooSlow { .. } => "too_slow",
+            CheckError::Uncompressed => "uncompressed",
+            CheckError::UnexpectedlyReachable { .. } => "unexpectedly_reachable",
+            CheckError::CorsMisconfigured { .. } => "cors_misconfigured",
+            CheckError::ConnectionRefused => "connection_refused",
+            CheckError::BadReply { .. } => "bad_reply",
+            CheckError::WrongRedirect { .. } => "wrong_redirect",
+            CheckError::Transport(_) => "other",
+        }
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckError::TimedOut { limit } => write!(f, "timed out after {limit:?}"),
+            CheckError::BodyTooLarge {
+                limit,
+                observed_at_least,
+            } => write!(
+                f,
+                "body exceeds {limit} bytes (at least {observed_at_least} bytes)"
+            ),
+            CheckError::TooSlow { limit, actual } => {
+                write!(f, "too slow: took {actual:?}, limit {limit:?}")
+            }
+            CheckError::Uncompressed => f.write_str("response not compressed"),
+            CheckError::UnexpectedlyReachable { status } => {
+                write!(f, "expected to be unreachable, got status {status}")
+            }
+            CheckError::CorsMisconfigured {
+                origin,
+                allow_origin,
+                allow_methods,
+            } => write!(
+                f,
+                "CORS misconfigured for origin {origin}: allow-origin={}, allow-methods={}",
+                allow_origin.as_deref().unwrap_or("<missing>"),
+                allow_methods.as_deref().unwrap_or("<missing>")
+            ),
+            CheckError::ConnectionRefused => f.write_str("connection refused"),
+            CheckError::BadReply { to, reply } if to == "greeting" => {
+                write!(f, "bad greeting: {reply}")
+            }
+            CheckError::BadReply { to, reply } => write!(f, "unexpected reply to {to}: {reply}"),
+            CheckError::WrongRedirect {
+                status,
+                location: Some(location),
+                expected,
+            } => write!(
+                f,
+                "expected a redirect to {expected}, got {status} to {location}"
+            ),
+            CheckError::WrongRedirect {
+                status,
+                location: None,
+                expected,
+            } => write!(f, "expected a redirect to {expected}, got status {status}"),
+            CheckError::Transport(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+impl From<String> for CheckError {
+    fn from(msg: String) -> Self {
+        CheckError::Transport(msg)
+    }
+}
+
+impl From<&str> for CheckError {
+    fn from(msg: &str) -> Self {
+        CheckError::Transport(msg.to_string())
+    }
+}
+
+/// JSON shape: `{"kind": "timed_out", "limit_ms": 5000.0}`,
+/// `{"kind": "body_too_large", "limit": .., "observed_at_least": ..}`,
+/// `{"kind": "too_slow", "limit_ms": .., "actual_ms": ..}`,
+/// `{"kind": "uncompressed"}`, `{"kind": "unexpectedly_reachable", "status": ..}`,
+/// `{"kind": "cors_misconfigured", "origin": .., "allow_origin": ..,
+/// "allow_methods": ..}`, `{"kind": "connection_refused"}`,
+/// `{"kind": "bad_reply", "to": .., "reply": ..}`,
+/// `{"kind": "wrong_redirect", "status": .., "location": .., "expected": ..}` or
+/// `{"kind": "transport", "message": "..."}`.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Tagged {
+    TimedOut {
+        #[serde(rename = "limit_ms", with = "serde_util::duration_ms")]
+        limit: Duration,
+    },
+    BodyTooLarge {
+        limit: u64,
+        observed_at_least: u64,
+    },
+    TooSlow {
+        #[serde(rename = "limit_ms", with = "serde_util::duration_ms")]
+        limit: Duration,
+        #[serde(rename = "actual_ms", with = "serde_util::duration_ms")]
+        actual: Duration,
+    },
+    Uncompressed,
+    UnexpectedlyReachable {
+        status: u16,
+    },
+    CorsMisconfigured {
+        origin: String,
+        allow_origin: Option<String>,
+        allow_methods: Option<String>,
+    },
+    ConnectionRefused,
+    BadReply {
+        to: String,
+        reply: String,
+    },
+    WrongRedirect {
+        status: u16,
+        location: Option<String>,
+        expected: String,
+    },
+    Transport {
+        message: String,
+    },
+}
+
+/// Files written before errors were typed hold a bare message string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Repr {
+    Tagged(Tagged),
+    Legacy(String),
+}
+
+impl From<CheckError> for Tagged {
+    fn from(err: CheckError) -> Self {
+        match err {
+            CheckError::TimedOut { limit } => Tagged::TimedOut { limit },
+            CheckError::BodyTooLarge {
+                limit,
+                observed_at_least,
+            } => Tagged::BodyTooLarge {
+                limit,
+                observed_at_least,
+            },
+            CheckError::TooSlow { limit, actual } => Tagged::TooSlow { limit, actual },
+            CheckError::Uncompressed => Tagged::Uncompressed,
+            CheckError::UnexpectedlyReachable { status } => {
+                Tagged::UnexpectedlyReachable { status }
+            }
+            CheckError::CorsMisconfigured {
+                origin,
+                allow_origin,
+                allow_methods,
+            } => Tagged::CorsMisconfigured {
+                origin,
+                allow_origin,
+                allow_methods,
+            },
+            CheckError::ConnectionRefused => Tagged::ConnectionRefused,
+            CheckError::BadReply { to, reply } => Tagged::BadReply { to, reply },
+            CheckError::WrongRedirect {
+                status,
+                location,
+                expected,
+            } => Tagged::WrongRedirect {
+                status,
+                location,
+                expected,
+            },
+            CheckError::Transport(message) => Tagged::Transport { message },
+        }
+    }
+}
+
+impl From<Repr> for CheckError {
+    fn from(repr: Repr) -> Self {
+        match repr {
+            Repr::Tagged(Tagged::TimedOut { limit }) => CheckError::TimedOut { limit },
+            Repr::Tagged(Tagged::BodyTooLarge {
+                limit,
+                observed_at_least,
+            }) => CheckError::BodyTooLarge {
+                limit,
+                observed_at_least,
+            },
+            Repr::Tagged(Tagged::TooSlow { limit, actual }) => {
+                CheckError::TooSlow { limit, actual }
+            }
+            Repr::Tagged(Tagged::Uncompressed) => CheckError::Uncompressed,
+            Repr::Tagged(Tagged::UnexpectedlyReachable { status }) => {
+                CheckError::UnexpectedlyReachable { status }
+            }
+            Repr::Tagged(Tagged::CorsMisconfigured {
+                origin,
+                allow_origin,
+                allow_methods,
+            }) => CheckError::CorsMisconfigured {
+                origin,
+                allow_origin,
+                allow_methods,
+            },
+            Repr::Tagged(Tagged::ConnectionRefused) => CheckError::ConnectionRefused,
+            Repr::Tagged(Tagged::BadReply { to, reply }) => CheckError::BadReply { to, reply },
+            Repr::Tagged(Tagged::WrongRedirect {
+                status,
+                location,
+                expected,
+            }) => CheckError::WrongRedirect {
+                status,
+                location,
+                expected,
+            },
+            Repr::Tagged(Tagged::Transport { message }) | Repr::Legacy(message) => {
+                CheckError::Transport(message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let timeout = CheckError::TimedOut {
+            limit: Duration::from_secs(5),
+        };
+        let v = serde_json::to_value(&timeout).unwrap();
+        assert_eq!(
+            v,
+            serde_json::json!({"kind": "timed_out", "limit_ms": 5000.0})
+        );
+        assert_eq!(serde_json::from_value::<CheckError>(v).unwrap(), timeout);
+
+        let slow = CheckError::TooSlow {
+            limit: Duration::from_millis(800),
+            actual: Duration::from_millis(1250),
+        };
+        let v = serde_json::to_value(&slow).unwrap();
+        assert_eq!(
+            v,
+            serde_json::json!({"kind": "too_slow", "limit_ms": 800.0, "actual_ms": 1250.0})
+        );
+        assert_eq!(serde_json::from_value::<CheckError>(v).unwrap(), slow);
+
+        let bad = CheckError::BadReply {
+            to: "greeting".into(),
+            reply: "554 no service".into(),
+        };
+        let v = serde_json::to_value(&bad).unwrap();
+        assert_eq!(
+            v,
+            serde_json::json!({"kind": "bad_reply", "to": "greeting", "reply": "554 no service"})
+        );
+        assert_eq!(serde_json::from_value::<CheckError>(v).unwrap(), bad);
+
+        let transport = CheckError::from("request error: connection refused");
+        let v = serde_json::to_value(&transport).unwrap();
+        assert_eq!(v["kind"], "transport");
+        assert_eq!(serde_json::from_value::<CheckError>(v).unwrap(), transport);
+    }
+
+    #[test]
+    fn legacy_strings_still_load() {
+        let err: CheckError = serde_json::from_str("\"request error: dns error\"").unwrap();
+        assert_eq!(
+            err,
+            CheckError::Transport("request error: dns error".into())
+        );
+    }
+}
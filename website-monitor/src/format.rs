@@ -0,0 +1,560 @@
+//! Human-readable renderings of results: the line the CLI prints for each,
+//! a longer form with everything captured, and aligned tables.
+
+use std::fmt::{self, Write};
+
+use crate::{CheckError, HttpMethod, WebsiteStatus};
+
+/// How a status reads in text: the code, or the error with timeouts and
+/// slow answers called out in capitals.
+pub fn status_label(status: &Result<u16, CheckError>) -> String {
+    match status {
+        Ok(code) => code.to_string(),
+        Err(CheckError::TimedOut { limit }) => format!("TIMEOUT ({limit:?})"),
+        Err(CheckError::TooSlow { limit, actual }) => {
+            format!("TOO SLOW ({actual:?} > {limit:?})")
+        }
+        Err(err) => err.to_string(),
+    }
+}
+
+fn outcome(ws: &WebsiteStatus, treat_4xx_as_failure: bool) -> &'static str {
+    if ws.in_maintenance {
+        "MAINT"
+    } else if ws.is_success(treat_4xx_as_failure) {
+        "OK"
+    } else {
+        "ERR"
+    }
+}
+
+fn method_name(method: HttpMethod) -> &'static str {
+    match method {
+        HttpMethod::Get => "GET",
+        HttpMethod::Head => "HEAD",
+    }
+}
+
+/// A result as the one line the CLI prints for it, from
+/// [`WebsiteStatus::display`]. The alternate form (`{:#}`) adds the details
+/// the CLI shows with `--verbose`.
+#[derive(Debug, Clone, Copy)]
+pub struct ResultLine<'a> {
+    ws: &'a WebsiteStatus,
+    treat_4xx_as_failure: bool,
+}
+
+impl fmt::Display for ResultLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ws = self.ws;
+        let verbose = f.alternate();
+        write!(f, "[{}] {}", outcome(ws, self.treat_4xx_as_failure), ws.url)?;
+        if let Some(host) = &ws.host_header {
+            write!(f, " (Host: {host})")?;
+        }
+        if ws.id() != ws.url {
+            write!(f, " (id: {})", ws.id())?;
+        }
+        if verbose && let Some(cache) = ws.cache_status {
+            write!(f, " [cache={cache}]")?;
+        }
+        if verbose && ws.headers_truncated {
+            f.write_str(" [headers truncated]")?;
+        }
+        if verbose && let Some(body) = &ws.body {
+            write!(f, " [body={} bytes]", body.len())?;
+        }
+        if verbose && let Some(c) = &ws.compression {
+            let size = |n: Option<u64>| n.map_or("?".to_string(), |n| n.to_string());
+            write!(
+                f,
+                " [encoding={} wire={} decoded={}]",
+                c.encoding.as_deref().unwrap_or("identity"),
+                size(c.wire_bytes),
+                size(c.decoded_bytes)
+            )?;
+        }
+        if ws.expected_down {
+            f.write_str(" (expected down)")?;
+        }
+        if let Some(flapping) = &ws.content_flapping {
+            write!(
+                f,
+                " (content flapping between {} versions)",
+                flapping.versions.len()
+            )?;
+        }
+        if let Some(trend) = &ws.latency_trend {
+            write!(
+                f,
+                " (latency up {:.1}x: {} ms, was {} ms)",
+                trend.ratio,
+                trend.after.as_millis(),
+                trend.before.as_millis()
+            )?;
+        }
+        if verbose && let Some(hash) = &ws.body_hash {
+            write!(f, " [body_hash={hash}]")?;
+        }
+        if verbose && let Some(banner) = &ws.banner {
+            write!(f, " [banner={banner:?}]")?;
+        }
+        if verbose && !ws.request_ids.is_empty() {
+            write!(f, " [request_ids={}]", ws.request_ids.join(","))?;
+        }
+        if let Some(p) = &ws.phases {
+            let tls = p.tls.map_or("-".to_string(), |d| d.as_millis().to_string());
+            write!(
+                f,
+                " [dns={} connect={} tls={} ttfb={} transfer={} ms]",
+                p.dns.as_millis(),
+                p.connect.as_millis(),
+                tls,
+                p.first_byte.as_millis(),
+                p.transfer.as_millis()
+            )?;
+        }
+        match &ws.status {
+            Ok(code) => write!(f, " | status={code}")?,
+            Err(_) => write!(f, " | {}", status_label(&ws.status))?,
+        }
+        write!(
+            f,
+            " | {} ms | {}",
+            ws.response_time.as_millis(),
+            ws.timestamp
+        )
+    }
+}
+
+/// The line the CLI prints for the result, counting 4xx responses as
+/// failures; see [`WebsiteStatus::display`] for another policy.
+impl fmt::Display for WebsiteStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.display(true), f)
+    }
+}
+
+impl WebsiteStatus {
+    /// The result as one line, judged with the given 4xx policy.
+    pub fn display(&self, treat_4xx_as_failure: bool) -> ResultLine<'_> {
+        ResultLine {
+            ws: self,
+            treat_4xx_as_failure,
+        }
+    }
+
+    /// The result over several lines, one per field that was captured,
+    /// headers last.
+    pub fn format_verbose(&self) -> String {
+        let mut fields: Vec<(&str, String)> = vec![
+            ("status", status_label(&self.status)),
+            (
+                "response time",
+                format!("{} ms", self.response_time.as_millis()),
+            ),
+            ("checked at", self.timestamp.to_string()),
+        ];
+        if self.id() != self.url {
+            fields.push(("id", self.id().to_string()));
+        }
+        if let Some(host) = &self.host_header {
+            fields.push(("host header", host.clone()));
+        }
+        if !self.labels.is_empty() {
+            let labels: Vec<String> = self
+                .labels
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect();
+            fields.push(("labels", labels.join(", ")));
+        }
+        if let Some(method) = self.method_used {
+            fields.push(("method", method_name(method).to_string()));
+        }
+        if self.retries > 0 {
+            fields.push(("retries", self.retries.to_string()));
+        }
+        if !self.request_ids.is_empty() {
+            fields.push(("request ids", self.request_ids.join(", ")));
+        }
+        if let Some(location) = &self.location {
+            fields.push(("location", location.clone()));
+        }
+        if let Some(cache) = self.cache_status {
+            fields.push(("cache", cache.to_string()));
+        }
+        if let Some(c) = &self.compression {
+            let size = |n: Option<u64>| n.map_or("?".to_string(), |n| n.to_string());
+            fields.push((
+                "compression",
+                format!(
+                    "{}, {} bytes on the wire, {} decoded",
+                    c.encoding.as_deref().unwrap_or("identity"),
+                    size(c.wire_bytes),
+                    size(c.decoded_bytes)
+                ),
+            ));
+        }
+        if let Some(p) = &self.phases {
+            let tls = p.tls.map_or("-".to_string(), |d| d.as_millis().to_string());
+            fields.push((
+                "phases",
+                format!(
+                    "dns {} | connect {} | tls {} | first byte {} | transfer {} ms",
+                    p.dns.as_millis(),
+                    p.connect.as_millis(),
+                    tls,
+                    p.first_byte.as_millis(),
+                    p.transfer.as_millis()
+                ),
+            ));
+        }
+        if let Some(body) = &self.body {
+            fields.push(("body", format!("{} bytes", body.len())));
+        }
+        if let Some(hash) = &self.body_hash {
+            fields.push(("body hash", hash.clone()));
+        }
+        if let Some(banner) = &self.banner {
+            fields.push(("banner", format!("{banner:?}")));
+        }
+        if self.expected_down {
+            fields.push(("expected down", "yes".into()));
+        }
+        if self.in_maintenance {
+            fields.push(("in maintenance", "yes".into()));
+        }
+        if let Some(flapping) = &self.content_flapping {
+            fields.push((
+                "content",
+                format!(
+                    "flapping between {} versions over {} s",
+                    flapping.versions.len(),
+                    flapping.window.as_secs()
+                ),
+            ));
+        }
+        if let Some(trend) = &self.latency_trend {
+            fields.push((
+                "latency",
+                format!(
+                    "up {:.1}x: {} ms, was {} ms",
+                    trend.ratio,
+                    trend.after.as_millis(),
+                    trend.before.as_millis()
+                ),
+            ));
+        }
+
+        let mut out = format!("{}\n", self.url);
+        for (name, value) in fields {
+            // Wide enough for the longest name, "in maintenance:"
+            let _ = writeln!(out, "  {:<15} {value}", format!("{name}:"));
+        }
+        if let Some(headers) = &self.headers {
+            let _ = writeln!(out, "  headers:");
+            for (name, value) in headers {
+                let _ = writeln!(out, "    {name}: {value}");
+            }
+            if self.headers_truncated {
+                let _ = writeln!(out, "    (truncated)");
+            }
+        }
+        out
+    }
+}
+
+/// Columns [`format_table_with`] can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// `OK`, `ERR` or `MAINT`, as in the CLI's lines
+    Outcome,
+    Url,
+    /// Status code, or what went wrong
+    Status,
+    /// Response time in ms
+    Time,
+    /// When the check was made (UTC)
+    Checked,
+    Retries,
+    /// HTTP method used
+    Method,
+    /// Target id
+    Id,
+}
+
+impl Column {
+    fn header(self) -> &'static str {
+        match self {
+            Column::Outcome => "RESULT",
+            Column::Url => "URL",
+            Column::Status => "STATUS",
+            Column::Time => "TIME",
+            Column::Checked => "CHECKED",
+            Column::Retries => "RETRIES",
+            Column::Method => "METHOD",
+            Column::Id => "ID",
+        }
+    }
+
+    fn right_aligned(self) -> bool {
+        matches!(self, Column::Time | Column::Retries)
+    }
+
+    fn cell(self, ws: &WebsiteStatus, treat_4xx_as_failure: bool) -> String {
+        match self {
+            Column::Outcome => outcome(ws, treat_4xx_as_failure).to_string(),
+            Column::Url => ws.url.clone(),
+            Column::Status => status_label(&ws.status),
+            Column::Time => format!("{} ms", ws.response_time.as_millis()),
+            Column::Checked => ws.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            Column::Retries => ws.retries.to_string(),
+            Column::Method => ws.method_used.map_or("-", method_name).to_string(),
+            Column::Id => ws.id().to_string(),
+        }
+    }
+}
+
+/// What [`format_table_with`] shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableOptions {
+    pub columns: Vec<Column>,
+    /// Widest a cell may be, in characters; longer ones are cut short with
+    /// `…`
+    pub max_width: usize,
+    pub treat_4xx_as_failure: bool,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            columns: vec![Column::Outcome, Column::Url, Column::Status, Column::Time],
+            max_width: 60,
+            treat_4xx_as_failure: true,
+        }
+    }
+}
+
+/// `text` cut to at most `max` characters (never inside one), ending in `…`
+/// when anything was cut.
+fn truncate(text: String, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text;
+    }
+    let mut cut: String = text.chars().take(max.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// The results as a table with the default columns, one row per result
+/// under a header row.
+pub fn format_table(results: &[WebsiteStatus]) -> String {
+    format_table_with(results, &TableOptions::default())
+}
+
+/// Like [`format_table`], with the columns and widths given. Columns are
+/// as wide as their widest cell (counting characters, so wide scripts may
+/// not line up exactly in a terminal) and two spaces apart.
+pub fn format_table_with(results: &[WebsiteStatus], options: &TableOptions) -> String {
+    let max = options.max_width.max(1);
+    let header: Vec<String> = options
+        .columns
+        .iter()
+        .map(|c| c.header().to_string())
+        .collect();
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .map(|ws| {
+            options
+                .columns
+                .iter()
+                .map(|c| truncate(c.cell(ws, options.treat_4xx_as_failure), max))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = (0..options.columns.len())
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(&rows)
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut out = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let mut line = String::new();
+        for ((cell, column), width) in row.iter().zip(&options.columns).zip(&widths) {
+            if !line.is_empty() {
+                line.push_str("  ");
+            }
+            if column.right_aligned() {
+                let _ = write!(line, "{cell:>width$}");
+            } else {
+                let _ = write!(line, "{cell:<width$}");
+            }
+        }
+        let _ = writeln!(out, "{}", line.trim_end());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CacheStatus;
+    use chrono::{TimeZone, Utc};
+    use std::{collections::BTreeMap, time::Duration};
+
+    fn results() -> Vec<WebsiteStatus> {
+        let ok = WebsiteStatus {
+            timestamp: Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap(),
+            labels: BTreeMap::from([("region".to_string(), "eu".to_string())]),
+            headers: Some(vec![("x-cache".into(), "HIT".into())]),
+            cache_status: Some(CacheStatus::Hit),
+            body_hash: Some("9a3f1c0b2d4e5f60".into()),
+            method_used: Some(HttpMethod::Head),
+            request_id: Some("id-2".into()),
+            request_ids: vec!["id-1".into(), "id-2".into()],
+            retries: 1,
+            ..WebsiteStatus::new(
+                "https://shop.example.com/",
+                Ok(200),
+                Duration::from_micros(80_500),
+            )
+        };
+        let timeout = WebsiteStatus {
+            url: "https://status.example.com/health".into(),
+            target_id: "status".into(),
+            status: Err(CheckError::TimedOut {
+                limit: Duration::from_secs(5),
+            }),
+            response_time: Duration::from_millis(5001),
+            labels: BTreeMap::new(),
+            headers: None,
+            cache_status: None,
+            body_hash: None,
+            method_used: None,
+            request_id: None,
+            request_ids: Vec::new(),
+            retries: 0,
+            ..ok.clone()
+        };
+        let missing = WebsiteStatus {
+            url: "https://例え.jp/ページ/とても長いパス".into(),
+            target_id: "https://例え.jp/ページ/とても長いパス".into(),
+            status: Ok(404),
+            response_time: Duration::from_millis(12),
+            ..timeout.clone()
+        };
+        let maintenance = WebsiteStatus {
+            url: "https://api.example.com/".into(),
+            target_id: "https://api.example.com/".into(),
+            status: Ok(503),
+            response_time: Duration::from_millis(230),
+            in_maintenance: true,
+            ..timeout.clone()
+        };
+        vec![ok, timeout, missing, maintenance]
+    }
+
+    #[test]
+    fn line_mirrors_the_cli() {
+        let r = results();
+        assert_eq!(
+            r[0].to_string(),
+            "[OK] https://shop.example.com/ | status=200 | 80 ms | 2026-10-01 12:00:00 UTC"
+        );
+        assert_eq!(
+            format!("{:#}", r[0]),
+            "[OK] https://shop.example.com/ [cache=hit] [body_hash=9a3f1c0b2d4e5f60] \
+             [request_ids=id-1,id-2] | status=200 | 80 ms | 2026-10-01 12:00:00 UTC"
+        );
+        assert_eq!(
+            r[1].to_string(),
+            "[ERR] https://status.example.com/health (id: status) | TIMEOUT (5s) | 5001 ms \
+             | 2026-10-01 12:00:00 UTC"
+        );
+        assert_eq!(
+            r[2].display(false).to_string(),
+            "[OK] https://例え.jp/ページ/とても長いパス | status=404 | 12 ms | 2026-10-01 12:00:00 UTC"
+        );
+        assert!(r[2].to_string().starts_with("[ERR] "));
+        assert!(r[3].to_string().starts_with("[MAINT] "));
+    }
+
+    #[test]
+    fn verbose_lists_what_was_captured() {
+        let r = results();
+        assert_eq!(
+            r[0].format_verbose(),
+            "\
+https://shop.example.com/
+  status:         200
+  response time:  80 ms
+  checked at:     2026-10-01 12:00:00 UTC
+  labels:         region=eu
+  method:         HEAD
+  retries:        1
+  request ids:    id-1, id-2
+  cache:          hit
+  body hash:      9a3f1c0b2d4e5f60
+  headers:
+    x-cache: HIT
+"
+        );
+        assert_eq!(
+            r[3].format_verbose(),
+            "\
+https://api.example.com/
+  status:         503
+  response time:  230 ms
+  checked at:     2026-10-01 12:00:00 UTC
+  in maintenance: yes
+"
+        );
+    }
+
+    #[test]
+    fn table_aligns_columns() {
+        assert_eq!(
+            format_table(&results()),
+            "\
+RESULT  URL                                STATUS           TIME
+OK      https://shop.example.com/          200             80 ms
+ERR     https://status.example.com/health  TIMEOUT (5s)  5001 ms
+ERR     https://例え.jp/ページ/とても長いパス          404             12 ms
+MAINT   https://api.example.com/           503            230 ms
+"
+        );
+    }
+
+    #[test]
+    fn table_columns_can_be_picked_and_cut_short() {
+        let options = TableOptions {
+            columns: vec![Column::Id, Column::Method, Column::Retries, Column::Checked],
+            max_width: 12,
+            treat_4xx_as_failure: false,
+        };
+        assert_eq!(
+            format_table_with(&results(), &options),
+            "\
+ID            METHOD  RETRIES  CHECKED
+https://sho…  HEAD          1  2026-10-01 …
+status        -             0  2026-10-01 …
+https://例え.…  -             0  2026-10-01 …
+https://api…  -             0  2026-10-01 …
+"
+        );
+        assert_eq!(
+            format_table_with(&[], &options),
+            "ID  METHOD  RETRIES  CHECKED\n"
+        );
+
+        // Cut by characters, never inside one
+        assert_eq!(truncate("例え例え".into(), 3), "例え…");
+        assert_eq!(truncate("例え".into(), 2), "例え");
+    }
+}
@@ -0,0 +1,136 @@
+use serde::Serialize;
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{WebsiteStatus, serde_util};
+
+/// Key used for results whose URL doesn't parse or has no host.
+pub const INVALID_HOST: &str = "invalid";
+
+/// Rollup of the results for one host.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HostSummary {
+    pub checks: usize,
+    pub failures: usize,
+    /// `failures / checks`, 0.0 to 1.0
+    pub failure_rate: f64,
+    #[serde(rename = "mean_latency_ms", with = "serde_util::duration_ms")]
+    pub mean_latency: Duration,
+    /// URLs checked on this host, in result order
+    pub urls: Vec<String>,
+}
+
+/// Host part of a URL used for grouping: lowercased, with the port when it
+/// isn't the scheme's default (`example.com:8443`, `[::1]:8080`).
+pub fn host_key(url: &str) -> String {
+    let Ok(parsed) = url::Url::parse(url) else {
+        return INVALID_HOST.to_string();
+    };
+    match (parsed.host_str(), parsed.port()) {
+        (Some(host), Some(port)) => format!("{}:{port}", host.to_ascii_lowercase()),
+        (Some(host), None) => host.to_ascii_lowercase(),
+        (None, _) => INVALID_HOST.to_string(),
+    }
+}
+
+/// Group results by host, counting 4xx responses as failures.
+pub fn group_by_host(results: &[WebsiteStatus]) -> BTreeMap<String, HostSummary> {
+    group_by_host_with(results, true)
+}
+
+/// Like [`group_by_host`], with an explicit 4xx policy.
+pub fn group_by_host_with(
+    results: &[WebsiteStatus],
+    treat_4xx_as_failure: bool,
+) -> BTreeMap<String, HostSummary> {
+    let mut groups: BTreeMap<String, (HostSummary, Duration)> = BTreeMap::new();
+    for ws in results {
+        let (summary, total) = groups.entry(host_key(&ws.url)).or_insert_with(|| {
+            (
+                HostSummary {
+                    checks: 0,
+                    failures: 0,
+                    failure_rate: 0.0,
+                    mean_latency: Duration::ZERO,
+                    urls: Vec::new(),
+                },
+                Duration::ZERO,
+            )
+        });
+        summary.checks += 1;
+        if !ws.is_success(treat_4xx_as_failure) {
+            summary.failures += 1;
+        }
+        summary.urls.push(ws.url.clone());
+        *total += ws.response_time;
+    }
+    groups
+        .into_iter()
+        .map(|(host, (mut summary, total))| {
+            summary.failure_rate = summary.failures as f64 / summary.checks as f64;
+            summary.mean_latency = total / summary.checks as u32;
+            (host, summary)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CheckError;
+
+    fn result(url: &str, status: Result<u16, &str>, ms: u64) -> WebsiteStatus {
+        WebsiteStatus::new(
+            url,
+            status.map_err(CheckError::from),
+            Duration::from_millis(ms),
+        )
+    }
+
+    #[test]
+    fn host_keys() {
+        assert_eq!(host_key("https://Example.COM/a"), "example.com");
+        assert_eq!(host_key("https://example.com:443/"), "example.com");
+        assert_eq!(host_key("https://example.com:8443/x"), "example.com:8443");
+        assert_eq!(host_key("http://127.0.0.1:8080/"), "127.0.0.1:8080");
+        assert_eq!(host_key("http://[::1]:9000/"), "[::1]:9000");
+        assert_eq!(host_key("not a url"), INVALID_HOST);
+        assert_eq!(host_key("mailto:someone@example.com"), INVALID_HOST);
+    }
+
+    #[test]
+    fn rolls_up_per_host() {
+        let results = vec![
+            result("https://a.test/1", Ok(200), 100),
+            result("https://A.test/2", Ok(503), 300),
+            result("https://a.test:8443/", Ok(200), 10),
+            result(
+                "http://10.0.0.1:8080/health",
+                Err("request error: refused"),
+                50,
+            ),
+            result("::garbage::", Err("request error: bad url"), 0),
+            result("https://b.test/", Ok(404), 20),
+        ];
+        let groups = group_by_host(&results);
+        assert_eq!(
+            groups.keys().collect::<Vec<_>>(),
+            vec![
+                "10.0.0.1:8080",
+                "a.test",
+                "a.test:8443",
+                "b.test",
+                "invalid"
+            ]
+        );
+
+        let a = &groups["a.test"];
+        assert_eq!((a.checks, a.failures), (2, 1));
+        assert_eq!(a.failure_rate, 0.5);
+        assert_eq!(a.mean_latency, Duration::from_millis(200));
+        assert_eq!(a.urls, vec!["https://a.test/1", "https://A.test/2"]);
+
+        assert_eq!(groups["invalid"].failures, 1);
+        assert_eq!(groups["b.test"].failures, 1);
+        assert_eq!(group_by_host_with(&results, false)["b.test"].failures, 0);
+    }
+}
@@ -0,0 +1,131 @@
+use reqwest::header::HeaderMap;
+
+/// Marks a header value that was cut short.
+const ELLIPSIS: &str = "…";
+
+/// How much of a response's headers capture keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderLimits {
+    /// Longest value kept, in bytes; longer ones are cut on a character
+    /// boundary and end in `…`
+    pub max_value_bytes: usize,
+    /// Name and value bytes kept per result; the headers past that are
+    /// dropped
+    pub max_total_bytes: usize,
+}
+
+impl Default for HeaderLimits {
+    fn default() -> Self {
+        Self {
+            max_value_bytes: 1024,
+            max_total_bytes: 16 * 1024,
+        }
+    }
+}
+
+/// Headers as captured, and whether any were cut or dropped to fit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CapturedHeaders {
+    pub headers: Vec<(String, String)>,
+    pub truncated: bool,
+}
+
+/// Keep `headers` within `limits`. Values that aren't UTF-8 (Latin-1 from
+/// older servers, say) are decoded lossily rather than dropped.
+pub(crate) fn capture<'a>(
+    headers: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+    limits: HeaderLimits,
+) -> CapturedHeaders {
+    let mut captured = CapturedHeaders::default();
+    let mut total = 0;
+    for (name, value) in headers {
+        let mut value = String::from_utf8_lossy(value).into_owned();
+        if value.len() > limits.max_value_bytes {
+            let mut end = limits.max_value_bytes;
+            while !value.is_char_boundary(end) {
+                end -= 1;
+            }
+            value.truncate(end);
+            value.push_str(ELLIPSIS);
+            captured.truncated = true;
+        }
+        total += name.len() + value.len();
+        if total > limits.max_total_bytes {
+            captured.truncated = true;
+            break;
+        }
+        captured.headers.push((name.to_string(), value));
+    }
+    captured
+}
+
+pub(crate) fn capture_map(headers: &HeaderMap, limits: HeaderLimits) -> CapturedHeaders {
+    capture(
+        headers.iter().map(|(k, v)| (k.as_str(), v.as_bytes())),
+        limits,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderName, HeaderValue};
+
+    fn map(pairs: &[(&'static str, &[u8])]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(k, v)| {
+                (
+                    HeaderName::from_static(k),
+                    HeaderValue::from_bytes(v).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn latin1_values_are_kept_lossily() {
+        // "Café" in Latin-1
+        let headers = map(&[("x-owner", b"Caf\xe9"), ("server", b"appliance")]);
+        let captured = capture_map(&headers, HeaderLimits::default());
+        assert_eq!(
+            captured.headers,
+            [
+                ("x-owner".to_string(), "Caf\u{fffd}".to_string()),
+                ("server".to_string(), "appliance".to_string())
+            ]
+        );
+        assert!(!captured.truncated);
+    }
+
+    #[test]
+    fn long_values_are_cut_on_a_char_boundary() {
+        let limits = HeaderLimits {
+            max_value_bytes: 5,
+            ..HeaderLimits::default()
+        };
+        let cookie = vec![b'a'; 4096];
+        // 'é' takes bytes 4 and 5, so the cut falls back to 4
+        let headers = map(&[("set-cookie", &cookie), ("x-name", "abcdéf".as_bytes())]);
+        let captured = capture_map(&headers, limits);
+        assert_eq!(captured.headers[0].1, "aaaaa…");
+        assert_eq!(captured.headers[1].1, "abcd…");
+        assert!(captured.truncated);
+    }
+
+    #[test]
+    fn headers_past_the_total_are_dropped() {
+        let limits = HeaderLimits {
+            max_value_bytes: 1024,
+            max_total_bytes: 20,
+        };
+        let headers = map(&[("a", b"123456789"), ("b", b"123456789"), ("c", b"1")]);
+        let captured = capture_map(&headers, limits);
+        let names: Vec<&str> = captured.headers.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+        assert!(captured.truncated);
+
+        let fits = capture_map(&map(&[("a", b"1")]), limits);
+        assert!(!fits.truncated);
+    }
+}
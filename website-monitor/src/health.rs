@@ -0,0 +1,212 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Write,
+    time::Duration,
+};
+
+use crate::WebsiteStatus;
+
+/// How results are turned into a health score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthConfig {
+    /// Most recent results kept per target
+    pub window: usize,
+    /// What a failed result costs, out of 1
+    pub failure_cost: f64,
+    /// What a success slower than `slow_after` costs, out of 1
+    pub slow_cost: f64,
+    pub slow_after: Duration,
+    /// Age at which a result counts half as much as a fresh one
+    pub half_life: Duration,
+    /// Weight of each target in the overall score, by target id; targets
+    /// not listed weigh 1
+    pub weights: HashMap<String, f64>,
+    pub treat_4xx_as_failure: bool,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            window: 100,
+            failure_cost: 1.0,
+            slow_cost: 0.25,
+            slow_after: Duration::from_secs(1),
+            half_life: Duration::from_secs(3600),
+            weights: HashMap::new(),
+            treat_4xx_as_failure: true,
+        }
+    }
+}
+
+/// How much a result `age` old counts next to a fresh one:
+/// `0.5 ^ (age / half_life)`, so 1 when fresh, 0.5 after one half-life and
+/// 0.25 after two. With a zero half-life only fresh results count.
+pub fn decay_weight(age: Duration, half_life: Duration) -> f64 {
+    if half_life.is_zero() {
+        return if age.is_zero() { 1.0 } else { 0.0 };
+    }
+    0.5_f64.powf(age.as_secs_f64() / half_life.as_secs_f64())
+}
+
+/// Score from 0 to 100 for results given as `(age, cost)`, costs between 0
+/// (fine) and 1 (failed): 100 times one minus the decay-weighted mean cost.
+/// No results (or only ones decayed to nothing) score 100.
+///
+/// Since it's a mean, adding a result that costs 1 can't raise the score,
+/// and as results age the newer ones take over.
+pub fn weighted_score(
+    results: impl IntoIterator<Item = (Duration, f64)>,
+    half_life: Duration,
+) -> f64 {
+    let (mut weight, mut cost) = (0.0, 0.0);
+    for (age, c) in results {
+        let w = decay_weight(age, half_life);
+        weight += w;
+        cost += w * c.clamp(0.0, 1.0);
+    }
+    if weight > 0.0 {
+        100.0 * (1.0 - cost / weight)
+    } else {
+        100.0
+    }
+}
+
+/// Health of every target seen, and overall.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthScore {
+    /// Per-target scores averaged by [`HealthConfig::weights`]
+    pub overall: f64,
+    /// By target id
+    pub targets: BTreeMap<String, f64>,
+}
+
+impl HealthScore {
+    /// Prometheus text exposition: one gauge per target (labelled `target`)
+    /// and `{name}_overall`.
+    pub fn to_prometheus(&self, name: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        for (id, score) in &self.targets {
+            let id = id
+                .replace('\\', r"\\")
+                .replace('"', "\\\"")
+                .replace('\n', r"\n");
+            let _ = writeln!(out, "{name}{{target=\"{id}\"}} {score}");
+        }
+        let _ = writeln!(out, "# TYPE {name}_overall gauge");
+        let _ = writeln!(out, "{name}_overall {}", self.overall);
+        out
+    }
+}
+
+/// Recent results per target, scored on request.
+#[derive(Debug, Default)]
+pub struct HealthTracker {
+    config: HealthConfig,
+    results: HashMap<String, VecDeque<(DateTime<Utc>, f64)>>,
+}
+
+impl HealthTracker {
+    pub fn new(config: HealthConfig) -> Self {
+        Self {
+            config,
+            results: HashMap::new(),
+        }
+    }
+
+    fn cost(&self, ws: &WebsiteStatus) -> f64 {
+        if !ws.is_success(self.config.treat_4xx_as_failure) {
+            self.config.failure_cost
+        } else if ws.response_time > self.config.slow_after {
+            self.config.slow_cost
+        } else {
+            0.0
+        }
+    }
+
+    /// Failures in maintenance aren't scored at all.
+    pub fn observe(&mut self, ws: &WebsiteStatus) {
+        if ws.in_maintenance {
+            return;
+        }
+        let cost = self.cost(ws);
+        let results = self.results.entry(ws.id().to_string()).or_default();
+        results.push_back((ws.timestamp, cost));
+        while results.len() > self.config.window.max(1) {
+            results.pop_front();
+        }
+    }
+
+    /// Scores as of `now`; 100 overall before any result is seen.
+    pub fn health_score(&self, now: DateTime<Utc>) -> HealthScore {
+        let targets: BTreeMap<String, f64> = self
+            .results
+            .iter()
+            .map(|(id, results)| {
+                let aged = results
+                    .iter()
+                    .map(|(at, cost)| ((now - *at).to_std().unwrap_or_default(), *cost));
+                (id.clone(), weighted_score(aged, self.config.half_life))
+            })
+            .collect();
+
+        let (mut weight, mut sum) = (0.0, 0.0);
+        for (id, score) in &targets {
+            let w = self.config.weights.get(id).copied().unwrap_or(1.0).max(0.0);
+            weight += w;
+            sum += w * score;
+        }
+        let overall = if weight > 0.0 { sum / weight } else { 100.0 };
+        HealthScore { overall, targets }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn hours(h: u64) -> Duration {
+        HOUR * h as u32
+    }
+
+    #[test]
+    fn decay_halves_every_half_life() {
+        assert_eq!(decay_weight(Duration::ZERO, HOUR), 1.0);
+        assert_eq!(decay_weight(hours(1), HOUR), 0.5);
+        assert_eq!(decay_weight(hours(2), HOUR), 0.25);
+        assert_eq!(decay_weight(hours(1), Duration::ZERO), 0.0);
+        assert_eq!(weighted_score([], HOUR), 100.0);
+    }
+
+    #[test]
+    fn adding_a_failure_never_raises_the_score() {
+        let histories: [&[(Duration, f64)]; 4] = [
+            &[],
+            &[(hours(0), 0.0), (hours(1), 0.25)],
+            &[(hours(3), 1.0), (hours(0), 0.0)],
+            &[(hours(0), 1.0), (hours(5), 1.0)],
+        ];
+        for history in histories {
+            let before = weighted_score(history.iter().copied(), HOUR);
+            for age in [hours(0), hours(1), hours(10)] {
+                let mut with = history.to_vec();
+                with.push((age, 1.0));
+                let after = weighted_score(with, HOUR);
+                assert!(after <= before, "{history:?} + failure {age:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn old_failures_decay() {
+        let fresh = weighted_score([(hours(0), 1.0), (hours(0), 0.0)], HOUR);
+        let older = weighted_score([(hours(2), 1.0), (hours(0), 0.0)], HOUR);
+        let oldest = weighted_score([(hours(8), 1.0), (hours(0), 0.0)], HOUR);
+        assert_eq!(fresh, 50.0);
+        assert!(fresh < older && older < oldest && oldest < 100.0);
+    }
+}
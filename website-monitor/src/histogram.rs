@@ -0,0 +1,197 @@
+use serde::{Serialize, Serializer, ser::SerializeStruct};
+use std::{fmt::Write, time::Duration};
+
+/// Upper bounds (inclusive, milliseconds) of the fixed buckets; anything
+/// slower lands in the final +Inf bucket.
+pub const BUCKET_BOUNDS_MS: [u64; 17] = [
+    1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024, 2048, 4096, 8192, 16384, 32768, 60000,
+];
+
+/// Response-time distribution over fixed exponential buckets (1ms .. 60s).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// Per-bucket (non-cumulative) counts; the last entry is the +Inf bucket
+    counts: [u64; BUCKET_BOUNDS_MS.len() + 1],
+    sum: Duration,
+    count: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: [0; BUCKET_BOUNDS_MS.len() + 1],
+            sum: Duration::ZERO,
+            count: 0,
+        }
+    }
+
+    /// Index of the bucket `d` falls into.
+    fn bucket_index(d: Duration) -> usize {
+        let ms = d.as_secs_f64() * 1000.0;
+        BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| ms <= bound as f64)
+            .unwrap_or(BUCKET_BOUNDS_MS.len())
+    }
+
+    pub fn record(&mut self, d: Duration) {
+        self.counts[Self::bucket_index(d)] += 1;
+        self.sum += d;
+        self.count += 1;
+    }
+
+    /// Fold another histogram (e.g. from an earlier pass) into this one.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (mine, theirs) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *mine += theirs;
+        }
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// `(upper bound, count)` per bucket, non-cumulative; `None` is +Inf.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        BUCKET_BOUNDS_MS
+            .iter()
+            .map(|&ms| Some(Duration::from_millis(ms)))
+            .chain(std::iter::once(None))
+            .zip(self.counts.iter().copied())
+    }
+
+    /// Prometheus text exposition (`_bucket` / `_sum` / `_count`), in seconds.
+    pub fn to_prometheus(&self, name: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        let mut cumulative = 0;
+        for (bound, count) in self.buckets() {
+            cumulative += count;
+            let le = match bound {
+                Some(d) => format!("{}", d.as_secs_f64()),
+                None => "+Inf".to_string(),
+            };
+            let _ = writeln!(out, "{name}_bucket{{le=\"{le}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_sum {}", self.sum.as_secs_f64());
+        let _ = writeln!(out, "{name}_count {}", self.count);
+        out
+    }
+}
+
+#[derive(Serialize)]
+struct BucketJson {
+    /// Upper bound in ms; null for +Inf
+    le_ms: Option<u64>,
+    count: u64,
+}
+
+impl Serialize for LatencyHistogram {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let buckets: Vec<BucketJson> = self
+            .buckets()
+            .map(|(bound, count)| BucketJson {
+                le_ms: bound.map(|d| d.as_millis() as u64),
+                count,
+            })
+            .collect();
+        let mut s = serializer.serialize_struct("LatencyHistogram", 3)?;
+        s.serialize_field("buckets", &buckets)?;
+        s.serialize_field("sum_ms", &(self.sum.as_secs_f64() * 1000.0))?;
+        s.serialize_field("count", &self.count)?;
+        s.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn bucket_boundaries_are_inclusive() {
+        assert_eq!(LatencyHistogram::bucket_index(Duration::ZERO), 0);
+        assert_eq!(LatencyHistogram::bucket_index(ms(1)), 0);
+        assert_eq!(
+            LatencyHistogram::bucket_index(Duration::from_micros(1001)),
+            1
+        );
+        assert_eq!(LatencyHistogram::bucket_index(ms(1024)), 10);
+        assert_eq!(LatencyHistogram::bucket_index(ms(60_000)), 16);
+        assert_eq!(LatencyHistogram::bucket_index(ms(60_001)), 17);
+    }
+
+    #[test]
+    fn counts_known_inputs() {
+        let mut h = LatencyHistogram::new();
+        for d in [ms(1), ms(3), ms(3), ms(100), ms(90_000)] {
+            h.record(d);
+        }
+        let counts: Vec<u64> = h.buckets().map(|(_, c)| c).collect();
+        assert_eq!(counts[0], 1); // <= 1ms
+        assert_eq!(counts[2], 2); // <= 4ms
+        assert_eq!(counts[7], 1); // <= 128ms
+        assert_eq!(counts[17], 1); // +Inf
+        assert_eq!(h.count(), 5);
+        assert_eq!(h.sum(), ms(90_107));
+    }
+
+    #[test]
+    fn merge_adds_everything() {
+        let mut a = LatencyHistogram::new();
+        a.record(ms(5));
+        let mut b = LatencyHistogram::new();
+        b.record(ms(5));
+        b.record(ms(2000));
+        a.merge(&b);
+        assert_eq!(a.count(), 3);
+        assert_eq!(a.sum(), ms(2010));
+        assert_eq!(a.buckets().nth(3).unwrap().1, 2);
+        assert_eq!(a.buckets().nth(11).unwrap().1, 1);
+    }
+
+    #[test]
+    fn prometheus_rendering_is_cumulative() {
+        let mut h = LatencyHistogram::new();
+        h.record(ms(1));
+        h.record(ms(3));
+        h.record(ms(70_000));
+        let text = h.to_prometheus("monitor_response_seconds");
+        assert!(text.starts_with("# TYPE monitor_response_seconds histogram\n"));
+        assert!(text.contains("monitor_response_seconds_bucket{le=\"0.001\"} 1\n"));
+        assert!(text.contains("monitor_response_seconds_bucket{le=\"0.002\"} 1\n"));
+        assert!(text.contains("monitor_response_seconds_bucket{le=\"0.004\"} 2\n"));
+        assert!(text.contains("monitor_response_seconds_bucket{le=\"60\"} 2\n"));
+        assert!(text.contains("monitor_response_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("monitor_response_seconds_sum 70.004\n"));
+        assert!(text.ends_with("monitor_response_seconds_count 3\n"));
+    }
+
+    #[test]
+    fn json_lists_every_bucket() {
+        let mut h = LatencyHistogram::new();
+        h.record(ms(2));
+        let v = serde_json::to_value(&h).unwrap();
+        let buckets = v["buckets"].as_array().unwrap();
+        assert_eq!(buckets.len(), 18);
+        assert_eq!(buckets[1], serde_json::json!({"le_ms": 2, "count": 1}));
+        assert_eq!(buckets[17]["le_ms"], serde_json::Value::Null);
+        assert_eq!(v["count"], 1);
+        assert_eq!(v["sum_ms"], 2.0);
+    }
+}
@@ -0,0 +1,331 @@
+//! Self-contained HTML report of a pass, for people who'd rather open a
+//! page in a browser than read monitor output.
+
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Write,
+    time::Duration,
+};
+
+use crate::{RunReport, WebsiteStatus, group_by_host_with};
+
+/// Slowest successful URLs listed in the report.
+const SLOWEST: usize = 10;
+
+/// Up/down results per URL over a trailing window, for the uptime bars of
+/// [`render_html`]. A continuous run records every pass into one.
+#[derive(Debug, Clone)]
+pub struct UptimeHistory {
+    window: Duration,
+    bars: usize,
+    /// `(checked at, up)` per URL, oldest first
+    results: BTreeMap<String, VecDeque<(DateTime<Utc>, bool)>>,
+}
+
+impl Default for UptimeHistory {
+    /// The last 24 hours, one bar per hour.
+    fn default() -> Self {
+        Self::new(Duration::from_secs(24 * 3600), 24)
+    }
+}
+
+impl UptimeHistory {
+    /// History over the last `window`, shown as `bars` bars (at least one).
+    pub fn new(window: Duration, bars: usize) -> Self {
+        Self {
+            window,
+            bars: bars.max(1),
+            results: BTreeMap::new(),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Add the results of a pass, forgetting those now older than the
+    /// window. Results during maintenance count as neither up nor down.
+    pub fn record(&mut self, report: &RunReport) {
+        let treat_4xx = report.summary.treat_4xx_as_failure;
+        for ws in report.results.iter().filter(|ws| !ws.in_maintenance) {
+            let seen = self.results.entry(ws.url.clone()).or_default();
+            seen.push_back((ws.timestamp, ws.is_success(treat_4xx)));
+        }
+        let Some(newest) = report.results.iter().map(|ws| ws.timestamp).max() else {
+            return;
+        };
+        let Some(cutoff) = chrono::TimeDelta::from_std(self.window)
+            .ok()
+            .and_then(|window| newest.checked_sub_signed(window))
+        else {
+            return;
+        };
+        for seen in self.results.values_mut() {
+            while seen.front().is_some_and(|(at, _)| *at <= cutoff) {
+                seen.pop_front();
+            }
+        }
+        self.results.retain(|_, seen| !seen.is_empty());
+    }
+
+    /// URLs with results in the window.
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        self.results.keys().map(String::as_str)
+    }
+
+    /// Share of `url`'s results that were up in each slice of the window
+    /// ending at `now`, oldest slice first; `None` for slices without
+    /// results.
+    pub fn bars(&self, url: &str, now: DateTime<Utc>) -> Vec<Option<f64>> {
+        let mut counts = vec![(0usize, 0usize); self.bars];
+        let slice = self.window.as_secs_f64() / self.bars as f64;
+        for (at, up) in self.results.get(url).into_iter().flatten() {
+            let age = (now - *at).to_std().unwrap_or(Duration::ZERO);
+            if age >= self.window {
+                continue;
+            }
+            let back = ((age.as_secs_f64() / slice) as usize).min(self.bars - 1);
+            let (ups, total) = &mut counts[self.bars - 1 - back];
+            *ups += usize::from(*up);
+            *total += 1;
+        }
+        counts
+            .into_iter()
+            .map(|(ups, total)| (total > 0).then(|| ups as f64 / total as f64))
+            .collect()
+    }
+
+    /// Share of `url`'s results in the window that were up.
+    pub fn uptime(&self, url: &str) -> Option<f64> {
+        let seen = self.results.get(url)?;
+        let ups = seen.iter().filter(|(_, up)| *up).count();
+        Some(ups as f64 / seen.len() as f64)
+    }
+}
+
+/// `text` with the characters that mean something in HTML escaped, safe in
+/// both element content and quoted attributes.
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+const STYLE: &str = "\
+body{font-family:system-ui,sans-serif;margin:2em;color:#222;max-width:70em}\
+h1{font-size:1.5em}h2{font-size:1.2em;margin-top:2em}\
+.meta{color:#666}\
+.numbers{display:flex;gap:2em;margin:0}\
+.numbers div{text-align:center}\
+.numbers dt{color:#666;font-size:.9em}\
+.numbers dd{font-size:2em;margin:0}\
+.numbers .bad{color:#c0392b}\
+table{border-collapse:collapse;width:100%}\
+th,td{text-align:left;padding:.3em .6em;border-bottom:1px solid #ddd;vertical-align:middle}\
+td.num,th.num{text-align:right}\
+td.url{word-break:break-all}\
+tr.failure td.status{color:#c0392b}\
+.bars{display:flex;gap:1px;height:1.5em;align-items:stretch}\
+.bar{flex:1;min-width:3px;background:#ddd}\
+.bar.up{background:#27ae60}.bar.partial{background:#f39c12}.bar.down{background:#c0392b}";
+
+fn status_text(ws: &WebsiteStatus) -> String {
+    match &ws.status {
+        Ok(code) => format!("HTTP {code}"),
+        Err(e) => e.to_string(),
+    }
+}
+
+fn ms(d: Duration) -> String {
+    format!("{} ms", d.as_millis())
+}
+
+fn percent(share: f64) -> String {
+    format!("{:.1}%", share * 100.0)
+}
+
+/// The report as one HTML page with everything inline (no scripts, styles
+/// or images from elsewhere): summary numbers, failures, the slowest URLs,
+/// a per-host rollup and, given a `history`, an uptime bar per URL over its
+/// window. URLs and error text are escaped.
+///
+/// Times are relative to the newest result in the report.
+pub fn render_html(report: &RunReport, history: Option<&UptimeHistory>) -> String {
+    let summary = &report.summary;
+    let treat_4xx = summary.treat_4xx_as_failure;
+    let now = report
+        .results
+        .iter()
+        .map(|ws| ws.timestamp)
+        .max()
+        .unwrap_or_else(Utc::now);
+
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Website monitor report</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+         <h1>Website monitor report</h1>\n<p class=\"meta\">As of {}</p>\n",
+        now.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+
+    // Summary numbers
+    let mean = match summary.latency.count() {
+        0 => Duration::ZERO,
+        n => summary.latency.sum() / n as u32,
+    };
+    let _ = writeln!(
+        out,
+        "<section id=\"summary\">\n<h2>Summary</h2>\n<dl class=\"numbers\">"
+    );
+    let mut numbers = vec![
+        ("Checked", summary.total.to_string(), false),
+        ("OK", summary.ok.to_string(), false),
+        ("Failed", summary.err.to_string(), summary.err > 0),
+    ];
+    if summary.in_maintenance > 0 {
+        numbers.push(("In maintenance", summary.in_maintenance.to_string(), false));
+    }
+    if summary.budget_skipped > 0 {
+        numbers.push(("Not checked", summary.budget_skipped.to_string(), false));
+    }
+    numbers.push(("Mean response", ms(mean), false));
+    for (label, value, bad) in numbers {
+        let class = if bad { " class=\"bad\"" } else { "" };
+        let _ = writeln!(out, "<div><dt>{label}</dt><dd{class}>{value}</dd></div>");
+    }
+    let _ = writeln!(out, "</dl>\n</section>");
+
+    // Failures
+    let failures: Vec<&WebsiteStatus> = report
+        .results
+        .iter()
+        .filter(|ws| !ws.is_success(treat_4xx))
+        .collect();
+    let _ = writeln!(
+        out,
+        "<section id=\"failures\">\n<h2>Failures ({})</h2>",
+        failures.len()
+    );
+    if failures.is_empty() {
+        let _ = writeln!(out, "<p>No failures.</p>");
+    } else {
+        let _ = writeln!(
+            out,
+            "<table>\n<tr><th>URL</th><th>Status</th><th class=\"num\">Time</th><th class=\"num\">Retries</th></tr>"
+        );
+        for ws in failures {
+            let _ = writeln!(
+                out,
+                "<tr class=\"failure\"><td class=\"url\">{}</td><td class=\"status\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                escape_html(&ws.url),
+                escape_html(&status_text(ws)),
+                ms(ws.response_time),
+                ws.retries
+            );
+        }
+        let _ = writeln!(out, "</table>");
+    }
+    let _ = writeln!(out, "</section>");
+
+    // Slowest URLs
+    let slowest = summary.worst_offenders(SLOWEST).slowest;
+    let _ = writeln!(out, "<section id=\"slowest\">\n<h2>Slowest URLs</h2>");
+    if slowest.is_empty() {
+        let _ = writeln!(out, "<p>No successful checks.</p>");
+    } else {
+        let _ = writeln!(
+            out,
+            "<table>\n<tr><th>URL</th><th class=\"num\">Time</th></tr>"
+        );
+        for (url, time) in &slowest {
+            let _ = writeln!(
+                out,
+                "<tr class=\"slow\"><td class=\"url\">{}</td><td class=\"num\">{}</td></tr>",
+                escape_html(url),
+                ms(*time)
+            );
+        }
+        let _ = writeln!(out, "</table>");
+    }
+    let _ = writeln!(out, "</section>");
+
+    // Per-host rollup
+    let _ = writeln!(
+        out,
+        "<section id=\"hosts\">\n<h2>Hosts</h2>\n<table>\n\
+         <tr><th>Host</th><th class=\"num\">Checks</th><th class=\"num\">Failed</th>\
+         <th class=\"num\">Failure rate</th><th class=\"num\">Mean time</th></tr>"
+    );
+    for (host, group) in group_by_host_with(&report.results, treat_4xx) {
+        let _ = writeln!(
+            out,
+            "<tr class=\"host\"><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+            escape_html(&host),
+            group.checks,
+            group.failures,
+            percent(group.failure_rate),
+            ms(group.mean_latency)
+        );
+    }
+    let _ = writeln!(out, "</table>\n</section>");
+
+    // Uptime bars
+    if let Some(history) = history {
+        let hours = history.window().as_secs_f64() / 3600.0;
+        let _ = writeln!(
+            out,
+            "<section id=\"uptime\">\n<h2>Uptime, last {hours:.0} h</h2>\n<table>\n\
+             <tr><th>URL</th><th>History</th><th class=\"num\">Uptime</th></tr>"
+        );
+        for url in history.urls() {
+            let _ = write!(
+                out,
+                "<tr class=\"uptime\"><td class=\"url\">{}</td><td><div class=\"bars\">",
+                escape_html(url)
+            );
+            for bar in history.bars(url, now) {
+                let (class, title) = match bar {
+                    None => ("bar", "no checks".to_string()),
+                    Some(share) if share >= 1.0 => ("bar up", percent(share)),
+                    Some(share) if share > 0.0 => ("bar partial", percent(share)),
+                    Some(share) => ("bar down", percent(share)),
+                };
+                let _ = write!(out, "<span class=\"{class}\" title=\"{title}\"></span>");
+            }
+            let _ = writeln!(
+                out,
+                "</div></td><td class=\"num\">{}</td></tr>",
+                history.uptime(url).map(percent).unwrap_or_default()
+            );
+        }
+        let _ = writeln!(out, "</table>\n</section>");
+    }
+
+    let _ = writeln!(out, "</body>\n</html>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup_and_quotes() {
+        assert_eq!(
+            escape_html(r#"<a href="x?a=1&b='2'">"#),
+            "&lt;a href=&quot;x?a=1&amp;b=&#39;2&#39;&quot;&gt;"
+        );
+        assert_eq!(escape_html("plain"), "plain");
+    }
+}
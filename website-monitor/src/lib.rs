@@ -1,22 +1,283 @@
 use chrono::{DateTime, Utc};
+use rand::Rng;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashSet,
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
+    io,
+    net::{IpAddr, SocketAddr, TcpListener},
     sync::{
-        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, PoisonError, Weak,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc,
-        Arc, Mutex,
     },
     thread,
     time::{Duration, Instant},
 };
 
+mod audit;
+mod body;
+mod budget;
+mod cache;
+mod canary;
+#[cfg(feature = "certs")]
+mod certs;
+mod checkpoint;
+mod compare;
+mod compression;
+mod content;
+mod cors;
+mod error;
+mod format;
+mod group;
+mod headers;
+mod health;
+mod histogram;
+mod html;
+mod mail;
+mod maintenance;
+mod merge;
+mod method;
+mod phases;
+mod request_id;
+mod retry;
+mod sample;
+mod schedule;
+mod schema;
+mod serde_util;
+mod socket;
+mod state;
+mod streak;
+mod summary;
+#[cfg(all(feature = "syslog", unix))]
+mod syslog;
+mod target;
+#[cfg(feature = "phase-timing")]
+mod timing;
+mod trend;
+mod urls;
+mod usage;
+mod writer;
+#[cfg(feature = "tungstenite")]
+mod ws;
+
+pub use audit::{HttpsAudit, HttpsAuditEntry, HttpsUpgrade, https_audit};
+pub use budget::{BudgetExhausted, RequestBudget};
+pub use cache::{CacheStatus, detect_cache_status};
+pub use canary::{CanaryComparison, CanaryOptions, CanaryPair, CanarySide, canary};
+#[cfg(feature = "certs")]
+pub use certs::{CertEntry, CertInfo, cert_inventory, inspect_certificate};
+pub use checkpoint::Checkpoint;
+pub use compare::{DiffOptions, LatencyRegression, ResultDiff, StateChange, compare};
+pub use compression::{Compression, Encoding};
+pub use content::{ContentFlapping, FlapConfig, FlapDetector, body_hash};
+pub use cors::CorsExpect;
+pub use error::CheckError;
+pub use format::{Column, ResultLine, TableOptions, format_table, format_table_with, status_label};
+pub use group::{HostSummary, INVALID_HOST, group_by_host, group_by_host_with, host_key};
+pub use headers::HeaderLimits;
+pub use health::{HealthConfig, HealthScore, HealthTracker, decay_weight, weighted_score};
+pub use histogram::{BUCKET_BOUNDS_MS, LatencyHistogram};
+pub use html::{UptimeHistory, render_html};
+pub use mail::MailProbe;
+pub use maintenance::{MaintenanceWindow, in_maintenance};
+pub use merge::{
+    Availability, MergedReport, MergedUrl, REGION_LABEL, RegionOutcome, merge_regions,
+};
+pub use method::{HttpMethod, MethodMemory};
+pub use phases::PhaseTimings;
+pub use retry::{CheckOutcome, ExponentialBackoff, FixedAttempts, NoRetry, RetryPolicy};
+pub use sample::{SampleSize, SampleSpec, Sampled};
+pub use schedule::{Clock, ContinuousConfig, Scheduler, SystemClock, monitor_continuous};
+pub use schema::{SCHEMA_VERSION, SchemaError, Versioned, parse_results};
+pub use socket::{SocketAddress, SocketWriter};
+pub use state::StateSnapshot;
+pub use streak::{StreakTracker, Transition, UrlState};
+pub use summary::{CacheCounts, RunReport, RunSummary, StatusClasses, WorkerStats, WorstOffenders};
+#[cfg(all(feature = "syslog", unix))]
+pub use syslog::{SyslogFormat, SyslogWriter};
+pub use target::{
+    ConfigError, CredentialRedaction, Credentials, CronSchedule, RedirectPolicy, Target,
+};
+pub use trend::{LatencyTrend, TrendConfig, TrendTracker};
+pub use urls::{InputCounts, UrlList, normalize_url};
+pub use usage::ResourceUsage;
+pub use writer::{ResultWriter, Severity};
+
+use retry::ConfigRetry;
+
 /// Output format
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebsiteStatus {
     pub url: String,
-    pub status: Result<u16, String>,
+    /// [`Target::id`] of the target checked (empty in results saved before
+    /// targets had ids; see [`WebsiteStatus::id`])
+    #[serde(default)]
+    pub target_id: String,
+    pub status: Result<u16, CheckError>,
+    #[serde(rename = "response_time_ms", with = "serde_util::duration_ms")]
     pub response_time: Duration,
     pub timestamp: DateTime<Utc>,
+    /// `Host` header sent instead of the one derived from the URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_header: Option<String>,
+    /// `MonitorConfig::labels` of the run that produced this result
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// Response headers, when header capture is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<Vec<(String, String)>>,
+    /// Some header values were cut short or headers dropped to stay within
+    /// `MonitorConfig::header_limits`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub headers_truncated: bool,
+    /// Cache hit/miss derived from the captured headers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_status: Option<CacheStatus>,
+    /// Content encoding and sizes, when header capture is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+    /// DNS / connect / TLS / transfer breakdown, when detailed timing is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phases: Option<PhaseTimings>,
+    /// Response body (lossy UTF-8), when body capture is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Hash of the response body, when body hashing is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_hash: Option<String>,
+    /// Greeting of a mail server (`smtp://` and `imap://` targets)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub banner: Option<String>,
+    /// Set in continuous mode when the URL has been serving more than one
+    /// body lately; a warning, not a failure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_flapping: Option<ContentFlapping>,
+    /// Set in continuous mode when the URL has been answering markedly
+    /// slower than before; a warning, not a failure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_trend: Option<LatencyTrend>,
+    /// Result of a negative check that came out as expected: the target was
+    /// unreachable or answered with its expected status
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub expected_down: bool,
+    /// A failure during one of the target's maintenance windows; it
+    /// counts neither for nor against the target
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_maintenance: bool,
+    /// In HEAD passes, the method the result came from: HEAD, or GET when
+    /// the server mishandles HEAD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method_used: Option<HttpMethod>,
+    /// `Location` of the answer, for targets that don't follow redirects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Id sent with the attempt this result came from, when
+    /// `MonitorConfig::inject_request_id` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Ids sent with every attempt, first to last, so retried failures can
+    /// be found in server logs too
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub request_ids: Vec<String>,
+    /// Retries it took to get this result (0 = first attempt)
+    #[serde(default, skip_serializing_if = "serde_util::is_zero")]
+    pub retries: u32,
+}
+
+impl WebsiteStatus {
+    /// The id of the target checked, or the URL for older results without
+    /// one.
+    pub fn id(&self) -> &str {
+        if self.target_id.is_empty() {
+            &self.url
+        } else {
+            &self.target_id
+        }
+    }
+
+    /// Whether this result counts as a success. 5xx responses never do;
+    /// 4xx responses only when `treat_4xx_as_failure` is off. Negative
+    /// checks that went as expected always do, and so do failures in
+    /// maintenance, so they never fail a run.
+    pub fn is_success(&self, treat_4xx_as_failure: bool) -> bool {
+        if self.expected_down || self.in_maintenance {
+            return true;
+        }
+        match self.status {
+            Ok(code) if code >= 500 => false,
+            Ok(code) if code >= 400 => !treat_4xx_as_failure,
+            Ok(_) => true,
+            Err(_) => false,
+        }
+    }
+
+    /// A result for `url` with nothing but its outcome, stamped now; for
+    /// results built by hand, e.g. in tests.
+    pub fn new(
+        url: impl Into<String>,
+        status: Result<u16, CheckError>,
+        response_time: Duration,
+    ) -> Self {
+        let url = url.into();
+        Self {
+            target_id: url.clone(),
+            url,
+            status,
+            response_time,
+            timestamp: Utc::now(),
+            host_header: None,
+            labels: BTreeMap::new(),
+            headers: None,
+            headers_truncated: false,
+            cache_status: None,
+            compression: None,
+            phases: None,
+            body: None,
+            body_hash: None,
+            banner: None,
+            content_flapping: None,
+            latency_trend: None,
+            expected_down: false,
+            in_maintenance: false,
+            method_used: None,
+            location: None,
+            request_id: None,
+            request_ids: Vec::new(),
+            retries: 0,
+        }
+    }
+
+    fn for_target(
+        target: &Target,
+        status: Result<u16, CheckError>,
+        response_time: Duration,
+    ) -> Self {
+        Self {
+            target_id: target.id().into_owned(),
+            host_header: target.host_header.clone(),
+            ..Self::new(target.display_url(), status, response_time)
+        }
+    }
+
+    fn from_fetch(target: &Target, fetched: Fetched, response_time: Duration) -> Self {
+        let mut ws = Self::for_target(target, Ok(fetched.code), response_time);
+        if let Some(captured) = fetched.headers {
+            ws.cache_status = Some(detect_cache_status(&captured.headers));
+            ws.headers = Some(captured.headers);
+            ws.headers_truncated = captured.truncated;
+        }
+        ws.compression = fetched.compression;
+        ws.phases = fetched.phases;
+        ws.body = fetched
+            .body
+            .map(|b| String::from_utf8_lossy(&b).into_owned());
+        ws.body_hash = fetched.body_hash;
+        ws.banner = fetched.banner;
+        ws.location = fetched.location;
+        ws
+    }
 }
 
 /// Configurable options
@@ -24,18 +285,172 @@ pub struct WebsiteStatus {
 pub struct MonitorConfig {
     /// Number of worker threads
     pub worker_threads: usize,
+    /// Cap on workers per available CPU; `worker_threads` is clamped to
+    /// `available_parallelism() * worker_multiplier`
+    pub worker_multiplier: usize,
     /// Per-request timeout (default 5s recommended)
     pub request_timeout: Duration,
     /// Maximum number of retries per website (0 = no retry)
     pub max_retries: u32,
+    /// Decides which failures are retried and when; replaces `max_retries`,
+    /// `retry_on_slow` and `retry_when_reachable` when set
+    pub retry_policy: Option<Arc<dyn RetryPolicy>>,
+    /// Frame to send after a WebSocket handshake (None = handshake only)
+    pub ws_probe: Option<WsProbe>,
+    /// What to ask mail servers after their greeting (None = greeting only)
+    pub mail_probe: Option<MailProbe>,
+    /// Random delay in `[0, jitter)` before each job's first attempt,
+    /// spreading out the initial burst of requests
+    pub startup_jitter: Option<Duration>,
+    /// Count 4xx responses as failures in summaries and exit codes
+    pub treat_4xx_as_failure: bool,
+    /// Record response headers (and the derived cache status) on results
+    pub capture_headers: bool,
+    /// How much of the headers `capture_headers` keeps
+    pub header_limits: HeaderLimits,
+    /// Time DNS, connect, TLS, first byte and transfer separately for HTTP
+    /// checks (requires the `phase-timing` feature; redirects aren't followed)
+    pub detailed_timing: bool,
+    /// Keep the response body on results (not with `detailed_timing`)
+    pub capture_body: bool,
+    /// Record a hash of each response body on results (not with
+    /// `detailed_timing`)
+    pub hash_body: bool,
+    /// Text removed from bodies before hashing, e.g. rotating CSRF tokens
+    pub body_hash_ignore: Vec<Regex>,
+    /// Fail responses whose body is larger than this, as sent or once
+    /// decoded; the download is aborted once the cap is passed
+    pub max_body_bytes: Option<u64>,
+    /// Codings to ask for in `Accept-Encoding` (none = no header, so
+    /// servers answer uncompressed); gzip and deflate bodies are decoded
+    pub accept_encoding: Vec<Encoding>,
+    /// Check a random subset of the (deduplicated) targets instead of all
+    pub sample: Option<SampleSpec>,
+    /// Retry negative checks that found their target reachable
+    pub retry_when_reachable: bool,
+    /// Retry checks that failed only for exceeding the target's
+    /// `max_response_time`
+    pub retry_on_slow: bool,
+    /// Source address for outgoing HTTP requests (not used by detailed
+    /// timing or WebSocket checks)
+    pub local_address: Option<IpAddr>,
+    /// Stamped on every result, e.g. `region=eu-west` to tell apart runs
+    /// from different places when merging them
+    pub labels: BTreeMap<String, String>,
+    /// Requests this pass may send, shared with whoever else holds a clone.
+    /// Targets left without one aren't checked and get no result (see
+    /// `RunSummary::budget_skipped`); retries stop once it runs out
+    pub request_budget: Option<RequestBudget>,
+    /// When failures of every target are expected; they're still checked,
+    /// but failures are marked `in_maintenance` instead of counting
+    pub maintenance: Vec<MaintenanceWindow>,
+    /// Method of HTTP checks (not CORS preflights or detailed timing)
+    pub method: HttpMethod,
+    /// Targets known to need GET in HEAD passes, shared with whoever else
+    /// holds a clone; continuous mode keeps one across passes
+    pub method_memory: Option<MethodMemory>,
+    /// Header (e.g. `X-Request-Id`) to send a fresh random id in with every
+    /// attempt of an HTTP check; the ids end up on the result
+    pub inject_request_id: Option<String>,
 }
 
 impl Default for MonitorConfig {
     fn default() -> Self {
         Self {
             worker_threads: 50,
+            worker_multiplier: 32,
             request_timeout: Duration::from_secs(5),
             max_retries: 0,
+            retry_policy: None,
+            ws_probe: None,
+            mail_probe: None,
+            startup_jitter: None,
+            treat_4xx_as_failure: true,
+            capture_headers: false,
+            header_limits: HeaderLimits::default(),
+            detailed_timing: false,
+            capture_body: false,
+            hash_body: false,
+            body_hash_ignore: Vec::new(),
+            max_body_bytes: None,
+            accept_encoding: Vec::new(),
+            sample: None,
+            retry_on_slow: false,
+            retry_when_reachable: false,
+            local_address: None,
+            labels: BTreeMap::new(),
+            request_budget: None,
+            maintenance: Vec::new(),
+            method: HttpMethod::Get,
+            method_memory: None,
+            inject_request_id: None,
+        }
+    }
+}
+
+/// What to send over a WebSocket once the handshake succeeds.
+/// The check waits for a pong (or any data frame for `Text`) within the timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WsProbe {
+    Ping,
+    Text(String),
+}
+
+/// Kind of check performed for a URL, picked from its scheme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Check {
+    /// Plain HTTP(S) GET
+    Http(String),
+    /// WebSocket handshake (`ws://` / `wss://`)
+    Ws(String),
+    /// SMTP greeting (`smtp://`, port 25 by default)
+    Smtp(String),
+    /// IMAP greeting (`imap://`, port 143 by default)
+    Imap(String),
+}
+
+impl Check {
+    pub fn from_url(url: &str) -> Self {
+        let scheme = url.split_once("://").map(|(s, _)| s.to_ascii_lowercase());
+        match scheme.as_deref() {
+            Some("ws") | Some("wss") => Check::Ws(url.to_string()),
+            Some("smtp") => Check::Smtp(url.to_string()),
+            Some("imap") => Check::Imap(url.to_string()),
+            _ => Check::Http(url.to_string()),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        match self {
+            Check::Http(url) | Check::Ws(url) | Check::Smtp(url) | Check::Imap(url) => url,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ShutdownState {
+    cancelled: AtomicBool,
+    /// Paired with `wake` so a cancel can't slip in between a waiter's check
+    /// and its wait
+    lock: Mutex<()>,
+    wake: Condvar,
+    /// Tokens made with [`Shutdown::child`], cancelled along with this one
+    children: Mutex<Vec<Weak<ShutdownState>>>,
+}
+
+impl ShutdownState {
+    fn cancel(&self) {
+        if self.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        {
+            let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+            self.wake.notify_all();
+        }
+        let children =
+            std::mem::take(&mut *self.children.lock().unwrap_or_else(PoisonError::into_inner));
+        for child in children.iter().filter_map(Weak::upgrade) {
+            child.cancel();
         }
     }
 }
@@ -44,172 +459,1083 @@ impl Default for MonitorConfig {
 /// Cancels new work and lets in-flight requests finish.
 #[derive(Clone, Default)]
 pub struct Shutdown {
-    cancelled: Arc<AtomicBool>,
+    state: Arc<ShutdownState>,
 }
 impl Shutdown {
     pub fn new() -> Self {
-        Self {
-            cancelled: Arc::new(AtomicBool::new(false)),
-        }
+        Self::default()
     }
     pub fn cancel(&self) {
-        self.cancelled.store(true, Ordering::SeqCst);
+        self.state.cancel();
     }
     pub fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::SeqCst)
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Sleep for up to `timeout`, waking as soon as the token is cancelled.
+    /// Returns true if it was cancelled (before or during the wait).
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let guard = self
+            .state
+            .lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let _ = self
+            .state
+            .wake
+            .wait_timeout_while(guard, timeout, |_| !self.is_cancelled());
+        self.is_cancelled()
+    }
+
+    /// A token that is cancelled along with this one but can also be
+    /// cancelled on its own, leaving this one untouched.
+    pub fn child(&self) -> Shutdown {
+        let child = Shutdown::new();
+        {
+            let mut children = self
+                .state
+                .children
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            children.retain(|c| c.strong_count() > 0);
+            children.push(Arc::downgrade(&child.state));
+        }
+        // Cancelled before the child was registered
+        if self.is_cancelled() {
+            child.cancel();
+        }
+        child
+    }
+}
+
+/// Clamp the requested worker count to what the machine can sensibly run
+/// and to the amount of work available. Returns a warning when the
+/// resource cap kicked in.
+fn effective_workers(requested: usize, multiplier: usize, jobs: usize) -> (usize, Option<String>) {
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    let cap = cpus.saturating_mul(multiplier.max(1));
+    let mut workers = requested.max(1);
+    let mut warning = None;
+    if workers > cap {
+        warning = Some(format!(
+            "requested {requested} workers, clamped to {cap} ({cpus} CPUs x {multiplier})"
+        ));
+        workers = cap;
+    }
+    (workers.min(jobs), warning)
+}
+
+/// How a worker settled a target.
+#[derive(Debug)]
+enum Settled {
+    Checked(Box<WebsiteStatus>),
+    /// Not checked for want of request budget; not a failure, as nothing
+    /// was sent
+    Skipped {
+        target_id: String,
+    },
+}
+
+impl From<WebsiteStatus> for Settled {
+    fn from(ws: WebsiteStatus) -> Self {
+        Settled::Checked(Box::new(ws))
     }
 }
 
 /// Internal job message
 #[derive(Debug, Clone)]
 struct Job {
-    url: String,
+    target: Arc<Target>,
     attempt: u32,
+    /// When the first attempt began, for [`RetryPolicy`]'s `elapsed`
+    first_started: Option<Instant>,
+    /// Request ids of the earlier attempts
+    request_ids: Vec<String>,
 }
 
-/// Perform a single HTTP GET and return the status code.
-fn fetch_status(client: &reqwest::blocking::Client, url: &str) -> Result<u16, String> {
-    let resp = client
-        .get(url)
-        .send()
-        .map_err(|e| format!("request error: {e}"))?;
+/// A queued job, taken lowest [`Target::priority`] first and in the order
+/// queued within a priority.
+struct Queued {
+    key: Reverse<(u8, u64)>,
+    job: Job,
+}
 
-    Ok(resp.status().as_u16())
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    jobs: BinaryHeap<Queued>,
+    /// Jobs queued so far, numbering them for the order within a priority
+    pushed: u64,
+    closed: bool,
+}
+
+/// The jobs of a pass, shared by its workers.
+#[derive(Default)]
+struct JobQueue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+impl JobQueue {
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// False once the queue is closed.
+    fn push(&self, job: Job) -> bool {
+        let mut state = self.lock();
+        if state.closed {
+            return false;
+        }
+        let key = Reverse((job.target.priority, state.pushed));
+        state.pushed += 1;
+        state.jobs.push(Queued { key, job });
+        self.ready.notify_one();
+        true
+    }
+
+    /// The next job, waiting up to `timeout` for one; `Disconnected` once
+    /// the queue is closed.
+    fn pop_timeout(&self, timeout: Duration) -> Result<Job, mpsc::RecvTimeoutError> {
+        let state = self.lock();
+        let (mut state, _) = self
+            .ready
+            .wait_timeout_while(state, timeout, |s| s.jobs.is_empty() && !s.closed)
+            .unwrap_or_else(PoisonError::into_inner);
+        if state.closed {
+            return Err(mpsc::RecvTimeoutError::Disconnected);
+        }
+        state
+            .jobs
+            .pop()
+            .map(|queued| queued.job)
+            .ok_or(mpsc::RecvTimeoutError::Timeout)
+    }
+
+    fn close(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        state.jobs.clear();
+        self.ready.notify_all();
+    }
+}
+
+/// Jobs of a pass not yet settled: queued, in a worker's hands or waiting
+/// out a retry delay. Settling the last one ends the pass, so workers stop
+/// once the work is done whether or not anyone reads the results.
+struct Outstanding {
+    jobs: AtomicUsize,
+    /// Closed when the pass ends, so a worker waiting on it wakes at once
+    queue: JobQueue,
+    pass_done: Shutdown,
+}
+
+impl Outstanding {
+    fn settle(&self) {
+        if self.jobs.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.close();
+        }
+    }
+
+    /// Queue `job` again, at its target's priority; false once the pass is
+    /// over.
+    fn requeue(&self, job: Job) -> bool {
+        self.queue.push(job)
+    }
+
+    fn close(&self) {
+        self.pass_done.cancel();
+        self.queue.close();
+    }
+}
+
+/// The job a worker has in hand, settled when dropped unless it was queued
+/// again for a retry. A worker panicking with it still reports its target
+/// as failed, so the pass gets a result for every target rather than
+/// waiting forever for this one.
+struct InHand<'a> {
+    target: Arc<Target>,
+    results: &'a mpsc::Sender<Settled>,
+    outstanding: &'a Outstanding,
+    requeued: bool,
+}
+
+impl Drop for InHand<'_> {
+    fn drop(&mut self) {
+        if self.requeued {
+            return;
+        }
+        if thread::panicking() {
+            let _ = self.results.send(
+                WebsiteStatus::for_target(
+                    &self.target,
+                    Err("worker panicked".into()),
+                    Duration::ZERO,
+                )
+                .into(),
+            );
+        }
+        self.outstanding.settle();
+    }
+}
+
+/// What a successful check brings back.
+#[derive(Debug)]
+struct Fetched {
+    code: u16,
+    headers: Option<headers::CapturedHeaders>,
+    compression: Option<Compression>,
+    phases: Option<PhaseTimings>,
+    body: Option<Vec<u8>>,
+    body_hash: Option<String>,
+    banner: Option<String>,
+    /// Only kept for targets that don't follow redirects
+    location: Option<String>,
+}
+
+impl From<u16> for Fetched {
+    fn from(code: u16) -> Self {
+        Self {
+            code,
+            headers: None,
+            compression: None,
+            phases: None,
+            body: None,
+            body_hash: None,
+            banner: None,
+            location: None,
+        }
+    }
+}
+
+impl From<mail::MailCheck> for Fetched {
+    fn from(check: mail::MailCheck) -> Self {
+        Self {
+            banner: Some(check.banner),
+            ..Self::from(check.code)
+        }
+    }
+}
+
+/// Send `req`, telling timeouts apart from other failures.
+fn send(
+    req: reqwest::blocking::RequestBuilder,
+    timeout: Duration,
+) -> Result<reqwest::blocking::Response, CheckError> {
+    req.send().map_err(|e| {
+        if e.is_timeout() {
+            CheckError::TimedOut { limit: timeout }
+        } else {
+            CheckError::Transport(format!("request error: {e}"))
+        }
+    })
+}
+
+/// `fetch_status` with `config.method`. In HEAD passes a target whose server
+/// refuses HEAD or doesn't answer it is tried again with GET, and if GET
+/// works it's remembered in `config.method_memory` to skip HEAD next time.
+/// That GET is a second request, so it's only sent if the request budget
+/// has room; otherwise the HEAD result stands. Also gives the method the
+/// result came from, in HEAD passes.
+fn fetch_with_method(
+    client: &reqwest::blocking::Client,
+    target: &Target,
+    config: &MonitorConfig,
+    request_id: Option<(&str, &str)>,
+) -> (Result<Fetched, CheckError>, Option<HttpMethod>) {
+    if config.method == HttpMethod::Get {
+        return (
+            fetch_status(client, target, config, HttpMethod::Get, request_id),
+            None,
+        );
+    }
+    let memory = config.method_memory.as_ref();
+    let id = target.id();
+    if !memory.is_some_and(|m| m.needs_get(&id)) {
+        let head = fetch_status(client, target, config, HttpMethod::Head, request_id);
+        if !method::mishandles_head(head.as_ref().map(|f| f.code))
+            || !config
+                .request_budget
+                .as_ref()
+                .is_none_or(RequestBudget::try_take)
+        {
+            return (head, Some(HttpMethod::Head));
+        }
+    }
+    let get = fetch_status(client, target, config, HttpMethod::Get, request_id);
+    if let Some(memory) = memory
+        && get
+            .as_ref()
+            .is_ok_and(|f| !method::mishandles_head(Ok(f.code)))
+    {
+        memory.remember_get(&id);
+    }
+    (get, Some(HttpMethod::Get))
+}
+
+/// Perform a single HTTP request and return the status code (plus headers
+/// and body if asked). `request_id` is a header name and value to send
+/// along.
+fn fetch_status(
+    client: &reqwest::blocking::Client,
+    target: &Target,
+    config: &MonitorConfig,
+    method: HttpMethod,
+    request_id: Option<(&str, &str)>,
+) -> Result<Fetched, CheckError> {
+    let timeout = config.request_timeout;
+    let mut req = client.request(method.as_reqwest(), &target.url);
+    // Host can't go in default headers; it has to be set per request.
+    if let Some(host) = &target.host_header {
+        req = req.header(reqwest::header::HOST, host);
+    }
+    for (name, value) in target.request_headers() {
+        req = req.header(name, value.as_ref());
+    }
+    if let Some(accept) = compression::accept_encoding(&config.accept_encoding) {
+        req = req.header(reqwest::header::ACCEPT_ENCODING, accept);
+    }
+    if let Some((name, id)) = request_id {
+        req = req.header(name, id);
+    }
+    let resp = send(req, timeout)?;
+
+    let headers = config
+        .capture_headers
+        .then(|| headers::capture_map(resp.headers(), config.header_limits));
+    let encoding = resp
+        .headers()
+        .get(reqwest::header::CONTENT_ENCODING)
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned());
+    if target.require_compressed && compression::is_identity(encoding.as_deref()) {
+        return Err(CheckError::Uncompressed);
+    }
+    let content_length = resp.content_length();
+
+    let code = resp.status().as_u16();
+    let location = resp
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .filter(|_| target.redirect_policy.follow_limit().is_none())
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned());
+    if let Some(limit) = config.max_body_bytes
+        && let Some(len) = resp.content_length().filter(|&len| len > limit)
+    {
+        // Dropping the response closes the connection without reading on.
+        return Err(CheckError::BodyTooLarge {
+            limit,
+            observed_at_least: len,
+        });
+    }
+    let keep = config.capture_body || config.hash_body;
+    let limit = config.max_body_bytes.unwrap_or(u64::MAX);
+    let body = if keep || config.max_body_bytes.is_some() {
+        body::read_capped(resp, limit, keep, timeout)?
+    } else {
+        None
+    };
+    let wire_bytes = content_length.or(body.as_ref().map(|b| b.len() as u64));
+    // Keep the decoded body; one that can't be decoded is kept as sent. The
+    // limit applies after decoding too, or a small gzip could fill memory.
+    let decoded = body
+        .as_deref()
+        .and_then(|b| compression::decode(encoding.as_deref(), b, limit));
+    if let Some(len) = decoded
+        .as_ref()
+        .map(|b| b.len() as u64)
+        .filter(|&len| len > limit)
+    {
+        return Err(CheckError::BodyTooLarge {
+            limit,
+            observed_at_least: len,
+        });
+    }
+    let compression = config.capture_headers.then(|| Compression {
+        encoding,
+        wire_bytes,
+        decoded_bytes: decoded.as_ref().map(|b| b.len() as u64),
+    });
+
+    let body = decoded.or(body);
+    let body_hash = body
+        .as_deref()
+        .filter(|_| config.hash_body)
+        .map(|b| body_hash(b, &config.body_hash_ignore));
+
+    Ok(Fetched {
+        code,
+        headers,
+        compression,
+        phases: None,
+        body: body.filter(|_| config.capture_body),
+        body_hash,
+        banner: None,
+        location,
+    })
+}
+
+/// Hold a check's outcome against the target's expectations: `Ok` is the
+/// finished result, `Err` a failure the caller may retry.
+fn settle(
+    target: &Target,
+    result: Result<Fetched, CheckError>,
+    elapsed: Duration,
+) -> Result<WebsiteStatus, CheckError> {
+    if target.expects_down() {
+        let mut ws = match result {
+            Ok(fetched) if Some(fetched.code) == target.expect_status => {
+                WebsiteStatus::from_fetch(target, fetched, elapsed)
+            }
+            Ok(fetched) => {
+                return Err(CheckError::UnexpectedlyReachable {
+                    status: fetched.code,
+                });
+            }
+            Err(err) => WebsiteStatus::for_target(target, Err(err), elapsed),
+        };
+        ws.expected_down = true;
+        return Ok(ws);
+    }
+    if let RedirectPolicy::ExpectRedirectTo(expected) = &target.redirect_policy
+        && let Ok(fetched) = &result
+    {
+        let to = fetched
+            .location
+            .as_deref()
+            .map(|location| absolute_location(&target.url, location));
+        let redirected = (300..400).contains(&fetched.code)
+            && to.is_some_and(|to| to.starts_with(expected.as_str()));
+        if !redirected {
+            return Err(CheckError::WrongRedirect {
+                status: fetched.code,
+                location: fetched.location.clone(),
+                expected: expected.clone(),
+            });
+        }
+    }
+    match target.max_response_time {
+        Some(limit) if result.is_ok() && elapsed > limit => Err(CheckError::TooSlow {
+            limit,
+            actual: elapsed,
+        }),
+        _ => result.map(|fetched| WebsiteStatus::from_fetch(target, fetched, elapsed)),
+    }
+}
+
+/// `location` as an absolute URL, taking relative ones from `base`.
+fn absolute_location(base: &str, location: &str) -> String {
+    url::Url::parse(base)
+        .and_then(|base| base.join(location))
+        .map_or_else(|_| location.to_string(), String::from)
+}
+
+/// What a worker's HTTP clients differ by: source address, the host and
+/// address of a resolve override, and how many redirects to follow.
+type ClientKey = (Option<IpAddr>, Option<(String, SocketAddr)>, Option<usize>);
+
+fn build_client(
+    config: &MonitorConfig,
+    (local_address, resolve, follow): &ClientKey,
+) -> reqwest::blocking::Client {
+    let redirect = match follow {
+        Some(max) => reqwest::redirect::Policy::limited(*max),
+        None => reqwest::redirect::Policy::none(),
+    };
+    let builder = reqwest::blocking::Client::builder()
+        .timeout(config.request_timeout)
+        .redirect(redirect)
+        .local_address(*local_address);
+    match resolve {
+        Some((host, addr)) => builder.resolve(host, *addr),
+        None => builder,
+    }
+    .build()
+    .expect("failed to build reqwest client")
+}
+
+/// Whether outgoing connections can be made from `addr`, i.e. it's an
+/// address of this host.
+pub fn check_local_address(addr: IpAddr) -> io::Result<()> {
+    TcpListener::bind((addr, 0)).map(drop)
+}
+
+/// Run whichever check the URL scheme calls for, sending `request_id` (a
+/// header name and value) with HTTP requests.
+/// Also gives the method used, for HTTP checks in HEAD passes.
+fn run_check(
+    client: &reqwest::blocking::Client,
+    target: &Target,
+    config: &MonitorConfig,
+    request_id: Option<(&str, &str)>,
+) -> (Result<Fetched, CheckError>, Option<HttpMethod>) {
+    let result = match &Check::from_url(&target.url) {
+        Check::Http(_) if let Some(expect) = &target.cors_expect => {
+            cors::preflight(client, target, expect, config, request_id)
+        }
+        #[cfg(feature = "phase-timing")]
+        Check::Http(_) if config.detailed_timing => timing::timed_fetch(target, config, request_id),
+        #[cfg(not(feature = "phase-timing"))]
+        Check::Http(_) if config.detailed_timing => {
+            Err("detailed timing requires the `phase-timing` feature".into())
+        }
+        Check::Http(_) => return fetch_with_method(client, target, config, request_id),
+        #[cfg(feature = "tungstenite")]
+        Check::Ws(url) => {
+            ws::check_ws(url, config.request_timeout, config.ws_probe.as_ref()).map(Fetched::from)
+        }
+        #[cfg(not(feature = "tungstenite"))]
+        Check::Ws(_) => {
+            let _ = config;
+            Err("websocket checks require the `tungstenite` feature".into())
+        }
+        Check::Smtp(url) => mail::check_mail(
+            mail::MailProtocol::Smtp,
+            url,
+            config.request_timeout,
+            config.mail_probe.as_ref(),
+        )
+        .map(Fetched::from),
+        Check::Imap(url) => mail::check_mail(
+            mail::MailProtocol::Imap,
+            url,
+            config.request_timeout,
+            config.mail_probe.as_ref(),
+        )
+        .map(Fetched::from),
+    };
+    (result, None)
 }
 
 /// Core monitoring function.
 pub fn monitor_websites(
     urls: Vec<String>,
-    mut config: MonitorConfig,
+    config: MonitorConfig,
     shutdown: Option<Shutdown>,
 ) -> Vec<WebsiteStatus> {
-    if urls.is_empty() {
-        return Vec::new();
+    run_pass(urls, config, shutdown).results
+}
+
+/// Run a single pass and return the results with their summary.
+pub fn run_pass<T: Into<Target>>(
+    targets: Vec<T>,
+    config: MonitorConfig,
+    shutdown: Option<Shutdown>,
+) -> RunReport {
+    let usage = usage::UsageProbe::start();
+    let mut summary = RunSummary::new(config.treat_4xx_as_failure);
+    let mut iter = MonitorIter::start(targets, config, shutdown);
+    let results: Vec<WebsiteStatus> = iter.by_ref().inspect(|ws| summary.record(ws)).collect();
+    finish_pass(iter, usage, summary, results)
+}
+
+/// Like [`run_pass`], resuming from `checkpoint`: URLs it already holds
+/// aren't checked again (their saved results are reported instead), and
+/// every new result is appended to it.
+///
+/// A failing checkpoint write doesn't stop the pass; it ends up in the
+/// summary warnings and checkpointing stops.
+pub fn run_pass_checkpointed<T: Into<Target>>(
+    targets: Vec<T>,
+    config: MonitorConfig,
+    shutdown: Option<Shutdown>,
+    checkpoint: &mut Checkpoint,
+) -> RunReport {
+    let usage = usage::UsageProbe::start();
+    let mut summary = RunSummary::new(config.treat_4xx_as_failure);
+    let mut results = Vec::new();
+    let mut todo = Vec::new();
+    let mut resumed = HashSet::new();
+    for target in targets.into_iter().map(Into::<Target>::into) {
+        match checkpoint.saved(&target.id()) {
+            Some(ws) => {
+                if resumed.insert(ws.id().to_string()) {
+                    summary.record(ws);
+                    results.push(ws.clone());
+                }
+            }
+            None => todo.push(target),
+        }
     }
 
-    if config.worker_threads == 0 {
-        config.worker_threads = 1;
+    let mut write_error = None;
+    let mut iter = MonitorIter::start(todo, config, shutdown);
+    for ws in iter.by_ref() {
+        if write_error.is_none()
+            && let Err(e) = checkpoint.record(&ws)
+        {
+            write_error = Some(e);
+        }
+        summary.record(&ws);
+        results.push(ws);
+    }
+    if let Some(e) = write_error.or_else(|| checkpoint.flush().err()) {
+        iter.warnings.push(format!("checkpoint not updated: {e}"));
+    }
+    finish_pass(iter, usage, summary, results)
+}
+
+/// The report for a pass whose results have all been taken from `iter`,
+/// with what the pass learned along the way added to `summary`.
+fn finish_pass(
+    mut iter: MonitorIter,
+    usage: usage::UsageProbe,
+    mut summary: RunSummary,
+    results: Vec<WebsiteStatus>,
+) -> RunReport {
+    summary.effective_workers = iter.effective_workers();
+    summary.resources = Some(usage.finish(iter.effective_workers()));
+    summary.sampled = iter.sampled();
+    summary.budget_skipped = iter.budget_skipped();
+    summary.warnings = std::mem::take(&mut iter.warnings);
+    let workers = std::mem::take(&mut iter.worker_stats);
+    RunReport {
+        results,
+        summary,
+        workers,
     }
-    config.worker_threads = config.worker_threads.min(urls.len());
+}
 
-    let shutdown = shutdown.unwrap_or_else(Shutdown::new);
+/// Start a pass and yield its results as they arrive.
+///
+/// Exhausting the iterator gives the same results as [`monitor_websites`];
+/// dropping it early cancels the pass and joins the workers. Fails if
+/// `config.local_address` can't be bound or not a single worker thread could
+/// be started.
+pub fn monitor_iter<T: Into<Target>>(
+    targets: Vec<T>,
+    config: MonitorConfig,
+) -> io::Result<MonitorIter> {
+    let mut iter = MonitorIter::start(targets, config, None);
+    if let Some(e) = iter.bind_error.take() {
+        return Err(e);
+    }
+    match iter.spawn_error.take() {
+        Some(e) if iter.workers.is_empty() => Err(e),
+        _ => Ok(iter),
+    }
+}
 
-    let (job_tx, job_rx) = mpsc::channel::<Job>();
-    let (res_tx, res_rx) = mpsc::channel::<WebsiteStatus>();
+/// Results of a running pass, one per unique URL, in completion order.
+/// See [`monitor_iter`].
+///
+/// No thread outlives the pass: workers stop once every job is settled (or
+/// the pass is cancelled), and are all joined when the iterator is
+/// exhausted or dropped, however the pass ended.
+pub struct MonitorIter {
+    results: mpsc::Receiver<Settled>,
+    seen: HashSet<String>,
+    expected: usize,
+    /// Child of the caller's token, also cancelled once the pass is over
+    /// (exhausted or dropped) so workers stop without cancelling the caller's
+    pass_done: Shutdown,
+    workers: Vec<thread::JoinHandle<WorkerStats>>,
+    /// Stats of the workers joined so far
+    worker_stats: Vec<WorkerStats>,
+    effective_workers: usize,
+    sampled: Option<Sampled>,
+    warnings: Vec<String>,
+    spawn_error: Option<io::Error>,
+    /// `MonitorConfig::local_address` couldn't be bound
+    bind_error: Option<io::Error>,
+    labels: BTreeMap<String, String>,
+    budget_skipped: usize,
+    outstanding: Option<Arc<Outstanding>>,
+}
 
-    // Enqueue initial jobs
-    for url in &urls {
-        let _ = job_tx.send(Job {
-            url: url.clone(),
-            attempt: 0,
+impl MonitorIter {
+    fn start<T: Into<Target>>(
+        targets: Vec<T>,
+        mut config: MonitorConfig,
+        shutdown: Option<Shutdown>,
+    ) -> Self {
+        let mut targets: Vec<Arc<Target>> =
+            targets.into_iter().map(|t| Arc::new(t.into())).collect();
+        let sampled = config.sample.map(|spec| {
+            let mut seen = HashSet::new();
+            targets.retain(|t| seen.insert(t.id().into_owned()));
+            let supplied = targets.len();
+            targets = spec.pick(std::mem::take(&mut targets));
+            Sampled {
+                selected: targets.len(),
+                supplied,
+            }
         });
-    }
-    // Share the receiver among workers
-    let job_rx = Arc::new(Mutex::new(job_rx));
-
-    // Spawn workers
-    let mut workers = Vec::with_capacity(config.worker_threads);
-    for _ in 0..config.worker_threads {
-        let jobs_shared = Arc::clone(&job_rx);
-        let results = res_tx.clone();
-        let job_tx_retry = job_tx.clone();
-        let shutdown_clone = shutdown.clone();
-        let timeout = config.request_timeout;
-        let max_retries = config.max_retries;
-
-        let client = reqwest::blocking::Client::builder()
-            .timeout(timeout)
-            .redirect(reqwest::redirect::Policy::limited(5))
-            .build()
-            .expect("failed to build reqwest client");
-
-        workers.push(thread::spawn(move || {
-            loop {
-                if shutdown_clone.is_cancelled() {
-                    break;
+        // One result per unique target
+        let expected = targets.iter().map(|t| t.id()).collect::<HashSet<_>>().len();
+
+        let (res_tx, res_rx) = mpsc::channel::<Settled>();
+        let mut iter = Self {
+            results: res_rx,
+            seen: HashSet::with_capacity(expected),
+            expected,
+            pass_done: Shutdown::new(),
+            workers: Vec::new(),
+            worker_stats: Vec::new(),
+            effective_workers: 0,
+            sampled,
+            warnings: Vec::new(),
+            spawn_error: None,
+            bind_error: None,
+            labels: config.labels.clone(),
+            budget_skipped: 0,
+            outstanding: None,
+        };
+        if targets.is_empty() {
+            return iter;
+        }
+
+        let (workers_wanted, clamp_warning) = effective_workers(
+            config.worker_threads,
+            config.worker_multiplier,
+            targets.len(),
+        );
+        config.worker_threads = workers_wanted;
+        iter.warnings.extend(clamp_warning);
+
+        let shutdown = shutdown.unwrap_or_default();
+        iter.pass_done = shutdown.child();
+
+        let queue = JobQueue::default();
+
+        // Source addresses that can't be bound fail their targets up front
+        let mut unbindable = HashMap::new();
+        let addresses: HashSet<IpAddr> = targets
+            .iter()
+            .filter_map(|t| t.local_address.or(config.local_address))
+            .collect();
+        for addr in addresses {
+            if let Err(e) = check_local_address(addr) {
+                iter.warnings
+                    .push(format!("cannot bind to local address {addr}: {e}"));
+                if config.local_address == Some(addr) {
+                    iter.bind_error = Some(e);
                 }
+                unbindable.insert(
+                    addr,
+                    format!("setup error: cannot bind to local address {addr}"),
+                );
+            }
+        }
 
-                // Poll the shared receiver with a short timeout so we can notice shutdown.
-                let job_opt = {
-                    let rx_guard = jobs_shared.lock().expect("poisoned receiver mutex");
-                    rx_guard.recv_timeout(Duration::from_millis(100)).ok()
-                };
-
-                let Some(job) = job_opt else {
-                    // timeout or channel closed; if channel closed, we’re done
-                    // check if all senders are gone (recv_timeout Err::Disconnected)
-                    // We can detect it by trying again immediately; but simplest:
-                    // if there are no more senders AND queue is empty, all workers will get None repeatedly.
-                    // We’ll break when shutdown is requested or when no more jobs will ever arrive.
-                    // To avoid spin, sleep a touch.
-                    if shutdown_clone.is_cancelled() {
-                        break;
-                    }
-                    // If channel is actually disconnected, future recv_timeout will always Err,
-                    // but we'll still loop and exit after not receiving any new URLs and seen-count completes upstream.
-                    continue;
-                };
-
-                let start = Instant::now();
-                let result = fetch_status(&client, &job.url);
-                let elapsed = start.elapsed();
-
-                match result {
-                    Ok(code) => {
-                        let _ = results.send(WebsiteStatus {
-                            url: job.url,
-                            status: Ok(code),
-                            response_time: elapsed,
-                            timestamp: Utc::now(),
-                        });
-                    }
-                    Err(err) => {
-                        if !shutdown_clone.is_cancelled() && job.attempt < max_retries {
-                            // Light backoff
-                            let backoff = Duration::from_millis(100 * (job.attempt as u64 + 1));
-                            thread::sleep(backoff);
-                            let _ = job_tx_retry.send(Job {
-                                url: job.url,
-                                attempt: job.attempt + 1,
+        // Enqueue initial jobs
+        let mut queued = 0;
+        for target in &targets {
+            if let Some(err) = target
+                .local_address
+                .or(config.local_address)
+                .and_then(|addr| unbindable.get(&addr))
+            {
+                let _ = res_tx.send(
+                    WebsiteStatus::for_target(target, Err(err.clone().into()), Duration::ZERO)
+                        .into(),
+                );
+                continue;
+            }
+            queue.push(Job {
+                target: Arc::clone(target),
+                attempt: 0,
+                first_started: None,
+                request_ids: Vec::new(),
+            });
+            queued += 1;
+        }
+        let outstanding = Arc::new(Outstanding {
+            jobs: AtomicUsize::new(queued),
+            queue,
+            pass_done: iter.pass_done.clone(),
+        });
+        if queued == 0 {
+            outstanding.close();
+        }
+        iter.outstanding = Some(Arc::clone(&outstanding));
+
+        let policy: Arc<dyn RetryPolicy> = match &config.retry_policy {
+            Some(policy) => Arc::clone(policy),
+            None => Arc::new(ConfigRetry {
+                max_retries: config.max_retries,
+                retry_on_slow: config.retry_on_slow,
+                retry_when_reachable: config.retry_when_reachable,
+            }),
+        };
+
+        // Spawn workers
+        let pool_size = config.worker_threads;
+        iter.workers.reserve(pool_size);
+        for worker_id in 0..pool_size {
+            let results = res_tx.clone();
+            let shutdown_clone = shutdown.clone();
+            let pass_done = iter.pass_done.clone();
+            let outstanding = Arc::clone(&outstanding);
+            let config = config.clone();
+            let policy = Arc::clone(&policy);
+
+            let spawned = thread::Builder::new()
+                .name(format!("monitor-worker-{worker_id}"))
+                .spawn(move || {
+                    let mut stats = WorkerStats::new(worker_id);
+                    // One client per source address, resolve override and
+                    // redirect limit; almost always just one
+                    let mut clients: HashMap<ClientKey, reqwest::blocking::Client> = HashMap::new();
+                    // Start of the job in hand, closed out when the next wait begins
+                    let mut busy_since: Option<Instant> = None;
+                    loop {
+                        if let Some(since) = busy_since.take() {
+                            stats.busy += since.elapsed();
+                        }
+                        if pass_done.is_cancelled() {
+                            break;
+                        }
+
+                        // Wait on the queue with a short timeout so we can notice shutdown.
+                        let waiting_since = Instant::now();
+                        let job_opt = outstanding.queue.pop_timeout(Duration::from_millis(100));
+                        stats.idle += waiting_since.elapsed();
+
+                        let job = match job_opt {
+                            Ok(job) => job,
+                            // Nothing queued right now; loop to re-check shutdown / completion
+                            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                            // Queue closed: no more jobs will ever arrive
+                            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                        };
+                        stats.jobs += 1;
+                        busy_since = Some(Instant::now());
+                        let mut in_hand = InHand {
+                            target: Arc::clone(&job.target),
+                            results: &results,
+                            outstanding: &outstanding,
+                            requeued: false,
+                        };
+
+                        // Retries were paid for when they were queued
+                        if job.attempt == 0
+                            && let Some(budget) = &config.request_budget
+                            && !budget.try_take()
+                        {
+                            let _ = results.send(Settled::Skipped {
+                                target_id: job.target.id().into_owned(),
                             });
+                            continue;
+                        }
+
+                        if job.attempt == 0
+                            && let Some(jitter) = config.startup_jitter.filter(|j| !j.is_zero())
+                        {
+                            let delay = rand::rng().random_range(Duration::ZERO..jitter);
+                            if pass_done.wait_timeout(delay) {
+                                break;
+                            }
+                        }
+
+                        let local_address = job.target.local_address.or(config.local_address);
+                        let client = clients
+                            .entry((
+                                local_address,
+                                job.target.resolve_override(),
+                                job.target.redirect_policy.follow_limit(),
+                            ))
+                            .or_insert_with_key(|key| build_client(&config, key));
+                        let request_id = config
+                            .inject_request_id
+                            .as_deref()
+                            .filter(|_| matches!(Check::from_url(&job.target.url), Check::Http(_)))
+                            .map(|header| (header, request_id::new_request_id()));
+                        let start = Instant::now();
+                        let (result, method_used) = run_check(
+                            client,
+                            &job.target,
+                            &config,
+                            request_id
+                                .as_ref()
+                                .map(|(header, id)| (*header, id.as_str())),
+                        );
+                        let elapsed = start.elapsed();
+
+                        let first_started = job.first_started.unwrap_or(start);
+
+                        let mut ws = settle(&job.target, result, elapsed).unwrap_or_else(|err| {
+                            WebsiteStatus::for_target(&job.target, Err(err), elapsed)
+                        });
+                        ws.retries = job.attempt;
+                        ws.method_used = method_used;
+                        ws.request_ids = job.request_ids;
+                        if let Some((_, id)) = request_id {
+                            ws.request_ids.push(id.clone());
+                            ws.request_id = Some(id);
+                        }
+                        let retry = if ws.is_success(config.treat_4xx_as_failure)
+                            || shutdown_clone.is_cancelled()
+                        {
+                            None
                         } else {
-                            let _ = results.send(WebsiteStatus {
-                                url: job.url,
-                                status: Err(err),
+                            let outcome = CheckOutcome {
+                                target: &job.target,
+                                status: &ws.status,
                                 response_time: elapsed,
-                                timestamp: Utc::now(),
-                            });
+                            };
+                            policy
+                                .should_retry(&outcome, job.attempt, first_started.elapsed())
+                                .filter(|_| {
+                                    config
+                                        .request_budget
+                                        .as_ref()
+                                        .is_none_or(RequestBudget::try_take)
+                                })
+                        };
+                        match retry {
+                            Some(delay) => {
+                                if pass_done.wait_timeout(delay) {
+                                    break;
+                                }
+                                in_hand.requeued = outstanding.requeue(Job {
+                                    target: job.target,
+                                    attempt: job.attempt + 1,
+                                    first_started: Some(first_started),
+                                    request_ids: ws.request_ids,
+                                });
+                                stats.retries += usize::from(in_hand.requeued);
+                            }
+                            None => {
+                                ws.in_maintenance = !ws.is_success(config.treat_4xx_as_failure)
+                                    && in_maintenance(
+                                        job.target.maintenance.iter().chain(&config.maintenance),
+                                        ws.timestamp,
+                                    );
+                                let _ = results.send(ws.into());
+                            }
                         }
                     }
+                    if let Some(since) = busy_since {
+                        stats.busy += since.elapsed();
+                    }
+                    stats
+                });
+            match spawned {
+                Ok(handle) => iter.workers.push(handle),
+                Err(e) => {
+                    // Out of threads: carry on with the pool we have.
+                    iter.warnings.push(format!(
+                        "could only start {} of {pool_size} workers: {e}",
+                        iter.workers.len()
+                    ));
+                    iter.spawn_error = Some(e);
+                    break;
                 }
             }
-        }));
+        }
+        iter.effective_workers = iter.workers.len();
+
+        if iter.workers.is_empty() {
+            // Nothing can run; report every target as failed rather than hang.
+            for target in &targets {
+                let _ = res_tx.send(
+                    WebsiteStatus::for_target(
+                        target,
+                        Err("no worker threads could be started".into()),
+                        Duration::ZERO,
+                    )
+                    .into(),
+                );
+            }
+        }
+
+        // Drop our sender so the channel closes once the workers are gone
+        drop(res_tx);
+        iter
     }
 
-    // Drop main’s extra senders so the channel closes when workers finish retrying
-    drop(job_tx);
-    drop(res_tx);
+    /// Worker threads that were actually started for this pass.
+    pub fn effective_workers(&self) -> usize {
+        self.effective_workers
+    }
 
-    // Collect results: one per unique URL
-    let mut seen = HashSet::with_capacity(urls.len());
-    let mut out = Vec::with_capacity(urls.len());
+    /// How many targets were picked, when the config asked for a sample.
+    pub fn sampled(&self) -> Option<Sampled> {
+        self.sampled
+    }
 
-    while seen.len() < urls.len() {
-        match res_rx.recv() {
-            Ok(ws) => {
-                if seen.insert(ws.url.clone()) {
-                    out.push(ws);
-                }
+    /// Targets left unchecked, without a result, because
+    /// `MonitorConfig::request_budget` ran out; final once the pass is over.
+    pub fn budget_skipped(&self) -> usize {
+        self.budget_skipped
+    }
+
+    /// Setup problems that didn't stop the pass (e.g. worker clamping).
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// What each worker did, once the pass is over and they've been joined.
+    pub fn worker_stats(&self) -> &[WorkerStats] {
+        &self.worker_stats
+    }
+
+    /// Stop the workers and wait for them; in-flight requests run to completion.
+    fn finish(&mut self) {
+        self.pass_done.cancel();
+        if let Some(outstanding) = &self.outstanding {
+            outstanding.close();
+        }
+        for w in self.workers.drain(..) {
+            match w.join() {
+                Ok(stats) => self.worker_stats.push(stats),
+                Err(_) => self
+                    .warnings
+                    .push("a worker thread panicked; its target was reported as failed".into()),
             }
-            Err(_) => break, // all senders dropped
         }
     }
+}
+
+impl Iterator for MonitorIter {
+    type Item = WebsiteStatus;
 
-    for w in workers {
-        let _ = w.join();
+    fn next(&mut self) -> Option<WebsiteStatus> {
+        while self.seen.len() < self.expected {
+            match self.results.recv() {
+                Ok(Settled::Checked(mut ws)) => {
+                    if self.seen.insert(ws.target_id.clone()) {
+                        ws.labels.clone_from(&self.labels);
+                        return Some(*ws);
+                    }
+                }
+                Ok(Settled::Skipped { target_id }) => {
+                    if self.seen.insert(target_id) {
+                        self.budget_skipped += 1;
+                    }
+                }
+                Err(_) => break, // all senders dropped
+            }
+        }
+        // Everything is collected; let the workers wind down.
+        self.finish();
+        None
     }
+}
 
-    out
+impl Drop for MonitorIter {
+    fn drop(&mut self) {
+        self.finish();
+    }
 }
 
 /// Convenience: run a single pass with defaults and no shutdown handle.
@@ -227,6 +1553,17 @@ mod unit_tests {
         assert_eq!(cfg.request_timeout, Duration::from_secs(5));
     }
 
+    #[test]
+    fn worker_count_is_clamped() {
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        assert_eq!(effective_workers(0, 4, 10), (1, None));
+        assert_eq!(effective_workers(8, 1000, 3), (3, None));
+
+        let (n, warning) = effective_workers(1_000_000, 2, usize::MAX);
+        assert_eq!(n, cpus * 2);
+        assert!(warning.unwrap().contains("clamped"));
+    }
+
     #[test]
     fn shutdown_flag_works() {
         let s = Shutdown::new();
@@ -234,4 +1571,68 @@ mod unit_tests {
         s.cancel();
         assert!(s.is_cancelled());
     }
+
+    #[test]
+    fn wait_timeout_wakes_on_cancel() {
+        let s = Shutdown::new();
+        assert!(!s.wait_timeout(Duration::from_millis(20)));
+
+        let canceller = {
+            let s = s.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                s.cancel();
+            })
+        };
+        let start = Instant::now();
+        assert!(s.wait_timeout(Duration::from_secs(10)));
+        let waited = start.elapsed();
+        assert!(waited < Duration::from_millis(500), "woke after {waited:?}");
+        canceller.join().unwrap();
+        // Already cancelled: returns at once
+        assert!(s.wait_timeout(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn children_follow_their_parent_but_not_the_other_way() {
+        let parent = Shutdown::new();
+        let child = parent.child();
+        child.cancel();
+        assert!(!parent.is_cancelled());
+
+        let child = parent.child();
+        let waiter = {
+            let child = child.clone();
+            thread::spawn(move || child.wait_timeout(Duration::from_secs(10)))
+        };
+        thread::sleep(Duration::from_millis(20));
+        parent.cancel();
+        assert!(waiter.join().unwrap());
+        assert!(child.is_cancelled());
+        assert!(parent.child().is_cancelled());
+    }
+
+    #[test]
+    fn check_kind_follows_scheme() {
+        assert_eq!(
+            Check::from_url("wss://example.com/socket"),
+            Check::Ws("wss://example.com/socket".into())
+        );
+        assert_eq!(
+            Check::from_url("WS://example.com"),
+            Check::Ws("WS://example.com".into())
+        );
+        assert_eq!(
+            Check::from_url("https://example.com"),
+            Check::Http("https://example.com".into())
+        );
+        assert_eq!(
+            Check::from_url("smtp://mx.example.com:587"),
+            Check::Smtp("smtp://mx.example.com:587".into())
+        );
+        assert_eq!(
+            Check::from_url("IMAP://mail.example.com"),
+            Check::Imap("IMAP://mail.example.com".into())
+        );
+    }
 }
@@ -0,0 +1,395 @@
+//! Mail server checks for `smtp://` and `imap://` targets: connect, read the
+//! greeting and optionally ask for the server's capabilities, upgrading the
+//! connection with STARTTLS first when asked to (that part needs the
+//! `starttls` feature).
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use url::Url;
+
+use crate::CheckError;
+
+/// Reply lines longer than this are cut off.
+const MAX_LINE_BYTES: u64 = 4096;
+/// Replies with more lines than this are rejected.
+const MAX_REPLY_LINES: usize = 200;
+
+/// What to do after a mail server's greeting. Without one, a check only
+/// reads the greeting.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MailProbe {
+    /// Upgrade the connection with STARTTLS, then ask for the capabilities
+    /// again over TLS
+    pub starttls: bool,
+    /// Capabilities the server has to announce, e.g. `AUTH` or `IDLE`
+    /// (case-insensitive); checked against the last answer, so the one over
+    /// TLS when `starttls` is on
+    pub expect: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MailProtocol {
+    Smtp,
+    Imap,
+}
+
+impl MailProtocol {
+    fn default_port(self) -> u16 {
+        match self {
+            MailProtocol::Smtp => 25,
+            MailProtocol::Imap => 143,
+        }
+    }
+
+    /// Command asking for the capabilities, as named in errors
+    fn capability_command(self) -> &'static str {
+        match self {
+            MailProtocol::Smtp => "EHLO",
+            MailProtocol::Imap => "CAPABILITY",
+        }
+    }
+}
+
+/// A mail check that went through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MailCheck {
+    /// The greeting's reply code for SMTP; IMAP has no reply codes, so a
+    /// good IMAP greeting is reported as SMTP's 220 ("service ready")
+    pub code: u16,
+    /// The first line of the greeting
+    pub banner: String,
+}
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+struct Session {
+    reader: BufReader<Box<dyn Stream>>,
+    /// Second handle on the socket so timeouts can be re-armed once it's
+    /// wrapped
+    control: TcpStream,
+    deadline: Instant,
+    timeout: Duration,
+    /// Next IMAP command tag
+    tag: u32,
+}
+
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
+}
+
+impl Session {
+    fn io_error(&self, step: &str, err: io::Error) -> CheckError {
+        if is_timeout(&err) {
+            CheckError::TimedOut {
+                limit: self.timeout,
+            }
+        } else {
+            format!("mail error: {step}: {err}").into()
+        }
+    }
+
+    /// Give the socket whatever is left of the overall timeout.
+    fn arm(&self) -> Result<(), CheckError> {
+        let left = self
+            .deadline
+            .checked_duration_since(Instant::now())
+            .filter(|d| !d.is_zero())
+            .ok_or(CheckError::TimedOut {
+                limit: self.timeout,
+            })?;
+        self.control
+            .set_read_timeout(Some(left))
+            .and_then(|_| self.control.set_write_timeout(Some(left)))
+            .map_err(|e| self.io_error("connect", e))
+    }
+
+    fn send(&mut self, step: &str, line: &str) -> Result<(), CheckError> {
+        self.arm()?;
+        let stream = self.reader.get_mut();
+        stream
+            .write_all(format!("{line}\r\n").as_bytes())
+            .and_then(|_| stream.flush())
+            .map_err(|e| self.io_error(step, e))
+    }
+
+    /// One line, without its line ending.
+    fn read_line(&mut self, step: &str) -> Result<String, CheckError> {
+        self.arm()?;
+        let mut line = Vec::new();
+        let n = (&mut self.reader)
+            .take(MAX_LINE_BYTES)
+            .read_until(b'\n', &mut line)
+            .map_err(|e| self.io_error(step, e))?;
+        if n == 0 {
+            return Err(format!("mail error: {step}: connection closed").into());
+        }
+        let line = String::from_utf8_lossy(&line);
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+
+    /// An SMTP reply: its code and lines, codes stripped. A line that
+    /// doesn't start with a code ends up as the error's reply.
+    fn smtp_reply(&mut self, to: &str) -> Result<(u16, Vec<String>), CheckError> {
+        let mut lines = Vec::new();
+        loop {
+            let line = self.read_line(to)?;
+            let code = line.get(..3).and_then(|c| c.parse::<u16>().ok());
+            let (Some(code), sep) = (code, line.as_bytes().get(3)) else {
+                return Err(bad_reply(to, line));
+            };
+            lines.push(line.get(4..).unwrap_or_default().to_string());
+            match sep {
+                Some(b'-') if lines.len() < MAX_REPLY_LINES => {}
+                Some(b'-') => return Err(format!("mail error: {to}: reply too long").into()),
+                _ => return Ok((code, lines)),
+            }
+        }
+    }
+
+    /// Send an IMAP command and collect the untagged lines up to its tagged
+    /// answer, which has to be `OK`.
+    fn imap_command(&mut self, command: &str) -> Result<Vec<String>, CheckError> {
+        self.tag += 1;
+        let tag = format!("a{}", self.tag);
+        self.send(command, &format!("{tag} {command}"))?;
+        let mut untagged = Vec::new();
+        loop {
+            let line = self.read_line(command)?;
+            if let Some(status) = line.strip_prefix(&tag).and_then(|s| s.strip_prefix(' ')) {
+                return if status
+                    .get(..3)
+                    .is_some_and(|s| s.eq_ignore_ascii_case("OK "))
+                {
+                    Ok(untagged)
+                } else {
+                    Err(bad_reply(command, line))
+                };
+            }
+            if untagged.len() == MAX_REPLY_LINES {
+                return Err(format!("mail error: {command}: reply too long").into());
+            }
+            untagged.push(line);
+        }
+    }
+
+    #[cfg(feature = "starttls")]
+    fn upgrade(mut self, host: &str) -> Result<Self, CheckError> {
+        use rustls::{ClientConnection, StreamOwned, pki_types::ServerName};
+
+        // Anything the server sent past its STARTTLS answer would be read as
+        // if it came over TLS
+        if !self.reader.buffer().is_empty() {
+            return Err("mail error: STARTTLS: data sent before the TLS handshake".into());
+        }
+        let name = ServerName::try_from(host.to_string())
+            .map_err(|e| format!("mail error: STARTTLS: invalid server name: {e}"))?;
+        let conn = ClientConnection::new(tls::config(), name)
+            .map_err(|e| format!("mail error: STARTTLS: {e}"))?;
+        self.arm()?;
+        let mut stream = StreamOwned::new(conn, self.reader.into_inner());
+        while stream.conn.is_handshaking() {
+            if let Err(e) = stream.conn.complete_io(&mut stream.sock) {
+                return Err(if is_timeout(&e) {
+                    CheckError::TimedOut {
+                        limit: self.timeout,
+                    }
+                } else {
+                    format!("mail error: STARTTLS: tls handshake: {e}").into()
+                });
+            }
+        }
+        self.reader = BufReader::new(Box::new(stream));
+        Ok(self)
+    }
+
+    #[cfg(not(feature = "starttls"))]
+    fn upgrade(self, _: &str) -> Result<Self, CheckError> {
+        Err("STARTTLS requires the `starttls` feature".into())
+    }
+}
+
+#[cfg(feature = "starttls")]
+mod tls {
+    use rustls::{ClientConfig, RootCertStore};
+    use std::sync::{Arc, OnceLock};
+
+    pub(super) fn config() -> Arc<ClientConfig> {
+        static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+        CONFIG
+            .get_or_init(|| {
+                let roots = RootCertStore {
+                    roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+                };
+                let provider = Arc::new(rustls::crypto::ring::default_provider());
+                let config = ClientConfig::builder_with_provider(provider)
+                    .with_safe_default_protocol_versions()
+                    .expect("ring supports the default protocol versions")
+                    .with_root_certificates(roots)
+                    .with_no_client_auth();
+                Arc::new(config)
+            })
+            .clone()
+    }
+}
+
+fn bad_reply(to: &str, reply: String) -> CheckError {
+    CheckError::BadReply {
+        to: to.to_string(),
+        reply,
+    }
+}
+
+/// Connect to the first address that takes the connection. Refusals are
+/// told apart from other failures so "nothing listening" stands out.
+fn connect(addrs: &[SocketAddr], timeout: Duration) -> Result<TcpStream, CheckError> {
+    let mut last_err = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, timeout) {
+            Ok(sock) => return Ok(sock),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    match last_err {
+        Some(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+            Err(CheckError::ConnectionRefused)
+        }
+        Some(e) if is_timeout(&e) => Err(CheckError::TimedOut { limit: timeout }),
+        Some(e) => Err(format!("mail error: connect: {e}").into()),
+        None => Err("mail error: dns: no addresses".into()),
+    }
+}
+
+/// Check the mail server at `url` over `protocol`, all within `timeout`.
+pub(crate) fn check_mail(
+    protocol: MailProtocol,
+    url: &str,
+    timeout: Duration,
+    probe: Option<&MailProbe>,
+) -> Result<MailCheck, CheckError> {
+    let deadline = Instant::now() + timeout;
+    let url = Url::parse(url).map_err(|e| format!("invalid mail url: {e}"))?;
+    let host = url.host_str().ok_or("mail url has no host")?;
+    let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = url.port().unwrap_or(protocol.default_port());
+    let addrs: Vec<SocketAddr> = (bare_host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("mail error: dns: {e}"))?
+        .collect();
+
+    let sock = connect(&addrs, timeout)?;
+    let control = sock
+        .try_clone()
+        .map_err(|e| format!("mail error: connect: {e}"))?;
+    let mut session = Session {
+        reader: BufReader::new(Box::new(sock)),
+        control,
+        deadline,
+        timeout,
+        tag: 0,
+    };
+
+    let check = match protocol {
+        MailProtocol::Smtp => {
+            let (code, lines) = session.smtp_reply("greeting")?;
+            if code != 220 {
+                return Err(bad_reply("greeting", format!("{code} {}", lines.join(" "))));
+            }
+            MailCheck {
+                code,
+                banner: format!("{code} {}", lines[0]),
+            }
+        }
+        MailProtocol::Imap => {
+            let banner = session.read_line("greeting")?;
+            let status = banner.strip_prefix("* ").unwrap_or_default();
+            let ok = ["OK ", "PREAUTH "].iter().any(|s| {
+                status
+                    .get(..s.len())
+                    .is_some_and(|p| p.eq_ignore_ascii_case(s))
+            });
+            if !ok {
+                return Err(bad_reply("greeting", banner));
+            }
+            MailCheck { code: 220, banner }
+        }
+    };
+
+    if let Some(probe) = probe {
+        let mut caps = capabilities(&mut session, protocol)?;
+        if probe.starttls {
+            match protocol {
+                MailProtocol::Smtp => {
+                    session.send("STARTTLS", "STARTTLS")?;
+                    let (code, lines) = session.smtp_reply("STARTTLS")?;
+                    if code != 220 {
+                        return Err(bad_reply("STARTTLS", format!("{code} {}", lines.join(" "))));
+                    }
+                }
+                MailProtocol::Imap => {
+                    session.imap_command("STARTTLS")?;
+                }
+            }
+            session = session.upgrade(bare_host)?;
+            caps = capabilities(&mut session, protocol)?;
+        }
+        let missing: Vec<&str> = probe
+            .expect
+            .iter()
+            .filter(|want| !caps.iter().any(|cap| cap.eq_ignore_ascii_case(want)))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(bad_reply(
+                protocol.capability_command(),
+                format!("{} (missing {})", caps.join(" "), missing.join(", ")),
+            ));
+        }
+    }
+
+    // Best effort: the check already has its answer
+    match protocol {
+        MailProtocol::Smtp => {
+            let _ = session.send("QUIT", "QUIT");
+        }
+        MailProtocol::Imap => {
+            let _ = session.imap_command("LOGOUT");
+        }
+    }
+    Ok(check)
+}
+
+/// The capability names the server announces: the first word of each
+/// EHLO line after the greeting one, or each word of an IMAP `CAPABILITY`
+/// line.
+fn capabilities(session: &mut Session, protocol: MailProtocol) -> Result<Vec<String>, CheckError> {
+    match protocol {
+        MailProtocol::Smtp => {
+            session.send("EHLO", "EHLO website-monitor")?;
+            let (code, lines) = session.smtp_reply("EHLO")?;
+            if code != 250 {
+                return Err(bad_reply("EHLO", format!("{code} {}", lines.join(" "))));
+            }
+            Ok(lines
+                .iter()
+                .skip(1)
+                .filter_map(|l| l.split_whitespace().next())
+                .map(str::to_string)
+                .collect())
+        }
+        MailProtocol::Imap => Ok(session
+            .imap_command("CAPABILITY")?
+            .iter()
+            .filter_map(|l| l.strip_prefix("* CAPABILITY "))
+            .flat_map(str::split_whitespace)
+            .map(str::to_string)
+            .collect()),
+    }
+}
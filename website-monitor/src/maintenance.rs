@@ -0,0 +1,282 @@
+use chrono::{DateTime, Datelike, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use std::fmt;
+
+use crate::ConfigError;
+
+const DAY: u32 = 24 * 60 * 60;
+
+/// A weekly stretch of time during which a target is expected to be down,
+/// e.g. `Sun 02:00-02:30`. Times are wall-clock times in the window's
+/// timezone (UTC by default), and a window whose end is not after its start
+/// runs past midnight into the next day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    /// Days the window starts on, bit `n` for `n` days from Monday
+    days: u8,
+    /// Seconds from midnight, start inclusive and end exclusive; `end` may
+    /// be a whole day for windows running until midnight
+    start: u32,
+    end: u32,
+    tz: Tz,
+}
+
+impl MaintenanceWindow {
+    /// Parse `"<days> <HH:MM>-<HH:MM> [tz=<zone>]"`, where days are `*` or
+    /// a comma-separated list of day names and ranges (`Mon-Fri`,
+    /// `Sat,Sun`). An end of `24:00` means midnight.
+    pub fn parse(spec: &str) -> Result<Self, ConfigError> {
+        let invalid = |reason: &str| ConfigError::InvalidWindow {
+            spec: spec.to_string(),
+            reason: reason.to_string(),
+        };
+        let mut parts = spec.split_whitespace();
+        let (Some(days), Some(times)) = (parts.next(), parts.next()) else {
+            return Err(invalid("expected \"<days> <HH:MM>-<HH:MM> [tz=<zone>]\""));
+        };
+        let tz = match parts.next() {
+            Some(tz) => {
+                let name = tz
+                    .strip_prefix("tz=")
+                    .ok_or_else(|| invalid("expected tz=<zone> after the times"))?;
+                name.parse::<Tz>()
+                    .map_err(|_| invalid(&format!("unknown timezone {name:?}")))?
+            }
+            None => Tz::UTC,
+        };
+        if parts.next().is_some() {
+            return Err(invalid("unexpected text after the timezone"));
+        }
+
+        let days = parse_days(days).map_err(|e| invalid(&e))?;
+        let (start, end) = times
+            .split_once('-')
+            .ok_or_else(|| invalid("expected a time range like 02:00-02:30"))?;
+        let start = parse_time(start).filter(|&s| s < DAY);
+        let end = parse_time(end);
+        let (Some(start), Some(end)) = (start, end) else {
+            return Err(invalid("times must be HH:MM between 00:00 and 24:00"));
+        };
+        if start == end {
+            return Err(invalid(
+                "the window is empty; use 00:00-24:00 for a whole day",
+            ));
+        }
+        Ok(Self {
+            days,
+            start,
+            end,
+            tz,
+        })
+    }
+
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days & (1 << day.num_days_from_monday()) != 0
+    }
+
+    /// Whether `at` falls inside the window.
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.tz);
+        let secs = local.num_seconds_from_midnight();
+        let day = local.weekday();
+        if self.start < self.end {
+            self.starts_on(day) && (self.start..self.end).contains(&secs)
+        } else {
+            // Past midnight: the evening of a listed day, or the early
+            // hours after one
+            (self.starts_on(day) && secs >= self.start)
+                || (self.starts_on(day.pred()) && secs < self.end)
+        }
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.tz
+    }
+}
+
+/// Whether `at` falls inside any of `windows`; overlapping windows simply
+/// both match.
+pub fn in_maintenance<'a>(
+    windows: impl IntoIterator<Item = &'a MaintenanceWindow>,
+    at: DateTime<Utc>,
+) -> bool {
+    windows.into_iter().any(|w| w.contains(at))
+}
+
+fn parse_days(spec: &str) -> Result<u8, String> {
+    if spec == "*" {
+        return Ok(0x7f);
+    }
+    let day = |name: &str| {
+        name.parse::<Weekday>()
+            .map_err(|_| format!("unknown day {name:?}"))
+    };
+    let mut days = 0u8;
+    for part in spec.split(',') {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (day(first)?, day(last)?),
+            None => (day(part)?, day(part)?),
+        };
+        // Ranges may wrap around the week (Fri-Mon)
+        let mut d = first;
+        loop {
+            days |= 1 << d.num_days_from_monday();
+            if d == last {
+                break;
+            }
+            d = d.succ();
+        }
+    }
+    Ok(days)
+}
+
+/// Seconds from midnight for `HH:MM`, up to `24:00`.
+fn parse_time(s: &str) -> Option<u32> {
+    let (h, m) = s.split_once(':')?;
+    if h.len() != 2 || m.len() != 2 {
+        return None;
+    }
+    let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+    let secs = h * 3600 + m * 60;
+    (m < 60 && secs <= DAY).then_some(secs)
+}
+
+impl fmt::Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.days == 0x7f {
+            f.write_str("*")?;
+        } else {
+            let mut day = Weekday::Mon;
+            let mut first = true;
+            for _ in 0..7 {
+                if self.starts_on(day) {
+                    if !first {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{day}")?;
+                    first = false;
+                }
+                day = day.succ();
+            }
+        }
+        let hm = |secs: u32| format!("{:02}:{:02}", secs / 3600, secs % 3600 / 60);
+        write!(f, " {}-{}", hm(self.start), hm(self.end))?;
+        if self.tz != Tz::UTC {
+            write!(f, " tz={}", self.tz)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(d: u32, h: u32, m: u32, s: u32) -> DateTime<Utc> {
+        // 2024-03-03 is a Sunday
+        Utc.with_ymd_and_hms(2024, 3, d, h, m, s).unwrap()
+    }
+
+    fn window(spec: &str) -> MaintenanceWindow {
+        MaintenanceWindow::parse(spec).unwrap()
+    }
+
+    #[test]
+    fn boundaries_are_start_inclusive_end_exclusive() {
+        let w = window("Sun 02:00-02:30");
+        assert!(!w.contains(at(3, 1, 59, 59)));
+        assert!(w.contains(at(3, 2, 0, 0)));
+        assert!(w.contains(at(3, 2, 29, 59)));
+        assert!(!w.contains(at(3, 2, 30, 0)));
+        // Same time, other days
+        assert!(!w.contains(at(2, 2, 15, 0)));
+        assert!(!w.contains(at(4, 2, 15, 0)));
+        assert!(w.contains(at(10, 2, 15, 0)));
+    }
+
+    #[test]
+    fn windows_cross_midnight_into_the_next_day() {
+        let w = window("Sat 23:30-00:15");
+        assert!(!w.contains(at(2, 23, 29, 0)));
+        assert!(w.contains(at(2, 23, 30, 0)));
+        // Sunday's early minutes belong to Saturday's window
+        assert!(w.contains(at(3, 0, 0, 0)));
+        assert!(w.contains(at(3, 0, 14, 59)));
+        assert!(!w.contains(at(3, 0, 15, 0)));
+        // Saturday morning isn't after a Saturday, and Sunday night isn't one
+        assert!(!w.contains(at(2, 0, 5, 0)));
+        assert!(!w.contains(at(3, 23, 45, 0)));
+
+        let sunday_night = window("Sun 22:00-02:00");
+        assert!(sunday_night.contains(at(4, 1, 0, 0)));
+        assert!(!sunday_night.contains(at(3, 1, 0, 0)));
+
+        let until_midnight = window("Sun 23:00-24:00");
+        assert!(until_midnight.contains(at(3, 23, 59, 59)));
+        assert!(!until_midnight.contains(at(4, 0, 0, 0)));
+    }
+
+    #[test]
+    fn times_are_read_in_the_window_timezone() {
+        // Berlin is UTC+1 in early March, UTC+2 from the end of March
+        let w = window("Sun 02:00-02:30 tz=Europe/Berlin");
+        assert!(w.contains(at(3, 1, 0, 0)));
+        assert!(!w.contains(at(3, 2, 0, 0)));
+        let summer = Utc.with_ymd_and_hms(2024, 7, 7, 0, 10, 0).unwrap();
+        assert!(w.contains(summer));
+
+        // 23:00-01:00 in UTC-5 is 04:00-06:00 UTC, on the next UTC day
+        let w = window("Sat 23:00-01:00 tz=America/Bogota");
+        assert!(w.contains(at(3, 4, 0, 0)));
+        assert!(w.contains(at(3, 5, 59, 0)));
+        assert!(!w.contains(at(3, 6, 0, 0)));
+        assert!(!w.contains(at(2, 23, 30, 0)));
+
+        // UTC by default, whatever the local zone
+        assert_eq!(window("* 00:00-24:00").timezone(), Tz::UTC);
+    }
+
+    #[test]
+    fn day_lists_ranges_and_overlaps() {
+        let weekdays = window("Mon-Fri 12:00-13:00");
+        assert!(weekdays.contains(at(4, 12, 0, 0)));
+        assert!(weekdays.contains(at(8, 12, 59, 0)));
+        assert!(!weekdays.contains(at(9, 12, 30, 0)));
+
+        let wrapped = window("Fri-Mon 00:00-24:00");
+        assert!(wrapped.contains(at(3, 12, 0, 0)));
+        assert!(!wrapped.contains(at(6, 12, 0, 0)));
+        assert_eq!(wrapped.to_string(), "Mon,Fri,Sat,Sun 00:00-24:00");
+
+        let windows = [window("Sun 02:00-02:30"), window("Sat,Sun 02:15-03:00")];
+        assert!(in_maintenance(&windows, at(3, 2, 10, 0)));
+        assert!(in_maintenance(&windows, at(3, 2, 45, 0)));
+        assert!(in_maintenance(&windows, at(2, 2, 20, 0)));
+        assert!(!in_maintenance(&windows, at(3, 3, 0, 0)));
+        assert!(!in_maintenance(&[], at(3, 2, 10, 0)));
+    }
+
+    #[test]
+    fn bad_specs_are_rejected() {
+        for spec in [
+            "Sun",
+            "Sun 02:00",
+            "Sun 02:00-02:00",
+            "Sun 2:00-02:30",
+            "Sun 02:00-24:01",
+            "Sun 24:00-01:00",
+            "Sun 02:60-03:00",
+            "Sunnyday 02:00-02:30",
+            "Sun 02:00-02:30 Europe/Berlin",
+            "Sun 02:00-02:30 tz=Mars/Olympus",
+        ] {
+            let err = MaintenanceWindow::parse(spec).unwrap_err();
+            assert!(err.to_string().contains(spec), "{err}");
+        }
+        assert_eq!(
+            window("sunday 02:00-02:30 tz=Europe/Berlin").to_string(),
+            "Sun 02:00-02:30 tz=Europe/Berlin"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
+
+use crate::{CheckError, WebsiteStatus, serde_util};
+
+/// Label naming the region a run was made from.
+pub const REGION_LABEL: &str = "region";
+
+/// How a URL fared across the regions that checked it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    Up,
+    Down,
+    /// Failing from some regions, fine from others
+    PartialOutage,
+}
+
+/// One region's result for a URL.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegionOutcome {
+    pub region: String,
+    pub status: Result<u16, CheckError>,
+    #[serde(rename = "response_time_ms", with = "serde_util::duration_ms")]
+    pub response_time: Duration,
+    pub success: bool,
+}
+
+/// A URL's results from every region.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MergedUrl {
+    pub url: String,
+    pub availability: Availability,
+    /// In the order of [`MergedReport::regions`]
+    pub outcomes: Vec<RegionOutcome>,
+    /// Regions whose results don't have the URL
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_from: Vec<String>,
+}
+
+/// The results of several regions side by side, sorted by URL.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MergedReport {
+    pub regions: Vec<String>,
+    pub urls: Vec<MergedUrl>,
+}
+
+impl MergedReport {
+    pub fn count(&self, availability: Availability) -> usize {
+        self.urls
+            .iter()
+            .filter(|u| u.availability == availability)
+            .count()
+    }
+
+    pub fn partial_outages(&self) -> impl Iterator<Item = &MergedUrl> {
+        self.urls
+            .iter()
+            .filter(|u| u.availability == Availability::PartialOutage)
+    }
+}
+
+/// Put the results of one run per region side by side.
+///
+/// A run's region is the [`REGION_LABEL`] of its first result that has one,
+/// or `run N` (counting from 1) when none does; a name already taken gets
+/// a `#N` suffix. If a URL appears more than once in a run, its last result
+/// is used. Results are matched up by target id (see
+/// [`WebsiteStatus::id`]), which is what [`MergedUrl::url`] holds.
+pub fn merge_regions(runs: Vec<Vec<WebsiteStatus>>, treat_4xx_as_failure: bool) -> MergedReport {
+    let mut regions = Vec::with_capacity(runs.len());
+    let mut taken = HashSet::new();
+    let mut by_url: BTreeMap<String, Vec<Option<RegionOutcome>>> = BTreeMap::new();
+    let count = runs.len();
+
+    for (i, run) in runs.into_iter().enumerate() {
+        let base = run
+            .iter()
+            .find_map(|ws| ws.labels.get(REGION_LABEL).cloned())
+            .unwrap_or_else(|| format!("run {}", i + 1));
+        let mut region = base.clone();
+        for n in 2.. {
+            if taken.insert(region.clone()) {
+                break;
+            }
+            region = format!("{base}#{n}");
+        }
+
+        for ws in run {
+            let success = ws.is_success(treat_4xx_as_failure);
+            let slots = by_url
+                .entry(ws.id().to_string())
+                .or_insert_with(|| vec![None; count]);
+            slots[i] = Some(RegionOutcome {
+                region: region.clone(),
+                status: ws.status,
+                response_time: ws.response_time,
+                success,
+            });
+        }
+        regions.push(region);
+    }
+
+    let urls = by_url
+        .into_iter()
+        .map(|(url, slots)| {
+            let missing_from = slots
+                .iter()
+                .zip(&regions)
+                .filter(|(slot, _)| slot.is_none())
+                .map(|(_, region)| region.clone())
+                .collect();
+            let outcomes: Vec<RegionOutcome> = slots.into_iter().flatten().collect();
+            let up = outcomes.iter().filter(|o| o.success).count();
+            let availability = match up {
+                0 => Availability::Down,
+                n if n == outcomes.len() => Availability::Up,
+                _ => Availability::PartialOutage,
+            };
+            MergedUrl {
+                url,
+                availability,
+                outcomes,
+                missing_from,
+            }
+        })
+        .collect();
+    MergedReport { regions, urls }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(region: Option<&str>, url: &str, status: Result<u16, &str>) -> WebsiteStatus {
+        WebsiteStatus {
+            labels: region
+                .map(|r| (REGION_LABEL.to_string(), r.to_string()))
+                .into_iter()
+                .collect(),
+            ..WebsiteStatus::new(
+                url,
+                status.map_err(CheckError::from),
+                Duration::from_millis(50),
+            )
+        }
+    }
+
+    fn run(region: &str, results: &[(&str, Result<u16, &str>)]) -> Vec<WebsiteStatus> {
+        results
+            .iter()
+            .map(|(url, status)| result(Some(region), url, *status))
+            .collect()
+    }
+
+    fn availability(report: &MergedReport) -> Vec<(&str, Availability)> {
+        report
+            .urls
+            .iter()
+            .map(|u| (u.url.as_str(), u.availability))
+            .collect()
+    }
+
+    #[test]
+    fn up_down_and_split_brain() {
+        let report = merge_regions(
+            vec![
+                run(
+                    "eu-west",
+                    &[
+                        ("https://up.test", Ok(200)),
+                        ("https://down.test", Ok(503)),
+                        ("https://split.test", Ok(200)),
+                    ],
+                ),
+                run(
+                    "us-east",
+                    &[
+                        ("https://up.test", Ok(204)),
+                        ("https://down.test", Err("connect error")),
+                        ("https://split.test", Err("connect error")),
+                    ],
+                ),
+                run(
+                    "ap-south",
+                    &[
+                        ("https://up.test", Ok(200)),
+                        ("https://down.test", Ok(500)),
+                        ("https://split.test", Ok(200)),
+                    ],
+                ),
+            ],
+            true,
+        );
+        assert_eq!(report.regions, ["eu-west", "us-east", "ap-south"]);
+        assert_eq!(
+            availability(&report),
+            [
+                ("https://down.test", Availability::Down),
+                ("https://split.test", Availability::PartialOutage),
+                ("https://up.test", Availability::Up),
+            ]
+        );
+        let split: Vec<&MergedUrl> = report.partial_outages().collect();
+        let failing: Vec<&str> = split[0]
+            .outcomes
+            .iter()
+            .filter(|o| !o.success)
+            .map(|o| o.region.as_str())
+            .collect();
+        assert_eq!(failing, ["us-east"]);
+        assert_eq!(report.count(Availability::Up), 1);
+    }
+
+    #[test]
+    fn urls_missing_from_some_regions_are_judged_on_the_rest() {
+        let report = merge_regions(
+            vec![
+                run(
+                    "eu-west",
+                    &[("https://a.test", Ok(200)), ("https://b.test", Ok(502))],
+                ),
+                run("us-east", &[("https://a.test", Ok(200))]),
+                // Same region twice, and a run without labels
+                run("us-east", &[("https://a.test", Ok(404))]),
+                vec![result(None, "https://c.test", Ok(200))],
+            ],
+            false,
+        );
+        assert_eq!(report.regions, ["eu-west", "us-east", "us-east#2", "run 4"]);
+        assert_eq!(
+            availability(&report),
+            [
+                // 404 is a success with 4xx allowed
+                ("https://a.test", Availability::Up),
+                ("https://b.test", Availability::Down),
+                ("https://c.test", Availability::Up),
+            ]
+        );
+        assert_eq!(report.urls[0].missing_from, ["run 4"]);
+        assert_eq!(
+            report.urls[1].missing_from,
+            ["us-east", "us-east#2", "run 4"]
+        );
+        assert_eq!(report.urls[2].outcomes.len(), 1);
+        assert!(merge_regions(Vec::new(), true).urls.is_empty());
+    }
+}
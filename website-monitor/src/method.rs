@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// Request method of HTTP checks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    /// Cheaper for large pages, but some servers refuse it (405, 501) or
+    /// never answer it; those targets are checked with GET instead
+    Head,
+}
+
+impl HttpMethod {
+    pub(crate) fn as_reqwest(self) -> reqwest::Method {
+        match self {
+            HttpMethod::Get => reqwest::Method::GET,
+            HttpMethod::Head => reqwest::Method::HEAD,
+        }
+    }
+}
+
+/// Targets (by id) whose servers mishandle HEAD, shared by every worker
+/// (and every pass) that holds a clone. Once a target needed GET, it's
+/// checked with GET straight away from then on.
+#[derive(Debug, Clone, Default)]
+pub struct MethodMemory {
+    get_instead: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl MethodMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start out knowing `ids` need GET, e.g. as saved by an earlier run.
+    pub fn with_ids(ids: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            get_instead: Arc::new(Mutex::new(ids.into_iter().collect())),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeSet<String>> {
+        self.get_instead
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn needs_get(&self, id: &str) -> bool {
+        self.lock().contains(id)
+    }
+
+    pub fn remember_get(&self, id: &str) {
+        self.lock().insert(id.to_string());
+    }
+
+    /// Every target remembered so far.
+    pub fn ids(&self) -> BTreeSet<String> {
+        self.lock().clone()
+    }
+}
+
+/// Whether a HEAD answer (or its absence) suggests the server doesn't
+/// handle HEAD, so GET is worth a try.
+pub(crate) fn mishandles_head(code: Result<u16, &crate::CheckError>) -> bool {
+    match code {
+        Ok(code) => matches!(code, 405 | 501),
+        Err(err) => err.is_timeout(),
+    }
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::serde_util;
+
+/// Where the time of an HTTP check went, phase by phase.
+///
+/// Only filled in by detailed-timing checks (the `phase-timing` feature).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    /// Name resolution
+    #[serde(rename = "dns_ms", with = "serde_util::duration_ms")]
+    pub dns: Duration,
+    /// TCP connect
+    #[serde(rename = "connect_ms", with = "serde_util::duration_ms")]
+    pub connect: Duration,
+    /// TLS handshake; None for plain HTTP
+    #[serde(rename = "tls_ms", default, with = "serde_util::opt_duration_ms")]
+    pub tls: Option<Duration>,
+    /// Request sent until the first response byte (server think time)
+    #[serde(rename = "first_byte_ms", with = "serde_util::duration_ms")]
+    pub first_byte: Duration,
+    /// First response byte until the end of the body
+    #[serde(rename = "transfer_ms", with = "serde_util::duration_ms")]
+    pub transfer: Duration,
+}
+
+impl PhaseTimings {
+    /// Sum of all phases.
+    pub fn total(&self) -> Duration {
+        self.dns + self.connect + self.tls.unwrap_or_default() + self.first_byte + self.transfer
+    }
+}
@@ -0,0 +1,262 @@
+use std::{fmt, time::Duration};
+
+use crate::{CheckError, Target};
+
+/// A failed attempt, as shown to a [`RetryPolicy`].
+#[derive(Debug, Clone, Copy)]
+pub struct CheckOutcome<'a> {
+    pub target: &'a Target,
+    /// A status code the pass counts as a failure, or why there isn't one
+    pub status: &'a Result<u16, CheckError>,
+    /// How long this attempt took
+    pub response_time: Duration,
+}
+
+impl CheckOutcome<'_> {
+    pub fn error(&self) -> Option<&CheckError> {
+        self.status.as_ref().err()
+    }
+
+    pub fn status_code(&self) -> Option<u16> {
+        self.status.as_ref().ok().copied()
+    }
+}
+
+/// Decides whether a failed check is tried again.
+///
+/// Consulted after every failing attempt; `attempt` is 0 for the first try
+/// and `elapsed` runs from the start of the first try, backoffs included.
+/// Closures with the same signature are policies too.
+pub trait RetryPolicy: Send + Sync {
+    /// `None` to give up and report the outcome, `Some(delay)` to try again
+    /// after `delay`.
+    fn should_retry(
+        &self,
+        outcome: &CheckOutcome<'_>,
+        attempt: u32,
+        elapsed: Duration,
+    ) -> Option<Duration>;
+}
+
+impl<F> RetryPolicy for F
+where
+    F: Fn(&CheckOutcome<'_>, u32, Duration) -> Option<Duration> + Send + Sync,
+{
+    fn should_retry(
+        &self,
+        outcome: &CheckOutcome<'_>,
+        attempt: u32,
+        elapsed: Duration,
+    ) -> Option<Duration> {
+        self(outcome, attempt, elapsed)
+    }
+}
+
+impl fmt::Debug for dyn RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RetryPolicy")
+    }
+}
+
+/// Report every failure straight away.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoRetry;
+
+impl RetryPolicy for NoRetry {
+    fn should_retry(&self, _: &CheckOutcome<'_>, _: u32, _: Duration) -> Option<Duration> {
+        None
+    }
+}
+
+/// Retry check errors (not status codes) up to `max_retries` times with the
+/// same delay each time.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedAttempts {
+    pub max_retries: u32,
+    pub delay: Duration,
+}
+
+impl RetryPolicy for FixedAttempts {
+    fn should_retry(
+        &self,
+        outcome: &CheckOutcome<'_>,
+        attempt: u32,
+        _: Duration,
+    ) -> Option<Duration> {
+        (outcome.error().is_some() && attempt < self.max_retries).then_some(self.delay)
+    }
+}
+
+/// Retry check errors (not status codes) with a delay that doubles each
+/// time, starting at `initial` and capped at `max_delay`. With `max_elapsed`
+/// set, a retry that would start past that much total time isn't made.
+#[derive(Debug, Clone, Copy)]
+pub struct ExponentialBackoff {
+    pub max_retries: u32,
+    pub initial: Duration,
+    pub max_delay: Duration,
+    pub max_elapsed: Option<Duration>,
+}
+
+impl ExponentialBackoff {
+    /// Up to `max_retries` retries, the first after `initial`, delays capped
+    /// at 30 s.
+    pub fn new(max_retries: u32, initial: Duration) -> Self {
+        Self {
+            max_retries,
+            initial,
+            max_delay: Duration::from_secs(30),
+            max_elapsed: None,
+        }
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn with_max_elapsed(mut self, max_elapsed: Duration) -> Self {
+        self.max_elapsed = Some(max_elapsed);
+        self
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn should_retry(
+        &self,
+        outcome: &CheckOutcome<'_>,
+        attempt: u32,
+        elapsed: Duration,
+    ) -> Option<Duration> {
+        if outcome.error().is_none() || attempt >= self.max_retries {
+            return None;
+        }
+        let delay = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        match self.max_elapsed {
+            Some(max) if elapsed + delay > max => None,
+            _ => Some(delay),
+        }
+    }
+}
+
+/// The policy used when the config doesn't name one: retry check errors up
+/// to `max_retries` times with a linear backoff, slow answers and reachable
+/// negative checks only when asked to.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ConfigRetry {
+    pub max_retries: u32,
+    pub retry_on_slow: bool,
+    pub retry_when_reachable: bool,
+}
+
+impl RetryPolicy for ConfigRetry {
+    fn should_retry(
+        &self,
+        outcome: &CheckOutcome<'_>,
+        attempt: u32,
+        _: Duration,
+    ) -> Option<Duration> {
+        let retryable = match outcome.error()? {
+            CheckError::TooSlow { .. } => self.retry_on_slow,
+            CheckError::UnexpectedlyReachable { .. } => self.retry_when_reachable,
+            _ => true,
+        };
+        (retryable && attempt < self.max_retries)
+            .then(|| Duration::from_millis(100 * (attempt as u64 + 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome<'a>(target: &'a Target, status: &'a Result<u16, CheckError>) -> CheckOutcome<'a> {
+        CheckOutcome {
+            target,
+            status,
+            response_time: Duration::from_millis(5),
+        }
+    }
+
+    fn delays(policy: &dyn RetryPolicy, status: Result<u16, CheckError>) -> Vec<Duration> {
+        let target = Target::new("https://example.com");
+        let outcome = outcome(&target, &status);
+        let mut elapsed = Duration::ZERO;
+        let mut out = Vec::new();
+        for attempt in 0.. {
+            let Some(delay) = policy.should_retry(&outcome, attempt, elapsed) else {
+                break;
+            };
+            out.push(delay);
+            elapsed += delay + outcome.response_time;
+        }
+        out
+    }
+
+    fn timeout() -> Result<u16, CheckError> {
+        Err(CheckError::TimedOut {
+            limit: Duration::from_secs(1),
+        })
+    }
+
+    fn ms(list: &[u64]) -> Vec<Duration> {
+        list.iter().map(|&n| Duration::from_millis(n)).collect()
+    }
+
+    #[test]
+    fn exponential_backoff_doubles_up_to_the_caps() {
+        let policy = ExponentialBackoff::new(6, Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(1000));
+        assert_eq!(
+            delays(&policy, timeout()),
+            ms(&[100, 200, 400, 800, 1000, 1000])
+        );
+
+        let policy = policy.with_max_elapsed(Duration::from_millis(1000));
+        assert_eq!(delays(&policy, timeout()), ms(&[100, 200, 400]));
+
+        // Status codes aren't check errors
+        assert!(delays(&policy, Ok(503)).is_empty());
+    }
+
+    #[test]
+    fn fixed_attempts_and_no_retry() {
+        let policy = FixedAttempts {
+            max_retries: 3,
+            delay: Duration::from_millis(50),
+        };
+        assert_eq!(delays(&policy, timeout()), ms(&[50, 50, 50]));
+        assert!(delays(&NoRetry, timeout()).is_empty());
+    }
+
+    #[test]
+    fn config_policy_matches_the_flags() {
+        let policy = ConfigRetry {
+            max_retries: 2,
+            retry_on_slow: false,
+            retry_when_reachable: true,
+        };
+        assert_eq!(delays(&policy, timeout()), ms(&[100, 200]));
+        let slow = Err(CheckError::TooSlow {
+            limit: Duration::from_millis(10),
+            actual: Duration::from_millis(20),
+        });
+        assert!(delays(&policy, slow).is_empty());
+        let reachable = Err(CheckError::UnexpectedlyReachable { status: 200 });
+        assert_eq!(delays(&policy, reachable).len(), 2);
+        assert!(delays(&policy, Ok(500)).is_empty());
+    }
+
+    #[test]
+    fn closures_are_policies() {
+        let only_5xx = |o: &CheckOutcome<'_>, attempt: u32, _: Duration| {
+            (matches!(o.status_code(), Some(500..=599)) && attempt < 1)
+                .then_some(Duration::from_millis(7))
+        };
+        assert_eq!(delays(&only_5xx, Ok(502)), ms(&[7]));
+        assert!(delays(&only_5xx, timeout()).is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use rand::{SeedableRng, rngs::StdRng, seq::index};
+use serde::Serialize;
+
+/// How many targets a sample keeps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSize {
+    /// Share of the list in `[0, 1]`, rounded to the nearest whole target
+    Fraction(f64),
+    /// Fixed number of targets (all of them when the list is shorter)
+    Count(usize),
+}
+
+/// A random subset of the targets to check instead of all of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleSpec {
+    pub size: SampleSize,
+    /// Same seed and same list = same subset
+    pub seed: Option<u64>,
+}
+
+impl SampleSpec {
+    pub fn fraction(fraction: f64) -> Self {
+        Self {
+            size: SampleSize::Fraction(fraction),
+            seed: None,
+        }
+    }
+
+    pub fn count(count: usize) -> Self {
+        Self {
+            size: SampleSize::Count(count),
+            seed: None,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Number of items kept out of `len`.
+    pub fn sample_len(&self, len: usize) -> usize {
+        match self.size {
+            SampleSize::Fraction(f) => (len as f64 * f.clamp(0.0, 1.0)).round() as usize,
+            SampleSize::Count(n) => n.min(len),
+        }
+    }
+
+    /// Pick the sample, keeping the items in their original order.
+    pub fn pick<T>(&self, items: Vec<T>) -> Vec<T> {
+        let n = self.sample_len(items.len());
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_rng(&mut rand::rng()),
+        };
+        let mut keep = vec![false; items.len()];
+        for i in index::sample(&mut rng, items.len(), n) {
+            keep[i] = true;
+        }
+        items
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(item, keep)| keep.then_some(item))
+            .collect()
+    }
+}
+
+/// How much of the supplied list a sampled pass checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Sampled {
+    pub selected: usize,
+    /// Unique targets before sampling
+    pub supplied: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list() -> Vec<u32> {
+        (0..1000).collect()
+    }
+
+    #[test]
+    fn a_seed_picks_the_same_subset() {
+        let spec = SampleSpec::fraction(0.05).with_seed(42);
+        let a = spec.pick(list());
+        assert_eq!(a.len(), 50);
+        assert_eq!(a, spec.pick(list()));
+        assert!(a.windows(2).all(|w| w[0] < w[1]), "original order kept");
+        assert_ne!(a, SampleSpec::fraction(0.05).with_seed(43).pick(list()));
+    }
+
+    #[test]
+    fn boundary_fractions() {
+        assert!(SampleSpec::fraction(0.0).pick(list()).is_empty());
+        assert_eq!(SampleSpec::fraction(1.0).pick(list()), list());
+        // Out-of-range fractions are clamped
+        assert_eq!(SampleSpec::fraction(1.5).sample_len(10), 10);
+        assert_eq!(SampleSpec::fraction(-1.0).sample_len(10), 0);
+        assert_eq!(SampleSpec::fraction(0.05).sample_len(10), 1);
+    }
+
+    #[test]
+    fn counts_past_the_end_take_everything() {
+        assert_eq!(SampleSpec::count(5000).pick(list()), list());
+        assert_eq!(SampleSpec::count(3).with_seed(1).pick(list()).len(), 3);
+        assert!(SampleSpec::count(3).pick(Vec::<u32>::new()).is_empty());
+    }
+}
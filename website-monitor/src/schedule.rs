@@ -0,0 +1,523 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+use crate::{
+    BudgetExhausted, CronSchedule, FlapConfig, FlapDetector, HealthConfig, HealthTracker,
+    HttpMethod, MethodMemory, MonitorConfig, RequestBudget, RunReport, Shutdown, StreakTracker,
+    Target, TrendConfig, TrendTracker, UrlState, run_pass,
+};
+
+/// Source of "now" for the continuous scheduler, so tests can drive time.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+    /// Block until `deadline` or until `shutdown` is cancelled.
+    fn sleep_until(&self, deadline: DateTime<Utc>, shutdown: &Shutdown);
+}
+
+/// Wall clock backed by `Utc::now()`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep_until(&self, deadline: DateTime<Utc>, shutdown: &Shutdown) {
+        if let Ok(left) = (deadline - Utc::now()).to_std() {
+            shutdown.wait_timeout(left);
+        }
+    }
+}
+
+/// Options for continuous mode.
+#[derive(Debug, Clone)]
+pub struct ContinuousConfig {
+    /// Interval between checks for targets without a cron schedule
+    pub interval: Duration,
+    /// Space interval targets evenly across the interval instead of
+    /// checking them all at once
+    pub spread_over_interval: bool,
+    /// Flag URLs whose body hash keeps changing between a few values (needs
+    /// `MonitorConfig::hash_body`)
+    pub content_flapping: Option<FlapConfig>,
+    /// Flag URLs whose recent response times have drifted well above the
+    /// ones before
+    pub latency_trend: Option<TrendConfig>,
+    /// Stop launching checks once this many requests (retries included)
+    /// have been sent
+    pub request_budget: Option<usize>,
+    /// Stop launching checks once the run has gone on this long
+    pub run_budget: Option<Duration>,
+    /// When the budgets start over (e.g. every midnight UTC); without one
+    /// the run ends once a budget runs out
+    pub budget_reset: Option<CronSchedule>,
+    /// Score the health of each target over its recent results and put it
+    /// in every pass's summary
+    pub health: Option<HealthConfig>,
+    /// Failed checks in a row before a target counts as down; fewer make
+    /// it only suspect (see [`StreakTracker`])
+    pub failures_before_down: u32,
+}
+
+impl Default for ContinuousConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            spread_over_interval: false,
+            content_flapping: None,
+            latency_trend: None,
+            request_budget: None,
+            run_budget: None,
+            budget_reset: None,
+            health: None,
+            failures_before_down: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    target: Target,
+    next: Option<DateTime<Utc>>,
+}
+
+/// Mark the results of `report` whose URL is flapping, with a warning for
+/// each.
+fn flag_flapping(detector: &mut FlapDetector, report: &mut RunReport) {
+    for ws in &mut report.results {
+        if let Some(flapping) = detector.observe(ws) {
+            report.summary.warnings.push(format!(
+                "content flapping: {} served {} different bodies in the last {}s",
+                ws.url,
+                flapping.versions.len(),
+                flapping.window.as_secs()
+            ));
+            ws.content_flapping = Some(flapping);
+        }
+    }
+}
+
+/// Mark the results of `report` whose URL has slowed down, with a warning
+/// for each.
+fn flag_regressions(tracker: &mut TrendTracker, report: &mut RunReport) {
+    for ws in &mut report.results {
+        if let Some(regression) = tracker.observe(ws) {
+            report.summary.warnings.push(format!(
+                "latency regression: {} median {} ms over the last {} checks, {:.1}x the {} ms before",
+                ws.url,
+                regression.after.as_millis(),
+                regression.samples,
+                regression.ratio,
+                regression.before.as_millis()
+            ));
+            ws.latency_trend = Some(regression);
+        }
+    }
+}
+
+/// The budget that rules out a pass at `next` for a budget window that
+/// began at `started`, if any.
+fn exhausted_at(
+    requests: Option<&RequestBudget>,
+    run_budget: Option<Duration>,
+    started: DateTime<Utc>,
+    next: DateTime<Utc>,
+) -> Option<BudgetExhausted> {
+    if let Some(budget) = requests.filter(|b| b.is_exhausted()) {
+        return Some(BudgetExhausted::Requests {
+            limit: budget.limit(),
+        });
+    }
+    run_budget
+        .filter(|&limit| (next - started).to_std().is_ok_and(|ran| ran >= limit))
+        .map(|limit| BudgetExhausted::Time { limit })
+}
+
+/// Tracks the next fire time of every target.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    entries: Vec<Entry>,
+    interval: chrono::Duration,
+}
+
+impl Scheduler {
+    /// Interval targets are due immediately; cron targets at their next fire time.
+    pub fn new(targets: Vec<Target>, interval: Duration, now: DateTime<Utc>) -> Self {
+        Self::with_spread(targets, interval, now, false)
+    }
+
+    /// Like [`Scheduler::new`], but with `spread` the interval targets get
+    /// evenly staggered first fire times across one interval.
+    pub fn with_spread(
+        targets: Vec<Target>,
+        interval: Duration,
+        now: DateTime<Utc>,
+        spread: bool,
+    ) -> Self {
+        let plain = targets.iter().filter(|t| t.cron.is_none()).count() as u32;
+        let step = if spread && plain > 0 {
+            interval / plain
+        } else {
+            Duration::ZERO
+        };
+        let step = chrono::Duration::from_std(step).unwrap_or(chrono::Duration::zero());
+        let interval = chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::MAX);
+
+        let mut slot = 0;
+        let entries = targets
+            .into_iter()
+            .map(|target| {
+                let next = match &target.cron {
+                    Some(cron) => cron.next_after(now),
+                    None => {
+                        let offset = step * slot;
+                        slot += 1;
+                        Some(now + offset)
+                    }
+                };
+                Entry { target, next }
+            })
+            .collect();
+        Self { entries, interval }
+    }
+
+    /// Earliest upcoming fire time across all targets (None = nothing left to run).
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.entries.iter().filter_map(|e| e.next).min()
+    }
+
+    /// Remove and return the targets due at `now`, rescheduling each of them.
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Vec<Target> {
+        let mut due = Vec::new();
+        for entry in &mut self.entries {
+            let Some(next) = entry.next else { continue };
+            if next > now {
+                continue;
+            }
+            entry.next = match &entry.target.cron {
+                Some(cron) => cron.next_after(now),
+                None => {
+                    // Keep the cadence anchored; skip slots missed while a pass ran long.
+                    let mut n = next + self.interval;
+                    while n <= now {
+                        n += self.interval;
+                    }
+                    Some(n)
+                }
+            };
+            due.push(entry.target.clone());
+        }
+        due
+    }
+}
+
+/// Run passes until `shutdown` is cancelled, dispatching each target when due.
+/// `on_pass` receives the report of every pass.
+///
+/// Once a budget of `continuous` runs out, no further checks are launched;
+/// the pass that used it up says so in its summary. The run then ends, or
+/// with a `budget_reset` idles until the next reset and carries on.
+pub fn monitor_continuous<F>(
+    targets: Vec<Target>,
+    config: &MonitorConfig,
+    continuous: &ContinuousConfig,
+    shutdown: &Shutdown,
+    clock: &dyn Clock,
+    mut on_pass: F,
+) where
+    F: FnMut(RunReport),
+{
+    let mut scheduler = Scheduler::with_spread(
+        targets,
+        continuous.interval,
+        clock.now(),
+        continuous.spread_over_interval,
+    );
+
+    let mut flapping = continuous.content_flapping.map(FlapDetector::new);
+    let mut trends = continuous
+        .latency_trend
+        .map(|trend| TrendTracker::new(trend, config.treat_4xx_as_failure));
+    let mut health = continuous.health.clone().map(HealthTracker::new);
+    let mut streaks =
+        StreakTracker::new(continuous.failures_before_down, config.treat_4xx_as_failure);
+
+    let budget = continuous.request_budget.map(RequestBudget::new);
+    let mut config = config.clone();
+    if budget.is_some() {
+        config.request_budget.clone_from(&budget);
+    }
+    // Remember servers that mishandle HEAD from one pass to the next
+    if config.method == HttpMethod::Head && config.method_memory.is_none() {
+        config.method_memory = Some(MethodMemory::new());
+    }
+    let mut window_start = clock.now();
+
+    while !shutdown.is_cancelled() {
+        let Some(next) = scheduler.next_due() else {
+            break;
+        };
+        if exhausted_at(budget.as_ref(), continuous.run_budget, window_start, next).is_some() {
+            let reset = continuous
+                .budget_reset
+                .as_ref()
+                .and_then(|cron| cron.next_after(clock.now()));
+            let Some(reset) = reset else {
+                break;
+            };
+            clock.sleep_until(reset, shutdown);
+            if shutdown.is_cancelled() {
+                break;
+            }
+            if let Some(budget) = &budget {
+                budget.reset();
+            }
+            window_start = clock.now();
+            continue;
+        }
+        clock.sleep_until(next, shutdown);
+        if shutdown.is_cancelled() {
+            break;
+        }
+
+        let mut due = scheduler.take_due(clock.now());
+        if let Some(budget) = &budget {
+            due.truncate(budget.remaining());
+        }
+        if due.is_empty() {
+            continue;
+        }
+        let mut report = run_pass(due, config.clone(), Some(shutdown.clone()));
+        if let Some(detector) = &mut flapping {
+            flag_flapping(detector, &mut report);
+        }
+        if let Some(tracker) = &mut trends {
+            flag_regressions(tracker, &mut report);
+        }
+        for ws in &report.results {
+            report.summary.transitions.extend(streaks.observe(ws));
+        }
+        report.summary.down = streaks
+            .not_up()
+            .into_iter()
+            .filter(|(_, state)| *state == UrlState::Down)
+            .map(|(id, _)| id.to_string())
+            .collect();
+        if let Some(tracker) = &mut health {
+            for ws in &report.results {
+                tracker.observe(ws);
+            }
+            report.summary.health = Some(tracker.health_score(clock.now()));
+        }
+        report.summary.budget_exhausted = scheduler.next_due().and_then(|next| {
+            exhausted_at(budget.as_ref(), continuous.run_budget, window_start, next)
+        });
+        on_pass(report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::Mutex;
+
+    /// Clock that jumps straight to whatever deadline it is asked to wait for.
+    struct FakeClock {
+        now: Mutex<DateTime<Utc>>,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.now.lock().unwrap()
+        }
+
+        fn sleep_until(&self, deadline: DateTime<Utc>, _shutdown: &Shutdown) {
+            let mut now = self.now.lock().unwrap();
+            if deadline > *now {
+                *now = deadline;
+            }
+        }
+    }
+
+    fn at(h: u32, m: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 4, h, m, s).unwrap()
+    }
+
+    /// Fire times produced by stepping a scheduler `n` times with a fake clock.
+    fn fire_times(
+        scheduler: &mut Scheduler,
+        clock: &FakeClock,
+        n: usize,
+    ) -> Vec<(DateTime<Utc>, Vec<String>)> {
+        let shutdown = Shutdown::new();
+        let mut out = Vec::new();
+        while out.len() < n {
+            let next = scheduler.next_due().unwrap();
+            clock.sleep_until(next, &shutdown);
+            let now = clock.now();
+            let urls = scheduler.take_due(now).into_iter().map(|t| t.url).collect();
+            out.push((now, urls));
+        }
+        out
+    }
+
+    #[test]
+    fn cron_fires_on_the_half_hour() {
+        let clock = FakeClock {
+            now: Mutex::new(at(10, 7, 0)),
+        };
+        let target = Target::new("https://half.test")
+            .with_cron("0 0,30 * * * *", None)
+            .unwrap();
+        let mut scheduler = Scheduler::new(vec![target], Duration::from_secs(60), clock.now());
+
+        let times: Vec<_> = fire_times(&mut scheduler, &clock, 3)
+            .into_iter()
+            .map(|(t, _)| t)
+            .collect();
+        assert_eq!(times, vec![at(10, 30, 0), at(11, 0, 0), at(11, 30, 0)]);
+    }
+
+    #[test]
+    fn interval_and_cron_targets_interleave() {
+        let clock = FakeClock {
+            now: Mutex::new(at(10, 0, 30)),
+        };
+        let cron = Target::new("https://cron.test")
+            .with_cron("0 1 * * * *", None)
+            .unwrap();
+        let plain = Target::new("https://plain.test");
+        let mut scheduler = Scheduler::new(vec![cron, plain], Duration::from_secs(20), clock.now());
+
+        let fires = fire_times(&mut scheduler, &clock, 4);
+        assert_eq!(
+            fires[0],
+            (at(10, 0, 30), vec!["https://plain.test".to_string()])
+        );
+        assert_eq!(
+            fires[1],
+            (at(10, 0, 50), vec!["https://plain.test".to_string()])
+        );
+        assert_eq!(
+            fires[2],
+            (at(10, 1, 0), vec!["https://cron.test".to_string()])
+        );
+        assert_eq!(
+            fires[3],
+            (at(10, 1, 10), vec!["https://plain.test".to_string()])
+        );
+    }
+
+    #[test]
+    fn spread_staggers_interval_targets() {
+        let clock = FakeClock {
+            now: Mutex::new(at(12, 0, 0)),
+        };
+        let targets = ["a", "b", "c", "d"].map(|n| Target::new(format!("https://{n}.test")));
+        let mut scheduler =
+            Scheduler::with_spread(targets.to_vec(), Duration::from_secs(60), clock.now(), true);
+
+        let fires = fire_times(&mut scheduler, &clock, 5);
+        let times: Vec<_> = fires.iter().map(|(t, _)| *t).collect();
+        assert_eq!(
+            times,
+            vec![
+                at(12, 0, 0),
+                at(12, 0, 15),
+                at(12, 0, 30),
+                at(12, 0, 45),
+                at(12, 1, 0)
+            ]
+        );
+        assert!(fires.iter().all(|(_, urls)| urls.len() == 1));
+        assert_eq!(fires[4].1, vec!["https://a.test".to_string()]);
+    }
+
+    #[test]
+    fn missed_interval_slots_are_skipped() {
+        let mut scheduler = Scheduler::new(
+            vec![Target::new("https://x.test")],
+            Duration::from_secs(10),
+            at(0, 0, 0),
+        );
+        assert_eq!(scheduler.take_due(at(0, 0, 0)).len(), 1);
+        // The pass overran by 25s: next fire lands on the next slot boundary.
+        assert_eq!(scheduler.take_due(at(0, 0, 35)).len(), 1);
+        assert_eq!(scheduler.next_due(), Some(at(0, 0, 40)));
+    }
+
+    #[test]
+    fn continuous_stops_when_cancelled() {
+        let clock = FakeClock {
+            now: Mutex::new(at(0, 0, 0)),
+        };
+        let shutdown = Shutdown::new();
+        let mut passes = 0;
+        // An unreachable port still produces a result per pass.
+        let targets = vec![Target::new("http://127.0.0.1:9/")];
+        let config = MonitorConfig {
+            worker_threads: 1,
+            request_timeout: Duration::from_millis(500),
+            ..MonitorConfig::default()
+        };
+        monitor_continuous(
+            targets,
+            &config,
+            &ContinuousConfig::default(),
+            &shutdown,
+            &clock,
+            |report| {
+                assert_eq!(report.results.len(), 1);
+                passes += 1;
+                if passes == 3 {
+                    shutdown.cancel();
+                }
+            },
+        );
+        assert_eq!(passes, 3);
+        assert_eq!(clock.now(), at(0, 2, 0));
+    }
+
+    #[test]
+    fn summaries_report_when_a_target_goes_down() {
+        let clock = FakeClock {
+            now: Mutex::new(at(0, 0, 0)),
+        };
+        let shutdown = Shutdown::new();
+        let url = "http://127.0.0.1:9/";
+        let config = MonitorConfig {
+            worker_threads: 1,
+            request_timeout: Duration::from_millis(500),
+            ..MonitorConfig::default()
+        };
+        let continuous = ContinuousConfig {
+            failures_before_down: 2,
+            ..ContinuousConfig::default()
+        };
+        let mut seen = Vec::new();
+        monitor_continuous(
+            vec![Target::new(url)],
+            &config,
+            &continuous,
+            &shutdown,
+            &clock,
+            |report| {
+                let to: Vec<UrlState> = report.summary.transitions.iter().map(|t| t.to).collect();
+                seen.push((to, report.summary.down.len()));
+                if seen.len() == 3 {
+                    shutdown.cancel();
+                }
+            },
+        );
+        assert_eq!(
+            seen,
+            [
+                (vec![UrlState::Suspect], 0),
+                (vec![UrlState::Down], 1),
+                (vec![], 1)
+            ]
+        );
+    }
+}
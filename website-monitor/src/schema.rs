@@ -0,0 +1,141 @@
+//! Versioning of the JSON the monitor writes (reports, NDJSON records,
+//! checkpoint lines and state snapshots), so tools reading it can tell a
+//! format change from a bug.
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{fmt, io};
+
+use crate::WebsiteStatus;
+
+/// Version of the JSON written by this build, in the `schema_version` field
+/// of every document. Bumped whenever a field changes incompatibly; fields
+/// that are only added don't bump it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Oldest version this build still reads. Documents without a
+/// `schema_version` predate it and are read as version 1.
+const OLDEST_READABLE: u32 = 1;
+
+/// `inner` serialized with a `schema_version` field in front of its own.
+#[derive(Debug, Serialize)]
+pub struct Versioned<'a, T> {
+    schema_version: u32,
+    #[serde(flatten)]
+    inner: &'a T,
+}
+
+impl<'a, T> Versioned<'a, T> {
+    pub fn new(inner: &'a T) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            inner,
+        }
+    }
+}
+
+/// Why a saved document couldn't be read.
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(io::Error),
+    /// Not JSON, or cut off
+    Corrupt(serde_json::Error),
+    /// JSON, but not the document expected
+    Unexpected(serde_json::Error),
+    /// Written by a newer build
+    Newer {
+        found: u32,
+    },
+    /// Written by a build too old for this one to read
+    Older {
+        found: u32,
+    },
+    /// No results document in the input at all
+    Empty,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Io(e) => write!(f, "cannot read file: {e}"),
+            SchemaError::Corrupt(e) => write!(f, "corrupt JSON ({e})"),
+            SchemaError::Unexpected(e) => {
+                write!(f, "not a website-monitor JSON result file ({e})")
+            }
+            SchemaError::Newer { found } => write!(
+                f,
+                "file is from a newer version (schema {found}; this version reads up to {SCHEMA_VERSION})"
+            ),
+            SchemaError::Older { found } => write!(
+                f,
+                "file is from an older version (schema {found}; this version reads {OLDEST_READABLE} to {SCHEMA_VERSION})"
+            ),
+            SchemaError::Empty => {
+                write!(f, "no results found (expected `--format json` output)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SchemaError::Io(e) => Some(e),
+            SchemaError::Corrupt(e) | SchemaError::Unexpected(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<SchemaError> for io::Error {
+    fn from(e: SchemaError) -> Self {
+        match e {
+            SchemaError::Io(e) => e,
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
+/// Check the `schema_version` of `doc`, then read it as a `T`.
+pub(crate) fn from_versioned<T: DeserializeOwned>(
+    doc: serde_json::Value,
+) -> Result<T, SchemaError> {
+    let found = match doc.get("schema_version") {
+        None => OLDEST_READABLE,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| {
+                SchemaError::Unexpected(serde::de::Error::custom(format!(
+                    "schema_version must be a number, got {v}"
+                )))
+            })?,
+    };
+    if found > SCHEMA_VERSION {
+        return Err(SchemaError::Newer { found });
+    }
+    if found < OLDEST_READABLE {
+        return Err(SchemaError::Older { found });
+    }
+    serde_json::from_value(doc).map_err(SchemaError::Unexpected)
+}
+
+/// The part of a saved pass that holds the results.
+#[derive(Deserialize)]
+struct SavedPass {
+    results: Vec<WebsiteStatus>,
+}
+
+/// Results of the last pass in `--format json` output (continuous runs
+/// write one document per pass), refusing documents from versions this one
+/// can't read.
+pub fn parse_results(reader: impl io::Read) -> Result<Vec<WebsiteStatus>, SchemaError> {
+    let mut last = None;
+    for doc in serde_json::Deserializer::from_reader(reader).into_iter::<serde_json::Value>() {
+        let doc = doc.map_err(|e| match e.classify() {
+            serde_json::error::Category::Io => SchemaError::Io(e.into()),
+            _ => SchemaError::Corrupt(e),
+        })?;
+        last = Some(from_versioned::<SavedPass>(doc)?);
+    }
+    last.map(|pass| pass.results).ok_or(SchemaError::Empty)
+}
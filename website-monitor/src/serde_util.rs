@@ -0,0 +1,48 @@
+//! Serde helpers for durations, which are written as (fractional) milliseconds.
+
+/// For `skip_serializing_if` on counters that are usually zero.
+pub(crate) fn is_zero<T: Default + PartialEq>(n: &T) -> bool {
+    *n == T::default()
+}
+
+pub(crate) mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_f64(d.as_secs_f64() * 1000.0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        let ms = f64::deserialize(d)?;
+        if !ms.is_finite() || ms < 0.0 {
+            return Err(serde::de::Error::custom(
+                "duration must be a non-negative number of ms",
+            ));
+        }
+        Ok(Duration::from_secs_f64(ms / 1000.0))
+    }
+}
+
+/// Like [`duration_ms`] for optional durations (`null` when absent).
+pub(crate) mod opt_duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match d {
+            Some(d) => super::duration_ms::serialize(d, s),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        match Option::<f64>::deserialize(d)? {
+            Some(ms) if !ms.is_finite() || ms < 0.0 => Err(serde::de::Error::custom(
+                "duration must be a non-negative number of ms",
+            )),
+            Some(ms) => Ok(Some(Duration::from_secs_f64(ms / 1000.0))),
+            None => Ok(None),
+        }
+    }
+}
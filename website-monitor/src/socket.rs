@@ -0,0 +1,348 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    str::FromStr,
+    sync::{Arc, Condvar, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::{os::unix::net::UnixStream, path::PathBuf};
+
+use crate::{ResultWriter, Versioned, WebsiteStatus};
+
+/// How long a TCP connect may take before it counts as failed.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a write may block on a collector that stopped reading before
+/// the connection counts as lost.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long dropping a [`SocketWriter`] waits for the queue to go out.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where a [`SocketWriter`] sends results: `tcp://host:port` or
+/// `unix:///path/to/socket`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SocketAddress {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for SocketAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.split_once("://") {
+            Some(("tcp", addr)) if !addr.is_empty() => Ok(SocketAddress::Tcp(addr.to_string())),
+            #[cfg(unix)]
+            Some(("unix", path)) if !path.is_empty() => Ok(SocketAddress::Unix(path.into())),
+            _ => Err(format!(
+                "expected tcp://HOST:PORT or unix:///PATH, got {s:?}"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SocketAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SocketAddress::Tcp(addr) => write!(f, "tcp://{addr}"),
+            #[cfg(unix)]
+            SocketAddress::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+/// A connected stream of either kind.
+trait Conn: Read + Write + Send {
+    fn set_nonblocking(&self, on: bool) -> io::Result<()>;
+}
+
+impl Conn for TcpStream {
+    fn set_nonblocking(&self, on: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, on)
+    }
+}
+
+#[cfg(unix)]
+impl Conn for UnixStream {
+    fn set_nonblocking(&self, on: bool) -> io::Result<()> {
+        UnixStream::set_nonblocking(self, on)
+    }
+}
+
+impl SocketAddress {
+    fn connect(&self) -> io::Result<Box<dyn Conn>> {
+        match self {
+            SocketAddress::Tcp(addr) => {
+                let mut last = io::Error::new(io::ErrorKind::NotFound, "no address resolved");
+                for addr in addr.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                        Ok(stream) => {
+                            stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                            return Ok(Box::new(stream));
+                        }
+                        Err(e) => last = e,
+                    }
+                }
+                Err(last)
+            }
+            #[cfg(unix)]
+            SocketAddress::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+}
+
+/// Whether the collector has hung up. Collectors don't talk back, so
+/// anything but "nothing to read yet" means the connection is gone; a write
+/// to it could still succeed and lose the line.
+fn hung_up(conn: &mut dyn Conn) -> bool {
+    if conn.set_nonblocking(true).is_err() {
+        return true;
+    }
+    let mut buf = [0u8; 256];
+    let gone = match conn.read(&mut buf) {
+        Ok(0) => true,
+        Ok(_) => false,
+        Err(e) => e.kind() != io::ErrorKind::WouldBlock,
+    };
+    gone || conn.set_nonblocking(false).is_err()
+}
+
+type Log = Box<dyn Fn(&str) + Send>;
+
+#[derive(Default)]
+struct Queue {
+    lines: VecDeque<String>,
+    dropped: u64,
+    closing: bool,
+    /// The sending thread has returned
+    finished: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    wake: Condvar,
+    capacity: usize,
+}
+
+impl Shared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Sends one JSON line per result to a collector listening on a TCP or
+/// Unix socket.
+///
+/// Results are queued and sent from a background thread, so a slow or
+/// absent collector never holds up a pass. While it's unreachable the
+/// thread reconnects every `retry_delay`, and once `capacity` lines are
+/// waiting the oldest are dropped to make room. A collector that stops
+/// reading counts as gone once a write has been stuck for a couple of
+/// seconds. Dropping the writer sends what it can over one more connection
+/// attempt, waiting a few seconds at most.
+pub struct SocketWriter {
+    shared: Arc<Shared>,
+    sender: Option<thread::JoinHandle<()>>,
+}
+
+impl fmt::Debug for SocketWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SocketWriter")
+            .field("capacity", &self.shared.capacity)
+            .field("pending", &self.pending())
+            .field("dropped", &self.dropped())
+            .finish()
+    }
+}
+
+impl SocketWriter {
+    /// Keep up to `capacity` lines while the collector is away (at least 1).
+    pub fn new(address: SocketAddress, capacity: usize, retry_delay: Duration) -> Self {
+        Self::with_log(address, capacity, retry_delay, |_| {})
+    }
+
+    /// Like [`SocketWriter::new`], telling `log` whenever the connection
+    /// comes up or goes down, and the first time it can't be made.
+    pub fn with_log(
+        address: SocketAddress,
+        capacity: usize,
+        retry_delay: Duration,
+        log: impl Fn(&str) + Send + 'static,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue::default()),
+            wake: Condvar::new(),
+            capacity: capacity.max(1),
+        });
+        let sender = {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name("socket-writer".into())
+                .spawn(move || {
+                    send_loop(&shared, &address, retry_delay, Box::new(log));
+                    shared.lock().finished = true;
+                    shared.wake.notify_all();
+                })
+                .ok()
+        };
+        Self { shared, sender }
+    }
+
+    /// Lines dropped so far because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.lock().dropped
+    }
+
+    /// Lines waiting to be sent.
+    pub fn pending(&self) -> usize {
+        self.shared.lock().lines.len()
+    }
+}
+
+/// Take lines off the queue and send them until the writer is dropped.
+fn send_loop(shared: &Shared, address: &SocketAddress, retry_delay: Duration, log: Log) {
+    let mut conn: Option<Box<dyn Conn>> = None;
+    // Connect failures are only logged on the way down, not every retry
+    let mut reachable = true;
+    loop {
+        let mut queue = shared.lock();
+        while queue.lines.is_empty() && !queue.closing {
+            queue = shared
+                .wake
+                .wait(queue)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        if queue.lines.is_empty() {
+            return;
+        }
+        let closing = queue.closing;
+        drop(queue);
+
+        if conn.as_mut().is_some_and(|c| hung_up(c.as_mut())) {
+            log(&format!("{address}: collector hung up"));
+            conn = None;
+        }
+        let stream = match &mut conn {
+            Some(stream) => stream,
+            None => match address.connect() {
+                Ok(stream) => {
+                    log(&format!("{address}: connected"));
+                    reachable = true;
+                    conn.insert(stream)
+                }
+                Err(e) => {
+                    if reachable {
+                        log(&format!("{address}: cannot connect: {e}"));
+                        reachable = false;
+                    }
+                    if closing {
+                        return;
+                    }
+                    let queue = shared.lock();
+                    let _ = shared
+                        .wake
+                        .wait_timeout_while(queue, retry_delay, |q| !q.closing);
+                    continue;
+                }
+            },
+        };
+
+        let Some(line) = shared.lock().lines.pop_front() else {
+            continue;
+        };
+        if let Err(e) = stream.write_all(line.as_bytes()) {
+            // A write that timed out ends up here too
+            log(&format!("{address}: connection lost: {e}"));
+            if closing {
+                return;
+            }
+            conn = None;
+            // Back to the front, unless newer lines have filled the queue
+            let mut queue = shared.lock();
+            if queue.lines.len() < shared.capacity {
+                queue.lines.push_front(line);
+            } else {
+                queue.dropped += 1;
+            }
+        }
+    }
+}
+
+impl ResultWriter for SocketWriter {
+    fn write_result(&mut self, ws: &WebsiteStatus, _: bool) -> io::Result<()> {
+        let mut line = serde_json::to_string(&Versioned::new(ws)).map_err(io::Error::other)?;
+        line.push('\n');
+        let mut queue = self.shared.lock();
+        if queue.lines.len() >= self.shared.capacity {
+            queue.lines.pop_front();
+            queue.dropped += 1;
+        }
+        queue.lines.push_back(line);
+        drop(queue);
+        self.shared.wake.notify_all();
+        if self.sender.is_none() {
+            return Err(io::Error::other(
+                "socket writer thread could not be started",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SocketWriter {
+    fn drop(&mut self) {
+        let mut queue = self.shared.lock();
+        queue.closing = true;
+        self.shared.wake.notify_all();
+        let deadline = Instant::now() + CLOSE_TIMEOUT;
+        while !queue.finished && self.sender.is_some() {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                // Leave the thread to finish or die with the process
+                return;
+            };
+            queue = self
+                .shared
+                .wake
+                .wait_timeout(queue, left)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        drop(queue);
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_parse_and_print_back() {
+        let address: SocketAddress = "tcp://127.0.0.1:5170".parse().unwrap();
+        assert_eq!(address.to_string(), "tcp://127.0.0.1:5170");
+        #[cfg(unix)]
+        assert_eq!(
+            "unix:///run/collector.sock".parse(),
+            Ok(SocketAddress::Unix("/run/collector.sock".into()))
+        );
+        assert_eq!(
+            "tcp://[::1]:80".parse(),
+            Ok(SocketAddress::Tcp("[::1]:80".into()))
+        );
+        assert!("udp://127.0.0.1:514".parse::<SocketAddress>().is_err());
+        assert!("tcp://".parse::<SocketAddress>().is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+use crate::schema::{SchemaError, Versioned, from_versioned};
+
+/// What a continuous run learns about its targets that's worth keeping
+/// across restarts, saved as JSON between passes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Targets (by id) to check with GET in HEAD passes; see
+    /// [`MethodMemory`](crate::MethodMemory)
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub get_instead_of_head: BTreeSet<String>,
+}
+
+impl StateSnapshot {
+    /// Read the snapshot at `path`; a missing file is an empty snapshot.
+    /// One written by a newer version is an `InvalidData` error.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)
+                .map_err(SchemaError::Corrupt)
+                .and_then(from_versioned)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the snapshot to `path` through a temporary file, so a crash
+    /// mid-write leaves the previous snapshot in place.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let json = serde_json::to_vec_pretty(&Versioned::new(self)).map_err(io::Error::other)?;
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_and_loads_back() {
+        let dir = std::env::temp_dir().join(format!("wm-state-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+        assert_eq!(
+            StateSnapshot::load(&path).unwrap(),
+            StateSnapshot::default()
+        );
+
+        let snapshot = StateSnapshot {
+            get_instead_of_head: ["https://a.test".to_string()].into(),
+        };
+        snapshot.save(&path).unwrap();
+        assert_eq!(StateSnapshot::load(&path).unwrap(), snapshot);
+
+        fs::write(&path, "{ not json").unwrap();
+        assert!(StateSnapshot::load(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
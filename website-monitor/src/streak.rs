@@ -0,0 +1,191 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::WebsiteStatus;
+
+/// What a target's recent checks add up to. Results themselves stay as
+/// they were; this is only a reading of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlState {
+    #[default]
+    Up,
+    /// Failing, but not for long enough to count as down
+    Suspect,
+    Down,
+}
+
+/// A target going from one state to another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Transition {
+    /// Target id
+    pub target: String,
+    pub from: UrlState,
+    pub to: UrlState,
+    /// Consecutive failures as of the change
+    pub failures: u32,
+}
+
+/// Consecutive failures per target, so a single failed check only makes a
+/// target [`UrlState::Suspect`] and it takes `failures_before_down` in a row
+/// to make it [`UrlState::Down`]. One success sets it back to up.
+#[derive(Debug, Clone)]
+pub struct StreakTracker {
+    failures_before_down: u32,
+    treat_4xx_as_failure: bool,
+    failures: HashMap<String, u32>,
+}
+
+impl StreakTracker {
+    /// With `failures_before_down` 0 or 1, the first failure is down.
+    pub fn new(failures_before_down: u32, treat_4xx_as_failure: bool) -> Self {
+        Self {
+            failures_before_down: failures_before_down.max(1),
+            treat_4xx_as_failure,
+            failures: HashMap::new(),
+        }
+    }
+
+    fn state_after(&self, failures: u32) -> UrlState {
+        match failures {
+            0 => UrlState::Up,
+            n if n >= self.failures_before_down => UrlState::Down,
+            _ => UrlState::Suspect,
+        }
+    }
+
+    /// Take in a result; `Some` when it changes its target's state.
+    /// Failures in maintenance leave the streak as it was.
+    pub fn observe(&mut self, ws: &WebsiteStatus) -> Option<Transition> {
+        if ws.in_maintenance {
+            return None;
+        }
+        let failed = !ws.is_success(self.treat_4xx_as_failure);
+        let before = self.streak(ws.id());
+        let after = if failed { before.saturating_add(1) } else { 0 };
+        self.failures.insert(ws.id().to_string(), after);
+
+        let (from, to) = (self.state_after(before), self.state_after(after));
+        (from != to).then(|| Transition {
+            target: ws.id().to_string(),
+            from,
+            to,
+            failures: after,
+        })
+    }
+
+    /// Consecutive failures of the target with id `id` so far.
+    pub fn streak(&self, id: &str) -> u32 {
+        self.failures.get(id).copied().unwrap_or(0)
+    }
+
+    /// Targets never seen are up.
+    pub fn state(&self, id: &str) -> UrlState {
+        self.state_after(self.streak(id))
+    }
+
+    /// Every target seen that isn't up, by id.
+    pub fn not_up(&self) -> BTreeMap<&str, UrlState> {
+        self.failures
+            .iter()
+            .filter(|(_, n)| **n > 0)
+            .map(|(id, n)| (id.as_str(), self.state_after(*n)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CheckError;
+    use std::time::Duration;
+
+    fn result(url: &str, status: Result<u16, &str>) -> WebsiteStatus {
+        WebsiteStatus::new(
+            url,
+            status.map_err(CheckError::from),
+            Duration::from_millis(20),
+        )
+    }
+
+    /// State after each outcome, and the transitions seen on the way.
+    fn walk(
+        tracker: &mut StreakTracker,
+        outcomes: &[Result<u16, &str>],
+    ) -> (Vec<UrlState>, Vec<(UrlState, UrlState, u32)>) {
+        let mut states = Vec::new();
+        let mut transitions = Vec::new();
+        for &outcome in outcomes {
+            let ws = result("https://a.test", outcome);
+            if let Some(t) = tracker.observe(&ws) {
+                assert_eq!(t.target, "https://a.test");
+                transitions.push((t.from, t.to, t.failures));
+            }
+            states.push(tracker.state("https://a.test"));
+        }
+        (states, transitions)
+    }
+
+    #[test]
+    fn down_only_after_enough_failures_in_a_row() {
+        use UrlState::*;
+        let mut tracker = StreakTracker::new(3, true);
+        let (states, transitions) = walk(
+            &mut tracker,
+            &[
+                Ok(200),
+                Err("connect error"),
+                Ok(503),
+                // Recovery from suspect
+                Ok(200),
+                Ok(500),
+                Err("connect error"),
+                Ok(502),
+                Ok(502),
+                Ok(204),
+            ],
+        );
+        assert_eq!(
+            states,
+            [Up, Suspect, Suspect, Up, Suspect, Suspect, Down, Down, Up]
+        );
+        assert_eq!(
+            transitions,
+            [
+                (Up, Suspect, 1),
+                (Suspect, Up, 0),
+                (Up, Suspect, 1),
+                (Suspect, Down, 3),
+                (Down, Up, 0),
+            ]
+        );
+        assert!(tracker.not_up().is_empty());
+    }
+
+    #[test]
+    fn a_threshold_of_one_skips_suspect() {
+        use UrlState::*;
+        let mut tracker = StreakTracker::new(1, false);
+        // 404 passes with 4xx allowed
+        let (states, transitions) = walk(&mut tracker, &[Ok(404), Ok(500), Ok(500), Ok(200)]);
+        assert_eq!(states, [Up, Down, Down, Up]);
+        assert_eq!(transitions, [(Up, Down, 1), (Down, Up, 0)]);
+
+        let mut tracker = StreakTracker::new(0, true);
+        tracker.observe(&result("https://b.test", Ok(404)));
+        assert_eq!(tracker.state("https://b.test"), Down);
+        assert_eq!(tracker.state("https://never.test"), Up);
+        assert_eq!(tracker.not_up(), BTreeMap::from([("https://b.test", Down)]));
+    }
+
+    #[test]
+    fn maintenance_failures_leave_the_streak_alone() {
+        let mut tracker = StreakTracker::new(2, true);
+        tracker.observe(&result("https://a.test", Ok(500)));
+        let mut maintenance = result("https://a.test", Ok(500));
+        maintenance.in_maintenance = true;
+        assert_eq!(tracker.observe(&maintenance), None);
+        assert_eq!(tracker.streak("https://a.test"), 1);
+        assert_eq!(tracker.state("https://a.test"), UrlState::Suspect);
+    }
+}
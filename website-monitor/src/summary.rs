@@ -0,0 +1,504 @@
+use serde::Serialize;
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{
+    BudgetExhausted, CacheStatus, CheckError, HealthScore, InputCounts, LatencyHistogram,
+    ResourceUsage, Sampled, Transition, WebsiteStatus, serde_util,
+};
+
+/// Result counts by status class.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StatusClasses {
+    /// 1xx and anything outside 100-599
+    pub other: usize,
+    #[serde(rename = "2xx")]
+    pub class_2xx: usize,
+    #[serde(rename = "3xx")]
+    pub class_3xx: usize,
+    #[serde(rename = "4xx")]
+    pub class_4xx: usize,
+    #[serde(rename = "5xx")]
+    pub class_5xx: usize,
+    /// Errors other than timeouts (DNS, connect, TLS, ...)
+    pub transport_errors: usize,
+    pub timeouts: usize,
+    /// Responses cut off by `max_body_bytes`
+    pub body_too_large: usize,
+    /// Answers slower than the target's `max_response_time`
+    pub too_slow: usize,
+    /// Identity-encoded answers from targets requiring compression
+    pub uncompressed: usize,
+    /// Negative checks whose target answered
+    pub unexpectedly_reachable: usize,
+    /// CORS preflights without the expected allow headers
+    pub cors_misconfigured: usize,
+    /// Connections refused outright (mail checks)
+    pub connection_refused: usize,
+    /// Mail servers that answered with an error or a bad greeting
+    pub bad_reply: usize,
+    /// Targets expecting a redirect that got none, or one elsewhere
+    pub wrong_redirect: usize,
+}
+
+impl StatusClasses {
+    fn record(&mut self, status: &Result<u16, CheckError>) {
+        match status {
+            Ok(200..=299) => self.class_2xx += 1,
+            Ok(300..=399) => self.class_3xx += 1,
+            Ok(400..=499) => self.class_4xx += 1,
+            Ok(500..=599) => self.class_5xx += 1,
+            Ok(_) => self.other += 1,
+            Err(CheckError::TimedOut { .. }) => self.timeouts += 1,
+            Err(CheckError::BodyTooLarge { .. }) => self.body_too_large += 1,
+            Err(CheckError::TooSlow { .. }) => self.too_slow += 1,
+            Err(CheckError::Uncompressed) => self.uncompressed += 1,
+            Err(CheckError::UnexpectedlyReachable { .. }) => self.unexpectedly_reachable += 1,
+            Err(CheckError::CorsMisconfigured { .. }) => self.cors_misconfigured += 1,
+            Err(CheckError::ConnectionRefused) => self.connection_refused += 1,
+            Err(CheckError::BadReply { .. }) => self.bad_reply += 1,
+            Err(CheckError::WrongRedirect { .. }) => self.wrong_redirect += 1,
+            Err(CheckError::Transport(_)) => self.transport_errors += 1,
+        }
+    }
+}
+
+/// Cache hit/miss counts over results that had headers captured.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CacheCounts {
+    pub hit: usize,
+    pub miss: usize,
+    pub unknown: usize,
+}
+
+impl CacheCounts {
+    /// True when at least one result carried cache information.
+    pub fn has_data(&self) -> bool {
+        self.hit + self.miss + self.unknown > 0
+    }
+}
+
+/// The results most worth a look after a pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorstOffenders {
+    /// Slowest successful checks, slowest first
+    pub slowest: Vec<(String, Duration)>,
+    /// URLs that needed the most retries, most first (only retried ones)
+    pub most_retried: Vec<(String, u32)>,
+    /// Every failed URL by failure kind (see [`CheckError::kind`]); failing
+    /// status codes go under `"http_status"`
+    pub failures: BTreeMap<&'static str, Vec<String>>,
+}
+
+impl WorstOffenders {
+    pub fn is_empty(&self) -> bool {
+        self.slowest.is_empty() && self.most_retried.is_empty() && self.failures.is_empty()
+    }
+}
+
+/// What `worst_offenders` needs from each result.
+#[derive(Debug, Clone)]
+struct Checked {
+    url: String,
+    response_time: Duration,
+    retries: u32,
+    /// Failure kind, `None` for successes
+    failure: Option<&'static str>,
+}
+
+/// Aggregate view of one monitoring pass.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub total: usize,
+    pub ok: usize,
+    pub err: usize,
+    /// Failures during maintenance, counted as neither `ok` nor `err`
+    #[serde(skip_serializing_if = "crate::serde_util::is_zero")]
+    pub in_maintenance: usize,
+    /// Policy used for `ok`/`err`: 4xx responses count as failures
+    pub treat_4xx_as_failure: bool,
+    pub classes: StatusClasses,
+    /// Occurrences of each individual status code
+    pub codes: BTreeMap<u16, usize>,
+    pub cache: CacheCounts,
+    /// Response times of every result in the pass
+    pub latency: LatencyHistogram,
+    /// Worker threads that actually ran the pass
+    pub effective_workers: usize,
+    /// What the pass cost the monitor itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceUsage>,
+    /// Targets checked out of those supplied, for sampled passes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampled: Option<Sampled>,
+    /// How the URL list read in, when the caller deduplicated one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<InputCounts>,
+    /// Setup problems that didn't stop the pass (e.g. worker clamping)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Targets left unchecked because the request budget ran out; they have
+    /// no result and count as neither `ok` nor `err`
+    #[serde(skip_serializing_if = "crate::serde_util::is_zero")]
+    pub budget_skipped: usize,
+    /// Set on the last pass a continuous run makes before a budget runs out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget_exhausted: Option<BudgetExhausted>,
+    /// Health over the recent passes of a continuous run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthScore>,
+    /// Targets of a continuous run that changed state with this pass
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<Transition>,
+    /// Targets of a continuous run down as of this pass, by id
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub down: Vec<String>,
+    #[serde(skip)]
+    checked: Vec<Checked>,
+}
+
+impl Default for RunSummary {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl RunSummary {
+    pub fn new(treat_4xx_as_failure: bool) -> Self {
+        Self {
+            total: 0,
+            ok: 0,
+            err: 0,
+            in_maintenance: 0,
+            treat_4xx_as_failure,
+            classes: StatusClasses::default(),
+            codes: BTreeMap::new(),
+            cache: CacheCounts::default(),
+            latency: LatencyHistogram::new(),
+            effective_workers: 0,
+            resources: None,
+            sampled: None,
+            input: None,
+            warnings: Vec::new(),
+            budget_skipped: 0,
+            budget_exhausted: None,
+            health: None,
+            transitions: Vec::new(),
+            down: Vec::new(),
+            checked: Vec::new(),
+        }
+    }
+
+    /// Fold one result into the totals.
+    pub fn record(&mut self, ws: &WebsiteStatus) {
+        self.total += 1;
+        let failure = if ws.in_maintenance {
+            self.in_maintenance += 1;
+            None
+        } else if ws.is_success(self.treat_4xx_as_failure) {
+            self.ok += 1;
+            None
+        } else {
+            self.err += 1;
+            Some(
+                ws.status
+                    .as_ref()
+                    .map_or_else(CheckError::kind, |_| "http_status"),
+            )
+        };
+        self.checked.push(Checked {
+            url: ws.url.clone(),
+            response_time: ws.response_time,
+            retries: ws.retries,
+            failure,
+        });
+        self.classes.record(&ws.status);
+        if let Ok(code) = ws.status {
+            *self.codes.entry(code).or_default() += 1;
+        }
+        match ws.cache_status {
+            Some(CacheStatus::Hit) => self.cache.hit += 1,
+            Some(CacheStatus::Miss) => self.cache.miss += 1,
+            Some(CacheStatus::Unknown) => self.cache.unknown += 1,
+            None => {}
+        }
+        self.latency.record(ws.response_time);
+    }
+
+    pub fn from_results(results: &[WebsiteStatus], treat_4xx_as_failure: bool) -> Self {
+        let mut summary = Self::new(treat_4xx_as_failure);
+        for ws in results {
+            summary.record(ws);
+        }
+        summary
+    }
+
+    /// The `n` most common status codes, most frequent first (ties by code).
+    pub fn top_codes(&self, n: usize) -> Vec<(u16, usize)> {
+        let mut codes: Vec<(u16, usize)> = self.codes.iter().map(|(&c, &n)| (c, n)).collect();
+        codes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        codes.truncate(n);
+        codes
+    }
+
+    /// The `n` slowest successes and `n` most retried URLs (ties by URL),
+    /// plus every failure grouped by kind.
+    pub fn worst_offenders(&self, n: usize) -> WorstOffenders {
+        let mut slowest: Vec<(String, Duration)> = self
+            .checked
+            .iter()
+            .filter(|c| c.failure.is_none())
+            .map(|c| (c.url.clone(), c.response_time))
+            .collect();
+        slowest.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        slowest.truncate(n);
+
+        let mut most_retried: Vec<(String, u32)> = self
+            .checked
+            .iter()
+            .filter(|c| c.retries > 0)
+            .map(|c| (c.url.clone(), c.retries))
+            .collect();
+        most_retried.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        most_retried.truncate(n);
+
+        let mut failures: BTreeMap<&'static str, Vec<String>> = BTreeMap::new();
+        for c in &self.checked {
+            if let Some(kind) = c.failure {
+                failures.entry(kind).or_default().push(c.url.clone());
+            }
+        }
+        for urls in failures.values_mut() {
+            urls.sort();
+        }
+
+        WorstOffenders {
+            slowest,
+            most_retried,
+            failures,
+        }
+    }
+}
+
+/// What one worker thread did during a pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct WorkerStats {
+    pub worker_id: usize,
+    /// Jobs taken off the queue, retries included
+    pub jobs: usize,
+    /// Time spent handling jobs (requests, jitter and retry backoff)
+    #[serde(rename = "busy_ms", with = "serde_util::duration_ms")]
+    pub busy: Duration,
+    /// Time spent waiting for the queue, lock contention included
+    #[serde(rename = "idle_ms", with = "serde_util::duration_ms")]
+    pub idle: Duration,
+    /// Retry jobs this worker queued
+    pub retries: usize,
+}
+
+impl WorkerStats {
+    pub fn new(worker_id: usize) -> Self {
+        Self {
+            worker_id,
+            ..Self::default()
+        }
+    }
+}
+
+/// Results of a pass together with its summary.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunReport {
+    pub results: Vec<WebsiteStatus>,
+    pub summary: RunSummary,
+    /// One entry per worker thread that ran the pass
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub workers: Vec<WorkerStats>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(status: Result<u16, &str>) -> WebsiteStatus {
+        let status = status.map_err(|e| match e {
+            "timeout" => CheckError::TimedOut {
+                limit: Duration::from_secs(5),
+            },
+            other => other.into(),
+        });
+        WebsiteStatus::new("https://x.test", status, Duration::from_millis(10))
+    }
+
+    fn synthetic() -> Vec<WebsiteStatus> {
+        [
+            Ok(200),
+            Ok(200),
+            Ok(204),
+            Ok(301),
+            Ok(404),
+            Ok(404),
+            Ok(403),
+            Ok(503),
+            Ok(101),
+            Err("timeout"),
+            Err("request error: connection refused"),
+            Err("request error: dns error"),
+        ]
+        .into_iter()
+        .map(result)
+        .collect()
+    }
+
+    #[test]
+    fn every_bucket_is_counted() {
+        let summary = RunSummary::from_results(&synthetic(), true);
+        assert_eq!(
+            summary.classes,
+            StatusClasses {
+                other: 1,
+                class_2xx: 3,
+                class_3xx: 1,
+                class_4xx: 3,
+                class_5xx: 1,
+                transport_errors: 2,
+                timeouts: 1,
+                body_too_large: 0,
+                too_slow: 0,
+                uncompressed: 0,
+                unexpectedly_reachable: 0,
+                cors_misconfigured: 0,
+                connection_refused: 0,
+                bad_reply: 0,
+                wrong_redirect: 0,
+            }
+        );
+        assert_eq!(summary.total, 12);
+        assert_eq!(summary.latency.count(), 12);
+    }
+
+    #[test]
+    fn four_xx_policy_changes_ok_and_err() {
+        let strict = RunSummary::from_results(&synthetic(), true);
+        assert_eq!((strict.ok, strict.err), (5, 7));
+
+        let lenient = RunSummary::from_results(&synthetic(), false);
+        assert_eq!((lenient.ok, lenient.err), (8, 4));
+    }
+
+    #[test]
+    fn top_codes_by_frequency_then_code() {
+        let summary = RunSummary::from_results(&synthetic(), true);
+        assert_eq!(
+            summary.top_codes(5),
+            vec![(200, 2), (404, 2), (101, 1), (204, 1), (301, 1)]
+        );
+        assert_eq!(summary.top_codes(1), vec![(200, 2)]);
+    }
+
+    #[test]
+    fn cache_counts_only_with_data() {
+        let mut results = synthetic();
+        assert!(!RunSummary::from_results(&results, true).cache.has_data());
+
+        results[0].cache_status = Some(CacheStatus::Hit);
+        results[1].cache_status = Some(CacheStatus::Hit);
+        results[2].cache_status = Some(CacheStatus::Miss);
+        results[3].cache_status = Some(CacheStatus::Unknown);
+        let summary = RunSummary::from_results(&results, true);
+        assert_eq!(
+            summary.cache,
+            CacheCounts {
+                hit: 2,
+                miss: 1,
+                unknown: 1
+            }
+        );
+    }
+
+    #[test]
+    fn json_uses_class_names() {
+        let summary = RunSummary::from_results(&synthetic(), true);
+        let v = serde_json::to_value(&summary).unwrap();
+        assert_eq!(v["classes"]["4xx"], 3);
+        assert_eq!(v["classes"]["timeouts"], 1);
+        assert_eq!(v["codes"]["404"], 2);
+    }
+
+    fn offender(url: &str, status: Result<u16, &str>, ms: u64, retries: u32) -> WebsiteStatus {
+        WebsiteStatus {
+            url: url.into(),
+            response_time: Duration::from_millis(ms),
+            retries,
+            ..result(status)
+        }
+    }
+
+    fn offenders() -> RunSummary {
+        let results = [
+            offender("https://a.test", Ok(200), 120, 0),
+            offender("https://b.test", Ok(200), 900, 2),
+            offender("https://c.test", Ok(204), 450, 1),
+            offender("https://d.test", Ok(200), 450, 2),
+            offender("https://e.test", Ok(503), 2000, 0),
+            offender("https://f.test", Err("timeout"), 5000, 3),
+            offender("https://g.test", Err("request error: dns error"), 5, 0),
+            offender("https://h.test", Err("request error: refused"), 1, 1),
+        ];
+        RunSummary::from_results(&results, true)
+    }
+
+    #[test]
+    fn slowest_only_counts_successes() {
+        let worst = offenders().worst_offenders(3);
+        // e.test and f.test were slower but failed; c and d tie on time
+        assert_eq!(
+            worst.slowest,
+            vec![
+                ("https://b.test".to_string(), Duration::from_millis(900)),
+                ("https://c.test".to_string(), Duration::from_millis(450)),
+                ("https://d.test".to_string(), Duration::from_millis(450)),
+            ]
+        );
+        assert_eq!(offenders().worst_offenders(1).slowest.len(), 1);
+    }
+
+    #[test]
+    fn most_retried_breaks_ties_by_url() {
+        let worst = offenders().worst_offenders(3);
+        assert_eq!(
+            worst.most_retried,
+            vec![
+                ("https://f.test".to_string(), 3),
+                ("https://b.test".to_string(), 2),
+                ("https://d.test".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn failures_are_grouped_by_kind() {
+        // The limit only applies to the ranked lists
+        let worst = offenders().worst_offenders(1);
+        let failures: Vec<(&str, Vec<&str>)> = worst
+            .failures
+            .iter()
+            .map(|(k, urls)| (*k, urls.iter().map(String::as_str).collect()))
+            .collect();
+        assert_eq!(
+            failures,
+            vec![
+                ("http_status", vec!["https://e.test"]),
+                ("other", vec!["https://g.test", "https://h.test"]),
+                ("timed_out", vec!["https://f.test"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_clean_pass_has_no_offenders_but_its_slowest() {
+        let summary = RunSummary::from_results(&[offender("https://a.test", Ok(200), 5, 0)], true);
+        let worst = summary.worst_offenders(5);
+        assert!(worst.most_retried.is_empty() && worst.failures.is_empty());
+        assert!(!worst.is_empty());
+        assert!(RunSummary::new(true).worst_offenders(5).is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    io,
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+};
+
+use crate::{ResultWriter, Severity, WebsiteStatus};
+
+/// Facility `daemon`
+const FACILITY: u8 = 3;
+/// Structured-data ID; 32473 is the enterprise number reserved for examples
+const SD_ID: &str = "check@32473";
+
+/// Line format sent to syslog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyslogFormat {
+    /// RFC 5424 with the result fields as structured data
+    #[default]
+    Rfc5424,
+    /// `<PRI>website-monitor[pid]: message`, for older daemons
+    Plain,
+}
+
+/// Sends one datagram per result to the local syslog socket.
+///
+/// The socket is connected lazily and reconnected after a failed send, so a
+/// syslog daemon that's down or restarting costs messages, not the pass.
+#[derive(Debug)]
+pub struct SyslogWriter {
+    path: PathBuf,
+    format: SyslogFormat,
+    socket: Option<UnixDatagram>,
+}
+
+impl SyslogWriter {
+    /// Writer for the socket at `path` (usually `/dev/log`).
+    pub fn new(path: impl AsRef<Path>, format: SyslogFormat) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            format,
+            socket: None,
+        }
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        let socket = match &self.socket {
+            Some(socket) => socket,
+            None => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(&self.path)?;
+                self.socket.insert(socket)
+            }
+        };
+        if let Err(e) = socket.send(line.as_bytes()) {
+            self.socket = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+impl ResultWriter for SyslogWriter {
+    fn write_result(&mut self, ws: &WebsiteStatus, treat_4xx_as_failure: bool) -> io::Result<()> {
+        let severity = Severity::of(ws, treat_4xx_as_failure);
+        let line = match self.format {
+            SyslogFormat::Rfc5424 => rfc5424(ws, severity),
+            SyslogFormat::Plain => plain(ws, severity),
+        };
+        self.send(&line)
+    }
+}
+
+fn pri(severity: Severity) -> u8 {
+    let level = match severity {
+        Severity::Error => 3,
+        Severity::Warning => 4,
+        Severity::Info => 6,
+    };
+    FACILITY * 8 + level
+}
+
+fn message(ws: &WebsiteStatus, severity: Severity) -> String {
+    let tag = if severity == Severity::Error {
+        "ERR"
+    } else {
+        "OK"
+    };
+    let outcome = match &ws.status {
+        Ok(code) => format!("status={code}"),
+        Err(e) => e.to_string(),
+    };
+    format!(
+        "{tag} {} {outcome} {} ms",
+        ws.url,
+        ws.response_time.as_millis()
+    )
+}
+
+/// Escape an SD-PARAM value (RFC 5424 section 6.3.3).
+fn sd_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn rfc5424(ws: &WebsiteStatus, severity: Severity) -> String {
+    let outcome = match &ws.status {
+        Ok(code) => format!("status=\"{code}\""),
+        Err(e) => format!("error=\"{}\"", e.kind()),
+    };
+    format!(
+        "<{}>1 {} - website-monitor {} check \
+         [{SD_ID} url=\"{}\" {outcome} response_ms=\"{}\" retries=\"{}\"] {}",
+        pri(severity),
+        ws.timestamp
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        std::process::id(),
+        sd_escape(&ws.url),
+        ws.response_time.as_millis(),
+        ws.retries,
+        message(ws, severity)
+    )
+}
+
+fn plain(ws: &WebsiteStatus, severity: Severity) -> String {
+    format!(
+        "<{}>website-monitor[{}]: {}",
+        pri(severity),
+        std::process::id(),
+        message(ws, severity)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sd_values_are_escaped() {
+        assert_eq!(sd_escape(r#"a"b\c]d"#), r#"a\"b\\c\]d"#);
+    }
+
+    #[test]
+    fn priority_combines_facility_and_severity() {
+        assert_eq!(pri(Severity::Error), 27);
+        assert_eq!(pri(Severity::Warning), 28);
+        assert_eq!(pri(Severity::Info), 30);
+    }
+}
@@ -0,0 +1,416 @@
+//! HTTP checks with a per-phase timing breakdown (enabled with the
+//! `phase-timing` feature).
+//!
+//! reqwest doesn't expose DNS / connect / TLS timings, so these checks do the
+//! work by hand: resolve, connect, handshake with rustls and speak a minimal
+//! HTTP/1.1 GET with `Connection: close`. Redirects are not followed.
+
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned, pki_types::ServerName};
+use url::{Position, Url};
+
+use crate::{
+    CheckError, Compression, Fetched, MonitorConfig, PhaseTimings, Target, compression, headers,
+};
+
+/// Response heads larger than this are rejected.
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+fn tls_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let roots = RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            };
+            let provider = Arc::new(rustls::crypto::ring::default_provider());
+            let config = ClientConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()
+                .expect("ring supports the default protocol versions")
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            Arc::new(config)
+        })
+        .clone()
+}
+
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
+}
+
+/// Error for an I/O failure during `phase`.
+fn io_error(phase: &str, err: io::Error, timeout: Duration) -> CheckError {
+    if is_timeout(&err) {
+        CheckError::TimedOut { limit: timeout }
+    } else {
+        format!("request error: {phase}: {err}").into()
+    }
+}
+
+/// Time left before `deadline`, or a timeout error.
+fn remaining(deadline: Instant, timeout: Duration) -> Result<Duration, CheckError> {
+    let left = deadline.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+        Err(CheckError::TimedOut { limit: timeout })
+    } else {
+        Ok(left)
+    }
+}
+
+/// Bound the next socket operation by what's left of the overall timeout.
+fn arm(
+    sock: &TcpStream,
+    deadline: Instant,
+    timeout: Duration,
+    phase: &str,
+) -> Result<(), CheckError> {
+    let left = remaining(deadline, timeout)?;
+    sock.set_read_timeout(Some(left))
+        .and_then(|_| sock.set_write_timeout(Some(left)))
+        .map_err(|e| io_error(phase, e, timeout))
+}
+
+/// Read once; a peer closing without TLS close_notify counts as EOF.
+fn read_some(stream: &mut dyn Stream, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        match stream.read(buf) {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(0),
+            other => return other,
+        }
+    }
+}
+
+/// How the end of the body is found.
+#[derive(Debug, PartialEq, Eq)]
+enum Framing {
+    Length(u64),
+    Chunked,
+    UntilClose,
+}
+
+/// Parsed status line and headers.
+#[derive(Debug)]
+struct Head {
+    code: u16,
+    headers: Vec<(String, String)>,
+}
+
+impl Head {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn framing(&self) -> Framing {
+        if matches!(self.code, 100..=199 | 204 | 304) {
+            return Framing::Length(0);
+        }
+        if self
+            .header("transfer-encoding")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"))
+        {
+            return Framing::Chunked;
+        }
+        match self
+            .header("content-length")
+            .and_then(|v| v.trim().parse().ok())
+        {
+            Some(len) => Framing::Length(len),
+            None => Framing::UntilClose,
+        }
+    }
+}
+
+fn parse_head(raw: &[u8]) -> Result<Head, CheckError> {
+    let text = String::from_utf8_lossy(raw);
+    let mut lines = text.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+    let code = status_line
+        .strip_prefix("HTTP/")
+        .and_then(|rest| rest.split_whitespace().nth(1))
+        .and_then(|c| c.parse().ok())
+        .ok_or_else(|| format!("request error: malformed status line {status_line:?}"))?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+    Ok(Head { code, headers })
+}
+
+fn find_head_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n").map(|p| p + 4)
+}
+
+/// GET `target` over a hand-rolled connection, timing every phase.
+/// `max_body_bytes` is enforced, but the body itself is never kept.
+/// `request_id` is a header name and value to send along.
+pub(crate) fn timed_fetch(
+    target: &Target,
+    config: &MonitorConfig,
+    request_id: Option<(&str, &str)>,
+) -> Result<Fetched, CheckError> {
+    let timeout = config.request_timeout;
+    let url = Url::parse(&target.url).map_err(|e| format!("request error: invalid url: {e}"))?;
+    let tls = match url.scheme() {
+        "http" => false,
+        "https" => true,
+        other => return Err(format!("request error: unsupported scheme {other:?}").into()),
+    };
+    let host = url.host_str().ok_or("request error: url has no host")?;
+    let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = url
+        .port_or_known_default()
+        .unwrap_or(if tls { 443 } else { 80 });
+
+    let start = Instant::now();
+    let deadline = start + timeout;
+
+    // DNS (std offers no timeout here; the overall deadline still applies after)
+    let addrs: Vec<SocketAddr> = (bare_host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("request error: dns: {e}"))?
+        .collect();
+    let dns = start.elapsed();
+    if addrs.is_empty() {
+        return Err(format!("request error: dns: no address for {host}").into());
+    }
+
+    // Connect, trying each resolved address in turn
+    let t = Instant::now();
+    let mut last_err = None;
+    let mut sock = None;
+    for addr in &addrs {
+        let left = remaining(deadline, timeout)?;
+        match TcpStream::connect_timeout(addr, left) {
+            Ok(s) => {
+                sock = Some(s);
+                break;
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+    let sock = match (sock, last_err) {
+        (Some(sock), _) => sock,
+        (None, Some(e)) => return Err(io_error("connect", e, timeout)),
+        (None, None) => unreachable!("addrs is not empty"),
+    };
+    let connect = t.elapsed();
+    let _ = sock.set_nodelay(true);
+    // Second handle on the socket so timeouts can be re-armed once it's wrapped
+    let control = sock
+        .try_clone()
+        .map_err(|e| io_error("connect", e, timeout))?;
+
+    // TLS handshake
+    arm(&control, deadline, timeout, "tls handshake")?;
+    let (mut stream, tls_time): (Box<dyn Stream>, Option<Duration>) = if tls {
+        let t = Instant::now();
+        let name = ServerName::try_from(bare_host.to_string())
+            .map_err(|e| format!("request error: tls: invalid server name: {e}"))?;
+        let conn = ClientConnection::new(tls_config(), name)
+            .map_err(|e| format!("request error: tls: {e}"))?;
+        let mut tls_stream = StreamOwned::new(conn, sock);
+        while tls_stream.conn.is_handshaking() {
+            tls_stream
+                .conn
+                .complete_io(&mut tls_stream.sock)
+                .map_err(|e| io_error("tls handshake", e, timeout))?;
+        }
+        (Box::new(tls_stream), Some(t.elapsed()))
+    } else {
+        (Box::new(sock), None)
+    };
+
+    // Request
+    let host_value = match (&target.host_header, url.port()) {
+        (Some(h), _) => h.clone(),
+        (None, Some(p)) => format!("{host}:{p}"),
+        (None, None) => host.to_string(),
+    };
+    let path = &url[Position::BeforePath..Position::AfterQuery];
+    let accept_encoding = compression::accept_encoding(&config.accept_encoding)
+        .map_or(String::new(), |v| format!("Accept-Encoding: {v}\r\n"));
+    let mut extra = String::new();
+    let request_id = request_id.map(|(name, id)| (name, Cow::Borrowed(id)));
+    for (name, value) in target.request_headers().chain(request_id) {
+        if [name, value.as_ref()]
+            .iter()
+            .any(|s| s.contains(['\r', '\n']))
+        {
+            return Err(format!("request error: invalid header {name:?}").into());
+        }
+        extra.push_str(&format!("{name}: {value}\r\n"));
+    }
+    let request = format!(
+        "GET {path} HTTP/1.1\r\nHost: {host_value}\r\nUser-Agent: website-monitor\r\n\
+         Accept: */*\r\n{accept_encoding}{extra}Connection: close\r\n\r\n"
+    );
+    arm(&control, deadline, timeout, "request")?;
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|_| stream.flush())
+        .map_err(|e| io_error("request", e, timeout))?;
+    let sent = Instant::now();
+
+    // First byte
+    let mut buf = [0u8; 8192];
+    arm(&control, deadline, timeout, "first byte")?;
+    let n = read_some(&mut *stream, &mut buf).map_err(|e| io_error("first byte", e, timeout))?;
+    if n == 0 {
+        return Err("request error: connection closed before response".into());
+    }
+    let first_byte = sent.elapsed();
+    let first_at = Instant::now();
+
+    // Response head
+    let mut head_buf = buf[..n].to_vec();
+    let head_end = loop {
+        if let Some(end) = find_head_end(&head_buf) {
+            break end;
+        }
+        if head_buf.len() > MAX_HEAD_BYTES {
+            return Err("request error: response head too large".into());
+        }
+        arm(&control, deadline, timeout, "response head")?;
+        let n =
+            read_some(&mut *stream, &mut buf).map_err(|e| io_error("response head", e, timeout))?;
+        if n == 0 {
+            return Err("request error: connection closed mid-response".into());
+        }
+        head_buf.extend_from_slice(&buf[..n]);
+    };
+    let head = parse_head(&head_buf[..head_end])?;
+    let encoding = head.header("content-encoding").map(str::to_string);
+    if target.require_compressed && compression::is_identity(encoding.as_deref()) {
+        return Err(CheckError::Uncompressed);
+    }
+
+    // Body: counted and discarded; only a chunked terminator's tail is kept
+    let mut body_read = (head_buf.len() - head_end) as u64;
+    let mut tail: Vec<u8> = head_buf[head_end..].to_vec();
+    let framing = head.framing();
+    let too_large = |observed_at_least: u64| {
+        config
+            .max_body_bytes
+            .filter(|&limit| observed_at_least > limit)
+            .map(|limit| CheckError::BodyTooLarge {
+                limit,
+                observed_at_least,
+            })
+    };
+    if let Framing::Length(len) = framing
+        && let Some(err) = too_large(len)
+    {
+        return Err(err);
+    }
+    loop {
+        if let Some(err) = too_large(body_read) {
+            return Err(err);
+        }
+        let done = match framing {
+            Framing::Length(len) => body_read >= len,
+            Framing::Chunked => tail.ends_with(b"0\r\n\r\n"),
+            Framing::UntilClose => false,
+        };
+        if done {
+            break;
+        }
+        arm(&control, deadline, timeout, "body")?;
+        let n = read_some(&mut *stream, &mut buf).map_err(|e| io_error("body", e, timeout))?;
+        if n == 0 {
+            if let Framing::Length(len) = framing {
+                return Err(
+                    format!("request error: body ended after {body_read} of {len} bytes").into(),
+                );
+            }
+            break;
+        }
+        body_read += n as u64;
+        tail.extend_from_slice(&buf[..n]);
+        let keep = tail.len().saturating_sub(5);
+        tail.drain(..keep);
+    }
+    let transfer = first_at.elapsed();
+
+    Ok(Fetched {
+        code: head.code,
+        headers: config.capture_headers.then(|| {
+            let pairs = head.headers.iter().map(|(k, v)| (k.as_str(), v.as_bytes()));
+            headers::capture(pairs, config.header_limits)
+        }),
+        // The body is only counted here, so there's no decoded size
+        compression: config.capture_headers.then_some(Compression {
+            encoding,
+            wire_bytes: Some(body_read),
+            decoded_bytes: None,
+        }),
+        phases: Some(PhaseTimings {
+            dns,
+            connect,
+            tls: tls_time,
+            first_byte,
+            transfer,
+        }),
+        body: None,
+        body_hash: None,
+        banner: None,
+        // Redirects are never followed here
+        location: head
+            .header("location")
+            .filter(|_| target.redirect_policy.follow_limit().is_none())
+            .map(String::from),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_status_and_headers() {
+        let head =
+            parse_head(b"HTTP/1.1 404 Not Found\r\nContent-Length: 12\r\nX-Cache: HIT\r\n\r\n")
+                .unwrap();
+        assert_eq!(head.code, 404);
+        assert_eq!(head.header("x-cache"), Some("HIT"));
+        assert_eq!(head.framing(), Framing::Length(12));
+
+        assert!(parse_head(b"garbage\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn framing_follows_headers_and_status() {
+        let framing = |raw: &[u8]| parse_head(raw).unwrap().framing();
+        assert_eq!(
+            framing(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            Framing::Chunked
+        );
+        assert_eq!(framing(b"HTTP/1.0 200 OK\r\n\r\n"), Framing::UntilClose);
+        assert_eq!(
+            framing(b"HTTP/1.1 304 Not Modified\r\nContent-Length: 99\r\n\r\n"),
+            Framing::Length(0)
+        );
+    }
+
+    #[test]
+    fn finds_end_of_head() {
+        assert_eq!(find_head_end(b"HTTP/1.1 200 OK\r\n\r\nbody"), Some(19));
+        assert_eq!(find_head_end(b"HTTP/1.1 200 OK\r\n"), None);
+    }
+}
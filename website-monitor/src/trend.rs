@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use crate::{WebsiteStatus, serde_util};
+
+/// When to call a URL's latency regressed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrendConfig {
+    /// Successful checks in each of the two windows compared
+    pub samples: usize,
+    /// How many times the earlier median the recent one has to be
+    pub factor: f64,
+}
+
+impl Default for TrendConfig {
+    fn default() -> Self {
+        Self {
+            samples: 10,
+            factor: 1.5,
+        }
+    }
+}
+
+/// A URL answering markedly slower than it used to: the median response
+/// time of its last `samples` successful checks over that of the `samples`
+/// before them. Catches slow degradation no absolute limit notices yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyTrend {
+    #[serde(rename = "before_ms", with = "serde_util::duration_ms")]
+    pub before: Duration,
+    #[serde(rename = "after_ms", with = "serde_util::duration_ms")]
+    pub after: Duration,
+    /// `after` over `before`
+    pub ratio: f64,
+    /// Checks in each window
+    pub samples: usize,
+}
+
+/// Middle value, or the mean of the two middle ones; `None` when empty.
+fn median(latencies: impl Iterator<Item = Duration>) -> Option<Duration> {
+    let mut sorted: Vec<Duration> = latencies.collect();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(sorted[mid]),
+        _ => Some((sorted[mid - 1] + sorted[mid]) / 2),
+    }
+}
+
+/// Response times of the recent successful checks per target, two windows'
+/// worth each.
+#[derive(Debug, Default)]
+pub struct TrendTracker {
+    config: TrendConfig,
+    treat_4xx_as_failure: bool,
+    latencies: HashMap<String, VecDeque<Duration>>,
+}
+
+impl TrendTracker {
+    pub fn new(config: TrendConfig, treat_4xx_as_failure: bool) -> Self {
+        Self {
+            config,
+            treat_4xx_as_failure,
+            latencies: HashMap::new(),
+        }
+    }
+
+    /// Take in a result; `Some` when its URL's latency has regressed as of
+    /// this result. Failed checks (and those in maintenance) say nothing
+    /// about latency, so they're skipped rather than counted as gaps.
+    pub fn observe(&mut self, ws: &WebsiteStatus) -> Option<LatencyTrend> {
+        if ws.in_maintenance || !ws.is_success(self.treat_4xx_as_failure) {
+            return None;
+        }
+        let samples = self.config.samples.max(1);
+        let latencies = self.latencies.entry(ws.id().to_string()).or_default();
+        latencies.push_back(ws.response_time);
+        while latencies.len() > 2 * samples {
+            latencies.pop_front();
+        }
+        if latencies.len() < 2 * samples {
+            return None;
+        }
+
+        let before = median(latencies.iter().take(samples).copied())?;
+        let after = median(latencies.iter().skip(samples).copied())?;
+        if before.is_zero() {
+            return None;
+        }
+        let ratio = after.as_secs_f64() / before.as_secs_f64();
+        (ratio > self.config.factor).then_some(LatencyTrend {
+            before,
+            after,
+            ratio,
+            samples,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CheckError;
+
+    fn result(status: Result<u16, &str>, ms: u64) -> WebsiteStatus {
+        WebsiteStatus::new(
+            "https://a.test",
+            status.map_err(CheckError::from),
+            Duration::from_millis(ms),
+        )
+    }
+
+    /// Indexes of the checks (response times in ms) the warning fires on.
+    fn fires(tracker: &mut TrendTracker, series: &[(Result<u16, &str>, u64)]) -> Vec<usize> {
+        series
+            .iter()
+            .enumerate()
+            .filter(|(_, (status, ms))| tracker.observe(&result(*status, *ms)).is_some())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn tracker(samples: usize) -> TrendTracker {
+        TrendTracker::new(
+            TrendConfig {
+                samples,
+                factor: 1.5,
+            },
+            true,
+        )
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        let ms = |v: &[u64]| median(v.iter().map(|&m| Duration::from_millis(m)));
+        assert_eq!(ms(&[]), None);
+        assert_eq!(ms(&[300, 100, 200]), Some(Duration::from_millis(200)));
+        assert_eq!(ms(&[400, 100, 200, 300]), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn flat_series_never_fires() {
+        // Noisy, with a couple of outliers the medians shrug off
+        let series: Vec<_> = (0..60)
+            .map(|i| {
+                let ms = match i {
+                    17 | 41 => 2000,
+                    _ => 200 + (i * 37 % 50),
+                };
+                (Ok(200), ms)
+            })
+            .collect();
+        assert_eq!(fires(&mut tracker(5), &series), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn step_change_fires_until_both_windows_have_caught_up() {
+        let series: Vec<_> = (0..20)
+            .map(|i| (Ok(200), if i < 10 { 200 } else { 600 }))
+            .collect();
+        // Recent median is slow from the third slow check; the earlier one
+        // from the eighth
+        assert_eq!(fires(&mut tracker(5), &series), [12, 13, 14, 15, 16]);
+
+        // A drop in latency is no regression
+        let faster: Vec<_> = series.iter().rev().copied().collect();
+        assert_eq!(fires(&mut tracker(5), &faster), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn gradual_ramp_fires_once_the_windows_drift_apart() {
+        // 200 ms to 1.5 s, a step at a time
+        let ramp: Vec<_> = (0..40).map(|i| (Ok(200), 200 + i * 1300 / 39)).collect();
+        // Medians five checks apart differ by more than 1.5x only early on,
+        // while the times are still small
+        let fired = fires(&mut tracker(5), &ramp);
+        assert_eq!(fired.first(), Some(&9));
+        assert!(fired.windows(2).all(|w| w[1] == w[0] + 1), "{fired:?}");
+        assert!(*fired.last().unwrap() < 20, "{fired:?}");
+
+        // With wider windows the ramp stays visible for longer
+        let fired = fires(&mut tracker(15), &ramp);
+        assert_eq!(fired.first(), Some(&29));
+        assert_eq!(fired.last(), Some(&39));
+    }
+
+    #[test]
+    fn failed_checks_are_left_out() {
+        // The step change again, with a timeout or a 404 (a failure here)
+        // after every check; only successes count
+        let mut series = Vec::new();
+        for i in 0..20 {
+            series.push((Ok(200), if i < 10 { 200 } else { 600 }));
+            series.push(if i % 2 == 0 {
+                (Err("timeout"), 5000)
+            } else {
+                (Ok(404), 5000)
+            });
+        }
+        let fired: Vec<usize> = fires(&mut tracker(5), &series)
+            .into_iter()
+            .map(|i| i / 2)
+            .collect();
+        assert_eq!(fired, [12, 13, 14, 15, 16]);
+
+        // Slow failures don't fill the windows either
+        let mut tracker = tracker(5);
+        for _ in 0..7 {
+            assert_eq!(tracker.observe(&result(Ok(200), 100)), None);
+        }
+        for _ in 0..3 {
+            assert_eq!(tracker.observe(&result(Ok(503), 900)), None);
+        }
+        assert_eq!(tracker.observe(&result(Ok(200), 900)), None);
+        assert_eq!(tracker.observe(&result(Ok(200), 900)), None);
+        let regression = tracker.observe(&result(Ok(200), 900)).unwrap();
+        assert_eq!(
+            (regression.before, regression.after, regression.ratio),
+            (Duration::from_millis(100), Duration::from_millis(900), 9.0)
+        );
+    }
+}
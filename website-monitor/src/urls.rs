@@ -0,0 +1,210 @@
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    sync::Arc,
+};
+use url::Url;
+
+/// Bytes per line assumed when sizing for a URL file up front
+const TYPICAL_LINE_BYTES: u64 = 48;
+
+/// Invalid entries kept as examples for the warning
+const INVALID_EXAMPLES: usize = 3;
+
+/// How a URL list read in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct InputCounts {
+    /// URLs kept, after normalizing and dropping repeats
+    pub unique: usize,
+    /// Entries that normalized to a URL already in the list
+    pub duplicates: usize,
+    /// Entries that weren't absolute URLs with a host
+    pub invalid: usize,
+}
+
+/// `text` as an absolute URL with a host, with the scheme and host
+/// lowercased, a default port and any fragment dropped, and an empty path
+/// written as `/`; `None` when it isn't one.
+pub fn normalize_url(text: &str) -> Option<String> {
+    let mut url = Url::parse(text).ok()?;
+    if !url.has_host() {
+        return None;
+    }
+    url.set_fragment(None);
+    Some(url.into())
+}
+
+/// `entry` with whatever is between `://` and an `@` in the host part
+/// replaced by `***`, for entries that didn't parse but may hold a password.
+fn redact_userinfo(entry: &str) -> String {
+    if let Some((scheme, rest)) = entry.split_once("://") {
+        let host_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        if let Some(at) = rest[..host_end].rfind('@') {
+            return format!("{scheme}://***{}", &rest[at..]);
+        }
+    }
+    entry.to_string()
+}
+
+/// URLs to check, read in one streaming pass: each entry is normalized and
+/// kept the first time it's seen. A `!` prefix (expect the target to be
+/// down) is kept, so `!u` and `u` are different entries.
+#[derive(Debug, Default)]
+pub struct UrlList {
+    /// In the order first seen; each string is shared with `seen`
+    urls: Vec<Arc<str>>,
+    seen: HashSet<Arc<str>>,
+    duplicates: usize,
+    invalid: usize,
+    invalid_examples: Vec<String>,
+}
+
+impl UrlList {
+    /// An empty list with room for about `n` URLs.
+    pub fn with_capacity(n: usize) -> Self {
+        Self {
+            urls: Vec::with_capacity(n),
+            seen: HashSet::with_capacity(n),
+            ..Self::default()
+        }
+    }
+
+    /// Add one entry. Surrounding whitespace is ignored, as are blank
+    /// entries and `#` comments.
+    pub fn push(&mut self, entry: &str) {
+        let entry = entry.trim();
+        if entry.is_empty() || entry.starts_with('#') {
+            return;
+        }
+        let (prefix, url) = match entry.strip_prefix('!') {
+            Some(url) => ("!", url.trim_start()),
+            None => ("", entry),
+        };
+        let Some(normalized) = normalize_url(url) else {
+            self.invalid += 1;
+            if self.invalid_examples.len() < INVALID_EXAMPLES {
+                self.invalid_examples.push(redact_userinfo(entry));
+            }
+            return;
+        };
+        let key: Arc<str> = if prefix.is_empty() {
+            normalized.into()
+        } else {
+            format!("{prefix}{normalized}").into()
+        };
+        if self.seen.contains(&key) {
+            self.duplicates += 1;
+            return;
+        }
+        self.seen.insert(key.clone());
+        self.urls.push(key);
+    }
+
+    /// Add one entry per line of `reader`.
+    pub fn read_from(&mut self, mut reader: impl BufRead) -> io::Result<()> {
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            self.push(&line);
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// Add the lines of the file at `path`, or of stdin when `path` is `-`.
+    pub fn read_path(&mut self, path: &Path) -> io::Result<()> {
+        if path == Path::new("-") {
+            return self.read_from(io::stdin().lock());
+        }
+        let file = File::open(path)?;
+        let hint = file.metadata().map_or(0, |m| m.len() / TYPICAL_LINE_BYTES) as usize;
+        self.urls.reserve(hint);
+        self.seen.reserve(hint);
+        self.read_from(BufReader::new(file))
+    }
+
+    pub fn urls(&self) -> &[Arc<str>] {
+        &self.urls
+    }
+
+    pub fn len(&self) -> usize {
+        self.urls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    pub fn counts(&self) -> InputCounts {
+        InputCounts {
+            unique: self.urls.len(),
+            duplicates: self.duplicates,
+            invalid: self.invalid,
+        }
+    }
+
+    /// The first few invalid entries, as given
+    pub fn invalid_examples(&self) -> &[String] {
+        &self.invalid_examples
+    }
+
+    pub fn into_urls(self) -> Vec<Arc<str>> {
+        self.urls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizing_folds_spelling_differences() {
+        assert_eq!(
+            normalize_url("HTTPS://Example.COM:443").as_deref(),
+            Some("https://example.com/")
+        );
+        assert_eq!(
+            normalize_url("http://example.com:8080/a?b=1#top").as_deref(),
+            Some("http://example.com:8080/a?b=1")
+        );
+        assert_eq!(normalize_url("example.com"), None);
+        assert_eq!(normalize_url("mailto:someone@example.com"), None);
+    }
+
+    #[test]
+    fn repeats_and_junk_are_counted_not_kept() {
+        let text = "\
+# production
+https://example.com
+https://EXAMPLE.com/
+
+!https://down.test
+! https://down.test
+https://down.test
+not a url
+https://example.com/#section
+";
+        let mut list = UrlList::default();
+        list.read_from(text.as_bytes()).unwrap();
+        let urls: Vec<&str> = list.urls().iter().map(|u| &**u).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://example.com/",
+                "!https://down.test/",
+                "https://down.test/"
+            ]
+        );
+        assert_eq!(
+            list.counts(),
+            InputCounts {
+                unique: 3,
+                duplicates: 3,
+                invalid: 1
+            }
+        );
+        assert_eq!(list.invalid_examples(), ["not a url"]);
+    }
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::serde_util;
+
+/// What a pass cost the monitor itself, for tuning `worker_threads`.
+/// Figures the platform can't provide (or that couldn't be read) are
+/// `None`; RSS and CPU time are read from `/proc/self` on Linux only.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ResourceUsage {
+    /// From the start of the pass until its last result
+    #[serde(rename = "wall_ms", with = "serde_util::duration_ms")]
+    pub wall: Duration,
+    /// Worker threads spawned for the pass
+    pub threads_spawned: usize,
+    /// User and system CPU time of the whole process during the pass
+    #[serde(
+        rename = "cpu_ms",
+        with = "serde_util::opt_duration_ms",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpu: Option<Duration>,
+    /// Peak resident set size of the process so far, not only this pass
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_rss_bytes: Option<u64>,
+}
+
+/// Started with a pass, finished once its results are in.
+#[derive(Debug)]
+pub(crate) struct UsageProbe {
+    started: Instant,
+    cpu_at_start: Option<Duration>,
+}
+
+impl UsageProbe {
+    pub(crate) fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            cpu_at_start: process_cpu_time(),
+        }
+    }
+
+    pub(crate) fn finish(&self, threads_spawned: usize) -> ResourceUsage {
+        let cpu = self
+            .cpu_at_start
+            .zip(process_cpu_time())
+            .map(|(start, end)| end.saturating_sub(start));
+        ResourceUsage {
+            wall: self.started.elapsed(),
+            threads_spawned,
+            cpu,
+            peak_rss_bytes: peak_rss_bytes(),
+        }
+    }
+}
+
+/// Clock ticks per second of the times in `/proc/self/stat` (`USER_HZ`,
+/// which the kernel keeps at 100 for userspace).
+#[cfg(target_os = "linux")]
+const TICKS_PER_SEC: u64 = 100;
+
+/// utime + stime from the contents of `/proc/self/stat`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_cpu_ticks(stat: &str) -> Option<u64> {
+    // The command name in parentheses may hold spaces; fields count from
+    // the state right after it (field 3), so utime (14) and stime (15)
+    // are the 12th and 13th
+    let (_, fields) = stat.rsplit_once(')')?;
+    let mut fields = fields.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// `VmHWM` from the contents of `/proc/self/status`, in bytes.
+#[cfg(any(target_os = "linux", test))]
+fn parse_peak_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find_map(|l| l.strip_prefix("VmHWM:"))?;
+    let kib: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(target_os = "linux")]
+fn process_cpu_time() -> Option<Duration> {
+    let ticks = parse_cpu_ticks(&std::fs::read_to_string("/proc/self/stat").ok()?)?;
+    Some(Duration::from_millis(ticks * 1000 / TICKS_PER_SEC))
+}
+
+#[cfg(target_os = "linux")]
+fn peak_rss_bytes() -> Option<u64> {
+    parse_peak_rss(&std::fs::read_to_string("/proc/self/status").ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn peak_rss_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proc_files_parse() {
+        let stat = "4242 (web monitor) S 1 4242 4242 0 -1 4194560 1250 0 0 0 \
+                    37 12 0 0 20 0 9 0 123456 987654321 2048";
+        assert_eq!(parse_cpu_ticks(stat), Some(49));
+        assert_eq!(parse_cpu_ticks("4242 (cut"), None);
+
+        let status =
+            "Name:\twebsite-monit\nVmPeak:\t  300000 kB\nVmHWM:\t   23456 kB\nThreads:\t9\n";
+        assert_eq!(parse_peak_rss(status), Some(23456 * 1024));
+        assert_eq!(parse_peak_rss("Name:\tx\n"), None);
+    }
+}
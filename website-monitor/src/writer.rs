@@ -0,0 +1,41 @@
+use std::io;
+
+use crate::WebsiteStatus;
+
+/// A sink results are handed to one at a time as a pass reports them.
+pub trait ResultWriter {
+    /// Emit one result. `treat_4xx_as_failure` is the pass's success policy.
+    fn write_result(&mut self, ws: &WebsiteStatus, treat_4xx_as_failure: bool) -> io::Result<()>;
+
+    /// Push out anything buffered; called at the end of each pass.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// How bad a result is, for sinks with levels of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Failed check
+    Error,
+    /// Succeeded, but only after retries, with a tolerated 4xx, while its
+    /// content is flapping or while its latency has regressed
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn of(ws: &WebsiteStatus, treat_4xx_as_failure: bool) -> Self {
+        if !ws.is_success(treat_4xx_as_failure) {
+            Severity::Error
+        } else if ws.retries > 0
+            || matches!(ws.status, Ok(400..=499))
+            || ws.content_flapping.is_some()
+            || ws.latency_trend.is_some()
+        {
+            Severity::Warning
+        } else {
+            Severity::Info
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! WebSocket endpoint checks (enabled with the `tungstenite` feature).
+
+use std::{
+    io,
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use tungstenite::{
+    Error as WsError, Message, client::IntoClientRequest, handshake::HandshakeError,
+};
+
+use crate::{CheckError, WsProbe};
+
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
+}
+
+/// Perform the WebSocket handshake (and optional probe) against `url`.
+/// Returns the handshake status code (101) on success.
+pub(crate) fn check_ws(
+    url: &str,
+    timeout: Duration,
+    probe: Option<&WsProbe>,
+) -> Result<u16, CheckError> {
+    let request = url
+        .into_client_request()
+        .map_err(|e| format!("invalid websocket url: {e}"))?;
+    let uri = request.uri();
+    let host = uri.host().ok_or("websocket url has no host")?.to_string();
+    let default_port = if uri.scheme_str() == Some("wss") {
+        443
+    } else {
+        80
+    };
+    let port = uri.port_u16().unwrap_or(default_port);
+
+    let deadline = Instant::now() + timeout;
+    let addr = (host.trim_start_matches('[').trim_end_matches(']'), port)
+        .to_socket_addrs()
+        .map_err(|e| format!("websocket dns error: {e}"))?
+        .next()
+        .ok_or_else(|| format!("websocket dns error: no address for {host}"))?;
+
+    let stream = TcpStream::connect_timeout(&addr, timeout).map_err(|e| {
+        if is_timeout(&e) {
+            CheckError::TimedOut { limit: timeout }
+        } else {
+            format!("websocket connect error: {e}").into()
+        }
+    })?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|e| format!("websocket connect error: {e}"))?;
+
+    let (mut socket, response) = match tungstenite::client_tls(request, stream) {
+        Ok(pair) => pair,
+        Err(HandshakeError::Failure(WsError::Http(resp))) => {
+            return Err(format!(
+                "websocket handshake rejected: HTTP {}",
+                resp.status().as_u16()
+            )
+            .into());
+        }
+        Err(HandshakeError::Failure(WsError::Io(e))) if is_timeout(&e) => {
+            return Err(CheckError::TimedOut { limit: timeout });
+        }
+        Err(HandshakeError::Failure(e)) => {
+            return Err(format!("websocket handshake failed: {e}").into());
+        }
+        Err(HandshakeError::Interrupted(_)) => {
+            return Err(CheckError::TimedOut { limit: timeout });
+        }
+    };
+    let status = response.status().as_u16();
+
+    if let Some(probe) = probe {
+        let frame = match probe {
+            WsProbe::Ping => Message::Ping(Vec::new().into()),
+            WsProbe::Text(text) => Message::text(text.clone()),
+        };
+        socket
+            .send(frame)
+            .map_err(|e| format!("websocket send error: {e}"))?;
+
+        loop {
+            if Instant::now() >= deadline {
+                return Err(CheckError::TimedOut { limit: timeout });
+            }
+            match socket.read() {
+                Ok(Message::Pong(_)) if *probe == WsProbe::Ping => break,
+                Ok(Message::Text(_)) | Ok(Message::Binary(_))
+                    if matches!(probe, WsProbe::Text(_)) =>
+                {
+                    break;
+                }
+                Ok(Message::Close(_)) => {
+                    return Err("websocket closed before responding".into());
+                }
+                // Server pings and unrelated frames: keep waiting
+                Ok(_) => continue,
+                Err(WsError::Io(e)) if is_timeout(&e) => {
+                    return Err(CheckError::TimedOut { limit: timeout });
+                }
+                Err(e) => return Err(format!("websocket read error: {e}").into()),
+            }
+        }
+    }
+
+    let _ = socket.close(None);
+    Ok(status)
+}
@@ -0,0 +1,285 @@
+use chrono::{DateTime, TimeZone, Utc};
+use httpmock::{Method::HEAD, prelude::*};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use website_monitor::{
+    BudgetExhausted, CheckOutcome, Clock, ContinuousConfig, CronSchedule, HttpMethod,
+    MonitorConfig, Shutdown, Target, monitor_continuous,
+};
+
+/// Jumps straight to each deadline.
+struct FakeClock(Mutex<DateTime<Utc>>);
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+
+    fn sleep_until(&self, deadline: DateTime<Utc>, _: &Shutdown) {
+        let mut now = self.0.lock().unwrap();
+        *now = (*now).max(deadline);
+    }
+}
+
+fn at(h: u32, m: u32, s: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 4, h, m, s).unwrap()
+}
+
+fn config() -> MonitorConfig {
+    MonitorConfig {
+        worker_threads: 4,
+        request_timeout: Duration::from_secs(2),
+        ..MonitorConfig::default()
+    }
+}
+
+/// When each pass ran, its result count and the budget its summary says
+/// ran out.
+type Passes = Vec<(DateTime<Utc>, usize, Option<BudgetExhausted>)>;
+
+/// Run until a budget ends the run or `max_passes` passes are done.
+fn run(
+    targets: Vec<Target>,
+    config: &MonitorConfig,
+    continuous: &ContinuousConfig,
+    start: DateTime<Utc>,
+    max_passes: usize,
+) -> Passes {
+    let clock = FakeClock(Mutex::new(start));
+    let shutdown = Shutdown::new();
+    let mut passes = Vec::new();
+    monitor_continuous(targets, config, continuous, &shutdown, &clock, |report| {
+        passes.push((
+            clock.now(),
+            report.results.len(),
+            report.summary.budget_exhausted,
+        ));
+        if passes.len() == max_passes {
+            shutdown.cancel();
+        }
+    });
+    passes
+}
+
+#[test]
+fn request_budget_stops_the_run_mid_pass() {
+    let server = MockServer::start();
+    let a = server.mock(|when, then| {
+        when.method(GET).path("/a");
+        then.status(200);
+    });
+    let b = server.mock(|when, then| {
+        when.method(GET).path("/b");
+        then.status(200);
+    });
+    let continuous = ContinuousConfig {
+        interval: Duration::from_secs(10),
+        request_budget: Some(5),
+        ..ContinuousConfig::default()
+    };
+    let targets = vec![Target::new(server.url("/a")), Target::new(server.url("/b"))];
+    let passes = run(targets, &config(), &continuous, at(9, 0, 0), 10);
+
+    let exhausted = Some(BudgetExhausted::Requests { limit: 5 });
+    assert_eq!(
+        passes,
+        [
+            (at(9, 0, 0), 2, None),
+            (at(9, 0, 10), 2, None),
+            (at(9, 0, 20), 1, exhausted),
+        ]
+    );
+    assert_eq!(a.hits() + b.hits(), 5);
+}
+
+#[test]
+fn retries_count_against_the_budget() {
+    let server = MockServer::start();
+    // Errors are retried, status codes aren't
+    let failing = server.mock(|when, then| {
+        when.method(GET).path("/down");
+        then.status(200).delay(Duration::from_millis(300));
+    });
+    let config = MonitorConfig {
+        max_retries: 2,
+        request_timeout: Duration::from_millis(100),
+        ..config()
+    };
+    let continuous = ContinuousConfig {
+        interval: Duration::from_secs(10),
+        request_budget: Some(4),
+        ..ContinuousConfig::default()
+    };
+    let passes = run(
+        vec![Target::new(server.url("/down"))],
+        &config,
+        &continuous,
+        at(9, 0, 0),
+        10,
+    );
+    // Three requests for the first pass, the last one for the second, whose
+    // retries are cut off
+    assert_eq!(passes.len(), 2);
+    assert_eq!(passes[1].2, Some(BudgetExhausted::Requests { limit: 4 }));
+    assert_eq!(failing.hits(), 4);
+}
+
+#[test]
+fn budgets_start_over_at_the_reset_time() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/");
+        then.status(200);
+    });
+    let continuous = ContinuousConfig {
+        interval: Duration::from_secs(10),
+        request_budget: Some(3),
+        budget_reset: Some(CronSchedule::parse("reset", "0 0 0 * * *", None).unwrap()),
+        ..ContinuousConfig::default()
+    };
+    let passes = run(
+        vec![Target::new(server.url("/"))],
+        &config(),
+        &continuous,
+        at(23, 59, 0),
+        6,
+    );
+
+    let exhausted = Some(BudgetExhausted::Requests { limit: 3 });
+    let midnight = Utc.with_ymd_and_hms(2024, 3, 5, 0, 0, 0).unwrap();
+    let times: Vec<DateTime<Utc>> = passes.iter().map(|(t, ..)| *t).collect();
+    assert_eq!(
+        times,
+        [
+            at(23, 59, 0),
+            at(23, 59, 10),
+            at(23, 59, 20),
+            midnight,
+            midnight + chrono::Duration::seconds(10),
+            midnight + chrono::Duration::seconds(20),
+        ]
+    );
+    assert_eq!(passes[2].2, exhausted);
+    assert_eq!(passes[5].2, exhausted);
+    assert!(passes[..2].iter().all(|(_, _, e)| e.is_none()));
+    assert_eq!(mock.hits(), 6);
+}
+
+#[test]
+fn run_budget_ends_the_run_before_the_deadline() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/");
+        then.status(200);
+    });
+    let continuous = ContinuousConfig {
+        interval: Duration::from_secs(10),
+        run_budget: Some(Duration::from_secs(25)),
+        ..ContinuousConfig::default()
+    };
+    let passes = run(
+        vec![Target::new(server.url("/"))],
+        &config(),
+        &continuous,
+        at(9, 0, 0),
+        10,
+    );
+    let exhausted = Some(BudgetExhausted::Time {
+        limit: Duration::from_secs(25),
+    });
+    assert_eq!(
+        passes,
+        [
+            (at(9, 0, 0), 1, None),
+            (at(9, 0, 10), 1, None),
+            (at(9, 0, 20), 1, exhausted),
+        ]
+    );
+    assert_eq!(mock.hits(), 3);
+}
+
+#[test]
+fn targets_a_retry_left_without_budget_are_not_failed() {
+    let server = MockServer::start();
+    let broken = server.mock(|when, then| {
+        when.method(GET).path("/broken");
+        then.status(500);
+    });
+    let ok = server.mock(|when, then| {
+        when.method(GET).path("/ok");
+        then.status(200);
+    });
+    // One worker, so the retry of /broken takes the request /ok needed
+    let config = MonitorConfig {
+        worker_threads: 1,
+        retry_policy: Some(Arc::new(|_: &CheckOutcome<'_>, attempt: u32, _| {
+            (attempt < 1).then_some(Duration::ZERO)
+        })),
+        ..config()
+    };
+    let continuous = ContinuousConfig {
+        interval: Duration::from_secs(10),
+        request_budget: Some(2),
+        failures_before_down: 1,
+        ..ContinuousConfig::default()
+    };
+    let targets = vec![
+        Target::new(server.url("/broken")),
+        Target::new(server.url("/ok")),
+    ];
+    let clock = FakeClock(Mutex::new(at(9, 0, 0)));
+    let mut reports = Vec::new();
+    monitor_continuous(
+        targets,
+        &config,
+        &continuous,
+        &Shutdown::new(),
+        &clock,
+        |report| reports.push(report),
+    );
+
+    assert_eq!(reports.len(), 1);
+    let summary = &reports[0].summary;
+    assert_eq!((summary.ok, summary.err, summary.budget_skipped), (0, 1, 1));
+    assert_eq!(
+        summary.budget_exhausted,
+        Some(BudgetExhausted::Requests { limit: 2 })
+    );
+    assert_eq!(reports[0].results.len(), 1);
+    let broken_url = server.url("/broken");
+    assert!(summary.transitions.iter().all(|t| t.target == broken_url));
+    assert_eq!(summary.down, [broken_url]);
+    assert_eq!((broken.hits(), ok.hits()), (2, 0));
+}
+
+#[test]
+fn get_after_a_refused_head_is_paid_for() {
+    let server = MockServer::start();
+    let head = server.mock(|when, then| {
+        when.method(HEAD);
+        then.status(405);
+    });
+    let get = server.mock(|when, then| {
+        when.method(GET);
+        then.status(200);
+    });
+    let config = MonitorConfig {
+        worker_threads: 1,
+        method: HttpMethod::Head,
+        ..config()
+    };
+    let continuous = ContinuousConfig {
+        interval: Duration::from_secs(10),
+        request_budget: Some(3),
+        ..ContinuousConfig::default()
+    };
+    let targets = vec![Target::new(server.url("/a")), Target::new(server.url("/b"))];
+    let passes = run(targets, &config, &continuous, at(9, 0, 0), 10);
+
+    // HEAD and GET for /a, then only the HEAD for /b fits
+    let exhausted = Some(BudgetExhausted::Requests { limit: 3 });
+    assert_eq!(passes, [(at(9, 0, 0), 2, exhausted)]);
+    assert_eq!((head.hits(), get.hits()), (2, 1));
+}
@@ -0,0 +1,109 @@
+use httpmock::prelude::*;
+use std::{net::SocketAddr, process::Command};
+use website_monitor::{CanaryOptions, CanaryPair, MonitorConfig, canary};
+
+/// Served identically by both origins, except `/page`'s body and `/new`,
+/// which only the candidate has.
+fn origin(page_body: &str, has_new: bool) -> MockServer {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/page");
+        then.status(200).body(page_body);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/same");
+        then.status(200).body("same everywhere");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/new");
+        then.status(if has_new { 200 } else { 404 });
+    });
+    server
+}
+
+/// Without a port of its own, a URL is sent to the port of the origin.
+fn url(path: &str) -> String {
+    format!("http://canary.test{path}")
+}
+
+#[test]
+fn divergent_origins_are_reported_per_url() {
+    let (old, new) = (origin("old page", false), origin("new page", true));
+    let pairs = ["/same", "/page", "/new"]
+        .iter()
+        .map(|p| CanaryPair::new(&url(p), *old.address(), *new.address()))
+        .collect();
+    let comparisons = canary(pairs, MonitorConfig::default(), &CanaryOptions::default());
+
+    let [same, page, added] = comparisons.as_slice() else {
+        panic!("one comparison per URL: {comparisons:?}");
+    };
+    assert_eq!(same.url, url("/same"));
+    assert!(!same.diverges(), "{same:?}");
+    assert_eq!(same.content_match, Some(true));
+
+    assert!(page.status_match);
+    assert_eq!(page.content_match, Some(false));
+    assert!(page.diverges());
+
+    assert!(!added.status_match && added.diverges());
+    assert_eq!(added.baseline.as_ref().unwrap().status, Ok(404));
+    assert_eq!(added.candidate.as_ref().unwrap().status, Ok(200));
+    assert_eq!(added.candidate.as_ref().unwrap().address, *new.address());
+}
+
+#[test]
+fn pairs_hold_when_one_origin_is_down() {
+    let old = origin("page", false);
+    // Nothing listens here once the listener is gone
+    let gone: SocketAddr = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    };
+    let pairs = vec![
+        CanaryPair::new(&url("/page"), *old.address(), gone),
+        CanaryPair::new(&url("/same"), *old.address(), gone),
+    ];
+    let comparisons = canary(pairs, MonitorConfig::default(), &CanaryOptions::default());
+    assert_eq!(comparisons.len(), 2);
+    for c in &comparisons {
+        assert_eq!(c.baseline.as_ref().unwrap().status, Ok(200));
+        assert!(c.candidate.as_ref().unwrap().status.is_err());
+        assert!(!c.status_match && c.diverges());
+        assert_eq!((c.latency_delta, c.content_match), (None, None));
+    }
+}
+
+#[test]
+fn cli_prints_mismatches_and_exits_non_zero() {
+    let (old, new) = (origin("old page", false), origin("new page", true));
+    let run = |paths: &[&str]| {
+        let out = Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+            .arg("canary")
+            .args(["--baseline", &old.address().to_string()])
+            .args(["--candidate", &new.address().to_string()])
+            .args(paths.iter().map(|p| url(p)))
+            .output()
+            .expect("binary runs");
+        (
+            out.status.code(),
+            String::from_utf8_lossy(&out.stdout).into_owned(),
+        )
+    };
+
+    let (code, stdout) = run(&["/same", "/page"]);
+    assert_eq!(code, Some(1), "{stdout}");
+    assert!(
+        stdout.contains(&format!("MISMATCH {}: body differs", url("/page"))),
+        "{stdout}"
+    );
+    assert!(!stdout.contains(&format!("MISMATCH {}", url("/same"))));
+    assert!(stdout.contains("2 URLs compared: 1 match, 1 diverge"));
+
+    let (code, stdout) = run(&["/new"]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.contains("status 404 vs 200"), "{stdout}");
+
+    let (code, stdout) = run(&["/same"]);
+    assert_eq!(code, Some(0), "{stdout}");
+}
@@ -0,0 +1,203 @@
+#![cfg(feature = "certs")]
+
+use chrono::{DateTime, Duration as Days, Utc};
+use ring::{
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use rustls::{
+    ServerConfig, ServerConnection,
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+};
+use std::{
+    io::Read,
+    net::TcpListener,
+    process::Command,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+use website_monitor::{CheckError, cert_inventory};
+
+// --- a minimal DER writer, enough for one self-signed Ed25519 certificate
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match content.len() {
+        n if n < 0x80 => out.push(n as u8),
+        n if n <= 0xff => out.extend([0x81, n as u8]),
+        n => out.extend([0x82, (n >> 8) as u8, n as u8]),
+    }
+    out.extend(content);
+    out
+}
+
+fn seq(parts: &[Vec<u8>]) -> Vec<u8> {
+    tlv(0x30, &parts.concat())
+}
+
+fn name(cn: &str) -> Vec<u8> {
+    let atv = seq(&[tlv(0x06, &[0x55, 0x04, 0x03]), tlv(0x0c, cn.as_bytes())]);
+    seq(&[tlv(0x31, &atv)])
+}
+
+fn utc_time(t: DateTime<Utc>) -> Vec<u8> {
+    tlv(0x17, t.format("%y%m%d%H%M%SZ").to_string().as_bytes())
+}
+
+/// Self-signed certificate for `localhost` and 127.0.0.1 valid from a day ago
+/// until `not_after`.
+fn self_signed(
+    cn: &str,
+    not_after: DateTime<Utc>,
+) -> (CertificateDer<'static>, PrivateKeyDer<'static>) {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+    let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+    let ed25519 = seq(&[tlv(0x06, &[0x2b, 0x65, 0x70])]);
+
+    let mut public_key = vec![0];
+    public_key.extend(key.public_key().as_ref());
+    let sans = seq(&[tlv(0x82, b"localhost"), tlv(0x87, &[127, 0, 0, 1])]);
+    let san_extension = seq(&[tlv(0x06, &[0x55, 0x1d, 0x11]), tlv(0x04, &sans)]);
+    let tbs = seq(&[
+        tlv(0xa0, &tlv(0x02, &[2])),
+        tlv(0x02, &[0x01, 0x23]),
+        ed25519.clone(),
+        name(cn),
+        seq(&[utc_time(Utc::now() - Days::days(1)), utc_time(not_after)]),
+        name(cn),
+        seq(&[ed25519.clone(), tlv(0x03, &public_key)]),
+        tlv(0xa3, &seq(&[san_extension])),
+    ]);
+    let mut signature = vec![0];
+    signature.extend(key.sign(&tbs).as_ref());
+    let cert = seq(&[tbs, ed25519, tlv(0x03, &signature)]);
+
+    (
+        CertificateDer::from(cert),
+        PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(pkcs8.as_ref().to_vec())),
+    )
+}
+
+/// A TLS server on 127.0.0.1 presenting a fresh certificate; returns its port
+/// and a count of the connections it accepted.
+fn serve(cn: &str, not_after: DateTime<Utc>) -> (u16, Arc<AtomicUsize>) {
+    let (cert, key) = self_signed(cn, not_after);
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert], key)
+        .unwrap();
+    let config = Arc::new(config);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let counter = accepted.clone();
+    thread::spawn(move || {
+        for sock in listener.incoming() {
+            let Ok(mut sock) = sock else { continue };
+            counter.fetch_add(1, Ordering::SeqCst);
+            let config = config.clone();
+            thread::spawn(move || {
+                let _ = sock.set_read_timeout(Some(Duration::from_secs(2)));
+                let mut conn = ServerConnection::new(config).unwrap();
+                while conn.is_handshaking() {
+                    if conn.complete_io(&mut sock).is_err() {
+                        return;
+                    }
+                }
+                let _ = conn.complete_io(&mut sock);
+                let _ = conn.reader().read(&mut [0; 64]);
+            });
+        }
+    });
+    (port, accepted)
+}
+
+/// A port nothing is listening on.
+fn closed_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+#[test]
+fn each_host_is_inspected_once() {
+    let (port, accepted) = serve("localhost", Utc::now() + Days::days(10));
+    let urls = [
+        format!("https://127.0.0.1:{port}/a"),
+        format!("https://127.0.0.1:{port}/b?x=1"),
+        format!("http://127.0.0.1:{port}/"),
+        "not a url".to_string(),
+    ];
+
+    let entries = cert_inventory(&urls, Duration::from_secs(5));
+    assert_eq!(entries.len(), 1);
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+    let entry = &entries[0];
+    assert_eq!((entry.host.as_str(), entry.port), ("127.0.0.1", port));
+    let cert = entry.result.as_ref().expect("handshake succeeds");
+    assert_eq!(cert.subject, "CN=localhost");
+    assert_eq!(cert.issuer, "CN=localhost");
+    assert_eq!(cert.sans, ["localhost", "127.0.0.1"]);
+    assert_eq!(cert.days_remaining(Utc::now()), 9);
+    assert!(cert.not_before < Utc::now());
+    // Self-signed: reported, but flagged
+    assert!(cert.untrusted.is_some());
+    assert!(entry.expires_within(14, Utc::now()));
+    assert!(!entry.expires_within(7, Utc::now()));
+}
+
+#[test]
+fn soonest_expiry_first_and_failures_last() {
+    let (later, _) = serve("later", Utc::now() + Days::days(200));
+    let (sooner, _) = serve("sooner", Utc::now() + Days::days(3));
+    let dead = closed_port();
+    let urls = [
+        format!("https://127.0.0.1:{dead}/"),
+        format!("https://localhost:{later}/"),
+        format!("https://127.0.0.1:{sooner}/"),
+    ];
+
+    let entries = cert_inventory(&urls, Duration::from_secs(5));
+    let ports: Vec<u16> = entries.iter().map(|e| e.port).collect();
+    assert_eq!(ports, [sooner, later, dead]);
+    assert_eq!(entries[0].result.as_ref().unwrap().subject, "CN=sooner");
+    assert_eq!(entries[1].result.as_ref().unwrap().subject, "CN=later");
+    assert!(matches!(entries[2].result, Err(CheckError::Transport(_))));
+}
+
+fn certs_cli(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+        .arg("certs")
+        .args(args)
+        .output()
+        .expect("binary runs")
+}
+
+#[test]
+fn cli_exits_nonzero_only_when_something_expires_soon() {
+    let (port, _) = serve("localhost", Utc::now() + Days::days(10));
+    let dead = closed_port();
+    let url = format!("https://127.0.0.1:{port}/");
+    let dead_url = format!("https://127.0.0.1:{dead}/");
+
+    let out = certs_cli(&[&url, &dead_url, "--warn-days", "14"]);
+    assert_eq!(out.status.code(), Some(1));
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.starts_with("HOST"), "{text}");
+    assert!(text.contains(&format!("127.0.0.1:{port}")));
+    assert!(text.contains("CN=localhost"));
+    assert!(text.contains("Unreachable (1):"));
+
+    let out = certs_cli(&[&url, "--warn-days", "5", "--format", "json"]);
+    assert_eq!(out.status.code(), Some(0));
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(v[0]["port"], port);
+    assert_eq!(v[0]["result"]["Ok"]["sans"][1], "127.0.0.1");
+}
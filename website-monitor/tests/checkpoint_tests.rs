@@ -0,0 +1,96 @@
+use httpmock::prelude::*;
+use std::{fs, io::Write, path::PathBuf, thread, time::Duration};
+use website_monitor::{Checkpoint, MonitorConfig, Shutdown, run_pass_checkpointed};
+
+fn checkpoint_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "website-monitor-{name}-{}.ndjson",
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn one_worker() -> MonitorConfig {
+    MonitorConfig {
+        worker_threads: 1,
+        request_timeout: Duration::from_secs(2),
+        ..MonitorConfig::default()
+    }
+}
+
+#[test]
+fn resumed_pass_fetches_each_url_once() {
+    let server = MockServer::start();
+    let mocks: Vec<_> = (0..6)
+        .map(|i| {
+            server.mock(|when, then| {
+                when.method(GET).path(format!("/page{i}"));
+                then.status(200).delay(Duration::from_millis(150));
+            })
+        })
+        .collect();
+    let urls: Vec<String> = (0..6).map(|i| server.url(format!("/page{i}"))).collect();
+    let path = checkpoint_path("resume");
+
+    // First run: cancelled partway through
+    let shutdown = Shutdown::new();
+    let canceller = {
+        let s = shutdown.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(350));
+            s.cancel();
+        })
+    };
+    let mut checkpoint = Checkpoint::open(&path).unwrap();
+    let first = run_pass_checkpointed(urls.clone(), one_worker(), Some(shutdown), &mut checkpoint);
+    canceller.join().unwrap();
+    drop(checkpoint);
+    let done = first.results.len();
+    assert!(done > 0 && done < 6, "first run completed {done} of 6");
+
+    // Second run picks up the rest and reports everything
+    let mut checkpoint = Checkpoint::open(&path).unwrap();
+    assert_eq!(checkpoint.len(), done);
+    let second = run_pass_checkpointed(urls.clone(), one_worker(), None, &mut checkpoint);
+    assert_eq!(second.results.len(), 6);
+    assert_eq!(second.summary.ok, 6);
+    for mock in &mocks {
+        assert_eq!(mock.hits(), 1);
+    }
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn half_written_last_line_is_ignored_and_trimmed() {
+    let server = MockServer::start();
+    let ok = server.mock(|when, then| {
+        when.method(GET).path("/ok");
+        then.status(200);
+    });
+    let path = checkpoint_path("torn");
+
+    let mut checkpoint = Checkpoint::open(&path).unwrap();
+    run_pass_checkpointed(vec![server.url("/ok")], one_worker(), None, &mut checkpoint);
+    drop(checkpoint);
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"{\"url\": \"http://half").unwrap();
+    drop(file);
+
+    let mut checkpoint = Checkpoint::open(&path).unwrap();
+    assert_eq!(checkpoint.len(), 1);
+    let report = run_pass_checkpointed(
+        vec![server.url("/ok"), server.url("/other")],
+        one_worker(),
+        None,
+        &mut checkpoint,
+    );
+    drop(checkpoint);
+    assert_eq!(report.results.len(), 2);
+    assert_eq!(ok.hits(), 1);
+    // The fragment is gone and new lines follow the good ones
+    let text = fs::read_to_string(&path).unwrap();
+    assert_eq!(text.lines().count(), 2);
+    assert!(!text.contains("http://half"));
+    let _ = fs::remove_file(&path);
+}
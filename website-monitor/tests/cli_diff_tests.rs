@@ -0,0 +1,115 @@
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+fn diff(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+        .arg("diff")
+        .args(args)
+        .output()
+        .expect("binary runs")
+}
+
+fn stdout(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+fn stderr(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stderr).into_owned()
+}
+
+#[test]
+fn text_diff_lists_every_category() {
+    let old = fixture("diff_old.json");
+    let new = fixture("diff_new.json");
+    let out = diff(&[old.to_str().unwrap(), new.to_str().unwrap()]);
+
+    assert_eq!(out.status.code(), Some(1), "{}", stderr(&out));
+    let text = stdout(&out);
+    assert!(text.contains("Changed state (2):"), "{text}");
+    assert!(text.contains("[OK -> ERR] https://shop.example.com/ | 200 -> 503"));
+    assert!(text.contains("[ERR -> OK] https://status.example.com/"));
+    assert!(text.contains("Latency regressions (1):"));
+    assert!(text.contains("https://api.example.com/health | 120 ms -> 610 ms"));
+    assert!(text.contains("Added (1):\n  https://beta.example.com/"));
+    assert!(text.contains("Removed (1):\n  https://legacy.example.com/"));
+}
+
+#[test]
+fn json_diff_has_the_same_content() {
+    let old = fixture("diff_old.json");
+    let new = fixture("diff_new.json");
+    let out = diff(&[
+        old.to_str().unwrap(),
+        new.to_str().unwrap(),
+        "--format",
+        "json",
+    ]);
+
+    assert_eq!(out.status.code(), Some(1));
+    let v: serde_json::Value = serde_json::from_str(&stdout(&out)).unwrap();
+    assert_eq!(v["changed"].as_array().unwrap().len(), 2);
+    assert_eq!(v["regressions"][0]["url"], "https://api.example.com/health");
+    assert_eq!(v["regressions"][0]["after_ms"], 610.0);
+    assert_eq!(v["added"], serde_json::json!(["https://beta.example.com/"]));
+    assert_eq!(
+        v["removed"],
+        serde_json::json!(["https://legacy.example.com/"])
+    );
+}
+
+#[test]
+fn loose_thresholds_and_identical_files_pass() {
+    let old = fixture("diff_old.json");
+    let out = diff(&[old.to_str().unwrap(), old.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(stdout(&out).trim(), "No differences");
+
+    // A huge threshold hides the latency regression, but the new 503 still fails
+    let new = fixture("diff_new.json");
+    let out = diff(&[
+        old.to_str().unwrap(),
+        new.to_str().unwrap(),
+        "--threshold-ms",
+        "10000",
+    ]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(!stdout(&out).contains("Latency regressions"));
+}
+
+#[test]
+fn bad_input_names_the_file() {
+    let dir = std::env::temp_dir().join(format!("wm-diff-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let corrupt = dir.join("corrupt.json");
+    std::fs::write(&corrupt, "{\"results\": [").unwrap();
+    let good = fixture("diff_old.json");
+
+    let out = diff(&[good.to_str().unwrap(), corrupt.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(2));
+    let err = stderr(&out);
+    assert!(err.contains("corrupt.json: corrupt JSON"), "{err}");
+
+    let other = fixture("not_results.json");
+    let out = diff(&[other.to_str().unwrap(), good.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(2));
+    let err = stderr(&out);
+    assert!(
+        err.contains("not_results.json: not a website-monitor JSON result file"),
+        "{err}"
+    );
+
+    let missing = dir.join("missing.json");
+    let out = diff(&[good.to_str().unwrap(), missing.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(stderr(&out).contains("missing.json: cannot read file"));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,70 @@
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+fn merge(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+        .arg("merge")
+        .args(args)
+        .output()
+        .expect("binary runs")
+}
+
+fn stdout(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+#[test]
+fn unlabeled_files_are_named_after_the_file() {
+    let old = fixture("diff_old.json");
+    let new = fixture("diff_new.json");
+    let out = merge(&[old.to_str().unwrap(), new.to_str().unwrap()]);
+
+    assert_eq!(out.status.code(), Some(1));
+    let text = stdout(&out);
+    assert!(text.starts_with("Regions: diff_old, diff_new\n"), "{text}");
+    assert!(
+        text.contains("[PARTIAL OUTAGE] https://shop.example.com/ | diff_old=200 diff_new=503"),
+        "{text}"
+    );
+    assert!(
+        text.contains("[UP] https://legacy.example.com/ | diff_old=200 diff_new=-"),
+        "{text}"
+    );
+    assert!(
+        text.ends_with("3 up | 0 down | 2 partial outage\n"),
+        "{text}"
+    );
+}
+
+#[test]
+fn json_report_and_bad_input() {
+    let old = fixture("diff_old.json");
+    let new = fixture("diff_new.json");
+    let out = merge(&[
+        old.to_str().unwrap(),
+        new.to_str().unwrap(),
+        "--format",
+        "json",
+    ]);
+    let v: serde_json::Value = serde_json::from_str(&stdout(&out)).unwrap();
+    assert_eq!(v["regions"], serde_json::json!(["diff_old", "diff_new"]));
+    let shop = v["urls"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|u| u["url"] == "https://shop.example.com/")
+        .unwrap();
+    assert_eq!(shop["availability"], "partial_outage");
+    assert_eq!(shop["outcomes"][1]["success"], false);
+
+    let out = merge(&[old.to_str().unwrap(), "/nonexistent/results.json"]);
+    assert_eq!(out.status.code(), Some(2));
+}
@@ -0,0 +1,119 @@
+use flate2::{Compression as Level, write::GzEncoder};
+use httpmock::prelude::*;
+use once_cell::sync::Lazy;
+use std::{io::Write, time::Duration};
+use website_monitor::{CheckError, Compression, Encoding, MonitorConfig, Target, run_pass};
+
+const TEXT: &str = "compress me please ";
+
+fn gzip(body: &[u8]) -> Vec<u8> {
+    let mut enc = GzEncoder::new(Vec::new(), Level::default());
+    enc.write_all(body).unwrap();
+    enc.finish().unwrap()
+}
+
+/// `/gzip` answers with a gzipped body, `/plain` with the same text as is,
+/// and `/bomb` with a few kilobytes that unpack to 4 MiB of zeros.
+static SERVER: Lazy<MockServer> = Lazy::new(|| {
+    let gz = gzip(TEXT.repeat(50).as_bytes());
+
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/gzip")
+            .header("accept-encoding", "gzip");
+        then.status(200).header("content-encoding", "gzip").body(gz);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/plain");
+        then.status(200).body(TEXT.repeat(50));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/bomb");
+        then.status(200)
+            .header("content-encoding", "gzip")
+            .body(gzip(&vec![0; 4 << 20]));
+    });
+    server
+});
+
+fn config(capture_body: bool) -> MonitorConfig {
+    MonitorConfig {
+        worker_threads: 2,
+        request_timeout: Duration::from_secs(2),
+        accept_encoding: vec![Encoding::Gzip],
+        capture_headers: true,
+        capture_body,
+        ..MonitorConfig::default()
+    }
+}
+
+fn compression(result: &website_monitor::WebsiteStatus) -> &Compression {
+    result.compression.as_ref().expect("compression recorded")
+}
+
+#[test]
+fn gzip_records_wire_and_decoded_sizes() {
+    let report = run_pass(vec![SERVER.url("/gzip")], config(true), None);
+    let ws = &report.results[0];
+    assert_eq!(ws.status, Ok(200));
+
+    let c = compression(ws);
+    assert_eq!(c.encoding.as_deref(), Some("gzip"));
+    let decoded = (TEXT.len() * 50) as u64;
+    assert_eq!(c.decoded_bytes, Some(decoded));
+    assert!(c.wire_bytes.unwrap() < decoded);
+    assert!(c.ratio().unwrap() > 1.0);
+    // The kept body is the decoded text
+    assert_eq!(ws.body.as_deref(), Some(TEXT.repeat(50).as_str()));
+}
+
+#[test]
+fn identity_body_has_equal_sizes() {
+    let report = run_pass(vec![SERVER.url("/plain")], config(true), None);
+    let c = compression(&report.results[0]);
+    assert_eq!(c.encoding, None);
+    assert_eq!(c.wire_bytes, c.decoded_bytes);
+    assert_eq!(c.ratio(), Some(1.0));
+}
+
+#[test]
+fn no_decoded_size_without_body_capture() {
+    let report = run_pass(vec![SERVER.url("/gzip")], config(false), None);
+    let c = compression(&report.results[0]);
+    assert!(c.wire_bytes.is_some());
+    assert_eq!(c.decoded_bytes, None);
+    assert_eq!(report.results[0].body, None);
+}
+
+#[test]
+fn body_limit_applies_after_decoding() {
+    let config = MonitorConfig {
+        max_body_bytes: Some(64 * 1024),
+        ..config(true)
+    };
+    let report = run_pass(vec![SERVER.url("/bomb")], config, None);
+    assert_eq!(
+        report.results[0].status,
+        Err(CheckError::BodyTooLarge {
+            limit: 64 * 1024,
+            observed_at_least: 64 * 1024 + 1,
+        })
+    );
+}
+
+#[test]
+fn require_compressed_fails_identity_responses() {
+    let targets = vec![
+        Target::new(SERVER.url("/gzip")).with_require_compressed(),
+        Target::new(SERVER.url("/plain")).with_require_compressed(),
+    ];
+    let report = run_pass(targets, config(false), None);
+    let status = |path: &str| {
+        let ws = report.results.iter().find(|r| r.url.ends_with(path));
+        ws.unwrap().status.clone()
+    };
+    assert_eq!(status("/gzip"), Ok(200));
+    assert_eq!(status("/plain"), Err(CheckError::Uncompressed));
+    assert_eq!(report.summary.classes.uncompressed, 1);
+}
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use httpmock::prelude::*;
+use regex::Regex;
+use std::{sync::Mutex, time::Duration};
+use website_monitor::{
+    Clock, ContinuousConfig, FlapConfig, MonitorConfig, Severity, Shutdown, Target,
+    monitor_continuous,
+};
+
+/// Jumps straight to each deadline.
+struct FakeClock(Mutex<DateTime<Utc>>);
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+
+    fn sleep_until(&self, deadline: DateTime<Utc>, _: &Shutdown) {
+        let mut now = self.0.lock().unwrap();
+        *now = (*now).max(deadline);
+    }
+}
+
+/// Run `passes` passes over one URL whose body switches to the next of
+/// `bodies` before each pass, returning whether each pass flagged it.
+fn flags(bodies: &[&str], passes: usize, ignore: Vec<Regex>, min_occurrences: usize) -> Vec<bool> {
+    let server = MockServer::start();
+    let mut mock = server.mock(|when, then| {
+        when.method(GET).path("/page");
+        then.status(200).body(bodies[0]);
+    });
+    let config = MonitorConfig {
+        worker_threads: 1,
+        request_timeout: Duration::from_secs(2),
+        hash_body: true,
+        body_hash_ignore: ignore,
+        ..MonitorConfig::default()
+    };
+    let continuous = ContinuousConfig {
+        interval: Duration::from_secs(60),
+        content_flapping: Some(FlapConfig {
+            window: Duration::from_secs(3600),
+            min_occurrences,
+        }),
+        ..ContinuousConfig::default()
+    };
+    let clock = FakeClock(Mutex::new(Utc::now()));
+    let shutdown = Shutdown::new();
+    let mut out = Vec::new();
+    monitor_continuous(
+        vec![Target::new(server.url("/page"))],
+        &config,
+        &continuous,
+        &shutdown,
+        &clock,
+        |report| {
+            let ws = &report.results[0];
+            assert!(ws.body_hash.is_some());
+            out.push(ws.content_flapping.is_some());
+            if ws.content_flapping.is_some() {
+                assert_eq!(Severity::of(ws, true), Severity::Warning);
+                assert!(report.summary.warnings[0].starts_with("content flapping: "));
+            }
+            if out.len() == passes {
+                shutdown.cancel();
+                return;
+            }
+            mock.delete();
+            let body = bodies[out.len() % bodies.len()];
+            mock = server.mock(|when, then| {
+                when.method(GET).path("/page");
+                then.status(200).body(body);
+            });
+        },
+    );
+    out
+}
+
+#[test]
+fn alternating_bodies_flag_once_each_reaches_the_threshold() {
+    let old_and_new = ["<p>release 41</p>", "<p>release 42</p>"];
+    // Two of each are needed: passes go A, B, A, B, ...
+    assert_eq!(
+        flags(&old_and_new, 6, Vec::new(), 2),
+        [false, false, false, true, true, true]
+    );
+    assert_eq!(
+        flags(&old_and_new, 6, Vec::new(), 3),
+        [false, false, false, false, false, true]
+    );
+}
+
+#[test]
+fn a_steady_body_and_ignored_tokens_never_flag() {
+    assert!(
+        flags(&["<p>same</p>"], 4, Vec::new(), 2)
+            .iter()
+            .all(|&f| !f)
+    );
+
+    let tokens = [
+        r#"<input name="csrf" value="a1">"#,
+        r#"<input name="csrf" value="b2">"#,
+    ];
+    assert_eq!(
+        flags(&tokens, 4, Vec::new(), 2),
+        [false, false, false, true]
+    );
+    let csrf = Regex::new(r#"value="[^"]*""#).unwrap();
+    assert!(flags(&tokens, 4, vec![csrf], 2).iter().all(|&f| !f));
+}
@@ -0,0 +1,77 @@
+use httpmock::prelude::*;
+use std::time::Duration;
+use website_monitor::{CheckError, CorsExpect, MonitorConfig, Target, run_pass};
+
+fn fast_config() -> MonitorConfig {
+    MonitorConfig {
+        worker_threads: 2,
+        request_timeout: Duration::from_secs(2),
+        ..MonitorConfig::default()
+    }
+}
+
+/// A server answering preflights to `/api` with the given allow headers.
+fn preflight_server(allow_origin: Option<&str>, allow_methods: &str) -> MockServer {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(OPTIONS)
+            .path("/api")
+            .header("origin", "https://app.test")
+            .header("access-control-request-method", "POST");
+        let then = then
+            .status(204)
+            .header("access-control-allow-methods", allow_methods);
+        if let Some(origin) = allow_origin {
+            then.header("access-control-allow-origin", origin);
+        }
+    });
+    server
+}
+
+fn check(server: &MockServer, expect: CorsExpect) -> Result<u16, CheckError> {
+    let target = Target::new(server.url("/api")).with_cors_expect(expect);
+    let report = run_pass(vec![target], fast_config(), None);
+    report.results[0].status.clone()
+}
+
+fn app() -> CorsExpect {
+    CorsExpect::new("https://app.test", "POST")
+}
+
+#[test]
+fn matching_preflight_passes() {
+    let server = preflight_server(Some("https://app.test"), "GET, POST");
+    assert_eq!(check(&server, app()), Ok(204));
+}
+
+#[test]
+fn missing_allow_origin_is_misconfigured() {
+    let server = preflight_server(None, "POST");
+    assert_eq!(
+        check(&server, app()),
+        Err(CheckError::CorsMisconfigured {
+            origin: "https://app.test".into(),
+            allow_origin: None,
+            allow_methods: Some("POST".into()),
+        })
+    );
+}
+
+#[test]
+fn wildcard_origin_only_passes_without_an_explicit_expectation() {
+    let server = preflight_server(Some("*"), "POST");
+    assert_eq!(check(&server, app()), Ok(204));
+    assert!(matches!(
+        check(&server, app().with_allow_origin("https://app.test")),
+        Err(CheckError::CorsMisconfigured { .. })
+    ));
+}
+
+#[test]
+fn unlisted_method_is_misconfigured() {
+    let server = preflight_server(Some("https://app.test"), "POST");
+    assert!(matches!(
+        check(&server, app().with_allow_methods(["POST", "DELETE"])),
+        Err(CheckError::CorsMisconfigured { .. })
+    ));
+}
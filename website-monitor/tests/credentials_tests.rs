@@ -0,0 +1,120 @@
+use httpmock::prelude::*;
+use std::{fs, process::Command};
+use website_monitor::{CredentialRedaction, MonitorConfig, Target, run_pass};
+
+const PASSWORD: &str = "hunter2";
+
+fn mock_with_auth(server: &MockServer) -> httpmock::Mock<'_> {
+    server.mock(|when, then| {
+        // base64("ops:hunter2")
+        when.method(GET)
+            .path("/status")
+            .header("authorization", "Basic b3BzOmh1bnRlcjI=");
+        then.status(200);
+    })
+}
+
+fn url_with_credentials(server: &MockServer) -> String {
+    format!("http://ops:{PASSWORD}@{}/status", server.address())
+}
+
+#[test]
+fn credentials_become_basic_auth_and_stay_out_of_results() {
+    let server = MockServer::start();
+    let mock = mock_with_auth(&server);
+    let target = Target::new(url_with_credentials(&server));
+    assert!(!format!("{target:?}").contains(PASSWORD));
+
+    let report = run_pass(vec![target], MonitorConfig::default(), None);
+    mock.assert();
+    let ws = &report.results[0];
+    assert_eq!(ws.status, Ok(200));
+    assert_eq!(
+        ws.url,
+        format!("http://ops:***@{}/status", server.address())
+    );
+    assert!(ws.id().starts_with(&format!("{}#", ws.url)), "{}", ws.id());
+    for artifact in [
+        format!("{ws:?}"),
+        serde_json::to_string(&report.results).unwrap(),
+        serde_json::to_string(&report.summary).unwrap(),
+    ] {
+        assert!(!artifact.contains(PASSWORD), "{artifact}");
+    }
+}
+
+#[test]
+fn anonymous_and_credentialed_checks_of_a_url_are_both_reported() {
+    let server = MockServer::start();
+    let authed = mock_with_auth(&server);
+    let anonymous = server.mock(|when, then| {
+        when.method(GET).path("/status").matches(|req| {
+            !req.headers
+                .iter()
+                .flatten()
+                .any(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+        });
+        then.status(401);
+    });
+    let targets = vec![
+        Target::new(url_with_credentials(&server)).with_redaction(CredentialRedaction::Strip),
+        Target::new(server.url("/status")),
+    ];
+
+    let report = run_pass(targets, MonitorConfig::default(), None);
+    assert_eq!((authed.hits(), anonymous.hits()), (1, 1));
+    let mut codes: Vec<u16> = report
+        .results
+        .iter()
+        .map(|ws| ws.status.clone().unwrap())
+        .collect();
+    codes.sort();
+    assert_eq!(codes, [200, 401]);
+    // Both show the same URL, so only their ids tell them apart
+    assert_eq!(report.results[0].url, report.results[1].url);
+    assert_ne!(report.results[0].id(), report.results[1].id());
+}
+
+#[test]
+fn cli_output_and_checkpoint_never_show_the_password() {
+    let server = MockServer::start();
+    let mock = mock_with_auth(&server);
+    let url = url_with_credentials(&server);
+    let dir = std::env::temp_dir().join(format!("wm-credentials-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let checkpoint = dir.join("checkpoint.ndjson");
+
+    let run = |extra: &[&str]| {
+        let out = Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+            .args(extra)
+            .arg(&url)
+            .arg("not-a-url://ops:hunter2@")
+            .output()
+            .expect("binary runs");
+        let text = String::from_utf8_lossy(&out.stdout).into_owned()
+            + &String::from_utf8_lossy(&out.stderr);
+        assert!(!text.contains(PASSWORD), "{text}");
+        text
+    };
+    let text = run(&["--verbose"]);
+    assert!(text.contains("http://ops:***@"), "{text}");
+    run(&["--format", "json"]);
+    let stripped = run(&["--strip-credentials"]);
+    assert!(!stripped.contains("ops:"), "{stripped}");
+
+    // A finished CLI run removes its checkpoint, so check one written
+    // through the library
+    let mut saved = website_monitor::Checkpoint::open(&checkpoint).unwrap();
+    website_monitor::run_pass_checkpointed(
+        vec![Target::new(url.clone())],
+        MonitorConfig::default(),
+        None,
+        &mut saved,
+    );
+    drop(saved);
+    let written = fs::read_to_string(&checkpoint).unwrap();
+    assert!(written.contains("ops:***@"), "{written}");
+    assert!(!written.contains(PASSWORD));
+    assert_eq!(mock.hits(), 4);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, TimeZone, Utc};
+use httpmock::prelude::*;
+use std::{sync::Mutex, time::Duration};
+use website_monitor::{
+    Clock, ContinuousConfig, HealthConfig, HealthScore, MonitorConfig, Shutdown, Target,
+    monitor_continuous,
+};
+
+/// Jumps straight to each deadline.
+struct FakeClock(Mutex<DateTime<Utc>>);
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+
+    fn sleep_until(&self, deadline: DateTime<Utc>, _: &Shutdown) {
+        let mut now = self.0.lock().unwrap();
+        *now = (*now).max(deadline);
+    }
+}
+
+#[test]
+fn continuous_summaries_carry_the_health_score() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/home");
+        then.status(200);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/careers");
+        then.status(500);
+    });
+    let (home, careers) = (server.url("/home"), server.url("/careers"));
+    let config = MonitorConfig {
+        worker_threads: 2,
+        request_timeout: Duration::from_secs(2),
+        ..MonitorConfig::default()
+    };
+    let continuous = ContinuousConfig {
+        interval: Duration::from_secs(60),
+        health: Some(HealthConfig {
+            weights: [(home.clone(), 3.0)].into(),
+            ..HealthConfig::default()
+        }),
+        ..ContinuousConfig::default()
+    };
+    let clock = FakeClock(Mutex::new(
+        Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap(),
+    ));
+    let shutdown = Shutdown::new();
+    let mut scores: Vec<HealthScore> = Vec::new();
+    monitor_continuous(
+        vec![Target::new(&home), Target::new(&careers)],
+        &config,
+        &continuous,
+        &shutdown,
+        &clock,
+        |report| {
+            scores.push(report.summary.health.expect("health is scored"));
+            if scores.len() == 2 {
+                shutdown.cancel();
+            }
+        },
+    );
+
+    for score in &scores {
+        assert_eq!(score.targets[&home], 100.0);
+        assert_eq!(score.targets[&careers], 0.0);
+        // The homepage weighs three times as much
+        assert_eq!(score.overall, 75.0);
+    }
+    let prom = scores[1].to_prometheus("website_monitor_health_score");
+    assert!(prom.starts_with("# TYPE website_monitor_health_score gauge\n"));
+    assert!(prom.contains(&format!(
+        "website_monitor_health_score{{target=\"{careers}\"}} 0\n"
+    )));
+    assert!(prom.ends_with("website_monitor_health_score_overall 75\n"));
+}
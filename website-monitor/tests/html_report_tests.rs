@@ -0,0 +1,163 @@
+use chrono::{TimeDelta, Utc};
+use httpmock::prelude::*;
+use std::{fs, process::Command, time::Duration};
+use website_monitor::{
+    CheckError, MonitorConfig, RunReport, RunSummary, UptimeHistory, WebsiteStatus, render_html,
+    run_pass,
+};
+
+/// `/ok`, `/slow` (answers after 100 ms) and `/broken` (500).
+fn server() -> MockServer {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/ok");
+        then.status(200);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200).delay(Duration::from_millis(100));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/broken");
+        then.status(500);
+    });
+    server
+}
+
+fn pass(server: &MockServer) -> RunReport {
+    let urls = ["/ok", "/slow", "/broken"].map(|p| server.url(p));
+    run_pass(urls.to_vec(), MonitorConfig::default(), None)
+}
+
+fn report_of(results: Vec<WebsiteStatus>) -> RunReport {
+    RunReport {
+        summary: RunSummary::from_results(&results, true),
+        results,
+        workers: Vec::new(),
+    }
+}
+
+#[test]
+fn report_has_every_section() {
+    let server = server();
+    let html = render_html(&pass(&server), None);
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<dt>Checked</dt><dd>3</dd>"), "{html}");
+    assert!(html.contains("<dt>Failed</dt><dd class=\"bad\">1</dd>"));
+
+    assert!(html.contains("<h2>Failures (1)</h2>"));
+    assert_eq!(html.matches("<tr class=\"failure\">").count(), 1);
+    assert!(html.contains(&format!(
+        "<td class=\"url\">{}</td><td class=\"status\">HTTP 500</td>",
+        server.url("/broken")
+    )));
+
+    // Slowest first, failures left out
+    let slow = html.find(&server.url("/slow")).unwrap();
+    let ok = html.rfind(&server.url("/ok")).unwrap();
+    assert!(slow < ok);
+    assert_eq!(html.matches("<tr class=\"slow\">").count(), 2);
+
+    assert_eq!(html.matches("<tr class=\"host\">").count(), 1);
+    assert!(html.contains("33.3%"), "failure rate of the host");
+
+    // No uptime without history, and nothing loaded from elsewhere
+    assert!(!html.contains("id=\"uptime\""));
+    for external in ["<script", "<link", "src=", "url("] {
+        assert!(!html.contains(external), "{external}");
+    }
+}
+
+#[test]
+fn urls_and_errors_are_escaped() {
+    let server = server();
+    let mut report = pass(&server);
+    let ws = &mut report.results[2];
+    ws.url = "https://x.test/?q=<script>alert('hi')</script>&a=\"b\"".into();
+    ws.status = Err(CheckError::Transport("<b>connection</b> & reset".into()));
+    let report = report_of(report.results);
+
+    let html = render_html(&report, None);
+    assert!(!html.contains("<script"), "{html}");
+    assert!(!html.contains("<b>"));
+    assert!(html.contains(
+        "https://x.test/?q=&lt;script&gt;alert(&#39;hi&#39;)&lt;/script&gt;&amp;a=&quot;b&quot;"
+    ));
+    assert!(html.contains("&lt;b&gt;connection&lt;/b&gt; &amp; reset"));
+}
+
+#[test]
+fn uptime_bars_cover_the_last_day() {
+    let server = server();
+    let template = pass(&server).results.remove(0);
+    let now = Utc::now();
+    let mut history = UptimeHistory::default();
+    // One check an hour for two days; down 3 and 10 hours ago, and a second
+    // check 5 hours ago that failed
+    for hours in (0..48).rev() {
+        let mut results = Vec::new();
+        let at = now - TimeDelta::hours(hours);
+        let status = if hours == 3 || hours == 10 {
+            Err(CheckError::Transport("refused".into()))
+        } else {
+            Ok(200)
+        };
+        results.push(WebsiteStatus {
+            status,
+            timestamp: at,
+            ..template.clone()
+        });
+        if hours == 5 {
+            results.push(WebsiteStatus {
+                status: Ok(503),
+                timestamp: at - TimeDelta::minutes(30),
+                ..template.clone()
+            });
+        }
+        history.record(&report_of(results));
+    }
+
+    let bars = history.bars(&template.url, now);
+    assert_eq!(bars.len(), 24);
+    let down: Vec<usize> = (0..24).filter(|&i| bars[i] == Some(0.0)).collect();
+    assert_eq!(down, [23 - 10, 23 - 3]);
+    assert_eq!(bars[23 - 5], Some(0.5));
+    assert_eq!(bars.iter().filter(|b| **b == Some(1.0)).count(), 21);
+    assert_eq!(history.uptime(&template.url), Some(22.0 / 25.0));
+
+    let report = report_of(vec![WebsiteStatus {
+        timestamp: now,
+        ..template.clone()
+    }]);
+    let html = render_html(&report, Some(&history));
+    assert!(html.contains("<h2>Uptime, last 24 h</h2>"));
+    assert_eq!(html.matches("<span class=\"bar").count(), 24);
+    assert_eq!(html.matches("class=\"bar down\"").count(), 2);
+    assert_eq!(
+        html.matches("class=\"bar partial\" title=\"50.0%\"")
+            .count(),
+        1
+    );
+    assert!(html.contains("<td class=\"num\">88.0%</td>"), "{html}");
+}
+
+#[test]
+fn cli_writes_the_report() {
+    let server = server();
+    let dir = std::env::temp_dir().join(format!("wm-html-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("report.html");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+        .arg("--report-html")
+        .arg(&path)
+        .args([server.url("/ok"), server.url("/broken")])
+        .output()
+        .expect("binary runs");
+    assert_eq!(out.status.code(), Some(1));
+    let html = fs::read_to_string(&path).unwrap();
+    assert!(html.contains("<h2>Failures (1)</h2>"));
+    assert!(html.contains(&server.url("/broken")));
+    fs::remove_dir_all(&dir).unwrap();
+}
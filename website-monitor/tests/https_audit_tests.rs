@@ -0,0 +1,99 @@
+use httpmock::prelude::*;
+use std::{net::TcpListener, time::Duration};
+use website_monitor::{CheckError, HttpsUpgrade, https_audit};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+fn redirect(server: &MockServer, path: &str, location: String) {
+    server.mock(|when, then| {
+        when.method(GET).path(path);
+        then.status(301).header("location", location);
+    });
+}
+
+#[test]
+fn each_answer_is_classified() {
+    let server = MockServer::start();
+    let https = format!("https://{}", server.address());
+    redirect(&server, "/secure", format!("{https}/secure?"));
+    redirect(&server, "/moved", format!("{https}/elsewhere"));
+    redirect(&server, "/still-http", server.url("/still-http-2"));
+    server.mock(|when, then| {
+        when.method(GET).path("/plain");
+        then.status(200).body("hello");
+    });
+
+    let urls = [
+        server.url("/secure?"),
+        server.url("/moved"),
+        server.url("/still-http"),
+        server.url("/plain"),
+    ];
+    let audit = https_audit(&urls, TIMEOUT, false);
+    let upgrades: Vec<HttpsUpgrade> = audit.entries.iter().map(|e| e.upgrade).collect();
+    assert_eq!(
+        upgrades,
+        [
+            HttpsUpgrade::RedirectsToHttps,
+            HttpsUpgrade::RedirectsElsewhere,
+            HttpsUpgrade::RedirectsElsewhere,
+            HttpsUpgrade::ServesPlainHttp,
+        ]
+    );
+    assert_eq!(audit.entries[0].status, Ok(301));
+    assert_eq!(
+        audit.entries[1].location.as_deref(),
+        Some(format!("{https}/elsewhere").as_str())
+    );
+    assert_eq!(audit.entries[3].status, Ok(200));
+    assert!(audit.entries[3].location.is_none());
+
+    let insecure: Vec<&str> = audit.insecure().map(|e| e.url.as_str()).collect();
+    assert_eq!(insecure, [&urls[1], &urls[2], &urls[3]]);
+}
+
+#[test]
+fn nothing_listening_is_unavailable() {
+    // Nothing listens on a port whose listener is gone
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    drop(listener);
+    let audit = https_audit(&[url], TIMEOUT, false);
+    let entry = &audit.entries[0];
+    assert_eq!(entry.upgrade, HttpsUpgrade::HttpUnavailable);
+    assert!(matches!(entry.status, Err(CheckError::Transport(_))));
+    assert_eq!(audit.count(HttpsUpgrade::HttpUnavailable), 1);
+    assert_eq!(audit.insecure().count(), 0);
+}
+
+#[test]
+fn https_urls_are_audited_only_when_asked() {
+    let server = MockServer::start();
+    let https = format!("https://{}/login", server.address());
+    redirect(&server, "/login", https.clone());
+
+    let urls = [https.clone(), "ftp://files.test/".to_string()];
+    assert!(https_audit(&urls, TIMEOUT, false).entries.is_empty());
+
+    // The http variant keeps the port, which is where the mock listens
+    let audit = https_audit(&urls, TIMEOUT, true);
+    assert_eq!(audit.entries.len(), 1);
+    let entry = &audit.entries[0];
+    assert_eq!(entry.url, https);
+    assert_eq!(entry.http_url, server.url("/login"));
+    assert_eq!(entry.upgrade, HttpsUpgrade::RedirectsToHttps);
+}
+
+#[test]
+fn repeated_http_urls_are_checked_once() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/");
+        then.status(200);
+    });
+    let http = server.url("/");
+    let https = format!("https://{}/", server.address());
+    let audit = https_audit(&[http.clone(), https, http], TIMEOUT, true);
+    assert_eq!(audit.entries.len(), 1);
+    mock.assert_hits(1);
+}
@@ -0,0 +1,195 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use website_monitor::{CheckError, MailProbe, MonitorConfig, WebsiteStatus, monitor_websites};
+
+/// Server for one connection: sends `greeting`, then answers each line it
+/// gets with `reply(line)` until that gives `None` or the client hangs up.
+/// Joining it gives the lines it got.
+fn spawn_server(
+    scheme: &str,
+    greeting: &'static str,
+    reply: fn(&str) -> Option<&'static str>,
+) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("{scheme}://{}", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        writer.write_all(greeting.as_bytes()).unwrap();
+        let mut got = Vec::new();
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            let answer = reply(&line);
+            got.push(line);
+            match answer {
+                Some(answer) => writer.write_all(answer.as_bytes()).unwrap(),
+                None => break,
+            }
+        }
+        got
+    });
+    (url, server)
+}
+
+fn check(url: String, probe: Option<MailProbe>) -> WebsiteStatus {
+    let config = MonitorConfig {
+        worker_threads: 1,
+        request_timeout: Duration::from_secs(2),
+        mail_probe: probe,
+        ..MonitorConfig::default()
+    };
+    monitor_websites(vec![url], config, None).remove(0)
+}
+
+fn expect(capabilities: &[&str]) -> Option<MailProbe> {
+    Some(MailProbe {
+        starttls: false,
+        expect: capabilities.iter().map(|c| c.to_string()).collect(),
+    })
+}
+
+fn smtp(line: &str) -> Option<&'static str> {
+    match line {
+        "EHLO website-monitor" => Some(
+            "250-mx.test greets you\r\n250-PIPELINING\r\n250-AUTH PLAIN LOGIN\r\n250 8BITMIME\r\n",
+        ),
+        "QUIT" => None,
+        _ => Some("500 unrecognized command\r\n"),
+    }
+}
+
+#[test]
+fn smtp_greeting_is_captured() {
+    let (url, server) = spawn_server("smtp", "220 mx.test ESMTP ready\r\n", smtp);
+    let ws = check(url, None);
+    assert_eq!(ws.status, Ok(220));
+    assert_eq!(ws.banner.as_deref(), Some("220 mx.test ESMTP ready"));
+    assert_eq!(server.join().unwrap(), ["QUIT"]);
+}
+
+#[test]
+fn smtp_capabilities_are_checked() {
+    let (url, server) = spawn_server("smtp", "220-mx.test\r\n220 ESMTP\r\n", smtp);
+    let ws = check(url, expect(&["auth", "PIPELINING"]));
+    assert_eq!(ws.status, Ok(220));
+    assert_eq!(ws.banner.as_deref(), Some("220 mx.test"));
+    assert_eq!(server.join().unwrap(), ["EHLO website-monitor", "QUIT"]);
+
+    let (url, _server) = spawn_server("smtp", "220 mx.test\r\n", smtp);
+    let ws = check(url, expect(&["AUTH", "SMTPUTF8"]));
+    assert_eq!(
+        ws.status,
+        Err(CheckError::BadReply {
+            to: "EHLO".into(),
+            reply: "PIPELINING AUTH 8BITMIME (missing SMTPUTF8)".into()
+        })
+    );
+    assert!(ws.banner.is_none());
+}
+
+#[test]
+fn bad_banner_and_refused_connection_are_told_apart() {
+    let (url, _server) = spawn_server("smtp", "554 mx.test no service\r\n", smtp);
+    let ws = check(url, None);
+    let err = ws.status.unwrap_err();
+    assert_eq!(err.kind(), "bad_reply");
+    assert_eq!(err.to_string(), "bad greeting: 554 mx.test no service");
+
+    let (url, _server) = spawn_server("imap", "* BYE too many connections\r\n", |_| None);
+    let err = check(url, None).status.unwrap_err();
+    assert_eq!(err.to_string(), "bad greeting: * BYE too many connections");
+
+    // Nothing listens on a port whose listener is gone
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("smtp://{}", listener.local_addr().unwrap());
+    drop(listener);
+    assert_eq!(check(url, None).status, Err(CheckError::ConnectionRefused));
+}
+
+fn imap(line: &str) -> Option<&'static str> {
+    match line {
+        "a1 CAPABILITY" => Some("* CAPABILITY IMAP4rev1 IDLE STARTTLS\r\na1 OK done\r\n"),
+        "a2 LOGOUT" => Some("* BYE logging out\r\na2 OK bye\r\n"),
+        _ => Some("* BAD unexpected\r\n"),
+    }
+}
+
+#[test]
+fn imap_greeting_and_capabilities() {
+    let (url, server) = spawn_server("imap", "* OK [CAPABILITY IMAP4rev1] ready\r\n", imap);
+    let ws = check(url, expect(&["IDLE"]));
+    assert_eq!(ws.status, Ok(220));
+    assert_eq!(
+        ws.banner.as_deref(),
+        Some("* OK [CAPABILITY IMAP4rev1] ready")
+    );
+    assert_eq!(server.join().unwrap(), ["a1 CAPABILITY", "a2 LOGOUT"]);
+
+    let (url, _server) = spawn_server("imap", "* OK ready\r\n", |_| Some("a1 NO later\r\n"));
+    let err = check(url, expect(&[])).status.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unexpected reply to CAPABILITY: a1 NO later"
+    );
+}
+
+#[test]
+fn starttls_refusal_is_a_bad_reply() {
+    let (url, _server) = spawn_server("smtp", "220 mx.test\r\n", |line| match line {
+        "STARTTLS" => Some("454 TLS not available\r\n"),
+        line => smtp(line),
+    });
+    let probe = MailProbe {
+        starttls: true,
+        expect: Vec::new(),
+    };
+    let err = check(url, Some(probe)).status.unwrap_err();
+    assert_eq!(
+        err,
+        CheckError::BadReply {
+            to: "STARTTLS".into(),
+            reply: "454 TLS not available".into()
+        }
+    );
+}
+
+#[test]
+fn starttls_goes_on_to_the_handshake() {
+    let (url, _server) = spawn_server("smtp", "220 mx.test\r\n", |line| match line {
+        // Not a TLS server hello
+        "STARTTLS" => Some("220 go ahead\r\nthis is not tls\r\n"),
+        line => smtp(line),
+    });
+    let probe = MailProbe {
+        starttls: true,
+        expect: Vec::new(),
+    };
+    let err = check(url, Some(probe)).status.unwrap_err().to_string();
+    if cfg!(feature = "starttls") {
+        assert!(err.starts_with("mail error: STARTTLS: "), "{err}");
+    } else {
+        assert_eq!(err, "STARTTLS requires the `starttls` feature");
+    }
+}
+
+#[test]
+fn silent_server_times_out() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("imap://{}", listener.local_addr().unwrap());
+    let config = MonitorConfig {
+        request_timeout: Duration::from_millis(300),
+        ..MonitorConfig::default()
+    };
+    let ws = monitor_websites(vec![url], config, None).remove(0);
+    assert_eq!(
+        ws.status,
+        Err(CheckError::TimedOut {
+            limit: Duration::from_millis(300)
+        })
+    );
+    drop(listener);
+}
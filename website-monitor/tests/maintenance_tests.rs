@@ -0,0 +1,89 @@
+use httpmock::prelude::*;
+use std::process::Command;
+use website_monitor::{MaintenanceWindow, MonitorConfig, Target, run_pass};
+
+/// Always open, so the tests don't depend on when they run.
+const ALWAYS: &str = "* 00:00-24:00";
+
+#[test]
+fn failures_in_maintenance_are_flagged_and_not_counted() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/down");
+        then.status(503);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/up");
+        then.status(200);
+    });
+    let window = MaintenanceWindow::parse(ALWAYS).unwrap();
+    let targets = vec![
+        Target::new(server.url("/down")).with_maintenance(window.clone()),
+        Target::new(server.url("/up")).with_maintenance(window),
+        Target::new(server.url("/down?no-window")),
+    ];
+    let report = run_pass(targets, MonitorConfig::default(), None);
+
+    let flagged: Vec<(&str, bool)> = report
+        .results
+        .iter()
+        .map(|ws| (ws.url.rsplit('/').next().unwrap(), ws.in_maintenance))
+        .collect();
+    assert!(flagged.contains(&("down", true)));
+    // Successes in a window are just successes
+    assert!(flagged.contains(&("up", false)));
+    assert!(flagged.contains(&("down?no-window", false)));
+    let summary = &report.summary;
+    assert_eq!(
+        (
+            summary.total,
+            summary.ok,
+            summary.err,
+            summary.in_maintenance
+        ),
+        (3, 1, 1, 1)
+    );
+
+    let json = serde_json::to_value(&report.results).unwrap();
+    let down = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["url"] == server.url("/down"))
+        .unwrap();
+    assert_eq!(down["in_maintenance"], true);
+}
+
+#[test]
+fn cli_exits_zero_when_every_failure_is_in_maintenance() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/reboot");
+        then.status(502);
+    });
+    let url = server.url("/reboot");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+            .args(args)
+            .arg(&url)
+            .output()
+            .expect("binary runs")
+    };
+
+    let out = run(&["--maintenance", ALWAYS]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert_eq!(out.status.code(), Some(0), "{stdout}");
+    assert!(stdout.contains("[MAINT]"), "{stdout}");
+    assert!(stdout.contains("0 OK, 0 ERR, 1 in maintenance"), "{stdout}");
+
+    let spec = format!("{url} {ALWAYS}");
+    assert_eq!(run(&["--maintenance-for", &spec]).status.code(), Some(0));
+    assert_eq!(run(&[]).status.code(), Some(1));
+
+    let other = format!("https://other.test {ALWAYS}");
+    let out = run(&["--maintenance-for", &other]);
+    assert_eq!(out.status.code(), Some(2));
+    let out = run(&["--maintenance", "Sun 02:00-02:00"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("window is empty"));
+}
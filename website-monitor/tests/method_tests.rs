@@ -0,0 +1,150 @@
+use chrono::{DateTime, TimeZone, Utc};
+use httpmock::{Method::HEAD, prelude::*};
+use std::{fs, process::Command, sync::Mutex, time::Duration};
+use website_monitor::{
+    Clock, ContinuousConfig, HttpMethod, MethodMemory, MonitorConfig, Shutdown, StateSnapshot,
+    Target, monitor_continuous, run_pass,
+};
+
+/// Jumps straight to each deadline.
+struct FakeClock(Mutex<DateTime<Utc>>);
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+
+    fn sleep_until(&self, deadline: DateTime<Utc>, _: &Shutdown) {
+        let mut now = self.0.lock().unwrap();
+        *now = (*now).max(deadline);
+    }
+}
+
+fn head_config() -> MonitorConfig {
+    MonitorConfig {
+        worker_threads: 2,
+        request_timeout: Duration::from_secs(2),
+        method: HttpMethod::Head,
+        ..MonitorConfig::default()
+    }
+}
+
+#[test]
+fn second_pass_goes_straight_to_get() {
+    let server = MockServer::start();
+    let refused = server.mock(|when, then| {
+        when.method(HEAD).path("/no-head");
+        then.status(405);
+    });
+    let get = server.mock(|when, then| {
+        when.method(GET).path("/no-head");
+        then.status(200);
+    });
+    let head_ok = server.mock(|when, then| {
+        when.method(HEAD).path("/fine");
+        then.status(200);
+    });
+    let no_head = server.url("/no-head");
+
+    let clock = FakeClock(Mutex::new(
+        Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap(),
+    ));
+    let shutdown = Shutdown::new();
+    let mut passes = Vec::new();
+    monitor_continuous(
+        vec![Target::new(&no_head), Target::new(server.url("/fine"))],
+        &head_config(),
+        &ContinuousConfig::default(),
+        &shutdown,
+        &clock,
+        |report| {
+            let hits = (refused.hits(), get.hits());
+            passes.push((report, hits));
+            if passes.len() == 2 {
+                shutdown.cancel();
+            }
+        },
+    );
+
+    // First pass: HEAD refused, then GET; second pass: GET only
+    assert_eq!(passes[0].1, (1, 1));
+    assert_eq!(passes[1].1, (1, 2));
+    for (report, _) in &passes {
+        assert_eq!(report.summary.err, 0);
+        for ws in &report.results {
+            let expected = if ws.url == no_head {
+                HttpMethod::Get
+            } else {
+                HttpMethod::Head
+            };
+            assert_eq!(ws.method_used, Some(expected), "{}", ws.url);
+        }
+    }
+    assert_eq!(head_ok.hits(), 2);
+}
+
+#[test]
+fn head_that_never_answers_falls_back_too() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(HEAD).path("/hangs");
+        then.status(200).delay(Duration::from_secs(3));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/hangs");
+        then.status(200);
+    });
+    let memory = MethodMemory::new();
+    let config = MonitorConfig {
+        request_timeout: Duration::from_millis(300),
+        method_memory: Some(memory.clone()),
+        ..head_config()
+    };
+    let report = run_pass(vec![Target::new(server.url("/hangs"))], config, None);
+    let ws = &report.results[0];
+    assert_eq!(ws.status, Ok(200));
+    assert_eq!(ws.method_used, Some(HttpMethod::Get));
+    assert!(memory.needs_get(&ws.target_id));
+
+    // GET passes don't say
+    let report = run_pass(
+        vec![Target::new(server.url("/hangs"))],
+        MonitorConfig::default(),
+        None,
+    );
+    assert_eq!(report.results[0].method_used, None);
+}
+
+#[test]
+fn cli_keeps_the_memory_in_the_state_file() {
+    let server = MockServer::start();
+    let refused = server.mock(|when, then| {
+        when.method(HEAD).path("/no-head");
+        then.status(501);
+    });
+    let get = server.mock(|when, then| {
+        when.method(GET).path("/no-head");
+        then.status(200);
+    });
+    let url = server.url("/no-head");
+    let dir = std::env::temp_dir().join(format!("wm-method-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let state = dir.join("state.json");
+
+    for _ in 0..2 {
+        let out = Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+            .args(["--method", "head", "--format", "json", "--state"])
+            .arg(&state)
+            .arg(&url)
+            .output()
+            .expect("binary runs");
+        assert_eq!(out.status.code(), Some(0));
+        let json: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+        assert_eq!(json["results"][0]["method_used"], "GET");
+    }
+    // HEAD only on the first run
+    assert_eq!((refused.hits(), get.hits()), (1, 2));
+    let snapshot = StateSnapshot::load(&state).unwrap();
+    assert_eq!(snapshot.get_instead_of_head, [url].into());
+    fs::remove_dir_all(&dir).unwrap();
+}
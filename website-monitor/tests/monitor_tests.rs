@@ -1,154 +1,924 @@
 use httpmock::prelude::*;
 use once_cell::sync::Lazy;
-use std::time::{Duration, Instant};
-use website_monitor::{monitor_websites, MonitorConfig, Shutdown};
-
-pub fn monitor_websites(
-    urls: Vec<String>,
-    mut config: MonitorConfig,
-    shutdown: Option<Shutdown>,
-) -> Vec<WebsiteStatus> {
-    use std::sync::{mpsc, Arc, Mutex};
-    use std::time::{Duration, Instant};
-
-    if urls.is_empty() {
-        return Vec::new();
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use website_monitor::{
+    CacheStatus, CheckError, CheckOutcome, MonitorConfig, NoRetry, SampleSpec, Sampled, Shutdown,
+    Target, monitor_iter, monitor_websites, run_pass,
+};
+
+/// One mock server shared by tests that only need fixed responses.
+static SERVER: Lazy<MockServer> = Lazy::new(|| {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/ok");
+        then.status(200);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/missing");
+        then.status(404);
+    });
+    server
+});
+
+fn fast_config() -> MonitorConfig {
+    MonitorConfig {
+        worker_threads: 4,
+        request_timeout: Duration::from_secs(2),
+        ..MonitorConfig::default()
     }
+}
+
+#[test]
+fn empty_input_returns_nothing() {
+    assert!(monitor_websites(Vec::new(), fast_config(), None).is_empty());
+}
+
+#[test]
+fn reports_status_codes() {
+    let urls = vec![SERVER.url("/ok"), SERVER.url("/missing")];
+    let results = monitor_websites(urls, fast_config(), None);
+
+    assert_eq!(results.len(), 2);
+    let ok = results.iter().find(|r| r.url.ends_with("/ok")).unwrap();
+    let missing = results
+        .iter()
+        .find(|r| r.url.ends_with("/missing"))
+        .unwrap();
+    assert_eq!(ok.status, Ok(200));
+    assert_eq!(missing.status, Ok(404));
+}
+
+#[test]
+fn unreachable_url_is_an_error() {
+    // Port 9 on localhost is almost certainly closed.
+    let results = monitor_websites(vec!["http://127.0.0.1:9/".into()], fast_config(), None);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].status.is_err());
+}
+
+#[test]
+fn retries_failed_requests() {
+    let config = MonitorConfig {
+        max_retries: 2,
+        ..fast_config()
+    };
+    let start = Instant::now();
+    let results = monitor_websites(vec!["http://127.0.0.1:9/".into()], config, None);
+    assert!(results[0].status.is_err());
+    // Two backoffs: 100ms + 200ms
+    assert!(start.elapsed() >= Duration::from_millis(300));
+}
+
+#[test]
+fn cancelled_shutdown_returns_quickly() {
+    let shutdown = Shutdown::new();
+    shutdown.cancel();
+    let start = Instant::now();
+    let results = monitor_websites(vec![SERVER.url("/ok")], fast_config(), Some(shutdown));
+    assert!(results.len() <= 1);
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn caller_shutdown_is_not_cancelled_by_a_pass() {
+    let shutdown = Shutdown::new();
+    let results = monitor_websites(
+        vec![SERVER.url("/ok")],
+        fast_config(),
+        Some(shutdown.clone()),
+    );
+    assert_eq!(results.len(), 1);
+    assert!(!shutdown.is_cancelled());
+}
+
+#[test]
+fn run_pass_summarizes_results() {
+    let urls = vec![
+        SERVER.url("/ok"),
+        SERVER.url("/missing"),
+        "http://127.0.0.1:9/".to_string(),
+    ];
+    let report = run_pass(urls, fast_config(), None);
+    assert_eq!(report.results.len(), 3);
+    assert_eq!(report.summary.total, 3);
+    // The 404 counts as a failure by default
+    assert_eq!(report.summary.ok, 1);
+    assert_eq!(report.summary.err, 2);
+    assert_eq!(report.summary.classes.class_4xx, 1);
+    assert_eq!(report.summary.classes.transport_errors, 1);
+    assert_eq!(report.summary.latency.count(), 3);
+}
+
+#[test]
+fn allowing_4xx_counts_them_as_ok() {
+    let config = MonitorConfig {
+        treat_4xx_as_failure: false,
+        ..fast_config()
+    };
+    let report = run_pass(
+        vec![SERVER.url("/ok"), SERVER.url("/missing")],
+        config,
+        None,
+    );
+    assert_eq!((report.summary.ok, report.summary.err), (2, 0));
+}
+
+#[test]
+fn startup_jitter_spreads_first_attempts() {
+    let jitter = Duration::from_millis(400);
+    let config = MonitorConfig {
+        worker_threads: 10,
+        startup_jitter: Some(jitter),
+        ..fast_config()
+    };
+    let urls: Vec<String> = (0..10).map(|i| SERVER.url(format!("/ok?n={i}"))).collect();
+    let start = chrono::Utc::now();
+    let results = monitor_websites(urls, config, None);
+    assert_eq!(results.len(), 10);
+
+    let offsets: Vec<i64> = results
+        .iter()
+        .map(|r| (r.timestamp - start).num_milliseconds())
+        .collect();
+    let (min, max) = (offsets.iter().min().unwrap(), offsets.iter().max().unwrap());
+    assert!(max - min >= 50, "hits not spread out: {offsets:?}");
+    assert!(
+        *max < 400 + 1000,
+        "hits exceeded the jitter window: {offsets:?}"
+    );
+}
+
+#[test]
+fn startup_jitter_is_interrupted_by_shutdown() {
+    let config = MonitorConfig {
+        startup_jitter: Some(Duration::from_secs(30)),
+        ..fast_config()
+    };
+    let shutdown = Shutdown::new();
+    let s = shutdown.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        s.cancel();
+    });
+    let start = Instant::now();
+    let results = monitor_websites(vec![SERVER.url("/ok")], config, Some(shutdown));
+    assert!(results.is_empty());
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn sampling_happens_after_dedup() {
+    let mut urls: Vec<String> = (0..5).map(|i| SERVER.url(format!("/ok?n={i}"))).collect();
+    urls.push(urls[0].clone());
+    let config = MonitorConfig {
+        sample: Some(SampleSpec::count(2).with_seed(7)),
+        ..fast_config()
+    };
+
+    let first = run_pass(urls.clone(), config.clone(), None);
+    assert_eq!(
+        first.summary.sampled,
+        Some(Sampled {
+            selected: 2,
+            supplied: 5
+        })
+    );
+    let picked = |report: &website_monitor::RunReport| {
+        let mut urls: Vec<String> = report.results.iter().map(|r| r.url.clone()).collect();
+        urls.sort();
+        urls
+    };
+    assert_eq!(picked(&first), picked(&run_pass(urls, config, None)));
+    assert_eq!(
+        run_pass(vec![SERVER.url("/ok")], fast_config(), None)
+            .summary
+            .sampled,
+        None
+    );
+}
+
+#[test]
+fn negative_check_passes_when_unreachable() {
+    let target = Target::new("http://127.0.0.1:9/").expect_unreachable();
+    let report = run_pass(vec![target], fast_config(), None);
+    let ws = &report.results[0];
+    assert!(ws.status.is_err());
+    assert!(ws.expected_down);
+    assert_eq!((report.summary.ok, report.summary.err), (1, 0));
+}
+
+#[test]
+fn negative_check_fails_when_reachable() {
+    let server = MockServer::start();
+    let admin = server.mock(|when, then| {
+        when.method(GET).path("/admin");
+        then.status(200);
+    });
+    let target = Target::new(server.url("/admin")).expect_unreachable();
+    let config = MonitorConfig {
+        max_retries: 2,
+        ..fast_config()
+    };
+    let report = run_pass(vec![target], config, None);
+    assert_eq!(
+        report.results[0].status,
+        Err(CheckError::UnexpectedlyReachable { status: 200 })
+    );
+    assert!(!report.results[0].expected_down);
+    assert_eq!((report.summary.ok, report.summary.err), (0, 1));
+    assert_eq!(report.summary.classes.unexpectedly_reachable, 1);
+    // Not retried by default
+    admin.assert_hits(1);
+}
+
+#[test]
+fn expected_status_counts_as_down() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/internal");
+        then.status(403);
+    });
+    let blocked = Target::new(server.url("/internal")).with_expect_status(403);
+    let open = Target::new(SERVER.url("/ok")).with_expect_status(403);
+    let report = run_pass(vec![blocked, open], fast_config(), None);
+    let status = |path: &str| {
+        let ws = report.results.iter().find(|r| r.url.ends_with(path));
+        ws.unwrap().status.clone()
+    };
+    assert_eq!(status("/internal"), Ok(403));
+    assert_eq!(
+        status("/ok"),
+        Err(CheckError::UnexpectedlyReachable { status: 200 })
+    );
+    assert_eq!((report.summary.ok, report.summary.err), (1, 1));
+}
+
+#[test]
+fn reachable_negative_checks_retry_when_asked() {
+    let server = MockServer::start();
+    let admin = server.mock(|when, then| {
+        when.method(GET).path("/admin");
+        then.status(200);
+    });
+    let config = MonitorConfig {
+        max_retries: 2,
+        retry_when_reachable: true,
+        ..fast_config()
+    };
+    let target = Target::new(server.url("/admin")).expect_unreachable();
+    let report = run_pass(vec![target], config, None);
+    assert_eq!(report.results[0].retries, 2);
+    admin.assert_hits(3);
+}
+
+#[test]
+fn duplicate_urls_yield_one_result() {
+    let urls = vec![SERVER.url("/ok"), SERVER.url("/ok")];
+    let results = monitor_websites(urls, fast_config(), None);
+    assert_eq!(results.len(), 1);
+}
+
+#[test]
+fn host_header_override_reaches_the_wire() {
+    let server = MockServer::start();
+    let prod = server.mock(|when, then| {
+        when.method(GET)
+            .path("/")
+            .header("host", "www.prod.example");
+        then.status(200);
+    });
+
+    let target = Target::new(server.url("/")).with_host_header("www.prod.example");
+    let report = run_pass(vec![target], fast_config(), None);
+
+    prod.assert();
+    let ws = &report.results[0];
+    assert_eq!(ws.status, Ok(200));
+    assert_eq!(ws.host_header.as_deref(), Some("www.prod.example"));
+}
+
+#[test]
+fn no_host_override_by_default() {
+    let results = monitor_websites(vec![SERVER.url("/ok")], fast_config(), None);
+    assert_eq!(results[0].host_header, None);
+}
+
+#[test]
+fn captured_headers_yield_cache_status() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.path("/cached");
+        then.status(200)
+            .header("X-Cache", "HIT")
+            .header("Age", "42");
+    });
+    server.mock(|when, then| {
+        when.path("/fresh");
+        then.status(200).header("CF-Cache-Status", "MISS");
+    });
+
+    let config = MonitorConfig {
+        capture_headers: true,
+        ..fast_config()
+    };
+    let report = run_pass(
+        vec![server.url("/cached"), server.url("/fresh")],
+        config,
+        None,
+    );
+    let cached = report
+        .results
+        .iter()
+        .find(|r| r.url.ends_with("/cached"))
+        .unwrap();
+    let fresh = report
+        .results
+        .iter()
+        .find(|r| r.url.ends_with("/fresh"))
+        .unwrap();
+
+    assert_eq!(cached.cache_status, Some(CacheStatus::Hit));
+    assert_eq!(fresh.cache_status, Some(CacheStatus::Miss));
+    assert!(
+        cached
+            .headers
+            .as_ref()
+            .unwrap()
+            .iter()
+            .any(|(k, v)| k == "age" && v == "42")
+    );
+    assert_eq!(
+        (report.summary.cache.hit, report.summary.cache.miss),
+        (1, 1)
+    );
+
+    let json = serde_json::to_value(cached).unwrap();
+    assert_eq!(json["cache_status"], "hit");
+}
+
+#[test]
+fn no_cache_status_without_capture() {
+    let report = run_pass(vec![SERVER.url("/ok")], fast_config(), None);
+    assert_eq!(report.results[0].cache_status, None);
+    assert!(!report.summary.cache.has_data());
+    let json = serde_json::to_value(&report.results[0]).unwrap();
+    assert!(json.get("cache_status").is_none());
+}
+
+#[test]
+fn slow_jobs_spread_over_every_worker() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200).delay(Duration::from_millis(300));
+    });
+    let urls: Vec<String> = (0..8).map(|i| server.url(format!("/slow?n={i}"))).collect();
+
+    let report = run_pass(urls, fast_config(), None);
+    assert_eq!(report.results.len(), 8);
+    assert_eq!(report.workers.len(), 4);
+    for w in &report.workers {
+        assert!(w.jobs >= 1, "worker {} was never used", w.worker_id);
+        assert!(w.busy >= Duration::from_millis(300));
+        assert_eq!(w.retries, 0);
+    }
+    assert_eq!(report.workers.iter().map(|w| w.jobs).sum::<usize>(), 8);
+}
+
+#[test]
+fn worker_stats_count_retries() {
+    let config = MonitorConfig {
+        worker_threads: 1,
+        max_retries: 2,
+        ..fast_config()
+    };
+    let report = run_pass(vec!["http://127.0.0.1:9/".to_string()], config, None);
+    let w = &report.workers[0];
+    assert_eq!((w.jobs, w.retries), (3, 2));
+}
+
+#[test]
+fn absurd_worker_count_still_completes() {
+    let config = MonitorConfig {
+        worker_threads: 1_000_000,
+        worker_multiplier: 1,
+        ..fast_config()
+    };
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let urls: Vec<String> = (0..cpus * 2 + 1)
+        .map(|i| SERVER.url(format!("/ok?w={i}")))
+        .collect();
+    let n = urls.len();
+
+    let report = run_pass(urls, config, None);
+    assert_eq!(report.results.len(), n);
+    assert_eq!(report.summary.effective_workers, cpus);
+    assert!(
+        report
+            .summary
+            .warnings
+            .iter()
+            .any(|w| w.contains("clamped"))
+    );
+}
 
-    // Normalize worker count
-    if config.worker_threads == 0 {
-        config.worker_threads = 1;
+#[test]
+fn report_says_what_the_pass_cost() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200).delay(Duration::from_millis(100));
+    });
+    let urls: Vec<String> = (0..3).map(|i| server.url(format!("/slow?u={i}"))).collect();
+    let report = run_pass(urls, fast_config(), None);
+
+    let usage = report.summary.resources.expect("usage recorded");
+    assert_eq!(usage.threads_spawned, 3);
+    assert!(usage.wall >= Duration::from_millis(100), "{usage:?}");
+    if cfg!(target_os = "linux") {
+        assert!(usage.cpu.is_some(), "{usage:?}");
+        assert!(usage.peak_rss_bytes.is_some_and(|b| b > 0), "{usage:?}");
     }
-    config.worker_threads = config.worker_threads.min(urls.len());
 
-    let shutdown = shutdown.unwrap_or_else(Shutdown::new);
+    let json = serde_json::to_value(&report.summary).unwrap();
+    assert_eq!(json["resources"]["threads_spawned"], 3);
+    assert!(json["resources"]["wall_ms"].as_f64().unwrap() >= 100.0);
+}
+
+#[test]
+fn iterator_yields_everything_when_exhausted() {
+    let urls = vec![SERVER.url("/ok"), SERVER.url("/missing"), SERVER.url("/ok")];
+    let mut from_iter: Vec<_> = monitor_iter(urls.clone(), fast_config())
+        .unwrap()
+        .map(|r| (r.url, r.status))
+        .collect();
+    let mut from_vec: Vec<_> = monitor_websites(urls, fast_config(), None)
+        .into_iter()
+        .map(|r| (r.url, r.status))
+        .collect();
+    from_iter.sort_by(|a, b| a.0.cmp(&b.0));
+    from_vec.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(from_iter, from_vec);
+    assert_eq!(from_iter.len(), 2);
+}
 
-    // Channels: jobs in, results out
-    let (job_tx, job_rx) = mpsc::channel::<Job>();
-    let (res_tx, res_rx) = mpsc::channel::<WebsiteStatus>();
+#[test]
+fn dropping_the_iterator_stops_the_run() {
+    let server = MockServer::start();
+    let slow = server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200).delay(Duration::from_millis(50));
+    });
+    let urls: Vec<String> = (0..40)
+        .map(|i| server.url(format!("/slow?i={i}")))
+        .collect();
+    let config = MonitorConfig {
+        worker_threads: 2,
+        ..fast_config()
+    };
 
-    // Queue initial jobs
-    for url in &urls {
-        let _ = job_tx.send(Job {
-            url: url.clone(),
-            attempt: 0,
-        });
+    let mut iter = monitor_iter(urls, config).unwrap();
+    for _ in 0..2 {
+        assert_eq!(iter.next().unwrap().status, Ok(200));
     }
+    drop(iter);
 
-    // Share receiver among workers (Receiver isn't cloneable)
-    let job_rx = Arc::new(Mutex::new(job_rx));
-
-    // Spawn workers
-    let mut workers = Vec::with_capacity(config.worker_threads);
-    for _ in 0..config.worker_threads {
-        let jobs = Arc::clone(&job_rx);
-        let results = res_tx.clone();
-        let job_tx_retry = job_tx.clone(); // used only when retrying
-        let shutdown_clone = shutdown.clone();
-        let timeout = config.request_timeout;
-        let max_retries = config.max_retries;
-
-        // Build a per-thread blocking client with timeout & limited redirects
-        let client = reqwest::blocking::Client::builder()
-            .timeout(timeout)
-            .redirect(reqwest::redirect::Policy::limited(5))
-            .build()
-            .expect("failed to build reqwest client");
-
-        workers.push(std::thread::spawn(move || {
-            use std::sync::mpsc::RecvTimeoutError;
-
-            loop {
-                if shutdown_clone.is_cancelled() {
-                    break;
-                }
-
-                // Try to receive a job with a short timeout so we can re-check shutdown regularly.
-                let job = {
-                    let rx = jobs.lock().unwrap();
-                    rx.recv_timeout(Duration::from_millis(100))
-                };
-
-                let job = match job {
-                    Ok(j) => j,
-                    Err(RecvTimeoutError::Timeout) => {
-                        // No job right now — loop to check shutdown or await more work
-                        continue
-                    }
-                    Err(RecvTimeoutError::Disconnected) => {
-                        // All senders dropped; no more jobs will arrive
-                        break
-                    }
-                };
-
-                // Execute the job
-                let start = Instant::now();
-                let result = fetch_status(&client, &job.url);
-                let elapsed = start.elapsed();
-
-                match result {
-                    Ok(code) => {
-                        let _ = results.send(WebsiteStatus {
-                            url: job.url,
-                            status: Ok(code),
-                            response_time: elapsed,
-                            timestamp: Utc::now(),
-                        });
-                    }
-                    Err(err) => {
-                        // Retry with light backoff if allowed and not shutting down
-                        if !shutdown_clone.is_cancelled() && job.attempt < max_retries {
-                            let backoff = Duration::from_millis(100 * (job.attempt as u64 + 1));
-                            std::thread::sleep(backoff);
-                            let _ = job_tx_retry.send(Job {
-                                url: job.url,
-                                attempt: job.attempt + 1,
-                            });
-                        } else {
-                            let _ = results.send(WebsiteStatus {
-                                url: job.url,
-                                status: Err(err),
-                                response_time: elapsed,
-                                timestamp: Utc::now(),
-                            });
-                        }
-                    }
-                }
-            }
-            // Worker exits: drops its Sender clones, helping receivers detect closure if needed
-        }));
+    // Drop joined the workers, so nothing is left to hit the server.
+    let hits = slow.hits();
+    assert!(hits < 40, "{hits} hits after an early break");
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(slow.hits(), hits);
+}
+
+#[test]
+fn slow_response_is_a_timeout_with_the_limit() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200).delay(Duration::from_secs(2));
+    });
+    let config = MonitorConfig {
+        request_timeout: Duration::from_millis(200),
+        ..fast_config()
+    };
+
+    let report = run_pass(vec![server.url("/slow"), SERVER.url("/ok")], config, None);
+    let slow = report
+        .results
+        .iter()
+        .find(|r| r.url.ends_with("/slow"))
+        .unwrap();
+    assert_eq!(
+        slow.status,
+        Err(CheckError::TimedOut {
+            limit: Duration::from_millis(200)
+        })
+    );
+    assert_eq!(report.summary.classes.timeouts, 1);
+    assert_eq!(report.summary.classes.transport_errors, 0);
+}
+
+#[test]
+fn just_under_max_response_time_passes() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/delayed");
+        then.status(200).delay(Duration::from_millis(100));
+    });
+    let target =
+        Target::new(server.url("/delayed")).with_max_response_time(Duration::from_millis(600));
+    let report = run_pass(vec![target], fast_config(), None);
+    assert_eq!(report.results[0].status, Ok(200));
+    assert_eq!(report.summary.classes.too_slow, 0);
+}
+
+#[test]
+fn just_over_max_response_time_is_too_slow() {
+    let server = MockServer::start();
+    let delayed = server.mock(|when, then| {
+        when.method(GET).path("/delayed");
+        then.status(200).delay(Duration::from_millis(300));
+    });
+    let target =
+        Target::new(server.url("/delayed")).with_max_response_time(Duration::from_millis(200));
+    let config = MonitorConfig {
+        max_retries: 2,
+        ..fast_config()
+    };
+    let report = run_pass(vec![target], config, None);
+
+    match &report.results[0].status {
+        Err(CheckError::TooSlow { limit, actual }) => {
+            assert_eq!(*limit, Duration::from_millis(200));
+            assert!(*actual >= Duration::from_millis(300));
+        }
+        other => panic!("expected TooSlow, got {other:?}"),
     }
+    assert_eq!(report.summary.classes.too_slow, 1);
+    assert_eq!(report.summary.err, 1);
+    // Slow answers aren't retried unless asked to
+    assert_eq!(delayed.hits(), 1);
+}
 
-    // Drop main clones we no longer need; workers retain their own clones.
-    drop(job_tx);
-    drop(res_tx);
-
-    // Collect exactly one result per unique URL.
-    use std::collections::HashSet;
-    let mut seen = HashSet::with_capacity(urls.len());
-    let mut out = Vec::with_capacity(urls.len());
-
-    while seen.len() < urls.len() {
-        match res_rx.recv() {
-            Ok(ws) => {
-                if seen.insert(ws.url.clone()) {
-                    out.push(ws);
-                }
-            }
-            Err(_) => {
-                // All result senders dropped; workers done
-                break;
+#[test]
+fn retry_on_slow_retries_slow_answers() {
+    let server = MockServer::start();
+    let delayed = server.mock(|when, then| {
+        when.method(GET).path("/delayed");
+        then.status(200).delay(Duration::from_millis(300));
+    });
+    let target =
+        Target::new(server.url("/delayed")).with_max_response_time(Duration::from_millis(200));
+    let config = MonitorConfig {
+        max_retries: 2,
+        retry_on_slow: true,
+        ..fast_config()
+    };
+    let report = run_pass(vec![target], config, None);
+    assert!(matches!(
+        report.results[0].status,
+        Err(CheckError::TooSlow { .. })
+    ));
+    assert_eq!(delayed.hits(), 3);
+}
+
+#[test]
+fn scripted_retry_policy_sets_delays_and_give_up_point() {
+    let server = MockServer::start();
+    let flaky = server.mock(|when, then| {
+        when.method(GET).path("/flaky");
+        then.status(503);
+    });
+    // Retry 5xx answers after 50 ms, then 150 ms, then give up
+    let script = [Some(50), Some(150), None];
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&calls);
+    let policy = move |outcome: &CheckOutcome<'_>, attempt: u32, elapsed: Duration| {
+        seen.lock()
+            .unwrap()
+            .push((outcome.status_code(), attempt, elapsed));
+        script[attempt as usize].map(Duration::from_millis)
+    };
+    let config = MonitorConfig {
+        worker_threads: 1,
+        retry_policy: Some(Arc::new(policy)),
+        ..fast_config()
+    };
+
+    let report = run_pass(vec![server.url("/flaky")], config, None);
+    assert_eq!(report.results[0].status, Ok(503));
+    assert_eq!(report.results[0].retries, 2);
+    assert_eq!(flaky.hits(), 3);
+
+    let calls = calls.lock().unwrap();
+    let attempts: Vec<_> = calls.iter().map(|(code, n, _)| (*code, *n)).collect();
+    assert_eq!(attempts, [(Some(503), 0), (Some(503), 1), (Some(503), 2)]);
+    // Each retry started only after its scripted delay
+    let gaps: Vec<Duration> = calls.windows(2).map(|w| w[1].2 - w[0].2).collect();
+    assert!(gaps[0] >= Duration::from_millis(50), "{gaps:?}");
+    assert!(gaps[1] >= Duration::from_millis(150), "{gaps:?}");
+}
+
+#[test]
+fn no_retry_overrides_max_retries() {
+    let config = MonitorConfig {
+        max_retries: 3,
+        retry_policy: Some(Arc::new(NoRetry)),
+        ..fast_config()
+    };
+    let report = run_pass(vec!["http://127.0.0.1:1/"], config, None);
+    assert!(report.results[0].status.is_err());
+    assert_eq!(report.results[0].retries, 0);
+}
+
+#[test]
+fn explicit_local_address_still_reaches_the_server() {
+    let config = MonitorConfig {
+        local_address: Some(Ipv4Addr::LOCALHOST.into()),
+        ..fast_config()
+    };
+    let report = run_pass(vec![SERVER.url("/ok")], config, None);
+    assert_eq!(report.results[0].status, Ok(200));
+    assert!(report.summary.warnings.is_empty());
+}
+
+/// Answers one request with 200 and reports the peer address it came from.
+fn spawn_peer_reporting_server() -> (String, std::sync::mpsc::Receiver<IpAddr>) {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let (mut stream, peer) = listener.accept().unwrap();
+        let _ = tx.send(peer.ip());
+        let mut buf = [0u8; 1024];
+        let _ = stream.read(&mut buf);
+        let _ =
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    });
+    (format!("http://{addr}/"), rx)
+}
+
+// All of 127/8 is local on Linux, so a second source address is available
+#[cfg(target_os = "linux")]
+#[test]
+fn per_target_local_address_overrides_the_config() {
+    let (url, peer) = spawn_peer_reporting_server();
+    let source = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+    let config = MonitorConfig {
+        local_address: Some(Ipv4Addr::LOCALHOST.into()),
+        ..fast_config()
+    };
+    let report = run_pass(
+        vec![Target::new(url).with_local_address(source)],
+        config,
+        None,
+    );
+    assert_eq!(report.results[0].status, Ok(200));
+    assert_eq!(peer.recv_timeout(Duration::from_secs(2)).unwrap(), source);
+}
+
+#[test]
+fn unbindable_local_address_is_a_setup_error() {
+    // TEST-NET-1: never assigned to this host
+    let foreign = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let report = run_pass(
+        vec![
+            Target::new(SERVER.url("/ok")).with_local_address(foreign),
+            Target::new(SERVER.url("/missing")),
+        ],
+        fast_config(),
+        None,
+    );
+    let failed = report
+        .results
+        .iter()
+        .find(|r| r.url.ends_with("/ok"))
+        .unwrap();
+    assert!(
+        matches!(&failed.status, Err(CheckError::Transport(msg)) if msg.contains("setup error")),
+        "{:?}",
+        failed.status
+    );
+    assert_eq!(failed.response_time, Duration::ZERO);
+    assert!(report.summary.warnings[0].contains("192.0.2.1"));
+    // Targets without the override are checked as usual
+    let other = report.results.iter().find(|r| r.url.ends_with("/missing"));
+    assert_eq!(other.unwrap().status, Ok(404));
+
+    let config = MonitorConfig {
+        local_address: Some(foreign),
+        ..fast_config()
+    };
+    assert!(monitor_iter(vec![SERVER.url("/ok")], config).is_err());
+}
+
+/// Raw server streaming `total` bytes with no Content-Length; reports how
+/// many bytes it managed to write before the client hung up.
+fn spawn_streaming_server(total: usize) -> (String, std::sync::mpsc::Receiver<usize>) {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0u8; 1024];
+        let _ = stream.read(&mut buf);
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n");
+        let chunk = [b'x'; 8192];
+        let mut written = 0;
+        while written < total {
+            match stream.write(&chunk) {
+                Ok(n) => written += n,
+                Err(_) => break,
             }
         }
+        let _ = tx.send(written);
+    });
+    (format!("http://{addr}/"), rx)
+}
+
+#[test]
+fn oversized_stream_is_aborted() {
+    let total = 512 * 1024 * 1024;
+    let (url, written) = spawn_streaming_server(total);
+    let config = MonitorConfig {
+        max_body_bytes: Some(64 * 1024),
+        capture_body: true,
+        ..fast_config()
+    };
+
+    let results = monitor_websites(vec![url], config, None);
+    match results[0].status {
+        Err(CheckError::BodyTooLarge {
+            limit,
+            observed_at_least,
+        }) => {
+            assert_eq!(limit, 64 * 1024);
+            assert!(observed_at_least > limit);
+        }
+        ref other => panic!("unexpected status {other:?}"),
     }
+    assert!(results[0].body.is_none());
+    let written = written.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(written < total, "server wrote the whole body");
+}
 
-    // We’ve gathered all results we need — trigger graceful shutdown so workers exit
-    shutdown.cancel();
+#[test]
+fn content_length_over_the_cap_skips_the_download() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/big");
+        then.status(200).body(vec![b'x'; 1_000_000]);
+    });
+    let config = MonitorConfig {
+        max_body_bytes: Some(1000),
+        ..fast_config()
+    };
 
-    // Join workers
-    for w in workers {
-        let _ = w.join();
+    let report = run_pass(vec![server.url("/big")], config, None);
+    assert_eq!(
+        report.results[0].status,
+        Err(CheckError::BodyTooLarge {
+            limit: 1000,
+            observed_at_least: 1_000_000
+        })
+    );
+    assert_eq!(report.summary.classes.body_too_large, 1);
+}
+
+#[test]
+fn body_within_the_cap_is_captured() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/small");
+        then.status(200).body("hello");
+    });
+    let config = MonitorConfig {
+        max_body_bytes: Some(5),
+        capture_body: true,
+        ..fast_config()
+    };
+
+    let results = monitor_websites(vec![server.url("/small")], config, None);
+    assert_eq!(results[0].status, Ok(200));
+    assert_eq!(results[0].body.as_deref(), Some("hello"));
+}
+
+#[test]
+fn labels_are_stamped_on_every_result() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET);
+        then.status(200);
+    });
+    let config = MonitorConfig {
+        labels: [("region".to_string(), "eu-west".to_string())].into(),
+        ..fast_config()
+    };
+
+    let results = monitor_websites(vec![server.url("/a"), "not a url".into()], config, None);
+    for ws in &results {
+        assert_eq!(ws.labels["region"], "eu-west", "{}", ws.url);
     }
+    let json = serde_json::to_value(&results[0]).unwrap();
+    assert_eq!(json["labels"], serde_json::json!({"region": "eu-west"}));
+}
+
+#[test]
+fn oversized_headers_are_cut_and_flagged() {
+    let server = MockServer::start();
+    let cookie = "x".repeat(5000);
+    server.mock(|when, then| {
+        when.path("/cookie");
+        then.status(200).header("Set-Cookie", &cookie);
+    });
+    server.mock(|when, then| {
+        when.path("/plain");
+        then.status(200).header("X-Small", "1");
+    });
+
+    let config = MonitorConfig {
+        capture_headers: true,
+        ..fast_config()
+    };
+    let report = run_pass(
+        vec![server.url("/cookie"), server.url("/plain")],
+        config,
+        None,
+    );
+    let find = |path: &str| {
+        report
+            .results
+            .iter()
+            .find(|r| r.url.ends_with(path))
+            .unwrap()
+    };
+    let cut = find("/cookie");
+    let (_, value) = cut
+        .headers
+        .as_ref()
+        .unwrap()
+        .iter()
+        .find(|(k, _)| k == "set-cookie")
+        .unwrap();
+    assert_eq!(value.len(), 1024 + "…".len());
+    assert!(value.ends_with('…'));
+    assert!(cut.headers_truncated);
+    assert_eq!(
+        serde_json::to_value(cut).unwrap()["headers_truncated"],
+        true
+    );
+
+    let plain = find("/plain");
+    assert!(!plain.headers_truncated);
+    assert!(
+        serde_json::to_value(plain)
+            .unwrap()
+            .get("headers_truncated")
+            .is_none()
+    );
+}
+
+#[test]
+fn one_url_checked_with_and_without_auth() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.path("/me").header("authorization", "Bearer secret");
+        then.status(200);
+    });
+    server.mock(|when, then| {
+        when.path("/me");
+        then.status(401);
+    });
+
+    let url = server.url("/me");
+    let authed = Target::new(url.as_str()).with_header("Authorization", "Bearer secret");
+    let anonymous = Target::new(url.as_str()).with_expect_status(401);
+    let named = Target::new(url.as_str()).with_id("me-named");
+    let report = run_pass(
+        vec![authed.clone(), anonymous.clone(), named, authed.clone()],
+        fast_config(),
+        None,
+    );
+
+    // The repeated target is still checked once
+    assert_eq!(report.results.len(), 3);
+    let by_id = |id: &str| report.results.iter().find(|r| r.id() == id).unwrap();
+    let ok = by_id(&authed.id());
+    assert_eq!(ok.status, Ok(200));
+    assert!(ok.is_success(true));
+    let denied = by_id(&anonymous.id());
+    assert_eq!(denied.status, Ok(401));
+    assert!(denied.expected_down && denied.is_success(true));
+    assert_eq!(by_id("me-named").status, Ok(401));
+    assert_eq!(report.summary.ok, 2);
 
-    out
+    let json = serde_json::to_value(ok).unwrap();
+    assert_eq!(json["url"], url);
+    assert_eq!(json["target_id"], authed.id().as_ref());
 }
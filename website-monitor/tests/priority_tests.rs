@@ -0,0 +1,108 @@
+use httpmock::prelude::*;
+use std::{
+    process::Command,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use website_monitor::{CheckOutcome, MonitorConfig, Target, run_pass};
+
+/// `(path, request id)` of every attempt, in the order they arrived.
+static SEEN: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Matcher noting each attempt once; matchers may run more than once per
+/// request, but the request id tells attempts apart.
+fn record(req: &HttpMockRequest) -> bool {
+    let id = req.headers.iter().flatten().find_map(|(name, value)| {
+        name.eq_ignore_ascii_case("x-request-id")
+            .then(|| value.clone())
+    });
+    if let Some(id) = id {
+        let mut seen = SEEN.lock().unwrap();
+        if !seen.iter().any(|(_, i)| *i == id) {
+            seen.push((req.path.clone(), id));
+        }
+    }
+    true
+}
+
+fn server() -> MockServer {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path_contains("/ok-").matches(record);
+        then.status(200).delay(Duration::from_millis(10));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path_contains("/broken-").matches(record);
+        then.status(500);
+    });
+    server
+}
+
+#[test]
+fn lower_priorities_are_checked_first() {
+    SEEN.lock().unwrap().clear();
+    let server = server();
+    // Classes interleaved, so input order alone would mix them
+    let targets: Vec<Target> = [
+        ("/ok-a", 1),
+        ("/ok-b", 0),
+        ("/ok-c", 1),
+        ("/broken-d", 0),
+        ("/ok-e", 0),
+        ("/ok-f", 1),
+    ]
+    .into_iter()
+    .map(|(path, priority)| Target::new(server.url(path)).with_priority(priority))
+    .collect();
+    let config = MonitorConfig {
+        worker_threads: 1,
+        request_timeout: Duration::from_secs(2),
+        inject_request_id: Some("X-Request-Id".into()),
+        retry_policy: Some(Arc::new(|_: &CheckOutcome<'_>, attempt: u32, _| {
+            (attempt < 1).then_some(Duration::ZERO)
+        })),
+        ..MonitorConfig::default()
+    };
+
+    let report = run_pass(targets, config, None);
+    assert_eq!(report.results.len(), 6);
+    let order: Vec<String> = SEEN
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(p, _)| p.clone())
+        .collect();
+    // Input order within a class, and the retry ahead of priority 1
+    assert_eq!(
+        order,
+        [
+            "/ok-b",
+            "/broken-d",
+            "/ok-e",
+            "/broken-d",
+            "/ok-a",
+            "/ok-c",
+            "/ok-f"
+        ]
+    );
+}
+
+#[test]
+fn cli_sets_priorities_per_url() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/ok");
+        then.status(200);
+    });
+    let run = |spec: String| {
+        Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+            .args(["--priority-for", &spec, &server.url("/ok")])
+            .output()
+            .expect("binary runs")
+            .status
+            .code()
+    };
+    assert_eq!(run(format!("{} 3", server.url("/ok"))), Some(0));
+    assert_eq!(run(format!("{} 256", server.url("/ok"))), Some(2));
+    assert_eq!(run(format!("{} 1", server.url("/other"))), Some(2));
+}
@@ -0,0 +1,145 @@
+use httpmock::prelude::*;
+use std::process::Command;
+use website_monitor::{CheckError, MonitorConfig, RedirectPolicy, Target, WebsiteStatus, run_pass};
+
+/// `/short` -> `/landing`, `/a` -> `/b` -> `/c`, `/login` -> the SSO host,
+/// `/login-elsewhere` -> somewhere else and `/login-open` answers directly.
+fn server() -> MockServer {
+    let server = MockServer::start();
+    for (path, status, location) in [
+        ("/short", 301, "/landing"),
+        ("/a", 302, "/b"),
+        ("/b", 302, "/c"),
+        ("/login", 302, "https://sso.test/authorize?client=app"),
+        ("/login-elsewhere", 302, "https://evil.test/"),
+        ("/login-relative", 303, "/sso/start"),
+    ] {
+        server.mock(|when, then| {
+            when.method(GET).path(path);
+            then.status(status).header("location", location);
+        });
+    }
+    for path in ["/landing", "/c", "/login-open"] {
+        server.mock(|when, then| {
+            when.method(GET).path(path);
+            then.status(200);
+        });
+    }
+    server
+}
+
+fn check(server: &MockServer, path: &str, policy: RedirectPolicy) -> WebsiteStatus {
+    let target = Target::new(server.url(path)).with_redirect_policy(policy);
+    let mut report = run_pass(vec![target], MonitorConfig::default(), None);
+    report.results.remove(0)
+}
+
+#[test]
+fn follow_judges_where_redirects_end_up() {
+    let server = server();
+    let ws = check(&server, "/short", RedirectPolicy::default());
+    assert_eq!(ws.status, Ok(200));
+    assert_eq!(ws.location, None);
+
+    let ws = check(&server, "/a", RedirectPolicy::Follow { max: 2 });
+    assert_eq!(ws.status, Ok(200));
+    let ws = check(&server, "/a", RedirectPolicy::Follow { max: 1 });
+    assert!(
+        matches!(&ws.status, Err(CheckError::Transport(msg)) if msg.contains("redirect")),
+        "{:?}",
+        ws.status
+    );
+}
+
+#[test]
+fn report_as_is_stops_at_the_redirect() {
+    let server = server();
+    let ws = check(&server, "/short", RedirectPolicy::ReportAsIs);
+    assert_eq!(ws.status, Ok(301));
+    assert!(ws.is_success(true));
+    assert_eq!(ws.location.as_deref(), Some("/landing"));
+
+    // Answers that aren't redirects are unaffected
+    let ws = check(&server, "/landing", RedirectPolicy::ReportAsIs);
+    assert_eq!((ws.status, ws.location), (Ok(200), None));
+}
+
+#[test]
+fn expect_redirect_to_passes_only_redirects_with_the_prefix() {
+    let server = server();
+    let sso = RedirectPolicy::ExpectRedirectTo("https://sso.test/".into());
+
+    let ws = check(&server, "/login", sso.clone());
+    assert_eq!(ws.status, Ok(302));
+    assert_eq!(
+        ws.location.as_deref(),
+        Some("https://sso.test/authorize?client=app")
+    );
+
+    let ws = check(&server, "/login-elsewhere", sso.clone());
+    assert_eq!(
+        ws.status,
+        Err(CheckError::WrongRedirect {
+            status: 302,
+            location: Some("https://evil.test/".into()),
+            expected: "https://sso.test/".into(),
+        })
+    );
+    assert!(!ws.is_success(false));
+
+    let ws = check(&server, "/login-open", sso);
+    assert!(matches!(
+        ws.status,
+        Err(CheckError::WrongRedirect {
+            status: 200,
+            location: None,
+            ..
+        })
+    ));
+
+    // Relative locations are taken from the URL checked
+    let ws = check(
+        &server,
+        "/login-relative",
+        RedirectPolicy::ExpectRedirectTo(server.url("/sso/")),
+    );
+    assert_eq!(ws.status, Ok(303));
+}
+
+#[test]
+fn cli_sets_policies_per_url() {
+    let server = server();
+    let run = |args: &[&str]| {
+        let out = Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+            .args(args)
+            .args([server.url("/short"), server.url("/login-elsewhere")])
+            .output()
+            .expect("binary runs");
+        (
+            out.status.code(),
+            String::from_utf8_lossy(&out.stdout).into_owned(),
+        )
+    };
+
+    // The per-URL policy overrides the one for every URL
+    let short = server.url("/short");
+    let login = server.url("/login-elsewhere");
+    let (code, stdout) = run(&[
+        "--redirects",
+        "as-is",
+        "--redirects-for",
+        &format!("{login} expect=https://sso.test/"),
+    ]);
+    assert_eq!(code, Some(1), "{stdout}");
+    assert!(stdout.contains(&format!("[OK] {short}")), "{stdout}");
+    assert!(stdout.contains("status=301"), "{stdout}");
+    assert!(
+        stdout.contains("expected a redirect to https://sso.test/, got 302 to https://evil.test/"),
+        "{stdout}"
+    );
+
+    let (code, _) = run(&["--redirects", "as-is"]);
+    assert_eq!(code, Some(0));
+    let (code, _) = run(&["--redirects", "sideways"]);
+    assert_eq!(code, Some(2));
+}
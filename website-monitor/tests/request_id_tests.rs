@@ -0,0 +1,150 @@
+use httpmock::prelude::*;
+use std::{
+    collections::BTreeSet,
+    process::Command,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use website_monitor::{CheckOutcome, MonitorConfig, run_pass};
+
+/// `(path, id)` of every request that came with an `X-Request-Id`.
+static SEEN: Mutex<BTreeSet<(String, String)>> = Mutex::new(BTreeSet::new());
+
+/// Matcher noting the id a request came with. Matchers may run more than
+/// once per request, hence the set.
+fn record_id(req: &HttpMockRequest) -> bool {
+    let id = req.headers.iter().flatten().find_map(|(name, value)| {
+        name.eq_ignore_ascii_case("x-request-id")
+            .then(|| value.clone())
+    });
+    if let Some(id) = id {
+        SEEN.lock().unwrap().insert((req.path.clone(), id));
+    }
+    true
+}
+
+fn seen_at(path: &str) -> BTreeSet<String> {
+    SEEN.lock()
+        .unwrap()
+        .iter()
+        .filter(|(p, _)| p == path)
+        .map(|(_, id)| id.clone())
+        .collect()
+}
+
+fn config() -> MonitorConfig {
+    MonitorConfig {
+        worker_threads: 4,
+        request_timeout: Duration::from_secs(2),
+        inject_request_id: Some("X-Request-Id".into()),
+        ..MonitorConfig::default()
+    }
+}
+
+#[test]
+fn each_check_sends_the_id_it_reports() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .path_contains("/page-")
+            .header_exists("x-request-id")
+            .matches(record_id);
+        then.status(200);
+    });
+    let urls: Vec<String> = (0..3).map(|i| server.url(format!("/page-{i}"))).collect();
+    let report = run_pass(urls, config(), None);
+    assert_eq!(mock.hits(), 3);
+
+    let mut ids = BTreeSet::new();
+    for ws in &report.results {
+        let id = ws.request_id.clone().expect("id recorded");
+        assert_eq!(ws.request_ids.as_slice(), std::slice::from_ref(&id));
+        let path = ws
+            .url
+            .rsplit_once('/')
+            .map(|(_, p)| format!("/{p}"))
+            .unwrap();
+        assert_eq!(seen_at(&path), BTreeSet::from([id.clone()]));
+        ids.insert(id);
+    }
+    assert_eq!(ids.len(), 3, "ids are unique");
+}
+
+#[test]
+fn retries_get_fresh_ids_and_all_are_listed() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/flaky").matches(record_id);
+        then.status(503);
+    });
+    let config = MonitorConfig {
+        retry_policy: Some(Arc::new(|_: &CheckOutcome<'_>, attempt: u32, _| {
+            (attempt < 2).then_some(Duration::from_millis(10))
+        })),
+        ..config()
+    };
+    let report = run_pass(vec![server.url("/flaky")], config, None);
+    assert_eq!(mock.hits(), 3);
+
+    let ws = &report.results[0];
+    assert_eq!(ws.status, Ok(503));
+    assert_eq!(ws.retries, 2);
+    assert_eq!(ws.request_ids.len(), 3);
+    assert_eq!(ws.request_id.as_ref(), ws.request_ids.last());
+    let listed: BTreeSet<String> = ws.request_ids.iter().cloned().collect();
+    assert_eq!(listed.len(), 3, "{:?}", ws.request_ids);
+    assert_eq!(seen_at("/flaky"), listed);
+}
+
+#[test]
+fn no_header_unless_asked() {
+    let server = MockServer::start();
+    let with_id = server.mock(|when, then| {
+        when.method(GET)
+            .path("/quiet")
+            .header_exists("x-request-id");
+        then.status(500);
+    });
+    let without = server.mock(|when, then| {
+        when.method(GET).path("/quiet");
+        then.status(200);
+    });
+    let report = run_pass(vec![server.url("/quiet")], MonitorConfig::default(), None);
+    let ws = &report.results[0];
+    assert_eq!(ws.status, Ok(200));
+    assert_eq!((&ws.request_id, ws.request_ids.len()), (&None, 0));
+    assert_eq!((with_id.hits(), without.hits()), (0, 1));
+
+    // Nor for checks without HTTP requests to carry it
+    let report = run_pass(vec!["smtp://127.0.0.1:9"], config(), None);
+    assert_eq!(report.results[0].request_id, None);
+}
+
+#[test]
+fn cli_names_the_header() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/cli")
+            .header_exists("x-correlation-id");
+        then.status(200);
+    });
+    let run = |header: &str| {
+        Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+            .args(["--format", "json", "--request-id-header", header])
+            .arg(server.url("/cli"))
+            .output()
+            .expect("binary runs")
+    };
+
+    let out = run("X-Correlation-Id");
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(mock.hits(), 1);
+    let json: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let result = &json["results"][0];
+    assert_eq!(result["request_ids"][0], result["request_id"]);
+    assert_eq!(result["request_id"].as_str().unwrap().len(), 36);
+
+    let out = run("not a header");
+    assert_eq!(out.status.code(), Some(2));
+}
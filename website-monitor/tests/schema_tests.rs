@@ -0,0 +1,221 @@
+use chrono::{TimeZone, Utc};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+use website_monitor::{
+    CacheStatus, CheckError, Checkpoint, HttpMethod, RunReport, RunSummary, SCHEMA_VERSION,
+    SchemaError, StateSnapshot, Versioned, WebsiteStatus, parse_results,
+};
+
+/// Documents as written by the current version; a change to what's written
+/// (or read) that breaks them needs a new schema version and new fixtures.
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/schema_v1")
+        .join(name)
+}
+
+fn read_fixture(name: &str) -> String {
+    fs::read_to_string(fixture(name)).unwrap()
+}
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wm-schema-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+fn results() -> Vec<WebsiteStatus> {
+    let at = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap();
+    let ok = WebsiteStatus {
+        timestamp: at,
+        labels: BTreeMap::from([("region".to_string(), "eu".to_string())]),
+        headers: Some(vec![("x-cache".into(), "HIT".into())]),
+        cache_status: Some(CacheStatus::Hit),
+        body_hash: Some("9a3f1c0b2d4e5f60".into()),
+        method_used: Some(HttpMethod::Head),
+        retries: 1,
+        ..WebsiteStatus::new(
+            "https://shop.example.com/",
+            Ok(200),
+            Duration::from_micros(80_500),
+        )
+    };
+    let timeout = WebsiteStatus {
+        url: "https://status.example.com/".into(),
+        target_id: "https://status.example.com/".into(),
+        status: Err(CheckError::TimedOut {
+            limit: Duration::from_secs(5),
+        }),
+        response_time: Duration::from_millis(5001),
+        headers: None,
+        cache_status: None,
+        body_hash: None,
+        method_used: None,
+        retries: 0,
+        ..ok.clone()
+    };
+    let redirect = WebsiteStatus {
+        url: "https://app.example.com/login".into(),
+        target_id: "https://app.example.com/login".into(),
+        status: Err(CheckError::WrongRedirect {
+            status: 302,
+            location: Some("https://evil.example.net/".into()),
+            expected: "https://sso.example.com/".into(),
+        }),
+        response_time: Duration::from_millis(40),
+        location: Some("https://evil.example.net/".into()),
+        ..timeout.clone()
+    };
+    vec![ok, timeout, redirect]
+}
+
+fn report() -> RunReport {
+    let results = results();
+    RunReport {
+        summary: RunSummary::from_results(&results, true),
+        results,
+        workers: Vec::new(),
+    }
+}
+
+fn json_lines(text: &str) -> Vec<Value> {
+    text.lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+#[test]
+fn written_documents_match_the_fixtures() {
+    let report = serde_json::to_value(Versioned::new(&report())).unwrap();
+    assert_eq!(report["schema_version"], SCHEMA_VERSION);
+    let expected: Value = serde_json::from_str(&read_fixture("report.json")).unwrap();
+    assert_eq!(report, expected);
+
+    let path = scratch("checkpoint.ndjson");
+    let _ = fs::remove_file(&path);
+    let mut checkpoint = Checkpoint::open(&path).unwrap();
+    for ws in results() {
+        checkpoint.record(&ws).unwrap();
+    }
+    checkpoint.flush().unwrap();
+    assert_eq!(
+        json_lines(&fs::read_to_string(&path).unwrap()),
+        json_lines(&read_fixture("checkpoint.ndjson"))
+    );
+
+    let path = scratch("state.json");
+    StateSnapshot {
+        get_instead_of_head: ["https://shop.example.com/".to_string()].into(),
+    }
+    .save(&path)
+    .unwrap();
+    let state: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let expected: Value = serde_json::from_str(&read_fixture("state.json")).unwrap();
+    assert_eq!(state, expected);
+}
+
+#[test]
+fn fixtures_read_back() {
+    let written: Vec<Value> = results()
+        .iter()
+        .map(|ws| serde_json::to_value(ws).unwrap())
+        .collect();
+
+    let parsed = parse_results(fs::File::open(fixture("report.json")).unwrap()).unwrap();
+    let parsed: Vec<Value> = parsed
+        .iter()
+        .map(|ws| serde_json::to_value(ws).unwrap())
+        .collect();
+    assert_eq!(parsed, written);
+
+    let path = scratch("resumed.ndjson");
+    fs::copy(fixture("checkpoint.ndjson"), &path).unwrap();
+    let checkpoint = Checkpoint::open(&path).unwrap();
+    assert_eq!(checkpoint.len(), 3);
+    let saved = checkpoint.saved("https://status.example.com/").unwrap();
+    assert_eq!(serde_json::to_value(saved).unwrap(), written[1]);
+
+    let state = StateSnapshot::load(fixture("state.json")).unwrap();
+    assert_eq!(
+        state.get_instead_of_head,
+        ["https://shop.example.com/".to_string()].into()
+    );
+}
+
+/// The report fixture claiming to come from schema `version`.
+fn report_from(version: u32) -> String {
+    let mut doc: Value = serde_json::from_str(&read_fixture("report.json")).unwrap();
+    doc["schema_version"] = version.into();
+    doc.to_string()
+}
+
+#[test]
+fn other_versions_are_refused_clearly() {
+    let newer = SCHEMA_VERSION + 1;
+    let err = parse_results(report_from(newer).as_bytes()).unwrap_err();
+    assert!(matches!(err, SchemaError::Newer { found } if found == newer));
+    assert!(
+        err.to_string().starts_with("file is from a newer version"),
+        "{err}"
+    );
+    let err = parse_results(report_from(0).as_bytes()).unwrap_err();
+    assert!(matches!(err, SchemaError::Older { found: 0 }), "{err}");
+
+    // Continuous output: any pass from a newer version spoils the file
+    let stream = report_from(SCHEMA_VERSION) + "\n" + &report_from(newer);
+    assert!(matches!(
+        parse_results(stream.as_bytes()),
+        Err(SchemaError::Newer { .. })
+    ));
+
+    // Unversioned documents predate versioning and still read
+    let mut doc: Value = serde_json::from_str(&read_fixture("report.json")).unwrap();
+    doc.as_object_mut().unwrap().remove("schema_version");
+    assert_eq!(parse_results(doc.to_string().as_bytes()).unwrap().len(), 3);
+
+    // A checkpoint line from a newer version isn't mistaken for crash damage
+    let path = scratch("newer.ndjson");
+    let mut lines = read_fixture("checkpoint.ndjson");
+    lines.push_str(&format!(
+        "{{\"schema_version\":{newer},\"url\":\"https://x.test\"}}\n"
+    ));
+    fs::write(&path, &lines).unwrap();
+    let err = Checkpoint::open(&path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().contains("newer version"), "{err}");
+    assert_eq!(fs::read_to_string(&path).unwrap(), lines);
+
+    let path = scratch("newer-state.json");
+    fs::write(
+        &path,
+        format!("{{\"schema_version\":{newer},\"get_instead_of_head\":[]}}"),
+    )
+    .unwrap();
+    let err = StateSnapshot::load(&path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn cli_names_the_file_from_a_newer_version() {
+    let path = scratch("newer-report.json");
+    fs::write(&path, report_from(SCHEMA_VERSION + 1)).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+        .arg("diff")
+        .arg(fixture("report.json"))
+        .arg(&path)
+        .output()
+        .expect("binary runs");
+    assert_eq!(out.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("newer-report.json: file is from a newer version"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,175 @@
+use std::{
+    io::{BufRead, BufReader},
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex, mpsc},
+    thread,
+    time::Duration,
+};
+use website_monitor::{ResultWriter, SocketAddress, SocketWriter, WebsiteStatus};
+
+fn result(n: usize) -> WebsiteStatus {
+    WebsiteStatus::new(
+        format!("https://site{n}.test"),
+        Ok(200),
+        Duration::from_millis(42),
+    )
+}
+
+/// Accepts one connection on `listener` and passes on the URL of every
+/// line it reads, until `stop` says so or the writer hangs up.
+fn collector(listener: TcpListener, lines: mpsc::Sender<String>, stop: mpsc::Receiver<()>) {
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        drop(listener);
+        stream
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        while stop.try_recv().is_err() {
+            match reader.read_line(&mut line) {
+                Ok(0) => return,
+                Ok(_) => {
+                    let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+                    lines.send(value["url"].as_str().unwrap().into()).unwrap();
+                    line.clear();
+                }
+                // Timed out; a partial line stays in `line`
+                Err(_) => {}
+            }
+        }
+    });
+}
+
+fn receive(lines: &mpsc::Receiver<String>, count: usize) -> Vec<String> {
+    (0..count)
+        .map(|_| lines.recv_timeout(Duration::from_secs(5)).unwrap())
+        .collect()
+}
+
+#[test]
+fn delivery_resumes_after_the_collector_restarts() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr: SocketAddr = listener.local_addr().unwrap();
+    let (lines_tx, lines) = mpsc::channel();
+    let (stop, stop_rx) = mpsc::channel();
+    collector(listener, lines_tx.clone(), stop_rx);
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut writer = {
+        let log = Arc::clone(&log);
+        SocketWriter::with_log(
+            SocketAddress::Tcp(addr.to_string()),
+            5,
+            Duration::from_millis(50),
+            move |m| log.lock().unwrap().push(m.to_string()),
+        )
+    };
+    for n in 0..3 {
+        writer.write_result(&result(n), true).unwrap();
+    }
+    assert_eq!(
+        receive(&lines, 3),
+        [
+            "https://site0.test",
+            "https://site1.test",
+            "https://site2.test"
+        ]
+    );
+
+    // Kill the collector, then keep writing while it's away
+    stop.send(()).unwrap();
+    thread::sleep(Duration::from_millis(200));
+    for n in 3..13 {
+        writer.write_result(&result(n), true).unwrap();
+    }
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(writer.pending(), 5);
+    assert_eq!(writer.dropped(), 5);
+
+    let (stop, stop_rx) = mpsc::channel();
+    collector(TcpListener::bind(addr).unwrap(), lines_tx, stop_rx);
+    let expected: Vec<String> = (8..13).map(|n| format!("https://site{n}.test")).collect();
+    assert_eq!(receive(&lines, 5), expected);
+    assert_eq!(writer.dropped(), 5);
+    drop(writer);
+    drop(stop);
+
+    let log = log.lock().unwrap();
+    let address = format!("tcp://{addr}");
+    assert_eq!(log[0], format!("{address}: connected"));
+    assert_eq!(log[1], format!("{address}: collector hung up"));
+    assert!(log[2].starts_with(&format!("{address}: cannot connect: ")));
+    assert_eq!(log[3], format!("{address}: connected"));
+    assert_eq!(log.len(), 4, "{log:?}");
+}
+
+#[cfg(unix)]
+#[test]
+fn lines_written_before_drop_reach_a_unix_collector() {
+    use std::{fs, io::Read, os::unix::net::UnixListener};
+
+    let path = std::env::temp_dir().join(format!("wm-socket-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let reader = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut all = String::new();
+        stream.read_to_string(&mut all).unwrap();
+        all
+    });
+
+    let mut writer = SocketWriter::new(
+        SocketAddress::Unix(path.clone()),
+        10,
+        Duration::from_millis(50),
+    );
+    for n in 0..4 {
+        writer.write_result(&result(n), true).unwrap();
+    }
+    // Dropping sends whatever is still queued and hangs up
+    drop(writer);
+    let all = reader.join().unwrap();
+    let urls: Vec<String> = all
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["url"].to_string())
+        .collect();
+    assert_eq!(urls.len(), 4, "{all}");
+    assert!(urls[3].contains("site3.test"));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn a_collector_that_stops_reading_does_not_hold_up_drop() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    // Accepts, then never reads
+    let (hold, held) = mpsc::channel::<()>();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let _ = held.recv();
+        drop(stream);
+    });
+
+    let mut writer = SocketWriter::new(
+        SocketAddress::Tcp(addr.to_string()),
+        200_000,
+        Duration::from_millis(50),
+    );
+    // Far more than the socket buffers hold
+    for n in 0..100_000 {
+        writer.write_result(&result(n), true).unwrap();
+    }
+    thread::sleep(Duration::from_millis(500));
+    assert!(writer.pending() > 0);
+
+    let (done, dropped) = mpsc::channel();
+    thread::spawn(move || {
+        drop(writer);
+        done.send(()).unwrap();
+    });
+    dropped
+        .recv_timeout(Duration::from_secs(10))
+        .expect("drop returns");
+    drop(hold);
+}
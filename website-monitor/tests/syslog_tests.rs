@@ -0,0 +1,99 @@
+#![cfg(all(feature = "syslog", unix))]
+
+use std::{fs, os::unix::net::UnixDatagram, path::PathBuf, time::Duration};
+use website_monitor::{CheckError, ResultWriter, SyslogFormat, SyslogWriter, WebsiteStatus};
+
+/// A bound datagram socket standing in for `/dev/log`.
+fn fake_syslog(name: &str) -> (UnixDatagram, PathBuf) {
+    let path = std::env::temp_dir().join(format!("wm-syslog-{name}-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let socket = UnixDatagram::bind(&path).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    (socket, path)
+}
+
+fn recv(socket: &UnixDatagram) -> String {
+    let mut buf = [0u8; 4096];
+    let n = socket.recv(&mut buf).unwrap();
+    String::from_utf8(buf[..n].to_vec()).unwrap()
+}
+
+fn result(url: &str, status: Result<u16, CheckError>, retries: u32) -> WebsiteStatus {
+    WebsiteStatus {
+        retries,
+        ..WebsiteStatus::new(url, status, Duration::from_millis(42))
+    }
+}
+
+#[test]
+fn rfc5424_lines_carry_severity_and_structured_data() {
+    let (socket, path) = fake_syslog("rfc5424");
+    let mut writer = SyslogWriter::new(&path, SyslogFormat::Rfc5424);
+
+    writer
+        .write_result(&result("https://ok.test", Ok(200), 0), true)
+        .unwrap();
+    writer
+        .write_result(&result("https://retried.test", Ok(200), 2), true)
+        .unwrap();
+    let timeout = CheckError::TimedOut {
+        limit: Duration::from_secs(5),
+    };
+    writer
+        .write_result(&result("https://down.test/\"x\"", Err(timeout), 0), true)
+        .unwrap();
+
+    let ok = recv(&socket);
+    assert!(ok.starts_with("<30>1 "), "{ok}");
+    assert!(ok.contains(" website-monitor "));
+    assert!(ok.contains(r#"[check@32473 url="https://ok.test" status="200" response_ms="42""#));
+    assert!(ok.ends_with("OK https://ok.test status=200 42 ms"));
+
+    let retried = recv(&socket);
+    assert!(retried.starts_with("<28>1 "), "{retried}");
+    assert!(retried.contains(r#"retries="2""#));
+
+    let down = recv(&socket);
+    assert!(down.starts_with("<27>1 "), "{down}");
+    assert!(down.contains(r#"url="https://down.test/\"x\"" error="timed_out""#));
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn plain_lines_are_priority_tagged() {
+    let (socket, path) = fake_syslog("plain");
+    let mut writer = SyslogWriter::new(&path, SyslogFormat::Plain);
+    writer
+        .write_result(&result("https://gone.test", Ok(404), 0), true)
+        .unwrap();
+    let line = recv(&socket);
+    assert!(line.starts_with("<27>website-monitor["), "{line}");
+    assert!(line.ends_with("]: ERR https://gone.test status=404 42 ms"));
+
+    // A 4xx the policy tolerates is only a warning
+    writer
+        .write_result(&result("https://gone.test", Ok(404), 0), false)
+        .unwrap();
+    assert!(recv(&socket).starts_with("<28>"));
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn a_missing_socket_is_an_error_not_a_panic_and_recovers() {
+    let path = std::env::temp_dir().join(format!("wm-syslog-late-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut writer = SyslogWriter::new(&path, SyslogFormat::Plain);
+    let ws = result("https://ok.test", Ok(200), 0);
+    assert!(writer.write_result(&ws, true).is_err());
+
+    // Once the daemon is up, the next result goes through
+    let socket = UnixDatagram::bind(&path).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    writer.write_result(&ws, true).unwrap();
+    assert!(recv(&socket).contains("OK https://ok.test"));
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,64 @@
+use httpmock::prelude::*;
+use std::process::Command;
+
+fn run(server: &MockServer, args: &[&str]) -> (Option<i32>, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_website-monitor"))
+        .args(args)
+        .args([server.url("/ok"), server.url("/gone")])
+        .output()
+        .expect("binary runs");
+    (
+        out.status.code(),
+        String::from_utf8_lossy(&out.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn cli_prints_results_as_a_table() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/ok");
+        then.status(200);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/gone");
+        then.status(404);
+    });
+    let (ok, gone) = (server.url("/ok"), server.url("/gone"));
+    let width = gone.len().max(ok.len());
+
+    let (code, stdout) = run(
+        &server,
+        &["--format", "table", "--columns", "result,url,status"],
+    );
+    assert_eq!(code, Some(1));
+    let rows: Vec<&str> = stdout.lines().take(3).collect();
+    assert_eq!(rows[0], format!("RESULT  {:<width$}  STATUS", "URL"));
+    assert!(
+        rows.contains(&format!("OK      {ok:<width$}  200").as_str()),
+        "{stdout}"
+    );
+    assert!(
+        rows.contains(&format!("ERR     {gone:<width$}  404").as_str()),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Summary: 1 OK, 1 ERR"), "{stdout}");
+
+    let (code, stdout) = run(&server, &["--format", "table", "--allow-4xx"]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.starts_with("RESULT  URL"), "{stdout}");
+    assert!(
+        stdout.contains(&format!("OK      {gone:<width$}  404")),
+        "{stdout}"
+    );
+
+    // Lines as before without it
+    let (_, stdout) = run(&server, &[]);
+    assert!(
+        stdout.contains(&format!("[OK] {ok} | status=200 | ")),
+        "{stdout}"
+    );
+
+    let (code, _) = run(&server, &["--format", "table", "--columns", "url,colour"]);
+    assert_eq!(code, Some(2));
+}
@@ -0,0 +1,164 @@
+#![cfg(target_os = "linux")]
+//! Passes join every thread they start, however they end. A single test,
+//! so the thread counts aren't muddled by other tests running alongside.
+
+use httpmock::prelude::*;
+use std::{fs, panic, sync::Arc, thread, time::Duration};
+use website_monitor::{
+    CheckError, CheckOutcome, MonitorConfig, SampleSpec, Shutdown, monitor_iter, run_pass,
+};
+
+fn threads() -> usize {
+    fs::read_dir("/proc/self/task").unwrap().count()
+}
+
+/// Run 100 passes, taking turns through `scenarios`, and check the process
+/// has no more threads after than before.
+fn assert_no_leaks(scenarios: &[(&str, &dyn Fn())]) {
+    // Each once first, for threads started on first use (the mock server's)
+    for (_, pass) in scenarios {
+        pass();
+    }
+    let before = threads();
+    let mut growth = vec![0isize; scenarios.len()];
+    for i in 0..100 {
+        let at = threads();
+        let (_, pass) = scenarios[i % scenarios.len()];
+        pass();
+        growth[i % scenarios.len()] += threads() as isize - at as isize;
+    }
+    let after = threads();
+    let growth: Vec<(&str, isize)> = scenarios
+        .iter()
+        .map(|(name, _)| *name)
+        .zip(growth)
+        .collect();
+    assert!(
+        after <= before,
+        "{before} threads before 100 passes, {after} after; growth by scenario: {growth:?}"
+    );
+}
+
+fn config() -> MonitorConfig {
+    MonitorConfig {
+        worker_threads: 2,
+        request_timeout: Duration::from_secs(2),
+        ..MonitorConfig::default()
+    }
+}
+
+#[test]
+fn passes_join_every_thread() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/ok");
+        then.status(200);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200).delay(Duration::from_millis(20));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/broken");
+        then.status(500);
+    });
+    let urls = |paths: &[&str]| -> Vec<String> { paths.iter().map(|p| server.url(*p)).collect() };
+
+    let success = || {
+        let config = MonitorConfig {
+            retry_policy: Some(Arc::new(|_: &CheckOutcome<'_>, attempt: u32, _| {
+                (attempt < 1).then_some(Duration::ZERO)
+            })),
+            ..config()
+        };
+        let report = run_pass(urls(&["/ok", "/broken", "/ok", "/slow"]), config, None);
+        assert_eq!((report.summary.ok, report.summary.err), (2, 1));
+    };
+
+    let cancelled_before_the_pass = || {
+        let shutdown = Shutdown::new();
+        shutdown.cancel();
+        run_pass(urls(&["/ok", "/slow"]), config(), Some(shutdown));
+    };
+
+    let dropped_mid_pass = || {
+        let mut iter = monitor_iter(urls(&["/ok", "/slow", "/broken"]), config()).unwrap();
+        assert!(iter.next().is_some());
+    };
+
+    let no_urls = || {
+        let report = run_pass(Vec::<String>::new(), config(), None);
+        assert_eq!(report.summary.total, 0);
+    };
+
+    let no_urls_after_sampling = || {
+        let config = MonitorConfig {
+            sample: Some(SampleSpec::count(0)),
+            ..config()
+        };
+        let report = run_pass(urls(&["/ok", "/ok"]), config, None);
+        assert_eq!(report.summary.total, 0);
+    };
+
+    let invalid_urls = || {
+        let report = run_pass(vec!["not a url", "http://", "::"], config(), None);
+        assert_eq!(report.summary.err, 3);
+    };
+
+    // A panicking worker still reports its target, and the rest of the pass
+    // goes on without it
+    let worker_panic = || {
+        let config = MonitorConfig {
+            retry_policy: Some(Arc::new(|_: &CheckOutcome<'_>, _: u32, _| {
+                panic!("retry policy bug")
+            })),
+            ..config()
+        };
+        let report = run_pass(urls(&["/ok", "/broken", "/slow"]), config, None);
+        assert_eq!(report.results.len(), 3);
+        let broken = report
+            .results
+            .iter()
+            .find(|ws| ws.url.ends_with("/broken"))
+            .unwrap();
+        assert_eq!(
+            broken.status,
+            Err(CheckError::Transport("worker panicked".into()))
+        );
+        assert!(
+            report
+                .summary
+                .warnings
+                .iter()
+                .any(|w| w.contains("panicked")),
+            "{:?}",
+            report.summary.warnings
+        );
+    };
+
+    let quiet = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    assert_no_leaks(&[
+        ("success", &success),
+        ("cancelled before the pass", &cancelled_before_the_pass),
+        ("dropped mid-pass", &dropped_mid_pass),
+        ("no URLs", &no_urls),
+        ("no URLs after sampling", &no_urls_after_sampling),
+        ("invalid URLs", &invalid_urls),
+        ("worker panic", &worker_panic),
+    ]);
+    panic::set_hook(quiet);
+
+    // Workers stop once every target is settled, without waiting for the
+    // results to be read
+    let before = threads();
+    let iter = monitor_iter(urls(&["/ok", "/broken", "/slow"]), config()).unwrap();
+    assert!(threads() > before);
+    let mut waited = Duration::ZERO;
+    while threads() > before && waited < Duration::from_secs(5) {
+        thread::sleep(Duration::from_millis(10));
+        waited += Duration::from_millis(10);
+    }
+    assert_eq!(threads(), before, "workers still running");
+    assert_eq!(iter.count(), 3);
+}
@@ -0,0 +1,110 @@
+#![cfg(feature = "phase-timing")]
+
+use httpmock::prelude::*;
+use std::time::Duration;
+use website_monitor::{CheckError, MonitorConfig, Target, run_pass};
+
+fn timing_config() -> MonitorConfig {
+    MonitorConfig {
+        worker_threads: 2,
+        request_timeout: Duration::from_secs(2),
+        detailed_timing: true,
+        ..MonitorConfig::default()
+    }
+}
+
+#[test]
+fn plain_http_phases_are_recorded() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200)
+            .body("x".repeat(10_000))
+            .delay(Duration::from_millis(150));
+    });
+
+    let report = run_pass(vec![server.url("/slow")], timing_config(), None);
+    let ws = &report.results[0];
+    assert_eq!(ws.status, Ok(200));
+    let phases = ws.phases.expect("phases recorded");
+    assert_eq!(phases.tls, None);
+    assert!(
+        phases.first_byte >= Duration::from_millis(150),
+        "{phases:?}"
+    );
+    assert!(phases.total() <= ws.response_time, "{phases:?}");
+}
+
+#[test]
+fn status_and_headers_come_through() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/gone");
+        then.status(404).header("X-Cache", "HIT").body("nope");
+    });
+
+    let config = MonitorConfig {
+        capture_headers: true,
+        ..timing_config()
+    };
+    let report = run_pass(vec![server.url("/gone")], config, None);
+    let ws = &report.results[0];
+    assert_eq!(ws.status, Ok(404));
+    assert!(ws.phases.is_some());
+    assert_eq!(ws.cache_status, Some(website_monitor::CacheStatus::Hit));
+}
+
+#[test]
+fn host_override_is_sent() {
+    let server = MockServer::start();
+    let vhost = server.mock(|when, then| {
+        when.method(GET)
+            .path("/")
+            .header("host", "www.prod.example");
+        then.status(204);
+    });
+
+    let target = Target::new(server.url("/")).with_host_header("www.prod.example");
+    let report = run_pass(vec![target], timing_config(), None);
+    assert_eq!(report.results[0].status, Ok(204));
+    vhost.assert();
+}
+
+#[test]
+fn slow_first_byte_times_out() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/hang");
+        then.status(200).delay(Duration::from_secs(3));
+    });
+
+    let config = MonitorConfig {
+        request_timeout: Duration::from_millis(300),
+        ..timing_config()
+    };
+    let report = run_pass(vec![server.url("/hang")], config, None);
+    let err = report.results[0].status.as_ref().unwrap_err();
+    assert_eq!(
+        *err,
+        CheckError::TimedOut {
+            limit: Duration::from_millis(300)
+        }
+    );
+    assert_eq!(report.summary.classes.timeouts, 1);
+}
+
+#[test]
+fn phases_serialize_in_json() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/");
+        then.status(200);
+    });
+
+    let report = run_pass(vec![server.url("/")], timing_config(), None);
+    let v = serde_json::to_value(&report.results[0]).unwrap();
+    let phases = &v["phases"];
+    assert!(phases["dns_ms"].is_number());
+    assert!(phases["first_byte_ms"].is_number());
+    assert!(phases["tls_ms"].is_null());
+}
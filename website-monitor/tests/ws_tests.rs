@@ -0,0 +1,94 @@
+#![cfg(feature = "tungstenite")]
+
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    thread,
+    time::Duration,
+};
+use website_monitor::{CheckError, MonitorConfig, WsProbe, monitor_websites};
+
+/// Echo server accepting a single WebSocket connection on an ephemeral port.
+fn spawn_echo_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut socket = tungstenite::accept(stream).unwrap();
+        while let Ok(msg) = socket.read() {
+            if msg.is_text() || msg.is_binary() {
+                let _ = socket.send(msg);
+            }
+        }
+    });
+    format!("ws://{addr}/")
+}
+
+/// Plain HTTP server that refuses the upgrade.
+fn spawn_rejecting_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0u8; 1024];
+        let _ = stream.read(&mut buf);
+        let _ = stream
+            .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    });
+    format!("ws://{addr}/")
+}
+
+fn config(probe: Option<WsProbe>) -> MonitorConfig {
+    MonitorConfig {
+        worker_threads: 1,
+        request_timeout: Duration::from_secs(2),
+        ws_probe: probe,
+        ..MonitorConfig::default()
+    }
+}
+
+#[test]
+fn handshake_only() {
+    let results = monitor_websites(vec![spawn_echo_server()], config(None), None);
+    assert_eq!(results[0].status, Ok(101));
+}
+
+#[test]
+fn ping_gets_pong() {
+    let results = monitor_websites(vec![spawn_echo_server()], config(Some(WsProbe::Ping)), None);
+    assert_eq!(results[0].status, Ok(101));
+}
+
+#[test]
+fn text_probe_gets_echo() {
+    let probe = WsProbe::Text("hello".into());
+    let results = monitor_websites(vec![spawn_echo_server()], config(Some(probe)), None);
+    assert_eq!(results[0].status, Ok(101));
+}
+
+#[test]
+fn rejected_handshake_is_reported() {
+    let results = monitor_websites(vec![spawn_rejecting_server()], config(None), None);
+    let err = results[0].status.clone().unwrap_err().to_string();
+    assert!(err.contains("handshake rejected: HTTP 403"), "{err}");
+}
+
+#[test]
+fn silent_server_times_out() {
+    // Accepts the connection but never answers the handshake.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}/", listener.local_addr().unwrap());
+    let cfg = MonitorConfig {
+        request_timeout: Duration::from_millis(300),
+        ..config(None)
+    };
+    let results = monitor_websites(vec![url], cfg, None);
+    let err = results[0].status.clone().unwrap_err();
+    assert_eq!(
+        err,
+        CheckError::TimedOut {
+            limit: Duration::from_millis(300)
+        }
+    );
+    drop(listener);
+}